@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS bot_permissions (
+    chat_id INTEGER PRIMARY KEY,
+    can_delete BOOLEAN NOT NULL,
+    can_pin BOOLEAN NOT NULL,
+    delete_notice_sent BOOLEAN NOT NULL DEFAULT 0,
+    updated_at INTEGER NOT NULL
+);
@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS duplicate_prompt (
+    item_id INTEGER PRIMARY KEY,
+    chat_id INTEGER NOT NULL,
+    message_id INTEGER,
+    created_at INTEGER NOT NULL
+);
@@ -0,0 +1,2 @@
+ALTER TABLE chat_state ADD COLUMN refresh_pending BOOLEAN NOT NULL DEFAULT 0;
+ALTER TABLE chat_state ADD COLUMN pending_notice TEXT;
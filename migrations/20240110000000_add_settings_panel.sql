@@ -0,0 +1,5 @@
+CREATE TABLE IF NOT EXISTS settings_panel (
+    chat_id INTEGER PRIMARY KEY,
+    message_id INTEGER NOT NULL,
+    last_used_at INTEGER NOT NULL
+);
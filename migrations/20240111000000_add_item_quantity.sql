@@ -0,0 +1 @@
+ALTER TABLE items ADD COLUMN quantity INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS archives (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    chat_id INTEGER NOT NULL,
+    archived_at INTEGER NOT NULL,
+    items TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS archives_chat_id_idx ON archives(chat_id, archived_at);
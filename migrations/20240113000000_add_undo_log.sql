@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS undo_log (
+    chat_id INTEGER PRIMARY KEY,
+    action TEXT NOT NULL,
+    items TEXT NOT NULL,
+    archive_id INTEGER
+);
@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS edit_session (
+    user_id INTEGER PRIMARY KEY,
+    chat_id INTEGER NOT NULL,
+    item_id INTEGER,
+    dm_message_id INTEGER,
+    started_at INTEGER NOT NULL
+);
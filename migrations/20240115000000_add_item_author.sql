@@ -0,0 +1,2 @@
+ALTER TABLE items ADD COLUMN added_by_user_id INTEGER;
+ALTER TABLE items ADD COLUMN added_by_name TEXT;
@@ -0,0 +1 @@
+ALTER TABLE items ADD COLUMN created_at INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS item_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    chat_id INTEGER NOT NULL,
+    key TEXT NOT NULL,
+    text TEXT NOT NULL,
+    added_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS item_history_chat_key_idx ON item_history(chat_id, key);
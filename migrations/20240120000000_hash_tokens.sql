@@ -0,0 +1,5 @@
+-- Tokens are stored as SHA-256 hashes; existing plaintext rows are hashed on
+-- startup, marked by an empty preview.
+ALTER TABLE tokens RENAME COLUMN token TO token_hash;
+ALTER TABLE tokens ADD COLUMN token_preview TEXT NOT NULL DEFAULT '';
+CREATE INDEX IF NOT EXISTS tokens_token_hash_idx ON tokens(token_hash);
@@ -0,0 +1 @@
+ALTER TABLE tokens ADD COLUMN scope TEXT NOT NULL DEFAULT 'write';
@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS share_links (
+    slug TEXT PRIMARY KEY,
+    chat_id INTEGER NOT NULL,
+    created_at INTEGER NOT NULL,
+    revoked_at INTEGER
+);
+CREATE INDEX IF NOT EXISTS share_links_chat_id_idx ON share_links(chat_id);
@@ -0,0 +1 @@
+ALTER TABLE chat_state ADD COLUMN revision INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+ALTER TABLE items ADD COLUMN position INTEGER;
+UPDATE items SET position = id;
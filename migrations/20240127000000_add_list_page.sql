@@ -0,0 +1 @@
+ALTER TABLE chat_state ADD COLUMN list_page INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1 @@
+ALTER TABLE delete_session ADD COLUMN page INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS pairings (
+    code TEXT PRIMARY KEY,
+    created_at INTEGER NOT NULL,
+    expires_at INTEGER NOT NULL,
+    chat_id INTEGER,
+    issuer_user_id INTEGER,
+    issuer_name TEXT,
+    claimed_at INTEGER
+);
@@ -0,0 +1 @@
+ALTER TABLE chat_state ADD COLUMN thread_id INTEGER;
@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS message_items (
+    chat_id INTEGER NOT NULL,
+    message_id INTEGER NOT NULL,
+    item_id INTEGER NOT NULL,
+    created BOOLEAN NOT NULL,
+    PRIMARY KEY (chat_id, message_id, item_id)
+);
@@ -0,0 +1 @@
+ALTER TABLE items ADD COLUMN done_at INTEGER;
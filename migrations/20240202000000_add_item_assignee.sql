@@ -0,0 +1,8 @@
+ALTER TABLE items ADD COLUMN assigned_to_user_id INTEGER;
+ALTER TABLE items ADD COLUMN assigned_to_name TEXT;
+
+CREATE TABLE IF NOT EXISTS assign_session (
+    user_id INTEGER PRIMARY KEY,
+    chat_id INTEGER NOT NULL,
+    dm_message_id INTEGER
+);
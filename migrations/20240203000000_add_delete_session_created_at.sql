@@ -0,0 +1 @@
+ALTER TABLE delete_session ADD COLUMN created_at INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,26 @@
+-- Bump the chat's list revision in the same transaction as every change to
+-- its items, so a client reading the revision right after a write sees it.
+-- Rows already in the trash are not on the list and do not count.
+CREATE TRIGGER IF NOT EXISTS items_insert_revision
+AFTER INSERT ON items
+WHEN NEW.deleted_at IS NULL
+BEGIN
+    INSERT INTO chat_state (chat_id, revision) VALUES (NEW.chat_id, 1)
+    ON CONFLICT(chat_id) DO UPDATE SET revision = revision + 1;
+END;
+
+CREATE TRIGGER IF NOT EXISTS items_update_revision
+AFTER UPDATE ON items
+WHEN NEW.deleted_at IS NULL OR OLD.deleted_at IS NULL
+BEGIN
+    INSERT INTO chat_state (chat_id, revision) VALUES (NEW.chat_id, 1)
+    ON CONFLICT(chat_id) DO UPDATE SET revision = revision + 1;
+END;
+
+CREATE TRIGGER IF NOT EXISTS items_delete_revision
+AFTER DELETE ON items
+WHEN OLD.deleted_at IS NULL
+BEGIN
+    INSERT INTO chat_state (chat_id, revision) VALUES (OLD.chat_id, 1)
+    ON CONFLICT(chat_id) DO UPDATE SET revision = revision + 1;
+END;
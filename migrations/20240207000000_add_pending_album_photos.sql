@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS pending_album_photos (
+    chat_id INTEGER NOT NULL,
+    media_group_id TEXT NOT NULL,
+    message_id INTEGER NOT NULL,
+    message TEXT NOT NULL,
+    PRIMARY KEY (chat_id, media_group_id, message_id)
+);
@@ -1,7 +1,11 @@
 use anyhow::{anyhow, Result};
 use serde::Deserialize;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
 use tracing::{debug, instrument, trace, warn};
 
+use crate::metrics::Metrics;
+
 #[derive(Deserialize)]
 struct ChatChoice {
     message: ChatMessage,
@@ -36,24 +40,195 @@ struct ItemsJson {
     items: Vec<String>,
 }
 
+/// An item read together with its price, e.g. from a receipt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PricedItem {
+    pub text: String,
+    pub price_cents: Option<i64>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PriceJson {
+    Number(f64),
+    Text(String),
+}
+
+impl PriceJson {
+    /// Whole cents, rejecting negative and unreadable prices.
+    fn cents(&self) -> Option<i64> {
+        let amount = match self {
+            PriceJson::Number(amount) => *amount,
+            PriceJson::Text(text) => text.trim().replace(',', ".").parse().ok()?,
+        };
+        (amount.is_finite() && amount >= 0.0).then(|| (amount * 100.0).round() as i64)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PricedEntry {
+    Plain(String),
+    Priced {
+        text: String,
+        #[serde(default)]
+        price: Option<PriceJson>,
+    },
+}
+
+#[derive(Deserialize)]
+struct PricedItemsJson {
+    items: Vec<PricedEntry>,
+}
+
+/// Parse `{"items":[{"text":"Milk","price":3.49}]}`. Plain strings and
+/// missing or unreadable prices give items without a price.
+fn parse_priced_items(content: &str) -> Result<Vec<PricedItem>> {
+    let parsed: PricedItemsJson = serde_json::from_str(content)?;
+    Ok(parsed
+        .items
+        .into_iter()
+        .filter_map(|entry| {
+            let (text, price) = match entry {
+                PricedEntry::Plain(text) => (text, None),
+                PricedEntry::Priced { text, price } => (text, price),
+            };
+            let text = crate::text_utils::parse_item_line(&text)?;
+            let price_cents = price.as_ref().and_then(PriceJson::cents);
+            Some(PricedItem { text, price_cents })
+        })
+        .collect())
+}
+
 pub const OPENAI_CHAT_URL: &str = "https://api.openai.com/v1/chat/completions";
 
-fn is_loopback_host(host: &str) -> bool {
-    matches!(host, "localhost" | "127.0.0.1" | "::1")
+pub const DEFAULT_OPENAI_MAX_ATTEMPTS: u32 = 3;
+pub const DEFAULT_OPENAI_RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+pub const DEFAULT_OPENAI_RETRY_DEADLINE: Duration = Duration::from_secs(20);
+pub const DEFAULT_OPENAI_TIMEOUT: Duration = Duration::from_secs(60);
+const OPENAI_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long [`send_openai_request`] waits for an answer and how it retries
+/// rate limits and server errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestPolicy {
+    /// Limit for a single attempt, from connecting to reading the body.
+    pub timeout: Duration,
+    /// Attempts in total, the first one included.
+    pub max_attempts: u32,
+    /// Delay before the first retry; it doubles for every further one.
+    pub base_delay: Duration,
+    /// No retry starts later than this after the first attempt.
+    pub deadline: Duration,
+}
+
+impl Default for RequestPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_OPENAI_TIMEOUT,
+            max_attempts: DEFAULT_OPENAI_MAX_ATTEMPTS,
+            base_delay: DEFAULT_OPENAI_RETRY_BASE_DELAY,
+            deadline: DEFAULT_OPENAI_RETRY_DEADLINE,
+        }
+    }
+}
+
+/// What every OpenAI request shares besides its key and body: the retry
+/// policy, the pooled HTTP clients and the registry outcomes are counted in.
+/// Built once per configuration; clones share the connection pools.
+#[derive(Debug, Clone, Default)]
+pub struct OpenAiClient {
+    policy: RequestPolicy,
+    metrics: Metrics,
+    http: Arc<HttpClients>,
+}
+
+/// Built on first use so a configuration that never talks to OpenAI does
+/// not open a pool.
+#[derive(Debug, Default)]
+struct HttpClients {
+    direct: OnceLock<reqwest::Client>,
+    /// Skips proxies, for servers on loopback addresses such as test mocks.
+    loopback: OnceLock<reqwest::Client>,
+}
+
+impl OpenAiClient {
+    pub fn new(policy: RequestPolicy, metrics: Metrics) -> Self {
+        Self {
+            policy,
+            metrics,
+            http: Arc::default(),
+        }
+    }
+
+    pub fn policy(&self) -> &RequestPolicy {
+        &self.policy
+    }
+
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// The pooled client for `url`, limited to the policy's timeout per
+    /// attempt.
+    pub(crate) fn http_client(&self, url: &str) -> Result<reqwest::Client> {
+        let use_no_proxy = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|parsed| parsed.host_str().map(is_loopback_host))
+            .unwrap_or(false);
+        let cell = if use_no_proxy {
+            &self.http.loopback
+        } else {
+            &self.http.direct
+        };
+        if let Some(client) = cell.get() {
+            return Ok(client.clone());
+        }
+        let builder = reqwest::Client::builder()
+            .connect_timeout(OPENAI_CONNECT_TIMEOUT)
+            .timeout(self.policy.timeout);
+        let builder = if use_no_proxy {
+            builder.no_proxy()
+        } else {
+            builder
+        };
+        debug!(use_no_proxy, timeout = ?self.policy.timeout, "Building OpenAI client");
+        let client = builder.build()?;
+        Ok(cell.get_or_init(|| client).clone())
+    }
+}
+
+impl RequestPolicy {
+    /// Exponential backoff before retry number `retry`, counting from one,
+    /// with up to half of it taken off at random so that callers hitting the
+    /// same limit do not retry in lockstep.
+    fn backoff(&self, retry: u32) -> Duration {
+        let full = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)));
+        full.mul_f64(1.0 - rand::random::<f64>() / 2.0)
+    }
 }
 
-pub(crate) fn client_for_url(url: &str) -> Result<reqwest::Client> {
-    let use_no_proxy = reqwest::Url::parse(url)
+/// Rate limits and the server errors that tend to go away on their own.
+fn is_retryable(status: reqwest::StatusCode) -> bool {
+    matches!(status.as_u16(), 429 | 500 | 502 | 503)
+}
+
+/// The delay a `Retry-After` header asks for, in whole seconds.
+fn retry_after(resp: &reqwest::Response) -> Option<Duration> {
+    resp.headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
         .ok()
-        .and_then(|parsed| parsed.host_str().map(is_loopback_host))
-        .unwrap_or(false);
-    let builder = reqwest::Client::builder();
-    let builder = if use_no_proxy {
-        builder.no_proxy()
-    } else {
-        builder
-    };
-    Ok(builder.build()?)
+        .map(Duration::from_secs)
+}
+
+fn is_loopback_host(host: &str) -> bool {
+    matches!(host, "localhost" | "127.0.0.1" | "::1")
 }
 
 /// Build a chat completion request body for text input.
@@ -72,68 +247,125 @@ pub fn build_text_chat_body(
     })
 }
 
-/// Build a chat completion request body for an image input.
+/// Build a chat completion request body for one or more image inputs,
+/// followed by `user_text` as a separate user message when given.
 pub fn build_image_chat_body(
     model: &str,
     system_prompt: &str,
-    image_url: &str,
+    image_urls: &[String],
+    user_text: Option<&str>,
 ) -> serde_json::Value {
+    let content: Vec<_> = image_urls
+        .iter()
+        .map(|url| serde_json::json!({ "type": "image_url", "image_url": { "url": url } }))
+        .collect();
+    let mut messages = vec![
+        serde_json::json!({ "role": "system", "content": system_prompt }),
+        serde_json::json!({ "role": "user", "content": content }),
+    ];
+    if let Some(text) = user_text {
+        messages.push(serde_json::json!({ "role": "user", "content": text }));
+    }
     serde_json::json!({
         "model": model,
         "response_format": { "type": "json_object" },
-        "messages": [
-            { "role": "system", "content": system_prompt },
-            {
-                "role": "user",
-                "content": [ { "type": "image_url", "image_url": { "url": image_url } } ],
-            }
-        ]
+        "messages": messages
     })
 }
 
-#[instrument(level = "trace", skip(api_key, builder))]
+/// Send the request `build` makes, retrying rate limits and server errors as
+/// the policy of `openai` allows. `build` is called again for every attempt
+/// because multipart bodies cannot be cloned.
+#[instrument(level = "trace", skip(api_key, build))]
 pub async fn send_openai_request(
     api_key: &str,
-    builder: reqwest::RequestBuilder,
+    openai: &OpenAiClient,
+    build: impl Fn() -> Result<reqwest::RequestBuilder>,
 ) -> Result<reqwest::Response> {
-    let url = builder
-        .try_clone()
-        .and_then(|b| b.build().ok())
-        .map(|req| req.url().clone());
+    let policy = &openai.policy;
+    let first_attempt = Instant::now();
+    let mut attempt = 1;
+    loop {
+        let builder = build()?;
+        let url = builder
+            .try_clone()
+            .and_then(|b| b.build().ok())
+            .map(|req| req.url().clone());
+
+        let endpoint = url
+            .as_ref()
+            .map(|u| u.path())
+            .unwrap_or("unknown")
+            .to_string();
+        let started = Instant::now();
+        let resp = match builder.bearer_auth(api_key).send().await {
+            Ok(resp) => resp,
+            Err(err) => {
+                openai
+                    .metrics
+                    .record_openai_request(&endpoint, false, started.elapsed());
+                return Err(err.into());
+            }
+        };
+        openai.metrics.record_openai_request(
+            &endpoint,
+            resp.status().is_success(),
+            started.elapsed(),
+        );
+        debug!(url = %url.as_ref().map(|u| u.as_str()).unwrap_or(""), status = %resp.status(), attempt, "OpenAI request completed");
 
-    let resp = builder.bearer_auth(api_key).send().await?;
-    debug!(url = %url.as_ref().map(|u| u.as_str()).unwrap_or(""), status = %resp.status(), "OpenAI request completed");
+        if resp.status().is_success() {
+            return Ok(resp);
+        }
 
-    if !resp.status().is_success() {
         let status = resp.status();
+        let requested_delay = retry_after(&resp);
         let err_text = resp.text().await.unwrap_or_default();
         let snippet: String = err_text.chars().take(200).collect();
         debug!(url = %url.as_ref().map(|u| u.as_str()).unwrap_or(""), %status, snippet = %snippet, "error body");
-        warn!(%status, "OpenAI API error");
+
+        if is_retryable(status) && attempt < policy.max_attempts {
+            let delay = requested_delay.unwrap_or_else(|| policy.backoff(attempt));
+            if first_attempt.elapsed() + delay <= policy.deadline {
+                debug!(%status, attempt, ?delay, "Retrying OpenAI request");
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+                continue;
+            }
+            debug!(%status, attempt, ?delay, "Retry would pass the deadline; giving up");
+        }
+        warn!(%status, attempt, "OpenAI API error");
         return Err(anyhow!("OpenAI API error {status}: {err_text}"));
     }
-
-    Ok(resp)
 }
 
-#[instrument(level = "trace", skip(api_key, body))]
-pub async fn request_items(
+/// Send a chat completion request and return the first choice's content.
+pub async fn request_chat_content(
     api_key: &str,
     body: &serde_json::Value,
     url: &str,
-) -> Result<Vec<String>> {
+    openai: &OpenAiClient,
+) -> Result<String> {
     debug!(url, "sending chat completion request");
 
-    let client = client_for_url(url)?;
-    let builder = client.post(url).json(body);
-    let resp = send_openai_request(api_key, builder).await?;
+    let client = openai.http_client(url)?;
+    let resp = send_openai_request(api_key, openai, || Ok(client.post(url).json(body))).await?;
 
     let raw = resp.text().await?;
     let snippet: String = raw.chars().take(200).collect();
     debug!(snippet = %snippet, "chat response body");
     trace!(raw = %raw, "chat response");
-    let content = parse_chat_content(&raw)?;
+    parse_chat_content(&raw)
+}
 
+#[instrument(level = "trace", skip(api_key, body))]
+pub async fn request_items(
+    api_key: &str,
+    body: &serde_json::Value,
+    url: &str,
+    openai: &OpenAiClient,
+) -> Result<Vec<String>> {
+    let content = request_chat_content(api_key, body, url, openai).await?;
     let items_json: ItemsJson = serde_json::from_str(&content)?;
 
     Ok(items_json
@@ -142,3 +374,77 @@ pub async fn request_items(
         .filter_map(|s| crate::text_utils::parse_item_line(&s))
         .collect())
 }
+
+/// Like [`request_items`], for answers that may give each item a price.
+#[instrument(level = "trace", skip(api_key, body))]
+pub async fn request_priced_items(
+    api_key: &str,
+    body: &serde_json::Value,
+    url: &str,
+    openai: &OpenAiClient,
+) -> Result<Vec<PricedItem>> {
+    let content = request_chat_content(api_key, body, url, openai).await?;
+    let items = parse_priced_items(&content)?;
+    debug!(
+        items = items.len(),
+        priced = items
+            .iter()
+            .filter(|item| item.price_cents.is_some())
+            .count(),
+        "Parsed priced items"
+    );
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_and_keeps_at_least_half() {
+        let policy = RequestPolicy {
+            base_delay: Duration::from_millis(100),
+            ..RequestPolicy::default()
+        };
+        for (retry, full) in [(1, 100), (2, 200), (3, 400)] {
+            let delay = policy.backoff(retry);
+            assert!(delay <= Duration::from_millis(full), "{delay:?}");
+            assert!(delay >= Duration::from_millis(full / 2), "{delay:?}");
+        }
+    }
+
+    #[test]
+    fn priced_items_accept_mixed_shapes() {
+        let items = parse_priced_items(
+            r#"{"items":[{"text":"Milk","price":3.49},"Bread",{"text":"Eggs","price":"2,10"},{"text":"Tea","price":null},{"text":"Jam","price":"n/a"},{"text":"Salt"}]}"#,
+        )
+        .unwrap();
+        let priced: Vec<_> = items
+            .iter()
+            .map(|item| (item.text.as_str(), item.price_cents))
+            .collect();
+        assert_eq!(
+            priced,
+            [
+                ("Milk", Some(349)),
+                ("Bread", None),
+                ("Eggs", Some(210)),
+                ("Tea", None),
+                ("Jam", None),
+                ("Salt", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn only_rate_limits_and_server_errors_are_retried() {
+        for status in [429, 500, 502, 503] {
+            assert!(is_retryable(reqwest::StatusCode::from_u16(status).unwrap()));
+        }
+        for status in [400, 401, 404, 504] {
+            assert!(!is_retryable(
+                reqwest::StatusCode::from_u16(status).unwrap()
+            ));
+        }
+    }
+}
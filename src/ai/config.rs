@@ -1,4 +1,17 @@
 use std::env;
+use std::time::Duration;
+
+use crate::ai::common::{
+    OpenAiClient, RequestPolicy, DEFAULT_OPENAI_MAX_ATTEMPTS, DEFAULT_OPENAI_RETRY_BASE_DELAY,
+    DEFAULT_OPENAI_RETRY_DEADLINE, DEFAULT_OPENAI_TIMEOUT,
+};
+use crate::ai::prompts::Prompts;
+use crate::ai::vision::DEFAULT_VISION_MAX_EDGE;
+use crate::metrics::Metrics;
+
+/// Voice deletions of more items than this ask for confirmation first.
+pub const DEFAULT_VOICE_DELETE_CONFIRM_THRESHOLD: usize = 3;
+
 #[derive(Clone)]
 pub struct AiConfig {
     pub api_key: String,
@@ -7,6 +20,41 @@ pub struct AiConfig {
     pub vision_model: String,
     pub openai_chat_url: Option<String>,
     pub openai_stt_url: Option<String>,
+    pub enable_stt: bool,
+    pub enable_vision: bool,
+    pub enable_gpt_parse: bool,
+    pub enable_voice_commands: bool,
+    pub voice_delete_confirm_threshold: usize,
+    /// ISO-639-1 code of the language voice messages are spoken in, passed
+    /// to transcription to stop it from guessing.
+    pub stt_language: Option<String>,
+    /// Retry policy and metrics for every OpenAI request; `run()` points the
+    /// metrics at the shared registry.
+    pub openai: OpenAiClient,
+    /// Prompts replaced through `OPENAI_*_PROMPT` variables.
+    pub prompts: Prompts,
+    /// Photos are scaled down to this longest edge before vision parsing;
+    /// `None` sends them as they are.
+    pub vision_max_edge: Option<u32>,
+}
+
+/// Read a boolean capability switch, treating unset or unrecognized values as enabled.
+fn env_flag(name: &str) -> bool {
+    match env::var(name) {
+        Ok(value) => !matches!(
+            value.trim().to_ascii_lowercase().as_str(),
+            "0" | "false" | "no" | "off"
+        ),
+        Err(_) => true,
+    }
+}
+
+/// Read a numeric setting, falling back to `default` when unset or invalid.
+fn env_number<T: std::str::FromStr>(name: &str, default: T) -> T {
+    env::var(name)
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or(default)
 }
 
 impl AiConfig {
@@ -22,6 +70,42 @@ impl AiConfig {
             vision_model: env::var("OPENAI_VISION_MODEL").unwrap_or_else(|_| "gpt-4o".to_string()),
             openai_chat_url: env::var("OPENAI_CHAT_URL").ok(),
             openai_stt_url: env::var("OPENAI_STT_URL").ok(),
+            enable_stt: env_flag("AI_ENABLE_STT"),
+            enable_vision: env_flag("AI_ENABLE_VISION"),
+            enable_gpt_parse: env_flag("AI_ENABLE_GPT_PARSE"),
+            enable_voice_commands: env_flag("AI_ENABLE_VOICE_COMMANDS"),
+            voice_delete_confirm_threshold: env::var("VOICE_DELETE_CONFIRM_THRESHOLD")
+                .ok()
+                .and_then(|s| s.parse::<usize>().ok())
+                .unwrap_or(DEFAULT_VOICE_DELETE_CONFIRM_THRESHOLD),
+            stt_language: env::var("OPENAI_STT_LANGUAGE")
+                .ok()
+                .map(|s| s.trim().to_ascii_lowercase())
+                .filter(|s| !s.is_empty()),
+            openai: OpenAiClient::new(
+                RequestPolicy {
+                    timeout: Duration::from_secs(
+                        env_number("OPENAI_TIMEOUT_SECS", DEFAULT_OPENAI_TIMEOUT.as_secs()).max(1),
+                    ),
+                    max_attempts: env_number("OPENAI_MAX_ATTEMPTS", DEFAULT_OPENAI_MAX_ATTEMPTS)
+                        .max(1),
+                    base_delay: Duration::from_millis(env_number(
+                        "OPENAI_RETRY_BASE_DELAY_MS",
+                        DEFAULT_OPENAI_RETRY_BASE_DELAY.as_millis() as u64,
+                    )),
+                    deadline: Duration::from_secs(env_number(
+                        "OPENAI_RETRY_DEADLINE_SECS",
+                        DEFAULT_OPENAI_RETRY_DEADLINE.as_secs(),
+                    )),
+                },
+                Metrics::default(),
+            ),
+            prompts: Prompts::from_env(),
+            vision_max_edge: Some(env_number(
+                "OPENAI_VISION_MAX_EDGE",
+                DEFAULT_VISION_MAX_EDGE,
+            ))
+            .filter(|edge| *edge > 0),
         })
     }
 }
@@ -1,5 +1,5 @@
-use crate::ai::common::{request_items, OPENAI_CHAT_URL};
-use crate::ai::prompts::TEXT_PARSING_PROMPT;
+use crate::ai::common::{request_chat_content, request_items, OpenAiClient, OPENAI_CHAT_URL};
+use crate::ai::prompts::{LIST_CLASSIFIER_PROMPT, TEXT_PARSING_PROMPT};
 use anyhow::Result;
 use tracing::instrument;
 
@@ -13,9 +13,10 @@ pub async fn parse_items_gpt(
     model: &str,
     text: &str,
     url: Option<&str>,
+    openai: &OpenAiClient,
 ) -> Result<Vec<String>> {
     let url = url.unwrap_or(OPENAI_CHAT_URL);
-    parse_items_gpt_inner(api_key, model, text, url).await
+    parse_items_gpt_inner(api_key, model, text, url, openai).await
 }
 
 #[cfg_attr(not(test), allow(dead_code))]
@@ -25,55 +26,174 @@ pub async fn parse_items_gpt_inner(
     model: &str,
     text: &str,
     url: &str,
+    openai: &OpenAiClient,
 ) -> Result<Vec<String>> {
     let body = crate::ai::common::build_text_chat_body(model, TEXT_PARSING_PROMPT, text);
 
-    request_items(api_key, &body, url).await
+    request_items(api_key, &body, url, openai).await
+}
+
+#[derive(serde::Deserialize)]
+struct ClassificationJson {
+    is_list: bool,
+}
+
+/// Ask the model whether `text` is meant as list items rather than chat.
+#[instrument(level = "trace", skip(api_key))]
+pub async fn classify_list_message(
+    api_key: &str,
+    model: &str,
+    text: &str,
+    url: Option<&str>,
+    openai: &OpenAiClient,
+) -> Result<bool> {
+    let url = url.unwrap_or(OPENAI_CHAT_URL);
+    let body = crate::ai::common::build_text_chat_body(model, LIST_CLASSIFIER_PROMPT, text);
+    let content = request_chat_content(api_key, &body, url, openai).await?;
+    let parsed: ClassificationJson = serde_json::from_str(&content)?;
+    tracing::debug!(is_list = parsed.is_list, "Classified message");
+    Ok(parsed.is_list)
+}
+
+/// An item parsed by the model together with its suggested category.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CategorizedItem {
+    pub text: String,
+    pub category: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum CategorizedEntry {
+    Plain(String),
+    Categorized {
+        text: String,
+        category: Option<String>,
+    },
+}
+
+#[derive(serde::Deserialize)]
+struct CategorizedJson {
+    items: Vec<CategorizedEntry>,
+}
+
+/// Like [`parse_items_gpt`], but with `prompt` asking the model for a category
+/// per item, expecting `{"items":[{"text":"milk","category":"dairy"}]}`. Plain
+/// strings in the array are accepted as uncategorized items.
+#[instrument(level = "trace", skip(api_key, prompt))]
+pub async fn parse_categorized_items_gpt(
+    api_key: &str,
+    model: &str,
+    prompt: &str,
+    text: &str,
+    url: Option<&str>,
+    openai: &OpenAiClient,
+) -> Result<Vec<CategorizedItem>> {
+    use tracing::{debug, trace};
+
+    let url = url.unwrap_or(OPENAI_CHAT_URL);
+    let body = crate::ai::common::build_text_chat_body(model, prompt, text);
+    debug!(url, "sending chat completion request");
+
+    let client = openai.http_client(url)?;
+    let resp = crate::ai::common::send_openai_request(api_key, openai, || {
+        Ok(client.post(url).json(&body))
+    })
+    .await?;
+
+    let raw = resp.text().await?;
+    let snippet: String = raw.chars().take(200).collect();
+    debug!(snippet = %snippet, "chat response body");
+    trace!(raw = %raw, "chat response");
+    let content = crate::ai::common::parse_chat_content(&raw)?;
+    let parsed: CategorizedJson = serde_json::from_str(&content)?;
+
+    Ok(parsed
+        .items
+        .into_iter()
+        .filter_map(|entry| {
+            let (text, category) = match entry {
+                CategorizedEntry::Plain(text) => (text, None),
+                CategorizedEntry::Categorized { text, category } => (text, category),
+            };
+            let text = crate::text_utils::parse_item_line(&text)?;
+            let category = category
+                .map(|category| category.trim().to_lowercase())
+                .filter(|category| !category.is_empty());
+            Some(CategorizedItem { text, category })
+        })
+        .collect())
 }
 
 #[derive(Debug, PartialEq)]
 pub enum VoiceCommand {
     Add(Vec<String>),
     Delete(Vec<String>),
+    /// Mark items on the list as done without removing them.
+    Check(Vec<String>),
 }
 
 #[derive(serde::Deserialize)]
 struct CommandJson {
     add: Option<Vec<String>>,
     delete: Option<Vec<String>>,
+    check: Option<Vec<String>>,
 }
 
-#[instrument(level = "trace", skip(api_key))]
+/// A transcribed request together with what the model needs to read it.
+#[derive(Debug, Clone, Copy)]
+pub struct VoiceCommandRequest<'a> {
+    /// Prompt that gets the list and the spoken language appended.
+    pub instructions: &'a str,
+    pub text: &'a str,
+    /// Language the request was spoken in, when known.
+    pub language: Option<&'a str>,
+    pub list: &'a [String],
+}
+
+/// Interpret a transcribed request.
+#[instrument(level = "trace", skip(api_key, request), fields(text = request.text))]
 pub async fn interpret_voice_command(
     api_key: &str,
     model: &str,
-    text: &str,
-    list: &[String],
+    request: VoiceCommandRequest<'_>,
     url: Option<&str>,
+    openai: &OpenAiClient,
 ) -> Result<VoiceCommand> {
     let url = url.unwrap_or(OPENAI_CHAT_URL);
-    interpret_voice_command_inner(api_key, model, text, list, url).await
+    interpret_voice_command_inner(api_key, model, request, url, openai).await
 }
 
 #[cfg_attr(not(test), allow(dead_code))]
-#[instrument(level = "trace", skip(api_key))]
+#[instrument(level = "trace", skip(api_key, request), fields(text = request.text))]
 pub async fn interpret_voice_command_inner(
     api_key: &str,
     model: &str,
-    text: &str,
-    list: &[String],
+    request: VoiceCommandRequest<'_>,
     url: &str,
+    openai: &OpenAiClient,
 ) -> Result<VoiceCommand> {
+    let VoiceCommandRequest {
+        instructions,
+        text,
+        language,
+        list,
+    } = request;
     let list_text = if list.is_empty() {
         "The list is empty.".to_string()
     } else {
         format!("Current items: {}.", list.join(", "))
     };
     let list_json = serde_json::to_string(list)?;
+    let language_text = match language {
+        Some(language) => format!(
+            " The request was transcribed from {language} speech, so fix words that look misheard or oddly transliterated."
+        ),
+        None => String::new(),
+    };
 
-    let prompt = format!(
-        "You manage a list of items. {list_text} The list as JSON is {list_json}. Decide whether the user's request adds items or removes items from the list. Return a JSON object like {{\"add\":[...]}} or {{\"delete\":[...]}}. For deletions, include each item exactly as it appears in the list, including any leading quantities. If unsure, treat it as an addition request. Use nominative forms for item names when possible and convert number words to digits."
-    );
+    let prompt =
+        format!("{instructions} {list_text} The list as JSON is {list_json}.{language_text}");
 
     let body = crate::ai::common::build_text_chat_body(model, &prompt, text);
 
@@ -81,9 +201,11 @@ pub async fn interpret_voice_command_inner(
 
     debug!(url, "sending chat completion request");
 
-    let client = crate::ai::common::client_for_url(url)?;
-    let builder = client.post(url).json(&body);
-    let resp = crate::ai::common::send_openai_request(api_key, builder).await?;
+    let client = openai.http_client(url)?;
+    let resp = crate::ai::common::send_openai_request(api_key, openai, || {
+        Ok(client.post(url).json(&body))
+    })
+    .await?;
 
     let raw = resp.text().await?;
     let snippet: String = raw.chars().take(200).collect();
@@ -92,33 +214,32 @@ pub async fn interpret_voice_command_inner(
     let content = crate::ai::common::parse_chat_content(&raw)?;
 
     let cmd: CommandJson = serde_json::from_str(&content)?;
-
-    if let Some(delete) = cmd.delete {
-        let cleaned: Vec<String> = delete
+    let clean = |items: Vec<String>| -> Vec<String> {
+        items
             .into_iter()
             .filter_map(|s| crate::text_utils::parse_item_line(&s))
-            .collect();
-        Ok(VoiceCommand::Delete(cleaned))
+            .collect()
+    };
+
+    if let Some(delete) = cmd.delete {
+        Ok(VoiceCommand::Delete(clean(delete)))
+    } else if let Some(check) = cmd.check {
+        Ok(VoiceCommand::Check(clean(check)))
     } else {
-        let add = cmd.add.unwrap_or_default();
-        let cleaned: Vec<String> = add
-            .into_iter()
-            .filter_map(|s| crate::text_utils::parse_item_line(&s))
-            .collect();
-        Ok(VoiceCommand::Add(cleaned))
+        Ok(VoiceCommand::Add(clean(cmd.add.unwrap_or_default())))
     }
 }
 
 #[cfg(test)]
-#[instrument(level = "trace", skip(api_key))]
+#[instrument(level = "trace", skip(api_key, request), fields(text = request.text))]
 pub async fn interpret_voice_command_test(
     api_key: &str,
     model: &str,
-    text: &str,
-    list: &[String],
+    request: VoiceCommandRequest<'_>,
     url: &str,
+    openai: &OpenAiClient,
 ) -> Result<VoiceCommand> {
-    interpret_voice_command_inner(api_key, model, text, list, url).await
+    interpret_voice_command_inner(api_key, model, request, url, openai).await
 }
 
 // Re-export the inner implementation so integration tests can still call
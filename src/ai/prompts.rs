@@ -2,16 +2,193 @@
 //!
 //! Centralizing these strings makes it easy to tweak how text, photos
 //! and audio are interpreted without digging through multiple modules.
+//! Each one can be replaced by an environment variable and, per chat, with
+//! `/prompt`; see [`Prompts`].
+
+use std::env;
 
 /// System prompt for parsing items from free-form text.
 pub const TEXT_PARSING_PROMPT: &str = "Extract the items from the user's text. Use the nominative form for nouns when it does not change the meaning. Convert number words to digits so 'три ананаса' becomes '3 ананаса'. Respond with a JSON object like {\"items\": [\"1 milk\"]}";
 
+/// System prompt for parsing items from free-form text and sorting each one
+/// into a short store-section category.
+pub const CATEGORIZED_PARSING_PROMPT: &str = "Extract the items from the user's text. Use the nominative form for nouns when it does not change the meaning. Convert number words to digits so 'три ананаса' becomes '3 ананаса'. Give each item a short lowercase category naming the store section it belongs to, such as 'dairy', 'produce' or 'bakery', in the language of the text. Respond with a JSON object like {\"items\": [{\"text\": \"1 milk\", \"category\": \"dairy\"}]}";
+
+/// System prompt for strict mode, deciding whether a group message is meant
+/// for the list at all.
+pub const LIST_CLASSIFIER_PROMPT: &str = "Decide whether the user's message is a shopping or to-do list entry, such as item names with optional quantities, or ordinary conversation like thanks, questions or replies. Respond with a JSON object like {\"is_list\": true}.";
+
 /// System prompt for parsing items from a photo.
 pub const PHOTO_PARSING_PROMPT: &str = "Extract the items shown in the photo. Respond with a JSON object like {\"items\": [\"apples\"]}.";
 
+/// Introduces the caption of a photo, sent to the vision model as a separate
+/// user message after the images.
+pub const PHOTO_CAPTION_PREFIX: &str = "The photo was sent with this caption. Follow any instructions in it and also include the items it names:";
+
+/// Vision prompt for chats in receipt mode, asking for the price paid per
+/// line as well.
+pub const RECEIPT_PARSING_PROMPT: &str = "The photo shows a shopping receipt. Extract each purchased product with the price paid for that line as a number, without currency symbols. Respond with a JSON object like {\"items\": [{\"text\": \"Milk\", \"price\": 3.49}]}. Use null for the price when it cannot be read, and skip totals, taxes, discounts and payment lines.";
+
 /// Default instructions passed to GPT-based transcription models.
 /// The prompt also asks the model to keep verbs intact so commands like
 /// "delete" are not dropped during transcription. Quantities should be
 /// written using digits when possible. Convert spelled-out numbers to digits
 /// so phrases like "три ананаса" become "3 ананаса".
 pub const DEFAULT_STT_PROMPT: &str = "Transcribe the user's request about the list. Keep verbs like 'add' or 'delete' exactly as spoken. Use digits for quantities and convert number words to digits.";
+
+/// System prompt for interpreting a spoken request. The current list is
+/// appended to it.
+pub const VOICE_COMMAND_PROMPT: &str = "You manage a list of items. Decide whether the user's request adds items, removes items from the list, or marks items on the list as done, bought or checked off. Return a JSON object like {\"add\":[...]}, {\"delete\":[...]} or {\"check\":[...]}. For deletions and checks, include each item exactly as it appears in the list, including any leading quantities. If unsure, treat it as an addition request. Use nominative forms for item names when possible and convert number words to digits.";
+
+/// A prompt that can be overridden.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptKind {
+    /// Parsing `/parse` text into categorized items.
+    Text,
+    Photo,
+    Transcription,
+    VoiceCommand,
+}
+
+impl PromptKind {
+    pub const ALL: [PromptKind; 4] = [
+        PromptKind::Text,
+        PromptKind::Photo,
+        PromptKind::Transcription,
+        PromptKind::VoiceCommand,
+    ];
+
+    /// The name `/prompt` knows the prompt by.
+    pub fn name(self) -> &'static str {
+        match self {
+            PromptKind::Text => "text",
+            PromptKind::Photo => "photo",
+            PromptKind::Transcription => "transcription",
+            PromptKind::VoiceCommand => "voice",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn env_var(self) -> &'static str {
+        match self {
+            PromptKind::Text => "OPENAI_TEXT_PROMPT",
+            PromptKind::Photo => "OPENAI_PHOTO_PROMPT",
+            PromptKind::Transcription => "OPENAI_STT_PROMPT",
+            PromptKind::VoiceCommand => "OPENAI_VOICE_COMMAND_PROMPT",
+        }
+    }
+
+    /// The `chat_settings` key of a chat's override.
+    pub fn setting_key(self) -> &'static str {
+        match self {
+            PromptKind::Text => "prompt_text",
+            PromptKind::Photo => "prompt_photo",
+            PromptKind::Transcription => "prompt_transcription",
+            PromptKind::VoiceCommand => "prompt_voice",
+        }
+    }
+
+    pub fn from_setting_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.setting_key() == key)
+    }
+
+    pub fn built_in(self) -> &'static str {
+        match self {
+            PromptKind::Text => CATEGORIZED_PARSING_PROMPT,
+            PromptKind::Photo => PHOTO_PARSING_PROMPT,
+            PromptKind::Transcription => DEFAULT_STT_PROMPT,
+            PromptKind::VoiceCommand => VOICE_COMMAND_PROMPT,
+        }
+    }
+}
+
+/// Overrides of the built-in prompts. The environment sets them for every
+/// chat on [`AiConfig`](crate::ai::config::AiConfig), chat settings for one
+/// chat; a chat's override wins over the environment, which wins over the
+/// built-in prompt.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Prompts {
+    overrides: [Option<String>; 4],
+}
+
+impl Prompts {
+    pub fn from_env() -> Self {
+        let mut prompts = Self::default();
+        for kind in PromptKind::ALL {
+            if let Ok(prompt) = env::var(kind.env_var()) {
+                prompts.set(kind, &prompt);
+            }
+        }
+        prompts
+    }
+
+    /// Override `kind`; a blank prompt keeps the fallback.
+    pub fn set(&mut self, kind: PromptKind, prompt: &str) {
+        let prompt = prompt.trim();
+        self.overrides[kind as usize] = (!prompt.is_empty()).then(|| prompt.to_string());
+    }
+
+    pub fn override_of(&self, kind: PromptKind) -> Option<&str> {
+        self.overrides[kind as usize].as_deref()
+    }
+
+    /// These prompts with the overrides of `chat` on top.
+    pub fn overridden_by(&self, chat: &Prompts) -> Prompts {
+        let mut merged = self.clone();
+        for kind in PromptKind::ALL {
+            if let Some(prompt) = chat.override_of(kind) {
+                merged.set(kind, prompt);
+            }
+        }
+        merged
+    }
+
+    /// The prompt in effect for `kind`.
+    pub fn get(&self, kind: PromptKind) -> &str {
+        self.override_of(kind).unwrap_or(kind.built_in())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chat_overrides_win_over_env_over_built_in() {
+        let mut env = Prompts::default();
+        assert_eq!(env.get(PromptKind::Photo), PHOTO_PARSING_PROMPT);
+
+        env.set(PromptKind::Photo, "env photo");
+        env.set(PromptKind::Text, "env text");
+        let mut chat = Prompts::default();
+        chat.set(PromptKind::Text, " chat text ");
+        let prompts = env.overridden_by(&chat);
+
+        assert_eq!(prompts.get(PromptKind::Text), "chat text");
+        assert_eq!(prompts.get(PromptKind::Photo), "env photo");
+        assert_eq!(prompts.get(PromptKind::VoiceCommand), VOICE_COMMAND_PROMPT);
+    }
+
+    #[test]
+    fn blank_overrides_are_ignored() {
+        let mut prompts = Prompts::default();
+        prompts.set(PromptKind::Transcription, "  ");
+        assert_eq!(prompts.override_of(PromptKind::Transcription), None);
+        assert_eq!(prompts.get(PromptKind::Transcription), DEFAULT_STT_PROMPT);
+    }
+
+    #[test]
+    fn kinds_round_trip_through_names_and_keys() {
+        for kind in PromptKind::ALL {
+            assert_eq!(PromptKind::parse(kind.name()), Some(kind));
+            assert_eq!(PromptKind::from_setting_key(kind.setting_key()), Some(kind));
+        }
+        assert_eq!(PromptKind::parse("VOICE"), Some(PromptKind::VoiceCommand));
+        assert_eq!(PromptKind::parse("list"), None);
+    }
+}
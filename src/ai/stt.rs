@@ -3,55 +3,95 @@ use reqwest::multipart::{Form, Part};
 use serde::Deserialize;
 use tracing::{debug, instrument, trace};
 
+use crate::ai::common::OpenAiClient;
+
 pub use crate::ai::prompts::DEFAULT_STT_PROMPT as DEFAULT_PROMPT;
 
-#[derive(Deserialize)]
-struct TranscriptionResponse {
-    text: String,
+/// What the transcription API heard. `language` is only reported by models
+/// that support the `verbose_json` response format.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Transcription {
+    pub text: String,
+    #[serde(default)]
+    pub language: Option<String>,
 }
 
 const OPENAI_STT_URL: &str = "https://api.openai.com/v1/audio/transcriptions";
 
-#[instrument(level = "trace", skip(api_key, bytes))]
+/// A recording to transcribe. The API tells formats apart by the file name,
+/// so it has to carry the right extension.
+#[derive(Debug, Clone, Copy)]
+pub struct AudioFile<'a> {
+    pub bytes: &'a [u8],
+    pub file_name: &'a str,
+    pub mime_type: Option<&'a str>,
+}
+
+#[instrument(level = "trace", skip(api_key, audio), fields(file_name = audio.file_name))]
 async fn transcribe_audio_inner(
     model: &str,
     api_key: &str,
     prompt: Option<&str>,
-    bytes: &[u8],
+    language: Option<&str>,
+    audio: AudioFile<'_>,
     url: &str,
-) -> Result<String> {
-    let part = Part::bytes(bytes.to_vec()).file_name("voice.ogg");
-    let mut form = Form::new()
-        .part("file", part)
-        .text("model", model.to_string());
-    if let Some(p) = prompt {
-        form = form.text("prompt", p.to_string());
-    }
+    openai: &OpenAiClient,
+) -> Result<Transcription> {
+    let build_form = || -> Result<Form> {
+        let mut part = Part::bytes(audio.bytes.to_vec()).file_name(audio.file_name.to_string());
+        if let Some(mime_type) = audio.mime_type {
+            part = part.mime_str(mime_type)?;
+        }
+        let mut form = Form::new()
+            .part("file", part)
+            .text("model", model.to_string());
+        if let Some(p) = prompt {
+            form = form.text("prompt", p.to_string());
+        }
+        if let Some(language) = language {
+            form = form.text("language", language.to_string());
+        }
+        if reports_language(model) {
+            form = form.text("response_format", "verbose_json");
+        }
+        Ok(form)
+    };
 
-    debug!(model, prompt=?prompt, url, "sending transcription request");
+    debug!(model, prompt=?prompt, language, url, "sending transcription request");
 
-    let client = crate::ai::common::client_for_url(url)?;
-    let builder = client.post(url).multipart(form);
-    let resp = crate::ai::common::send_openai_request(api_key, builder).await?;
+    let client = openai.http_client(url)?;
+    let resp = crate::ai::common::send_openai_request(api_key, openai, || {
+        Ok(client.post(url).multipart(build_form()?))
+    })
+    .await?;
 
     let raw = resp.text().await?;
     let snippet: String = raw.chars().take(200).collect();
     debug!(snippet = %snippet, "transcription response body");
-    let data: TranscriptionResponse = serde_json::from_str(&raw)?;
+    let data: Transcription = serde_json::from_str(&raw)?;
+    debug!(language = ?data.language, "transcription language");
     trace!(transcription = %data.text, "transcription successful");
-    Ok(data.text)
+    Ok(data)
+}
+
+/// Only the Whisper models answer with `verbose_json`, which carries the
+/// detected language; the newer transcription models reject it.
+fn reports_language(model: &str) -> bool {
+    model.starts_with("whisper")
 }
 
-#[instrument(level = "trace", skip(api_key, bytes))]
+#[instrument(level = "trace", skip(api_key, audio), fields(file_name = audio.file_name))]
 pub async fn transcribe_audio(
     model: &str,
     api_key: &str,
     prompt: Option<&str>,
-    bytes: &[u8],
+    language: Option<&str>,
+    audio: AudioFile<'_>,
     url: Option<&str>,
-) -> Result<String> {
+    openai: &OpenAiClient,
+) -> Result<Transcription> {
     let url = url.unwrap_or(OPENAI_STT_URL);
-    transcribe_audio_inner(model, api_key, prompt, bytes, url).await
+    transcribe_audio_inner(model, api_key, prompt, language, audio, url, openai).await
 }
 
 /// Split a text string into individual items.
@@ -1,31 +1,265 @@
-use crate::ai::common::{request_items, OPENAI_CHAT_URL};
-use crate::ai::prompts::PHOTO_PARSING_PROMPT;
+use crate::ai::common::{
+    request_items, request_priced_items, OpenAiClient, PricedItem, OPENAI_CHAT_URL,
+};
+use crate::ai::config::AiConfig;
+use crate::ai::prompts::{PHOTO_CAPTION_PREFIX, RECEIPT_PARSING_PROMPT};
 use anyhow::Result;
 use base64::Engine as _;
-use tracing::instrument;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::ImageReader;
+use std::io::Cursor;
+use tracing::{debug, instrument, warn};
 
-#[instrument(level = "trace", skip(api_key, bytes))]
+/// Longest edge, in pixels, of images sent to the vision model by default.
+pub const DEFAULT_VISION_MAX_EDGE: u32 = 1024;
+const DOWNSCALED_JPEG_QUALITY: u8 = 80;
+
+/// The vision model photos are sent to and how they are sent.
+#[derive(Clone, Copy)]
+pub struct VisionModel<'a> {
+    pub api_key: &'a str,
+    pub model: &'a str,
+    /// Images larger than this are scaled down first.
+    pub max_edge: Option<u32>,
+    pub url: Option<&'a str>,
+    pub openai: &'a OpenAiClient,
+}
+
+impl<'a> From<&'a AiConfig> for VisionModel<'a> {
+    fn from(config: &'a AiConfig) -> Self {
+        Self {
+            api_key: &config.api_key,
+            model: &config.vision_model,
+            max_edge: config.vision_max_edge,
+            url: config.openai_chat_url.as_deref(),
+            openai: &config.openai,
+        }
+    }
+}
+
+/// Parse items from an image. `mime` names its type when the caller knows
+/// it; otherwise the type is read from the image's leading bytes. A
+/// `caption` sent with the photo is passed along so the model can follow it.
+#[instrument(level = "trace", skip(vision, prompt, bytes), fields(model = vision.model))]
 pub async fn parse_photo_items(
-    api_key: &str,
-    model: &str,
+    vision: &VisionModel<'_>,
+    prompt: &str,
     bytes: &[u8],
-    url: Option<&str>,
+    mime: Option<&str>,
+    caption: Option<&str>,
 ) -> Result<Vec<String>> {
-    let url = url.unwrap_or(OPENAI_CHAT_URL);
-    parse_photo_items_inner(api_key, model, bytes, url).await
+    let url = vision.url.unwrap_or(OPENAI_CHAT_URL);
+    parse_photo_items_inner(vision, prompt, bytes, mime, caption, url).await
 }
 
 #[cfg_attr(not(test), allow(dead_code))]
-#[instrument(level = "trace", skip(api_key, bytes))]
+#[instrument(level = "trace", skip(vision, prompt, bytes), fields(model = vision.model))]
 pub async fn parse_photo_items_inner(
-    api_key: &str,
-    model: &str,
+    vision: &VisionModel<'_>,
+    prompt: &str,
     bytes: &[u8],
+    mime: Option<&str>,
+    caption: Option<&str>,
     url: &str,
 ) -> Result<Vec<String>> {
+    let data_url = vision_data_url(bytes, mime, vision.max_edge);
+    let note = caption.map(caption_note);
+    let body = crate::ai::common::build_image_chat_body(
+        vision.model,
+        prompt,
+        &[data_url],
+        note.as_deref(),
+    );
+
+    request_items(vision.api_key, &body, url, vision.openai).await
+}
+
+/// Parse items from the photos of one album in a single request, so the
+/// model sees every page of a list at once.
+#[instrument(level = "trace", skip(vision, prompt, photos), fields(model = vision.model))]
+pub async fn parse_album_items(
+    vision: &VisionModel<'_>,
+    prompt: &str,
+    photos: &[Vec<u8>],
+    caption: Option<&str>,
+) -> Result<Vec<String>> {
+    let url = vision.url.unwrap_or(OPENAI_CHAT_URL);
+    debug!(photos = photos.len(), "Parsing album with OpenAI vision");
+    let data_urls: Vec<String> = photos
+        .iter()
+        .map(|bytes| vision_data_url(bytes, None, vision.max_edge))
+        .collect();
+    let note = caption.map(caption_note);
+    let body =
+        crate::ai::common::build_image_chat_body(vision.model, prompt, &data_urls, note.as_deref());
+
+    request_items(vision.api_key, &body, url, vision.openai).await
+}
+
+/// Read the products and prices on the receipt in `photos`, which may be
+/// several pages of one receipt. Lines without a readable price are kept.
+#[instrument(level = "trace", skip(vision, photos), fields(model = vision.model))]
+pub async fn parse_receipt_items(
+    vision: &VisionModel<'_>,
+    photos: &[Vec<u8>],
+    caption: Option<&str>,
+) -> Result<Vec<PricedItem>> {
+    let url = vision.url.unwrap_or(OPENAI_CHAT_URL);
+    debug!(photos = photos.len(), "Parsing receipt with OpenAI vision");
+    let data_urls: Vec<String> = photos
+        .iter()
+        .map(|bytes| vision_data_url(bytes, None, vision.max_edge))
+        .collect();
+    let note = caption.map(caption_note);
+    let body = crate::ai::common::build_image_chat_body(
+        vision.model,
+        RECEIPT_PARSING_PROMPT,
+        &data_urls,
+        note.as_deref(),
+    );
+
+    request_priced_items(vision.api_key, &body, url, vision.openai).await
+}
+
+/// The extra user message that carries a photo's caption.
+fn caption_note(caption: &str) -> String {
+    format!("{PHOTO_CAPTION_PREFIX} {}", caption.trim())
+}
+
+/// The data URL of an image, scaled down to `max_edge` when it is larger.
+fn vision_data_url(bytes: &[u8], mime: Option<&str>, max_edge: Option<u32>) -> String {
+    match max_edge.and_then(|max_edge| downscale(bytes, max_edge)) {
+        Some(jpeg) => image_data_url(&jpeg, Some("image/jpeg")),
+        None => image_data_url(bytes, mime),
+    }
+}
+
+/// Scale the image down so its longest edge is at most `max_edge` and
+/// re-encode it as JPEG. `None` when it is already small enough or cannot be
+/// read, in which case the original is sent.
+fn downscale(bytes: &[u8], max_edge: u32) -> Option<Vec<u8>> {
+    let reader = ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .ok()?;
+    let (width, height) = match reader.into_dimensions() {
+        Ok(dimensions) => dimensions,
+        Err(err) => {
+            debug!(error = %err, "Could not read image dimensions; not downscaling");
+            return None;
+        }
+    };
+    if width.max(height) <= max_edge {
+        debug!(
+            width,
+            height, max_edge, "Image small enough; not downscaling"
+        );
+        return None;
+    }
+
+    let image = match image::load_from_memory(bytes) {
+        Ok(image) => image,
+        Err(err) => {
+            warn!(error = %err, "Could not decode image; sending the original");
+            return None;
+        }
+    };
+    let resized = image
+        .resize(max_edge, max_edge, FilterType::Triangle)
+        .to_rgb8();
+    let mut jpeg = Vec::new();
+    let encoder = JpegEncoder::new_with_quality(&mut jpeg, DOWNSCALED_JPEG_QUALITY);
+    if let Err(err) = resized.write_with_encoder(encoder) {
+        warn!(error = %err, "Could not encode downscaled image; sending the original");
+        return None;
+    }
+    debug!(
+        width,
+        height,
+        new_width = resized.width(),
+        new_height = resized.height(),
+        original_bytes = bytes.len(),
+        downscaled_bytes = jpeg.len(),
+        "Downscaled image for vision"
+    );
+    Some(jpeg)
+}
+
+/// The image type given away by the magic bytes at the start of `bytes`.
+fn sniff_image_mime(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if bytes.len() >= 12 && bytes.starts_with(b"RIFF") && &bytes[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
+    }
+}
+
+fn image_data_url(bytes: &[u8], mime: Option<&str>) -> String {
+    let mime = match mime.or_else(|| sniff_image_mime(bytes)) {
+        Some(mime) => mime,
+        None => {
+            warn!(
+                len = bytes.len(),
+                "Unknown image format; sending it untyped"
+            );
+            "application/octet-stream"
+        }
+    };
+    debug!(mime, "Encoding image for vision");
     let encoded = base64::engine::general_purpose::STANDARD.encode(bytes);
-    let data_url = format!("data:image/png;base64,{}", encoded);
-    let body = crate::ai::common::build_image_chat_body(model, PHOTO_PARSING_PROMPT, &data_url);
+    format!("data:{mime};base64,{encoded}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_urls_carry_the_sniffed_type() {
+        let jpeg = [0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10, b'J', b'F', b'I', b'F'];
+        let png = b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR";
+        let webp = b"RIFF\x24\x00\x00\x00WEBPVP8 ";
+        assert!(image_data_url(&jpeg, None).starts_with("data:image/jpeg;base64,"));
+        assert!(image_data_url(png, None).starts_with("data:image/png;base64,"));
+        assert!(image_data_url(webp, None).starts_with("data:image/webp;base64,"));
+        assert!(
+            image_data_url(b"GIF89a", None).starts_with("data:application/octet-stream;base64,")
+        );
+    }
+
+    fn png_of(width: u32, height: u32) -> Vec<u8> {
+        let mut png = Vec::new();
+        image::RgbImage::new(width, height)
+            .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        png
+    }
+
+    #[test]
+    fn large_images_are_downscaled_to_jpeg() {
+        let jpeg = downscale(&png_of(4000, 3000), DEFAULT_VISION_MAX_EDGE).unwrap();
+        assert_eq!(sniff_image_mime(&jpeg), Some("image/jpeg"));
+        let decoded = image::load_from_memory(&jpeg).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (1024, 768));
+    }
+
+    #[test]
+    fn small_and_unreadable_images_are_left_alone() {
+        assert_eq!(downscale(&png_of(800, 600), DEFAULT_VISION_MAX_EDGE), None);
+        assert_eq!(downscale(b"not an image", DEFAULT_VISION_MAX_EDGE), None);
+    }
 
-    request_items(api_key, &body, url).await
+    #[test]
+    fn explicit_types_win_over_sniffing() {
+        let url = image_data_url(b"RIFF\x24\x00\x00\x00WEBPVP8 ", Some("image/heic"));
+        assert!(url.starts_with("data:image/heic;base64,"));
+        assert_eq!(
+            image_data_url(b"img", None),
+            "data:application/octet-stream;base64,aW1n"
+        );
+    }
 }
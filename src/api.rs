@@ -1,27 +1,85 @@
 use axum::{
     body::Body,
-    extract::{Extension, State},
-    http::{header::AUTHORIZATION, HeaderMap, HeaderName, Request, StatusCode},
+    extract::{
+        rejection::{JsonRejection, QueryRejection},
+        DefaultBodyLimit, Extension, FromRef, Query, State,
+    },
+    http::{
+        header::{AUTHORIZATION, CONTENT_TYPE, LINK},
+        HeaderMap, HeaderName, HeaderValue, Request, StatusCode,
+    },
     middleware::{self, Next},
     response::{IntoResponse, Response},
     routing::{get, post},
     Json, Router,
 };
 use serde::{Deserialize, Serialize};
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use teloxide::types::ChatId;
+use tokio::net::TcpListener;
 use tokio::sync::Mutex;
+use tracing::Instrument;
 use uuid::Uuid;
 
-use crate::db::{Database, Item};
+use crate::db::{Database, Item, ItemAuthor, ItemFilter, ItemSort, TokenScope};
+use crate::notify::{ListEvent, ListEventKind};
+use crate::state::SharedState;
+use crate::text_utils::parse_item_line;
+
+mod archives;
+mod changes;
+mod cors;
+mod error;
+mod export;
+mod health;
+mod integrations;
+mod items;
+mod openapi;
+mod pairing;
+mod reorder;
+mod request_log;
+mod scheduler;
+mod share;
+mod tokens;
+
+pub use cors::CorsOrigins;
+
+use error::{ApiError, ErrorCode};
+
+pub(crate) use integrations::SIGNATURE_HEADER;
+
+/// Header used to tie an API request to logs across services.
+const CORRELATION_ID_HEADER: &str = "x-correlation-id";
+
+/// Prefix of the stable, versioned routes.
+const API_V1_PREFIX: &str = "/api/v1";
+
+/// Prefix of the unversioned aliases kept for existing clients.
+const LEGACY_PREFIX: &str = "/api";
+
+/// Sent on every response so clients can tell which API they talk to.
+const API_VERSION_HEADER: &str = "x-api-version";
+
+/// Largest request body accepted; item text is capped separately.
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// Longest item text accepted unless configured otherwise, in characters.
+pub const DEFAULT_MAX_ITEM_LENGTH: usize = 256;
 
 #[derive(Debug, Serialize, Deserialize)]
 struct ApiItem {
     id: i64,
     text: String,
     done: bool,
+    quantity: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    added_by: Option<String>,
+    /// Unix seconds when the item was added; 0 for items older than timestamps.
+    #[serde(default)]
+    created_at: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -29,9 +87,33 @@ struct ListResponse {
     items: Vec<ApiItem>,
 }
 
+#[derive(Debug, Deserialize)]
+struct ListQuery {
+    /// Only return items added at or after this unix timestamp.
+    #[serde(default)]
+    since: Option<i64>,
+    #[serde(default)]
+    done: Option<bool>,
+    /// Substring to match, trimmed and compared ignoring case.
+    #[serde(default)]
+    q: Option<String>,
+    #[serde(default)]
+    sort: Option<ListSort>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ListSort {
+    Created,
+    Alpha,
+}
+
 #[derive(Debug, Deserialize)]
 struct AddRequest {
     text: String,
+    /// Name shown as the item's author when the chat displays authors.
+    #[serde(default)]
+    added_by: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -49,16 +131,63 @@ struct MutationResponse {
     affected: u64,
 }
 
-#[derive(Debug, Serialize)]
-struct ErrorResponse {
-    error: &'static str,
+/// What `POST /api/v1/done` moved into a partial archive and how many items
+/// are left; `archive_id` is `None` when nothing was checked.
+#[derive(Debug, Serialize, Deserialize)]
+struct DoneResponse {
+    archived: Vec<ApiItem>,
+    remaining: u64,
+    archive_id: Option<i64>,
+}
+
+/// What `POST /api/v1/archive` stored; `archive_id` is `None` when the list
+/// was empty and nothing was archived.
+#[derive(Debug, Serialize, Deserialize)]
+struct ArchiveResponse {
+    affected: u64,
+    archive_id: Option<i64>,
+    items: Vec<ApiItem>,
 }
 
 #[derive(Clone, Debug)]
 pub struct ApiConfig {
     pub rate_limit_per_second: Option<u64>,
+    /// Shared secret for signed assistant webhooks; routes are disabled when unset.
+    pub integration_secret: Option<String>,
+    /// Sustained mutation rate above which API writes are queued.
+    pub mutation_rate_per_second: Option<u64>,
+    /// Longest item text accepted, counted in characters.
+    pub max_item_length: usize,
+    /// Browser origins allowed by CORS; no CORS headers are sent when unset.
+    pub cors_origins: Option<CorsOrigins>,
+    /// Trace request bodies, with secret fields redacted.
+    pub log_request_bodies: bool,
+}
+
+/// Router state of the authenticated routes. Handlers extract the parts
+/// they need, e.g. `State<Database>`.
+#[derive(Clone)]
+struct ApiState {
+    db: Database,
+    shared: SharedState,
 }
 
+impl FromRef<ApiState> for Database {
+    fn from_ref(state: &ApiState) -> Self {
+        state.db.clone()
+    }
+}
+
+impl FromRef<ApiState> for SharedState {
+    fn from_ref(state: &ApiState) -> Self {
+        state.shared.clone()
+    }
+}
+
+/// `ApiConfig::max_item_length`, handed to handlers as an extension.
+#[derive(Clone, Copy, Debug)]
+struct ItemTextLimit(usize);
+
 #[derive(Clone, Debug)]
 struct RequestContext {
     request_id: String,
@@ -68,42 +197,179 @@ struct RequestContext {
 struct AuthenticatedContext {
     chat_id: ChatId,
     token_preview: String,
+    scope: TokenScope,
 }
 
+/// Sliding-window request limit, tracked separately for every chat so one
+/// busy integration cannot use up another chat's quota.
 #[derive(Debug)]
 struct RateLimiter {
     limit: u64,
     window: Duration,
-    timestamps: Mutex<VecDeque<Instant>>,
+    buckets: Mutex<RateBuckets>,
+}
+
+#[derive(Debug)]
+struct RateBuckets {
+    by_chat: HashMap<i64, VecDeque<Instant>>,
+    last_sweep: Instant,
+}
+
+impl RateLimiter {
+    fn new(limit: u64, window: Duration) -> Self {
+        Self {
+            limit,
+            window,
+            buckets: Mutex::new(RateBuckets {
+                by_chat: HashMap::new(),
+                last_sweep: Instant::now(),
+            }),
+        }
+    }
+
+    /// Record a request for `chat_id` if it is under the limit. Buckets
+    /// idle for a whole window are dropped at most once per window.
+    async fn try_acquire(&self, chat_id: ChatId, now: Instant) -> bool {
+        let mut buckets = self.buckets.lock().await;
+        if now.duration_since(buckets.last_sweep) >= self.window {
+            let window = self.window;
+            let before = buckets.by_chat.len();
+            buckets.by_chat.retain(|_, timestamps| {
+                timestamps
+                    .back()
+                    .is_some_and(|ts| now.duration_since(*ts) < window)
+            });
+            buckets.last_sweep = now;
+            tracing::trace!(
+                evicted = before - buckets.by_chat.len(),
+                remaining = buckets.by_chat.len(),
+                "Swept idle rate limit buckets"
+            );
+        }
+
+        let timestamps = buckets.by_chat.entry(chat_id.0).or_default();
+        while timestamps
+            .front()
+            .is_some_and(|ts| now.duration_since(*ts) >= self.window)
+        {
+            timestamps.pop_front();
+        }
+        if timestamps.len() as u64 >= self.limit {
+            return false;
+        }
+        timestamps.push_back(now);
+        true
+    }
+
+    #[cfg(test)]
+    async fn bucket_count(&self) -> usize {
+        self.buckets.lock().await.by_chat.len()
+    }
 }
 
-pub fn router(db: Database, config: ApiConfig) -> Router {
+pub fn router(db: Database, shared: SharedState, config: ApiConfig) -> Router {
+    let metrics = shared.metrics.clone();
     let auth_layer = middleware::from_fn_with_state(db.clone(), require_auth);
     let request_id_layer = middleware::from_fn(assign_request_id);
+    let legacy = Router::new()
+        .route("/list", get(get_list))
+        .route("/add", post(add_item))
+        .route("/toggle", post(toggle_item))
+        .route("/delete", post(delete_item))
+        .route("/archive", post(archive_list))
+        .route("/nuke", post(nuke_list))
+        .route("/done", post(done_list))
+        .merge(items::routes());
+    // Endpoints added after versioning only exist under v1.
+    let v1 = legacy
+        .clone()
+        .merge(archives::routes())
+        .merge(changes::routes())
+        .merge(export::routes())
+        .merge(reorder::routes())
+        .merge(tokens::routes());
     let mut router = Router::new()
-        .route("/api/list", get(get_list))
-        .route("/api/add", post(add_item))
-        .route("/api/toggle", post(toggle_item))
-        .route("/api/delete", post(delete_item))
-        .route("/api/archive", post(archive_list))
-        .route("/api/nuke", post(nuke_list))
-        .route("/api/done", post(done_list))
-        .with_state(db);
+        .nest(API_V1_PREFIX, v1)
+        .nest(
+            LEGACY_PREFIX,
+            legacy.layer(middleware::from_fn(mark_deprecated)),
+        )
+        .route("/metrics", get(render_metrics))
+        .layer(Extension(ItemTextLimit(config.max_item_length)))
+        .with_state(ApiState {
+            db: db.clone(),
+            shared: shared.clone(),
+        });
+
+    let scheduler = config.mutation_rate_per_second.map(|rate| {
+        Arc::new(scheduler::WriteScheduler::new(
+            rate,
+            Duration::from_secs(1),
+            metrics.clone(),
+        ))
+    });
+    if let Some(scheduler) = scheduler.clone() {
+        let scheduler_layer =
+            middleware::from_fn_with_state(scheduler, scheduler::schedule_mutations);
+        router = router.layer(scheduler_layer);
+    }
 
     if let Some(rate_limit) = config.rate_limit_per_second {
-        let limiter = Arc::new(RateLimiter {
-            limit: rate_limit,
-            window: Duration::from_secs(1),
-            timestamps: Mutex::new(VecDeque::new()),
-        });
+        let limiter = Arc::new(RateLimiter::new(rate_limit, Duration::from_secs(1)));
         let rate_limit_layer = middleware::from_fn_with_state(limiter, rate_limit_requests);
         router = router.layer(rate_limit_layer);
     }
 
-    router.layer(auth_layer).layer(request_id_layer)
+    // Added last so it runs first: the rate limiter and scheduler only ever
+    // see authenticated requests.
+    let mut router = router
+        .layer(auth_layer)
+        .merge(health::router(db.clone()))
+        .merge(share::router(db.clone()))
+        .merge(pairing::router(db.clone()))
+        .merge(openapi::router(config.integration_secret.is_some()));
+    if let Some(secret) = config.integration_secret {
+        router = router.merge(integrations::router(db, shared, secret, scheduler));
+    }
+    let mut router = router.layer(DefaultBodyLimit::max(MAX_BODY_BYTES));
+    if config.log_request_bodies {
+        router = router.layer(middleware::from_fn(request_log::log_request_bodies));
+    }
+    if let Some(origins) = config.cors_origins {
+        // Outside auth so preflights, which carry no token, are answered.
+        router = router.layer(middleware::from_fn_with_state(
+            Arc::new(origins),
+            cors::handle_cors,
+        ));
+    }
+    router
+        .layer(middleware::from_fn_with_state(
+            metrics,
+            request_log::log_requests,
+        ))
+        .layer(request_id_layer)
+}
+
+/// Serve `router` on `listener` until `shutdown` resolves; requests already
+/// in flight are allowed to finish.
+pub async fn serve<F>(listener: TcpListener, router: Router, shutdown: F) -> std::io::Result<()>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let api_addr = listener.local_addr()?;
+    tracing::info!(api_addr = %api_addr, "API server listening");
+    axum::serve(listener, router)
+        .with_graceful_shutdown(shutdown)
+        .await?;
+    tracing::info!(api_addr = %api_addr, "API server stopped");
+    Ok(())
 }
 
-async fn require_auth(State(db): State<Database>, mut req: Request<Body>, next: Next) -> Response {
+async fn require_auth(
+    State(db): State<Database>,
+    mut req: Request<Body>,
+    next: Next,
+) -> Result<Response, ApiError> {
     let request_id = req
         .extensions()
         .get::<RequestContext>()
@@ -113,22 +379,22 @@ async fn require_auth(State(db): State<Database>, mut req: Request<Body>, next:
         Some(token) => token,
         None => {
             tracing::debug!(request_id, "Missing bearer token");
-            return unauthorized_response();
+            return Err(ApiError::new(ErrorCode::Unauthorized, request_id));
         }
     };
 
     let preview = token_preview(&token);
     tracing::debug!(request_id, token_preview = %preview, "Checking bearer token");
     let used_at = chrono::Utc::now().timestamp();
-    let chat_id = match db.use_token(&token, used_at).await {
-        Ok(Some(chat_id)) => chat_id,
+    let (chat_id, scope) = match db.use_token(&token, used_at).await {
+        Ok(Some(grant)) => grant,
         Ok(None) => {
             tracing::debug!(request_id, token_preview = %preview, "Bearer token rejected");
-            return unauthorized_response();
+            return Err(ApiError::new(ErrorCode::Unauthorized, request_id));
         }
         Err(err) => {
             tracing::error!(request_id, token_preview = %preview, error = %err, "Failed to validate bearer token");
-            return internal_error_response();
+            return Err(ApiError::new(ErrorCode::Internal, request_id));
         }
     };
 
@@ -136,22 +402,58 @@ async fn require_auth(State(db): State<Database>, mut req: Request<Body>, next:
         request_id,
         chat_id = chat_id.0,
         token_preview = %preview,
+        scope = scope.name(),
         "Authenticated API request"
     );
-    req.extensions_mut().insert(AuthenticatedContext {
+    let context = AuthenticatedContext {
         chat_id,
+        scope,
         token_preview: preview,
-    });
-    next.run(req).await
+    };
+    req.extensions_mut().insert(context.clone());
+    let mut response = next.run(req).await;
+    // Lets the request log attribute the request to the chat.
+    response.extensions_mut().insert(context);
+    Ok(response)
 }
 
 async fn get_list(
     State(db): State<Database>,
     Extension(context): Extension<AuthenticatedContext>,
     Extension(request): Extension<RequestContext>,
-) -> Response {
+    query: Result<Query<ListQuery>, QueryRejection>,
+) -> Result<Response, ApiError> {
     let chat_id = context.chat_id;
-    let items = match db.list_items(chat_id).await {
+    let Query(query) = query.map_err(|rejection| {
+        tracing::debug!(
+            request_id = %request.request_id,
+            chat_id = chat_id.0,
+            error = %rejection.body_text(),
+            "Invalid list query"
+        );
+        request
+            .error(ErrorCode::InvalidRequest)
+            .with_message(rejection.body_text())
+    })?;
+    if query.q.as_deref().is_some_and(|q| q.trim().is_empty()) {
+        return Err(request
+            .error(ErrorCode::InvalidRequest)
+            .with_message("q must contain text to match"));
+    }
+
+    // Without an explicit sort, `since` keeps its oldest-first order.
+    let sort = match (query.sort, query.since) {
+        (Some(ListSort::Created), _) | (None, Some(_)) => ItemSort::Created,
+        (Some(ListSort::Alpha), _) => ItemSort::Alpha,
+        (None, None) => ItemSort::Position,
+    };
+    let filter = ItemFilter {
+        since: query.since,
+        done: query.done,
+        query: query.q,
+        sort,
+    };
+    let items = match db.list_items_filtered(chat_id, &filter).await {
         Ok(items) => items,
         Err(err) => {
             tracing::error!(
@@ -161,7 +463,7 @@ async fn get_list(
                 error = %err,
                 "Failed to load items"
             );
-            return internal_error_response();
+            return Err(request.error(ErrorCode::Internal));
         }
     };
 
@@ -170,28 +472,39 @@ async fn get_list(
         chat_id = chat_id.0,
         token_preview = %context.token_preview,
         item_count = items.len(),
+        ?filter,
         "Read list items"
     );
     let response = ListResponse {
         items: items.into_iter().map(ApiItem::from).collect(),
     };
-    (StatusCode::OK, Json(response)).into_response()
+    Ok((StatusCode::OK, Json(response)).into_response())
 }
 
 async fn add_item(
     State(db): State<Database>,
+    State(shared): State<SharedState>,
     Extension(context): Extension<AuthenticatedContext>,
     Extension(request): Extension<RequestContext>,
-    Json(payload): Json<AddRequest>,
-) -> Response {
+    Extension(limit): Extension<ItemTextLimit>,
+    payload: Result<Json<AddRequest>, JsonRejection>,
+) -> Result<Response, ApiError> {
+    reject_read_only(&context, &request)?;
+    let payload = json_body(&request, payload)?;
     let chat_id = context.chat_id;
-    let text = payload.text.trim();
-    if text.is_empty() {
-        return bad_request_response();
-    }
+    let text = item_text(&request, &payload.text, limit)?;
 
-    let affected = match db.add_item_count(chat_id, text).await {
-        Ok(affected) => affected,
+    let author = payload
+        .added_by
+        .as_deref()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| ItemAuthor {
+            user_id: None,
+            name: name.to_string(),
+        });
+    let item = match db.add_item_returning(chat_id, &text, author.as_ref()).await {
+        Ok(item) => item,
         Err(err) => {
             tracing::error!(
                 request_id = %request.request_id,
@@ -200,7 +513,7 @@ async fn add_item(
                 error = %err,
                 "Failed to add item"
             );
-            return internal_error_response();
+            return Err(request.error(ErrorCode::Internal));
         }
     };
 
@@ -208,19 +521,26 @@ async fn add_item(
         request_id = %request.request_id,
         chat_id = chat_id.0,
         token_preview = %context.token_preview,
-        affected,
+        item_id = item.id,
         text = %text,
         "Added item via API"
     );
-    (StatusCode::CREATED, Json(MutationResponse { affected })).into_response()
+    shared.metrics.record_items_added("api", 1);
+    shared
+        .events
+        .list_changed(&db, ListEvent::item(chat_id, ListEventKind::Add, item.id));
+    Ok((StatusCode::CREATED, Json(ApiItem::from(item))).into_response())
 }
 
 async fn toggle_item(
     State(db): State<Database>,
+    State(shared): State<SharedState>,
     Extension(context): Extension<AuthenticatedContext>,
     Extension(request): Extension<RequestContext>,
-    Json(payload): Json<ToggleRequest>,
-) -> Response {
+    payload: Result<Json<ToggleRequest>, JsonRejection>,
+) -> Result<Response, ApiError> {
+    reject_read_only(&context, &request)?;
+    let payload = json_body(&request, payload)?;
     let chat_id = context.chat_id;
     let affected = match db.toggle_item_count(chat_id, payload.id).await {
         Ok(affected) => affected,
@@ -232,7 +552,7 @@ async fn toggle_item(
                 error = %err,
                 "Failed to toggle item"
             );
-            return internal_error_response();
+            return Err(request.error(ErrorCode::Internal));
         }
     };
 
@@ -245,17 +565,24 @@ async fn toggle_item(
         "Toggled item via API"
     );
     if affected == 0 {
-        return not_found_response();
+        return Err(request.error(ErrorCode::NotFound));
     }
-    (StatusCode::OK, Json(MutationResponse { affected })).into_response()
+    shared.events.list_changed(
+        &db,
+        ListEvent::item(chat_id, ListEventKind::Toggle, payload.id),
+    );
+    Ok((StatusCode::OK, Json(MutationResponse { affected })).into_response())
 }
 
 async fn delete_item(
     State(db): State<Database>,
+    State(shared): State<SharedState>,
     Extension(context): Extension<AuthenticatedContext>,
     Extension(request): Extension<RequestContext>,
-    Json(payload): Json<DeleteRequest>,
-) -> Response {
+    payload: Result<Json<DeleteRequest>, JsonRejection>,
+) -> Result<Response, ApiError> {
+    reject_read_only(&context, &request)?;
+    let payload = json_body(&request, payload)?;
     let chat_id = context.chat_id;
     let affected = match db.delete_item_count(chat_id, payload.id).await {
         Ok(affected) => affected,
@@ -267,7 +594,7 @@ async fn delete_item(
                 error = %err,
                 "Failed to delete item"
             );
-            return internal_error_response();
+            return Err(request.error(ErrorCode::Internal));
         }
     };
 
@@ -280,19 +607,26 @@ async fn delete_item(
         "Deleted item via API"
     );
     if affected == 0 {
-        return not_found_response();
+        return Err(request.error(ErrorCode::NotFound));
     }
-    (StatusCode::OK, Json(MutationResponse { affected })).into_response()
+    shared.events.list_changed(
+        &db,
+        ListEvent::item(chat_id, ListEventKind::Delete, payload.id),
+    );
+    Ok((StatusCode::OK, Json(MutationResponse { affected })).into_response())
 }
 
 async fn archive_list(
     State(db): State<Database>,
+    State(shared): State<SharedState>,
     Extension(context): Extension<AuthenticatedContext>,
     Extension(request): Extension<RequestContext>,
-) -> Response {
+) -> Result<Response, ApiError> {
+    reject_read_only(&context, &request)?;
     let chat_id = context.chat_id;
-    let affected = match db.delete_all_items_count(chat_id).await {
-        Ok(affected) => affected,
+    let archived_at = chrono::Utc::now().timestamp();
+    let archive = match db.create_archive(chat_id, archived_at).await {
+        Ok(archive) => archive,
         Err(err) => {
             tracing::error!(
                 request_id = %request.request_id,
@@ -301,24 +635,46 @@ async fn archive_list(
                 error = %err,
                 "Failed to archive list"
             );
-            return internal_error_response();
+            return Err(request.error(ErrorCode::Internal));
         }
     };
+    let archive_id = archive.as_ref().map(|archive| archive.id);
+    let items: Vec<ApiItem> = archive
+        .map(|archive| archive.items.into_iter().map(ApiItem::from).collect())
+        .unwrap_or_default();
+    let affected = items.len() as u64;
     tracing::debug!(
         request_id = %request.request_id,
         chat_id = chat_id.0,
         token_preview = %context.token_preview,
+        archive_id,
         affected,
         "Archived list via API"
     );
-    (StatusCode::OK, Json(MutationResponse { affected })).into_response()
+    if affected > 0 {
+        shared.events.list_changed(
+            &db,
+            ListEvent::batch(chat_id, ListEventKind::Archive, affected),
+        );
+    }
+    Ok((
+        StatusCode::OK,
+        Json(ArchiveResponse {
+            affected,
+            archive_id,
+            items,
+        }),
+    )
+        .into_response())
 }
 
 async fn nuke_list(
     State(db): State<Database>,
+    State(shared): State<SharedState>,
     Extension(context): Extension<AuthenticatedContext>,
     Extension(request): Extension<RequestContext>,
-) -> Response {
+) -> Result<Response, ApiError> {
+    reject_read_only(&context, &request)?;
     let chat_id = context.chat_id;
     let affected = match db.delete_all_items_count(chat_id).await {
         Ok(affected) => affected,
@@ -330,7 +686,7 @@ async fn nuke_list(
                 error = %err,
                 "Failed to nuke list"
             );
-            return internal_error_response();
+            return Err(request.error(ErrorCode::Internal));
         }
     };
     tracing::debug!(
@@ -340,71 +696,101 @@ async fn nuke_list(
         affected,
         "Nuked list via API"
     );
-    (StatusCode::OK, Json(MutationResponse { affected })).into_response()
+    if affected > 0 {
+        shared.events.list_changed(
+            &db,
+            ListEvent::batch(chat_id, ListEventKind::Nuke, affected),
+        );
+    }
+    Ok((StatusCode::OK, Json(MutationResponse { affected })).into_response())
 }
 
 async fn done_list(
     State(db): State<Database>,
+    State(shared): State<SharedState>,
     Extension(context): Extension<AuthenticatedContext>,
     Extension(request): Extension<RequestContext>,
-) -> Response {
+) -> Result<Response, ApiError> {
+    reject_read_only(&context, &request)?;
     let chat_id = context.chat_id;
-    let items = match db.list_items(chat_id).await {
-        Ok(items) => items,
-        Err(err) => {
-            tracing::error!(
-                request_id = %request.request_id,
-                chat_id = chat_id.0,
-                token_preview = %context.token_preview,
-                error = %err,
-                "Failed to load items"
-            );
-            return internal_error_response();
-        }
-    };
-
-    let done_ids: Vec<i64> = items
-        .iter()
-        .filter(|item| item.done)
-        .map(|i| i.id)
-        .collect();
-    let affected = match db.delete_items_count(chat_id, &done_ids).await {
-        Ok(affected) => affected,
-        Err(err) => {
-            tracing::error!(
-                request_id = %request.request_id,
-                chat_id = chat_id.0,
-                token_preview = %context.token_preview,
-                error = %err,
-                "Failed to archive checked items"
-            );
-            return internal_error_response();
-        }
+    let archived_at = chrono::Utc::now().timestamp();
+    let internal = |err: anyhow::Error| {
+        tracing::error!(
+            request_id = %request.request_id,
+            chat_id = chat_id.0,
+            token_preview = %context.token_preview,
+            error = %err,
+            "Failed to archive checked items"
+        );
+        request.error(ErrorCode::Internal)
     };
-
+    let archive = db
+        .create_checked_archive(chat_id, archived_at)
+        .await
+        .map_err(internal)?;
+    let (_, remaining) = db.count_items(chat_id).await.map_err(internal)?;
+
+    let archive_id = archive.as_ref().map(|archive| archive.id);
+    let archived: Vec<ApiItem> = archive
+        .map(|archive| archive.items.into_iter().map(ApiItem::from).collect())
+        .unwrap_or_default();
+    let affected = archived.len() as u64;
     tracing::debug!(
         request_id = %request.request_id,
         chat_id = chat_id.0,
         token_preview = %context.token_preview,
+        archive_id,
         affected,
-        done_count = done_ids.len(),
+        remaining,
         "Archived checked items via API"
     );
-    (StatusCode::OK, Json(MutationResponse { affected })).into_response()
+    if affected > 0 {
+        shared.events.list_changed(
+            &db,
+            ListEvent::batch(chat_id, ListEventKind::ArchiveDone, affected),
+        );
+    }
+    Ok((
+        StatusCode::OK,
+        Json(DoneResponse {
+            archived,
+            remaining,
+            archive_id,
+        }),
+    )
+        .into_response())
+}
+
+/// Prometheus text exposition of [`crate::metrics`]; any valid token may
+/// scrape it.
+async fn render_metrics(
+    State(shared): State<SharedState>,
+    Extension(context): Extension<AuthenticatedContext>,
+    Extension(request): Extension<RequestContext>,
+) -> Response {
+    tracing::debug!(
+        request_id = %request.request_id,
+        token_preview = %context.token_preview,
+        "Rendered metrics"
+    );
+    (
+        StatusCode::OK,
+        [(CONTENT_TYPE, "text/plain; version=0.0.4")],
+        shared.metrics.render(),
+    )
+        .into_response()
 }
 
+/// The token of an `Authorization: Bearer <token>` header. The scheme is
+/// compared case-insensitively and may be followed by any run of whitespace.
 fn extract_bearer_token(headers: &HeaderMap) -> Option<String> {
-    headers
-        .get(AUTHORIZATION)
-        .and_then(|value| value.to_str().ok())
-        .and_then(|value| {
-            value
-                .strip_prefix("Bearer ")
-                .or_else(|| value.strip_prefix("bearer "))
-        })
-        .map(str::trim)
-        .filter(|token| !token.is_empty())
-        .map(str::to_string)
+    let value = headers.get(AUTHORIZATION)?.to_str().ok()?.trim_start();
+    let (scheme, token) = value.split_once(char::is_whitespace)?;
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return None;
+    }
+    let token = token.trim();
+    (!token.is_empty()).then(|| token.to_string())
 }
 
 fn token_preview(token: &str) -> String {
@@ -413,30 +799,52 @@ fn token_preview(token: &str) -> String {
 
 async fn assign_request_id(mut req: Request<Body>, next: Next) -> Response {
     let request_id = Uuid::new_v4().to_string();
+    let correlation_id = req
+        .headers()
+        .get(CORRELATION_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| request_id.clone());
     req.extensions_mut().insert(RequestContext {
         request_id: request_id.clone(),
     });
-    let method = req.method().clone();
-    let uri = req.uri().clone();
-    let response = next.run(req).await;
-    let status = response.status();
-    let mut response = response;
-    let header_value = match request_id.parse() {
-        Ok(value) => value,
-        Err(_) => {
-            return response;
+    let span = crate::telemetry::api_request_span(&request_id, &correlation_id);
+    let mut response = next.run(req).instrument(span).await;
+    response.headers_mut().insert(
+        HeaderName::from_static(API_VERSION_HEADER),
+        HeaderValue::from_static("1"),
+    );
+    for (name, value) in [
+        ("x-request-id", &request_id),
+        (CORRELATION_ID_HEADER, &correlation_id),
+    ] {
+        if let Ok(header_value) = value.parse() {
+            response
+                .headers_mut()
+                .insert(HeaderName::from_static(name), header_value);
         }
-    };
+    }
     response
-        .headers_mut()
-        .insert(HeaderName::from_static("x-request-id"), header_value);
-    tracing::debug!(
-        request_id,
-        method = %method,
-        uri = %uri,
-        status = %status,
-        "API request completed"
+}
+
+/// Flag responses from the unversioned aliases as deprecated and point at the
+/// v1 route that replaces them.
+async fn mark_deprecated(req: Request<Body>, next: Next) -> Response {
+    let path = req.uri().path();
+    let successor = format!(
+        "<{API_V1_PREFIX}{}>; rel=\"successor-version\"",
+        path.strip_prefix(LEGACY_PREFIX).unwrap_or(path)
     );
+    let mut response = next.run(req).await;
+    response.headers_mut().insert(
+        HeaderName::from_static("deprecation"),
+        HeaderValue::from_static("true"),
+    );
+    if let Ok(link) = HeaderValue::from_str(&successor) {
+        response.headers_mut().insert(LINK, link);
+    }
     response
 }
 
@@ -450,72 +858,87 @@ async fn rate_limit_requests(
         .get::<RequestContext>()
         .map(|ctx| ctx.request_id.as_str())
         .unwrap_or("unknown");
-    let token_preview = req
-        .extensions()
-        .get::<AuthenticatedContext>()
-        .map(|ctx| ctx.token_preview.as_str())
-        .unwrap_or("unknown");
-    let now = Instant::now();
-    let mut timestamps = limiter.timestamps.lock().await;
-    while let Some(ts) = timestamps.front() {
-        if now.duration_since(*ts) >= limiter.window {
-            timestamps.pop_front();
-        } else {
-            break;
-        }
-    }
-
-    if timestamps.len() as u64 >= limiter.limit {
-        tracing::debug!(request_id, token_preview, "API rate limit exceeded");
-        return (
-            StatusCode::TOO_MANY_REQUESTS,
-            Json(ErrorResponse {
-                error: "rate_limited",
-            }),
-        )
-            .into_response();
+    let Some(context) = req.extensions().get::<AuthenticatedContext>() else {
+        return next.run(req).await;
+    };
+    if !limiter.try_acquire(context.chat_id, Instant::now()).await {
+        tracing::debug!(
+            request_id,
+            chat_id = context.chat_id.0,
+            token_preview = %context.token_preview,
+            "API rate limit exceeded"
+        );
+        return ApiError::new(ErrorCode::RateLimited, request_id).into_response();
     }
-    timestamps.push_back(now);
-    drop(timestamps);
     next.run(req).await
 }
 
-fn unauthorized_response() -> Response {
-    (
-        StatusCode::UNAUTHORIZED,
-        Json(ErrorResponse {
-            error: "unauthorized",
-        }),
-    )
-        .into_response()
-}
-
-fn internal_error_response() -> Response {
-    (
-        StatusCode::INTERNAL_SERVER_ERROR,
-        Json(ErrorResponse {
-            error: "internal_error",
-        }),
-    )
-        .into_response()
+/// Turn away mutations made with a read-only token.
+fn reject_read_only(
+    context: &AuthenticatedContext,
+    request: &RequestContext,
+) -> Result<(), ApiError> {
+    if context.scope.can_write() {
+        return Ok(());
+    }
+    tracing::debug!(
+        request_id = %request.request_id,
+        chat_id = context.chat_id.0,
+        token_preview = %context.token_preview,
+        "Read-only token attempted a mutation"
+    );
+    Err(request.error(ErrorCode::InsufficientScope))
 }
 
-fn bad_request_response() -> Response {
-    (
-        StatusCode::BAD_REQUEST,
-        Json(ErrorResponse {
-            error: "invalid_request",
-        }),
-    )
-        .into_response()
+/// Unwrap a JSON body, answering oversized bodies with the byte limit and
+/// malformed ones with serde's explanation.
+fn json_body<T>(
+    request: &RequestContext,
+    payload: Result<Json<T>, JsonRejection>,
+) -> Result<T, ApiError> {
+    match payload {
+        Ok(Json(payload)) => Ok(payload),
+        Err(rejection) if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE => {
+            tracing::debug!(request_id = %request.request_id, "Request body too large");
+            Err(request
+                .error(ErrorCode::PayloadTooLarge)
+                .with_max(MAX_BODY_BYTES))
+        }
+        Err(rejection) => {
+            tracing::debug!(
+                request_id = %request.request_id,
+                error = %rejection.body_text(),
+                "Invalid request body"
+            );
+            Err(request
+                .error(ErrorCode::InvalidRequest)
+                .with_message(rejection.body_text()))
+        }
+    }
 }
 
-fn not_found_response() -> Response {
-    (
-        StatusCode::NOT_FOUND,
-        Json(ErrorResponse { error: "not_found" }),
-    )
-        .into_response()
+/// Clean item text like a chat message line and enforce the length limit.
+fn item_text(
+    request: &RequestContext,
+    text: &str,
+    limit: ItemTextLimit,
+) -> Result<String, ApiError> {
+    let Some(text) = parse_item_line(text) else {
+        return Err(request
+            .error(ErrorCode::InvalidRequest)
+            .with_message("text must contain an item"));
+    };
+    let length = text.chars().count();
+    if length > limit.0 {
+        tracing::debug!(
+            request_id = %request.request_id,
+            length,
+            max = limit.0,
+            "Item text too long"
+        );
+        return Err(request.error(ErrorCode::TextTooLong).with_max(limit.0));
+    }
+    Ok(text)
 }
 
 impl From<Item> for ApiItem {
@@ -524,6 +947,9 @@ impl From<Item> for ApiItem {
             id: item.id,
             text: item.text,
             done: item.done,
+            quantity: item.quantity,
+            added_by: item.added_by_name,
+            created_at: item.created_at,
         }
     }
 }
@@ -531,6 +957,7 @@ impl From<Item> for ApiItem {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::db::NewToken;
     use crate::tests::util::init_test_db;
     use axum::body::{to_bytes, Body};
     use axum::http::Request;
@@ -539,13 +966,33 @@ mod tests {
     use teloxide::types::ChatId;
     use tower::ServiceExt;
 
+    #[tokio::test]
+    async fn rate_limiter_keeps_a_bucket_per_chat() {
+        let limiter = RateLimiter::new(1, Duration::from_secs(1));
+        let start = Instant::now();
+        assert!(limiter.try_acquire(ChatId(1), start).await);
+        assert!(!limiter.try_acquire(ChatId(1), start).await);
+        assert!(limiter.try_acquire(ChatId(2), start).await);
+        assert_eq!(limiter.bucket_count().await, 2);
+
+        let later = start + Duration::from_secs(2);
+        assert!(limiter.try_acquire(ChatId(1), later).await);
+        assert_eq!(limiter.bucket_count().await, 1);
+    }
+
     #[tokio::test]
     async fn list_requires_auth() {
         let db = init_test_db().await;
         let app = router(
             db,
+            SharedState::new().unwrap(),
             ApiConfig {
                 rate_limit_per_second: None,
+                integration_secret: None,
+                mutation_rate_per_second: None,
+                max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+                cors_origins: None,
+                log_request_bodies: false,
             },
         );
         let response = app
@@ -565,8 +1012,14 @@ mod tests {
         let db = init_test_db().await;
         let app = router(
             db,
+            SharedState::new().unwrap(),
             ApiConfig {
                 rate_limit_per_second: None,
+                integration_secret: None,
+                mutation_rate_per_second: None,
+                max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+                cors_origins: None,
+                log_request_bodies: false,
             },
         );
         let response = app
@@ -587,15 +1040,28 @@ mod tests {
     async fn list_returns_items() {
         let db = init_test_db().await;
         let chat_id = ChatId(10);
-        db.create_token(chat_id, "token-123", None, None, None, 1)
-            .await
-            .unwrap();
+        db.create_token(
+            chat_id,
+            "token-123",
+            &NewToken {
+                issued_at: 1,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
         db.add_item_count(chat_id, "Milk").await.unwrap();
 
         let app = router(
             db.clone(),
+            SharedState::new().unwrap(),
             ApiConfig {
                 rate_limit_per_second: None,
+                integration_secret: None,
+                mutation_rate_per_second: None,
+                max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+                cors_origins: None,
+                log_request_bodies: false,
             },
         );
         let response = app
@@ -622,67 +1088,464 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn add_toggle_delete_flow() {
+    async fn list_since_returns_newer_items() {
         let db = init_test_db().await;
-        let chat_id = ChatId(11);
-        db.create_token(chat_id, "token-add", None, None, None, 1)
+        let chat_id = ChatId(10);
+        db.create_token(
+            chat_id,
+            "token-123",
+            &NewToken {
+                issued_at: 1,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        db.add_items_count(chat_id, &["Milk".into(), "Eggs".into()])
             .await
             .unwrap();
+        sqlx::query("UPDATE items SET created_at = 100 WHERE text = 'Milk'")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        sqlx::query("UPDATE items SET created_at = 200 WHERE text = 'Eggs'")
+            .execute(db.pool())
+            .await
+            .unwrap();
+
         let app = router(
-            db.clone(),
+            db,
+            SharedState::new().unwrap(),
             ApiConfig {
                 rate_limit_per_second: None,
+                integration_secret: None,
+                mutation_rate_per_second: None,
+                max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+                cors_origins: None,
+                log_request_bodies: false,
             },
         );
-
-        let response = app
-            .clone()
-            .oneshot(
+        let fetch = |uri: &'static str| {
+            app.clone().oneshot(
                 Request::builder()
-                    .method("POST")
-                    .uri("/api/add")
-                    .header(AUTHORIZATION, "Bearer token-add")
-                    .header("content-type", "application/json")
-                    .body(Body::from(
-                        serde_json::to_vec(&json!({ "text": "Oats" })).unwrap(),
-                    ))
+                    .uri(uri)
+                    .header(AUTHORIZATION, "Bearer token-123")
+                    .body(Body::empty())
                     .unwrap(),
             )
-            .await
-            .unwrap();
-        assert_eq!(response.status(), StatusCode::CREATED);
-
-        let items = db.list_items(chat_id).await.unwrap();
-        assert_eq!(items.len(), 1);
-        assert!(!items[0].done);
+        };
 
-        let response = app
-            .clone()
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/api/toggle")
-                    .header(AUTHORIZATION, "Bearer token-add")
-                    .header("content-type", "application/json")
-                    .body(Body::from(
-                        serde_json::to_vec(&json!({ "id": items[0].id })).unwrap(),
-                    ))
-                    .unwrap(),
-            )
-            .await
-            .unwrap();
+        let response = fetch("/api/list?since=150").await.unwrap();
         assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let payload: ListResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(payload.items.len(), 1);
+        assert_eq!(payload.items[0].text, "Eggs");
+        assert_eq!(payload.items[0].created_at, 200);
 
-        let items = db.list_items(chat_id).await.unwrap();
-        assert_eq!(items.len(), 1);
-        assert!(items[0].done);
+        let response = fetch("/api/list").await.unwrap();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let payload: ListResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(payload.items.len(), 2);
 
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/api/delete")
-                    .header(AUTHORIZATION, "Bearer token-add")
+        let response = fetch("/api/list?since=yesterday").await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn list_filters_and_sorts_by_query() {
+        let db = init_test_db().await;
+        let chat_id = ChatId(11);
+        db.create_token(
+            chat_id,
+            "token-filter",
+            &NewToken {
+                issued_at: 1,
+                scope: TokenScope::Read,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        db.add_items_count(
+            chat_id,
+            &["Soy milk".into(), "Bread".into(), "Almond milk".into()],
+        )
+        .await
+        .unwrap();
+        let bread = db.list_items(chat_id).await.unwrap()[1].id;
+        db.toggle_item_count(chat_id, bread).await.unwrap();
+
+        let app = router(
+            db,
+            SharedState::new().unwrap(),
+            ApiConfig {
+                rate_limit_per_second: None,
+                integration_secret: None,
+                mutation_rate_per_second: None,
+                max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+                cors_origins: None,
+                log_request_bodies: false,
+            },
+        );
+        let fetch = |uri: &'static str| {
+            app.clone().oneshot(
+                Request::builder()
+                    .uri(uri)
+                    .header(AUTHORIZATION, "Bearer token-filter")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+        let texts = |body: &[u8]| -> Vec<String> {
+            let payload: ListResponse = serde_json::from_slice(body).unwrap();
+            payload.items.into_iter().map(|item| item.text).collect()
+        };
+
+        for (uri, expected) in [
+            ("/api/list?done=true", vec!["Bread"]),
+            ("/api/list?done=false", vec!["Soy milk", "Almond milk"]),
+            ("/api/list?q=MILK", vec!["Soy milk", "Almond milk"]),
+            (
+                "/api/list?sort=alpha",
+                vec!["Almond milk", "Bread", "Soy milk"],
+            ),
+            (
+                "/api/list?sort=created",
+                vec!["Soy milk", "Bread", "Almond milk"],
+            ),
+            (
+                "/api/list?done=false&q=milk&sort=alpha",
+                vec!["Almond milk", "Soy milk"],
+            ),
+        ] {
+            let response = fetch(uri).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{uri}");
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            assert_eq!(texts(&body[..]), expected, "{uri}");
+        }
+
+        for uri in [
+            "/api/list?done=maybe",
+            "/api/list?sort=price",
+            "/api/list?q=%20",
+        ] {
+            let response = fetch(uri).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{uri}");
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(payload["error"], "invalid_request");
+            assert!(payload["message"].as_str().is_some_and(|m| !m.is_empty()));
+        }
+    }
+
+    #[tokio::test]
+    async fn list_query_matches_any_script_and_digits() {
+        let db = init_test_db().await;
+        let chat_id = ChatId(11);
+        db.create_token(
+            chat_id,
+            "token-search",
+            &NewToken {
+                issued_at: 1,
+                scope: TokenScope::Read,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        db.add_items_count(
+            chat_id,
+            &["Молоко".into(), "Éclair".into(), "Cola 500 ml".into()],
+        )
+        .await
+        .unwrap();
+        let app = router(
+            db,
+            SharedState::new().unwrap(),
+            ApiConfig {
+                rate_limit_per_second: None,
+                integration_secret: None,
+                mutation_rate_per_second: None,
+                max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+                cors_origins: None,
+                log_request_bodies: false,
+            },
+        );
+
+        for (uri, expected) in [
+            ("/api/list?q=%D0%BC%D0%BE%D0%BB%D0%BE%D0%BA%D0%BE", "Молоко"),
+            ("/api/list?q=%C3%89clair", "Éclair"),
+            ("/api/list?q=500", "Cola 500 ml"),
+        ] {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .uri(uri)
+                        .header(AUTHORIZATION, "Bearer token-search")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{uri}");
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let payload: ListResponse = serde_json::from_slice(&body).unwrap();
+            let texts: Vec<_> = payload.items.into_iter().map(|item| item.text).collect();
+            assert_eq!(texts, vec![expected], "{uri}");
+        }
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn correlation_id_reaches_database_logs() {
+        use crate::telemetry::capture::CaptureLayer;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let layer = CaptureLayer::default();
+        let subscriber = tracing_subscriber::registry().with(layer.clone());
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let db = init_test_db().await;
+        db.create_token(
+            ChatId(10),
+            "token-123",
+            &NewToken {
+                issued_at: 1,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let app = router(
+            db,
+            SharedState::new().unwrap(),
+            ApiConfig {
+                rate_limit_per_second: None,
+                integration_secret: None,
+                mutation_rate_per_second: None,
+                max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+                cors_origins: None,
+                log_request_bodies: false,
+            },
+        );
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/list")
+                    .header(AUTHORIZATION, "Bearer token-123")
+                    .header(CORRELATION_ID_HEADER, "sync-42")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CORRELATION_ID_HEADER], "sync-42");
+
+        let events = layer.events.lock().unwrap();
+        let listing = events
+            .iter()
+            .find(|event| event.message == "Listing filtered items")
+            .expect("database event captured");
+        assert_eq!(listing.span_fields["correlation_id"], "sync-42");
+        assert!(listing.span_fields.contains_key("request_id"));
+    }
+
+    #[tokio::test]
+    async fn add_records_optional_author() {
+        let db = init_test_db().await;
+        let chat_id = ChatId(14);
+        db.create_token(
+            chat_id,
+            "token-author",
+            &NewToken {
+                issued_at: 1,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let app = router(
+            db.clone(),
+            SharedState::new().unwrap(),
+            ApiConfig {
+                rate_limit_per_second: None,
+                integration_secret: None,
+                mutation_rate_per_second: None,
+                max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+                cors_origins: None,
+                log_request_bodies: false,
+            },
+        );
+
+        for body in [
+            json!({ "text": "Oats", "added_by": "Shortcut" }),
+            json!({ "text": "Tea" }),
+        ] {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri("/api/add")
+                        .header(AUTHORIZATION, "Bearer token-author")
+                        .header("content-type", "application/json")
+                        .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::CREATED);
+        }
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/list")
+                    .header(AUTHORIZATION, "Bearer token-author")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let payload: ListResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(payload.items[0].added_by.as_deref(), Some("Shortcut"));
+        assert_eq!(payload.items[1].added_by, None);
+        assert_eq!(
+            db.list_items(chat_id).await.unwrap()[0].added_by_user_id,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn add_returns_created_item() {
+        let db = init_test_db().await;
+        let chat_id = ChatId(12);
+        db.create_token(
+            chat_id,
+            "token-new",
+            &NewToken {
+                issued_at: 1,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let app = router(
+            db.clone(),
+            SharedState::new().unwrap(),
+            ApiConfig {
+                rate_limit_per_second: None,
+                integration_secret: None,
+                mutation_rate_per_second: None,
+                max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+                cors_origins: None,
+                log_request_bodies: false,
+            },
+        );
+        let add = |text: &str| {
+            Request::builder()
+                .method("POST")
+                .uri("/api/add")
+                .header(AUTHORIZATION, "Bearer token-new")
+                .header("content-type", "application/json")
+                .body(Body::from(json!({ "text": text }).to_string()))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(add("Milk")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let created: ApiItem = serde_json::from_slice(&body).unwrap();
+        let items = db.list_items(chat_id).await.unwrap();
+        assert_eq!(created.id, items[0].id);
+        assert_eq!(created.text, "Milk");
+        assert!(!created.done);
+
+        let response = app.oneshot(add("milk")).await.unwrap();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let merged: ApiItem = serde_json::from_slice(&body).unwrap();
+        assert_eq!(merged.id, created.id);
+        assert_eq!(merged.quantity, 2);
+    }
+
+    #[tokio::test]
+    async fn add_toggle_delete_flow() {
+        let db = init_test_db().await;
+        let chat_id = ChatId(11);
+        db.create_token(
+            chat_id,
+            "token-add",
+            &NewToken {
+                issued_at: 1,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let app = router(
+            db.clone(),
+            SharedState::new().unwrap(),
+            ApiConfig {
+                rate_limit_per_second: None,
+                integration_secret: None,
+                mutation_rate_per_second: None,
+                max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+                cors_origins: None,
+                log_request_bodies: false,
+            },
+        );
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/add")
+                    .header(AUTHORIZATION, "Bearer token-add")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({ "text": "Oats" })).unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let items = db.list_items(chat_id).await.unwrap();
+        assert_eq!(items.len(), 1);
+        assert!(!items[0].done);
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/toggle")
+                    .header(AUTHORIZATION, "Bearer token-add")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({ "id": items[0].id })).unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let items = db.list_items(chat_id).await.unwrap();
+        assert_eq!(items.len(), 1);
+        assert!(items[0].done);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/delete")
+                    .header(AUTHORIZATION, "Bearer token-add")
                     .header("content-type", "application/json")
                     .body(Body::from(
                         serde_json::to_vec(&json!({ "id": items[0].id })).unwrap(),
@@ -701,9 +1564,16 @@ mod tests {
     async fn done_archives_checked_items() {
         let db = init_test_db().await;
         let chat_id = ChatId(12);
-        db.create_token(chat_id, "token-done", None, None, None, 1)
-            .await
-            .unwrap();
+        db.create_token(
+            chat_id,
+            "token-done",
+            &NewToken {
+                issued_at: 1,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
         db.add_item_count(chat_id, "Tea").await.unwrap();
         db.add_item_count(chat_id, "Sugar").await.unwrap();
         let items = db.list_items(chat_id).await.unwrap();
@@ -711,41 +1581,77 @@ mod tests {
 
         let app = router(
             db.clone(),
+            SharedState::new().unwrap(),
             ApiConfig {
                 rate_limit_per_second: None,
+                integration_secret: None,
+                mutation_rate_per_second: None,
+                max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+                cors_origins: None,
+                log_request_bodies: false,
             },
         );
-        let response = app
-            .oneshot(
-                Request::builder()
-                    .method("POST")
-                    .uri("/api/done")
-                    .header(AUTHORIZATION, "Bearer token-done")
-                    .body(Body::empty())
-                    .unwrap(),
-            )
+        let done = || {
+            Request::builder()
+                .method("POST")
+                .uri("/api/done")
+                .header(AUTHORIZATION, "Bearer token-done")
+                .body(Body::empty())
+                .unwrap()
+        };
+        let response = app.clone().oneshot(done()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let payload: DoneResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(payload.archived.len(), 1);
+        assert_eq!(payload.archived[0].text, "Tea");
+        assert_eq!(payload.remaining, 1);
+        let archive = db
+            .get_archive(chat_id, payload.archive_id.unwrap())
             .await
+            .unwrap()
             .unwrap();
-        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(archive.items[0].text, "Tea");
 
         let items = db.list_items(chat_id).await.unwrap();
         assert_eq!(items.len(), 1);
         assert!(!items[0].done);
         assert_eq!(items[0].text, "Sugar");
+
+        let response = app.oneshot(done()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let payload: DoneResponse = serde_json::from_slice(&body).unwrap();
+        assert!(payload.archived.is_empty());
+        assert_eq!(payload.remaining, 1);
+        assert_eq!(payload.archive_id, None);
     }
 
     #[tokio::test]
     async fn archive_and_nuke_clear_items() {
         let db = init_test_db().await;
         let chat_id = ChatId(13);
-        db.create_token(chat_id, "token-archive", None, None, None, 1)
-            .await
-            .unwrap();
+        db.create_token(
+            chat_id,
+            "token-archive",
+            &NewToken {
+                issued_at: 1,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
         db.add_item_count(chat_id, "Bread").await.unwrap();
         let app = router(
             db.clone(),
+            SharedState::new().unwrap(),
             ApiConfig {
                 rate_limit_per_second: None,
+                integration_secret: None,
+                mutation_rate_per_second: None,
+                max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+                cors_origins: None,
+                log_request_bodies: false,
             },
         );
 
@@ -778,27 +1684,89 @@ mod tests {
             .unwrap();
         assert_eq!(response.status(), StatusCode::OK);
         assert!(db.list_items(chat_id).await.unwrap().is_empty());
+
+        let archives = db.recent_archives(chat_id, 5).await.unwrap();
+        assert_eq!(archives.len(), 1);
+        assert_eq!(archives[0].items[0].text, "Bread");
     }
 
     #[tokio::test]
     async fn list_rejects_invalid_token() {
         let db = init_test_db().await;
         let chat_id = ChatId(7);
-        db.create_token(chat_id, "token-abc", None, None, None, 1)
+        db.create_token(
+            chat_id,
+            "token-abc",
+            &NewToken {
+                issued_at: 1,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let app = router(
+            db.clone(),
+            SharedState::new().unwrap(),
+            ApiConfig {
+                rate_limit_per_second: None,
+                integration_secret: None,
+                mutation_rate_per_second: None,
+                max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+                cors_origins: None,
+                log_request_bodies: false,
+            },
+        );
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/list")
+                    .header(AUTHORIZATION, "Bearer wrong-token")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
             .await
             .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let tokens = db.list_tokens(chat_id).await.unwrap();
+        assert!(tokens[0].last_used_at.is_none());
+    }
+
+    #[tokio::test]
+    async fn list_rejects_expired_token() {
+        let db = init_test_db().await;
+        let chat_id = ChatId(7);
+        let expired_at = chrono::Utc::now().timestamp() - 1;
+        db.create_token(
+            chat_id,
+            "token-old",
+            &NewToken {
+                issued_at: 1,
+                expires_at: Some(expired_at),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
 
         let app = router(
             db.clone(),
+            SharedState::new().unwrap(),
             ApiConfig {
                 rate_limit_per_second: None,
+                integration_secret: None,
+                mutation_rate_per_second: None,
+                max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+                cors_origins: None,
+                log_request_bodies: false,
             },
         );
         let response = app
             .oneshot(
                 Request::builder()
                     .uri("/api/list")
-                    .header(AUTHORIZATION, "Bearer wrong-token")
+                    .header(AUTHORIZATION, "Bearer token-old")
                     .body(Body::empty())
                     .unwrap(),
             )
@@ -811,17 +1779,245 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn list_allows_empty_response() {
+    async fn read_only_token_can_list_but_not_mutate() {
         let db = init_test_db().await;
-        let chat_id = ChatId(42);
-        db.create_token(chat_id, "token-empty", None, None, None, 1)
+        let chat_id = ChatId(8);
+        db.create_token(
+            chat_id,
+            "token-ro",
+            &NewToken {
+                issued_at: 1,
+                scope: TokenScope::Read,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        db.add_item_count(chat_id, "Milk").await.unwrap();
+
+        let app = router(
+            db.clone(),
+            SharedState::new().unwrap(),
+            ApiConfig {
+                rate_limit_per_second: None,
+                integration_secret: None,
+                mutation_rate_per_second: None,
+                max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+                cors_origins: None,
+                log_request_bodies: false,
+            },
+        );
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/api/list")
+                    .header(AUTHORIZATION, "Bearer token-ro")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/add")
+                    .header(AUTHORIZATION, "Bearer token-ro")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"text":"Eggs"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(payload["error"], "insufficient_scope");
+
+        let items = db.list_items(chat_id).await.unwrap();
+        assert_eq!(items.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn errors_carry_the_request_id() {
+        let db = init_test_db().await;
+        db.create_token(
+            ChatId(9),
+            "token-err",
+            &NewToken {
+                issued_at: 1,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let app = router(
+            db,
+            SharedState::new().unwrap(),
+            ApiConfig {
+                rate_limit_per_second: None,
+                integration_secret: None,
+                mutation_rate_per_second: None,
+                max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+                cors_origins: None,
+                log_request_bodies: false,
+            },
+        );
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/toggle")
+                    .header(AUTHORIZATION, "Bearer token-err")
+                    .header("content-type", "application/json")
+                    .body(Body::from(json!({ "id": 999 }).to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let request_id = response.headers()["x-request-id"]
+            .to_str()
+            .unwrap()
+            .to_string();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(payload["error"], "not_found");
+        assert_eq!(payload["request_id"], request_id);
+    }
+
+    #[tokio::test]
+    async fn metrics_count_authenticated_adds() {
+        let db = init_test_db().await;
+        db.create_token(
+            ChatId(12),
+            "token-metrics",
+            &NewToken {
+                issued_at: 1,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let app = router(
+            db,
+            SharedState::new().unwrap(),
+            ApiConfig {
+                rate_limit_per_second: None,
+                integration_secret: None,
+                mutation_rate_per_second: None,
+                max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+                cors_origins: None,
+                log_request_bodies: false,
+            },
+        );
+        let scrape = || async {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .uri("/metrics")
+                        .header(AUTHORIZATION, "Bearer token-metrics")
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert!(response.headers()[CONTENT_TYPE]
+                .to_str()
+                .unwrap()
+                .starts_with("text/plain"));
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            String::from_utf8(body.to_vec()).unwrap()
+        };
+        let value = |text: &str, series: &str| -> u64 {
+            text.lines()
+                .find_map(|line| line.strip_prefix(series))
+                .map(|value| value.trim().parse().unwrap())
+                .unwrap_or(0)
+        };
+        let adds = "shopbot_api_requests_total{method=\"POST\",route=\"/api/add\",status=\"201\"} ";
+        let items = "shopbot_items_added_total{source=\"api\"} ";
+
+        let before = scrape().await;
+        assert_eq!(value(&before, items), 0);
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/add")
+                    .header(AUTHORIZATION, "Bearer token-metrics")
+                    .header("content-type", "application/json")
+                    .body(Body::from(json!({ "text": "Flour" }).to_string()))
+                    .unwrap(),
+            )
             .await
             .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let after = scrape().await;
 
+        assert_eq!(value(&after, adds), 1);
+        assert_eq!(value(&after, items), 1);
+        assert!(after.contains("# TYPE shopbot_api_request_duration_seconds histogram"));
+    }
+
+    #[tokio::test]
+    async fn metrics_require_auth() {
+        let db = init_test_db().await;
         let app = router(
             db,
+            SharedState::new().unwrap(),
             ApiConfig {
                 rate_limit_per_second: None,
+                integration_secret: None,
+                mutation_rate_per_second: None,
+                max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+                cors_origins: None,
+                log_request_bodies: false,
+            },
+        );
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/metrics")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn list_allows_empty_response() {
+        let db = init_test_db().await;
+        let chat_id = ChatId(42);
+        db.create_token(
+            chat_id,
+            "token-empty",
+            &NewToken {
+                issued_at: 1,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let app = router(
+            db,
+            SharedState::new().unwrap(),
+            ApiConfig {
+                rate_limit_per_second: None,
+                integration_secret: None,
+                mutation_rate_per_second: None,
+                max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+                cors_origins: None,
+                log_request_bodies: false,
             },
         );
         let response = app
@@ -841,14 +2037,120 @@ mod tests {
         assert!(payload.items.is_empty());
     }
 
+    #[tokio::test]
+    async fn add_enforces_body_and_text_limits() {
+        let db = init_test_db().await;
+        let chat_id = ChatId(21);
+        db.create_token(
+            chat_id,
+            "token-limits",
+            &NewToken {
+                issued_at: 1,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let app = router(
+            db.clone(),
+            SharedState::new().unwrap(),
+            ApiConfig {
+                rate_limit_per_second: None,
+                integration_secret: None,
+                mutation_rate_per_second: None,
+                max_item_length: 10,
+                cors_origins: None,
+                log_request_bodies: false,
+            },
+        );
+        let add = |body: Vec<u8>| {
+            app.clone().oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/add")
+                    .header(AUTHORIZATION, "Bearer token-limits")
+                    .header("content-type", "application/json")
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+        };
+        let error_body = |response: Response| async move {
+            let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        };
+
+        let huge = "a".repeat(MAX_BODY_BYTES + 1);
+        let response = add(serde_json::to_vec(&json!({ "text": huge })).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let body = error_body(response).await;
+        assert_eq!(body["error"], "payload_too_large");
+        assert_eq!(body["max"], MAX_BODY_BYTES);
+
+        let response = add(serde_json::to_vec(&json!({ "text": "a".repeat(11) })).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = error_body(response).await;
+        assert_eq!(body["error"], "text_too_long");
+        assert_eq!(body["max"], 10);
+
+        // Ten characters but twenty bytes: the limit counts characters.
+        let response = add(serde_json::to_vec(&json!({ "text": "é".repeat(10) })).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let response = add(serde_json::to_vec(&json!({ "text": "é".repeat(11) })).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let response = add(serde_json::to_vec(&json!({ "text": "✅ " })).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(error_body(response).await["error"], "invalid_request");
+
+        let items = db.list_items(chat_id).await.unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].text, "é".repeat(10));
+    }
+
+    fn authorization(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn bearer_token_rejects_other_schemes_and_empty_tokens() {
+        for value in [
+            "Basic dXNlcjpwYXNz",
+            "Bearer",
+            "Bearer   ",
+            "Bearertoken",
+            "Token abc",
+        ] {
+            assert_eq!(extract_bearer_token(&authorization(value)), None, "{value}");
+        }
+        assert_eq!(extract_bearer_token(&HeaderMap::new()), None);
+    }
+
     proptest! {
         #[test]
-        fn bearer_token_parses_from_header(token in "[A-Za-z0-9_-]{1,64}") {
-            let mut headers = HeaderMap::new();
-            headers.insert(
-                AUTHORIZATION,
-                format!("Bearer {token}").parse().unwrap(),
-            );
+        fn bearer_token_parses_from_header(
+            token in "[A-Za-z0-9_-]{1,64}",
+            upper in proptest::collection::vec(any::<bool>(), 6),
+            gap in "[ \t]{1,4}",
+            trailing in "[ \t]{0,3}",
+        ) {
+            let scheme: String = "bearer"
+                .chars()
+                .zip(upper)
+                .map(|(c, upper)| if upper { c.to_ascii_uppercase() } else { c })
+                .collect();
+            let headers = authorization(&format!("{scheme}{gap}{token}{trailing}"));
             let parsed = extract_bearer_token(&headers);
             prop_assert_eq!(parsed, Some(token));
         }
@@ -0,0 +1,228 @@
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde::Serialize;
+
+use super::{ApiError, ApiItem, ApiState, AuthenticatedContext, ErrorCode, RequestContext};
+use crate::db::{ArchiveRecord, Database};
+
+/// How many archives `GET /api/v1/archives` lists, newest first.
+const ARCHIVE_LIST_LIMIT: i64 = 50;
+
+/// An archive without its items.
+#[derive(Debug, Serialize)]
+struct ArchiveSummary {
+    id: i64,
+    archived_at: i64,
+    item_count: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct ArchiveList {
+    archives: Vec<ArchiveSummary>,
+}
+
+#[derive(Debug, Serialize)]
+struct ApiArchive {
+    id: i64,
+    archived_at: i64,
+    items: Vec<ApiItem>,
+}
+
+impl From<ArchiveRecord> for ApiArchive {
+    fn from(archive: ArchiveRecord) -> Self {
+        Self {
+            id: archive.id,
+            archived_at: archive.archived_at,
+            items: archive.items.into_iter().map(ApiItem::from).collect(),
+        }
+    }
+}
+
+/// Read-only access to the snapshots stored by `/archive` and `/done`.
+/// Archives of other chats answer 404 exactly like missing ones.
+pub(super) fn routes() -> Router<ApiState> {
+    Router::new()
+        .route("/archives", get(list_archives))
+        .route("/archives/{id}", get(get_archive))
+}
+
+async fn list_archives(
+    State(db): State<Database>,
+    Extension(context): Extension<AuthenticatedContext>,
+    Extension(request): Extension<RequestContext>,
+) -> Result<Response, ApiError> {
+    let chat_id = context.chat_id;
+    let archives = db
+        .recent_archives(chat_id, ARCHIVE_LIST_LIMIT)
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                request_id = %request.request_id,
+                chat_id = chat_id.0,
+                token_preview = %context.token_preview,
+                error = %err,
+                "Failed to list archives"
+            );
+            request.error(ErrorCode::Internal)
+        })?;
+    tracing::debug!(
+        request_id = %request.request_id,
+        chat_id = chat_id.0,
+        token_preview = %context.token_preview,
+        count = archives.len(),
+        "Listed archives via API"
+    );
+    let archives = archives
+        .into_iter()
+        .map(|archive| ArchiveSummary {
+            id: archive.id,
+            archived_at: archive.archived_at,
+            item_count: archive.items.len(),
+        })
+        .collect();
+    Ok((StatusCode::OK, Json(ArchiveList { archives })).into_response())
+}
+
+async fn get_archive(
+    State(db): State<Database>,
+    Extension(context): Extension<AuthenticatedContext>,
+    Extension(request): Extension<RequestContext>,
+    Path(id): Path<i64>,
+) -> Result<Response, ApiError> {
+    let chat_id = context.chat_id;
+    match db.get_archive(chat_id, id).await {
+        Ok(Some(archive)) => {
+            tracing::debug!(
+                request_id = %request.request_id,
+                chat_id = chat_id.0,
+                token_preview = %context.token_preview,
+                archive_id = id,
+                "Read archive via API"
+            );
+            Ok((StatusCode::OK, Json(ApiArchive::from(archive))).into_response())
+        }
+        Ok(None) => Err(request.error(ErrorCode::NotFound)),
+        Err(err) => {
+            tracing::error!(
+                request_id = %request.request_id,
+                chat_id = chat_id.0,
+                token_preview = %context.token_preview,
+                error = %err,
+                "Failed to load archive"
+            );
+            Err(request.error(ErrorCode::Internal))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{router, ApiConfig, DEFAULT_MAX_ITEM_LENGTH};
+    use super::*;
+    use crate::db::NewToken;
+    use crate::state::SharedState;
+    use crate::tests::util::init_test_db;
+    use axum::body::{to_bytes, Body};
+    use axum::http::{header::AUTHORIZATION, Request};
+    use teloxide::types::ChatId;
+    use tower::ServiceExt;
+
+    async fn setup() -> (Database, Router) {
+        let db = init_test_db().await;
+        db.create_token(
+            ChatId(1),
+            "token-archives",
+            &NewToken {
+                issued_at: 1,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let app = router(
+            db.clone(),
+            SharedState::new().unwrap(),
+            ApiConfig {
+                rate_limit_per_second: None,
+                integration_secret: None,
+                mutation_rate_per_second: None,
+                max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+                cors_origins: None,
+                log_request_bodies: false,
+            },
+        );
+        (db, app)
+    }
+
+    async fn call(app: &Router, method: &str, uri: &str) -> (StatusCode, serde_json::Value) {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method(method)
+                    .uri(uri)
+                    .header(AUTHORIZATION, "Bearer token-archives")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn archive_keeps_a_snapshot_and_nuke_does_not() {
+        let (db, app) = setup().await;
+        db.add_item_count(ChatId(1), "Milk").await.unwrap();
+        db.add_item_count(ChatId(1), "Eggs").await.unwrap();
+
+        let (status, archived) = call(&app, "POST", "/api/v1/archive").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(archived["affected"], 2);
+        assert_eq!(archived["items"][0]["text"], "Milk");
+        let archive_id = archived["archive_id"].as_i64().unwrap();
+
+        db.add_item_count(ChatId(1), "Bread").await.unwrap();
+        let (status, nuked) = call(&app, "POST", "/api/v1/nuke").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(nuked["affected"], 1);
+
+        let (status, list) = call(&app, "GET", "/api/v1/archives").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(list["archives"].as_array().unwrap().len(), 1);
+        assert_eq!(list["archives"][0]["id"], archive_id);
+        assert_eq!(list["archives"][0]["item_count"], 2);
+
+        let (status, archive) = call(&app, "GET", &format!("/api/v1/archives/{archive_id}")).await;
+        assert_eq!(status, StatusCode::OK);
+        let texts: Vec<&str> = archive["items"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["text"].as_str().unwrap())
+            .collect();
+        assert_eq!(texts, ["Milk", "Eggs"]);
+    }
+
+    #[tokio::test]
+    async fn empty_archive_and_foreign_archives() {
+        let (db, app) = setup().await;
+        let (status, archived) = call(&app, "POST", "/api/v1/archive").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(archived["affected"], 0);
+        assert!(archived["archive_id"].is_null());
+
+        db.add_item_count(ChatId(2), "Milk").await.unwrap();
+        let foreign = db.create_archive(ChatId(2), 100).await.unwrap().unwrap();
+        let (status, body) = call(&app, "GET", &format!("/api/v1/archives/{}", foreign.id)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"], "not_found");
+    }
+}
@@ -0,0 +1,241 @@
+use axum::{
+    extract::{rejection::QueryRejection, Extension, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::time::Instant;
+
+use super::{ApiError, ApiState, AuthenticatedContext, ErrorCode, RequestContext};
+use crate::db::Database;
+use crate::state::SharedState;
+
+const DEFAULT_TIMEOUT_SECS: u64 = 25;
+/// Longest a single poll may block, whatever the client asks for.
+const MAX_TIMEOUT_SECS: u64 = 60;
+
+#[derive(Debug, Deserialize)]
+struct ChangesQuery {
+    /// The last revision the client has seen.
+    #[serde(default)]
+    since: i64,
+    /// Seconds to wait for a change before answering `changed: false`.
+    #[serde(default)]
+    timeout: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+struct ChangesResponse {
+    revision: i64,
+    changed: bool,
+}
+
+pub(super) fn routes() -> Router<ApiState> {
+    Router::new().route("/changes", get(wait_for_changes))
+}
+
+/// Long-poll until the chat's list revision passes `since` or the timeout
+/// expires. Only the revision reads touch the pool; waiting holds no
+/// connection.
+async fn wait_for_changes(
+    State(db): State<Database>,
+    State(shared): State<SharedState>,
+    Extension(context): Extension<AuthenticatedContext>,
+    Extension(request): Extension<RequestContext>,
+    query: Result<Query<ChangesQuery>, QueryRejection>,
+) -> Result<Response, ApiError> {
+    let chat_id = context.chat_id;
+    let Query(query) = query.map_err(|rejection| {
+        tracing::debug!(
+            request_id = %request.request_id,
+            chat_id = chat_id.0,
+            error = %rejection.body_text(),
+            "Invalid changes query"
+        );
+        request
+            .error(ErrorCode::InvalidRequest)
+            .with_message(rejection.body_text())
+    })?;
+
+    let timeout = query
+        .timeout
+        .unwrap_or(DEFAULT_TIMEOUT_SECS)
+        .min(MAX_TIMEOUT_SECS);
+    let deadline = Instant::now() + Duration::from_secs(timeout);
+    tracing::debug!(
+        request_id = %request.request_id,
+        chat_id = chat_id.0,
+        since = query.since,
+        timeout,
+        "Waiting for list changes"
+    );
+
+    let waiters = shared.events.waiters().subscribe(chat_id);
+    loop {
+        // Register for the wake-up before reading so a bump in between is not lost.
+        let notified = waiters.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+
+        let revision = db.list_revision(chat_id).await.map_err(|err| {
+            tracing::error!(
+                request_id = %request.request_id,
+                chat_id = chat_id.0,
+                token_preview = %context.token_preview,
+                error = %err,
+                "Failed to load list revision"
+            );
+            request.error(ErrorCode::Internal)
+        })?;
+        if revision > query.since {
+            tracing::debug!(
+                request_id = %request.request_id,
+                chat_id = chat_id.0,
+                revision,
+                "List changed"
+            );
+            return Ok(changes(revision, true));
+        }
+        if tokio::time::timeout_at(deadline, notified).await.is_err() {
+            tracing::debug!(
+                request_id = %request.request_id,
+                chat_id = chat_id.0,
+                revision,
+                "No list changes before timeout"
+            );
+            return Ok(changes(revision, false));
+        }
+    }
+}
+
+fn changes(revision: i64, changed: bool) -> Response {
+    (StatusCode::OK, Json(ChangesResponse { revision, changed })).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{router, ApiConfig, DEFAULT_MAX_ITEM_LENGTH};
+    use super::*;
+    use crate::db::NewToken;
+    use crate::tests::util::init_test_db;
+    use axum::body::{to_bytes, Body};
+    use axum::http::{
+        header::{AUTHORIZATION, CONTENT_TYPE},
+        Request,
+    };
+    use teloxide::types::ChatId;
+    use tower::ServiceExt;
+
+    async fn setup() -> Router {
+        let db = init_test_db().await;
+        db.create_token(
+            ChatId(1),
+            "token-changes",
+            &NewToken {
+                issued_at: 1,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        router(
+            db,
+            SharedState::new().unwrap(),
+            ApiConfig {
+                rate_limit_per_second: None,
+                integration_secret: None,
+                mutation_rate_per_second: None,
+                max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+                cors_origins: None,
+                log_request_bodies: false,
+            },
+        )
+    }
+
+    async fn poll(app: &Router, query: &str) -> serde_json::Value {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/api/v1/changes{query}"))
+                    .header(AUTHORIZATION, "Bearer token-changes")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn concurrent_add_wakes_the_poll() {
+        let app = setup().await;
+        let current = poll(&app, "?timeout=0").await;
+        assert_eq!(current["changed"], false);
+        let since = current["revision"].as_i64().unwrap();
+
+        let started = std::time::Instant::now();
+        let waiting = tokio::spawn({
+            let app = app.clone();
+            async move { poll(&app, &format!("?since={since}&timeout=20")).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/add")
+                    .header(AUTHORIZATION, "Bearer token-changes")
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(Body::from(r#"{"text":"Milk"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let body = waiting.await.unwrap();
+        assert_eq!(body["changed"], true);
+        assert!(body["revision"].as_i64().unwrap() > since);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn own_write_is_visible_right_away() {
+        let app = setup().await;
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/add")
+                    .header(AUTHORIZATION, "Bearer token-changes")
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(Body::from(r#"{"text":"Milk"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let body = poll(&app, "?since=0&timeout=0").await;
+        assert_eq!(body["changed"], true);
+        assert!(body["revision"].as_i64().unwrap() > 0);
+    }
+
+    #[tokio::test]
+    async fn quiet_list_times_out_unchanged() {
+        let app = setup().await;
+        let started = std::time::Instant::now();
+        let body = poll(&app, "?since=0&timeout=1").await;
+        assert_eq!(body["changed"], false);
+        assert_eq!(body["revision"], 0);
+        assert!(started.elapsed() >= Duration::from_secs(1));
+    }
+}
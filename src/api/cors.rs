@@ -0,0 +1,274 @@
+use axum::{
+    body::Body,
+    extract::State,
+    http::{
+        header::{
+            ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+            ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS, ACCESS_CONTROL_MAX_AGE,
+            ACCESS_CONTROL_REQUEST_METHOD, ORIGIN, VARY,
+        },
+        HeaderMap, HeaderValue, Method, Request, StatusCode,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+
+const ALLOW_METHODS: &str = "GET, POST, PUT, DELETE, OPTIONS";
+const ALLOW_HEADERS: &str = "authorization, content-type";
+const EXPOSE_HEADERS: &str = "x-request-id, x-api-version, deprecation, link";
+/// How long browsers may cache a preflight answer, in seconds.
+const MAX_AGE_SECS: &str = "600";
+
+/// Browser origins allowed to call the API, from `API_CORS_ORIGINS`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CorsOrigins {
+    Any,
+    List(Vec<String>),
+}
+
+impl CorsOrigins {
+    /// Parse `*` or a comma-separated list of origins; blank values disable CORS.
+    pub fn parse(value: &str) -> Option<Self> {
+        if value.trim() == "*" {
+            return Some(Self::Any);
+        }
+        let origins: Vec<String> = value
+            .split(',')
+            .map(|origin| origin.trim().trim_end_matches('/').to_string())
+            .filter(|origin| !origin.is_empty())
+            .collect();
+        (!origins.is_empty()).then_some(Self::List(origins))
+    }
+
+    /// The `Access-Control-Allow-Origin` value for `origin`, if it is allowed.
+    fn allow_origin(&self, origin: &str) -> Option<HeaderValue> {
+        match self {
+            Self::Any => Some(HeaderValue::from_static("*")),
+            Self::List(origins) => origins
+                .iter()
+                .any(|allowed| allowed == origin)
+                .then(|| HeaderValue::from_str(origin).ok())
+                .flatten(),
+        }
+    }
+}
+
+fn is_preflight(req: &Request<Body>) -> bool {
+    req.method() == Method::OPTIONS && req.headers().contains_key(ACCESS_CONTROL_REQUEST_METHOD)
+}
+
+/// Answer preflights for allowed origins here, before auth, and add the
+/// allow-origin header to their other responses. Disallowed origins get no
+/// CORS headers at all, so the browser blocks them.
+pub(super) async fn handle_cors(
+    State(origins): State<Arc<CorsOrigins>>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    let Some(origin) = req
+        .headers()
+        .get(ORIGIN)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+    else {
+        return next.run(req).await;
+    };
+    let allowed = origins.allow_origin(&origin);
+
+    if is_preflight(&req) {
+        tracing::debug!(origin = %origin, allowed = allowed.is_some(), "CORS preflight");
+        let mut response = StatusCode::NO_CONTENT.into_response();
+        let headers = response.headers_mut();
+        headers.insert(VARY, HeaderValue::from_static("origin"));
+        if let Some(allowed) = allowed {
+            headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, allowed);
+            headers.insert(
+                ACCESS_CONTROL_ALLOW_METHODS,
+                HeaderValue::from_static(ALLOW_METHODS),
+            );
+            headers.insert(
+                ACCESS_CONTROL_ALLOW_HEADERS,
+                HeaderValue::from_static(ALLOW_HEADERS),
+            );
+            headers.insert(
+                ACCESS_CONTROL_MAX_AGE,
+                HeaderValue::from_static(MAX_AGE_SECS),
+            );
+        }
+        return response;
+    }
+
+    let mut response = next.run(req).await;
+    match allowed {
+        Some(allowed) => add_cors_headers(response.headers_mut(), allowed),
+        None => {
+            tracing::debug!(origin = %origin, "Origin not allowed by CORS");
+            response
+                .headers_mut()
+                .insert(VARY, HeaderValue::from_static("origin"));
+        }
+    }
+    response
+}
+
+fn add_cors_headers(headers: &mut HeaderMap, allowed: HeaderValue) {
+    headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, allowed);
+    headers.insert(
+        ACCESS_CONTROL_EXPOSE_HEADERS,
+        HeaderValue::from_static(EXPOSE_HEADERS),
+    );
+    headers.insert(VARY, HeaderValue::from_static("origin"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{router, ApiConfig, DEFAULT_MAX_ITEM_LENGTH};
+    use super::*;
+    use crate::db::NewToken;
+    use crate::state::SharedState;
+    use crate::tests::util::init_test_db;
+    use axum::http::header::{ACCESS_CONTROL_REQUEST_HEADERS, AUTHORIZATION};
+    use axum::Router;
+    use teloxide::types::ChatId;
+    use tower::ServiceExt;
+
+    async fn setup(cors_origins: Option<CorsOrigins>) -> Router {
+        let db = init_test_db().await;
+        db.create_token(
+            ChatId(1),
+            "token-cors",
+            &NewToken {
+                issued_at: 1,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        router(
+            db,
+            SharedState::new().unwrap(),
+            ApiConfig {
+                rate_limit_per_second: None,
+                integration_secret: None,
+                mutation_rate_per_second: None,
+                max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+                cors_origins,
+                log_request_bodies: false,
+            },
+        )
+    }
+
+    fn preflight(origin: &str) -> Request<Body> {
+        Request::builder()
+            .method(Method::OPTIONS)
+            .uri("/api/v1/add")
+            .header(ORIGIN, origin)
+            .header(ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .header(
+                ACCESS_CONTROL_REQUEST_HEADERS,
+                "authorization, content-type",
+            )
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[test]
+    fn parses_origin_lists() {
+        assert_eq!(CorsOrigins::parse(" * "), Some(CorsOrigins::Any));
+        assert_eq!(
+            CorsOrigins::parse("https://a.example, https://b.example/"),
+            Some(CorsOrigins::List(vec![
+                "https://a.example".into(),
+                "https://b.example".into()
+            ]))
+        );
+        assert_eq!(CorsOrigins::parse(" , "), None);
+    }
+
+    #[tokio::test]
+    async fn preflight_allows_configured_origin() {
+        let origins = CorsOrigins::parse("https://app.example");
+        let app = setup(origins).await;
+
+        let response = app
+            .clone()
+            .oneshot(preflight("https://app.example"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let headers = response.headers();
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_ORIGIN], "https://app.example");
+        assert!(headers[ACCESS_CONTROL_ALLOW_METHODS]
+            .to_str()
+            .unwrap()
+            .contains("POST"));
+        assert!(headers[ACCESS_CONTROL_ALLOW_HEADERS]
+            .to_str()
+            .unwrap()
+            .contains("authorization"));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/v1/list")
+                    .header(ORIGIN, "https://app.example")
+                    .header(AUTHORIZATION, "Bearer token-cors")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://app.example"
+        );
+    }
+
+    #[tokio::test]
+    async fn disallowed_origin_gets_no_cors_headers() {
+        let app = setup(CorsOrigins::parse("https://app.example")).await;
+
+        let response = app
+            .clone()
+            .oneshot(preflight("https://evil.example"))
+            .await
+            .unwrap();
+        assert!(response
+            .headers()
+            .get(ACCESS_CONTROL_ALLOW_ORIGIN)
+            .is_none());
+        assert!(response
+            .headers()
+            .get(ACCESS_CONTROL_ALLOW_METHODS)
+            .is_none());
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/v1/list")
+                    .header(ORIGIN, "https://evil.example")
+                    .header(AUTHORIZATION, "Bearer token-cors")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert!(response
+            .headers()
+            .get(ACCESS_CONTROL_ALLOW_ORIGIN)
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn unset_origins_leave_preflight_to_the_router() {
+        let app = setup(None).await;
+        let response = app.oneshot(preflight("https://app.example")).await.unwrap();
+        assert_ne!(response.status(), StatusCode::NO_CONTENT);
+        assert!(response
+            .headers()
+            .get(ACCESS_CONTROL_ALLOW_ORIGIN)
+            .is_none());
+    }
+}
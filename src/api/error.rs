@@ -0,0 +1,147 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+
+use super::RequestContext;
+
+/// Machine-readable reason sent as the `error` field of every failed request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum ErrorCode {
+    Unauthorized,
+    InsufficientScope,
+    InvalidRequest,
+    NotFound,
+    PayloadTooLarge,
+    TextTooLong,
+    OrderMismatch,
+    RateLimited,
+    Internal,
+    Unavailable,
+}
+
+impl ErrorCode {
+    pub(super) const ALL: [ErrorCode; 10] = [
+        ErrorCode::Unauthorized,
+        ErrorCode::InsufficientScope,
+        ErrorCode::InvalidRequest,
+        ErrorCode::NotFound,
+        ErrorCode::PayloadTooLarge,
+        ErrorCode::TextTooLong,
+        ErrorCode::OrderMismatch,
+        ErrorCode::RateLimited,
+        ErrorCode::Internal,
+        ErrorCode::Unavailable,
+    ];
+
+    pub(super) fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::Unauthorized => "unauthorized",
+            ErrorCode::InsufficientScope => "insufficient_scope",
+            ErrorCode::InvalidRequest => "invalid_request",
+            ErrorCode::NotFound => "not_found",
+            ErrorCode::PayloadTooLarge => "payload_too_large",
+            ErrorCode::TextTooLong => "text_too_long",
+            ErrorCode::OrderMismatch => "order_mismatch",
+            ErrorCode::RateLimited => "rate_limited",
+            ErrorCode::Internal => "internal_error",
+            ErrorCode::Unavailable => "unavailable",
+        }
+    }
+
+    pub(super) fn status(self) -> StatusCode {
+        match self {
+            ErrorCode::Unauthorized => StatusCode::UNAUTHORIZED,
+            ErrorCode::InsufficientScope => StatusCode::FORBIDDEN,
+            ErrorCode::InvalidRequest => StatusCode::BAD_REQUEST,
+            ErrorCode::NotFound => StatusCode::NOT_FOUND,
+            ErrorCode::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            ErrorCode::TextTooLong | ErrorCode::OrderMismatch => StatusCode::UNPROCESSABLE_ENTITY,
+            ErrorCode::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            ErrorCode::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+            ErrorCode::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+}
+
+/// Error returned by API handlers and middleware. The body carries the
+/// request id so users can quote it in bug reports.
+#[derive(Debug)]
+pub(super) struct ApiError {
+    code: ErrorCode,
+    request_id: String,
+    message: Option<String>,
+    max: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorBody<'a> {
+    error: &'static str,
+    request_id: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<&'a str>,
+    /// The limit that was exceeded, for size errors.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max: Option<usize>,
+}
+
+impl ApiError {
+    pub(super) fn new(code: ErrorCode, request_id: impl Into<String>) -> Self {
+        Self {
+            code,
+            request_id: request_id.into(),
+            message: None,
+            max: None,
+        }
+    }
+
+    /// Explain to the client what was wrong with the request.
+    pub(super) fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    /// Report the limit the request went over.
+    pub(super) fn with_max(mut self, max: usize) -> Self {
+        self.max = Some(max);
+        self
+    }
+}
+
+impl RequestContext {
+    /// Build an [`ApiError`] tagged with this request's id.
+    pub(super) fn error(&self, code: ErrorCode) -> ApiError {
+        ApiError::new(code, self.request_id.as_str())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = ErrorBody {
+            error: self.code.as_str(),
+            request_id: &self.request_id,
+            message: self.message.as_deref(),
+            max: self.max,
+        };
+        (self.code.status(), Json(body)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::to_bytes;
+    use serde_json::Value;
+
+    #[tokio::test]
+    async fn error_body_includes_code_and_request_id() {
+        let response = ApiError::new(ErrorCode::NotFound, "req-1").into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["error"], "not_found");
+        assert_eq!(body["request_id"], "req-1");
+    }
+}
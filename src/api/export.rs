@@ -0,0 +1,199 @@
+use axum::{
+    extract::{rejection::QueryRejection, Extension, Query, State},
+    http::{
+        header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+        StatusCode,
+    },
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use serde::Deserialize;
+
+use super::{ApiError, ApiState, AuthenticatedContext, ErrorCode, RequestContext};
+use crate::db::Database;
+use crate::export::ExportFormat;
+
+#[derive(Debug, Deserialize)]
+struct ExportQuery {
+    #[serde(default)]
+    format: ExportFormat,
+}
+
+pub(super) fn routes() -> Router<ApiState> {
+    Router::new().route("/export", get(export_list))
+}
+
+async fn export_list(
+    State(db): State<Database>,
+    Extension(context): Extension<AuthenticatedContext>,
+    Extension(request): Extension<RequestContext>,
+    query: Result<Query<ExportQuery>, QueryRejection>,
+) -> Result<Response, ApiError> {
+    let chat_id = context.chat_id;
+    let Query(query) = query.map_err(|rejection| {
+        tracing::debug!(
+            request_id = %request.request_id,
+            chat_id = chat_id.0,
+            error = %rejection.body_text(),
+            "Invalid export query"
+        );
+        request
+            .error(ErrorCode::InvalidRequest)
+            .with_message(rejection.body_text())
+    })?;
+
+    let items = db.list_items(chat_id).await.map_err(|err| {
+        tracing::error!(
+            request_id = %request.request_id,
+            chat_id = chat_id.0,
+            token_preview = %context.token_preview,
+            error = %err,
+            "Failed to load items for export"
+        );
+        request.error(ErrorCode::Internal)
+    })?;
+
+    let format = query.format;
+    tracing::debug!(
+        request_id = %request.request_id,
+        chat_id = chat_id.0,
+        token_preview = %context.token_preview,
+        ?format,
+        item_count = items.len(),
+        "Exported list via API"
+    );
+    let disposition = format!("attachment; filename=\"list.{}\"", format.extension());
+    Ok((
+        StatusCode::OK,
+        [
+            (CONTENT_TYPE, format.content_type().to_string()),
+            (CONTENT_DISPOSITION, disposition),
+        ],
+        format.render(&items),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{router, ApiConfig, DEFAULT_MAX_ITEM_LENGTH};
+    use super::*;
+    use crate::db::{NewToken, TokenScope};
+    use crate::state::SharedState;
+    use crate::tests::util::init_test_db;
+    use axum::body::{to_bytes, Body};
+    use axum::http::{header::AUTHORIZATION, Request};
+    use teloxide::types::ChatId;
+    use tower::ServiceExt;
+
+    async fn setup(items: &[&str]) -> (Database, Router) {
+        let db = init_test_db().await;
+        db.create_token(
+            ChatId(1),
+            "token-export",
+            &NewToken {
+                issued_at: 1,
+                scope: TokenScope::Read,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        for item in items {
+            db.add_item_count(ChatId(1), item).await.unwrap();
+        }
+        let app = router(
+            db.clone(),
+            SharedState::new().unwrap(),
+            ApiConfig {
+                rate_limit_per_second: None,
+                integration_secret: None,
+                mutation_rate_per_second: None,
+                max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+                cors_origins: None,
+                log_request_bodies: false,
+            },
+        );
+        (db, app)
+    }
+
+    async fn export(app: &Router, query: &str) -> Response {
+        app.clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/api/v1/export{query}"))
+                    .header(AUTHORIZATION, "Bearer token-export")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+    }
+
+    async fn text_body(response: Response) -> String {
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn csv_export_escapes_commas_and_quotes() {
+        let (db, app) = setup(&["Eggs, large", "12\" pizza", "Milk"]).await;
+        let ids: Vec<i64> = db
+            .list_items(ChatId(1))
+            .await
+            .unwrap()
+            .iter()
+            .map(|item| item.id)
+            .collect();
+        db.toggle_item_count(ChatId(1), ids[2]).await.unwrap();
+
+        let response = export(&app, "?format=csv").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_TYPE], "text/csv; charset=utf-8");
+        assert_eq!(
+            response.headers()[CONTENT_DISPOSITION],
+            "attachment; filename=\"list.csv\""
+        );
+        assert_eq!(
+            text_body(response).await,
+            format!(
+                "id,text,done,quantity\r\n\
+                 {},\"Eggs, large\",false,1\r\n\
+                 {},\"12\"\" pizza\",false,1\r\n\
+                 {},Milk,true,1\r\n",
+                ids[0], ids[1], ids[2]
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn markdown_and_text_exports() {
+        let (db, app) = setup(&["Milk", "Bread"]).await;
+        let bread = db.list_items(ChatId(1)).await.unwrap()[1].id;
+        db.toggle_item_count(ChatId(1), bread).await.unwrap();
+
+        let response = export(&app, "?format=markdown").await;
+        assert_eq!(
+            response.headers()[CONTENT_TYPE],
+            "text/markdown; charset=utf-8"
+        );
+        assert_eq!(text_body(response).await, "- [ ] Milk\n- [x] Bread\n");
+
+        let response = export(&app, "").await;
+        assert_eq!(
+            response.headers()[CONTENT_TYPE],
+            "text/plain; charset=utf-8"
+        );
+        assert_eq!(text_body(response).await, "• Milk\n• Bread\n");
+    }
+
+    #[tokio::test]
+    async fn unknown_format_is_rejected() {
+        let (_db, app) = setup(&["Milk"]).await;
+        let response = export(&app, "?format=pdf").await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = serde_json::from_str(&text_body(response).await).unwrap();
+        assert_eq!(body["error"], "invalid_request");
+    }
+}
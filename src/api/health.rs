@@ -0,0 +1,114 @@
+use axum::{
+    extract::{Extension, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde::Serialize;
+
+use super::{ApiError, ErrorCode, RequestContext};
+use crate::db::Database;
+
+pub(super) const HEALTH_PATH: &str = "/healthz";
+pub(super) const READY_PATH: &str = "/readyz";
+
+#[derive(Debug, Serialize)]
+struct StatusResponse {
+    status: &'static str,
+}
+
+/// Probes for load balancers and orchestrators. They take no token and are
+/// merged outside the auth and rate limit layers.
+pub(super) fn router(db: Database) -> Router {
+    Router::new()
+        .route(HEALTH_PATH, get(health))
+        .route(READY_PATH, get(ready))
+        .with_state(db)
+}
+
+/// The process is up and serving requests.
+async fn health() -> Response {
+    (StatusCode::OK, Json(StatusResponse { status: "ok" })).into_response()
+}
+
+/// The database answers queries.
+async fn ready(
+    State(db): State<Database>,
+    Extension(request): Extension<RequestContext>,
+) -> Result<Response, ApiError> {
+    if let Err(err) = db.ping().await {
+        tracing::warn!(
+            request_id = %request.request_id,
+            error = %err,
+            "Readiness check failed"
+        );
+        return Err(request.error(ErrorCode::Unavailable));
+    }
+    Ok((StatusCode::OK, Json(StatusResponse { status: "ready" })).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{router as api_router, ApiConfig, DEFAULT_MAX_ITEM_LENGTH};
+    use crate::state::SharedState;
+    use crate::tests::util::init_test_db;
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    fn app(db: crate::db::Database) -> axum::Router {
+        api_router(
+            db,
+            SharedState::new().unwrap(),
+            ApiConfig {
+                rate_limit_per_second: Some(1),
+                integration_secret: None,
+                mutation_rate_per_second: None,
+                max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+                cors_origins: None,
+                log_request_bodies: false,
+            },
+        )
+    }
+
+    async fn get(app: &axum::Router, uri: &str) -> (StatusCode, Value) {
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn probes_need_no_token_and_skip_rate_limit() {
+        let app = app(init_test_db().await);
+        for _ in 0..3 {
+            let (status, body) = get(&app, "/healthz").await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(body["status"], "ok");
+
+            let (status, body) = get(&app, "/readyz").await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(body["status"], "ready");
+        }
+    }
+
+    #[tokio::test]
+    async fn readyz_fails_when_pool_is_closed() {
+        let db = init_test_db().await;
+        let app = app(db.clone());
+        db.pool().close().await;
+
+        let (status, body) = get(&app, "/readyz").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["error"], "unavailable");
+
+        let (status, _) = get(&app, "/healthz").await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}
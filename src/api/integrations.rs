@@ -0,0 +1,615 @@
+use axum::{
+    body::Bytes,
+    extract::{Extension, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use hmac::{Hmac, Mac};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use teloxide::types::ChatId;
+
+use super::scheduler::WriteScheduler;
+use super::{token_preview, ApiError, ErrorCode, RequestContext};
+use crate::ai::stt::parse_items;
+use crate::db::Database;
+use crate::messages::{integration_added_text, INTEGRATION_NO_ITEMS};
+use crate::notify::{ListEvent, ListEventKind};
+use crate::state::SharedState;
+use crate::text_utils::capitalize_first;
+
+/// Header carrying `sha256=<hex>` HMAC of the raw request body.
+pub const SIGNATURE_HEADER: &str = "x-shopbot-signature";
+
+#[derive(Clone)]
+struct IntegrationState {
+    db: Database,
+    shared: SharedState,
+    secret: Arc<str>,
+    /// Queues each batch by its item count when API writes are throttled.
+    scheduler: Option<Arc<WriteScheduler>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenQuery {
+    token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AlexaRequest {
+    request: AlexaRequestBody,
+}
+
+#[derive(Debug, Deserialize)]
+struct AlexaRequestBody {
+    intent: Option<AlexaIntent>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AlexaIntent {
+    #[serde(default)]
+    slots: BTreeMap<String, AlexaSlot>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AlexaSlot {
+    value: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct AlexaResponse {
+    version: &'static str,
+    response: AlexaResponseBody,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AlexaResponseBody {
+    output_speech: AlexaSpeech,
+    should_end_session: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct AlexaSpeech {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    text: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GoogleRequest {
+    query_result: GoogleQueryResult,
+}
+
+#[derive(Debug, Deserialize)]
+struct GoogleQueryResult {
+    #[serde(default)]
+    parameters: BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GoogleResponse {
+    fulfillment_text: String,
+}
+
+pub(super) fn router(
+    db: Database,
+    shared: SharedState,
+    secret: String,
+    scheduler: Option<Arc<WriteScheduler>>,
+) -> Router {
+    Router::new()
+        .route("/integrations/alexa", post(alexa_webhook))
+        .route("/integrations/google", post(google_webhook))
+        .with_state(IntegrationState {
+            db,
+            shared,
+            secret: secret.into(),
+            scheduler,
+        })
+}
+
+async fn alexa_webhook(
+    State(state): State<IntegrationState>,
+    Extension(request): Extension<RequestContext>,
+    Query(query): Query<TokenQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, ApiError> {
+    let Authorized { chat_id, payload } =
+        authorize::<AlexaRequest>(&state, &request, query, &headers, &body, "alexa").await?;
+    let phrases = payload
+        .request
+        .intent
+        .map(|intent| {
+            intent
+                .slots
+                .into_values()
+                .filter_map(|slot| slot.value)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let text = add_phrases(&state, &request, chat_id, phrases, "alexa").await?;
+    let response = AlexaResponse {
+        version: "1.0",
+        response: AlexaResponseBody {
+            output_speech: AlexaSpeech {
+                kind: "PlainText",
+                text,
+            },
+            should_end_session: true,
+        },
+    };
+    Ok((StatusCode::OK, Json(response)).into_response())
+}
+
+async fn google_webhook(
+    State(state): State<IntegrationState>,
+    Extension(request): Extension<RequestContext>,
+    Query(query): Query<TokenQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, ApiError> {
+    let Authorized { chat_id, payload } =
+        authorize::<GoogleRequest>(&state, &request, query, &headers, &body, "google").await?;
+    let phrases = payload
+        .query_result
+        .parameters
+        .into_values()
+        .flat_map(|value| match value {
+            serde_json::Value::String(text) => vec![text],
+            serde_json::Value::Array(values) => values
+                .into_iter()
+                .filter_map(|value| value.as_str().map(str::to_string))
+                .collect(),
+            _ => Vec::new(),
+        })
+        .collect();
+
+    let text = add_phrases(&state, &request, chat_id, phrases, "google").await?;
+    let response = GoogleResponse {
+        fulfillment_text: text,
+    };
+    Ok((StatusCode::OK, Json(response)).into_response())
+}
+
+/// Payload accepted by a webhook together with the chat it belongs to.
+struct Authorized<T> {
+    chat_id: ChatId,
+    payload: T,
+}
+
+async fn authorize<T: DeserializeOwned>(
+    state: &IntegrationState,
+    request: &RequestContext,
+    query: TokenQuery,
+    headers: &HeaderMap,
+    body: &[u8],
+    integration: &str,
+) -> Result<Authorized<T>, ApiError> {
+    let request_id = request.request_id.as_str();
+    let signature = headers
+        .get(SIGNATURE_HEADER)
+        .and_then(|value| value.to_str().ok());
+    if !verify_signature(&state.secret, body, signature) {
+        tracing::debug!(request_id, integration, "Integration signature rejected");
+        return Err(request.error(ErrorCode::Unauthorized));
+    }
+
+    let Some(token) = query.token.filter(|token| !token.is_empty()) else {
+        tracing::debug!(request_id, integration, "Missing integration token");
+        return Err(request.error(ErrorCode::Unauthorized));
+    };
+    let preview = token_preview(&token);
+    let used_at = chrono::Utc::now().timestamp();
+    let chat_id = match state.db.use_token(&token, used_at).await {
+        Ok(Some((chat_id, scope))) if scope.can_write() => chat_id,
+        Ok(Some(_)) => {
+            tracing::debug!(request_id, integration, token_preview = %preview, "Read-only integration token rejected");
+            return Err(request.error(ErrorCode::InsufficientScope));
+        }
+        Ok(None) => {
+            tracing::debug!(request_id, integration, token_preview = %preview, "Integration token rejected");
+            return Err(request.error(ErrorCode::Unauthorized));
+        }
+        Err(err) => {
+            tracing::error!(request_id, integration, token_preview = %preview, error = %err, "Failed to validate integration token");
+            return Err(request.error(ErrorCode::Internal));
+        }
+    };
+
+    let payload = serde_json::from_slice(body).map_err(|err| {
+        tracing::debug!(request_id, integration, error = %err, "Invalid integration payload");
+        request.error(ErrorCode::InvalidRequest)
+    })?;
+    Ok(Authorized { chat_id, payload })
+}
+
+/// Split spoken phrases into items, store them, and return the reply text.
+async fn add_phrases(
+    state: &IntegrationState,
+    request: &RequestContext,
+    chat_id: ChatId,
+    phrases: Vec<String>,
+    integration: &str,
+) -> Result<String, ApiError> {
+    let items: Vec<String> = phrases
+        .iter()
+        .flat_map(|phrase| parse_items(phrase))
+        .map(|item| capitalize_first(&item))
+        .collect();
+    if items.is_empty() {
+        tracing::debug!(
+            request_id = %request.request_id,
+            chat_id = chat_id.0,
+            integration,
+            "Integration request carried no items"
+        );
+        return Ok(INTEGRATION_NO_ITEMS.to_string());
+    }
+
+    if let Some(scheduler) = &state.scheduler {
+        scheduler.admit(items.len() as u64).await;
+        tracing::debug!(
+            request_id = %request.request_id,
+            chat_id = chat_id.0,
+            integration,
+            weight = items.len(),
+            queue_depth = scheduler.queue_depth(),
+            "Admitted integration batch"
+        );
+    }
+
+    let db = &state.db;
+    let affected = db.add_items_count(chat_id, &items).await.map_err(|err| {
+        tracing::error!(
+            request_id = %request.request_id,
+            chat_id = chat_id.0,
+            integration,
+            error = %err,
+            "Failed to add integration items"
+        );
+        request.error(ErrorCode::Internal)
+    })?;
+    tracing::debug!(
+        request_id = %request.request_id,
+        chat_id = chat_id.0,
+        integration,
+        affected,
+        "Added items via integration"
+    );
+    state
+        .shared
+        .metrics
+        .record_items_added("integration", affected);
+    state
+        .shared
+        .events
+        .list_changed(db, ListEvent::batch(chat_id, ListEventKind::Add, affected));
+    Ok(integration_added_text(&items))
+}
+
+/// Check a `sha256=<hex>` signature against the raw body.
+fn verify_signature(secret: &str, body: &[u8], signature: Option<&str>) -> bool {
+    let Some(expected) = signature
+        .and_then(|value| value.trim().strip_prefix("sha256="))
+        .and_then(|value| hex::decode(value).ok())
+    else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(body);
+    mac.verify_slice(&expected).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{router as api_router, ApiConfig, DEFAULT_MAX_ITEM_LENGTH};
+    use crate::db::NewToken;
+    use crate::tests::util::init_test_db;
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use proptest::prelude::*;
+    use serde_json::{json, Value};
+    use tower::ServiceExt;
+
+    const SECRET: &str = "integration-secret";
+
+    fn sign(secret: &str, body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(body);
+        format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    fn app(db: Database, secret: Option<&str>) -> Router {
+        throttled_app(db, secret, None)
+    }
+
+    fn throttled_app(db: Database, secret: Option<&str>, mutation_rate: Option<u64>) -> Router {
+        api_router(
+            db,
+            SharedState::new().unwrap(),
+            ApiConfig {
+                rate_limit_per_second: None,
+                integration_secret: secret.map(str::to_string),
+                mutation_rate_per_second: mutation_rate,
+                max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+                cors_origins: None,
+                log_request_bodies: false,
+            },
+        )
+    }
+
+    async fn post_webhook(app: Router, uri: &str, body: Value, signature: &str) -> Response {
+        let body = body.to_string();
+        app.oneshot(
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("content-type", "application/json")
+                .header(SIGNATURE_HEADER, signature)
+                .body(Body::from(body))
+                .unwrap(),
+        )
+        .await
+        .unwrap()
+    }
+
+    async fn json_body(response: Response) -> Value {
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    fn item_texts(items: Vec<crate::db::Item>) -> Vec<String> {
+        items.into_iter().map(|item| item.text).collect()
+    }
+
+    fn alexa_payload(value: &str) -> Value {
+        json!({
+            "version": "1.0",
+            "request": {
+                "type": "IntentRequest",
+                "intent": {
+                    "name": "AddToShoppingListIntent",
+                    "slots": { "item": { "name": "item", "value": value } }
+                }
+            }
+        })
+    }
+
+    #[tokio::test]
+    async fn batches_are_scheduled_by_item_count() {
+        let db = init_test_db().await;
+        db.create_token(
+            ChatId(1),
+            "alexa-token",
+            &NewToken {
+                issued_at: 1,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let app = throttled_app(db.clone(), Some(SECRET), Some(2));
+        let uri = "/integrations/alexa?token=alexa-token";
+
+        let body = alexa_payload("milk and eggs");
+        let signature = sign(SECRET, body.to_string().as_bytes());
+        let started = std::time::Instant::now();
+        let response = post_webhook(app.clone(), uri, body, &signature).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(started.elapsed() < std::time::Duration::from_millis(500));
+
+        // The first batch used the whole second's budget.
+        let body = alexa_payload("bread");
+        let signature = sign(SECRET, body.to_string().as_bytes());
+        let response = post_webhook(app, uri, body, &signature).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(started.elapsed() >= std::time::Duration::from_millis(500));
+        assert_eq!(
+            item_texts(db.list_items(ChatId(1)).await.unwrap()),
+            ["Milk", "Eggs", "Bread"]
+        );
+    }
+
+    #[tokio::test]
+    async fn alexa_adds_items_to_token_chat() {
+        let db = init_test_db().await;
+        db.create_token(
+            ChatId(1),
+            "alexa-token",
+            &NewToken {
+                issued_at: 1,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        db.create_token(
+            ChatId(2),
+            "other-token",
+            &NewToken {
+                issued_at: 1,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let body = alexa_payload("milk and eggs");
+        let signature = sign(SECRET, body.to_string().as_bytes());
+        let response = post_webhook(
+            app(db.clone(), Some(SECRET)),
+            "/integrations/alexa?token=alexa-token",
+            body,
+            &signature,
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let payload = json_body(response).await;
+        assert_eq!(payload["version"], "1.0");
+        assert_eq!(
+            payload["response"]["outputSpeech"]["text"],
+            "Added Milk, Eggs to your list."
+        );
+        assert_eq!(payload["response"]["shouldEndSession"], true);
+
+        let items = db.list_items(ChatId(1)).await.unwrap();
+        assert_eq!(item_texts(items), vec!["Milk", "Eggs"]);
+        assert!(db.list_items(ChatId(2)).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn google_adds_items_from_parameters() {
+        let db = init_test_db().await;
+        db.create_token(
+            ChatId(5),
+            "google-token",
+            &NewToken {
+                issued_at: 1,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let body = json!({
+            "responseId": "abc",
+            "queryResult": {
+                "queryText": "add bread and butter to my shopping list",
+                "parameters": { "item": ["bread", "butter"] }
+            }
+        });
+        let signature = sign(SECRET, body.to_string().as_bytes());
+        let response = post_webhook(
+            app(db.clone(), Some(SECRET)),
+            "/integrations/google?token=google-token",
+            body,
+            &signature,
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let payload = json_body(response).await;
+        assert_eq!(
+            payload["fulfillmentText"],
+            "Added Bread, Butter to your list."
+        );
+
+        let items = db.list_items(ChatId(5)).await.unwrap();
+        assert_eq!(item_texts(items), vec!["Bread", "Butter"]);
+    }
+
+    #[tokio::test]
+    async fn empty_slot_is_acknowledged_without_items() {
+        let db = init_test_db().await;
+        db.create_token(
+            ChatId(1),
+            "alexa-token",
+            &NewToken {
+                issued_at: 1,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let body = json!({ "request": { "type": "LaunchRequest" } });
+        let signature = sign(SECRET, body.to_string().as_bytes());
+        let response = post_webhook(
+            app(db.clone(), Some(SECRET)),
+            "/integrations/alexa?token=alexa-token",
+            body,
+            &signature,
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let payload = json_body(response).await;
+        assert_eq!(
+            payload["response"]["outputSpeech"]["text"],
+            INTEGRATION_NO_ITEMS
+        );
+        assert!(db.list_items(ChatId(1)).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn rejects_bad_signature_and_unknown_token() {
+        let db = init_test_db().await;
+        db.create_token(
+            ChatId(1),
+            "alexa-token",
+            &NewToken {
+                issued_at: 1,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+        let body = alexa_payload("milk");
+        let response = post_webhook(
+            app(db.clone(), Some(SECRET)),
+            "/integrations/alexa?token=alexa-token",
+            body.clone(),
+            &sign("wrong-secret", body.to_string().as_bytes()),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let response = post_webhook(
+            app(db.clone(), Some(SECRET)),
+            "/integrations/alexa?token=missing",
+            body.clone(),
+            &sign(SECRET, body.to_string().as_bytes()),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert!(db.list_items(ChatId(1)).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn routes_disabled_without_secret() {
+        let db = init_test_db().await;
+        db.create_token(
+            ChatId(1),
+            "alexa-token",
+            &NewToken {
+                issued_at: 1,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let body = alexa_payload("milk");
+        let signature = sign(SECRET, body.to_string().as_bytes());
+        let response = post_webhook(
+            app(db.clone(), None),
+            "/integrations/alexa?token=alexa-token",
+            body,
+            &signature,
+        )
+        .await;
+        // Without a secret the integration routes are not mounted at all.
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(db.list_items(ChatId(1)).await.unwrap().is_empty());
+    }
+
+    proptest! {
+        #[test]
+        fn signature_roundtrip(secret in "[a-z0-9]{1,32}", body in proptest::collection::vec(any::<u8>(), 0..64)) {
+            let signature = sign(&secret, &body);
+            prop_assert!(verify_signature(&secret, &body, Some(&signature)));
+            let other_secret = format!("{secret}x");
+            prop_assert!(!verify_signature(&other_secret, &body, Some(&signature)));
+        }
+    }
+}
@@ -0,0 +1,305 @@
+use axum::{
+    extract::{rejection::JsonRejection, Extension, Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde::Deserialize;
+
+use super::{
+    item_text, json_body, reject_read_only, ApiError, ApiItem, ApiState, AuthenticatedContext,
+    ErrorCode, ItemTextLimit, RequestContext,
+};
+use crate::db::Database;
+use crate::notify::{ListEvent, ListEventKind};
+use crate::state::SharedState;
+
+/// Fields to change on `PUT /api/v1/items/{id}`; at least one must be present.
+#[derive(Debug, Deserialize)]
+struct UpdateRequest {
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    done: Option<bool>,
+}
+
+/// REST-style routes addressing a single item by id. Items of other chats
+/// answer 404 exactly like missing ones.
+pub(super) fn routes() -> Router<ApiState> {
+    Router::new().route(
+        "/items/{id}",
+        get(get_item).put(update_item).delete(delete_item),
+    )
+}
+
+async fn get_item(
+    State(db): State<Database>,
+    Extension(context): Extension<AuthenticatedContext>,
+    Extension(request): Extension<RequestContext>,
+    Path(id): Path<i64>,
+) -> Result<Response, ApiError> {
+    let chat_id = context.chat_id;
+    match db.get_item(chat_id, id).await {
+        Ok(Some(item)) => {
+            tracing::debug!(
+                request_id = %request.request_id,
+                chat_id = chat_id.0,
+                token_preview = %context.token_preview,
+                item_id = id,
+                "Read item via API"
+            );
+            Ok((StatusCode::OK, Json(ApiItem::from(item))).into_response())
+        }
+        Ok(None) => Err(request.error(ErrorCode::NotFound)),
+        Err(err) => {
+            tracing::error!(
+                request_id = %request.request_id,
+                chat_id = chat_id.0,
+                token_preview = %context.token_preview,
+                error = %err,
+                "Failed to load item"
+            );
+            Err(request.error(ErrorCode::Internal))
+        }
+    }
+}
+
+async fn update_item(
+    State(db): State<Database>,
+    State(shared): State<SharedState>,
+    Extension(context): Extension<AuthenticatedContext>,
+    Extension(request): Extension<RequestContext>,
+    Extension(limit): Extension<ItemTextLimit>,
+    Path(id): Path<i64>,
+    payload: Result<Json<UpdateRequest>, JsonRejection>,
+) -> Result<Response, ApiError> {
+    reject_read_only(&context, &request)?;
+    let payload = json_body(&request, payload)?;
+    if payload.text.is_none() && payload.done.is_none() {
+        return Err(request.error(ErrorCode::InvalidRequest));
+    }
+    let text = payload
+        .text
+        .as_deref()
+        .map(|text| item_text(&request, text, limit))
+        .transpose()?;
+
+    let chat_id = context.chat_id;
+    match db
+        .update_item(chat_id, id, text.as_deref(), payload.done)
+        .await
+    {
+        Ok(Some(item)) => {
+            tracing::debug!(
+                request_id = %request.request_id,
+                chat_id = chat_id.0,
+                token_preview = %context.token_preview,
+                item_id = id,
+                ?text,
+                done = ?payload.done,
+                "Updated item via API"
+            );
+            shared
+                .events
+                .list_changed(&db, ListEvent::item(chat_id, ListEventKind::Update, id));
+            Ok((StatusCode::OK, Json(ApiItem::from(item))).into_response())
+        }
+        Ok(None) => Err(request.error(ErrorCode::NotFound)),
+        Err(err) => {
+            tracing::error!(
+                request_id = %request.request_id,
+                chat_id = chat_id.0,
+                token_preview = %context.token_preview,
+                error = %err,
+                "Failed to update item"
+            );
+            Err(request.error(ErrorCode::Internal))
+        }
+    }
+}
+
+async fn delete_item(
+    State(db): State<Database>,
+    State(shared): State<SharedState>,
+    Extension(context): Extension<AuthenticatedContext>,
+    Extension(request): Extension<RequestContext>,
+    Path(id): Path<i64>,
+) -> Result<Response, ApiError> {
+    reject_read_only(&context, &request)?;
+    let chat_id = context.chat_id;
+    match db.delete_item_count(chat_id, id).await {
+        Ok(0) => Err(request.error(ErrorCode::NotFound)),
+        Ok(affected) => {
+            tracing::debug!(
+                request_id = %request.request_id,
+                chat_id = chat_id.0,
+                token_preview = %context.token_preview,
+                item_id = id,
+                affected,
+                "Deleted item via API"
+            );
+            shared
+                .events
+                .list_changed(&db, ListEvent::item(chat_id, ListEventKind::Delete, id));
+            Ok(StatusCode::NO_CONTENT.into_response())
+        }
+        Err(err) => {
+            tracing::error!(
+                request_id = %request.request_id,
+                chat_id = chat_id.0,
+                token_preview = %context.token_preview,
+                error = %err,
+                "Failed to delete item"
+            );
+            Err(request.error(ErrorCode::Internal))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{router, ApiConfig, DEFAULT_MAX_ITEM_LENGTH};
+    use super::*;
+    use crate::db::NewToken;
+    use crate::tests::util::init_test_db;
+    use axum::body::{to_bytes, Body};
+    use axum::http::{header::AUTHORIZATION, Request};
+    use serde_json::{json, Value};
+    use teloxide::types::ChatId;
+    use tower::ServiceExt;
+
+    async fn setup() -> (Database, Router, i64) {
+        let db = init_test_db().await;
+        for (chat_id, token) in [(ChatId(1), "token-own"), (ChatId(2), "token-other")] {
+            db.create_token(
+                chat_id,
+                token,
+                &NewToken {
+                    issued_at: 1,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        }
+        db.add_item_count(ChatId(1), "Milk").await.unwrap();
+        let id = db.list_items(ChatId(1)).await.unwrap()[0].id;
+        let app = router(
+            db.clone(),
+            SharedState::new().unwrap(),
+            ApiConfig {
+                rate_limit_per_second: None,
+                integration_secret: None,
+                mutation_rate_per_second: None,
+                max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+                cors_origins: None,
+                log_request_bodies: false,
+            },
+        );
+        (db, app, id)
+    }
+
+    fn item_request(method: &str, id: i64, token: &str, body: Option<Value>) -> Request<Body> {
+        let builder = Request::builder()
+            .method(method)
+            .uri(format!("/api/v1/items/{id}"))
+            .header(AUTHORIZATION, format!("Bearer {token}"));
+        match body {
+            Some(body) => builder
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+            None => builder.body(Body::empty()).unwrap(),
+        }
+    }
+
+    async fn json_body(response: Response) -> Value {
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn get_update_and_delete_own_item() {
+        let (db, app, id) = setup().await;
+
+        let response = app
+            .clone()
+            .oneshot(item_request("GET", id, "token-own", None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(json_body(response).await["text"], "Milk");
+
+        let response = app
+            .clone()
+            .oneshot(item_request(
+                "PUT",
+                id,
+                "token-own",
+                Some(json!({ "text": " Oat milk ", "done": true })),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = json_body(response).await;
+        assert_eq!(body["text"], "Oat milk");
+        assert_eq!(body["done"], true);
+
+        let response = app
+            .clone()
+            .oneshot(item_request(
+                "PUT",
+                id,
+                "token-own",
+                Some(json!({ "done": false })),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(json_body(response).await["text"], "Oat milk");
+
+        let response = app
+            .clone()
+            .oneshot(item_request("PUT", id, "token-own", Some(json!({}))))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = app
+            .clone()
+            .oneshot(item_request("DELETE", id, "token-own", None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert!(db.list_items(ChatId(1)).await.unwrap().is_empty());
+
+        let response = app
+            .oneshot(item_request("GET", id, "token-own", None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn other_chats_items_are_not_found() {
+        let (db, app, id) = setup().await;
+
+        for (method, body) in [
+            ("GET", None),
+            ("PUT", Some(json!({ "text": "Stolen", "done": true }))),
+            ("DELETE", None),
+        ] {
+            let response = app
+                .clone()
+                .oneshot(item_request(method, id, "token-other", body))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::NOT_FOUND, "{method}");
+            assert_eq!(json_body(response).await["error"], "not_found");
+        }
+
+        let item = db.get_item(ChatId(1), id).await.unwrap().unwrap();
+        assert_eq!(item.text, "Milk");
+        assert!(!item.done);
+    }
+}
@@ -0,0 +1,709 @@
+//! Hand-built OpenAPI 3 description of the routes in [`super::router`].
+//! Keep it in step with the router when adding or changing routes.
+
+use axum::{extract::State, routing::get, Json, Router};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+use super::health::{HEALTH_PATH, READY_PATH};
+use super::pairing::{PAIR_BEGIN_PATH, PAIR_COMPLETE_PATH};
+use super::share::SHARE_PATH;
+use super::{ErrorCode, API_V1_PREFIX, LEGACY_PREFIX, SIGNATURE_HEADER};
+use crate::db::TokenScope;
+
+/// Route serving the document itself; it needs no token.
+pub(super) const SPEC_PATH: &str = "/api/openapi.json";
+
+#[derive(Debug, Serialize)]
+pub(super) struct Document {
+    openapi: &'static str,
+    info: Info,
+    /// Bearer auth unless an operation overrides it.
+    security: Vec<BTreeMap<&'static str, Vec<&'static str>>>,
+    paths: BTreeMap<String, BTreeMap<&'static str, Operation>>,
+    components: Components,
+}
+
+#[derive(Debug, Serialize)]
+struct Info {
+    title: &'static str,
+    version: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Components {
+    schemas: BTreeMap<&'static str, Value>,
+    security_schemes: BTreeMap<&'static str, Value>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Operation {
+    summary: &'static str,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    deprecated: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    parameters: Vec<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_body: Option<Value>,
+    responses: BTreeMap<String, Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    security: Option<Vec<Value>>,
+}
+
+impl Operation {
+    fn new(summary: &'static str) -> Self {
+        Self {
+            summary,
+            deprecated: false,
+            parameters: Vec::new(),
+            request_body: None,
+            responses: BTreeMap::new(),
+            security: None,
+        }
+    }
+
+    /// Drop the document-wide bearer requirement.
+    fn public(mut self) -> Self {
+        self.security = Some(Vec::new());
+        self
+    }
+
+    fn param(mut self, name: &'static str, location: &'static str, schema: Value) -> Self {
+        self.parameters.push(json!({
+            "name": name,
+            "in": location,
+            "required": location == "path",
+            "schema": schema,
+        }));
+        self
+    }
+
+    fn body(mut self, schema: &'static str) -> Self {
+        self.request_body = Some(json!({
+            "required": true,
+            "content": { "application/json": { "schema": schema_ref(schema) } },
+        }));
+        self
+    }
+
+    fn respond(mut self, status: u16, description: &str, schema: Option<&str>) -> Self {
+        let mut response = json!({ "description": description });
+        if let Some(schema) = schema {
+            response["content"] = json!({ "application/json": { "schema": schema_ref(schema) } });
+        }
+        self.responses.insert(status.to_string(), response);
+        self
+    }
+
+    fn errors(mut self, codes: &[ErrorCode]) -> Self {
+        for code in codes {
+            let response = json!({
+                "description": code.as_str(),
+                "content": { "application/json": { "schema": schema_ref("Error") } },
+            });
+            self.responses
+                .insert(code.status().as_u16().to_string(), response);
+        }
+        self
+    }
+}
+
+fn int64() -> Value {
+    json!({ "type": "integer", "format": "int64" })
+}
+
+fn schema_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{name}") })
+}
+
+const READ_ERRORS: &[ErrorCode] = &[
+    ErrorCode::Unauthorized,
+    ErrorCode::RateLimited,
+    ErrorCode::Internal,
+];
+
+const WRITE_ERRORS: &[ErrorCode] = &[
+    ErrorCode::Unauthorized,
+    ErrorCode::InsufficientScope,
+    ErrorCode::InvalidRequest,
+    ErrorCode::RateLimited,
+    ErrorCode::Internal,
+];
+
+/// Routes taking item text also enforce the body and text length limits.
+const TEXT_ERRORS: &[ErrorCode] = &[ErrorCode::PayloadTooLarge, ErrorCode::TextTooLong];
+
+/// Routes still answering under the unversioned prefix.
+const LEGACY_ROUTES: [&str; 8] = [
+    "/list",
+    "/add",
+    "/toggle",
+    "/delete",
+    "/archive",
+    "/nuke",
+    "/done",
+    "/items/{id}",
+];
+
+/// Describe the API; assistant webhooks are only listed when enabled.
+pub(super) fn document(integrations: bool) -> Document {
+    let mut paths: BTreeMap<String, BTreeMap<&'static str, Operation>> = BTreeMap::new();
+    let mut add = |path: &str, method: &'static str, operation: Operation| {
+        paths
+            .entry(path.to_string())
+            .or_default()
+            .insert(method, operation);
+    };
+
+    add(
+        "/api/v1/list",
+        "get",
+        Operation::new("List the chat's items")
+            .param("since", "query", int64())
+            .param("done", "query", json!({ "type": "boolean" }))
+            .param("q", "query", json!({ "type": "string" }))
+            .param(
+                "sort",
+                "query",
+                json!({ "type": "string", "enum": ["created", "alpha"] }),
+            )
+            .respond(200, "Current items", Some("ListResponse"))
+            .errors(READ_ERRORS),
+    );
+    add(
+        "/api/v1/add",
+        "post",
+        Operation::new("Add an item")
+            .body("AddRequest")
+            .respond(201, "The created item", Some("Item"))
+            .errors(WRITE_ERRORS)
+            .errors(TEXT_ERRORS),
+    );
+    for (path, summary) in [
+        ("/api/v1/toggle", "Toggle an item's done state"),
+        ("/api/v1/delete", "Delete an item"),
+    ] {
+        add(
+            path,
+            "post",
+            Operation::new(summary)
+                .body("IdRequest")
+                .respond(200, "Rows changed", Some("MutationResponse"))
+                .errors(WRITE_ERRORS)
+                .errors(&[ErrorCode::NotFound]),
+        );
+    }
+    add(
+        "/api/v1/archive",
+        "post",
+        Operation::new("Archive the whole list")
+            .respond(200, "The stored snapshot", Some("ArchiveResponse"))
+            .errors(WRITE_ERRORS),
+    );
+    add(
+        "/api/v1/nuke",
+        "post",
+        Operation::new("Delete every item without archiving")
+            .respond(200, "Rows changed", Some("MutationResponse"))
+            .errors(WRITE_ERRORS),
+    );
+    add(
+        "/api/v1/done",
+        "post",
+        Operation::new("Archive checked items")
+            .respond(
+                200,
+                "The archived items and how many are left",
+                Some("DoneResponse"),
+            )
+            .errors(WRITE_ERRORS),
+    );
+
+    add(
+        "/api/v1/items/{id}",
+        "get",
+        Operation::new("Fetch one item")
+            .param("id", "path", int64())
+            .respond(200, "The item", Some("Item"))
+            .errors(READ_ERRORS)
+            .errors(&[ErrorCode::NotFound]),
+    );
+    add(
+        "/api/v1/items/{id}",
+        "put",
+        Operation::new("Change an item's text or done state")
+            .param("id", "path", int64())
+            .body("UpdateRequest")
+            .respond(200, "The updated item", Some("Item"))
+            .errors(WRITE_ERRORS)
+            .errors(TEXT_ERRORS)
+            .errors(&[ErrorCode::NotFound]),
+    );
+    add(
+        "/api/v1/items/{id}",
+        "delete",
+        Operation::new("Delete one item")
+            .param("id", "path", int64())
+            .respond(204, "Deleted", None)
+            .errors(WRITE_ERRORS)
+            .errors(&[ErrorCode::NotFound]),
+    );
+    add(
+        "/api/v1/export",
+        "get",
+        Operation::new("Download the list as text, Markdown or CSV")
+            .param(
+                "format",
+                "query",
+                json!({ "type": "string", "enum": ["text", "markdown", "csv"], "default": "text" }),
+            )
+            .respond(200, "The list as an attachment", None)
+            .errors(READ_ERRORS)
+            .errors(&[ErrorCode::InvalidRequest]),
+    );
+    add(
+        "/api/v1/reorder",
+        "post",
+        Operation::new("Put the list in a new order")
+            .body("ReorderRequest")
+            .respond(200, "The list in its new order", Some("ListResponse"))
+            .errors(WRITE_ERRORS)
+            .errors(&[ErrorCode::OrderMismatch]),
+    );
+    add(
+        "/api/v1/archives",
+        "get",
+        Operation::new("List the chat's archives, newest first")
+            .respond(200, "Archives without their items", Some("ArchiveList"))
+            .errors(READ_ERRORS),
+    );
+    add(
+        "/api/v1/archives/{id}",
+        "get",
+        Operation::new("Fetch one archive with its items")
+            .param("id", "path", int64())
+            .respond(200, "The archive", Some("Archive"))
+            .errors(READ_ERRORS)
+            .errors(&[ErrorCode::NotFound]),
+    );
+    add(
+        "/api/v1/changes",
+        "get",
+        Operation::new("Wait until the list changes past a revision")
+            .param("since", "query", int64())
+            .param(
+                "timeout",
+                "query",
+                json!({ "type": "integer", "minimum": 0, "maximum": 60, "default": 25 }),
+            )
+            .respond(200, "The current revision", Some("Changes"))
+            .errors(READ_ERRORS)
+            .errors(&[ErrorCode::InvalidRequest]),
+    );
+    add(
+        "/api/v1/tokens",
+        "get",
+        Operation::new("List the chat's tokens (admin scope)")
+            .respond(200, "Token metadata without the tokens", Some("TokenList"))
+            .errors(READ_ERRORS)
+            .errors(&[ErrorCode::InsufficientScope]),
+    );
+    add(
+        "/api/v1/tokens",
+        "post",
+        Operation::new("Issue a token for the chat (admin scope)")
+            .body("CreateTokenRequest")
+            .respond(
+                201,
+                "The new token; the only time it is shown",
+                Some("CreatedToken"),
+            )
+            .errors(WRITE_ERRORS),
+    );
+    add(
+        "/api/v1/tokens",
+        "delete",
+        Operation::new("Revoke a token by id or preview (admin scope)")
+            .param("id", "query", int64())
+            .param("preview", "query", json!({ "type": "string" }))
+            .respond(204, "Revoked", None)
+            .errors(WRITE_ERRORS)
+            .errors(&[ErrorCode::NotFound]),
+    );
+    add(
+        "/metrics",
+        "get",
+        Operation::new("Prometheus metrics in text exposition format")
+            .respond(200, "Counters and histograms", None)
+            .errors(READ_ERRORS),
+    );
+    add(
+        HEALTH_PATH,
+        "get",
+        Operation::new("Liveness probe")
+            .public()
+            .respond(200, "The process is up", None),
+    );
+    add(
+        READY_PATH,
+        "get",
+        Operation::new("Readiness probe")
+            .public()
+            .respond(200, "The database answers queries", None)
+            .errors(&[ErrorCode::Unavailable]),
+    );
+    add(
+        SHARE_PATH,
+        "get",
+        Operation::new("Read-only view of a list shared with /sharelink")
+            .public()
+            .param("slug", "path", json!({ "type": "string" }))
+            .respond(
+                200,
+                "HTML, or JSON items when Accept asks for application/json",
+                None,
+            )
+            .errors(&[ErrorCode::NotFound, ErrorCode::Internal]),
+    );
+    add(
+        PAIR_BEGIN_PATH,
+        "post",
+        Operation::new("Start pairing a device through a /start deep link")
+            .public()
+            .respond(201, "A short-lived pairing code", Some("PairingStarted"))
+            .errors(&[ErrorCode::Internal]),
+    );
+    add(
+        PAIR_COMPLETE_PATH,
+        "post",
+        Operation::new("Exchange an opened pairing code for a token")
+            .public()
+            .body("CompletePairingRequest")
+            .respond(
+                201,
+                "The chat's new token; the only time it is shown",
+                Some("PairedToken"),
+            )
+            .respond(202, "The link has not been opened yet", None)
+            .errors(&[
+                ErrorCode::NotFound,
+                ErrorCode::InvalidRequest,
+                ErrorCode::Internal,
+            ]),
+    );
+    add(
+        SPEC_PATH,
+        "get",
+        Operation::new("This document")
+            .public()
+            .respond(200, "OpenAPI 3 document", None),
+    );
+
+    if integrations {
+        for (path, summary) in [
+            ("/integrations/alexa", "Alexa skill webhook"),
+            ("/integrations/google", "Google Assistant webhook"),
+        ] {
+            add(
+                path,
+                "post",
+                Operation::new(summary)
+                    .public()
+                    .param("token", "query", json!({ "type": "string" }))
+                    .param(SIGNATURE_HEADER, "header", json!({ "type": "string" }))
+                    .respond(200, "Assistant reply", None)
+                    .errors(&[
+                        ErrorCode::Unauthorized,
+                        ErrorCode::InsufficientScope,
+                        ErrorCode::InvalidRequest,
+                        ErrorCode::Internal,
+                    ]),
+            );
+        }
+    }
+
+    for route in LEGACY_ROUTES {
+        let mut operations = paths[&format!("{API_V1_PREFIX}{route}")].clone();
+        for operation in operations.values_mut() {
+            operation.deprecated = true;
+        }
+        paths.insert(format!("{LEGACY_PREFIX}{route}"), operations);
+    }
+
+    Document {
+        openapi: "3.0.3",
+        info: Info {
+            title: "shopbot API",
+            version: env!("CARGO_PKG_VERSION"),
+        },
+        security: vec![BTreeMap::from([("bearerAuth", Vec::new())])],
+        paths,
+        components: Components {
+            schemas: schemas(),
+            security_schemes: BTreeMap::from([(
+                "bearerAuth",
+                json!({ "type": "http", "scheme": "bearer" }),
+            )]),
+        },
+    }
+}
+
+fn schemas() -> BTreeMap<&'static str, Value> {
+    let codes: Vec<&str> = ErrorCode::ALL.iter().map(|code| code.as_str()).collect();
+    let scopes: Vec<&str> = TokenScope::ALL.iter().map(|scope| scope.name()).collect();
+    BTreeMap::from([
+        (
+            "Item",
+            json!({
+                "type": "object",
+                "required": ["id", "text", "done", "quantity", "created_at"],
+                "properties": {
+                    "id": { "type": "integer", "format": "int64" },
+                    "text": { "type": "string" },
+                    "done": { "type": "boolean" },
+                    "quantity": { "type": "integer", "format": "int64" },
+                    "added_by": { "type": "string" },
+                    "created_at": { "type": "integer", "format": "int64" },
+                },
+            }),
+        ),
+        (
+            "ListResponse",
+            json!({
+                "type": "object",
+                "required": ["items"],
+                "properties": {
+                    "items": { "type": "array", "items": schema_ref("Item") },
+                },
+            }),
+        ),
+        (
+            "AddRequest",
+            json!({
+                "type": "object",
+                "required": ["text"],
+                "properties": {
+                    "text": { "type": "string" },
+                    "added_by": { "type": "string" },
+                },
+            }),
+        ),
+        (
+            "IdRequest",
+            json!({
+                "type": "object",
+                "required": ["id"],
+                "properties": { "id": { "type": "integer", "format": "int64" } },
+            }),
+        ),
+        (
+            "ReorderRequest",
+            json!({
+                "type": "object",
+                "required": ["ids"],
+                "properties": {
+                    "ids": {
+                        "type": "array",
+                        "items": { "type": "integer", "format": "int64" },
+                    },
+                },
+            }),
+        ),
+        (
+            "UpdateRequest",
+            json!({
+                "type": "object",
+                "properties": {
+                    "text": { "type": "string" },
+                    "done": { "type": "boolean" },
+                },
+            }),
+        ),
+        (
+            "MutationResponse",
+            json!({
+                "type": "object",
+                "required": ["affected"],
+                "properties": { "affected": { "type": "integer", "format": "int64" } },
+            }),
+        ),
+        (
+            "Token",
+            json!({
+                "type": "object",
+                "required": ["id", "preview", "scope", "issued_at"],
+                "properties": {
+                    "id": { "type": "integer", "format": "int64" },
+                    "preview": { "type": "string" },
+                    "name": { "type": "string" },
+                    "scope": { "type": "string", "enum": scopes },
+                    "issued_by": { "type": "string" },
+                    "issued_at": { "type": "integer", "format": "int64" },
+                    "last_used_at": { "type": "integer", "format": "int64", "nullable": true },
+                    "expires_at": { "type": "integer", "format": "int64", "nullable": true },
+                    "revoked_at": { "type": "integer", "format": "int64", "nullable": true },
+                },
+            }),
+        ),
+        (
+            "ArchiveResponse",
+            json!({
+                "type": "object",
+                "required": ["affected", "archive_id", "items"],
+                "properties": {
+                    "affected": { "type": "integer", "format": "int64" },
+                    "archive_id": { "type": "integer", "format": "int64", "nullable": true },
+                    "items": { "type": "array", "items": schema_ref("Item") },
+                },
+            }),
+        ),
+        (
+            "DoneResponse",
+            json!({
+                "type": "object",
+                "required": ["archived", "remaining", "archive_id"],
+                "properties": {
+                    "archived": { "type": "array", "items": schema_ref("Item") },
+                    "remaining": { "type": "integer", "format": "int64" },
+                    "archive_id": { "type": "integer", "format": "int64", "nullable": true },
+                },
+            }),
+        ),
+        (
+            "ArchiveList",
+            json!({
+                "type": "object",
+                "required": ["archives"],
+                "properties": {
+                    "archives": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "required": ["id", "archived_at", "item_count"],
+                            "properties": {
+                                "id": { "type": "integer", "format": "int64" },
+                                "archived_at": { "type": "integer", "format": "int64" },
+                                "item_count": { "type": "integer" },
+                            },
+                        },
+                    },
+                },
+            }),
+        ),
+        (
+            "Archive",
+            json!({
+                "type": "object",
+                "required": ["id", "archived_at", "items"],
+                "properties": {
+                    "id": { "type": "integer", "format": "int64" },
+                    "archived_at": { "type": "integer", "format": "int64" },
+                    "items": { "type": "array", "items": schema_ref("Item") },
+                },
+            }),
+        ),
+        (
+            "Changes",
+            json!({
+                "type": "object",
+                "required": ["revision", "changed"],
+                "properties": {
+                    "revision": { "type": "integer", "format": "int64" },
+                    "changed": { "type": "boolean" },
+                },
+            }),
+        ),
+        (
+            "TokenList",
+            json!({
+                "type": "object",
+                "required": ["tokens"],
+                "properties": {
+                    "tokens": { "type": "array", "items": schema_ref("Token") },
+                },
+            }),
+        ),
+        (
+            "CreateTokenRequest",
+            json!({
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string" },
+                    "expires_in": { "type": "integer", "format": "int64", "minimum": 1 },
+                    "scope": { "type": "string", "enum": scopes, "default": "write" },
+                },
+            }),
+        ),
+        (
+            "CreatedToken",
+            json!({
+                "allOf": [
+                    schema_ref("Token"),
+                    {
+                        "type": "object",
+                        "required": ["token"],
+                        "properties": { "token": { "type": "string" } },
+                    },
+                ],
+            }),
+        ),
+        (
+            "PairingStarted",
+            json!({
+                "type": "object",
+                "required": ["code", "start_payload", "expires_at"],
+                "properties": {
+                    "code": { "type": "string" },
+                    "start_payload": { "type": "string" },
+                    "expires_at": { "type": "integer", "format": "int64" },
+                },
+            }),
+        ),
+        (
+            "CompletePairingRequest",
+            json!({
+                "type": "object",
+                "required": ["code"],
+                "properties": { "code": { "type": "string" } },
+            }),
+        ),
+        (
+            "PairedToken",
+            json!({
+                "type": "object",
+                "required": ["token", "preview", "scope"],
+                "properties": {
+                    "token": { "type": "string" },
+                    "preview": { "type": "string" },
+                    "scope": { "type": "string", "enum": scopes },
+                },
+            }),
+        ),
+        (
+            "Error",
+            json!({
+                "type": "object",
+                "required": ["error", "request_id"],
+                "properties": {
+                    "error": { "type": "string", "enum": codes },
+                    "request_id": { "type": "string" },
+                    "message": { "type": "string" },
+                    "max": { "type": "integer" },
+                },
+            }),
+        ),
+    ])
+}
+
+pub(super) fn router(integrations: bool) -> Router {
+    Router::new()
+        .route(SPEC_PATH, get(serve_document))
+        .with_state(integrations)
+}
+
+async fn serve_document(State(integrations): State<bool>) -> Json<Document> {
+    Json(document(integrations))
+}
@@ -0,0 +1,264 @@
+use axum::{
+    extract::{rejection::JsonRejection, Extension, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rand::rngs::OsRng;
+use rand::TryRngCore;
+use serde::{Deserialize, Serialize};
+
+use super::{json_body, ApiError, ErrorCode, RequestContext};
+use crate::db::tokens::token_preview;
+use crate::db::{Database, NewToken, PairingClaim, TokenScope};
+use crate::handlers::pairing::PAIRING_PREFIX;
+use crate::handlers::tokens::generate_token;
+
+pub(super) const PAIR_BEGIN_PATH: &str = "/api/v1/pair/begin";
+pub(super) const PAIR_COMPLETE_PATH: &str = "/api/v1/pair/complete";
+/// How long a pairing code can be opened in the bot and exchanged.
+pub const PAIRING_TTL_SECS: i64 = 5 * 60;
+const PAIRED_TOKEN_NAME: &str = "Paired device";
+
+/// Response of `POST /api/v1/pair/begin`.
+#[derive(Debug, Serialize)]
+struct PairingStarted {
+    code: String,
+    /// The `start` parameter of the bot's `t.me` link.
+    start_payload: String,
+    expires_at: i64,
+}
+
+/// Body of `POST /api/v1/pair/complete`.
+#[derive(Debug, Deserialize)]
+struct CompletePairingRequest {
+    code: String,
+}
+
+/// The only response that carries the paired token.
+#[derive(Debug, Serialize)]
+struct PairedToken {
+    token: String,
+    preview: String,
+    scope: &'static str,
+}
+
+/// Pairing a device with a chat through a `/start pair_<code>` deep link.
+/// These routes take no token and are merged outside the auth layer; the
+/// short-lived code is the credential.
+pub(super) fn router(db: Database) -> Router {
+    Router::new()
+        .route(PAIR_BEGIN_PATH, post(begin_pairing))
+        .route(PAIR_COMPLETE_PATH, post(complete_pairing))
+        .with_state(db)
+}
+
+fn generate_code() -> String {
+    let mut bytes = [0u8; 12];
+    OsRng
+        .try_fill_bytes(&mut bytes)
+        .expect("OS RNG should be available to issue pairing codes");
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+fn internal_error(request: &RequestContext, err: anyhow::Error, action: &'static str) -> ApiError {
+    tracing::error!(
+        request_id = %request.request_id,
+        error = %err,
+        "Failed to {action}"
+    );
+    request.error(ErrorCode::Internal)
+}
+
+async fn begin_pairing(
+    State(db): State<Database>,
+    Extension(request): Extension<RequestContext>,
+) -> Result<Response, ApiError> {
+    let code = generate_code();
+    let created_at = chrono::Utc::now().timestamp();
+    let expires_at = created_at + PAIRING_TTL_SECS;
+    db.create_pairing(&code, created_at, expires_at)
+        .await
+        .map_err(|err| internal_error(&request, err, "create pairing"))?;
+
+    tracing::debug!(request_id = %request.request_id, expires_at, "Started pairing");
+    let started = PairingStarted {
+        start_payload: format!("{PAIRING_PREFIX}{code}"),
+        code,
+        expires_at,
+    };
+    Ok((StatusCode::CREATED, Json(started)).into_response())
+}
+
+/// `202` while the link has not been opened, the token once it has, and a
+/// `404` for unknown, expired and already used codes alike.
+async fn complete_pairing(
+    State(db): State<Database>,
+    Extension(request): Extension<RequestContext>,
+    payload: Result<Json<CompletePairingRequest>, JsonRejection>,
+) -> Result<Response, ApiError> {
+    let payload = json_body(&request, payload)?;
+    let now = chrono::Utc::now().timestamp();
+    let claim = db
+        .claim_pairing(payload.code.trim(), now)
+        .await
+        .map_err(|err| internal_error(&request, err, "claim pairing"))?;
+    let (chat_id, issuer_user_id, issuer_name) = match claim {
+        PairingClaim::Claimed {
+            chat_id,
+            issuer_user_id,
+            issuer_name,
+        } => (chat_id, issuer_user_id, issuer_name),
+        PairingClaim::Pending => {
+            tracing::debug!(request_id = %request.request_id, "Pairing not opened yet");
+            return Ok(StatusCode::ACCEPTED.into_response());
+        }
+        PairingClaim::Gone => {
+            tracing::debug!(request_id = %request.request_id, "Unknown or used pairing code");
+            return Err(request.error(ErrorCode::NotFound));
+        }
+    };
+
+    let token = generate_token();
+    let preview = token_preview(&token);
+    let scope = TokenScope::Write;
+    db.create_token(
+        chat_id,
+        &token,
+        &NewToken {
+            name: Some(PAIRED_TOKEN_NAME),
+            issuer_user_id,
+            issuer_name: issuer_name.as_deref(),
+            issued_at: now,
+            expires_at: None,
+            scope,
+        },
+    )
+    .await
+    .map_err(|err| internal_error(&request, err, "issue paired token"))?;
+
+    tracing::debug!(
+        request_id = %request.request_id,
+        chat_id = chat_id.0,
+        new_token_preview = %preview,
+        "Completed pairing"
+    );
+    let paired = PairedToken {
+        token,
+        preview,
+        scope: scope.name(),
+    };
+    Ok((StatusCode::CREATED, Json(paired)).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{router as api_router, ApiConfig, DEFAULT_MAX_ITEM_LENGTH};
+    use super::*;
+    use crate::state::SharedState;
+    use crate::tests::util::init_test_db;
+    use axum::body::{to_bytes, Body};
+    use axum::http::{
+        header::{AUTHORIZATION, CONTENT_TYPE},
+        Request,
+    };
+    use teloxide::types::ChatId;
+    use tower::ServiceExt;
+
+    async fn setup() -> (Database, Router) {
+        let db = init_test_db().await;
+        let app = api_router(
+            db.clone(),
+            SharedState::new().unwrap(),
+            ApiConfig {
+                rate_limit_per_second: None,
+                integration_secret: None,
+                mutation_rate_per_second: None,
+                max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+                cors_origins: None,
+                log_request_bodies: false,
+            },
+        );
+        (db, app)
+    }
+
+    async fn post_json(app: &Router, uri: &str, body: String) -> (StatusCode, serde_json::Value) {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(uri)
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let json = serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null);
+        (status, json)
+    }
+
+    async fn complete(app: &Router, code: &str) -> (StatusCode, serde_json::Value) {
+        let body = serde_json::json!({ "code": code }).to_string();
+        post_json(app, PAIR_COMPLETE_PATH, body).await
+    }
+
+    #[tokio::test]
+    async fn paired_token_is_handed_out_once() {
+        let (db, app) = setup().await;
+        let (status, started) = post_json(&app, PAIR_BEGIN_PATH, String::new()).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let code = started["code"].as_str().unwrap();
+        assert_eq!(started["start_payload"], format!("pair_{code}"));
+
+        assert_eq!(complete(&app, code).await.0, StatusCode::ACCEPTED);
+        let now = chrono::Utc::now().timestamp();
+        assert!(db
+            .bind_pairing(code, ChatId(3), Some(5), Some("Ann"), now)
+            .await
+            .unwrap());
+
+        let (status, paired) = complete(&app, code).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let token = paired["token"].as_str().unwrap();
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/api/v1/list")
+                    .header(AUTHORIZATION, format!("Bearer {token}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let tokens = db.list_tokens(ChatId(3)).await.unwrap();
+        assert_eq!(tokens[0].issuer_name.as_deref(), Some("Ann"));
+
+        let (status, body) = complete(&app, code).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"], "not_found");
+        assert_eq!(db.list_tokens(ChatId(3)).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn expired_codes_are_not_found() {
+        let (db, app) = setup().await;
+        db.create_pairing("old", 0, PAIRING_TTL_SECS).await.unwrap();
+        assert!(db
+            .bind_pairing("old", ChatId(3), None, None, 1)
+            .await
+            .unwrap());
+
+        assert_eq!(complete(&app, "old").await.0, StatusCode::NOT_FOUND);
+        assert_eq!(complete(&app, "unknown").await.0, StatusCode::NOT_FOUND);
+        assert!(db.list_tokens(ChatId(3)).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,208 @@
+use axum::{
+    extract::{rejection::JsonRejection, Extension, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use serde::Deserialize;
+
+use super::{
+    json_body, reject_read_only, ApiError, ApiItem, ApiState, AuthenticatedContext, ErrorCode,
+    ListResponse, RequestContext,
+};
+use crate::db::Database;
+use crate::notify::{ListEvent, ListEventKind};
+use crate::state::SharedState;
+
+/// Body of `POST /api/v1/reorder`: every item id of the list, in the new order.
+#[derive(Debug, Deserialize)]
+struct ReorderRequest {
+    ids: Vec<i64>,
+}
+
+pub(super) fn routes() -> Router<ApiState> {
+    Router::new().route("/reorder", post(reorder_items))
+}
+
+async fn reorder_items(
+    State(db): State<Database>,
+    State(shared): State<SharedState>,
+    Extension(context): Extension<AuthenticatedContext>,
+    Extension(request): Extension<RequestContext>,
+    payload: Result<Json<ReorderRequest>, JsonRejection>,
+) -> Result<Response, ApiError> {
+    reject_read_only(&context, &request)?;
+    let payload = json_body(&request, payload)?;
+    let chat_id = context.chat_id;
+
+    let internal = |err: anyhow::Error| {
+        tracing::error!(
+            request_id = %request.request_id,
+            chat_id = chat_id.0,
+            token_preview = %context.token_preview,
+            error = %err,
+            "Failed to reorder items"
+        );
+        request.error(ErrorCode::Internal)
+    };
+    if !db
+        .reorder_items(chat_id, &payload.ids)
+        .await
+        .map_err(internal)?
+    {
+        return Err(request
+            .error(ErrorCode::OrderMismatch)
+            .with_message("ids must name every item of the list exactly once"));
+    }
+    let items = db.list_items(chat_id).await.map_err(internal)?;
+
+    tracing::debug!(
+        request_id = %request.request_id,
+        chat_id = chat_id.0,
+        token_preview = %context.token_preview,
+        count = items.len(),
+        "Reordered items via API"
+    );
+    shared.events.list_changed(
+        &db,
+        ListEvent::batch(chat_id, ListEventKind::Reorder, items.len() as u64),
+    );
+    let items = items.into_iter().map(ApiItem::from).collect();
+    Ok((StatusCode::OK, Json(ListResponse { items })).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{router, ApiConfig, DEFAULT_MAX_ITEM_LENGTH};
+    use super::*;
+    use crate::db::NewToken;
+    use crate::tests::util::init_test_db;
+    use axum::body::{to_bytes, Body};
+    use axum::http::{
+        header::{AUTHORIZATION, CONTENT_TYPE},
+        Request,
+    };
+    use teloxide::types::ChatId;
+    use tower::ServiceExt;
+
+    async fn setup() -> (Database, Router, Vec<i64>) {
+        throttled_setup(None).await
+    }
+
+    async fn throttled_setup(mutation_rate: Option<u64>) -> (Database, Router, Vec<i64>) {
+        let db = init_test_db().await;
+        db.create_token(
+            ChatId(1),
+            "token-reorder",
+            &NewToken {
+                issued_at: 1,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        db.add_items_count(ChatId(1), &["Milk".into(), "Eggs".into(), "Bread".into()])
+            .await
+            .unwrap();
+        let ids = db
+            .list_items(ChatId(1))
+            .await
+            .unwrap()
+            .iter()
+            .map(|item| item.id)
+            .collect();
+        let app = router(
+            db.clone(),
+            SharedState::new().unwrap(),
+            ApiConfig {
+                rate_limit_per_second: None,
+                integration_secret: None,
+                mutation_rate_per_second: mutation_rate,
+                max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+                cors_origins: None,
+                log_request_bodies: false,
+            },
+        );
+        (db, app, ids)
+    }
+
+    async fn reorder(app: &Router, ids: &[i64]) -> (StatusCode, serde_json::Value) {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/reorder")
+                    .header(AUTHORIZATION, "Bearer token-reorder")
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(Body::from(serde_json::json!({ "ids": ids }).to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    async fn texts(db: &Database) -> Vec<String> {
+        db.list_items(ChatId(1))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|item| item.text)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn reorder_persists_and_new_items_go_last() {
+        let (db, app, ids) = setup().await;
+        let (status, body) = reorder(&app, &[ids[2], ids[0], ids[1]]).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["items"][0]["text"], "Bread");
+
+        db.add_item_count(ChatId(1), "Butter").await.unwrap();
+        assert_eq!(texts(&db).await, ["Bread", "Milk", "Eggs", "Butter"]);
+    }
+
+    #[tokio::test]
+    async fn reorder_flood_does_not_block_bot_toggles() {
+        let (db, app, ids) = throttled_setup(Some(3)).await;
+        let flood: Vec<_> = (0..3)
+            .map(|_| {
+                let (app, ids) = (app.clone(), ids.clone());
+                tokio::spawn(async move { reorder(&app, &ids).await.0 })
+            })
+            .collect();
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        let started = std::time::Instant::now();
+        assert_eq!(db.toggle_item_count(ChatId(1), ids[0]).await.unwrap(), 1);
+        assert!(started.elapsed() < std::time::Duration::from_millis(200));
+        assert!(flood.iter().any(|request| !request.is_finished()));
+
+        for request in flood {
+            assert_eq!(request.await.unwrap(), StatusCode::OK);
+        }
+        assert!(db.list_items(ChatId(1)).await.unwrap()[0].done);
+    }
+
+    #[tokio::test]
+    async fn mismatched_and_foreign_ids_are_rejected() {
+        let (db, app, ids) = setup().await;
+        db.add_item_count(ChatId(2), "Tea").await.unwrap();
+        let foreign = db.list_items(ChatId(2)).await.unwrap()[0].id;
+
+        for attempt in [
+            vec![ids[1], ids[0]],
+            vec![ids[0], ids[0], ids[1], ids[2]],
+            vec![ids[2], ids[1], foreign],
+        ] {
+            let (status, body) = reorder(&app, &attempt).await;
+            assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{attempt:?}");
+            assert_eq!(body["error"], "order_mismatch");
+        }
+        assert_eq!(texts(&db).await, ["Milk", "Eggs", "Bread"]);
+    }
+}
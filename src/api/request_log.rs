@@ -0,0 +1,255 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{MatchedPath, State},
+    http::Request,
+    middleware::Next,
+    response::Response,
+};
+use serde_json::Value;
+use std::time::Instant;
+
+use super::{ApiError, AuthenticatedContext, ErrorCode, RequestContext, MAX_BODY_BYTES};
+use crate::metrics::Metrics;
+
+/// JSON keys whose values never reach the logs; matched case-insensitively
+/// as substrings, so `api_key` and `access_token` are covered too.
+const SECRET_KEYS: [&str; 5] = ["token", "secret", "password", "authorization", "api_key"];
+const REDACTED: &str = "[redacted]";
+
+fn request_id(req: &Request<Body>) -> String {
+    req.extensions()
+        .get::<RequestContext>()
+        .map(|ctx| ctx.request_id.clone())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// One `info` event per request with its outcome and latency. Runs inside the
+/// request span; the chat id is read back from the response, where
+/// `require_auth` leaves the caller's context.
+pub(super) async fn log_requests(
+    State(metrics): State<Metrics>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    let request_id = request_id(&req);
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let started = Instant::now();
+    let response = next.run(req).await;
+    let latency = started.elapsed();
+    let status = response.status().as_u16();
+    metrics.record_api_request(method.as_str(), &route, status, latency);
+    let chat_id = response
+        .extensions()
+        .get::<AuthenticatedContext>()
+        .map(|context| context.chat_id.0);
+    tracing::info!(
+        request_id,
+        method = %method,
+        path,
+        route,
+        status,
+        latency_ms = latency.as_millis() as u64,
+        chat_id,
+        "API request completed"
+    );
+    response
+}
+
+/// Trace the request body with secret fields redacted. Only layered when
+/// `ApiConfig::log_request_bodies` is set, and the body is only buffered
+/// while trace logging is enabled.
+pub(super) async fn log_request_bodies(
+    req: Request<Body>,
+    next: Next,
+) -> Result<Response, ApiError> {
+    if !tracing::enabled!(tracing::Level::TRACE) {
+        return Ok(next.run(req).await);
+    }
+    let request_id = request_id(&req);
+    let (parts, body) = req.into_parts();
+    let bytes = to_bytes(body, MAX_BODY_BYTES).await.map_err(|err| {
+        tracing::debug!(request_id, error = %err, "Failed to buffer request body");
+        ApiError::new(ErrorCode::PayloadTooLarge, request_id.as_str()).with_max(MAX_BODY_BYTES)
+    })?;
+    if !bytes.is_empty() {
+        tracing::trace!(request_id, body = %redacted_body(&bytes), "API request body");
+    }
+    Ok(next
+        .run(Request::from_parts(parts, Body::from(bytes)))
+        .await)
+}
+
+fn is_secret(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    SECRET_KEYS.iter().any(|secret| key.contains(secret))
+}
+
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if is_secret(key) {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+/// JSON bodies with secrets replaced; anything else is summarized by size.
+fn redacted_body(bytes: &[u8]) -> String {
+    match serde_json::from_slice::<Value>(bytes) {
+        Ok(mut value) => {
+            redact(&mut value);
+            value.to_string()
+        }
+        Err(_) => format!("<{} bytes, not JSON>", bytes.len()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{router, ApiConfig, DEFAULT_MAX_ITEM_LENGTH};
+    use super::*;
+    use crate::db::NewToken;
+    use crate::state::SharedState;
+    use crate::telemetry::capture::CaptureLayer;
+    use crate::tests::util::init_test_db;
+    use axum::http::{
+        header::{AUTHORIZATION, CONTENT_TYPE},
+        StatusCode,
+    };
+    use axum::Router;
+    use teloxide::types::ChatId;
+    use tower::ServiceExt;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    async fn setup(log_request_bodies: bool) -> Router {
+        let db = init_test_db().await;
+        db.create_token(
+            ChatId(4),
+            "token-log",
+            &NewToken {
+                issued_at: 1,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        router(
+            db,
+            SharedState::new().unwrap(),
+            ApiConfig {
+                rate_limit_per_second: None,
+                integration_secret: None,
+                mutation_rate_per_second: None,
+                max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+                cors_origins: None,
+                log_request_bodies,
+            },
+        )
+    }
+
+    #[test]
+    fn redacts_nested_secrets() {
+        let body = br#"{"text":"Milk","api_key":"k","nested":[{"Token":"t","name":"n"}]}"#;
+        assert_eq!(
+            redacted_body(body),
+            r#"{"api_key":"[redacted]","nested":[{"Token":"[redacted]","name":"n"}],"text":"Milk"}"#
+        );
+        assert_eq!(redacted_body(b"not json"), "<8 bytes, not JSON>");
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn one_event_per_request_with_outcome() {
+        let layer = CaptureLayer::default();
+        let subscriber = tracing_subscriber::registry().with(layer.clone());
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let app = setup(false).await;
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/api/v1/list")
+                    .header(AUTHORIZATION, "Bearer token-log")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let request_id = response.headers()["x-request-id"].to_str().unwrap();
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/v1/list")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let events = layer.events.lock().unwrap();
+        let completed: Vec<_> = events
+            .iter()
+            .filter(|event| event.message == "API request completed")
+            .collect();
+        assert_eq!(completed.len(), 2);
+        let fields = &completed[0].fields;
+        assert_eq!(fields["request_id"], request_id);
+        assert_eq!(fields["method"], "GET");
+        assert_eq!(fields["path"], "/api/v1/list");
+        assert_eq!(fields["status"], "200");
+        assert_eq!(fields["chat_id"], "4");
+        assert!(fields.contains_key("latency_ms"));
+        assert_eq!(completed[0].span_fields["request_id"], request_id);
+        assert_eq!(completed[1].fields["status"], "401");
+        assert!(!completed[1].fields.contains_key("chat_id"));
+        assert!(!events
+            .iter()
+            .any(|event| event.message == "API request body"));
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn bodies_are_traced_when_enabled() {
+        let layer = CaptureLayer::default();
+        let subscriber = tracing_subscriber::registry().with(layer.clone());
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let app = setup(true).await;
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/v1/add")
+                    .header(AUTHORIZATION, "Bearer token-log")
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(Body::from(r#"{"text":"Milk","secret":"hunter2"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        let events = layer.events.lock().unwrap();
+        let body = events
+            .iter()
+            .find(|event| event.message == "API request body")
+            .expect("body event captured");
+        assert!(body.fields["body"].contains("Milk"));
+        assert!(!body.fields["body"].contains("hunter2"));
+        assert!(body.span_fields.contains_key("request_id"));
+    }
+}
@@ -0,0 +1,248 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::State,
+    http::{Method, Request},
+    middleware::Next,
+    response::Response,
+};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, Semaphore};
+
+use super::{ApiError, AuthenticatedContext, ErrorCode, RequestContext, MAX_BODY_BYTES};
+use crate::metrics::Metrics;
+
+/// Queues API mutations above a sustained rate so bot writes keep their share
+/// of the SQLite connection pool.
+///
+/// Unlike the rate limiter, nothing is rejected: callers wait in FIFO order
+/// until the sliding window has room for their weight.
+#[derive(Debug)]
+pub(super) struct WriteScheduler {
+    rate: u64,
+    window: Duration,
+    turn: Semaphore,
+    admitted: Mutex<VecDeque<Instant>>,
+    queued: AtomicUsize,
+    metrics: Metrics,
+}
+
+impl WriteScheduler {
+    pub(super) fn new(rate: u64, window: Duration, metrics: Metrics) -> Self {
+        Self {
+            rate,
+            window,
+            turn: Semaphore::new(1),
+            admitted: Mutex::new(VecDeque::new()),
+            queued: AtomicUsize::new(0),
+            metrics,
+        }
+    }
+
+    /// Number of mutations currently waiting for admission.
+    pub(super) fn queue_depth(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    /// Wait until a mutation of `weight` writes fits into the window.
+    pub(super) async fn admit(&self, weight: u64) {
+        let weight = weight.clamp(1, self.rate);
+        let _queued = QueuedMutation::new(self);
+        let _turn = self
+            .turn
+            .acquire()
+            .await
+            .expect("scheduler semaphore closed");
+        loop {
+            let now = Instant::now();
+            let mut admitted = self.admitted.lock().await;
+            while admitted
+                .front()
+                .is_some_and(|ts| now.duration_since(*ts) >= self.window)
+            {
+                admitted.pop_front();
+            }
+            if admitted.len() as u64 + weight <= self.rate {
+                admitted.extend(std::iter::repeat_n(now, weight as usize));
+                break;
+            }
+            let wait = admitted
+                .front()
+                .map(|ts| self.window.saturating_sub(now.duration_since(*ts)))
+                .unwrap_or_default();
+            drop(admitted);
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// Counts a mutation as queued until it is admitted or its request is
+/// dropped while waiting.
+struct QueuedMutation<'a> {
+    scheduler: &'a WriteScheduler,
+}
+
+impl<'a> QueuedMutation<'a> {
+    fn new(scheduler: &'a WriteScheduler) -> Self {
+        let depth = scheduler.queued.fetch_add(1, Ordering::Relaxed) + 1;
+        scheduler.metrics.set_write_queue_depth(depth);
+        Self { scheduler }
+    }
+}
+
+impl Drop for QueuedMutation<'_> {
+    fn drop(&mut self) {
+        let depth = self.scheduler.queued.fetch_sub(1, Ordering::Relaxed) - 1;
+        self.scheduler.metrics.set_write_queue_depth(depth);
+    }
+}
+
+/// Number of items a mutation writes: the ids it lists, as in `/reorder`,
+/// or a single item otherwise.
+fn write_weight(body: &[u8]) -> u64 {
+    serde_json::from_slice::<serde_json::Value>(body)
+        .ok()
+        .and_then(|value| value.get("ids")?.as_array().map(Vec::len))
+        .map_or(1, |ids| ids.max(1) as u64)
+}
+
+pub(super) async fn schedule_mutations(
+    State(scheduler): State<Arc<WriteScheduler>>,
+    req: Request<Body>,
+    next: Next,
+) -> Result<Response, ApiError> {
+    if req.method() == Method::GET {
+        return Ok(next.run(req).await);
+    }
+
+    let request_id = req
+        .extensions()
+        .get::<RequestContext>()
+        .map(|ctx| ctx.request_id.clone())
+        .unwrap_or_else(|| "unknown".to_string());
+    let token_preview = req
+        .extensions()
+        .get::<AuthenticatedContext>()
+        .map(|ctx| ctx.token_preview.clone())
+        .unwrap_or_else(|| "unknown".to_string());
+    let (parts, body) = req.into_parts();
+    let bytes = to_bytes(body, MAX_BODY_BYTES).await.map_err(|err| {
+        tracing::debug!(request_id, error = %err, "Failed to buffer request body");
+        ApiError::new(ErrorCode::PayloadTooLarge, request_id.as_str()).with_max(MAX_BODY_BYTES)
+    })?;
+    let weight = write_weight(&bytes);
+    let started = Instant::now();
+    scheduler.admit(weight).await;
+    tracing::debug!(
+        request_id,
+        token_preview,
+        weight,
+        queue_depth = scheduler.queue_depth(),
+        waited_ms = started.elapsed().as_millis() as u64,
+        "Admitted API mutation"
+    );
+    Ok(next
+        .run(Request::from_parts(parts, Body::from(bytes)))
+        .await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn admits_within_rate_immediately() {
+        let scheduler = WriteScheduler::new(3, Duration::from_secs(1), Metrics::default());
+        let started = Instant::now();
+        for _ in 0..3 {
+            scheduler.admit(1).await;
+        }
+        assert!(started.elapsed() < Duration::from_millis(100));
+        assert_eq!(scheduler.queue_depth(), 0);
+    }
+
+    #[tokio::test]
+    async fn queues_writes_above_rate() {
+        let scheduler = Arc::new(WriteScheduler::new(
+            2,
+            Duration::from_millis(200),
+            Metrics::default(),
+        ));
+        scheduler.admit(2).await;
+
+        let waiting = {
+            let scheduler = scheduler.clone();
+            tokio::spawn(async move { scheduler.admit(1).await })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(scheduler.queue_depth(), 1);
+
+        let started = Instant::now();
+        waiting.await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(100));
+        assert_eq!(scheduler.queue_depth(), 0);
+    }
+
+    #[tokio::test]
+    async fn cancelled_waits_leave_the_queue() {
+        let metrics = Metrics::default();
+        let scheduler = Arc::new(WriteScheduler::new(
+            1,
+            Duration::from_secs(10),
+            metrics.clone(),
+        ));
+        scheduler.admit(1).await;
+
+        let waiting = {
+            let scheduler = scheduler.clone();
+            tokio::spawn(async move { scheduler.admit(1).await })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(scheduler.queue_depth(), 1);
+        assert!(metrics.render().contains("shopbot_write_queue_depth 1\n"));
+
+        waiting.abort();
+        assert!(waiting.await.unwrap_err().is_cancelled());
+        assert_eq!(scheduler.queue_depth(), 0);
+        assert!(metrics.render().contains("shopbot_write_queue_depth 0\n"));
+    }
+
+    #[tokio::test]
+    async fn heavy_writer_does_not_starve_light_one() {
+        let window = Duration::from_millis(200);
+        let scheduler = Arc::new(WriteScheduler::new(4, window, Metrics::default()));
+        let heavy = {
+            let scheduler = scheduler.clone();
+            tokio::spawn(async move {
+                for _ in 0..4 {
+                    scheduler.admit(4).await;
+                }
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let started = Instant::now();
+        scheduler.admit(1).await;
+        assert!(started.elapsed() < window * 3);
+        assert!(!heavy.is_finished());
+        heavy.await.unwrap();
+    }
+
+    #[test]
+    fn weight_counts_listed_ids() {
+        assert_eq!(write_weight(br#"{"ids":[3,1,2]}"#), 3);
+        assert_eq!(write_weight(br#"{"ids":[]}"#), 1);
+        assert_eq!(write_weight(br#"{"text":"milk"}"#), 1);
+        assert_eq!(write_weight(b""), 1);
+    }
+
+    #[tokio::test]
+    async fn batch_weight_is_capped_at_rate() {
+        let scheduler = WriteScheduler::new(2, Duration::from_secs(1), Metrics::default());
+        let started = Instant::now();
+        scheduler.admit(50).await;
+        assert!(started.elapsed() < Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,208 @@
+use axum::{
+    extract::{Extension, Path, State},
+    http::{
+        header::{ACCEPT, CONTENT_TYPE},
+        HeaderMap, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde::Serialize;
+use teloxide::utils::html::escape;
+
+use super::{ApiError, ErrorCode, RequestContext};
+use crate::db::{Database, Item};
+
+pub(super) const SHARE_PATH: &str = "/share/{slug}";
+
+/// What a share link exposes: no ids or authors.
+#[derive(Debug, Serialize)]
+struct SharedItem {
+    text: String,
+    done: bool,
+    quantity: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct SharedList {
+    items: Vec<SharedItem>,
+}
+
+/// Read-only views of a list behind a `/sharelink` slug. They take no token
+/// and are merged outside the auth layer; the slug is the credential.
+pub(super) fn router(db: Database) -> Router {
+    Router::new()
+        .route(SHARE_PATH, get(shared_list))
+        .with_state(db)
+}
+
+fn wants_json(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .any(|value| value.contains("application/json"))
+}
+
+fn render_html(items: &[Item]) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>List</title>\n</head>\n<body>\n",
+    );
+    if items.is_empty() {
+        html.push_str("<p>The list is empty.</p>\n");
+    } else {
+        html.push_str("<ul>\n");
+        for item in items {
+            let label = escape(&item.label());
+            if item.done {
+                html.push_str(&format!("<li>✅ <s>{label}</s></li>\n"));
+            } else {
+                html.push_str(&format!("<li>⬜ {label}</li>\n"));
+            }
+        }
+        html.push_str("</ul>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/// Unknown and revoked slugs get the same 404 so a probe learns nothing.
+async fn shared_list(
+    State(db): State<Database>,
+    Extension(request): Extension<RequestContext>,
+    Path(slug): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let chat_id = db.share_link_chat(&slug).await.map_err(|err| {
+        tracing::error!(
+            request_id = %request.request_id,
+            error = %err,
+            "Failed to resolve share link"
+        );
+        request.error(ErrorCode::Internal)
+    })?;
+    let Some(chat_id) = chat_id else {
+        tracing::debug!(request_id = %request.request_id, "Unknown or revoked share link");
+        return Err(request.error(ErrorCode::NotFound));
+    };
+
+    let items = db.list_items(chat_id).await.map_err(|err| {
+        tracing::error!(
+            request_id = %request.request_id,
+            chat_id = chat_id.0,
+            error = %err,
+            "Failed to load shared list"
+        );
+        request.error(ErrorCode::Internal)
+    })?;
+
+    let json = wants_json(&headers);
+    tracing::debug!(
+        request_id = %request.request_id,
+        chat_id = chat_id.0,
+        item_count = items.len(),
+        json,
+        "Served shared list"
+    );
+    if json {
+        let items = items
+            .into_iter()
+            .map(|item| SharedItem {
+                text: item.text,
+                done: item.done,
+                quantity: item.quantity,
+            })
+            .collect();
+        return Ok((StatusCode::OK, Json(SharedList { items })).into_response());
+    }
+    Ok((
+        StatusCode::OK,
+        [(CONTENT_TYPE, "text/html; charset=utf-8")],
+        render_html(&items),
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{router as api_router, ApiConfig, DEFAULT_MAX_ITEM_LENGTH};
+    use super::*;
+    use crate::state::SharedState;
+    use crate::tests::util::init_test_db;
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use teloxide::types::ChatId;
+    use tower::ServiceExt;
+
+    async fn setup() -> (Database, Router) {
+        let db = init_test_db().await;
+        db.add_item_count(ChatId(1), "Milk").await.unwrap();
+        db.add_item_count(ChatId(1), "<b>Bread</b>").await.unwrap();
+        db.create_share_link(ChatId(1), "slug-1", 0).await.unwrap();
+        let app = api_router(
+            db.clone(),
+            SharedState::new().unwrap(),
+            ApiConfig {
+                rate_limit_per_second: None,
+                integration_secret: None,
+                mutation_rate_per_second: None,
+                max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+                cors_origins: None,
+                log_request_bodies: false,
+            },
+        );
+        (db, app)
+    }
+
+    async fn fetch(app: &Router, uri: &str, accept: &str) -> (StatusCode, String) {
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(uri)
+                    .header(ACCEPT, accept)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn share_link_renders_html_or_json_without_a_token() {
+        let (_db, app) = setup().await;
+
+        let (status, html) = fetch(&app, "/share/slug-1", "text/html").await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(html.contains("<li>⬜ Milk</li>"));
+        assert!(html.contains("&lt;b&gt;Bread&lt;/b&gt;"));
+
+        let (status, json) = fetch(&app, "/share/slug-1", "application/json").await;
+        assert_eq!(status, StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(body["items"][0]["text"], "Milk");
+        assert!(body["items"][0].get("id").is_none());
+    }
+
+    #[tokio::test]
+    async fn revoked_and_unknown_slugs_look_the_same() {
+        let (db, app) = setup().await;
+        db.revoke_share_links(ChatId(1), 1).await.unwrap();
+
+        let (revoked_status, revoked) = fetch(&app, "/share/slug-1", "application/json").await;
+        let (unknown_status, unknown) = fetch(&app, "/share/nope", "application/json").await;
+        assert_eq!(revoked_status, StatusCode::NOT_FOUND);
+        assert_eq!(unknown_status, StatusCode::NOT_FOUND);
+        let revoked: serde_json::Value = serde_json::from_str(&revoked).unwrap();
+        let unknown: serde_json::Value = serde_json::from_str(&unknown).unwrap();
+        assert_eq!(revoked["error"], "not_found");
+        assert_eq!(revoked["error"], unknown["error"]);
+        assert!(revoked.get("message").is_none());
+    }
+}
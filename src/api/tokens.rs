@@ -0,0 +1,441 @@
+use axum::{
+    extract::{
+        rejection::{JsonRejection, QueryRejection},
+        Extension, Query, State,
+    },
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{json_body, ApiError, ApiState, AuthenticatedContext, ErrorCode, RequestContext};
+use crate::db::tokens::token_preview;
+use crate::db::{Database, NewToken, TokenRecord, TokenScope};
+use crate::handlers::tokens::generate_token;
+
+/// Body of `POST /api/v1/tokens`.
+#[derive(Debug, Deserialize)]
+struct CreateTokenRequest {
+    #[serde(default)]
+    name: Option<String>,
+    /// Seconds until the token expires; it never expires when unset.
+    #[serde(default)]
+    expires_in: Option<i64>,
+    /// `read`, `write` or `admin`; defaults to `write`.
+    #[serde(default)]
+    scope: Option<String>,
+}
+
+/// Picks the token to revoke on `DELETE /api/v1/tokens`; exactly one is needed.
+#[derive(Debug, Deserialize)]
+struct RevokeQuery {
+    #[serde(default)]
+    id: Option<i64>,
+    #[serde(default)]
+    preview: Option<String>,
+}
+
+/// Token metadata; never the token itself.
+#[derive(Debug, Serialize)]
+struct ApiToken {
+    id: i64,
+    preview: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    scope: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    issued_by: Option<String>,
+    issued_at: i64,
+    last_used_at: Option<i64>,
+    expires_at: Option<i64>,
+    revoked_at: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct TokenList {
+    tokens: Vec<ApiToken>,
+}
+
+/// The only response that carries the plaintext token.
+#[derive(Debug, Serialize)]
+struct CreatedToken {
+    token: String,
+    #[serde(flatten)]
+    details: ApiToken,
+}
+
+impl From<TokenRecord> for ApiToken {
+    fn from(record: TokenRecord) -> Self {
+        Self {
+            id: record.id,
+            scope: record.scope().name(),
+            preview: record.token_preview,
+            name: record.name,
+            issued_by: record.issuer_name,
+            issued_at: record.issued_at,
+            last_used_at: record.last_used_at,
+            expires_at: record.expires_at,
+            revoked_at: record.revoked_at,
+        }
+    }
+}
+
+/// Token management for provisioning tools. Every route needs an admin
+/// token and only touches tokens of that token's chat.
+pub(super) fn routes() -> Router<ApiState> {
+    Router::new().route(
+        "/tokens",
+        get(list_tokens).post(create_token).delete(revoke_token),
+    )
+}
+
+fn require_admin(context: &AuthenticatedContext, request: &RequestContext) -> Result<(), ApiError> {
+    if context.scope.can_manage_tokens() {
+        return Ok(());
+    }
+    tracing::debug!(
+        request_id = %request.request_id,
+        chat_id = context.chat_id.0,
+        token_preview = %context.token_preview,
+        "Non-admin token attempted to manage tokens"
+    );
+    Err(request.error(ErrorCode::InsufficientScope))
+}
+
+fn internal_error(
+    context: &AuthenticatedContext,
+    request: &RequestContext,
+    err: anyhow::Error,
+    action: &'static str,
+) -> ApiError {
+    tracing::error!(
+        request_id = %request.request_id,
+        chat_id = context.chat_id.0,
+        token_preview = %context.token_preview,
+        error = %err,
+        "Failed to {action}"
+    );
+    request.error(ErrorCode::Internal)
+}
+
+async fn list_tokens(
+    State(db): State<Database>,
+    Extension(context): Extension<AuthenticatedContext>,
+    Extension(request): Extension<RequestContext>,
+) -> Result<Response, ApiError> {
+    require_admin(&context, &request)?;
+    let tokens = db
+        .list_tokens(context.chat_id)
+        .await
+        .map_err(|err| internal_error(&context, &request, err, "list tokens"))?;
+    tracing::debug!(
+        request_id = %request.request_id,
+        chat_id = context.chat_id.0,
+        token_preview = %context.token_preview,
+        count = tokens.len(),
+        "Listed tokens via API"
+    );
+    let tokens = tokens.into_iter().map(ApiToken::from).collect();
+    Ok((StatusCode::OK, Json(TokenList { tokens })).into_response())
+}
+
+async fn create_token(
+    State(db): State<Database>,
+    Extension(context): Extension<AuthenticatedContext>,
+    Extension(request): Extension<RequestContext>,
+    payload: Result<Json<CreateTokenRequest>, JsonRejection>,
+) -> Result<Response, ApiError> {
+    require_admin(&context, &request)?;
+    let payload = json_body(&request, payload)?;
+    let scope = match payload.scope.as_deref() {
+        None => TokenScope::Write,
+        Some(name) => TokenScope::parse(name).ok_or_else(|| {
+            request
+                .error(ErrorCode::InvalidRequest)
+                .with_message("scope must be read, write or admin")
+        })?,
+    };
+    if payload.expires_in.is_some_and(|seconds| seconds <= 0) {
+        return Err(request
+            .error(ErrorCode::InvalidRequest)
+            .with_message("expires_in must be a positive number of seconds"));
+    }
+    let name = payload
+        .name
+        .as_deref()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string);
+
+    let token = generate_token();
+    let preview = token_preview(&token);
+    let issued_at = chrono::Utc::now().timestamp();
+    let expires_at = payload
+        .expires_in
+        .map(|seconds| issued_at.saturating_add(seconds));
+    let issued_by = format!("API token {}", context.token_preview);
+    let id = db
+        .create_token(
+            context.chat_id,
+            &token,
+            &NewToken {
+                name: name.as_deref(),
+                issuer_user_id: None,
+                issuer_name: Some(&issued_by),
+                issued_at,
+                expires_at,
+                scope,
+            },
+        )
+        .await
+        .map_err(|err| internal_error(&context, &request, err, "create token"))?;
+
+    tracing::debug!(
+        request_id = %request.request_id,
+        chat_id = context.chat_id.0,
+        token_preview = %context.token_preview,
+        new_token_preview = %preview,
+        scope = scope.name(),
+        expires_at,
+        "Issued token via API"
+    );
+    let created = CreatedToken {
+        token,
+        details: ApiToken {
+            id,
+            preview,
+            name,
+            scope: scope.name(),
+            issued_by: Some(issued_by),
+            issued_at,
+            last_used_at: None,
+            expires_at,
+            revoked_at: None,
+        },
+    };
+    Ok((StatusCode::CREATED, Json(created)).into_response())
+}
+
+async fn revoke_token(
+    State(db): State<Database>,
+    Extension(context): Extension<AuthenticatedContext>,
+    Extension(request): Extension<RequestContext>,
+    query: Result<Query<RevokeQuery>, QueryRejection>,
+) -> Result<Response, ApiError> {
+    require_admin(&context, &request)?;
+    let Query(query) = query.map_err(|rejection| {
+        request
+            .error(ErrorCode::InvalidRequest)
+            .with_message(rejection.body_text())
+    })?;
+
+    let id = match (query.id, query.preview) {
+        (Some(id), None) => id,
+        (None, Some(preview)) => {
+            let tokens = db
+                .list_tokens(context.chat_id)
+                .await
+                .map_err(|err| internal_error(&context, &request, err, "list tokens"))?;
+            let matches: Vec<i64> = tokens
+                .iter()
+                .filter(|token| token.revoked_at.is_none() && token.token_preview == preview)
+                .map(|token| token.id)
+                .collect();
+            match matches[..] {
+                [] => return Err(request.error(ErrorCode::NotFound)),
+                [id] => id,
+                _ => {
+                    return Err(request
+                        .error(ErrorCode::InvalidRequest)
+                        .with_message("preview matches several tokens; revoke by id"))
+                }
+            }
+        }
+        _ => {
+            return Err(request
+                .error(ErrorCode::InvalidRequest)
+                .with_message("pass either id or preview"))
+        }
+    };
+
+    let revoked = db
+        .revoke_token_by_id(context.chat_id, id, chrono::Utc::now().timestamp())
+        .await
+        .map_err(|err| internal_error(&context, &request, err, "revoke token"))?;
+    if !revoked {
+        return Err(request.error(ErrorCode::NotFound));
+    }
+    tracing::debug!(
+        request_id = %request.request_id,
+        chat_id = context.chat_id.0,
+        token_preview = %context.token_preview,
+        token_id = id,
+        "Revoked token via API"
+    );
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{router, ApiConfig, DEFAULT_MAX_ITEM_LENGTH};
+    use super::*;
+    use crate::state::SharedState;
+    use crate::tests::util::init_test_db;
+    use axum::body::{to_bytes, Body};
+    use axum::http::{header::AUTHORIZATION, Request};
+    use serde_json::{json, Value};
+    use teloxide::types::ChatId;
+    use tower::ServiceExt;
+
+    async fn setup() -> (Database, Router) {
+        let db = init_test_db().await;
+        for (token, scope) in [
+            ("token-admin", TokenScope::Admin),
+            ("token-write", TokenScope::Write),
+            ("token-read", TokenScope::Read),
+        ] {
+            db.create_token(
+                ChatId(1),
+                token,
+                &NewToken {
+                    issued_at: 1,
+                    scope,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        }
+        let app = router(
+            db.clone(),
+            SharedState::new().unwrap(),
+            ApiConfig {
+                rate_limit_per_second: None,
+                integration_secret: None,
+                mutation_rate_per_second: None,
+                max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+                cors_origins: None,
+                log_request_bodies: false,
+            },
+        );
+        (db, app)
+    }
+
+    async fn call(
+        app: &Router,
+        method: &str,
+        uri: &str,
+        token: &str,
+        body: Option<Value>,
+    ) -> (StatusCode, Value) {
+        let builder = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(AUTHORIZATION, format!("Bearer {token}"));
+        let request = match body {
+            Some(body) => builder
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap(),
+            None => builder.body(Body::empty()).unwrap(),
+        };
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = if body.is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_slice(&body).unwrap()
+        };
+        (status, body)
+    }
+
+    #[tokio::test]
+    async fn non_admin_tokens_are_forbidden() {
+        let (_db, app) = setup().await;
+        for token in ["token-write", "token-read"] {
+            for (method, uri, body) in [
+                ("GET", "/api/v1/tokens", None),
+                ("POST", "/api/v1/tokens", Some(json!({ "name": "x" }))),
+                ("DELETE", "/api/v1/tokens?id=1", None),
+            ] {
+                let (status, body) = call(&app, method, uri, token, body).await;
+                assert_eq!(status, StatusCode::FORBIDDEN, "{method} with {token}");
+                assert_eq!(body["error"], "insufficient_scope");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn admin_creates_lists_and_revokes_tokens() {
+        let (db, app) = setup().await;
+
+        let (status, created) = call(
+            &app,
+            "POST",
+            "/api/v1/tokens",
+            "token-admin",
+            Some(json!({ "name": "kiosk", "expires_in": 3600, "scope": "read" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let token = created["token"].as_str().unwrap().to_string();
+        assert_eq!(created["scope"], "read");
+        assert_eq!(created["name"], "kiosk");
+        assert_eq!(
+            created["expires_at"].as_i64().unwrap() - created["issued_at"].as_i64().unwrap(),
+            3600
+        );
+        assert_eq!(
+            db.use_token(&token, 2).await.unwrap(),
+            Some((ChatId(1), TokenScope::Read))
+        );
+
+        let (status, listed) = call(&app, "GET", "/api/v1/tokens", "token-admin", None).await;
+        assert_eq!(status, StatusCode::OK);
+        let tokens = listed["tokens"].as_array().unwrap();
+        assert_eq!(tokens.len(), 4);
+        assert!(!listed.to_string().contains(&token));
+        assert!(tokens.iter().all(|token| token.get("token").is_none()));
+
+        let (status, _) = call(
+            &app,
+            "DELETE",
+            &format!("/api/v1/tokens?preview={}", &token[..6]),
+            "token-admin",
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert_eq!(db.use_token(&token, 3).await.unwrap(), None);
+
+        let id = created["id"].as_i64().unwrap();
+        let (status, body) = call(
+            &app,
+            "DELETE",
+            &format!("/api/v1/tokens?id={id}"),
+            "token-admin",
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"], "not_found");
+    }
+
+    #[tokio::test]
+    async fn invalid_scope_is_rejected() {
+        let (_db, app) = setup().await;
+        let (status, body) = call(
+            &app,
+            "POST",
+            "/api/v1/tokens",
+            "token-admin",
+            Some(json!({ "scope": "root" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"], "invalid_request");
+    }
+}
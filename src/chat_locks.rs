@@ -0,0 +1,91 @@
+//! Per-chat locks that serialize list renders, so two updates racing for the
+//! same chat cannot interleave their reads and edits and leave the older
+//! state on screen.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use teloxide::types::ChatId;
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+
+type ChatLock = Arc<AsyncMutex<()>>;
+
+/// Locks by chat. An entry lives only while someone holds or waits for it;
+/// clones share the entries.
+#[derive(Clone, Debug, Default)]
+pub struct ChatLocks {
+    by_chat: Arc<Mutex<HashMap<ChatId, ChatLock>>>,
+}
+
+/// Holds a chat's lock until dropped; the last holder removes the entry.
+#[derive(Debug)]
+pub struct ChatLockGuard {
+    chat_id: ChatId,
+    by_chat: Arc<Mutex<HashMap<ChatId, ChatLock>>>,
+    guard: Option<OwnedMutexGuard<()>>,
+}
+
+fn locked(
+    by_chat: &Mutex<HashMap<ChatId, ChatLock>>,
+) -> std::sync::MutexGuard<'_, HashMap<ChatId, ChatLock>> {
+    by_chat
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl Drop for ChatLockGuard {
+    fn drop(&mut self) {
+        let mut by_chat = locked(&self.by_chat);
+        // Release while the map is held so no waiter can clone the entry
+        // between the count check and the removal.
+        drop(self.guard.take());
+        if by_chat
+            .get(&self.chat_id)
+            .is_some_and(|lock| Arc::strong_count(lock) == 1)
+        {
+            by_chat.remove(&self.chat_id);
+        }
+    }
+}
+
+impl ChatLocks {
+    /// Wait for exclusive access to the chat's list.
+    pub async fn lock(&self, chat_id: ChatId) -> ChatLockGuard {
+        let lock = locked(&self.by_chat).entry(chat_id).or_default().clone();
+        let guard = lock.lock_owned().await;
+        ChatLockGuard {
+            chat_id,
+            by_chat: self.by_chat.clone(),
+            guard: Some(guard),
+        }
+    }
+
+    #[cfg(test)]
+    fn tracked_chats(&self) -> usize {
+        locked(&self.by_chat).len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn same_chat_waits_and_idle_entries_are_evicted() {
+        let locks = ChatLocks::default();
+        let first = locks.lock(ChatId(1)).await;
+        let _other = locks.lock(ChatId(2)).await;
+
+        let waiter = tokio::spawn({
+            let locks = locks.clone();
+            async move { drop(locks.lock(ChatId(1)).await) }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiter.is_finished());
+        assert_eq!(locks.tracked_chats(), 2);
+
+        drop(first);
+        waiter.await.unwrap();
+        assert_eq!(locks.tracked_chats(), 1);
+    }
+}
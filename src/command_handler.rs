@@ -3,16 +3,144 @@ use teloxide::prelude::*;
 
 use crate::ai::config::AiConfig;
 use crate::commands::Command;
+use crate::config::{AdminCommands, DeleteSessionTtl, PublicUrl};
 use crate::db;
+use crate::handlers::permissions::sender_is_admin;
+use crate::messages::ADMIN_ONLY_COMMAND;
+use crate::state::SharedState;
+
+/// Settings commands read besides the chat and its state, built once in
+/// `run()` and injected as a single dependency.
+#[derive(Clone)]
+pub struct CommandContext {
+    pub ai_config: Option<AiConfig>,
+    pub delete_after_timeout: u64,
+    pub delete_session_ttl: DeleteSessionTtl,
+    pub public_url: PublicUrl,
+    /// Commands only group admins may use.
+    pub admin_commands: AdminCommands,
+}
 
 pub async fn handle_command(
     bot: Bot,
     msg: Message,
     cmd: Command,
     db: db::Database,
-    ai_config: Option<AiConfig>,
-    delete_after_timeout: u64,
+    shared: SharedState,
+    context: CommandContext,
 ) -> Result<()> {
-    cmd.dispatch(bot, msg, db, ai_config, delete_after_timeout)
+    if context.admin_commands.contains(&cmd) && !sender_is_admin(&bot, &msg).await? {
+        tracing::info!(
+            chat_id = msg.chat.id.0,
+            user_id = msg.from.as_ref().map(|user| user.id.0),
+            "Refusing admin-only command"
+        );
+        let notice = bot.send_message(msg.chat.id, ADMIN_ONLY_COMMAND).await?;
+        drop(crate::delete_after(
+            bot,
+            notice.chat.id,
+            notice.id,
+            context.delete_after_timeout,
+        ));
+        return Ok(());
+    }
+    cmd.dispatch(bot, msg, db, shared, context).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DEFAULT_ADMIN_ONLY_COMMANDS;
+    use crate::tests::util::{init_test_db, SENT_MESSAGE};
+    use reqwest::Client;
+    use wiremock::{
+        matchers::{body_string_contains, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    /// Answers GetChatMember with `status`, expects one SendMessage
+    /// containing `reply`, and accepts everything else.
+    async fn group_server(status: &str, reply: &str) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/GetChatMember"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                format!(
+                    r#"{{"ok":true,"result":{{"user":{{"id":7,"is_bot":false,"first_name":"Ann"}},{status}}}}}"#
+                ),
+                "application/json",
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SendMessage"))
+            .and(body_string_contains(reply))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(SENT_MESSAGE, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(r#"{"ok":true,"result":true}"#, "application/json"),
+            )
+            .mount(&server)
+            .await;
+        server
+    }
+
+    async fn nuke_in_group(server: &MockServer) {
+        let client = Client::builder().no_proxy().build().unwrap();
+        let bot = Bot::with_client("TEST", client)
+            .set_api_url(reqwest::Url::parse(&server.uri()).unwrap());
+        let msg: Message = serde_json::from_str(
+            r#"{"message_id":1,"date":0,"chat":{"id":-5,"type":"group","title":"Family"},"from":{"id":7,"is_bot":false,"first_name":"Ann"},"text":"/nuke"}"#,
+        )
+        .unwrap();
+        handle_command(
+            bot,
+            msg,
+            Command::Nuke,
+            init_test_db().await,
+            SharedState::new().unwrap(),
+            CommandContext {
+                ai_config: None,
+                delete_after_timeout: 5,
+                delete_session_ttl: DeleteSessionTtl::default(),
+                public_url: PublicUrl::default(),
+                admin_commands: AdminCommands::parse(DEFAULT_ADMIN_ONLY_COMMANDS),
+            },
+        )
         .await
+        .unwrap();
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn members_are_refused() {
+        let server = group_server(r#""status":"member""#, ADMIN_ONLY_COMMAND).await;
+        nuke_in_group(&server).await;
+    }
+
+    #[tokio::test]
+    async fn admins_get_the_nuke_prompt() {
+        let server = group_server(
+            r#""status":"creator","is_anonymous":false"#,
+            "nuke_confirm_7",
+        )
+        .await;
+        nuke_in_group(&server).await;
+    }
+
+    #[test]
+    fn admin_commands_parse_names() {
+        let commands = AdminCommands::parse(" /nuke, done ,bogus,");
+        assert!(commands.contains(&Command::Nuke));
+        assert!(commands.contains(&Command::ArchiveDone));
+        assert!(!commands.contains(&Command::Archive));
+        assert!(!AdminCommands::parse("").contains(&Command::Nuke));
+        let defaults = AdminCommands::parse(DEFAULT_ADMIN_ONLY_COMMANDS);
+        assert!(defaults.contains(&Command::RevokeToken("abc".into())));
+    }
 }
@@ -1,12 +1,15 @@
 use anyhow::Result;
 use teloxide::{prelude::*, utils::command::BotCommands};
 
-use crate::ai::config::AiConfig;
+use crate::command_handler::CommandContext;
 use crate::db;
 use crate::handlers::{
-    add_items_from_parsed_text, enter_delete_mode, help, issue_token, list_tokens, revoke_token,
-    show_system_info, ListService,
+    add_items_from_parsed_text, enter_assign_mode, enter_delete_mode, enter_edit_mode,
+    handle_prompt, handle_receipt, handle_settings, handle_share_link, handle_webhook, help,
+    issue_token, list_tokens, revoke_token, show_history, show_my_items, show_system_info,
+    show_trash, start, ListService,
 };
+use crate::state::SharedState;
 
 #[derive(BotCommands, Clone)]
 #[command(
@@ -15,7 +18,7 @@ use crate::handlers::{
 )]
 pub enum Command {
     #[command(description = "display this text.")]
-    Start,
+    Start(String),
     #[command(description = "display this text.")]
     Help,
     #[command(description = "show the current list.")]
@@ -27,25 +30,49 @@ pub enum Command {
         description = "archive only checked items and keep the rest."
     )]
     ArchiveDone,
+    #[command(description = "show recently archived lists.")]
+    History,
     #[command(description = "show a temporary panel to delete items from the list.")]
     Delete,
+    #[command(description = "rename an item via a private chat.")]
+    Edit,
+    #[command(description = "claim items via a private chat.")]
+    Assign,
+    #[command(description = "show the items assigned to you.")]
+    Mine,
     #[command(description = "send the list as plain text for copying.")]
     Share,
+    #[command(description = "send the list as a file (text, markdown or csv).")]
+    Export(String),
     #[command(description = "completely delete the current list.")]
     Nuke,
+    #[command(description = "reverse the last nuke, archive, or deletion.")]
+    Undo,
+    #[command(description = "restore recently deleted items.")]
+    Trash,
     #[command(description = "parse items from the given text using GPT.")]
     Parse,
     #[command(description = "show system information.")]
     Info,
     #[command(
         rename = "create_token",
-        description = "issue a new token for this list (optionally named)."
+        description = "issue a new token for this list (optionally named, with a lifetime like 7d, --readonly or --admin)."
     )]
     CreateToken(String),
     #[command(description = "list issued tokens for this list.")]
     Tokens,
     #[command(rename = "revoke_token", description = "revoke a token.")]
     RevokeToken(String),
+    #[command(description = "manage webhooks notified when the list changes.")]
+    Webhook(String),
+    #[command(description = "get a read-only web link to the list, or revoke it.")]
+    Sharelink(String),
+    #[command(description = "view or change chat settings.")]
+    Settings(String),
+    #[command(description = "view or replace the AI prompts for this chat.")]
+    Prompt(String),
+    #[command(description = "read photos as receipts with prices (on or off).")]
+    Receipt(String),
 }
 
 impl Command {
@@ -54,24 +81,118 @@ impl Command {
         bot: Bot,
         msg: Message,
         db: db::Database,
-        ai_config: Option<AiConfig>,
-        delete_after_timeout: u64,
+        shared: SharedState,
+        context: CommandContext,
     ) -> Result<()> {
-        let service = ListService::new(&db);
+        let CommandContext {
+            ai_config,
+            delete_after_timeout,
+            delete_session_ttl,
+            public_url,
+            ..
+        } = context;
+        let service = ListService::new(&db, &shared);
+        service.follow_topic(&msg).await?;
         match self {
-            Command::Start | Command::Help => help(bot, msg).await?,
+            Command::Start(payload) => start(bot, msg, db, payload).await?,
+            Command::Help => help(bot, msg).await?,
             Command::List => service.send_list(bot, msg.chat.id).await?,
             Command::Archive => service.archive(bot, msg.chat.id).await?,
             Command::ArchiveDone => service.archive_checked(bot, msg.chat.id).await?,
-            Command::Delete => enter_delete_mode(bot, msg, &db, delete_after_timeout).await?,
+            Command::History => show_history(bot, msg, db).await?,
+            Command::Delete => {
+                enter_delete_mode(bot, msg, &db, delete_after_timeout, delete_session_ttl).await?
+            }
+            Command::Edit => enter_edit_mode(bot, msg, &db, delete_after_timeout).await?,
+            Command::Assign => enter_assign_mode(bot, msg, &db, delete_after_timeout).await?,
+            Command::Mine => show_my_items(bot, msg, db).await?,
             Command::Share => service.share_list(bot, msg.chat.id).await?,
+            Command::Export(args) => service.export(bot, msg.chat.id, &args).await?,
             Command::Nuke => service.nuke(bot, msg, delete_after_timeout).await?,
-            Command::Parse => add_items_from_parsed_text(bot, msg, db, ai_config).await?,
+            Command::Undo => service.undo(bot, msg.chat.id).await?,
+            Command::Trash => show_trash(bot, msg, db).await?,
+            Command::Parse => add_items_from_parsed_text(bot, msg, db, shared, ai_config).await?,
             Command::Info => show_system_info(bot, msg).await?,
             Command::CreateToken(name) => issue_token(bot, msg, db, name).await?,
             Command::Tokens => list_tokens(bot, msg, db).await?,
             Command::RevokeToken(token) => revoke_token(bot, msg, db, token).await?,
+            Command::Webhook(args) => handle_webhook(bot, msg, db, args).await?,
+            Command::Sharelink(args) => handle_share_link(bot, msg, db, public_url, args).await?,
+            Command::Settings(args) => handle_settings(bot, msg, db, shared, args).await?,
+            Command::Prompt(args) => handle_prompt(bot, msg, db, ai_config, args).await?,
+            Command::Receipt(args) => handle_receipt(bot, msg, db, args).await?,
         }
         Ok(())
     }
 }
+
+/// Publish the commands in Telegram's command menu. Without the menu the bot
+/// still works, so a failure is only logged.
+pub async fn register_commands(bot: &Bot) {
+    let commands = Command::bot_commands();
+    let count = commands.len();
+    match bot.set_my_commands(commands).await {
+        Ok(_) => tracing::info!(count, "Registered bot commands"),
+        Err(err) => tracing::warn!(error = %err, "Failed to register bot commands"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::util::test_bot;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn menu_lists_every_command() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SetMyCommands"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(r#"{"ok":true,"result":true}"#, "application/json"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        register_commands(&test_bot(&server)).await;
+
+        let requests = server.received_requests().await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        let sent: Vec<&str> = body["commands"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|command| command["command"].as_str().unwrap())
+            .collect();
+        let expected: Vec<String> = Command::bot_commands()
+            .into_iter()
+            .map(|command| command.command)
+            .collect();
+        assert_eq!(sent, expected);
+        assert!(sent.contains(&"/share") && sent.contains(&"/done"));
+        assert!(body["commands"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .all(|command| !command["description"].as_str().unwrap().is_empty()));
+    }
+
+    #[tokio::test]
+    async fn failed_registration_is_not_fatal() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SetMyCommands"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        register_commands(&test_bot(&server)).await;
+        server.verify().await;
+    }
+}
@@ -1,15 +1,95 @@
 use std::env;
+use std::mem::{discriminant, Discriminant};
+use std::time::Duration;
+use teloxide::utils::command::BotCommands;
 
 use crate::ai::config::AiConfig;
+use crate::api::CorsOrigins;
+use crate::commands::Command;
+
+/// Base URL the API is reachable at from outside, used to build links the bot
+/// sends. Without it links are sent as paths relative to the API.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PublicUrl(Option<String>);
+
+impl PublicUrl {
+    pub fn new(base: Option<String>) -> Self {
+        Self(base.map(|base| base.trim_end_matches('/').to_string()))
+    }
+
+    pub fn is_configured(&self) -> bool {
+        self.0.is_some()
+    }
+
+    pub fn link(&self, path: &str) -> String {
+        match &self.0 {
+            Some(base) => format!("{base}{path}"),
+            None => path.to_string(),
+        }
+    }
+}
+
+/// How long a delete panel stays usable, from `DELETE_SESSION_TTL`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeleteSessionTtl(pub u64);
+
+impl Default for DeleteSessionTtl {
+    fn default() -> Self {
+        Self(crate::handlers::delete::DELETE_SESSION_TTL_SECS)
+    }
+}
+
+/// Admin-only commands when `ADMIN_ONLY_COMMANDS` is unset.
+pub const DEFAULT_ADMIN_ONLY_COMMANDS: &str = "nuke,archive,create_token,revoke_token";
+
+/// Commands that in groups and supergroups only owners and administrators
+/// may run. Private chats are never restricted.
+#[derive(Clone, Debug, Default)]
+pub struct AdminCommands(Vec<Discriminant<Command>>);
+
+impl AdminCommands {
+    /// Parse a comma-separated list of command names, with or without the
+    /// leading `/`. Unknown names are logged and skipped; a blank list
+    /// restricts nothing.
+    pub fn parse(value: &str) -> Self {
+        let commands = value
+            .split(',')
+            .map(|name| name.trim().trim_start_matches('/'))
+            .filter(|name| !name.is_empty())
+            .filter_map(|name| match Command::parse(&format!("/{name}"), "") {
+                Ok(command) => Some(discriminant(&command)),
+                Err(_) => {
+                    tracing::warn!(name, "Ignoring unknown admin-only command");
+                    None
+                }
+            })
+            .collect();
+        Self(commands)
+    }
+
+    pub fn contains(&self, command: &Command) -> bool {
+        self.0.contains(&discriminant(command))
+    }
+}
 
 #[derive(Clone)]
 pub struct Config {
     pub db_url: String,
     pub db_pool_size: u32,
+    pub db_busy_timeout: Duration,
     pub ai: Option<AiConfig>,
     pub delete_after_timeout: u64,
+    pub delete_session_ttl: DeleteSessionTtl,
     pub api_bind_addr: String,
     pub api_rate_limit_per_second: Option<u64>,
+    pub api_mutation_rate_per_second: Option<u64>,
+    pub api_max_item_length: usize,
+    pub api_cors_origins: Option<CorsOrigins>,
+    pub api_log_request_bodies: bool,
+    pub integration_secret: Option<String>,
+    pub public_url: PublicUrl,
+    pub startup_checks: bool,
+    pub admin_commands: AdminCommands,
 }
 
 impl Config {
@@ -20,24 +100,85 @@ impl Config {
             .ok()
             .and_then(|s| s.parse::<u32>().ok())
             .unwrap_or(5);
+        let db_busy_timeout = env::var("DB_BUSY_TIMEOUT_MS")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .map(Duration::from_millis)
+            .unwrap_or(crate::db::DEFAULT_BUSY_TIMEOUT);
         let delete_after_timeout = env::var("DELETE_AFTER_TIMEOUT")
             .ok()
             .and_then(|s| s.parse::<u64>().ok())
             .unwrap_or(crate::utils::DEFAULT_DELETE_AFTER_TIMEOUT);
+        let delete_session_ttl = env::var("DELETE_SESSION_TTL")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|value| *value > 0)
+            .map(DeleteSessionTtl)
+            .unwrap_or_default();
         let api_bind_addr =
             env::var("API_BIND_ADDR").unwrap_or_else(|_| "0.0.0.0:8080".to_string());
         let api_rate_limit_per_second = env::var("API_RATE_LIMIT_PER_SECOND")
             .ok()
             .and_then(|s| s.parse::<u64>().ok())
             .filter(|value| *value > 0);
+        let api_mutation_rate_per_second = env::var("API_MUTATION_RATE_PER_SECOND")
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|value| *value > 0);
+        let api_max_item_length = env::var("API_MAX_ITEM_LENGTH")
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok())
+            .filter(|value| *value > 0)
+            .unwrap_or(crate::api::DEFAULT_MAX_ITEM_LENGTH);
+        let api_cors_origins = env::var("API_CORS_ORIGINS")
+            .ok()
+            .and_then(|value| CorsOrigins::parse(&value));
+        let api_log_request_bodies = env::var("API_LOG_REQUEST_BODIES")
+            .map(|value| {
+                matches!(
+                    value.trim().to_ascii_lowercase().as_str(),
+                    "1" | "true" | "yes" | "on"
+                )
+            })
+            .unwrap_or(false);
+        let integration_secret = env::var("INTEGRATION_WEBHOOK_SECRET")
+            .ok()
+            .filter(|value| !value.is_empty());
+        let public_url = PublicUrl::new(
+            env::var("PUBLIC_URL")
+                .ok()
+                .filter(|value| !value.trim().is_empty()),
+        );
+        let startup_checks = env::var("STARTUP_CHECKS")
+            .map(|value| {
+                matches!(
+                    value.trim().to_ascii_lowercase().as_str(),
+                    "1" | "true" | "yes" | "on"
+                )
+            })
+            .unwrap_or(false);
+        let admin_commands = AdminCommands::parse(
+            &env::var("ADMIN_ONLY_COMMANDS")
+                .unwrap_or_else(|_| DEFAULT_ADMIN_ONLY_COMMANDS.to_string()),
+        );
         let ai = AiConfig::from_env();
         Self {
             db_url,
             db_pool_size,
+            db_busy_timeout,
             ai,
             delete_after_timeout,
+            delete_session_ttl,
             api_bind_addr,
             api_rate_limit_per_second,
+            api_mutation_rate_per_second,
+            api_max_item_length,
+            api_cors_origins,
+            api_log_request_bodies,
+            integration_secret,
+            public_url,
+            startup_checks,
+            admin_commands,
         }
     }
 }
@@ -1,18 +1,54 @@
 // Database related types and functions
 
 use anyhow::Result;
-use sqlx::{sqlite::SqlitePoolOptions, Pool, Sqlite};
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteSynchronous},
+    Pool, Sqlite,
+};
+use std::str::FromStr;
+use std::time::Duration;
 
+pub mod album_photos;
+pub mod archives;
+pub mod assignments;
+pub mod bot_permissions;
+pub mod chat_settings;
 pub mod chat_state;
 pub mod database;
 pub mod delete_session;
+pub mod duplicate_prompts;
+pub mod edit_session;
+pub mod item_history;
 pub mod items;
+pub mod message_items;
+pub mod migrations;
+pub mod pairings;
+pub mod settings_panel;
+pub mod share_links;
 pub mod tokens;
+pub mod trash;
+pub mod undo_log;
+pub mod voice_deletions;
+pub mod webhooks;
 
+pub use archives::ArchiveRecord;
+pub use assignments::AssignSession;
+pub use bot_permissions::BotCapabilities;
+pub use chat_settings::ChatSettings;
 pub use database::Database;
+pub use duplicate_prompts::{DuplicateAction, DuplicatePrompt};
+pub use edit_session::EditSession;
+pub use item_history::TopItem;
+pub use pairings::PairingClaim;
 
-pub use items::Item;
-pub use tokens::TokenRecord;
+pub use items::{Item, ItemAuthor, ItemFilter, ItemSort};
+pub use tokens::{NewToken, TokenRecord, TokenScope};
+pub use undo_log::UndoAction;
+pub use voice_deletions::VoiceDeletionPrompt;
+pub use webhooks::Webhook;
+
+/// How long a connection waits for a competing writer before giving up.
+pub const DEFAULT_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
 
 pub fn prepare_sqlite_url(url: &str) -> String {
     if url.starts_with("sqlite:") && !url.contains("mode=") && !url.contains(":memory:") {
@@ -26,11 +62,36 @@ pub fn prepare_sqlite_url(url: &str) -> String {
     }
 }
 
-pub async fn connect_db(db_url: &str, pool_size: u32) -> Result<Pool<Sqlite>> {
-    tracing::debug!(db_url = %db_url, pool_size, "Connecting to database");
+/// Reject database URLs for backends the queries are not written for, so a
+/// `postgres://` `DB_URL` fails with a clear message instead of a parse error.
+fn ensure_sqlite_url(db_url: &str) -> Result<()> {
+    let scheme = db_url.split(':').next().unwrap_or_default();
+    if scheme.eq_ignore_ascii_case("sqlite") {
+        Ok(())
+    } else {
+        anyhow::bail!("Unsupported database scheme {scheme:?} in DB_URL; only sqlite: is supported")
+    }
+}
+
+/// Open a pool with WAL journaling so the bot and the API can read while
+/// another connection writes; writers wait up to `busy_timeout` for the lock.
+pub async fn connect_db(
+    db_url: &str,
+    pool_size: u32,
+    busy_timeout: Duration,
+) -> Result<Pool<Sqlite>> {
+    tracing::debug!(db_url = %db_url, pool_size, ?busy_timeout, "Connecting to database");
+    ensure_sqlite_url(db_url)?;
+    let options = SqliteConnectOptions::from_str(db_url)?
+        .journal_mode(SqliteJournalMode::Wal)
+        .synchronous(SqliteSynchronous::Normal)
+        .busy_timeout(busy_timeout)
+        .foreign_keys(true)
+        // `REGEXP` backs item search, which folds case beyond ASCII.
+        .with_regexp();
     Ok(SqlitePoolOptions::new()
         .max_connections(pool_size)
-        .connect(db_url)
+        .connect_with(options)
         .await?)
 }
 
@@ -62,6 +123,14 @@ mod tests {
         );
     }
 
+    #[test]
+    fn only_sqlite_urls_are_accepted() {
+        assert!(ensure_sqlite_url("sqlite:items.db").is_ok());
+        assert!(ensure_sqlite_url("sqlite::memory:").is_ok());
+        let err = ensure_sqlite_url("postgres://user@db/list").unwrap_err();
+        assert!(err.to_string().contains("\"postgres\""));
+    }
+
     #[test]
     fn prepare_sqlite_url_memory() {
         assert_eq!(prepare_sqlite_url("sqlite::memory:"), "sqlite::memory:");
@@ -0,0 +1,123 @@
+use super::Database;
+use anyhow::Result;
+use teloxide::types::{ChatId, Message};
+
+impl Database {
+    /// Hold photo message `msg` until the rest of album `group` arrives.
+    /// Returns whether it is the album's first photo, whose handler then
+    /// flushes the album.
+    pub async fn add_album_photo(
+        &self,
+        chat_id: ChatId,
+        group: &str,
+        msg: &Message,
+    ) -> Result<bool> {
+        tracing::debug!(
+            chat_id = chat_id.0,
+            group,
+            message_id = msg.id.0,
+            "Storing album photo"
+        );
+        let mut tx = self.begin_write().await?;
+        sqlx::query(
+            "INSERT OR REPLACE INTO pending_album_photos \
+             (chat_id, media_group_id, message_id, message) VALUES (?, ?, ?, ?)",
+        )
+        .bind(chat_id.0)
+        .bind(group)
+        .bind(msg.id.0)
+        .bind(serde_json::to_string(msg)?)
+        .execute(&mut *tx)
+        .await?;
+        let photos: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM pending_album_photos WHERE chat_id = ? AND media_group_id = ?",
+        )
+        .bind(chat_id.0)
+        .bind(group)
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(photos == 1)
+    }
+
+    /// Remove the photos of album `group` and return them in the order they
+    /// were sent.
+    pub async fn take_album_photos(&self, chat_id: ChatId, group: &str) -> Result<Vec<Message>> {
+        tracing::debug!(chat_id = chat_id.0, group, "Taking album photos");
+        let mut tx = self.begin_write().await?;
+        let rows: Vec<String> = sqlx::query_scalar(
+            "SELECT message FROM pending_album_photos \
+             WHERE chat_id = ? AND media_group_id = ? ORDER BY message_id",
+        )
+        .bind(chat_id.0)
+        .bind(group)
+        .fetch_all(&mut *tx)
+        .await?;
+        sqlx::query("DELETE FROM pending_album_photos WHERE chat_id = ? AND media_group_id = ?")
+            .bind(chat_id.0)
+            .bind(group)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        rows.iter()
+            .map(|row| serde_json::from_str(row).map_err(Into::into))
+            .collect()
+    }
+
+    /// Albums whose photos are still waiting to be parsed, e.g. because the
+    /// bot restarted while collecting them.
+    pub async fn pending_albums(&self) -> Result<Vec<(ChatId, String)>> {
+        tracing::trace!("Listing pending albums");
+        let rows: Vec<(i64, String)> = sqlx::query_as(
+            "SELECT DISTINCT chat_id, media_group_id FROM pending_album_photos \
+             ORDER BY chat_id, media_group_id",
+        )
+        .fetch_all(self.pool())
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(chat_id, group)| (ChatId(chat_id), group))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::util::init_test_db;
+
+    fn photo(message_id: i32) -> Message {
+        serde_json::from_value(serde_json::json!({
+            "message_id": message_id,
+            "date": 0,
+            "chat": {"id": 1, "type": "private"},
+            "media_group_id": "album-1",
+            "photo": [{"file_id": "f", "file_unique_id": "u", "width": 1, "height": 1}],
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn album_photos_are_kept_until_taken() -> Result<()> {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        assert!(db.add_album_photo(chat, "album-1", &photo(8)).await?);
+        assert!(!db.add_album_photo(chat, "album-1", &photo(7)).await?);
+        assert!(db.add_album_photo(ChatId(2), "album-1", &photo(9)).await?);
+        assert_eq!(
+            db.pending_albums().await?,
+            [
+                (chat, "album-1".to_string()),
+                (ChatId(2), "album-1".to_string())
+            ]
+        );
+
+        let photos = db.take_album_photos(chat, "album-1").await?;
+        let ids: Vec<i32> = photos.iter().map(|msg| msg.id.0).collect();
+        assert_eq!(ids, [7, 8]);
+        assert_eq!(photos[0].media_group_id(), Some("album-1"));
+        assert!(db.take_album_photos(chat, "album-1").await?.is_empty());
+        assert_eq!(db.pending_albums().await?.len(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,272 @@
+use super::chat_settings::AUTO_ARCHIVE_AFTER_HOURS;
+use super::items::ITEM_COLUMNS;
+use super::message_items::clear_message_items;
+use super::undo_log::{record_undo, UndoAction};
+use super::{Database, Item};
+use anyhow::Result;
+use teloxide::types::ChatId;
+
+/// A list snapshot stored when the list was archived.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchiveRecord {
+    pub id: i64,
+    pub archived_at: i64,
+    pub items: Vec<Item>,
+}
+
+impl Database {
+    /// Move every item of the chat's list into a new archive. Returns the
+    /// archived items, or an empty vector when there was nothing to archive.
+    pub async fn archive_items(&self, chat_id: ChatId, archived_at: i64) -> Result<Vec<Item>> {
+        let archive = self.create_archive(chat_id, archived_at).await?;
+        Ok(archive.map(|archive| archive.items).unwrap_or_default())
+    }
+
+    /// Like [`Database::archive_items`], but returns the stored snapshot with
+    /// its id, or `None` when there was nothing to archive.
+    pub async fn create_archive(
+        &self,
+        chat_id: ChatId,
+        archived_at: i64,
+    ) -> Result<Option<ArchiveRecord>> {
+        self.archive_matching(chat_id, archived_at, false).await
+    }
+
+    /// Like [`Database::archive_items`], but only moves checked items.
+    pub async fn archive_checked_items(
+        &self,
+        chat_id: ChatId,
+        archived_at: i64,
+    ) -> Result<Vec<Item>> {
+        let archive = self.create_checked_archive(chat_id, archived_at).await?;
+        Ok(archive.map(|archive| archive.items).unwrap_or_default())
+    }
+
+    /// Like [`Database::create_archive`], but only moves checked items.
+    pub async fn create_checked_archive(
+        &self,
+        chat_id: ChatId,
+        archived_at: i64,
+    ) -> Result<Option<ArchiveRecord>> {
+        self.archive_matching(chat_id, archived_at, true).await
+    }
+
+    async fn archive_matching(
+        &self,
+        chat_id: ChatId,
+        archived_at: i64,
+        only_done: bool,
+    ) -> Result<Option<ArchiveRecord>> {
+        let filter = if only_done {
+            " AND deleted_at IS NULL AND done"
+        } else {
+            " AND deleted_at IS NULL"
+        };
+        let mut tx = self.begin_write().await?;
+        let items: Vec<Item> = sqlx::query_as(&format!(
+            "SELECT {ITEM_COLUMNS} FROM items WHERE chat_id = ?{filter} ORDER BY position, id"
+        ))
+        .bind(chat_id.0)
+        .fetch_all(&mut *tx)
+        .await?;
+        if items.is_empty() {
+            return Ok(None);
+        }
+
+        let archive_id: i64 = sqlx::query_scalar(
+            "INSERT INTO archives (chat_id, archived_at, items) VALUES (?, ?, ?) RETURNING id",
+        )
+        .bind(chat_id.0)
+        .bind(archived_at)
+        .bind(serde_json::to_string(&items)?)
+        .fetch_one(&mut *tx)
+        .await?;
+        sqlx::query(&format!("DELETE FROM items WHERE chat_id = ?{filter}"))
+            .bind(chat_id.0)
+            .execute(&mut *tx)
+            .await?;
+        if !only_done {
+            clear_message_items(&mut tx, chat_id).await?;
+        }
+        record_undo(
+            &mut tx,
+            chat_id,
+            UndoAction::Archive,
+            &items,
+            Some(archive_id),
+        )
+        .await?;
+        tx.commit().await?;
+
+        tracing::debug!(
+            chat_id = chat_id.0,
+            archive_id,
+            count = items.len(),
+            only_done,
+            "Archived items"
+        );
+        Ok(Some(ArchiveRecord {
+            id: archive_id,
+            archived_at,
+            items,
+        }))
+    }
+
+    /// One archive of the chat; archives of other chats yield `None`.
+    pub async fn get_archive(&self, chat_id: ChatId, id: i64) -> Result<Option<ArchiveRecord>> {
+        tracing::trace!(chat_id = chat_id.0, archive_id = id, "Loading archive");
+        let row: Option<(i64, String)> =
+            sqlx::query_as("SELECT archived_at, items FROM archives WHERE chat_id = ? AND id = ?")
+                .bind(chat_id.0)
+                .bind(id)
+                .fetch_optional(self.pool())
+                .await?;
+        row.map(|(archived_at, items)| {
+            Ok(ArchiveRecord {
+                id,
+                archived_at,
+                items: serde_json::from_str(&items)?,
+            })
+        })
+        .transpose()
+    }
+
+    /// The chat's most recent archives, newest first.
+    pub async fn recent_archives(&self, chat_id: ChatId, limit: i64) -> Result<Vec<ArchiveRecord>> {
+        tracing::trace!(chat_id = chat_id.0, limit, "Listing archives");
+        let rows: Vec<(i64, i64, String)> = sqlx::query_as(
+            "SELECT id, archived_at, items FROM archives WHERE chat_id = ? \
+             ORDER BY archived_at DESC, id DESC LIMIT ?",
+        )
+        .bind(chat_id.0)
+        .bind(limit)
+        .fetch_all(self.pool())
+        .await?;
+        rows.into_iter()
+            .map(|(id, archived_at, items)| {
+                Ok(ArchiveRecord {
+                    id,
+                    archived_at,
+                    items: serde_json::from_str(&items)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Chats with auto-archive enabled whose checked items were all checked
+    /// off at least the configured number of hours before `now`. Items
+    /// checked before `done_at` was tracked count as old.
+    pub async fn chats_due_for_auto_archive(&self, now: i64) -> Result<Vec<ChatId>> {
+        let chats: Vec<i64> = sqlx::query_scalar(
+            "SELECT items.chat_id FROM items \
+             JOIN chat_settings ON chat_settings.chat_id = items.chat_id AND chat_settings.key = ? \
+             WHERE items.deleted_at IS NULL AND items.done \
+             AND CAST(chat_settings.value AS INTEGER) > 0 \
+             GROUP BY items.chat_id \
+             HAVING MAX(COALESCE(items.done_at, 0)) \
+             <= ? - MAX(CAST(chat_settings.value AS INTEGER)) * 3600",
+        )
+        .bind(AUTO_ARCHIVE_AFTER_HOURS)
+        .bind(now)
+        .fetch_all(self.pool())
+        .await?;
+        tracing::trace!(now, count = chats.len(), "Found chats due for auto-archive");
+        Ok(chats.into_iter().map(ChatId).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::util::init_test_db;
+
+    #[tokio::test]
+    async fn archiving_twice_keeps_both_snapshots() -> Result<()> {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_item_count(chat, "Milk").await?;
+        db.add_item_count(chat, "Milk").await?;
+        assert_eq!(db.archive_items(chat, 100).await?.len(), 1);
+        db.add_item_count(chat, "Eggs").await?;
+        db.archive_items(chat, 200).await?;
+        assert!(db.list_items(chat).await?.is_empty());
+
+        let archives = db.recent_archives(chat, 5).await?;
+        assert_eq!(archives.len(), 2);
+        assert_eq!(archives[0].archived_at, 200);
+        assert_eq!(archives[0].items[0].text, "Eggs");
+        assert_eq!(archives[1].items[0].label(), "2× Milk");
+        assert!(db.recent_archives(ChatId(2), 5).await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn archiving_checked_keeps_unchecked() -> Result<()> {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_items_count(chat, &["Milk".into(), "Eggs".into()])
+            .await?;
+        let items = db.list_items(chat).await?;
+        db.toggle_item_count(chat, items[0].id).await?;
+
+        let archived = db.archive_checked_items(chat, 100).await?;
+        assert_eq!(archived.len(), 1);
+        assert_eq!(archived[0].text, "Milk");
+        assert_eq!(db.list_items(chat).await?[0].text, "Eggs");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn auto_archive_waits_for_the_last_checked_item() -> Result<()> {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_items_count(chat, &["Milk".into(), "Eggs".into()])
+            .await?;
+        let items = db.list_items(chat).await?;
+        db.toggle_item_count(chat, items[0].id).await?;
+        let now = chrono::Utc::now().timestamp();
+        assert!(db.chats_due_for_auto_archive(now + 7200).await?.is_empty());
+
+        db.set_setting(chat, AUTO_ARCHIVE_AFTER_HOURS, "1").await?;
+        db.set_setting(ChatId(2), AUTO_ARCHIVE_AFTER_HOURS, "1")
+            .await?;
+        assert!(db.chats_due_for_auto_archive(now).await?.is_empty());
+        assert_eq!(db.chats_due_for_auto_archive(now + 7200).await?, [chat]);
+
+        sqlx::query("UPDATE items SET done_at = ? WHERE id = ?")
+            .bind(now + 3600)
+            .bind(items[0].id)
+            .execute(db.pool())
+            .await?;
+        db.toggle_item_count(chat, items[1].id).await?;
+        assert!(db.chats_due_for_auto_archive(now + 5400).await?.is_empty());
+        assert_eq!(db.chats_due_for_auto_archive(now + 7300).await?, [chat]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn created_archive_can_be_loaded_by_id() -> Result<()> {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_item_count(chat, "Milk").await?;
+
+        let archive = db.create_archive(chat, 100).await?.unwrap();
+        assert_eq!(archive.items[0].text, "Milk");
+        assert_eq!(
+            db.get_archive(chat, archive.id).await?,
+            Some(archive.clone())
+        );
+        assert_eq!(db.get_archive(ChatId(2), archive.id).await?, None);
+        assert_eq!(db.create_archive(chat, 200).await?, None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn empty_list_is_not_archived() -> Result<()> {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        assert!(db.archive_items(chat, 100).await?.is_empty());
+        assert!(db.recent_archives(chat, 5).await?.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+use super::items::ITEM_COLUMNS;
+use super::{Database, Item};
+use anyhow::Result;
+use teloxide::types::{ChatId, MessageId};
+
+/// A user claiming items through the `/assign` DM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssignSession {
+    pub chat_id: ChatId,
+    pub dm_message_id: Option<MessageId>,
+}
+
+impl Database {
+    /// Claim an item for `user_id`, or give it up when it is already theirs.
+    /// Returns whether the item is now assigned to the user, or `None` when
+    /// it is gone.
+    pub async fn toggle_item_assignment(
+        &self,
+        chat_id: ChatId,
+        id: i64,
+        user_id: i64,
+        name: &str,
+    ) -> Result<Option<bool>> {
+        tracing::debug!(
+            chat_id = chat_id.0,
+            item_id = id,
+            user_id,
+            "Toggling item assignment"
+        );
+        let assigned: Option<Option<i64>> = sqlx::query_scalar(
+            "UPDATE items SET \
+             assigned_to_name = CASE WHEN assigned_to_user_id IS ? THEN NULL ELSE ? END, \
+             assigned_to_user_id = CASE WHEN assigned_to_user_id IS ? THEN NULL ELSE ? END \
+             WHERE id = ? AND chat_id = ? AND deleted_at IS NULL \
+             RETURNING assigned_to_user_id",
+        )
+        .bind(user_id)
+        .bind(name)
+        .bind(user_id)
+        .bind(user_id)
+        .bind(id)
+        .bind(chat_id.0)
+        .fetch_optional(self.pool())
+        .await?;
+        Ok(assigned.map(|assignee| assignee == Some(user_id)))
+    }
+
+    /// The items of the chat's list assigned to `user_id`, in list order.
+    pub async fn list_items_assigned_to(&self, chat_id: ChatId, user_id: i64) -> Result<Vec<Item>> {
+        tracing::trace!(chat_id = chat_id.0, user_id, "Listing assigned items");
+        sqlx::query_as(&format!(
+            "SELECT {ITEM_COLUMNS} FROM items \
+             WHERE chat_id = ? AND assigned_to_user_id = ? AND deleted_at IS NULL \
+             ORDER BY position, id"
+        ))
+        .bind(chat_id.0)
+        .bind(user_id)
+        .fetch_all(self.pool())
+        .await
+        .map_err(Into::into)
+    }
+
+    pub async fn init_assign_session(&self, user_id: i64, chat_id: ChatId) -> Result<()> {
+        tracing::debug!(user_id, chat_id = chat_id.0, "Initializing assign session");
+        sqlx::query(
+            "INSERT INTO assign_session (user_id, chat_id) VALUES (?, ?) \
+             ON CONFLICT(user_id) DO UPDATE SET chat_id = excluded.chat_id, dm_message_id = NULL",
+        )
+        .bind(user_id)
+        .bind(chat_id.0)
+        .execute(self.pool())
+        .await?;
+        Ok(())
+    }
+
+    pub async fn set_assign_dm_message(&self, user_id: i64, message_id: MessageId) -> Result<()> {
+        tracing::debug!(
+            user_id,
+            message_id = message_id.0,
+            "Setting assign DM message"
+        );
+        sqlx::query("UPDATE assign_session SET dm_message_id = ? WHERE user_id = ?")
+            .bind(message_id.0)
+            .bind(user_id)
+            .execute(self.pool())
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_assign_session(&self, user_id: i64) -> Result<Option<AssignSession>> {
+        tracing::trace!(user_id, "Fetching assign session");
+        let row: Option<(i64, Option<i32>)> =
+            sqlx::query_as("SELECT chat_id, dm_message_id FROM assign_session WHERE user_id = ?")
+                .bind(user_id)
+                .fetch_optional(self.pool())
+                .await?;
+        Ok(row.map(|(chat_id, dm_message_id)| AssignSession {
+            chat_id: ChatId(chat_id),
+            dm_message_id: dm_message_id.map(MessageId),
+        }))
+    }
+
+    pub async fn clear_assign_session(&self, user_id: i64) -> Result<()> {
+        tracing::debug!(user_id, "Clearing assign session");
+        sqlx::query("DELETE FROM assign_session WHERE user_id = ?")
+            .bind(user_id)
+            .execute(self.pool())
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::util::init_test_db;
+
+    #[tokio::test]
+    async fn claiming_twice_gives_the_item_up() -> Result<()> {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_items_count(chat, &["Milk".into(), "Eggs".into(), "Tea".into()])
+            .await?;
+        let items = db.list_items(chat).await?;
+        let (milk, eggs, tea) = (items[0].id, items[1].id, items[2].id);
+
+        assert_eq!(
+            db.toggle_item_assignment(chat, tea, 5, "Ann").await?,
+            Some(true)
+        );
+        assert_eq!(
+            db.toggle_item_assignment(chat, milk, 5, "Ann").await?,
+            Some(true)
+        );
+        assert_eq!(
+            db.toggle_item_assignment(chat, eggs, 6, "Bo").await?,
+            Some(true)
+        );
+        let mine = db.list_items_assigned_to(chat, 5).await?;
+        let texts: Vec<&str> = mine.iter().map(|item| item.text.as_str()).collect();
+        assert_eq!(texts, ["Milk", "Tea"]);
+        assert_eq!(mine[0].assigned_to_name.as_deref(), Some("Ann"));
+
+        // Claiming someone else's item takes it over; a second tap lets go.
+        assert_eq!(
+            db.toggle_item_assignment(chat, eggs, 5, "Ann").await?,
+            Some(true)
+        );
+        assert_eq!(
+            db.toggle_item_assignment(chat, milk, 5, "Ann").await?,
+            Some(false)
+        );
+        let milk_item = db.get_item(chat, milk).await?.unwrap();
+        assert_eq!(milk_item.assigned_to_user_id, None);
+        assert_eq!(milk_item.assigned_to_name, None);
+        assert!(db.list_items_assigned_to(chat, 6).await?.is_empty());
+        assert!(db.list_items_assigned_to(ChatId(2), 5).await?.is_empty());
+
+        db.delete_item_count(chat, tea).await?;
+        assert_eq!(db.toggle_item_assignment(chat, tea, 5, "Ann").await?, None);
+        assert_eq!(db.list_items_assigned_to(chat, 5).await?.len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn assign_session_roundtrip() -> Result<()> {
+        let db = init_test_db().await;
+        db.init_assign_session(1, ChatId(10)).await?;
+        db.set_assign_dm_message(1, MessageId(4)).await?;
+        assert_eq!(
+            db.get_assign_session(1).await?,
+            Some(AssignSession {
+                chat_id: ChatId(10),
+                dm_message_id: Some(MessageId(4)),
+            })
+        );
+
+        db.init_assign_session(1, ChatId(11)).await?;
+        let session = db.get_assign_session(1).await?.unwrap();
+        assert_eq!(session.chat_id, ChatId(11));
+        assert_eq!(session.dm_message_id, None);
+
+        db.clear_assign_session(1).await?;
+        assert!(db.get_assign_session(1).await?.is_none());
+        Ok(())
+    }
+}
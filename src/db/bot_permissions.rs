@@ -0,0 +1,123 @@
+use super::Database;
+use anyhow::Result;
+use teloxide::types::ChatId;
+
+/// What the bot is allowed to do in a chat. Chats without a cached row are
+/// assumed capable until Telegram says otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BotCapabilities {
+    pub can_delete: bool,
+    pub can_pin: bool,
+}
+
+impl Default for BotCapabilities {
+    fn default() -> Self {
+        Self {
+            can_delete: true,
+            can_pin: true,
+        }
+    }
+}
+
+impl Database {
+    pub async fn bot_capabilities(&self, chat_id: ChatId) -> Result<BotCapabilities> {
+        tracing::trace!(chat_id = chat_id.0, "Loading bot capabilities");
+        let row: Option<(bool, bool)> =
+            sqlx::query_as("SELECT can_delete, can_pin FROM bot_permissions WHERE chat_id = ?")
+                .bind(chat_id.0)
+                .fetch_optional(self.pool())
+                .await?;
+        Ok(row
+            .map(|(can_delete, can_pin)| BotCapabilities {
+                can_delete,
+                can_pin,
+            })
+            .unwrap_or_default())
+    }
+
+    /// Cache the bot's rights. Regaining delete rights re-arms the one-time
+    /// notice for the next time they are lost.
+    pub async fn set_bot_capabilities(
+        &self,
+        chat_id: ChatId,
+        capabilities: BotCapabilities,
+        updated_at: i64,
+    ) -> Result<()> {
+        tracing::debug!(
+            chat_id = chat_id.0,
+            can_delete = capabilities.can_delete,
+            can_pin = capabilities.can_pin,
+            "Updating bot capabilities"
+        );
+        sqlx::query(
+            "INSERT INTO bot_permissions (chat_id, can_delete, can_pin, updated_at) VALUES (?, ?, ?, ?) \
+             ON CONFLICT(chat_id) DO UPDATE SET can_delete = excluded.can_delete, \
+             can_pin = excluded.can_pin, updated_at = excluded.updated_at, \
+             delete_notice_sent = CASE WHEN excluded.can_delete THEN 0 ELSE delete_notice_sent END",
+        )
+        .bind(chat_id.0)
+        .bind(capabilities.can_delete)
+        .bind(capabilities.can_pin)
+        .bind(updated_at)
+        .execute(self.pool())
+        .await?;
+        Ok(())
+    }
+
+    /// Claim the missing-delete-rights notice. Returns `true` only for the
+    /// first caller after the rights were lost.
+    pub async fn claim_delete_notice(&self, chat_id: ChatId) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE bot_permissions SET delete_notice_sent = 1 \
+             WHERE chat_id = ? AND can_delete = 0 AND delete_notice_sent = 0",
+        )
+        .bind(chat_id.0)
+        .execute(self.pool())
+        .await?;
+        let claimed = result.rows_affected() == 1;
+        tracing::trace!(chat_id = chat_id.0, claimed, "Claiming delete notice");
+        Ok(claimed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::util::init_test_db;
+
+    const NO_DELETE: BotCapabilities = BotCapabilities {
+        can_delete: false,
+        can_pin: false,
+    };
+
+    #[tokio::test]
+    async fn capabilities_default_when_unknown() -> Result<()> {
+        let db = init_test_db().await;
+        assert_eq!(
+            db.bot_capabilities(ChatId(-1)).await?,
+            BotCapabilities::default()
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn delete_notice_claimed_once_until_rights_return() -> Result<()> {
+        let db = init_test_db().await;
+        let chat = ChatId(-1);
+        assert!(!db.claim_delete_notice(chat).await?);
+
+        db.set_bot_capabilities(chat, NO_DELETE, 1).await?;
+        assert_eq!(db.bot_capabilities(chat).await?, NO_DELETE);
+        assert!(db.claim_delete_notice(chat).await?);
+        assert!(!db.claim_delete_notice(chat).await?);
+
+        db.set_bot_capabilities(chat, NO_DELETE, 2).await?;
+        assert!(!db.claim_delete_notice(chat).await?);
+
+        db.set_bot_capabilities(chat, BotCapabilities::default(), 3)
+            .await?;
+        db.set_bot_capabilities(chat, NO_DELETE, 4).await?;
+        assert!(db.claim_delete_notice(chat).await?);
+        Ok(())
+    }
+}
@@ -0,0 +1,290 @@
+use super::Database;
+use anyhow::Result;
+use teloxide::types::ChatId;
+
+use super::DuplicateAction;
+use crate::ai::prompts::{PromptKind, Prompts};
+use crate::list_style::ListStyle;
+
+pub const VOICE_FEEDBACK: &str = "voice_feedback";
+pub const LIST_STYLE: &str = "list_style";
+pub const TIDY_USER_MESSAGES: &str = "tidy_user_messages";
+pub const DUPLICATE_DEFAULT: &str = "duplicate_default";
+pub const SHOW_AUTHORS: &str = "show_authors";
+pub const CHECKED_LAST: &str = "checked_last";
+pub const AUTO_ARCHIVE_AFTER_HOURS: &str = "auto_archive_after_hours";
+pub const RECEIPT_MODE: &str = "receipt_mode";
+pub const STRICT_MODE: &str = "strict_mode";
+pub const SKIP_LISTED_ITEMS: &str = "skip_listed_items";
+/// Marker row written once a private chat has seen the onboarding tour.
+pub const ONBOARDED: &str = "onboarded";
+
+/// Per-chat preferences. Missing rows fall back to the defaults below so
+/// chats that never touched a setting keep the original behaviour.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatSettings {
+    pub voice_feedback: bool,
+    pub list_style: ListStyle,
+    pub tidy_user_messages: bool,
+    /// Applied when nobody answers a checked-off duplicate prompt in time.
+    pub duplicate_default: DuplicateAction,
+    /// Append the first name of whoever added each item.
+    pub show_authors: bool,
+    /// Render checked items below the unchecked ones.
+    pub checked_last: bool,
+    /// Archive checked items once none was checked off for this many hours.
+    pub auto_archive_after_hours: Option<u32>,
+    /// Prompts set with `/prompt`, overriding the configured ones.
+    pub prompts: Prompts,
+    /// Read photos as receipts, keeping the price of each item.
+    pub receipt_mode: bool,
+    /// Ignore text messages that read as conversation instead of items.
+    pub strict_mode: bool,
+    /// Leave out AI-parsed items that are already on the list unchecked.
+    pub skip_listed_items: bool,
+}
+
+impl Default for ChatSettings {
+    fn default() -> Self {
+        Self {
+            voice_feedback: true,
+            list_style: ListStyle::default(),
+            tidy_user_messages: false,
+            duplicate_default: DuplicateAction::default(),
+            show_authors: false,
+            checked_last: false,
+            auto_archive_after_hours: None,
+            prompts: Prompts::default(),
+            receipt_mode: false,
+            strict_mode: false,
+            skip_listed_items: true,
+        }
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+impl ChatSettings {
+    fn apply(&mut self, key: &str, value: &str) {
+        match key {
+            VOICE_FEEDBACK => {
+                if let Some(flag) = parse_bool(value) {
+                    self.voice_feedback = flag;
+                }
+            }
+            LIST_STYLE => {
+                if let Some(style) = ListStyle::from_setting(value) {
+                    self.list_style = style;
+                }
+            }
+            TIDY_USER_MESSAGES => {
+                if let Some(flag) = parse_bool(value) {
+                    self.tidy_user_messages = flag;
+                }
+            }
+            DUPLICATE_DEFAULT => {
+                if let Some(action) = DuplicateAction::parse(value) {
+                    self.duplicate_default = action;
+                }
+            }
+            SHOW_AUTHORS => {
+                if let Some(flag) = parse_bool(value) {
+                    self.show_authors = flag;
+                }
+            }
+            CHECKED_LAST => {
+                if let Some(flag) = parse_bool(value) {
+                    self.checked_last = flag;
+                }
+            }
+            AUTO_ARCHIVE_AFTER_HOURS => {
+                if let Ok(hours) = value.parse::<u32>() {
+                    self.auto_archive_after_hours = (hours > 0).then_some(hours);
+                }
+            }
+            RECEIPT_MODE => {
+                if let Some(flag) = parse_bool(value) {
+                    self.receipt_mode = flag;
+                }
+            }
+            STRICT_MODE => {
+                if let Some(flag) = parse_bool(value) {
+                    self.strict_mode = flag;
+                }
+            }
+            SKIP_LISTED_ITEMS => {
+                if let Some(flag) = parse_bool(value) {
+                    self.skip_listed_items = flag;
+                }
+            }
+            ONBOARDED => {}
+            _ => match PromptKind::from_setting_key(key) {
+                Some(kind) => self.prompts.set(kind, value),
+                None => tracing::trace!(key, "Ignoring unknown chat setting"),
+            },
+        }
+    }
+}
+
+impl Database {
+    pub async fn get_settings(&self, chat_id: ChatId) -> Result<ChatSettings> {
+        tracing::trace!(chat_id = chat_id.0, "Loading chat settings");
+        let rows: Vec<(String, String)> =
+            sqlx::query_as("SELECT key, value FROM chat_settings WHERE chat_id = ?")
+                .bind(chat_id.0)
+                .fetch_all(self.pool())
+                .await?;
+        let mut settings = ChatSettings::default();
+        for (key, value) in rows {
+            settings.apply(&key, &value);
+        }
+        Ok(settings)
+    }
+
+    /// Whether any setting has ever been stored for the chat.
+    pub async fn has_settings(&self, chat_id: ChatId) -> Result<bool> {
+        let exists: bool =
+            sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM chat_settings WHERE chat_id = ?)")
+                .bind(chat_id.0)
+                .fetch_one(self.pool())
+                .await?;
+        tracing::trace!(chat_id = chat_id.0, exists, "Checked for chat settings");
+        Ok(exists)
+    }
+
+    pub async fn set_setting(&self, chat_id: ChatId, key: &str, value: &str) -> Result<()> {
+        tracing::debug!(chat_id = chat_id.0, key, value, "Updating chat setting");
+        sqlx::query(
+            "INSERT INTO chat_settings (chat_id, key, value) VALUES (?, ?, ?) \
+             ON CONFLICT(chat_id, key) DO UPDATE SET value = excluded.value",
+        )
+        .bind(chat_id.0)
+        .bind(key)
+        .bind(value)
+        .execute(self.pool())
+        .await?;
+        Ok(())
+    }
+
+    /// Forget a setting so it falls back to its default. Returns whether it
+    /// was set.
+    pub async fn clear_setting(&self, chat_id: ChatId, key: &str) -> Result<bool> {
+        let cleared = sqlx::query("DELETE FROM chat_settings WHERE chat_id = ? AND key = ?")
+            .bind(chat_id.0)
+            .bind(key)
+            .execute(self.pool())
+            .await?
+            .rows_affected();
+        tracing::debug!(chat_id = chat_id.0, key, cleared, "Cleared chat setting");
+        Ok(cleared > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::util::init_test_db;
+
+    #[tokio::test]
+    async fn settings_default_when_missing() -> Result<()> {
+        let db = init_test_db().await;
+        let settings = db.get_settings(ChatId(1)).await?;
+        assert_eq!(settings, ChatSettings::default());
+        assert!(settings.voice_feedback);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn settings_roundtrip_per_chat() -> Result<()> {
+        let db = init_test_db().await;
+        db.set_setting(ChatId(1), VOICE_FEEDBACK, "false").await?;
+        db.set_setting(ChatId(1), VOICE_FEEDBACK, "false").await?;
+
+        assert!(!db.get_settings(ChatId(1)).await?.voice_feedback);
+        assert!(db.get_settings(ChatId(2)).await?.voice_feedback);
+
+        db.set_setting(ChatId(1), VOICE_FEEDBACK, "true").await?;
+        assert!(db.get_settings(ChatId(1)).await?.voice_feedback);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn has_settings_tracks_first_row() -> Result<()> {
+        let db = init_test_db().await;
+        assert!(!db.has_settings(ChatId(1)).await?);
+        db.set_setting(ChatId(1), ONBOARDED, "true").await?;
+        assert!(db.has_settings(ChatId(1)).await?);
+        assert!(!db.has_settings(ChatId(2)).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn settings_ignore_malformed_values() -> Result<()> {
+        let db = init_test_db().await;
+        db.set_setting(ChatId(1), VOICE_FEEDBACK, "maybe").await?;
+        db.set_setting(ChatId(1), LIST_STYLE, "custom:only-one")
+            .await?;
+        db.set_setting(ChatId(1), "unknown", "true").await?;
+        assert_eq!(db.get_settings(ChatId(1)).await?, ChatSettings::default());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn settings_resolve_list_style() -> Result<()> {
+        let db = init_test_db().await;
+        db.set_setting(ChatId(1), LIST_STYLE, "minimal").await?;
+        db.set_setting(ChatId(2), LIST_STYLE, "custom:○ ● ★")
+            .await?;
+
+        let minimal = db.get_settings(ChatId(1)).await?.list_style;
+        assert_eq!(minimal, crate::list_style::ListStylePreset::Minimal.style());
+        let custom = db.get_settings(ChatId(2)).await?.list_style;
+        assert_eq!(custom.checked, "●");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn auto_archive_is_off_until_hours_are_set() -> Result<()> {
+        let db = init_test_db().await;
+        assert_eq!(
+            db.get_settings(ChatId(1)).await?.auto_archive_after_hours,
+            None
+        );
+        db.set_setting(ChatId(1), AUTO_ARCHIVE_AFTER_HOURS, "12")
+            .await?;
+        assert_eq!(
+            db.get_settings(ChatId(1)).await?.auto_archive_after_hours,
+            Some(12)
+        );
+        db.set_setting(ChatId(1), AUTO_ARCHIVE_AFTER_HOURS, "0")
+            .await?;
+        assert_eq!(
+            db.get_settings(ChatId(1)).await?.auto_archive_after_hours,
+            None
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn prompt_overrides_are_stored_and_cleared() -> Result<()> {
+        let db = init_test_db().await;
+        let key = PromptKind::Photo.setting_key();
+        db.set_setting(ChatId(1), key, "Read the receipt").await?;
+        let prompts = db.get_settings(ChatId(1)).await?.prompts;
+        assert_eq!(
+            prompts.override_of(PromptKind::Photo),
+            Some("Read the receipt")
+        );
+
+        assert!(db.clear_setting(ChatId(1), key).await?);
+        assert!(!db.clear_setting(ChatId(1), key).await?);
+        assert_eq!(db.get_settings(ChatId(1)).await?, ChatSettings::default());
+        Ok(())
+    }
+}
@@ -1,14 +1,32 @@
 use super::Database;
 use anyhow::Result;
-use teloxide::types::{ChatId, MessageId};
+use teloxide::types::{ChatId, MessageId, ThreadId};
 
 #[derive(sqlx::FromRow)]
 struct ChatState {
-    last_list_message_id: i32,
+    last_list_message_id: Option<i64>,
+}
+
+/// Telegram output that could not be delivered after the database was
+/// already updated, kept until a retry gets it through.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PendingRefresh {
+    /// The list message needs to be sent again.
+    pub list: bool,
+    /// Confirmation text that never reached the chat.
+    pub notice: Option<String>,
+}
+
+impl PendingRefresh {
+    pub fn is_empty(&self) -> bool {
+        !self.list && self.notice.is_none()
+    }
 }
 
 impl Database {
-    pub async fn get_last_list_message_id(&self, chat_id: ChatId) -> Result<Option<i32>> {
+    /// The tracked list message. Stored as a 64-bit integer because the Bot
+    /// API allows message ids beyond `i32`.
+    pub async fn get_last_list_message_id(&self, chat_id: ChatId) -> Result<Option<i64>> {
         tracing::trace!(chat_id = chat_id.0, "Fetching last list message id");
         let result = sqlx::query_as::<_, ChatState>(
             "SELECT last_list_message_id FROM chat_state WHERE chat_id = ?",
@@ -16,17 +34,17 @@ impl Database {
         .bind(chat_id.0)
         .fetch_optional(self.pool())
         .await?;
-        Ok(result.map(|r| r.last_list_message_id))
+        Ok(result.and_then(|r| r.last_list_message_id))
     }
 
     pub async fn update_last_list_message_id(
         &self,
         chat_id: ChatId,
-        message_id: MessageId,
+        message_id: i64,
     ) -> Result<()> {
         tracing::debug!(
             chat_id = chat_id.0,
-            message_id = message_id.0,
+            message_id,
             "Updating last list message id",
         );
         sqlx::query(
@@ -34,7 +52,7 @@ impl Database {
              ON CONFLICT(chat_id) DO UPDATE SET last_list_message_id = excluded.last_list_message_id",
         )
         .bind(chat_id.0)
-        .bind(message_id.0)
+        .bind(message_id)
         .execute(self.pool())
         .await?;
         Ok(())
@@ -42,10 +60,279 @@ impl Database {
 
     pub async fn clear_last_list_message_id(&self, chat_id: ChatId) -> Result<()> {
         tracing::debug!(chat_id = chat_id.0, "Clearing last list message id");
-        sqlx::query("DELETE FROM chat_state WHERE chat_id = ?")
+        sqlx::query("UPDATE chat_state SET last_list_message_id = NULL WHERE chat_id = ?")
             .bind(chat_id.0)
             .execute(self.pool())
             .await?;
         Ok(())
     }
+
+    /// Clear the stored id only if it still points at `message_id`, so a
+    /// list sent in the meantime stays tracked. Returns whether it was cleared.
+    pub async fn clear_last_list_message_id_if(
+        &self,
+        chat_id: ChatId,
+        message_id: i64,
+    ) -> Result<bool> {
+        tracing::debug!(
+            chat_id = chat_id.0,
+            message_id,
+            "Clearing last list message id if unchanged"
+        );
+        let result = sqlx::query(
+            "UPDATE chat_state SET last_list_message_id = NULL \
+             WHERE chat_id = ? AND last_list_message_id = ?",
+        )
+        .bind(chat_id.0)
+        .bind(message_id)
+        .execute(self.pool())
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Merge `pending` into what is already waiting for the chat.
+    pub async fn mark_refresh_pending(
+        &self,
+        chat_id: ChatId,
+        pending: &PendingRefresh,
+    ) -> Result<()> {
+        tracing::debug!(chat_id = chat_id.0, ?pending, "Marking refresh pending");
+        sqlx::query(
+            "INSERT INTO chat_state (chat_id, refresh_pending, pending_notice) VALUES (?, ?, ?) \
+             ON CONFLICT(chat_id) DO UPDATE SET \
+             refresh_pending = chat_state.refresh_pending OR excluded.refresh_pending, \
+             pending_notice = COALESCE(excluded.pending_notice, chat_state.pending_notice)",
+        )
+        .bind(chat_id.0)
+        .bind(pending.list)
+        .bind(pending.notice.as_deref())
+        .execute(self.pool())
+        .await?;
+        Ok(())
+    }
+
+    /// Clear and return whatever is pending for the chat.
+    pub async fn take_pending_refresh(&self, chat_id: ChatId) -> Result<PendingRefresh> {
+        let mut tx = self.begin_write().await?;
+        let row: Option<(bool, Option<String>)> = sqlx::query_as(
+            "SELECT refresh_pending, pending_notice FROM chat_state WHERE chat_id = ?",
+        )
+        .bind(chat_id.0)
+        .fetch_optional(&mut *tx)
+        .await?;
+        sqlx::query(
+            "UPDATE chat_state SET refresh_pending = 0, pending_notice = NULL WHERE chat_id = ?",
+        )
+        .bind(chat_id.0)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        let pending = row
+            .map(|(list, notice)| PendingRefresh { list, notice })
+            .unwrap_or_default();
+        tracing::trace!(chat_id = chat_id.0, ?pending, "Took pending refresh");
+        Ok(pending)
+    }
+
+    /// Chats with undelivered output, used to resume retries after a restart.
+    pub async fn pending_refresh_chats(&self) -> Result<Vec<ChatId>> {
+        let ids: Vec<i64> = sqlx::query_scalar(
+            "SELECT chat_id FROM chat_state WHERE refresh_pending OR pending_notice IS NOT NULL",
+        )
+        .fetch_all(self.pool())
+        .await?;
+        Ok(ids.into_iter().map(ChatId).collect())
+    }
+
+    /// Zero-based page of the list message the chat is looking at.
+    pub async fn list_page(&self, chat_id: ChatId) -> Result<usize> {
+        tracing::trace!(chat_id = chat_id.0, "Fetching list page");
+        let page: Option<i64> =
+            sqlx::query_scalar("SELECT list_page FROM chat_state WHERE chat_id = ?")
+                .bind(chat_id.0)
+                .fetch_optional(self.pool())
+                .await?;
+        Ok(page.map_or(0, |page| usize::try_from(page).unwrap_or(0)))
+    }
+
+    pub async fn set_list_page(&self, chat_id: ChatId, page: usize) -> Result<()> {
+        tracing::debug!(chat_id = chat_id.0, page, "Setting list page");
+        sqlx::query(
+            "INSERT INTO chat_state (chat_id, list_page) VALUES (?, ?) \
+             ON CONFLICT(chat_id) DO UPDATE SET list_page = excluded.list_page",
+        )
+        .bind(chat_id.0)
+        .bind(i64::try_from(page).unwrap_or(i64::MAX))
+        .execute(self.pool())
+        .await?;
+        Ok(())
+    }
+
+    /// The forum topic the chat's list and notices are sent to; `None` for
+    /// chats without topics and for the General topic.
+    pub async fn chat_thread(&self, chat_id: ChatId) -> Result<Option<ThreadId>> {
+        tracing::trace!(chat_id = chat_id.0, "Fetching chat thread");
+        let thread_id: Option<Option<i32>> =
+            sqlx::query_scalar("SELECT thread_id FROM chat_state WHERE chat_id = ?")
+                .bind(chat_id.0)
+                .fetch_optional(self.pool())
+                .await?;
+        Ok(thread_id.flatten().map(|id| ThreadId(MessageId(id))))
+    }
+
+    pub async fn set_chat_thread(
+        &self,
+        chat_id: ChatId,
+        thread_id: Option<ThreadId>,
+    ) -> Result<()> {
+        let thread_id = thread_id.map(|ThreadId(MessageId(id))| id);
+        tracing::debug!(chat_id = chat_id.0, thread_id, "Setting chat thread");
+        sqlx::query(
+            "INSERT INTO chat_state (chat_id, thread_id) VALUES (?, ?) \
+             ON CONFLICT(chat_id) DO UPDATE SET thread_id = excluded.thread_id",
+        )
+        .bind(chat_id.0)
+        .bind(thread_id)
+        .execute(self.pool())
+        .await?;
+        Ok(())
+    }
+
+    /// Counter that grows with every change to the chat's list; 0 until the
+    /// first change. Triggers on `items` bump it in the writing transaction,
+    /// once per row written.
+    pub async fn list_revision(&self, chat_id: ChatId) -> Result<i64> {
+        tracing::trace!(chat_id = chat_id.0, "Fetching list revision");
+        let revision: Option<i64> =
+            sqlx::query_scalar("SELECT revision FROM chat_state WHERE chat_id = ?")
+                .bind(chat_id.0)
+                .fetch_optional(self.pool())
+                .await?;
+        Ok(revision.unwrap_or(0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::util::init_test_db;
+
+    #[tokio::test]
+    async fn list_revision_counts_changes() -> Result<()> {
+        let db = init_test_db().await;
+        let chat = ChatId(3);
+        assert_eq!(db.list_revision(chat).await?, 0);
+        db.update_last_list_message_id(chat, 9).await?;
+        let item = db.add_item_returning(chat, "Milk", None).await?;
+        let added = db.list_revision(chat).await?;
+        assert!(added > 0);
+        db.toggle_item_count(chat, item.id).await?;
+        let toggled = db.list_revision(chat).await?;
+        assert!(toggled > added);
+        db.delete_item_count(chat, item.id).await?;
+        let deleted = db.list_revision(chat).await?;
+        assert!(deleted > toggled);
+        // Purging the trash does not change the list.
+        db.purge_deleted(i64::MAX).await?;
+        assert_eq!(db.list_revision(chat).await?, deleted);
+        assert_eq!(db.list_revision(ChatId(4)).await?, 0);
+        assert_eq!(db.get_last_list_message_id(chat).await?, Some(9));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn chat_thread_round_trips() -> Result<()> {
+        let db = init_test_db().await;
+        let chat = ChatId(-100);
+        assert_eq!(db.chat_thread(chat).await?, None);
+        db.update_last_list_message_id(chat, 9).await?;
+        db.set_chat_thread(chat, Some(ThreadId(MessageId(12))))
+            .await?;
+        assert_eq!(db.chat_thread(chat).await?, Some(ThreadId(MessageId(12))));
+        db.set_chat_thread(chat, None).await?;
+        assert_eq!(db.chat_thread(chat).await?, None);
+        assert_eq!(db.get_last_list_message_id(chat).await?, Some(9));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn pending_refresh_merges_and_clears() -> Result<()> {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.update_last_list_message_id(chat, 5).await?;
+        db.mark_refresh_pending(
+            chat,
+            &PendingRefresh {
+                list: false,
+                notice: Some("Archived".into()),
+            },
+        )
+        .await?;
+        db.mark_refresh_pending(
+            chat,
+            &PendingRefresh {
+                list: true,
+                notice: None,
+            },
+        )
+        .await?;
+        assert_eq!(db.pending_refresh_chats().await?, vec![chat]);
+        assert_eq!(db.get_last_list_message_id(chat).await?, Some(5));
+
+        let pending = db.take_pending_refresh(chat).await?;
+        assert_eq!(
+            pending,
+            PendingRefresh {
+                list: true,
+                notice: Some("Archived".into()),
+            }
+        );
+        assert!(db.take_pending_refresh(chat).await?.is_empty());
+        assert!(db.pending_refresh_chats().await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn pending_notice_survives_cleared_list() -> Result<()> {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.update_last_list_message_id(chat, 5).await?;
+        db.clear_last_list_message_id(chat).await?;
+        db.mark_refresh_pending(
+            chat,
+            &PendingRefresh {
+                list: false,
+                notice: Some("Archived".into()),
+            },
+        )
+        .await?;
+        assert_eq!(db.get_last_list_message_id(chat).await?, None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn list_message_id_round_trips_beyond_i32() -> Result<()> {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        let big = i64::from(i32::MAX) + 42;
+        db.update_last_list_message_id(chat, big).await?;
+        assert_eq!(db.get_last_list_message_id(chat).await?, Some(big));
+
+        assert!(!db.clear_last_list_message_id_if(chat, 42).await?);
+        assert!(db.clear_last_list_message_id_if(chat, big).await?);
+        assert_eq!(db.get_last_list_message_id(chat).await?, None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn list_page_defaults_to_first() -> Result<()> {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        assert_eq!(db.list_page(chat).await?, 0);
+        db.update_last_list_message_id(chat, 5).await?;
+        db.set_list_page(chat, 2).await?;
+        assert_eq!(db.list_page(chat).await?, 2);
+        assert_eq!(db.get_last_list_message_id(chat).await?, Some(5));
+        Ok(())
+    }
 }
@@ -1,4 +1,4 @@
-use sqlx::{Pool, Sqlite};
+use sqlx::{Pool, Sqlite, Transaction};
 
 #[derive(Clone)]
 pub struct Database {
@@ -13,6 +13,19 @@ impl Database {
     pub fn pool(&self) -> &Pool<Sqlite> {
         &self.pool
     }
+
+    /// Run a trivial query to check the pool can still reach the database.
+    pub async fn ping(&self) -> anyhow::Result<()> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    /// Start a transaction that takes the write lock up front. A deferred
+    /// transaction that reads first cannot wait for a busy writer in WAL
+    /// mode and fails with "database is locked" instead.
+    pub(crate) async fn begin_write(&self) -> sqlx::Result<Transaction<'static, Sqlite>> {
+        self.pool.begin_with("BEGIN IMMEDIATE").await
+    }
 }
 
 impl std::ops::Deref for Database {
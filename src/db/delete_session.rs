@@ -5,11 +5,14 @@ use teloxide::types::{ChatId, MessageId};
 
 #[derive(sqlx::FromRow)]
 struct DeleteSessionRow {
+    user_id: i64,
     chat_id: i64,
     selected: String,
     notice_chat_id: Option<i64>,
     notice_message_id: Option<i32>,
     dm_message_id: Option<i32>,
+    page: i64,
+    created_at: i64,
 }
 
 pub struct DeleteSession {
@@ -17,8 +20,44 @@ pub struct DeleteSession {
     pub selected: HashSet<i64>,
     pub notice: Option<(ChatId, MessageId)>,
     pub dm_message_id: Option<MessageId>,
+    /// Zero-based page of the selection keyboard.
+    pub page: usize,
+    /// Unix seconds when the panel was opened.
+    pub created_at: i64,
 }
 
+impl DeleteSession {
+    /// Chat holding the selection panel: the owner's private chat, or the
+    /// list's chat itself when the panel was posted there because the DM
+    /// could not be sent. Only DM panels come with a notice in the chat.
+    pub fn panel_chat(&self, user_id: i64) -> ChatId {
+        if self.notice.is_some() {
+            ChatId(user_id)
+        } else {
+            self.chat_id
+        }
+    }
+}
+
+impl DeleteSessionRow {
+    fn into_session(self) -> DeleteSession {
+        let notice = match (self.notice_chat_id, self.notice_message_id) {
+            (Some(c), Some(m)) => Some((ChatId(c), MessageId(m))),
+            _ => None,
+        };
+        DeleteSession {
+            chat_id: ChatId(self.chat_id),
+            selected: parse_selected(&self.selected),
+            notice,
+            dm_message_id: self.dm_message_id.map(MessageId),
+            page: usize::try_from(self.page).unwrap_or(0),
+            created_at: self.created_at,
+        }
+    }
+}
+
+const SESSION_COLUMNS: &str = "user_id, chat_id, selected, notice_chat_id, notice_message_id, dm_message_id, page, created_at";
+
 fn parse_selected(s: &str) -> HashSet<i64> {
     s.split(',').filter_map(|p| p.parse::<i64>().ok()).collect()
 }
@@ -33,14 +72,16 @@ fn join_selected(set: &HashSet<i64>) -> String {
 }
 
 impl Database {
+    /// Start a fresh session for `user_id`, stamped with the current time.
     pub async fn init_delete_session(&self, user_id: i64, chat_id: ChatId) -> Result<()> {
         tracing::debug!(user_id, chat_id = chat_id.0, "Initializing delete session");
         sqlx::query(
-            "INSERT INTO delete_session (user_id, chat_id, selected) VALUES (?, ?, '') \
-             ON CONFLICT(user_id) DO UPDATE SET chat_id=excluded.chat_id, selected='', notice_chat_id=NULL, notice_message_id=NULL, dm_message_id=NULL",
+            "INSERT INTO delete_session (user_id, chat_id, selected, created_at) VALUES (?, ?, '', ?) \
+             ON CONFLICT(user_id) DO UPDATE SET chat_id=excluded.chat_id, selected='', notice_chat_id=NULL, notice_message_id=NULL, dm_message_id=NULL, page=0, created_at=excluded.created_at",
         )
         .bind(user_id)
         .bind(chat_id.0)
+        .bind(chrono::Utc::now().timestamp())
         .execute(self.pool())
         .await?;
         Ok(())
@@ -98,28 +139,63 @@ impl Database {
         Ok(())
     }
 
+    pub async fn set_delete_page(&self, user_id: i64, page: usize) -> Result<()> {
+        tracing::debug!(user_id, page, "Setting delete page");
+        sqlx::query("UPDATE delete_session SET page = ? WHERE user_id = ?")
+            .bind(i64::try_from(page).unwrap_or(i64::MAX))
+            .bind(user_id)
+            .execute(self.pool())
+            .await?;
+        Ok(())
+    }
+
     pub async fn get_delete_session(&self, user_id: i64) -> Result<Option<DeleteSession>> {
         tracing::trace!(user_id, "Fetching delete session");
-        if let Some(row) = sqlx::query_as::<_, DeleteSessionRow>(
-            "SELECT chat_id, selected, notice_chat_id, notice_message_id, dm_message_id FROM delete_session WHERE user_id = ?",
-        )
+        let row = sqlx::query_as::<_, DeleteSessionRow>(&format!(
+            "SELECT {SESSION_COLUMNS} FROM delete_session WHERE user_id = ?"
+        ))
         .bind(user_id)
         .fetch_optional(self.pool())
-        .await?
-        {
-            let notice = match (row.notice_chat_id, row.notice_message_id) {
-                (Some(c), Some(m)) => Some((ChatId(c), MessageId(m))),
-                _ => None,
-            };
-            Ok(Some(DeleteSession {
-                chat_id: ChatId(row.chat_id),
-                selected: parse_selected(&row.selected),
-                notice,
-                dm_message_id: row.dm_message_id.map(MessageId),
-            }))
-        } else {
-            Ok(None)
-        }
+        .await?;
+        Ok(row.map(DeleteSessionRow::into_session))
+    }
+
+    /// Sessions with an open panel deleting from `chat_id`, each with the
+    /// user it belongs to.
+    pub async fn sessions_for_chat(&self, chat_id: ChatId) -> Result<Vec<(i64, DeleteSession)>> {
+        tracing::trace!(chat_id = chat_id.0, "Fetching delete sessions of chat");
+        let rows = sqlx::query_as::<_, DeleteSessionRow>(&format!(
+            "SELECT {SESSION_COLUMNS} FROM delete_session \
+             WHERE chat_id = ? AND dm_message_id IS NOT NULL ORDER BY user_id"
+        ))
+        .bind(chat_id.0)
+        .fetch_all(self.pool())
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.user_id, row.into_session()))
+            .collect())
+    }
+
+    /// The user whose session posted panel `message_id` in `chat_id`.
+    pub async fn delete_panel_owner(
+        &self,
+        chat_id: ChatId,
+        message_id: MessageId,
+    ) -> Result<Option<i64>> {
+        tracing::trace!(
+            chat_id = chat_id.0,
+            message_id = message_id.0,
+            "Looking up delete panel owner"
+        );
+        let owner = sqlx::query_scalar(
+            "SELECT user_id FROM delete_session WHERE chat_id = ? AND dm_message_id = ?",
+        )
+        .bind(chat_id.0)
+        .bind(message_id.0)
+        .fetch_optional(self.pool())
+        .await?;
+        Ok(owner)
     }
 
     pub async fn clear_delete_session(&self, user_id: i64) -> Result<()> {
@@ -194,8 +270,10 @@ mod tests {
         let chat_a = ChatId(10);
         db.init_delete_session(user, chat_a).await?;
 
+        let before = chrono::Utc::now().timestamp();
         let mut session = db.get_delete_session(user).await?.unwrap();
         assert_eq!(session.chat_id, chat_a);
+        assert!(session.created_at > 0 && session.created_at <= before);
         assert!(session.selected.is_empty());
         assert!(session.notice.is_none());
         assert!(session.dm_message_id.is_none());
@@ -215,9 +293,54 @@ mod tests {
         assert_eq!(session.notice, Some((ChatId(20), MessageId(3))));
         assert_eq!(session.dm_message_id, Some(MessageId(4)));
 
+        assert_eq!(session.page, 0);
+        db.set_delete_page(user, 2).await?;
+        assert_eq!(db.get_delete_session(user).await?.unwrap().page, 2);
+        db.init_delete_session(user, chat_a).await?;
+        assert_eq!(db.get_delete_session(user).await?.unwrap().page, 0);
+
         db.clear_delete_session(user).await?;
         assert!(db.get_delete_session(user).await?.is_none());
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn sessions_for_chat_lists_open_panels() -> Result<()> {
+        let db = init_test_db().await;
+        let chat = ChatId(10);
+        db.init_delete_session(1, chat).await?;
+        db.set_delete_dm_message(1, MessageId(4)).await?;
+        db.init_delete_session(2, chat).await?;
+        db.set_delete_dm_message(2, MessageId(5)).await?;
+        db.init_delete_session(3, chat).await?;
+        db.init_delete_session(4, ChatId(11)).await?;
+        db.set_delete_dm_message(4, MessageId(6)).await?;
+
+        let sessions = db.sessions_for_chat(chat).await?;
+        let panels: Vec<(i64, Option<MessageId>)> = sessions
+            .iter()
+            .map(|(user, session)| (*user, session.dm_message_id))
+            .collect();
+        assert_eq!(panels, [(1, Some(MessageId(4))), (2, Some(MessageId(5)))]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn in_chat_panels_are_found_by_their_message() -> Result<()> {
+        let db = init_test_db().await;
+        let group = ChatId(-100);
+        db.init_delete_session(1, group).await?;
+        db.set_delete_dm_message(1, MessageId(4)).await?;
+
+        let session = db.get_delete_session(1).await?.unwrap();
+        assert_eq!(session.panel_chat(1), group);
+        assert_eq!(db.delete_panel_owner(group, MessageId(4)).await?, Some(1));
+        assert_eq!(db.delete_panel_owner(group, MessageId(5)).await?, None);
+
+        db.set_delete_notice(1, group, MessageId(3)).await?;
+        let session = db.get_delete_session(1).await?.unwrap();
+        assert_eq!(session.panel_chat(1), ChatId(1));
+        Ok(())
+    }
 }
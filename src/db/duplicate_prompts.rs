@@ -0,0 +1,134 @@
+use super::Database;
+use anyhow::Result;
+use teloxide::types::{ChatId, MessageId};
+
+/// What to do with an item that was added again while already checked off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateAction {
+    /// Put a second, unchecked copy on the list.
+    Add,
+    /// Uncheck the existing item instead of adding a copy.
+    #[default]
+    Uncheck,
+}
+
+impl DuplicateAction {
+    pub const ALL: [Self; 2] = [Self::Add, Self::Uncheck];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Add => "add",
+            Self::Uncheck => "uncheck",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+/// A question waiting for an answer about one checked-off item.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicatePrompt {
+    pub item_id: i64,
+    pub chat_id: ChatId,
+    pub message_id: Option<MessageId>,
+}
+
+impl Database {
+    /// Record a pending prompt. Returns `false` when the item already has one
+    /// open so the chat is not asked twice.
+    pub async fn create_duplicate_prompt(
+        &self,
+        chat_id: ChatId,
+        item_id: i64,
+        created_at: i64,
+    ) -> Result<bool> {
+        tracing::debug!(chat_id = chat_id.0, item_id, "Creating duplicate prompt");
+        let result = sqlx::query(
+            "INSERT OR IGNORE INTO duplicate_prompt (item_id, chat_id, created_at) VALUES (?, ?, ?)",
+        )
+        .bind(item_id)
+        .bind(chat_id.0)
+        .bind(created_at)
+        .execute(self.pool())
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    pub async fn set_duplicate_prompt_message(
+        &self,
+        item_id: i64,
+        message_id: MessageId,
+    ) -> Result<()> {
+        tracing::trace!(
+            item_id,
+            message_id = message_id.0,
+            "Storing duplicate prompt message"
+        );
+        sqlx::query("UPDATE duplicate_prompt SET message_id = ? WHERE item_id = ?")
+            .bind(message_id.0)
+            .bind(item_id)
+            .execute(self.pool())
+            .await?;
+        Ok(())
+    }
+
+    /// Remove and return the pending prompt. Only the first of a button
+    /// press and the timeout gets it, so each prompt is resolved once.
+    pub async fn take_duplicate_prompt(
+        &self,
+        chat_id: ChatId,
+        item_id: i64,
+    ) -> Result<Option<DuplicatePrompt>> {
+        tracing::debug!(chat_id = chat_id.0, item_id, "Taking duplicate prompt");
+        let row: Option<(i64, i64, Option<i32>)> = sqlx::query_as(
+            "DELETE FROM duplicate_prompt WHERE item_id = ? AND chat_id = ? \
+             RETURNING item_id, chat_id, message_id",
+        )
+        .bind(item_id)
+        .bind(chat_id.0)
+        .fetch_optional(self.pool())
+        .await?;
+        Ok(row.map(|(item_id, chat_id, message_id)| DuplicatePrompt {
+            item_id,
+            chat_id: ChatId(chat_id),
+            message_id: message_id.map(MessageId),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::util::init_test_db;
+
+    #[tokio::test]
+    async fn prompt_is_taken_once() -> Result<()> {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        assert!(db.create_duplicate_prompt(chat, 3, 0).await?);
+        assert!(!db.create_duplicate_prompt(chat, 3, 1).await?);
+        db.set_duplicate_prompt_message(3, MessageId(9)).await?;
+
+        assert!(db.take_duplicate_prompt(ChatId(2), 3).await?.is_none());
+        assert_eq!(
+            db.take_duplicate_prompt(chat, 3).await?,
+            Some(DuplicatePrompt {
+                item_id: 3,
+                chat_id: chat,
+                message_id: Some(MessageId(9)),
+            })
+        );
+        assert!(db.take_duplicate_prompt(chat, 3).await?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn action_names_round_trip() {
+        for action in DuplicateAction::ALL {
+            assert_eq!(DuplicateAction::parse(action.name()), Some(action));
+        }
+        assert_eq!(DuplicateAction::parse("maybe"), None);
+    }
+}
@@ -0,0 +1,119 @@
+use super::Database;
+use anyhow::Result;
+use teloxide::types::{ChatId, MessageId};
+
+/// A user renaming an item through the `/edit` DM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditSession {
+    pub chat_id: ChatId,
+    /// The item picked from the keyboard, once the user has tapped one.
+    pub item_id: Option<i64>,
+    pub dm_message_id: Option<MessageId>,
+    pub started_at: i64,
+}
+
+impl Database {
+    pub async fn init_edit_session(
+        &self,
+        user_id: i64,
+        chat_id: ChatId,
+        started_at: i64,
+    ) -> Result<()> {
+        tracing::debug!(user_id, chat_id = chat_id.0, "Initializing edit session");
+        sqlx::query(
+            "INSERT INTO edit_session (user_id, chat_id, started_at) VALUES (?, ?, ?) \
+             ON CONFLICT(user_id) DO UPDATE SET chat_id = excluded.chat_id, item_id = NULL, \
+             dm_message_id = NULL, started_at = excluded.started_at",
+        )
+        .bind(user_id)
+        .bind(chat_id.0)
+        .bind(started_at)
+        .execute(self.pool())
+        .await?;
+        Ok(())
+    }
+
+    pub async fn set_edit_dm_message(&self, user_id: i64, message_id: MessageId) -> Result<()> {
+        tracing::debug!(
+            user_id,
+            message_id = message_id.0,
+            "Setting edit DM message"
+        );
+        sqlx::query("UPDATE edit_session SET dm_message_id = ? WHERE user_id = ?")
+            .bind(message_id.0)
+            .bind(user_id)
+            .execute(self.pool())
+            .await?;
+        Ok(())
+    }
+
+    pub async fn select_edit_item(&self, user_id: i64, item_id: i64) -> Result<()> {
+        tracing::debug!(user_id, item_id, "Selecting item to edit");
+        sqlx::query("UPDATE edit_session SET item_id = ? WHERE user_id = ?")
+            .bind(item_id)
+            .bind(user_id)
+            .execute(self.pool())
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_edit_session(&self, user_id: i64) -> Result<Option<EditSession>> {
+        tracing::trace!(user_id, "Fetching edit session");
+        let row: Option<(i64, Option<i64>, Option<i32>, i64)> = sqlx::query_as(
+            "SELECT chat_id, item_id, dm_message_id, started_at FROM edit_session WHERE user_id = ?",
+        )
+        .bind(user_id)
+        .fetch_optional(self.pool())
+        .await?;
+        Ok(row.map(
+            |(chat_id, item_id, dm_message_id, started_at)| EditSession {
+                chat_id: ChatId(chat_id),
+                item_id,
+                dm_message_id: dm_message_id.map(MessageId),
+                started_at,
+            },
+        ))
+    }
+
+    pub async fn clear_edit_session(&self, user_id: i64) -> Result<()> {
+        tracing::debug!(user_id, "Clearing edit session");
+        sqlx::query("DELETE FROM edit_session WHERE user_id = ?")
+            .bind(user_id)
+            .execute(self.pool())
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::util::init_test_db;
+
+    #[tokio::test]
+    async fn edit_session_roundtrip() -> Result<()> {
+        let db = init_test_db().await;
+        db.init_edit_session(1, ChatId(10), 100).await?;
+        db.set_edit_dm_message(1, MessageId(4)).await?;
+        db.select_edit_item(1, 7).await?;
+        assert_eq!(
+            db.get_edit_session(1).await?,
+            Some(EditSession {
+                chat_id: ChatId(10),
+                item_id: Some(7),
+                dm_message_id: Some(MessageId(4)),
+                started_at: 100,
+            })
+        );
+
+        // Starting over forgets the previous pick.
+        db.init_edit_session(1, ChatId(11), 200).await?;
+        let session = db.get_edit_session(1).await?.unwrap();
+        assert_eq!(session.item_id, None);
+        assert_eq!(session.dm_message_id, None);
+
+        db.clear_edit_session(1).await?;
+        assert!(db.get_edit_session(1).await?.is_none());
+        Ok(())
+    }
+}
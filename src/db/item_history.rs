@@ -0,0 +1,180 @@
+use super::Database;
+use anyhow::Result;
+use sqlx::SqliteConnection;
+use teloxide::types::ChatId;
+
+use crate::text_utils::split_quantity;
+
+/// History rows kept per chat; older adds are pruned as new ones arrive.
+const HISTORY_PER_CHAT: i64 = 1000;
+
+/// An item the chat adds often, as offered by the quick-add buttons.
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct TopItem {
+    /// History row of the latest add, used to look the text up again.
+    pub id: i64,
+    /// Text as it was last added.
+    pub text: String,
+    pub count: i64,
+}
+
+/// Entries count as the same item when their names match once the leading
+/// quantity is split off, ignoring case.
+fn history_key(text: &str) -> String {
+    split_quantity(text).1.trim().to_lowercase()
+}
+
+/// Log one history row per added entry, so repeated adds count repeatedly.
+pub(crate) async fn record_history(
+    conn: &mut SqliteConnection,
+    chat_id: ChatId,
+    items: &[String],
+    added_at: i64,
+) -> Result<()> {
+    let rows: Vec<(String, &str)> = items
+        .iter()
+        .map(|text| (history_key(text), text.trim()))
+        .filter(|(key, _)| !key.is_empty())
+        .collect();
+    if rows.is_empty() {
+        return Ok(());
+    }
+    tracing::trace!(
+        chat_id = chat_id.0,
+        count = rows.len(),
+        "Recording item history"
+    );
+    let mut insert = sqlx::QueryBuilder::<sqlx::Sqlite>::new(
+        "INSERT INTO item_history (chat_id, key, text, added_at) ",
+    );
+    insert.push_values(&rows, |mut row, (key, text)| {
+        row.push_bind(chat_id.0)
+            .push_bind(key.as_str())
+            .push_bind(*text)
+            .push_bind(added_at);
+    });
+    insert.build().execute(&mut *conn).await?;
+    prune_history(conn, chat_id, HISTORY_PER_CHAT).await
+}
+
+/// Drop all but the chat's `keep` newest history rows.
+async fn prune_history(conn: &mut SqliteConnection, chat_id: ChatId, keep: i64) -> Result<()> {
+    let pruned = sqlx::query(
+        "DELETE FROM item_history WHERE chat_id = ? AND id <= \
+         (SELECT id FROM item_history WHERE chat_id = ? ORDER BY id DESC LIMIT 1 OFFSET ?)",
+    )
+    .bind(chat_id.0)
+    .bind(chat_id.0)
+    .bind(keep)
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+    if pruned > 0 {
+        tracing::debug!(chat_id = chat_id.0, pruned, "Pruned item history");
+    }
+    Ok(())
+}
+
+impl Database {
+    /// The chat's most frequently added items, most used first. Entries that
+    /// differ only in case or leading quantity count as the same item.
+    pub async fn top_items(&self, chat_id: ChatId, limit: i64) -> Result<Vec<TopItem>> {
+        tracing::trace!(chat_id = chat_id.0, limit, "Loading top items");
+        // With a single MAX() aggregate SQLite takes the bare `text` column
+        // from the row holding the maximum, i.e. the latest add.
+        sqlx::query_as(
+            "SELECT MAX(id) AS id, text, COUNT(*) AS count FROM item_history \
+             WHERE chat_id = ? GROUP BY key ORDER BY count DESC, id DESC LIMIT ?",
+        )
+        .bind(chat_id.0)
+        .bind(limit)
+        .fetch_all(self.pool())
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Text of a history row, if it belongs to the chat.
+    pub async fn history_text(&self, chat_id: ChatId, id: i64) -> Result<Option<String>> {
+        sqlx::query_scalar("SELECT text FROM item_history WHERE id = ? AND chat_id = ?")
+            .bind(id)
+            .bind(chat_id.0)
+            .fetch_optional(self.pool())
+            .await
+            .map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::util::init_test_db;
+
+    #[tokio::test]
+    async fn top_items_counts_every_add() -> Result<()> {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_items_count(chat, &["Milk".into(), "Eggs".into()])
+            .await?;
+        db.add_items_count(chat, &["milk".into(), "Bread".into()])
+            .await?;
+        db.archive_items(chat, 100).await?;
+        db.add_items_count(chat, &["2 milk".into(), "Bread".into()])
+            .await?;
+        db.add_item_count(ChatId(2), "Tea").await?;
+
+        let top = db.top_items(chat, 2).await?;
+        let summary: Vec<_> = top
+            .iter()
+            .map(|item| (item.text.as_str(), item.count))
+            .collect();
+        assert_eq!(summary, vec![("2 milk", 3), ("Bread", 2)]);
+        assert_eq!(
+            db.history_text(chat, top[1].id).await?.as_deref(),
+            Some("Bread")
+        );
+        assert_eq!(db.history_text(ChatId(2), top[1].id).await?, None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn digits_in_names_are_kept_apart() -> Result<()> {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_items_count(chat, &["7up".into(), "Up".into(), "2 7up".into()])
+            .await?;
+        db.add_items_count(chat, &["1984 novel".into(), "Novel".into()])
+            .await?;
+
+        let top = db.top_items(chat, 10).await?;
+        let summary: Vec<_> = top
+            .iter()
+            .map(|item| (item.text.as_str(), item.count))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("2 7up", 2), ("Novel", 1), ("1984 novel", 1), ("Up", 1)]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn history_keeps_the_newest_rows_per_chat() -> Result<()> {
+        let db = init_test_db().await;
+        let texts: Vec<String> = (1..=5).map(|n| format!("Item {n}")).collect();
+        let mut conn = db.pool().acquire().await?;
+        record_history(&mut conn, ChatId(1), &texts, 0).await?;
+        record_history(&mut conn, ChatId(2), &texts, 0).await?;
+        prune_history(&mut conn, ChatId(1), 2).await?;
+        drop(conn);
+
+        let kept: Vec<_> = db
+            .top_items(ChatId(1), 10)
+            .await?
+            .into_iter()
+            .map(|item| item.text)
+            .collect();
+        assert_eq!(kept, vec!["Item 5", "Item 4"]);
+        assert_eq!(db.top_items(ChatId(2), 10).await?.len(), 5);
+        Ok(())
+    }
+}
@@ -1,87 +1,430 @@
 use super::Database;
 use anyhow::Result;
-use teloxide::types::ChatId;
+use serde::{Deserialize, Serialize};
+use sqlx::SqliteConnection;
+use std::collections::HashMap;
+use teloxide::types::{ChatId, User};
 
-#[derive(sqlx::FromRow, Debug, Clone, PartialEq)]
+use super::item_history::record_history;
+use super::message_items::clear_message_items;
+use super::undo_log::{record_undo, UndoAction};
+use crate::text_utils::{normalize_for_match, split_quantity};
+
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Item {
     pub id: i64,
     pub text: String,
     pub done: bool,
+    pub quantity: i64,
+    pub added_by_user_id: Option<i64>,
+    /// First name of whoever added the item, if known.
+    pub added_by_name: Option<String>,
+    /// Lowercase group such as "dairy"; uncategorized items have none.
+    pub category: Option<String>,
+    /// Unix seconds when the item was first added; 0 for rows that predate
+    /// timestamps.
+    #[serde(default)]
+    pub created_at: i64,
+    /// Who claimed the item through `/assign`, if anyone.
+    #[serde(default)]
+    pub assigned_to_user_id: Option<i64>,
+    #[serde(default)]
+    pub assigned_to_name: Option<String>,
+    /// Price read from a receipt, in cents.
+    #[serde(default)]
+    pub price_cents: Option<i64>,
+}
+
+/// Columns selected into an [`Item`].
+pub(crate) const ITEM_COLUMNS: &str =
+    "id, text, done, quantity, added_by_user_id, added_by_name, category, created_at, \
+     assigned_to_user_id, assigned_to_name, price_cents";
+
+/// Order of [`Database::list_items_filtered`] results.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ItemSort {
+    /// List order, as shown in the chat: insertion order unless the items
+    /// were reordered.
+    #[default]
+    Position,
+    /// Oldest first.
+    Created,
+    /// By text, ignoring ASCII case.
+    Alpha,
+}
+
+/// Conditions for [`Database::list_items_filtered`]; unset fields match
+/// every item.
+#[derive(Debug, Clone, Default)]
+pub struct ItemFilter {
+    /// Only items added at or after this unix timestamp.
+    pub since: Option<i64>,
+    pub done: Option<bool>,
+    /// Substring to look for, trimmed and compared ignoring case in any
+    /// script; digits are kept, so "500" finds "Cola 500 ml".
+    pub query: Option<String>,
+    pub sort: ItemSort,
+}
+
+/// Who put an item on the list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItemAuthor {
+    pub user_id: Option<i64>,
+    pub name: String,
+}
+
+impl From<&User> for ItemAuthor {
+    fn from(user: &User) -> Self {
+        Self {
+            user_id: Some(user.id.0 as i64),
+            name: user.first_name.clone(),
+        }
+    }
+}
+
+impl Item {
+    /// Text shown on the list, prefixed with the quantity when above one.
+    /// Counts typed with an item are stored in `quantity`, never in `text`.
+    pub fn label(&self) -> String {
+        if self.quantity > 1 {
+            format!("{}× {}", self.quantity, self.text)
+        } else {
+            self.text.clone()
+        }
+    }
 }
 
 impl Database {
     pub async fn add_item_count(&self, chat_id: ChatId, text: &str) -> Result<u64> {
         tracing::trace!(chat_id = chat_id.0, text = %text, "Adding item");
-        let result = sqlx::query("INSERT INTO items (chat_id, text) VALUES (?, ?)")
-            .bind(chat_id.0)
-            .bind(text)
-            .execute(self.pool())
-            .await?;
-        Ok(result.rows_affected())
+        self.add_items_count(chat_id, &[text.to_string()]).await
     }
 
     pub async fn add_items_count(&self, chat_id: ChatId, items: &[String]) -> Result<u64> {
+        self.add_items_by(chat_id, items, None).await
+    }
+
+    /// Add items to the list in one transaction. A leading count such as
+    /// "2 lemons" becomes the entry's quantity (see [`split_quantity`]). An
+    /// entry matching an unchecked item (compared with [`normalize_for_match`])
+    /// adds its quantity to that item instead of inserting another row,
+    /// keeping its original author; the remaining entries go in with a single
+    /// multi-row INSERT.
+    /// Either every entry is stored or none is. Returns the number of entries
+    /// added or merged.
+    pub async fn add_items_by(
+        &self,
+        chat_id: ChatId,
+        items: &[String],
+        author: Option<&ItemAuthor>,
+    ) -> Result<u64> {
         if items.is_empty() {
             return Ok(0);
         }
 
-        tracing::trace!(chat_id = chat_id.0, count = items.len(), "Adding items");
-        let mut builder =
-            sqlx::QueryBuilder::<sqlx::Sqlite>::new("INSERT INTO items (chat_id, text) ");
-        builder.push_values(items, |mut row, text| {
-            row.push_bind(chat_id.0).push_bind(text);
-        });
+        let mut tx = self.begin_write().await?;
+        store_items(&mut tx, chat_id, items, author).await?;
+        tx.commit().await?;
+        Ok(items.len() as u64)
+    }
 
-        let result = builder.build().execute(self.pool()).await?;
-        Ok(result.rows_affected())
+    /// Add one item like [`Database::add_items_by`] and return the row it
+    /// ended up in, which is the existing item when the entry was merged.
+    pub async fn add_item_returning(
+        &self,
+        chat_id: ChatId,
+        text: &str,
+        author: Option<&ItemAuthor>,
+    ) -> Result<Item> {
+        let mut tx = self.begin_write().await?;
+        let ids = store_items(&mut tx, chat_id, &[text.to_string()], author).await?;
+        let item: Item = sqlx::query_as(&format!("SELECT {ITEM_COLUMNS} FROM items WHERE id = ?"))
+            .bind(ids[0])
+            .fetch_one(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        tracing::debug!(chat_id = chat_id.0, item_id = item.id, "Added item");
+        Ok(item)
     }
 
     pub async fn list_items(&self, chat_id: ChatId) -> Result<Vec<Item>> {
         tracing::trace!(chat_id = chat_id.0, "Listing items");
-        sqlx::query_as("SELECT id, text, done FROM items WHERE chat_id = ? ORDER BY id")
-            .bind(chat_id.0)
+        sqlx::query_as(&format!(
+            "SELECT {ITEM_COLUMNS} FROM items WHERE chat_id = ? AND deleted_at IS NULL \
+             ORDER BY position, id"
+        ))
+        .bind(chat_id.0)
+        .fetch_all(self.pool())
+        .await
+        .map_err(Into::into)
+    }
+
+    /// `(done, total)` item counts for the chat's list in one query.
+    pub async fn count_items(&self, chat_id: ChatId) -> Result<(u64, u64)> {
+        tracing::trace!(chat_id = chat_id.0, "Counting items");
+        let (done, total): (i64, i64) = sqlx::query_as(
+            "SELECT COALESCE(SUM(done), 0), COUNT(*) FROM items \
+             WHERE chat_id = ? AND deleted_at IS NULL",
+        )
+        .bind(chat_id.0)
+        .fetch_one(self.pool())
+        .await?;
+        Ok((done as u64, total as u64))
+    }
+
+    /// Items matching `filter`, filtered and ordered in SQL.
+    pub async fn list_items_filtered(
+        &self,
+        chat_id: ChatId,
+        filter: &ItemFilter,
+    ) -> Result<Vec<Item>> {
+        tracing::trace!(chat_id = chat_id.0, ?filter, "Listing filtered items");
+        let mut builder = sqlx::QueryBuilder::<sqlx::Sqlite>::new("SELECT ");
+        builder.push(ITEM_COLUMNS);
+        builder.push(" FROM items WHERE deleted_at IS NULL AND chat_id = ");
+        builder.push_bind(chat_id.0);
+        if let Some(since) = filter.since {
+            builder.push(" AND created_at >= ");
+            builder.push_bind(since);
+        }
+        if let Some(done) = filter.done {
+            builder.push(" AND done = ");
+            builder.push_bind(done);
+        }
+        if let Some(query) = &filter.query {
+            // SQLite's own `lower()` and `LIKE` only fold ASCII letters.
+            builder.push(" AND text REGEXP ");
+            builder.push_bind(search_pattern(query));
+        }
+        builder.push(match filter.sort {
+            ItemSort::Position => " ORDER BY position, id",
+            ItemSort::Created => " ORDER BY created_at, id",
+            ItemSort::Alpha => " ORDER BY lower(text), id",
+        });
+
+        builder
+            .build_query_as()
             .fetch_all(self.pool())
             .await
             .map_err(Into::into)
     }
 
+    /// Set or clear the price of an item, in cents.
+    pub async fn set_item_price(
+        &self,
+        chat_id: ChatId,
+        id: i64,
+        price_cents: Option<i64>,
+    ) -> Result<u64> {
+        tracing::trace!(
+            chat_id = chat_id.0,
+            item_id = id,
+            ?price_cents,
+            "Setting item price"
+        );
+        let result = sqlx::query(
+            "UPDATE items SET price_cents = ? \
+             WHERE id = ? AND chat_id = ? AND deleted_at IS NULL",
+        )
+        .bind(price_cents)
+        .bind(id)
+        .bind(chat_id.0)
+        .execute(self.pool())
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Put an item into a category, or clear it with `None` or a blank
+    /// name. Categories are stored trimmed and lowercased so "Dairy" and
+    /// "dairy" land in the same group.
+    pub async fn set_item_category(
+        &self,
+        chat_id: ChatId,
+        id: i64,
+        category: Option<&str>,
+    ) -> Result<u64> {
+        let category = category
+            .map(|category| category.trim().to_lowercase())
+            .filter(|category| !category.is_empty());
+        tracing::trace!(
+            chat_id = chat_id.0,
+            item_id = id,
+            ?category,
+            "Setting item category"
+        );
+        let result = sqlx::query(
+            "UPDATE items SET category = ? \
+             WHERE id = ? AND chat_id = ? AND deleted_at IS NULL",
+        )
+        .bind(category)
+        .bind(id)
+        .bind(chat_id.0)
+        .execute(self.pool())
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// A single item of the chat's list; items of other chats and trashed
+    /// items are not found.
+    pub async fn get_item(&self, chat_id: ChatId, id: i64) -> Result<Option<Item>> {
+        tracing::trace!(chat_id = chat_id.0, item_id = id, "Loading item");
+        sqlx::query_as(&format!(
+            "SELECT {ITEM_COLUMNS} FROM items \
+             WHERE id = ? AND chat_id = ? AND deleted_at IS NULL"
+        ))
+        .bind(id)
+        .bind(chat_id.0)
+        .fetch_optional(self.pool())
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Replace an item's text and/or set its done state in one statement,
+    /// leaving fields passed as `None` unchanged. Returns the updated item.
+    pub async fn update_item(
+        &self,
+        chat_id: ChatId,
+        id: i64,
+        text: Option<&str>,
+        done: Option<bool>,
+    ) -> Result<Option<Item>> {
+        tracing::debug!(
+            chat_id = chat_id.0,
+            item_id = id,
+            ?text,
+            ?done,
+            "Updating item"
+        );
+        sqlx::query_as(&format!(
+            "UPDATE items SET text = COALESCE(?, text), done = COALESCE(?, done), \
+             done_at = CASE WHEN ? IS NULL OR ? = done THEN done_at WHEN ? THEN ? END \
+             WHERE id = ? AND chat_id = ? AND deleted_at IS NULL \
+             RETURNING {ITEM_COLUMNS}"
+        ))
+        .bind(text)
+        .bind(done)
+        .bind(done)
+        .bind(done)
+        .bind(done)
+        .bind(chrono::Utc::now().timestamp())
+        .bind(id)
+        .bind(chat_id.0)
+        .fetch_optional(self.pool())
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Flip an item's done state, stamping `done_at` when it gets checked.
     pub async fn toggle_item_count(&self, chat_id: ChatId, id: i64) -> Result<u64> {
         tracing::trace!(chat_id = chat_id.0, item_id = id, "Toggling item");
-        let result = sqlx::query("UPDATE items SET done = NOT done WHERE id = ? AND chat_id = ?")
-            .bind(id)
-            .bind(chat_id.0)
-            .execute(self.pool())
-            .await?;
+        let result = sqlx::query(
+            "UPDATE items SET done = NOT done, done_at = CASE WHEN done THEN NULL ELSE ? END \
+             WHERE id = ? AND chat_id = ? AND deleted_at IS NULL",
+        )
+        .bind(chrono::Utc::now().timestamp())
+        .bind(id)
+        .bind(chat_id.0)
+        .execute(self.pool())
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn uncheck_item_count(&self, chat_id: ChatId, id: i64) -> Result<u64> {
+        tracing::trace!(chat_id = chat_id.0, item_id = id, "Unchecking item");
+        let result = sqlx::query(
+            "UPDATE items SET done = 0, done_at = NULL \
+             WHERE id = ? AND chat_id = ? AND deleted_at IS NULL",
+        )
+        .bind(id)
+        .bind(chat_id.0)
+        .execute(self.pool())
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Replace an item's text. Returns the number of rows changed.
+    pub async fn rename_item(&self, chat_id: ChatId, id: i64, new_text: &str) -> Result<u64> {
+        tracing::debug!(chat_id = chat_id.0, item_id = id, text = %new_text, "Renaming item");
+        let result = sqlx::query(
+            "UPDATE items SET text = ? \
+             WHERE id = ? AND chat_id = ? AND deleted_at IS NULL",
+        )
+        .bind(new_text)
+        .bind(id)
+        .bind(chat_id.0)
+        .execute(self.pool())
+        .await?;
         Ok(result.rows_affected())
     }
 
     pub async fn delete_item_count(&self, chat_id: ChatId, id: i64) -> Result<u64> {
         tracing::trace!(chat_id = chat_id.0, item_id = id, "Deleting item");
-        let result = sqlx::query("DELETE FROM items WHERE id = ? AND chat_id = ?")
-            .bind(id)
-            .bind(chat_id.0)
-            .execute(self.pool())
-            .await?;
-        Ok(result.rows_affected())
+        self.delete_items_count(chat_id, &[id]).await
     }
 
+    /// Delete the whole list, keeping it in the undo log. Trashed items are
+    /// left alone.
     pub async fn delete_all_items_count(&self, chat_id: ChatId) -> Result<u64> {
         tracing::debug!(chat_id = chat_id.0, "Deleting all items");
-        let result = sqlx::query("DELETE FROM items WHERE chat_id = ?")
-            .bind(chat_id.0)
-            .execute(self.pool())
-            .await?;
-        Ok(result.rows_affected())
+        let mut tx = self.begin_write().await?;
+        let mut items: Vec<Item> = sqlx::query_as(&format!(
+            "DELETE FROM items WHERE chat_id = ? AND deleted_at IS NULL RETURNING {ITEM_COLUMNS}"
+        ))
+        .bind(chat_id.0)
+        .fetch_all(&mut *tx)
+        .await?;
+        items.sort_by_key(|item| item.id);
+        clear_message_items(&mut tx, chat_id).await?;
+        record_undo(&mut tx, chat_id, UndoAction::Nuke, &items, None).await?;
+        tx.commit().await?;
+        Ok(items.len() as u64)
     }
 
+    /// Rewrite the list order so items follow `ids`. Returns `false` and
+    /// changes nothing unless `ids` names every item of the chat's list
+    /// exactly once.
+    pub async fn reorder_items(&self, chat_id: ChatId, ids: &[i64]) -> Result<bool> {
+        tracing::debug!(chat_id = chat_id.0, count = ids.len(), "Reordering items");
+        let mut tx = self.begin_write().await?;
+        let mut current: Vec<i64> =
+            sqlx::query_scalar("SELECT id FROM items WHERE chat_id = ? AND deleted_at IS NULL")
+                .bind(chat_id.0)
+                .fetch_all(&mut *tx)
+                .await?;
+        let mut requested = ids.to_vec();
+        current.sort_unstable();
+        requested.sort_unstable();
+        if current != requested {
+            tracing::debug!(
+                chat_id = chat_id.0,
+                expected = current.len(),
+                "Reorder ids do not match the list"
+            );
+            return Ok(false);
+        }
+
+        for (position, id) in (1_i64..).zip(ids) {
+            sqlx::query("UPDATE items SET position = ? WHERE id = ?")
+                .bind(position)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// Move the given items to the trash, keeping them in the undo log. They
+    /// stay restorable until [`Database::purge_deleted`] removes them.
     pub async fn delete_items_count(&self, chat_id: ChatId, ids: &[i64]) -> Result<u64> {
         tracing::trace!(chat_id = chat_id.0, ?ids, "Deleting multiple items");
         if ids.is_empty() {
             return Ok(0);
         }
 
-        let mut builder =
-            sqlx::QueryBuilder::<sqlx::Sqlite>::new("DELETE FROM items WHERE chat_id = ");
+        let mut builder = sqlx::QueryBuilder::<sqlx::Sqlite>::new("UPDATE items SET deleted_at = ");
+        builder.push_bind(chrono::Utc::now().timestamp());
+        builder.push(" WHERE deleted_at IS NULL AND chat_id = ");
         builder.push_bind(chat_id.0);
         builder.push(" AND id IN (");
         {
@@ -90,11 +433,129 @@ impl Database {
                 separated.push_bind(id);
             }
         }
-        builder.push(")");
+        builder.push(") RETURNING ");
+        builder.push(ITEM_COLUMNS);
 
-        let result = builder.build().execute(self.pool()).await?;
-        Ok(result.rows_affected())
+        let mut tx = self.begin_write().await?;
+        let mut items: Vec<Item> = builder.build_query_as().fetch_all(&mut *tx).await?;
+        items.sort_by_key(|item| item.id);
+        record_undo(&mut tx, chat_id, UndoAction::Delete, &items, None).await?;
+        tx.commit().await?;
+        Ok(items.len() as u64)
+    }
+}
+
+/// A `REGEXP` pattern matching `query` literally anywhere in the text,
+/// ignoring case in every script the `regex` crate folds.
+fn search_pattern(query: &str) -> String {
+    format!("(?i){}", regex::escape(query.trim()))
+}
+
+/// Put rows inserted without a position at the end of the list. Ids only
+/// grow and reordered positions count from one, so an item's id is always
+/// past every position already in use.
+pub(super) async fn fill_positions(conn: &mut SqliteConnection, chat_id: ChatId) -> Result<()> {
+    sqlx::query("UPDATE items SET position = id WHERE chat_id = ? AND position IS NULL")
+        .bind(chat_id.0)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Where an entry passed to [`store_items`] ended up.
+enum Target {
+    /// Merged into an existing unchecked item.
+    Existing(i64),
+    /// Inserted as the n-th new row.
+    New(usize),
+}
+
+/// Store `items` and return the row id each entry ended up in, in input
+/// order. See [`Database::add_items_by`] for the merge rules.
+pub(super) async fn store_items(
+    conn: &mut SqliteConnection,
+    chat_id: ChatId,
+    items: &[String],
+    author: Option<&ItemAuthor>,
+) -> Result<Vec<i64>> {
+    let created_at = chrono::Utc::now().timestamp();
+    tracing::trace!(chat_id = chat_id.0, count = items.len(), "Adding items");
+    let unchecked: Vec<(i64, String)> = sqlx::query_as(
+        "SELECT id, text FROM items \
+             WHERE chat_id = ? AND NOT done AND deleted_at IS NULL ORDER BY id",
+    )
+    .bind(chat_id.0)
+    .fetch_all(&mut *conn)
+    .await?;
+    let mut by_text: HashMap<String, i64> = HashMap::new();
+    for (id, text) in unchecked {
+        by_text.entry(normalize_for_match(&text)).or_insert(id);
+    }
+
+    // Merges into existing rows become one quantity bump per row; new
+    // entries are folded together and written with a single INSERT.
+    let mut targets: Vec<Target> = Vec::with_capacity(items.len());
+    let mut bumps: Vec<(i64, i64)> = Vec::new();
+    let mut new_rows: Vec<(&str, i64)> = Vec::new();
+    let mut new_by_text: HashMap<String, usize> = HashMap::new();
+    for text in items {
+        let (quantity, name) = split_quantity(text);
+        let key = normalize_for_match(name);
+        if let Some(&id) = by_text.get(&key).filter(|_| !key.is_empty()) {
+            match bumps.iter_mut().find(|(bumped, _)| *bumped == id) {
+                Some((_, count)) => *count += quantity,
+                None => bumps.push((id, quantity)),
+            }
+            targets.push(Target::Existing(id));
+        } else if let Some(&row) = new_by_text.get(&key).filter(|_| !key.is_empty()) {
+            new_rows[row].1 += quantity;
+            targets.push(Target::New(row));
+        } else {
+            new_by_text.insert(key, new_rows.len());
+            targets.push(Target::New(new_rows.len()));
+            new_rows.push((name, quantity));
+        }
     }
+
+    for (id, count) in &bumps {
+        sqlx::query("UPDATE items SET quantity = quantity + ? WHERE id = ?")
+            .bind(count)
+            .bind(id)
+            .execute(&mut *conn)
+            .await?;
+    }
+    let mut inserted: Vec<i64> = Vec::new();
+    if !new_rows.is_empty() {
+        let mut insert = sqlx::QueryBuilder::<sqlx::Sqlite>::new(
+            "INSERT INTO items (chat_id, text, quantity, added_by_user_id, added_by_name, created_at) ",
+        );
+        insert.push_values(&new_rows, |mut row, (text, quantity)| {
+            row.push_bind(chat_id.0)
+                .push_bind(*text)
+                .push_bind(*quantity)
+                .push_bind(author.and_then(|author| author.user_id))
+                .push_bind(author.map(|author| author.name.as_str()))
+                .push_bind(created_at);
+        });
+        insert.push(" RETURNING id");
+        inserted = insert.build_query_scalar().fetch_all(&mut *conn).await?;
+        fill_positions(conn, chat_id).await?;
+    }
+    record_history(conn, chat_id, items, created_at).await?;
+
+    tracing::debug!(
+        chat_id = chat_id.0,
+        inserted = new_rows.len(),
+        merged = items.len() - new_rows.len(),
+        "Stored items"
+    );
+    Ok(targets
+        .into_iter()
+        .map(|target| match target {
+            Target::Existing(id) => id,
+            Target::New(row) => inserted[row],
+        })
+        .collect())
 }
 
 #[cfg(test)]
@@ -102,6 +563,56 @@ mod tests {
     use super::*;
     use crate::tests::util::init_test_db;
 
+    async fn done_at(db: &Database, id: i64) -> Option<i64> {
+        sqlx::query_scalar("SELECT done_at FROM items WHERE id = ?")
+            .bind(id)
+            .fetch_one(db.pool())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn toggling_tracks_when_an_item_was_checked() {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_item_count(chat, "Milk").await.unwrap();
+        let id = db.list_items(chat).await.unwrap()[0].id;
+        assert_eq!(done_at(&db, id).await, None);
+
+        let before = chrono::Utc::now().timestamp();
+        db.toggle_item_count(chat, id).await.unwrap();
+        assert!(done_at(&db, id).await.is_some_and(|at| at >= before));
+        db.toggle_item_count(chat, id).await.unwrap();
+        assert_eq!(done_at(&db, id).await, None);
+
+        db.update_item(chat, id, None, Some(true)).await.unwrap();
+        let checked = done_at(&db, id).await;
+        assert!(checked.is_some());
+        db.update_item(chat, id, Some("Oat milk"), Some(true))
+            .await
+            .unwrap();
+        assert_eq!(done_at(&db, id).await, checked);
+        db.uncheck_item_count(chat, id).await.unwrap();
+        assert_eq!(done_at(&db, id).await, None);
+    }
+
+    #[tokio::test]
+    async fn add_item_returning_reports_the_stored_row() -> Result<()> {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_items_count(chat, &["Bread".into()]).await?;
+
+        let eggs = db.add_item_returning(chat, "Eggs", None).await?;
+        let bread = db.add_item_returning(chat, "bread", None).await?;
+
+        let items = db.list_items(chat).await?;
+        assert_eq!(bread.id, items[0].id);
+        assert_eq!(bread.quantity, 2);
+        assert_eq!(eggs.id, items[1].id);
+        assert_eq!(eggs.text, "Eggs");
+        Ok(())
+    }
+
     #[tokio::test]
     async fn add_items_inserts_multiple() {
         let db = init_test_db().await;
@@ -116,6 +627,26 @@ mod tests {
         assert_eq!(texts, vec!["Apple".to_string(), "Banana".to_string()]);
     }
 
+    #[tokio::test]
+    async fn reorder_requires_the_exact_list() -> Result<()> {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_items_count(chat, &["Milk".into(), "Eggs".into()])
+            .await?;
+        let ids: Vec<i64> = db.list_items(chat).await?.iter().map(|i| i.id).collect();
+
+        assert!(!db.reorder_items(chat, &[ids[1]]).await?);
+        assert!(db.reorder_items(chat, &[ids[1], ids[0]]).await?);
+        let texts: Vec<String> = db
+            .list_items(chat)
+            .await?
+            .into_iter()
+            .map(|i| i.text)
+            .collect();
+        assert_eq!(texts, ["Eggs", "Milk"]);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn add_items_handles_empty() {
         let db = init_test_db().await;
@@ -128,4 +659,314 @@ mod tests {
         let stored = db.list_items(chat).await.unwrap();
         assert!(stored.is_empty());
     }
+
+    #[tokio::test]
+    async fn add_items_merges_unchecked_duplicates() {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_item_count(chat, "Milk").await.unwrap();
+        let items = ["milk ", "Bread", "bread", "MILK"].map(String::from);
+        assert_eq!(db.add_items_count(chat, &items).await.unwrap(), 4);
+
+        let stored = db.list_items(chat).await.unwrap();
+        let summary: Vec<_> = stored
+            .iter()
+            .map(|item| (item.text.as_str(), item.quantity))
+            .collect();
+        assert_eq!(summary, vec![("Milk", 3), ("Bread", 2)]);
+        assert_eq!(stored[0].label(), "3× Milk");
+        assert_eq!(stored[1].label(), "2× Bread");
+
+        // Toggling the merged row still works, and checked rows do not merge.
+        db.toggle_item_count(chat, stored[0].id).await.unwrap();
+        db.add_item_count(chat, "Milk").await.unwrap();
+        let stored = db.list_items(chat).await.unwrap();
+        assert_eq!(stored.len(), 3);
+        assert!(stored[0].done);
+        assert_eq!(stored[2].label(), "Milk");
+    }
+
+    #[tokio::test]
+    async fn leading_counts_become_quantities() {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_item_count(chat, "Lemons").await.unwrap();
+        db.add_item_count(chat, "2 lemons").await.unwrap();
+        db.add_items_count(chat, &["2 eggs".into(), "3× Eggs".into()])
+            .await
+            .unwrap();
+
+        let stored = db.list_items(chat).await.unwrap();
+        let labels: Vec<_> = stored.iter().map(Item::label).collect();
+        assert_eq!(labels, vec!["3× Lemons", "5× eggs"]);
+    }
+
+    #[tokio::test]
+    async fn repeated_counts_add_up_without_repeating_the_text() {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_item_count(chat, "2 lemons").await.unwrap();
+        db.add_item_count(chat, "2 lemons").await.unwrap();
+
+        let stored = db.list_items(chat).await.unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].label(), "4× lemons");
+    }
+
+    #[tokio::test]
+    async fn add_items_by_records_author_of_first_add() {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        let anna = ItemAuthor {
+            user_id: Some(7),
+            name: "Anna".to_string(),
+        };
+        let ben = ItemAuthor {
+            user_id: Some(8),
+            name: "Ben".to_string(),
+        };
+        db.add_items_by(chat, &["Milk".into()], Some(&anna))
+            .await
+            .unwrap();
+        db.add_items_by(chat, &["milk".into(), "Eggs".into()], Some(&ben))
+            .await
+            .unwrap();
+        db.add_item_count(chat, "Bread").await.unwrap();
+
+        let authors: Vec<_> = db
+            .list_items(chat)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|item| (item.added_by_user_id, item.added_by_name))
+            .collect();
+        assert_eq!(
+            authors,
+            vec![
+                (Some(7), Some("Anna".to_string())),
+                (Some(8), Some("Ben".to_string())),
+                (None, None),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn rename_item_only_touches_own_chat() {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_item_count(chat, "Mlik").await.unwrap();
+        let id = db.list_items(chat).await.unwrap()[0].id;
+
+        assert_eq!(db.rename_item(ChatId(2), id, "Milk").await.unwrap(), 0);
+        assert_eq!(db.rename_item(chat, id, "Milk").await.unwrap(), 1);
+        assert_eq!(db.list_items(chat).await.unwrap()[0].text, "Milk");
+    }
+
+    #[tokio::test]
+    async fn set_item_category_normalizes_and_clears() {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_item_count(chat, "Milk").await.unwrap();
+        let id = db.list_items(chat).await.unwrap()[0].id;
+
+        assert_eq!(
+            db.set_item_category(ChatId(2), id, Some("dairy"))
+                .await
+                .unwrap(),
+            0
+        );
+        db.set_item_category(chat, id, Some(" Dairy "))
+            .await
+            .unwrap();
+        let category = db.list_items(chat).await.unwrap()[0].category.clone();
+        assert_eq!(category.as_deref(), Some("dairy"));
+
+        db.set_item_category(chat, id, Some("  ")).await.unwrap();
+        assert_eq!(db.list_items(chat).await.unwrap()[0].category, None);
+    }
+
+    #[tokio::test]
+    async fn set_item_price_sets_and_clears() {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_item_count(chat, "Milk").await.unwrap();
+        let id = db.list_items(chat).await.unwrap()[0].id;
+
+        assert_eq!(
+            db.set_item_price(ChatId(2), id, Some(349)).await.unwrap(),
+            0
+        );
+        db.set_item_price(chat, id, Some(349)).await.unwrap();
+        assert_eq!(db.list_items(chat).await.unwrap()[0].price_cents, Some(349));
+
+        db.set_item_price(chat, id, None).await.unwrap();
+        assert_eq!(db.list_items(chat).await.unwrap()[0].price_cents, None);
+    }
+
+    #[tokio::test]
+    async fn add_items_stamps_creation_time() {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        let before = chrono::Utc::now().timestamp();
+        db.add_item_count(chat, "Milk").await.unwrap();
+        let item = db.list_items(chat).await.unwrap().remove(0);
+        assert!(item.created_at >= before);
+
+        // Merging a duplicate keeps the original timestamp.
+        sqlx::query("UPDATE items SET created_at = 5")
+            .execute(db.pool())
+            .await
+            .unwrap();
+        db.add_item_count(chat, "milk").await.unwrap();
+        assert_eq!(db.list_items(chat).await.unwrap()[0].created_at, 5);
+    }
+
+    #[tokio::test]
+    async fn list_items_filtered_applies_each_condition() {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_items_count(
+            chat,
+            &[
+                "Milk".into(),
+                "oat milk".into(),
+                "Bread".into(),
+                "Apples".into(),
+            ],
+        )
+        .await
+        .unwrap();
+        db.add_item_count(ChatId(2), "Milk chocolate")
+            .await
+            .unwrap();
+        for (text, created_at) in [
+            ("Milk", 400),
+            ("oat milk", 100),
+            ("Bread", 300),
+            ("Apples", 200),
+        ] {
+            sqlx::query("UPDATE items SET created_at = ? WHERE text = ?")
+                .bind(created_at)
+                .bind(text)
+                .execute(db.pool())
+                .await
+                .unwrap();
+        }
+        let bread = db.list_items(chat).await.unwrap()[2].id;
+        db.toggle_item_count(chat, bread).await.unwrap();
+
+        let texts =
+            |items: Vec<Item>| -> Vec<String> { items.into_iter().map(|item| item.text).collect() };
+        let list = |filter: ItemFilter| {
+            let db = db.clone();
+            async move { texts(db.list_items_filtered(chat, &filter).await.unwrap()) }
+        };
+
+        assert_eq!(
+            list(ItemFilter::default()).await,
+            vec!["Milk", "oat milk", "Bread", "Apples"]
+        );
+        assert_eq!(
+            list(ItemFilter {
+                done: Some(true),
+                ..Default::default()
+            })
+            .await,
+            vec!["Bread"]
+        );
+        assert_eq!(
+            list(ItemFilter {
+                query: Some(" MILK ".into()),
+                ..Default::default()
+            })
+            .await,
+            vec!["Milk", "oat milk"]
+        );
+        assert_eq!(
+            list(ItemFilter {
+                sort: ItemSort::Alpha,
+                ..Default::default()
+            })
+            .await,
+            vec!["Apples", "Bread", "Milk", "oat milk"]
+        );
+        assert_eq!(
+            list(ItemFilter {
+                since: Some(150),
+                done: Some(false),
+                query: Some("milk".into()),
+                sort: ItemSort::Created,
+            })
+            .await,
+            vec!["Milk"]
+        );
+    }
+
+    #[tokio::test]
+    async fn query_folds_case_in_any_script_and_keeps_digits() {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        let items = ["Молоко", "Éclair", "Cola 500 ml", "Milk 2 liters", "a.b"].map(String::from);
+        db.add_items_count(chat, &items).await.unwrap();
+
+        for (query, expected) in [
+            ("молоко", vec!["Молоко"]),
+            ("МОЛ", vec!["Молоко"]),
+            ("éCLAIR", vec!["Éclair"]),
+            ("500", vec!["Cola 500 ml"]),
+            ("2 liters", vec!["Milk 2 liters"]),
+            // Regex syntax in the query matches literally.
+            (".", vec!["a.b"]),
+        ] {
+            let filter = ItemFilter {
+                query: Some(query.into()),
+                ..Default::default()
+            };
+            let texts: Vec<String> = db
+                .list_items_filtered(chat, &filter)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|item| item.text)
+                .collect();
+            assert_eq!(texts, expected, "{query}");
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_batch_stores_nothing() {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_item_count(chat, "Milk").await.unwrap();
+        sqlx::query(
+            "CREATE TRIGGER reject_boom BEFORE INSERT ON items WHEN NEW.text = 'Boom' \
+             BEGIN SELECT RAISE(ABORT, 'boom'); END",
+        )
+        .execute(db.pool())
+        .await
+        .unwrap();
+
+        let items = ["milk", "Eggs", "Boom", "Bread"].map(String::from);
+        assert!(db.add_items_count(chat, &items).await.is_err());
+
+        let stored = db.list_items(chat).await.unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].label(), "Milk");
+    }
+
+    #[tokio::test]
+    async fn count_items_reports_done_and_total() {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        assert_eq!(db.count_items(chat).await.unwrap(), (0, 0));
+
+        db.add_items_count(chat, &["Milk".into(), "Eggs".into(), "Tea".into()])
+            .await
+            .unwrap();
+        let items = db.list_items(chat).await.unwrap();
+        db.toggle_item_count(chat, items[0].id).await.unwrap();
+        db.delete_item_count(chat, items[2].id).await.unwrap();
+        assert_eq!(db.count_items(chat).await.unwrap(), (1, 2));
+        assert_eq!(db.count_items(ChatId(2)).await.unwrap(), (0, 0));
+    }
 }
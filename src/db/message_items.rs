@@ -0,0 +1,152 @@
+use super::items::{store_items, ITEM_COLUMNS};
+use super::{Database, Item, ItemAuthor};
+use anyhow::Result;
+use sqlx::SqliteConnection;
+use teloxide::types::{ChatId, MessageId};
+
+impl Database {
+    /// Add items like [`Database::add_items_by`] and remember that
+    /// `message_id` put them on the list, so an edit of the message can
+    /// update them later.
+    pub async fn add_message_items(
+        &self,
+        chat_id: ChatId,
+        message_id: MessageId,
+        items: &[String],
+        author: Option<&ItemAuthor>,
+    ) -> Result<u64> {
+        if items.is_empty() {
+            return Ok(0);
+        }
+
+        let mut tx = self.begin_write().await?;
+        let newest: i64 = sqlx::query_scalar("SELECT COALESCE(MAX(id), 0) FROM items")
+            .fetch_one(&mut *tx)
+            .await?;
+        let ids = store_items(&mut tx, chat_id, items, author).await?;
+        for id in &ids {
+            sqlx::query(
+                "INSERT OR IGNORE INTO message_items (chat_id, message_id, item_id, created) \
+                 VALUES (?, ?, ?, ?)",
+            )
+            .bind(chat_id.0)
+            .bind(message_id.0)
+            .bind(id)
+            .bind(*id > newest)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        tracing::debug!(
+            chat_id = chat_id.0,
+            message_id = message_id.0,
+            count = ids.len(),
+            "Recorded message items"
+        );
+        Ok(items.len() as u64)
+    }
+
+    /// The live items `message_id` put on the list, with whether the message
+    /// created each one rather than adding to an existing item. `None` when
+    /// the message added nothing to the current list, which includes every
+    /// message sent before the list was last archived or nuked.
+    pub async fn message_items(
+        &self,
+        chat_id: ChatId,
+        message_id: MessageId,
+    ) -> Result<Option<Vec<(Item, bool)>>> {
+        tracing::trace!(
+            chat_id = chat_id.0,
+            message_id = message_id.0,
+            "Loading message items"
+        );
+        let created: Vec<(i64, bool)> = sqlx::query_as(
+            "SELECT item_id, created FROM message_items WHERE chat_id = ? AND message_id = ?",
+        )
+        .bind(chat_id.0)
+        .bind(message_id.0)
+        .fetch_all(self.pool())
+        .await?;
+        if created.is_empty() {
+            return Ok(None);
+        }
+        let items: Vec<Item> = sqlx::query_as(&format!(
+            "SELECT {ITEM_COLUMNS} FROM items WHERE chat_id = ? AND deleted_at IS NULL AND id IN \
+             (SELECT item_id FROM message_items WHERE chat_id = ? AND message_id = ?) \
+             ORDER BY position, id"
+        ))
+        .bind(chat_id.0)
+        .bind(chat_id.0)
+        .bind(message_id.0)
+        .fetch_all(self.pool())
+        .await?;
+        Ok(Some(
+            items
+                .into_iter()
+                .map(|item| {
+                    let made = created.iter().any(|&(id, made)| id == item.id && made);
+                    (item, made)
+                })
+                .collect(),
+        ))
+    }
+}
+
+/// Forget which messages added the chat's items; called when the whole list
+/// is archived or nuked so edits of older messages are ignored.
+pub(super) async fn clear_message_items(
+    conn: &mut SqliteConnection,
+    chat_id: ChatId,
+) -> Result<()> {
+    let cleared = sqlx::query("DELETE FROM message_items WHERE chat_id = ?")
+        .bind(chat_id.0)
+        .execute(conn)
+        .await?
+        .rows_affected();
+    tracing::trace!(chat_id = chat_id.0, cleared, "Cleared message items");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::util::init_test_db;
+
+    fn texts(items: &[(Item, bool)]) -> Vec<(&str, bool)> {
+        items
+            .iter()
+            .map(|(item, created)| (item.text.as_str(), *created))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn records_created_and_merged_items() -> Result<()> {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_item_count(chat, "Milk").await?;
+        db.add_message_items(chat, MessageId(7), &["milk".into(), "Eggs".into()], None)
+            .await?;
+
+        let items = db.message_items(chat, MessageId(7)).await?.unwrap();
+        assert_eq!(texts(&items), [("Milk", false), ("Eggs", true)]);
+        assert_eq!(db.message_items(chat, MessageId(8)).await?, None);
+        assert_eq!(db.message_items(ChatId(2), MessageId(7)).await?, None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn archive_and_nuke_start_a_new_epoch() -> Result<()> {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_message_items(chat, MessageId(7), &["Eggs".into()], None)
+            .await?;
+        db.archive_items(chat, 10).await?;
+        assert_eq!(db.message_items(chat, MessageId(7)).await?, None);
+
+        db.add_message_items(chat, MessageId(8), &["Tea".into()], None)
+            .await?;
+        db.delete_all_items_count(chat).await?;
+        assert_eq!(db.message_items(chat, MessageId(8)).await?, None);
+        Ok(())
+    }
+}
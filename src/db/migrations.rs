@@ -0,0 +1,413 @@
+use anyhow::{anyhow, bail, Context, Result};
+use sqlx::migrate::{MigrateError, Migration, Migrator};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+
+use super::Database;
+
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// Schema object a migration is expected to leave behind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaObject {
+    Table(&'static str),
+    Column(&'static str, &'static str),
+    Index(&'static str),
+    Trigger(&'static str),
+}
+
+/// Objects created by each migration, used to verify a dirty migration
+/// actually completed before it is marked resolved. Every file in
+/// `migrations/` needs an entry here.
+pub fn expected_objects(version: i64) -> Option<&'static [SchemaObject]> {
+    use SchemaObject::*;
+    let objects: &'static [SchemaObject] = match version {
+        20240101000000 => &[Table("items"), Table("chat_state"), Table("delete_session")],
+        20240102000000 => &[Column("delete_session", "dm_message_id")],
+        20240103000000 => &[Index("items_chat_id_idx")],
+        20240104000000 => &[Table("tokens"), Index("tokens_chat_id_index")],
+        20240105000000 => &[
+            Column("tokens", "name"),
+            Column("tokens", "issuer_user_id"),
+            Column("tokens", "issuer_name"),
+        ],
+        20240106000000 => &[Table("chat_settings")],
+        20240107000000 => &[Table("bot_permissions")],
+        20240108000000 => &[Table("duplicate_prompt")],
+        20240109000000 => &[
+            Column("chat_state", "refresh_pending"),
+            Column("chat_state", "pending_notice"),
+        ],
+        20240110000000 => &[Table("settings_panel")],
+        20240111000000 => &[Column("items", "quantity")],
+        20240112000000 => &[Table("archives"), Index("archives_chat_id_idx")],
+        20240113000000 => &[Table("undo_log")],
+        20240114000000 => &[Table("edit_session")],
+        20240115000000 => &[
+            Column("items", "added_by_user_id"),
+            Column("items", "added_by_name"),
+        ],
+        20240116000000 => &[Column("items", "category")],
+        20240117000000 => &[Column("items", "created_at")],
+        20240118000000 => &[Column("items", "deleted_at")],
+        20240119000000 => &[Table("item_history"), Index("item_history_chat_key_idx")],
+        20240120000000 => &[
+            Column("tokens", "token_hash"),
+            Column("tokens", "token_preview"),
+            Index("tokens_token_hash_idx"),
+        ],
+        20240121000000 => &[Column("tokens", "expires_at")],
+        20240122000000 => &[Column("tokens", "scope")],
+        20240123000000 => &[Table("webhooks"), Index("webhooks_chat_id_idx")],
+        20240124000000 => &[Table("share_links"), Index("share_links_chat_id_idx")],
+        20240125000000 => &[Column("chat_state", "revision")],
+        20240126000000 => &[Column("items", "position")],
+        20240127000000 => &[Column("chat_state", "list_page")],
+        20240128000000 => &[Column("delete_session", "page")],
+        20240129000000 => &[Table("pairings")],
+        20240130000000 => &[Column("chat_state", "thread_id")],
+        20240131000000 => &[Table("message_items")],
+        20240201000000 => &[Column("items", "done_at")],
+        20240202000000 => &[
+            Column("items", "assigned_to_user_id"),
+            Column("items", "assigned_to_name"),
+            Table("assign_session"),
+        ],
+        20240203000000 => &[Column("delete_session", "created_at")],
+        20240204000000 => &[Table("voice_deletion_prompt")],
+        20240205000000 => &[Column("items", "price_cents")],
+        20240206000000 => &[
+            Trigger("items_insert_revision"),
+            Trigger("items_update_revision"),
+            Trigger("items_delete_revision"),
+        ],
+        20240207000000 => &[Table("pending_album_photos")],
+        _ => return None,
+    };
+    Some(objects)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationState {
+    Applied,
+    Pending,
+    /// Recorded as started but never finished.
+    Dirty,
+    /// Applied, but the file on disk no longer matches the recorded checksum.
+    Modified,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub file_name: String,
+    pub state: MigrationState,
+}
+
+fn file_name(migration: &Migration) -> String {
+    format!(
+        "{}_{}.sql",
+        migration.version,
+        migration.description.replace(' ', "_")
+    )
+}
+
+fn migration_file_name(migrator: &Migrator, version: i64) -> String {
+    migrator
+        .iter()
+        .find(|migration| migration.version == version)
+        .map(file_name)
+        .unwrap_or_else(|| format!("{version} (not in migrations/)"))
+}
+
+async fn applied_migrations(pool: &SqlitePool) -> Result<HashMap<i64, (bool, Vec<u8>)>> {
+    let exists: bool = sqlx::query_scalar(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations')",
+    )
+    .fetch_one(pool)
+    .await?;
+    if !exists {
+        return Ok(HashMap::new());
+    }
+    let rows: Vec<(i64, bool, Vec<u8>)> =
+        sqlx::query_as("SELECT version, success, checksum FROM _sqlx_migrations")
+            .fetch_all(pool)
+            .await?;
+    Ok(rows
+        .into_iter()
+        .map(|(version, success, checksum)| (version, (success, checksum)))
+        .collect())
+}
+
+/// Compare the migrations on disk with what the database has recorded.
+pub async fn migration_status(
+    pool: &SqlitePool,
+    migrator: &Migrator,
+) -> Result<Vec<MigrationStatus>> {
+    let applied = applied_migrations(pool).await?;
+    Ok(migrator
+        .iter()
+        .map(|migration| {
+            let state = match applied.get(&migration.version) {
+                None => MigrationState::Pending,
+                Some((false, _)) => MigrationState::Dirty,
+                Some((true, checksum)) if checksum.as_slice() != &*migration.checksum => {
+                    MigrationState::Modified
+                }
+                Some((true, _)) => MigrationState::Applied,
+            };
+            MigrationStatus {
+                version: migration.version,
+                file_name: file_name(migration),
+                state,
+            }
+        })
+        .collect())
+}
+
+/// Schema objects from `objects` that are missing in the database.
+pub async fn missing_objects(
+    pool: &SqlitePool,
+    objects: &[SchemaObject],
+) -> Result<Vec<SchemaObject>> {
+    let mut missing = Vec::new();
+    for object in objects {
+        let exists: bool = match *object {
+            SchemaObject::Table(name) => {
+                sqlx::query_scalar(
+                    "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?)",
+                )
+                .bind(name)
+                .fetch_one(pool)
+                .await?
+            }
+            SchemaObject::Index(name) => {
+                sqlx::query_scalar(
+                    "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'index' AND name = ?)",
+                )
+                .bind(name)
+                .fetch_one(pool)
+                .await?
+            }
+            SchemaObject::Trigger(name) => sqlx::query_scalar(
+                "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'trigger' AND name = ?)",
+            )
+            .bind(name)
+            .fetch_one(pool)
+            .await?,
+            SchemaObject::Column(table, column) => {
+                sqlx::query_scalar(
+                    "SELECT EXISTS(SELECT 1 FROM pragma_table_info(?) WHERE name = ?)",
+                )
+                .bind(table)
+                .bind(column)
+                .fetch_one(pool)
+                .await?
+            }
+        };
+        if !exists {
+            missing.push(*object);
+        }
+    }
+    Ok(missing)
+}
+
+fn log_status(statuses: &[MigrationStatus]) {
+    for status in statuses {
+        match status.state {
+            MigrationState::Applied | MigrationState::Pending => tracing::debug!(
+                version = status.version,
+                file = %status.file_name,
+                state = ?status.state,
+                "Migration status"
+            ),
+            MigrationState::Dirty | MigrationState::Modified => tracing::warn!(
+                version = status.version,
+                file = %status.file_name,
+                state = ?status.state,
+                "Migration needs attention"
+            ),
+        }
+    }
+    let count = |state| statuses.iter().filter(|s| s.state == state).count();
+    tracing::info!(
+        applied = count(MigrationState::Applied),
+        pending = count(MigrationState::Pending),
+        dirty = count(MigrationState::Dirty),
+        modified = count(MigrationState::Modified),
+        "Migration status report"
+    );
+}
+
+fn explain_failure(migrator: &Migrator, err: MigrateError) -> anyhow::Error {
+    match err {
+        MigrateError::ExecuteMigration(source, version) => anyhow!(source).context(format!(
+            "Migration {} failed",
+            migration_file_name(migrator, version)
+        )),
+        MigrateError::Dirty(version) => anyhow!(
+            "Migration {} is partially applied; run with --repair-migrations once the schema is fixed",
+            migration_file_name(migrator, version)
+        ),
+        MigrateError::VersionMismatch(version) => anyhow!(
+            "Migration {} was applied but the file has changed since; restore the original file",
+            migration_file_name(migrator, version)
+        ),
+        other => anyhow!(other).context("Database migration failed"),
+    }
+}
+
+/// Log the migration status report and apply pending migrations.
+pub async fn run_migrations(db: &Database) -> Result<()> {
+    let statuses = migration_status(db.pool(), &MIGRATOR).await?;
+    log_status(&statuses);
+    MIGRATOR
+        .run(db.pool())
+        .await
+        .map_err(|err| explain_failure(&MIGRATOR, err))?;
+    db.hash_plaintext_tokens().await?;
+    Ok(())
+}
+
+/// Mark dirty migrations as applied after verifying the objects they create
+/// exist. Returns the versions that were repaired.
+pub async fn repair_migrations(pool: &SqlitePool, migrator: &Migrator) -> Result<Vec<i64>> {
+    let mut repaired = Vec::new();
+    for status in migration_status(pool, migrator).await? {
+        if status.state != MigrationState::Dirty {
+            continue;
+        }
+        let objects = expected_objects(status.version)
+            .with_context(|| format!("No schema checks are known for {}", status.file_name))?;
+        let missing = missing_objects(pool, objects).await?;
+        if !missing.is_empty() {
+            bail!(
+                "Cannot repair {}: missing {:?}; restore from a backup or apply the remaining statements by hand",
+                status.file_name,
+                missing
+            );
+        }
+        sqlx::query("UPDATE _sqlx_migrations SET success = TRUE WHERE version = ?")
+            .bind(status.version)
+            .execute(pool)
+            .await?;
+        tracing::info!(version = status.version, file = %status.file_name, "Marked migration as applied");
+        repaired.push(status.version);
+    }
+    Ok(repaired)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    async fn empty_pool() -> SqlitePool {
+        SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap()
+    }
+
+    async fn mark_dirty(pool: &SqlitePool, version: i64) {
+        sqlx::query("UPDATE _sqlx_migrations SET success = FALSE WHERE version = ?")
+            .bind(version)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn every_migration_has_schema_checks() {
+        for migration in MIGRATOR.iter() {
+            assert!(
+                expected_objects(migration.version).is_some(),
+                "add expected objects for {}",
+                file_name(migration)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn status_reports_pending_then_applied() {
+        let pool = empty_pool().await;
+        let statuses = migration_status(&pool, &MIGRATOR).await.unwrap();
+        assert!(statuses
+            .iter()
+            .all(|status| status.state == MigrationState::Pending));
+        assert_eq!(statuses[0].file_name, "20240101000000_init.sql");
+
+        MIGRATOR.run(&pool).await.unwrap();
+        let statuses = migration_status(&pool, &MIGRATOR).await.unwrap();
+        assert!(statuses
+            .iter()
+            .all(|status| status.state == MigrationState::Applied));
+    }
+
+    #[tokio::test]
+    async fn expected_objects_exist_after_migrating() {
+        let pool = empty_pool().await;
+        MIGRATOR.run(&pool).await.unwrap();
+        for migration in MIGRATOR.iter() {
+            let objects = expected_objects(migration.version).unwrap();
+            assert!(missing_objects(&pool, objects).await.unwrap().is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn dirty_migration_with_schema_is_repaired() {
+        let pool = empty_pool().await;
+        MIGRATOR.run(&pool).await.unwrap();
+        mark_dirty(&pool, 20240105000000).await;
+
+        let err = MIGRATOR.run(&pool).await.unwrap_err();
+        let message = format!("{:#}", explain_failure(&MIGRATOR, err));
+        assert!(message.contains("20240105000000_add_token_metadata.sql"));
+        assert!(message.contains("--repair-migrations"));
+
+        let repaired = repair_migrations(&pool, &MIGRATOR).await.unwrap();
+        assert_eq!(repaired, vec![20240105000000]);
+        MIGRATOR.run(&pool).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn dirty_migration_missing_column_is_refused() {
+        let pool = empty_pool().await;
+        MIGRATOR.run(&pool).await.unwrap();
+        // Simulate a crash after the first ALTER of the token metadata migration.
+        sqlx::query("ALTER TABLE tokens DROP COLUMN issuer_name")
+            .execute(&pool)
+            .await
+            .unwrap();
+        mark_dirty(&pool, 20240105000000).await;
+
+        let err = repair_migrations(&pool, &MIGRATOR).await.unwrap_err();
+        assert!(err.to_string().contains("issuer_name"));
+        let statuses = migration_status(&pool, &MIGRATOR).await.unwrap();
+        let token_metadata = statuses
+            .iter()
+            .find(|status| status.version == 20240105000000)
+            .unwrap();
+        assert_eq!(token_metadata.state, MigrationState::Dirty);
+    }
+
+    #[tokio::test]
+    async fn missing_table_is_detected() {
+        let pool = empty_pool().await;
+        sqlx::query("CREATE TABLE items (id INTEGER PRIMARY KEY)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let missing = missing_objects(&pool, expected_objects(20240101000000).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            missing,
+            vec![
+                SchemaObject::Table("chat_state"),
+                SchemaObject::Table("delete_session")
+            ]
+        );
+    }
+}
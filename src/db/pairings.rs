@@ -0,0 +1,150 @@
+use super::Database;
+use anyhow::Result;
+use teloxide::types::ChatId;
+
+/// Outcome of exchanging a pairing code for its chat.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PairingClaim {
+    /// The code was bound to a chat and is now used up.
+    Claimed {
+        chat_id: ChatId,
+        issuer_user_id: Option<i64>,
+        issuer_name: Option<String>,
+    },
+    /// Live, but nobody has opened the link yet.
+    Pending,
+    /// Unknown, expired or already claimed.
+    Gone,
+}
+
+impl Database {
+    /// Store a new unbound code. Expired codes are pruned on the way.
+    pub async fn create_pairing(&self, code: &str, created_at: i64, expires_at: i64) -> Result<()> {
+        tracing::debug!(expires_at, "Creating pairing");
+        let mut tx = self.begin_write().await?;
+        let pruned = sqlx::query("DELETE FROM pairings WHERE expires_at <= ?")
+            .bind(created_at)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        sqlx::query("INSERT INTO pairings (code, created_at, expires_at) VALUES (?, ?, ?)")
+            .bind(code)
+            .bind(created_at)
+            .bind(expires_at)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        if pruned > 0 {
+            tracing::debug!(pruned, "Pruned expired pairings");
+        }
+        Ok(())
+    }
+
+    /// Bind a live, unbound code to the chat that opened it. Returns whether
+    /// the code was bound.
+    pub async fn bind_pairing(
+        &self,
+        code: &str,
+        chat_id: ChatId,
+        issuer_user_id: Option<i64>,
+        issuer_name: Option<&str>,
+        now: i64,
+    ) -> Result<bool> {
+        tracing::debug!(chat_id = chat_id.0, "Binding pairing");
+        let result = sqlx::query(
+            "UPDATE pairings SET chat_id = ?, issuer_user_id = ?, issuer_name = ? \
+             WHERE code = ? AND chat_id IS NULL AND expires_at > ?",
+        )
+        .bind(chat_id.0)
+        .bind(issuer_user_id)
+        .bind(issuer_name)
+        .bind(code)
+        .bind(now)
+        .execute(self.pool())
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Use up a bound code. Only the first claim of a live code succeeds.
+    pub async fn claim_pairing(&self, code: &str, now: i64) -> Result<PairingClaim> {
+        tracing::trace!("Claiming pairing");
+        let claimed: Option<(i64, Option<i64>, Option<String>)> = sqlx::query_as(
+            "UPDATE pairings SET claimed_at = ? \
+             WHERE code = ? AND chat_id IS NOT NULL AND claimed_at IS NULL AND expires_at > ? \
+             RETURNING chat_id, issuer_user_id, issuer_name",
+        )
+        .bind(now)
+        .bind(code)
+        .bind(now)
+        .fetch_optional(self.pool())
+        .await?;
+        if let Some((chat_id, issuer_user_id, issuer_name)) = claimed {
+            return Ok(PairingClaim::Claimed {
+                chat_id: ChatId(chat_id),
+                issuer_user_id,
+                issuer_name,
+            });
+        }
+        let pending: Option<i64> = sqlx::query_scalar(
+            "SELECT 1 FROM pairings WHERE code = ? AND chat_id IS NULL AND expires_at > ?",
+        )
+        .bind(code)
+        .bind(now)
+        .fetch_optional(self.pool())
+        .await?;
+        Ok(if pending.is_some() {
+            PairingClaim::Pending
+        } else {
+            PairingClaim::Gone
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::util::init_test_db;
+
+    #[tokio::test]
+    async fn codes_bind_once_and_claim_once() -> Result<()> {
+        let db = init_test_db().await;
+        db.create_pairing("abc", 0, 300).await?;
+
+        assert_eq!(db.claim_pairing("abc", 10).await?, PairingClaim::Pending);
+        assert!(
+            db.bind_pairing("abc", ChatId(1), Some(5), Some("Ann"), 10)
+                .await?
+        );
+        assert!(!db.bind_pairing("abc", ChatId(2), None, None, 10).await?);
+        assert_eq!(
+            db.claim_pairing("abc", 20).await?,
+            PairingClaim::Claimed {
+                chat_id: ChatId(1),
+                issuer_user_id: Some(5),
+                issuer_name: Some("Ann".to_string()),
+            }
+        );
+        assert_eq!(db.claim_pairing("abc", 30).await?, PairingClaim::Gone);
+        assert_eq!(db.claim_pairing("missing", 30).await?, PairingClaim::Gone);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn expired_codes_neither_bind_nor_claim() -> Result<()> {
+        let db = init_test_db().await;
+        db.create_pairing("late", 0, 300).await?;
+        db.create_pairing("bound", 0, 300).await?;
+        assert!(db.bind_pairing("bound", ChatId(1), None, None, 100).await?);
+
+        assert!(!db.bind_pairing("late", ChatId(1), None, None, 300).await?);
+        assert_eq!(db.claim_pairing("late", 300).await?, PairingClaim::Gone);
+        assert_eq!(db.claim_pairing("bound", 300).await?, PairingClaim::Gone);
+
+        db.create_pairing("fresh", 400, 700).await?;
+        let remaining: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM pairings")
+            .fetch_one(db.pool())
+            .await?;
+        assert_eq!(remaining, 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+use super::Database;
+use anyhow::Result;
+use teloxide::types::{ChatId, MessageId};
+
+impl Database {
+    /// Record the chat's settings panel, returning the panel it replaces.
+    pub async fn open_settings_panel(
+        &self,
+        chat_id: ChatId,
+        message_id: MessageId,
+        now: i64,
+    ) -> Result<Option<MessageId>> {
+        tracing::debug!(
+            chat_id = chat_id.0,
+            message_id = message_id.0,
+            "Opening settings panel"
+        );
+        let mut tx = self.begin_write().await?;
+        let previous: Option<i32> =
+            sqlx::query_scalar("SELECT message_id FROM settings_panel WHERE chat_id = ?")
+                .bind(chat_id.0)
+                .fetch_optional(&mut *tx)
+                .await?;
+        sqlx::query(
+            "INSERT INTO settings_panel (chat_id, message_id, last_used_at) VALUES (?, ?, ?) \
+             ON CONFLICT(chat_id) DO UPDATE SET message_id = excluded.message_id, \
+             last_used_at = excluded.last_used_at",
+        )
+        .bind(chat_id.0)
+        .bind(message_id.0)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(previous.map(MessageId))
+    }
+
+    /// Mark the panel as used. Returns `false` when it is no longer open.
+    pub async fn touch_settings_panel(
+        &self,
+        chat_id: ChatId,
+        message_id: MessageId,
+        now: i64,
+    ) -> Result<bool> {
+        tracing::trace!(
+            chat_id = chat_id.0,
+            message_id = message_id.0,
+            "Touching settings panel"
+        );
+        let result = sqlx::query(
+            "UPDATE settings_panel SET last_used_at = ? WHERE chat_id = ? AND message_id = ?",
+        )
+        .bind(now)
+        .bind(chat_id.0)
+        .bind(message_id.0)
+        .execute(self.pool())
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Close the panel if it has not been used since `idle_since`. Returns
+    /// whether it was closed.
+    pub async fn expire_settings_panel(
+        &self,
+        chat_id: ChatId,
+        message_id: MessageId,
+        idle_since: i64,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            "DELETE FROM settings_panel WHERE chat_id = ? AND message_id = ? AND last_used_at <= ?",
+        )
+        .bind(chat_id.0)
+        .bind(message_id.0)
+        .bind(idle_since)
+        .execute(self.pool())
+        .await?;
+        let expired = result.rows_affected() == 1;
+        tracing::debug!(
+            chat_id = chat_id.0,
+            message_id = message_id.0,
+            expired,
+            "Checked settings panel expiry"
+        );
+        Ok(expired)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::util::init_test_db;
+
+    #[tokio::test]
+    async fn panel_expires_only_when_idle() -> Result<()> {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        assert_eq!(db.open_settings_panel(chat, MessageId(5), 100).await?, None);
+        assert!(db.touch_settings_panel(chat, MessageId(5), 150).await?);
+        assert!(!db.expire_settings_panel(chat, MessageId(5), 120).await?);
+        assert!(db.expire_settings_panel(chat, MessageId(5), 150).await?);
+        assert!(!db.touch_settings_panel(chat, MessageId(5), 200).await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn new_panel_replaces_old() -> Result<()> {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.open_settings_panel(chat, MessageId(5), 100).await?;
+        assert_eq!(
+            db.open_settings_panel(chat, MessageId(6), 110).await?,
+            Some(MessageId(5))
+        );
+        assert!(!db.touch_settings_panel(chat, MessageId(5), 120).await?);
+        assert!(db.touch_settings_panel(chat, MessageId(6), 120).await?);
+        Ok(())
+    }
+}
@@ -0,0 +1,87 @@
+use super::Database;
+use anyhow::Result;
+use teloxide::types::ChatId;
+
+impl Database {
+    /// The slug of the chat's live share link, if one was created and not
+    /// revoked since.
+    pub async fn active_share_link(&self, chat_id: ChatId) -> Result<Option<String>> {
+        tracing::trace!(chat_id = chat_id.0, "Loading share link");
+        sqlx::query_scalar(
+            "SELECT slug FROM share_links WHERE chat_id = ? AND revoked_at IS NULL \
+             ORDER BY created_at DESC LIMIT 1",
+        )
+        .bind(chat_id.0)
+        .fetch_optional(self.pool())
+        .await
+        .map_err(Into::into)
+    }
+
+    pub async fn create_share_link(
+        &self,
+        chat_id: ChatId,
+        slug: &str,
+        created_at: i64,
+    ) -> Result<()> {
+        tracing::debug!(chat_id = chat_id.0, "Creating share link");
+        sqlx::query("INSERT INTO share_links (slug, chat_id, created_at) VALUES (?, ?, ?)")
+            .bind(slug)
+            .bind(chat_id.0)
+            .bind(created_at)
+            .execute(self.pool())
+            .await?;
+        Ok(())
+    }
+
+    /// Revoke every live share link of the chat. Returns how many were revoked.
+    pub async fn revoke_share_links(&self, chat_id: ChatId, revoked_at: i64) -> Result<u64> {
+        tracing::debug!(chat_id = chat_id.0, "Revoking share links");
+        let result = sqlx::query(
+            "UPDATE share_links SET revoked_at = ? WHERE chat_id = ? AND revoked_at IS NULL",
+        )
+        .bind(revoked_at)
+        .bind(chat_id.0)
+        .execute(self.pool())
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Resolve a slug to its chat; revoked and unknown slugs both yield `None`.
+    pub async fn share_link_chat(&self, slug: &str) -> Result<Option<ChatId>> {
+        tracing::trace!("Resolving share link");
+        let chat_id: Option<i64> = sqlx::query_scalar(
+            "SELECT chat_id FROM share_links WHERE slug = ? AND revoked_at IS NULL",
+        )
+        .bind(slug)
+        .fetch_optional(self.pool())
+        .await?;
+        Ok(chat_id.map(ChatId))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::util::init_test_db;
+
+    #[tokio::test]
+    async fn revoked_links_stop_resolving() -> Result<()> {
+        let db = init_test_db().await;
+        db.create_share_link(ChatId(1), "abc", 10).await?;
+        db.create_share_link(ChatId(2), "other", 10).await?;
+
+        assert_eq!(
+            db.active_share_link(ChatId(1)).await?.as_deref(),
+            Some("abc")
+        );
+        assert_eq!(db.share_link_chat("abc").await?, Some(ChatId(1)));
+        assert_eq!(db.share_link_chat("missing").await?, None);
+
+        assert_eq!(db.revoke_share_links(ChatId(1), 20).await?, 1);
+        assert_eq!(db.revoke_share_links(ChatId(1), 21).await?, 0);
+        assert_eq!(db.active_share_link(ChatId(1)).await?, None);
+        assert_eq!(db.share_link_chat("abc").await?, None);
+        assert_eq!(db.share_link_chat("other").await?, Some(ChatId(2)));
+        Ok(())
+    }
+}
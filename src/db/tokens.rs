@@ -1,18 +1,94 @@
 use super::Database;
 use anyhow::Result;
+use sha2::{Digest, Sha256};
 use teloxide::types::ChatId;
 
+const TOKEN_COLUMNS: &str = "id, chat_id, token_hash, token_preview, name, issuer_user_id, issuer_name, issued_at, last_used_at, revoked_at, expires_at, scope";
+
+/// Only the hash of a token is stored, so a copy of the database cannot be
+/// used to call the API. The preview is enough to tell tokens apart.
 #[derive(sqlx::FromRow, Debug, Clone, PartialEq)]
 pub struct TokenRecord {
     pub id: i64,
     pub chat_id: i64,
-    pub token: String,
+    pub token_hash: String,
+    pub token_preview: String,
     pub name: Option<String>,
     pub issuer_user_id: Option<i64>,
     pub issuer_name: Option<String>,
     pub issued_at: i64,
     pub last_used_at: Option<i64>,
     pub revoked_at: Option<i64>,
+    pub expires_at: Option<i64>,
+    pub scope: String,
+}
+
+/// What an API token may do with its chat's list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TokenScope {
+    /// Only read the list.
+    Read,
+    /// Read and change the list.
+    #[default]
+    Write,
+    /// Read and change the list and manage the chat's tokens.
+    Admin,
+}
+
+impl TokenScope {
+    pub const ALL: [Self; 3] = [Self::Read, Self::Write, Self::Admin];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Write => "write",
+            Self::Admin => "admin",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|scope| scope.name() == name)
+    }
+
+    pub fn can_write(self) -> bool {
+        matches!(self, Self::Write | Self::Admin)
+    }
+
+    pub fn can_manage_tokens(self) -> bool {
+        self == Self::Admin
+    }
+}
+
+impl TokenRecord {
+    /// Unknown stored scopes are treated as read-only.
+    pub fn scope(&self) -> TokenScope {
+        TokenScope::parse(&self.scope).unwrap_or(TokenScope::Read)
+    }
+
+    /// A token stops working at the second it expires.
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+}
+
+/// Everything stored with a new token besides its chat and secret. Unset
+/// fields default to an anonymous write token that never expires.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NewToken<'a> {
+    pub name: Option<&'a str>,
+    pub issuer_user_id: Option<i64>,
+    pub issuer_name: Option<&'a str>,
+    pub issued_at: i64,
+    pub expires_at: Option<i64>,
+    pub scope: TokenScope,
+}
+
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+pub fn token_preview(token: &str) -> String {
+    token.chars().take(6).collect()
 }
 
 impl Database {
@@ -20,39 +96,48 @@ impl Database {
         &self,
         chat_id: ChatId,
         token: &str,
-        name: Option<&str>,
-        issuer_user_id: Option<i64>,
-        issuer_name: Option<&str>,
-        issued_at: i64,
-    ) -> Result<()> {
+        new: &NewToken<'_>,
+    ) -> Result<i64> {
+        let NewToken {
+            name,
+            issuer_user_id,
+            issuer_name,
+            issued_at,
+            expires_at,
+            scope,
+        } = *new;
         tracing::debug!(
             chat_id = chat_id.0,
             issued_at,
+            expires_at,
+            scope = scope.name(),
             name,
             issuer_user_id,
             issuer_name,
             "Creating token for chat"
         );
-        sqlx::query(
-            "INSERT INTO tokens (chat_id, token, name, issuer_user_id, issuer_name, issued_at) VALUES (?, ?, ?, ?, ?, ?)",
+        let id = sqlx::query_scalar(
+            "INSERT INTO tokens (chat_id, token_hash, token_preview, name, issuer_user_id, issuer_name, issued_at, expires_at, scope) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING id",
         )
             .bind(chat_id.0)
-            .bind(token)
+            .bind(hash_token(token))
+            .bind(token_preview(token))
             .bind(name)
             .bind(issuer_user_id)
             .bind(issuer_name)
             .bind(issued_at)
-            .execute(self.pool())
+            .bind(expires_at)
+            .bind(scope.name())
+            .fetch_one(self.pool())
             .await?;
-        Ok(())
+        Ok(id)
     }
 
     pub async fn list_tokens(&self, chat_id: ChatId) -> Result<Vec<TokenRecord>> {
         tracing::trace!(chat_id = chat_id.0, "Listing tokens");
-        sqlx::query_as(
-            "SELECT id, chat_id, token, name, issuer_user_id, issuer_name, issued_at, last_used_at, revoked_at \
-             FROM tokens WHERE chat_id = ? ORDER BY issued_at DESC, id DESC",
-        )
+        sqlx::query_as(&format!(
+            "SELECT {TOKEN_COLUMNS} FROM tokens WHERE chat_id = ? ORDER BY issued_at DESC, id DESC"
+        ))
         .bind(chat_id.0)
         .fetch_all(self.pool())
         .await
@@ -67,36 +152,87 @@ impl Database {
     ) -> Result<bool> {
         tracing::debug!(chat_id = chat_id.0, revoked_at, "Revoking token for chat");
         let result = sqlx::query(
-            "UPDATE tokens SET revoked_at = ? WHERE chat_id = ? AND token = ? AND revoked_at IS NULL",
+            "UPDATE tokens SET revoked_at = ? WHERE chat_id = ? AND token_hash = ? AND revoked_at IS NULL",
         )
         .bind(revoked_at)
         .bind(chat_id.0)
-        .bind(token)
+        .bind(hash_token(token))
         .execute(self.pool())
         .await?;
         Ok(result.rows_affected() > 0)
     }
 
-    pub async fn use_token(&self, token: &str, used_at: i64) -> Result<Option<ChatId>> {
-        let chat_id: Option<i64> =
-            sqlx::query_scalar("SELECT chat_id FROM tokens WHERE token = ? AND revoked_at IS NULL")
-                .bind(token)
-                .fetch_optional(self.pool())
-                .await?;
+    /// Revoke one of the chat's tokens by its row id. Returns whether an
+    /// active token was revoked.
+    pub async fn revoke_token_by_id(
+        &self,
+        chat_id: ChatId,
+        id: i64,
+        revoked_at: i64,
+    ) -> Result<bool> {
+        tracing::debug!(
+            chat_id = chat_id.0,
+            token_id = id,
+            revoked_at,
+            "Revoking token by id"
+        );
+        let result = sqlx::query(
+            "UPDATE tokens SET revoked_at = ? WHERE chat_id = ? AND id = ? AND revoked_at IS NULL",
+        )
+        .bind(revoked_at)
+        .bind(chat_id.0)
+        .bind(id)
+        .execute(self.pool())
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
 
-        if let Some(chat_id) = chat_id {
-            sqlx::query(
-                "UPDATE tokens SET last_used_at = ? WHERE token = ? AND revoked_at IS NULL",
-            )
-            .bind(used_at)
-            .bind(token)
-            .execute(self.pool())
-            .await?;
-            tracing::debug!(chat_id, used_at, "Updated token last_used_at");
-            return Ok(Some(ChatId(chat_id)));
-        }
+    /// Resolve a token to its chat and scope. Revoked and expired tokens
+    /// resolve to `None`.
+    pub async fn use_token(
+        &self,
+        token: &str,
+        used_at: i64,
+    ) -> Result<Option<(ChatId, TokenScope)>> {
+        let row: Option<(i64, String)> = sqlx::query_as(
+            "UPDATE tokens SET last_used_at = ? \
+             WHERE token_hash = ? AND revoked_at IS NULL AND (expires_at IS NULL OR expires_at > ?) \
+             RETURNING chat_id, scope",
+        )
+        .bind(used_at)
+        .bind(hash_token(token))
+        .bind(used_at)
+        .fetch_optional(self.pool())
+        .await?;
+
+        Ok(row.map(|(chat_id, scope)| {
+            tracing::debug!(chat_id, used_at, scope = %scope, "Updated token last_used_at");
+            let scope = TokenScope::parse(&scope).unwrap_or(TokenScope::Read);
+            (ChatId(chat_id), scope)
+        }))
+    }
 
-        Ok(None)
+    /// Hash tokens stored in plaintext before hashing was introduced. Those
+    /// rows still have an empty preview. Returns how many were converted.
+    pub async fn hash_plaintext_tokens(&self) -> Result<u64> {
+        let mut tx = self.begin_write().await?;
+        let rows: Vec<(i64, String)> =
+            sqlx::query_as("SELECT id, token_hash FROM tokens WHERE token_preview = ''")
+                .fetch_all(&mut *tx)
+                .await?;
+        for (id, plaintext) in &rows {
+            sqlx::query("UPDATE tokens SET token_hash = ?, token_preview = ? WHERE id = ?")
+                .bind(hash_token(plaintext))
+                .bind(token_preview(plaintext))
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        if !rows.is_empty() {
+            tracing::info!(count = rows.len(), "Hashed plaintext tokens");
+        }
+        Ok(rows.len() as u64)
     }
 }
 
@@ -111,15 +247,30 @@ mod tests {
     async fn token_create_and_list() -> Result<()> {
         let db = init_test_db().await;
         let chat_id = ChatId(42);
-        db.create_token(chat_id, "token-a", None, None, None, 100)
-            .await?;
-        db.create_token(chat_id, "token-b", None, None, None, 200)
-            .await?;
+        db.create_token(
+            chat_id,
+            "token-a",
+            &NewToken {
+                issued_at: 100,
+                ..Default::default()
+            },
+        )
+        .await?;
+        db.create_token(
+            chat_id,
+            "token-b",
+            &NewToken {
+                issued_at: 200,
+                ..Default::default()
+            },
+        )
+        .await?;
 
         let tokens = db.list_tokens(chat_id).await?;
         assert_eq!(tokens.len(), 2);
-        assert_eq!(tokens[0].token, "token-b");
-        assert_eq!(tokens[1].token, "token-a");
+        assert_eq!(tokens[0].token_hash, hash_token("token-b"));
+        assert_eq!(tokens[0].token_preview, "token-");
+        assert_eq!(tokens[1].token_hash, hash_token("token-a"));
         Ok(())
     }
 
@@ -127,8 +278,15 @@ mod tests {
     async fn token_revoke() -> Result<()> {
         let db = init_test_db().await;
         let chat_id = ChatId(7);
-        db.create_token(chat_id, "token-x", None, None, None, 123)
-            .await?;
+        db.create_token(
+            chat_id,
+            "token-x",
+            &NewToken {
+                issued_at: 123,
+                ..Default::default()
+            },
+        )
+        .await?;
 
         let revoked = db.revoke_token(chat_id, "token-x", 456).await?;
         assert!(revoked);
@@ -142,12 +300,19 @@ mod tests {
     async fn token_use_updates_last_used() -> Result<()> {
         let db = init_test_db().await;
         let chat_id = ChatId(9);
-        db.create_token(chat_id, "token-use", None, None, None, 123)
-            .await?;
+        db.create_token(
+            chat_id,
+            "token-use",
+            &NewToken {
+                issued_at: 123,
+                ..Default::default()
+            },
+        )
+        .await?;
 
         let used_at = 555;
         let resolved = db.use_token("token-use", used_at).await?;
-        assert_eq!(resolved, Some(chat_id));
+        assert_eq!(resolved, Some((chat_id, TokenScope::Write)));
 
         let tokens = db.list_tokens(chat_id).await?;
         assert_eq!(tokens[0].last_used_at, Some(used_at));
@@ -163,7 +328,7 @@ mod tests {
                 let chat_id = ChatId(1);
                 for (idx, issued_at) in issued_at_values.iter().enumerate() {
                     let token = format!("token-{idx}");
-                    db.create_token(chat_id, &token, None, None, None, *issued_at)
+                    db.create_token(chat_id, &token, &NewToken { issued_at: *issued_at, ..Default::default() })
                         .await
                         .unwrap();
                 }
@@ -177,10 +342,10 @@ mod tests {
                 expected.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| b.1.cmp(&a.1)));
 
                 let tokens = db.list_tokens(chat_id).await.unwrap();
-                let listed: Vec<String> = tokens.into_iter().map(|token| token.token).collect();
+                let listed: Vec<String> = tokens.into_iter().map(|token| token.token_hash).collect();
                 let expected_tokens: Vec<String> = expected
                     .iter()
-                    .map(|(_, idx)| format!("token-{idx}"))
+                    .map(|(_, idx)| hash_token(&format!("token-{idx}")))
                     .collect();
                 prop_assert_eq!(listed, expected_tokens);
                 Ok(())
@@ -188,6 +353,105 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn token_is_not_stored_in_plaintext() -> Result<()> {
+        let db = init_test_db().await;
+        let chat_id = ChatId(3);
+        db.create_token(
+            chat_id,
+            "secret-token",
+            &NewToken {
+                issued_at: 1,
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        let stored: Vec<String> = sqlx::query_scalar("SELECT token_hash FROM tokens")
+            .fetch_all(db.pool())
+            .await?;
+        assert_eq!(stored, vec![hash_token("secret-token")]);
+        assert!(db.use_token(&stored[0], 2).await?.is_none());
+        assert_eq!(
+            db.use_token("secret-token", 2).await?,
+            Some((chat_id, TokenScope::Write))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn plaintext_tokens_are_hashed_in_place() -> Result<()> {
+        let db = init_test_db().await;
+        sqlx::query(
+            "INSERT INTO tokens (chat_id, token_hash, issued_at) VALUES (5, 'legacy-token', 1)",
+        )
+        .execute(db.pool())
+        .await?;
+
+        assert_eq!(db.hash_plaintext_tokens().await?, 1);
+        assert_eq!(db.hash_plaintext_tokens().await?, 0);
+
+        let tokens = db.list_tokens(ChatId(5)).await?;
+        assert_eq!(tokens[0].token_hash, hash_token("legacy-token"));
+        assert_eq!(tokens[0].token_preview, "legacy");
+        assert_eq!(
+            db.use_token("legacy-token", 2).await?,
+            Some((ChatId(5), TokenScope::Write))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn token_expiry_boundaries() -> Result<()> {
+        let db = init_test_db().await;
+        let chat_id = ChatId(4);
+        db.create_token(
+            chat_id,
+            "guest-token",
+            &NewToken {
+                issued_at: 100,
+                expires_at: Some(200),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        assert!(db.use_token("guest-token", 199).await?.is_some());
+        assert!(db.use_token("guest-token", 200).await?.is_none());
+        assert!(db.use_token("guest-token", 201).await?.is_none());
+
+        let tokens = db.list_tokens(chat_id).await?;
+        assert_eq!(tokens[0].expires_at, Some(200));
+        assert_eq!(tokens[0].last_used_at, Some(199));
+        assert!(!tokens[0].is_expired(199));
+        assert!(tokens[0].is_expired(200));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn token_scope_is_stored_and_returned() -> Result<()> {
+        let db = init_test_db().await;
+        let chat_id = ChatId(6);
+        db.create_token(
+            chat_id,
+            "dashboard",
+            &NewToken {
+                issued_at: 1,
+                scope: TokenScope::Read,
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        let tokens = db.list_tokens(chat_id).await?;
+        assert_eq!(tokens[0].scope(), TokenScope::Read);
+        assert_eq!(
+            db.use_token("dashboard", 2).await?,
+            Some((chat_id, TokenScope::Read))
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     async fn token_create_stores_metadata() -> Result<()> {
         let db = init_test_db().await;
@@ -195,10 +459,13 @@ mod tests {
         db.create_token(
             chat_id,
             "token-meta",
-            Some("Ops"),
-            Some(777),
-            Some("On-call"),
-            321,
+            &NewToken {
+                name: Some("Ops"),
+                issuer_user_id: Some(777),
+                issuer_name: Some("On-call"),
+                issued_at: 321,
+                ..Default::default()
+            },
         )
         .await?;
 
@@ -0,0 +1,124 @@
+use super::items::ITEM_COLUMNS;
+use super::{Database, Item};
+use anyhow::Result;
+use teloxide::types::ChatId;
+
+impl Database {
+    /// The chat's trashed items, most recently deleted first.
+    pub async fn list_deleted_items(&self, chat_id: ChatId, limit: i64) -> Result<Vec<Item>> {
+        tracing::trace!(chat_id = chat_id.0, limit, "Listing trashed items");
+        sqlx::query_as(&format!(
+            "SELECT {ITEM_COLUMNS} FROM items WHERE chat_id = ? AND deleted_at IS NOT NULL \
+             ORDER BY deleted_at DESC, id DESC LIMIT ?"
+        ))
+        .bind(chat_id.0)
+        .bind(limit)
+        .fetch_all(self.pool())
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Put a trashed item back on the list. Returns 0 when the item is not in
+    /// the chat's trash, e.g. because someone restored it already.
+    pub async fn restore_item(&self, chat_id: ChatId, id: i64) -> Result<u64> {
+        tracing::debug!(chat_id = chat_id.0, item_id = id, "Restoring item");
+        let result = sqlx::query(
+            "UPDATE items SET deleted_at = NULL \
+             WHERE id = ? AND chat_id = ? AND deleted_at IS NOT NULL",
+        )
+        .bind(id)
+        .bind(chat_id.0)
+        .execute(self.pool())
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Permanently remove items trashed before `cutoff` (unix seconds) in
+    /// every chat. Returns how many rows were removed.
+    pub async fn purge_deleted(&self, cutoff: i64) -> Result<u64> {
+        let result =
+            sqlx::query("DELETE FROM items WHERE deleted_at IS NOT NULL AND deleted_at < ?")
+                .bind(cutoff)
+                .execute(self.pool())
+                .await?;
+        tracing::debug!(
+            cutoff,
+            purged = result.rows_affected(),
+            "Purged trashed items"
+        );
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::util::init_test_db;
+
+    fn texts(items: Vec<Item>) -> Vec<String> {
+        items.into_iter().map(|item| item.text).collect()
+    }
+
+    #[tokio::test]
+    async fn deleted_items_move_to_trash_and_back() -> Result<()> {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_items_count(chat, &["Milk".into(), "Eggs".into()])
+            .await?;
+        let items = db.list_items(chat).await?;
+        assert_eq!(db.delete_item_count(chat, items[0].id).await?, 1);
+        // Deleting a trashed item again is a no-op.
+        assert_eq!(db.delete_item_count(chat, items[0].id).await?, 0);
+
+        assert_eq!(texts(db.list_items(chat).await?), vec!["Eggs"]);
+        let trash = db.list_deleted_items(chat, 10).await?;
+        assert_eq!(texts(trash), vec!["Milk"]);
+        assert!(db.list_deleted_items(ChatId(2), 10).await?.is_empty());
+        assert_eq!(db.toggle_item_count(chat, items[0].id).await?, 0);
+
+        assert_eq!(db.restore_item(ChatId(2), items[0].id).await?, 0);
+        assert_eq!(db.restore_item(chat, items[0].id).await?, 1);
+        assert_eq!(db.restore_item(chat, items[0].id).await?, 0);
+        assert_eq!(texts(db.list_items(chat).await?), vec!["Milk", "Eggs"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn purge_removes_only_old_trash() -> Result<()> {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_items_count(chat, &["Milk".into(), "Eggs".into(), "Tea".into()])
+            .await?;
+        let items = db.list_items(chat).await?;
+        db.delete_items_count(chat, &[items[0].id, items[1].id])
+            .await?;
+        sqlx::query("UPDATE items SET deleted_at = 100 WHERE id = ?")
+            .bind(items[0].id)
+            .execute(db.pool())
+            .await?;
+        sqlx::query("UPDATE items SET deleted_at = 300 WHERE id = ?")
+            .bind(items[1].id)
+            .execute(db.pool())
+            .await?;
+
+        assert_eq!(db.purge_deleted(200).await?, 1);
+        assert_eq!(texts(db.list_deleted_items(chat, 10).await?), vec!["Eggs"]);
+        assert_eq!(texts(db.list_items(chat).await?), vec!["Tea"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn nuke_and_archive_leave_trash_alone() -> Result<()> {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_items_count(chat, &["Milk".into(), "Eggs".into()])
+            .await?;
+        let milk = db.list_items(chat).await?[0].id;
+        db.delete_item_count(chat, milk).await?;
+
+        assert_eq!(db.archive_items(chat, 100).await?.len(), 1);
+        assert_eq!(db.delete_all_items_count(chat).await?, 0);
+        assert_eq!(db.list_deleted_items(chat, 10).await?.len(), 1);
+        Ok(())
+    }
+}
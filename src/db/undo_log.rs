@@ -0,0 +1,216 @@
+use super::items::fill_positions;
+use super::{Database, Item};
+use anyhow::Result;
+use sqlx::SqliteConnection;
+use teloxide::types::ChatId;
+
+/// Destructive change that `/undo` can reverse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UndoAction {
+    Nuke,
+    Archive,
+    Delete,
+}
+
+impl UndoAction {
+    pub const ALL: [Self; 3] = [Self::Nuke, Self::Archive, Self::Delete];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Nuke => "nuke",
+            Self::Archive => "archive",
+            Self::Delete => "delete",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+/// Replace the chat's undo entry with the items just removed from its list.
+/// Does nothing when no items were removed, so the previous entry survives.
+pub(crate) async fn record_undo(
+    conn: &mut SqliteConnection,
+    chat_id: ChatId,
+    action: UndoAction,
+    items: &[Item],
+    archive_id: Option<i64>,
+) -> Result<()> {
+    if items.is_empty() {
+        return Ok(());
+    }
+    tracing::debug!(
+        chat_id = chat_id.0,
+        action = action.name(),
+        count = items.len(),
+        "Recording undo entry"
+    );
+    sqlx::query(
+        "INSERT INTO undo_log (chat_id, action, items, archive_id) VALUES (?, ?, ?, ?) \
+         ON CONFLICT(chat_id) DO UPDATE SET action = excluded.action, \
+         items = excluded.items, archive_id = excluded.archive_id",
+    )
+    .bind(chat_id.0)
+    .bind(action.name())
+    .bind(serde_json::to_string(items)?)
+    .bind(archive_id)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+impl Database {
+    /// Put the items removed by the chat's last destructive change back on
+    /// the list and drop the archive it created, if any. The entry is
+    /// consumed, so a second call returns `None`.
+    pub async fn undo_last(&self, chat_id: ChatId) -> Result<Option<(UndoAction, Vec<Item>)>> {
+        let mut tx = self.begin_write().await?;
+        let entry: Option<(String, String, Option<i64>)> = sqlx::query_as(
+            "DELETE FROM undo_log WHERE chat_id = ? RETURNING action, items, archive_id",
+        )
+        .bind(chat_id.0)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some((action, items, archive_id)) = entry else {
+            tracing::debug!(chat_id = chat_id.0, "Nothing to undo");
+            return Ok(None);
+        };
+        let Some(action) = UndoAction::parse(&action) else {
+            tracing::warn!(chat_id = chat_id.0, action, "Unknown undo action");
+            tx.commit().await?;
+            return Ok(None);
+        };
+        let items: Vec<Item> = serde_json::from_str(&items)?;
+        // Restored checked items count as just checked, so auto-archive does
+        // not immediately take back what the undo put on the list.
+        let restored_at = chrono::Utc::now().timestamp();
+
+        for item in &items {
+            // Deleted items are still in the trash; take them back out.
+            let restored = sqlx::query(
+                "UPDATE items SET deleted_at = NULL \
+                 WHERE id = ? AND chat_id = ? AND deleted_at IS NOT NULL",
+            )
+            .bind(item.id)
+            .bind(chat_id.0)
+            .execute(&mut *tx)
+            .await?;
+            if restored.rows_affected() > 0 {
+                continue;
+            }
+            sqlx::query(
+                "INSERT INTO items \
+                 (chat_id, text, done, quantity, added_by_user_id, added_by_name, category, \
+                 created_at, done_at, assigned_to_user_id, assigned_to_name, price_cents) \
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(chat_id.0)
+            .bind(&item.text)
+            .bind(item.done)
+            .bind(item.quantity)
+            .bind(item.added_by_user_id)
+            .bind(&item.added_by_name)
+            .bind(&item.category)
+            .bind(item.created_at)
+            .bind(item.done.then_some(restored_at))
+            .bind(item.assigned_to_user_id)
+            .bind(&item.assigned_to_name)
+            .bind(item.price_cents)
+            .execute(&mut *tx)
+            .await?;
+        }
+        fill_positions(&mut tx, chat_id).await?;
+        if let Some(archive_id) = archive_id {
+            sqlx::query("DELETE FROM archives WHERE id = ? AND chat_id = ?")
+                .bind(archive_id)
+                .bind(chat_id.0)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        tracing::debug!(
+            chat_id = chat_id.0,
+            action = action.name(),
+            count = items.len(),
+            "Undid last change"
+        );
+        Ok(Some((action, items)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::util::init_test_db;
+
+    fn summary(items: &[Item]) -> Vec<(String, bool, i64)> {
+        items
+            .iter()
+            .map(|item| (item.text.clone(), item.done, item.quantity))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn undo_restores_nuked_list_once() -> Result<()> {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_items_count(chat, &["Milk".into(), "Milk".into(), "Eggs".into()])
+            .await?;
+        let items = db.list_items(chat).await?;
+        db.toggle_item_count(chat, items[1].id).await?;
+        let before = summary(&db.list_items(chat).await?);
+
+        db.delete_all_items_count(chat).await?;
+        let (action, restored) = db.undo_last(chat).await?.unwrap();
+        assert_eq!(action, UndoAction::Nuke);
+        assert_eq!(restored.len(), 2);
+        assert_eq!(summary(&db.list_items(chat).await?), before);
+
+        assert!(db.undo_last(chat).await?.is_none());
+        assert_eq!(db.list_items(chat).await?.len(), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn undo_archive_drops_the_snapshot() -> Result<()> {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_item_count(chat, "Milk").await?;
+        db.archive_items(chat, 100).await?;
+
+        let (action, _) = db.undo_last(chat).await?.unwrap();
+        assert_eq!(action, UndoAction::Archive);
+        assert_eq!(db.list_items(chat).await?[0].text, "Milk");
+        assert!(db.recent_archives(chat, 5).await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn undo_restores_only_the_last_deletion() -> Result<()> {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_items_count(chat, &["Milk".into(), "Eggs".into(), "Bread".into()])
+            .await?;
+        let items = db.list_items(chat).await?;
+        db.delete_item_count(chat, items[0].id).await?;
+        db.delete_items_count(chat, &[items[1].id, items[2].id])
+            .await?;
+        // Deleting nothing keeps the previous entry.
+        db.delete_items_count(chat, &[items[0].id]).await?;
+
+        let (action, restored) = db.undo_last(chat).await?.unwrap();
+        assert_eq!(action, UndoAction::Delete);
+        assert_eq!(summary(&restored).len(), 2);
+        let texts: Vec<_> = db
+            .list_items(chat)
+            .await?
+            .into_iter()
+            .map(|item| item.text)
+            .collect();
+        assert_eq!(texts, vec!["Eggs", "Bread"]);
+        assert!(db.undo_last(ChatId(2)).await?.is_none());
+        Ok(())
+    }
+}
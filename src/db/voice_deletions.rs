@@ -0,0 +1,139 @@
+use super::Database;
+use anyhow::Result;
+use teloxide::types::{ChatId, MessageId};
+
+/// Prompts older than this are pruned when a new one is created.
+const PROMPT_RETENTION_SECS: i64 = 24 * 60 * 60;
+
+/// Items a voice command would remove, waiting for its sender to confirm.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VoiceDeletionPrompt {
+    pub user_id: i64,
+    pub item_ids: Vec<i64>,
+}
+
+impl Database {
+    /// Remember the items prompt `message_id` asks `user_id` to remove.
+    pub async fn create_voice_deletion_prompt(
+        &self,
+        chat_id: ChatId,
+        message_id: MessageId,
+        user_id: i64,
+        item_ids: &[i64],
+        created_at: i64,
+    ) -> Result<()> {
+        tracing::debug!(
+            chat_id = chat_id.0,
+            message_id = message_id.0,
+            count = item_ids.len(),
+            "Creating voice deletion prompt"
+        );
+        let mut tx = self.begin_write().await?;
+        let pruned = sqlx::query("DELETE FROM voice_deletion_prompt WHERE created_at <= ?")
+            .bind(created_at - PROMPT_RETENTION_SECS)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        sqlx::query(
+            "INSERT OR REPLACE INTO voice_deletion_prompt \
+             (chat_id, message_id, user_id, item_ids, created_at) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(chat_id.0)
+        .bind(message_id.0)
+        .bind(user_id)
+        .bind(serde_json::to_string(item_ids)?)
+        .bind(created_at)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        if pruned > 0 {
+            tracing::debug!(pruned, "Pruned old voice deletion prompts");
+        }
+        Ok(())
+    }
+
+    pub async fn get_voice_deletion_prompt(
+        &self,
+        chat_id: ChatId,
+        message_id: MessageId,
+    ) -> Result<Option<VoiceDeletionPrompt>> {
+        tracing::trace!(
+            chat_id = chat_id.0,
+            message_id = message_id.0,
+            "Fetching voice deletion prompt"
+        );
+        let row: Option<(i64, String)> = sqlx::query_as(
+            "SELECT user_id, item_ids FROM voice_deletion_prompt \
+             WHERE chat_id = ? AND message_id = ?",
+        )
+        .bind(chat_id.0)
+        .bind(message_id.0)
+        .fetch_optional(self.pool())
+        .await?;
+        row.map(|(user_id, item_ids)| {
+            Ok(VoiceDeletionPrompt {
+                user_id,
+                item_ids: serde_json::from_str(&item_ids)?,
+            })
+        })
+        .transpose()
+    }
+
+    /// Remove the prompt. Returns whether it was still pending, so each
+    /// prompt is answered once.
+    pub async fn take_voice_deletion_prompt(
+        &self,
+        chat_id: ChatId,
+        message_id: MessageId,
+    ) -> Result<bool> {
+        tracing::debug!(
+            chat_id = chat_id.0,
+            message_id = message_id.0,
+            "Taking voice deletion prompt"
+        );
+        let result =
+            sqlx::query("DELETE FROM voice_deletion_prompt WHERE chat_id = ? AND message_id = ?")
+                .bind(chat_id.0)
+                .bind(message_id.0)
+                .execute(self.pool())
+                .await?;
+        Ok(result.rows_affected() == 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::util::init_test_db;
+
+    #[tokio::test]
+    async fn prompts_are_taken_once_and_old_ones_pruned() -> Result<()> {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.create_voice_deletion_prompt(chat, MessageId(9), 5, &[3, 4], 0)
+            .await?;
+        assert_eq!(
+            db.get_voice_deletion_prompt(chat, MessageId(9)).await?,
+            Some(VoiceDeletionPrompt {
+                user_id: 5,
+                item_ids: vec![3, 4],
+            })
+        );
+        assert!(db.take_voice_deletion_prompt(chat, MessageId(9)).await?);
+        assert!(!db.take_voice_deletion_prompt(chat, MessageId(9)).await?);
+
+        db.create_voice_deletion_prompt(chat, MessageId(10), 5, &[3], 0)
+            .await?;
+        db.create_voice_deletion_prompt(chat, MessageId(11), 5, &[4], PROMPT_RETENTION_SECS)
+            .await?;
+        assert!(db
+            .get_voice_deletion_prompt(chat, MessageId(10))
+            .await?
+            .is_none());
+        assert!(db
+            .get_voice_deletion_prompt(chat, MessageId(11))
+            .await?
+            .is_some());
+        Ok(())
+    }
+}
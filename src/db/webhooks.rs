@@ -0,0 +1,86 @@
+use super::Database;
+use anyhow::Result;
+use teloxide::types::ChatId;
+
+/// A URL notified about every change to a chat's list. The secret signs each
+/// delivery and is kept in plaintext because it is needed to compute the
+/// signature.
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq)]
+pub struct Webhook {
+    pub id: i64,
+    pub chat_id: i64,
+    pub url: String,
+    pub secret: String,
+    pub created_at: i64,
+}
+
+impl Database {
+    pub async fn add_webhook(
+        &self,
+        chat_id: ChatId,
+        url: &str,
+        secret: &str,
+        created_at: i64,
+    ) -> Result<i64> {
+        tracing::debug!(chat_id = chat_id.0, url, "Adding webhook");
+        let id = sqlx::query_scalar(
+            "INSERT INTO webhooks (chat_id, url, secret, created_at) VALUES (?, ?, ?, ?) RETURNING id",
+        )
+        .bind(chat_id.0)
+        .bind(url)
+        .bind(secret)
+        .bind(created_at)
+        .fetch_one(self.pool())
+        .await?;
+        Ok(id)
+    }
+
+    pub async fn list_webhooks(&self, chat_id: ChatId) -> Result<Vec<Webhook>> {
+        tracing::trace!(chat_id = chat_id.0, "Listing webhooks");
+        sqlx::query_as(
+            "SELECT id, chat_id, url, secret, created_at FROM webhooks WHERE chat_id = ? ORDER BY id",
+        )
+        .bind(chat_id.0)
+        .fetch_all(self.pool())
+        .await
+        .map_err(Into::into)
+    }
+
+    /// Remove one of the chat's webhooks. Returns whether it existed.
+    pub async fn remove_webhook(&self, chat_id: ChatId, id: i64) -> Result<bool> {
+        tracing::debug!(chat_id = chat_id.0, webhook_id = id, "Removing webhook");
+        let result = sqlx::query("DELETE FROM webhooks WHERE id = ? AND chat_id = ?")
+            .bind(id)
+            .bind(chat_id.0)
+            .execute(self.pool())
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::util::init_test_db;
+
+    #[tokio::test]
+    async fn webhooks_are_scoped_to_their_chat() -> Result<()> {
+        let db = init_test_db().await;
+        let id = db
+            .add_webhook(ChatId(1), "https://hub.local/hook", "s3cret", 10)
+            .await?;
+        db.add_webhook(ChatId(2), "https://other.local/hook", "other", 11)
+            .await?;
+
+        let hooks = db.list_webhooks(ChatId(1)).await?;
+        assert_eq!(hooks.len(), 1);
+        assert_eq!(hooks[0].url, "https://hub.local/hook");
+        assert_eq!(hooks[0].secret, "s3cret");
+
+        assert!(!db.remove_webhook(ChatId(2), id).await?);
+        assert!(db.remove_webhook(ChatId(1), id).await?);
+        assert!(db.list_webhooks(ChatId(1)).await?.is_empty());
+        assert_eq!(db.list_webhooks(ChatId(2)).await?.len(), 1);
+        Ok(())
+    }
+}
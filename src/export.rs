@@ -0,0 +1,104 @@
+//! File renderings of a list, shared by `/export` and `GET /api/v1/export`.
+
+use serde::Deserialize;
+use std::borrow::Cow;
+
+use crate::db::Item;
+use crate::handlers::format_plain_list;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Text,
+    Markdown,
+    Csv,
+}
+
+impl ExportFormat {
+    /// Parse a format by name or file extension; empty input picks the
+    /// default.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "" | "text" | "txt" => Some(Self::Text),
+            "markdown" | "md" => Some(Self::Markdown),
+            "csv" => Some(Self::Csv),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Text => "text/plain; charset=utf-8",
+            ExportFormat::Markdown => "text/markdown; charset=utf-8",
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Text => "txt",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Csv => "csv",
+        }
+    }
+
+    pub fn render(self, items: &[Item]) -> String {
+        match self {
+            ExportFormat::Text => format_plain_list(items),
+            ExportFormat::Markdown => items
+                .iter()
+                .map(|item| {
+                    let mark = if item.done { "x" } else { " " };
+                    format!("- [{mark}] {}\n", item.label())
+                })
+                .collect(),
+            ExportFormat::Csv => render_csv(items),
+        }
+    }
+}
+
+/// CSV with a header row and RFC 4180 quoting, one item per line.
+fn render_csv(items: &[Item]) -> String {
+    let mut csv = String::from("id,text,done,quantity\r\n");
+    for item in items {
+        csv.push_str(&format!(
+            "{},{},{},{}\r\n",
+            item.id,
+            csv_field(&item.text),
+            item.done,
+            item.quantity
+        ));
+    }
+    csv
+}
+
+/// Quote a field when it contains a separator, quote or line break.
+fn csv_field(value: &str) -> Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_fields_are_quoted_only_when_needed() {
+        assert_eq!(csv_field("Milk"), "Milk");
+        assert_eq!(csv_field("Eggs, large"), "\"Eggs, large\"");
+        assert_eq!(csv_field("12\" pizza"), "\"12\"\" pizza\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn formats_parse_by_name_or_extension() {
+        assert_eq!(ExportFormat::parse(""), Some(ExportFormat::Text));
+        assert_eq!(ExportFormat::parse(" MD "), Some(ExportFormat::Markdown));
+        assert_eq!(ExportFormat::parse("csv"), Some(ExportFormat::Csv));
+        assert_eq!(ExportFormat::parse("pdf"), None);
+    }
+}
@@ -1,19 +1,49 @@
+pub mod assign;
+pub mod auto_archive;
+pub mod callbacks;
 pub mod delete;
+pub mod duplicates;
+pub mod edit;
+pub mod history;
 pub mod info;
 pub mod keyboard;
 pub mod list;
 pub mod list_service;
+pub mod message_tracker;
+pub mod nuke;
+pub mod onboarding;
+pub mod pagination;
+pub mod pairing;
 pub mod parse;
+pub mod permissions;
 pub mod photo;
+pub mod prompt;
+pub mod refresh;
+pub mod settings;
+pub mod share_links;
+pub mod suggestions;
 pub mod text;
 pub mod tokens;
+pub mod trash;
 pub mod voice;
+pub mod voice_confirm;
+pub mod webhooks;
 
+pub use assign::{enter_assign_mode, show_my_items};
 pub use delete::{callback_handler, enter_delete_mode, format_delete_list};
+pub use edit::enter_edit_mode;
+pub use history::show_history;
 pub use info::show_system_info;
-pub use list::{format_list, format_plain_list, insert_items};
+pub use list::{format_list, format_list_checked_last, format_plain_list, insert_items};
 pub use list_service::ListService;
+pub use onboarding::start;
+pub use permissions::handle_my_chat_member;
 pub use photo::add_items_from_photo;
-pub use text::{add_items_from_parsed_text, add_items_from_text, help};
+pub use prompt::handle_prompt;
+pub use settings::{handle_receipt, handle_settings};
+pub use share_links::handle_share_link;
+pub use text::{add_items_from_parsed_text, add_items_from_text, help, update_items_from_edit};
 pub use tokens::{issue_token, list_tokens, revoke_token};
+pub use trash::show_trash;
 pub use voice::add_items_from_voice;
+pub use webhooks::handle_webhook;
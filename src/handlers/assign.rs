@@ -0,0 +1,273 @@
+//! `/assign` opens a private keyboard where the sender claims items of the
+//! chat's list, and `/mine` lists what they claimed.
+
+use anyhow::Result;
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup, MaybeInaccessibleMessage, User, UserId},
+};
+
+use crate::db::{Database, Item};
+use crate::list_style::ListStyle;
+use crate::messages::{
+    assign_dm_text, item_assigned_text, mine_text, ASSIGN_DM_FAILED, ASSIGN_DONE_LABEL,
+    ASSIGN_FINISHED, ASSIGN_PANEL_STALE, ASSIGN_SELECT_PROMPT, DEFAULT_CHAT_NAME, ITEM_GONE,
+    MINE_EMPTY, NO_ACTIVE_LIST_TO_EDIT,
+};
+use crate::notify::{ListEvent, ListEventKind};
+use crate::state::SharedState;
+use crate::utils::{topic_of, try_delete_message, try_edit_message, InTopic};
+
+use super::list::format_plain_list;
+use super::list_service::ListService;
+use super::message_tracker::MessageTracker;
+use super::permissions::delete_user_message;
+
+pub const ASSIGN_PREFIX: &str = "assign_";
+const ASSIGN_DONE: &str = "done";
+/// Marks the items the viewer claimed.
+const MINE_MARK: &str = "✋";
+
+/// The claim keyboard as seen by `user_id`: their items are marked, items
+/// claimed by others name the assignee.
+pub fn format_assign_list(
+    items: &[Item],
+    user_id: i64,
+    style: &ListStyle,
+) -> (String, InlineKeyboardMarkup) {
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = items
+        .iter()
+        .map(|item| {
+            let label = match (item.assigned_to_user_id, item.assigned_to_name.as_deref()) {
+                (Some(id), _) if id == user_id => format!("{MINE_MARK} {}", item.label()),
+                (Some(_), Some(name)) => format!("{} {} → {name}", style.unchecked, item.label()),
+                _ => format!("{} {}", style.unchecked, item.label()),
+            };
+            vec![InlineKeyboardButton::callback(
+                label,
+                format!("{ASSIGN_PREFIX}{}", item.id),
+            )]
+        })
+        .collect();
+    keyboard.push(vec![InlineKeyboardButton::callback(
+        ASSIGN_DONE_LABEL,
+        format!("{ASSIGN_PREFIX}{ASSIGN_DONE}"),
+    )]);
+    (
+        ASSIGN_SELECT_PROMPT.to_string(),
+        InlineKeyboardMarkup::new(keyboard),
+    )
+}
+
+pub async fn enter_assign_mode(
+    bot: Bot,
+    msg: Message,
+    db: &Database,
+    delete_after_timeout: u64,
+) -> Result<()> {
+    tracing::debug!(
+        chat_id = msg.chat.id.0,
+        user_id = msg.from.as_ref().map(|u| u.id.0),
+        "Entering assign mode",
+    );
+    delete_user_message(&bot, db, msg.chat.id, msg.id).await?;
+
+    let items = db.list_items(msg.chat.id).await?;
+    if items.is_empty()
+        || MessageTracker::new(db)
+            .current(msg.chat.id)
+            .await?
+            .is_none()
+    {
+        let sent = bot
+            .send_message(msg.chat.id, NO_ACTIVE_LIST_TO_EDIT)
+            .in_topic(topic_of(&msg))
+            .await?;
+        drop(crate::delete_after(
+            bot.clone(),
+            sent.chat.id,
+            sent.id,
+            delete_after_timeout,
+        ));
+        return Ok(());
+    }
+
+    let Some(user) = msg.from.as_ref() else {
+        return Ok(());
+    };
+    let user_id = user.id.0 as i64;
+
+    if let Some(dm) = db
+        .get_assign_session(user_id)
+        .await?
+        .and_then(|previous| previous.dm_message_id)
+    {
+        try_delete_message(&bot, ChatId(user_id), dm).await;
+    }
+    db.init_assign_session(user_id, msg.chat.id).await?;
+
+    let style = db.get_settings(msg.chat.id).await?.list_style;
+    let (prompt, keyboard) = format_assign_list(&items, user_id, &style);
+    let chat_name = msg
+        .chat
+        .title()
+        .map(ToString::to_string)
+        .unwrap_or_else(|| DEFAULT_CHAT_NAME.to_string());
+
+    match bot
+        .send_message(UserId(user.id.0), assign_dm_text(&chat_name, &prompt))
+        .reply_markup(keyboard)
+        .await
+    {
+        Ok(dm) => db.set_assign_dm_message(user_id, dm.id).await?,
+        Err(err) => {
+            tracing::warn!(error = %err, user_id, "Failed to send assign DM");
+            db.clear_assign_session(user_id).await?;
+            let warn = bot
+                .send_message(msg.chat.id, ASSIGN_DM_FAILED)
+                .in_topic(topic_of(&msg))
+                .await?;
+            drop(crate::delete_after(
+                bot.clone(),
+                warn.chat.id,
+                warn.id,
+                delete_after_timeout,
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Handle a tap on the claim keyboard: claim or give up an item, or close
+/// the keyboard. Returns the toast to show.
+pub async fn handle_assign_callback(
+    bot: &Bot,
+    msg: &MaybeInaccessibleMessage,
+    user: &User,
+    db: &Database,
+    shared: &SharedState,
+    data: &str,
+) -> Result<Option<String>> {
+    let user_id = user.id.0 as i64;
+    let session = db
+        .get_assign_session(user_id)
+        .await?
+        .filter(|session| session.dm_message_id == Some(msg.id()));
+    let Some(session) = session else {
+        tracing::trace!(user_id, "Ignoring tap on a stale assign keyboard");
+        return Ok(Some(ASSIGN_PANEL_STALE.to_string()));
+    };
+
+    if data == ASSIGN_DONE {
+        db.clear_assign_session(user_id).await?;
+        let no_buttons = InlineKeyboardMarkup::new(Vec::<Vec<InlineKeyboardButton>>::new());
+        try_edit_message(bot, msg.chat().id, msg.id(), ASSIGN_FINISHED, no_buttons).await;
+        return Ok(None);
+    }
+    let Ok(item_id) = data.parse::<i64>() else {
+        return Ok(None);
+    };
+
+    let chat_id = session.chat_id;
+    let item = db.get_item(chat_id, item_id).await?;
+    let mine = match &item {
+        Some(_) => {
+            db.toggle_item_assignment(chat_id, item_id, user_id, &user.first_name)
+                .await?
+        }
+        None => None,
+    };
+    let answer = match (item, mine) {
+        (Some(item), Some(mine)) => {
+            tracing::debug!(
+                chat_id = chat_id.0,
+                item_id,
+                user_id,
+                mine,
+                "Item assignment changed"
+            );
+            shared
+                .events
+                .list_changed(db, ListEvent::item(chat_id, ListEventKind::Update, item_id));
+            item_assigned_text(&item.label(), mine)
+        }
+        _ => ITEM_GONE.to_string(),
+    };
+
+    let items = db.list_items(chat_id).await?;
+    let style = db.get_settings(chat_id).await?.list_style;
+    let (prompt, keyboard) = format_assign_list(&items, user_id, &style);
+    try_edit_message(bot, msg.chat().id, msg.id(), prompt, keyboard).await;
+    if let Some(list_id) = MessageTracker::new(db).current(chat_id).await? {
+        ListService::new(db, shared)
+            .update_message(bot, chat_id, list_id)
+            .await?;
+    }
+    Ok(Some(answer))
+}
+
+/// `/mine`: the items of the list the sender claimed with `/assign`.
+pub async fn show_my_items(bot: Bot, msg: Message, db: Database) -> Result<()> {
+    let Some(user) = msg.from.as_ref() else {
+        return Ok(());
+    };
+    let items = db
+        .list_items_assigned_to(msg.chat.id, user.id.0 as i64)
+        .await?;
+    tracing::debug!(
+        chat_id = msg.chat.id.0,
+        user_id = user.id.0,
+        count = items.len(),
+        "Showing assigned items"
+    );
+    let text = if items.is_empty() {
+        MINE_EMPTY.to_string()
+    } else {
+        mine_text(&format_plain_list(&items))
+    };
+    bot.send_message(msg.chat.id, text)
+        .in_topic(topic_of(&msg))
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: i64, text: &str, assignee: Option<(i64, &str)>) -> Item {
+        Item {
+            id,
+            text: text.to_string(),
+            done: false,
+            quantity: 1,
+            added_by_user_id: None,
+            added_by_name: None,
+            category: None,
+            created_at: 0,
+            assigned_to_user_id: assignee.map(|(id, _)| id),
+            assigned_to_name: assignee.map(|(_, name)| name.to_string()),
+            price_cents: None,
+        }
+    }
+
+    #[test]
+    fn assign_keyboard_marks_own_and_names_other_claims() {
+        let items = [
+            item(1, "Milk", Some((5, "Ann"))),
+            item(2, "Eggs", Some((6, "Bo"))),
+            item(3, "Tea", None),
+        ];
+        let (text, keyboard) = format_assign_list(&items, 5, &ListStyle::default());
+        assert_eq!(text, ASSIGN_SELECT_PROMPT);
+        let labels: Vec<&str> = keyboard
+            .inline_keyboard
+            .iter()
+            .map(|row| row[0].text.as_str())
+            .collect();
+        assert_eq!(
+            labels,
+            ["✋ Milk", "⬜ Eggs → Bo", "⬜ Tea", ASSIGN_DONE_LABEL]
+        );
+    }
+}
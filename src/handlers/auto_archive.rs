@@ -0,0 +1,113 @@
+//! Archives checked items of chats that turned on
+//! `/settings autoarchive <hours>` once nothing has been checked off for that
+//! long.
+
+use std::time::Duration;
+use teloxide::prelude::*;
+use tracing::Instrument;
+
+use super::list_service::ListService;
+use crate::db::Database;
+use crate::state::SharedState;
+
+const AUTO_ARCHIVE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Archive the checked items of every chat that is due at `now`.
+pub async fn run_auto_archive(
+    bot: &Bot,
+    db: &Database,
+    shared: &SharedState,
+    now: i64,
+) -> anyhow::Result<()> {
+    let chats = db.chats_due_for_auto_archive(now).await?;
+    if !chats.is_empty() {
+        tracing::debug!(count = chats.len(), "Chats due for auto-archive");
+    }
+    let service = ListService::new(db, shared);
+    for chat_id in chats {
+        if let Err(err) = service.auto_archive_checked(bot, chat_id).await {
+            tracing::warn!(error = %err, chat_id = chat_id.0, "Failed to auto-archive");
+        }
+    }
+    Ok(())
+}
+
+/// Periodically archive checked items of chats with auto-archive enabled.
+/// Due chats are derived from stored timestamps, so a restart or a late tick
+/// only delays the archive.
+pub fn spawn_auto_archive(bot: Bot, db: Database, shared: SharedState) {
+    tokio::spawn(
+        async move {
+            let mut interval = tokio::time::interval(AUTO_ARCHIVE_INTERVAL);
+            loop {
+                interval.tick().await;
+                let now = chrono::Utc::now().timestamp();
+                if let Err(err) = run_auto_archive(&bot, &db, &shared, now).await {
+                    tracing::warn!(error = %err, "Failed to look for chats to auto-archive");
+                }
+            }
+        }
+        .in_current_span(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::chat_settings::AUTO_ARCHIVE_AFTER_HOURS;
+    use crate::tests::util::{init_test_db, test_bot, SENT_MESSAGE};
+    use teloxide::types::ChatId;
+    use wiremock::{
+        matchers::{body_string_contains, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn due_chats_get_an_automatic_archive() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SendMessage"))
+            .and(body_string_contains("archived automatically"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(SENT_MESSAGE, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SendMessage"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(SENT_MESSAGE, "application/json"))
+            .mount(&server)
+            .await;
+
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_items_count(chat, &["Milk".into(), "Eggs".into()])
+            .await
+            .unwrap();
+        let milk = db.list_items(chat).await.unwrap()[0].id;
+        db.toggle_item_count(chat, milk).await.unwrap();
+        db.set_setting(chat, AUTO_ARCHIVE_AFTER_HOURS, "2")
+            .await
+            .unwrap();
+        let bot = test_bot(&server);
+        let now = chrono::Utc::now().timestamp();
+
+        run_auto_archive(&bot, &db, &SharedState::new().unwrap(), now + 3600)
+            .await
+            .unwrap();
+        assert_eq!(db.list_items(chat).await.unwrap().len(), 2);
+
+        run_auto_archive(&bot, &db, &SharedState::new().unwrap(), now + 3 * 3600)
+            .await
+            .unwrap();
+        let items = db.list_items(chat).await.unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].text, "Eggs");
+        assert_eq!(db.recent_archives(chat, 5).await.unwrap().len(), 1);
+
+        run_auto_archive(&bot, &db, &SharedState::new().unwrap(), now + 4 * 3600)
+            .await
+            .unwrap();
+        assert_eq!(db.recent_archives(chat, 5).await.unwrap().len(), 1);
+        server.verify().await;
+    }
+}
@@ -0,0 +1,179 @@
+//! Callback data of the inline buttons. Every payload starts with a prefix
+//! naming its action, so a new kind of button cannot be mistaken for an
+//! existing one.
+
+use std::fmt;
+
+use super::assign::ASSIGN_PREFIX;
+use super::duplicates::DUPLICATE_PREFIX;
+use super::edit::EDIT_PREFIX;
+use super::onboarding::ONBOARDING_PREFIX;
+use super::settings::SETTINGS_PREFIX;
+use super::suggestions::SUGGEST_PREFIX;
+use super::trash::RESTORE_PREFIX;
+use super::voice_confirm::VOICE_DELETE_PREFIX;
+
+pub const TOGGLE_PREFIX: &str = "toggle_";
+pub const DELETE_PREFIX: &str = "delete_";
+pub const DELETE_DONE: &str = "delete_done";
+/// Page buttons of the delete keyboard.
+pub const DELETE_PAGE_PREFIX: &str = "delete_page_";
+/// Page buttons under a long list.
+pub const PAGE_PREFIX: &str = "page_";
+/// Answers to the `/nuke` prompt, followed by the id of the user who sent it.
+pub const NUKE_CONFIRM_PREFIX: &str = "nuke_confirm_";
+pub const NUKE_CANCEL_PREFIX: &str = "nuke_cancel_";
+
+/// A prefix whose payload is handed unparsed to the module owning the button.
+type ForwardedPrefix = (&'static str, fn(String) -> CallbackAction);
+
+/// What an inline button asks for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CallbackAction {
+    /// Check or uncheck an item of the list message.
+    Toggle(i64),
+    /// Select or unselect an item in the delete keyboard.
+    Delete(i64),
+    /// Remove the selected items and close the delete keyboard.
+    DeleteDone,
+    DeletePage(usize),
+    ListPage(usize),
+    /// Wipe the list; only the user with this id may.
+    NukeConfirm(u64),
+    NukeCancel(u64),
+    /// Buttons whose payload after the prefix is parsed by their own module.
+    Edit(String),
+    Settings(String),
+    Duplicate(String),
+    Suggest(String),
+    Restore(String),
+    Onboarding(String),
+    Assign(String),
+    VoiceDelete(String),
+}
+
+impl CallbackAction {
+    /// `None` for unknown prefixes and malformed ids or pages.
+    pub fn parse(data: &str) -> Option<Self> {
+        if let Some(id) = data.strip_prefix(TOGGLE_PREFIX) {
+            return id.parse().ok().map(Self::Toggle);
+        }
+        if let Some(page) = data.strip_prefix(DELETE_PAGE_PREFIX) {
+            return page.parse().ok().map(Self::DeletePage);
+        }
+        if data == DELETE_DONE {
+            return Some(Self::DeleteDone);
+        }
+        if let Some(id) = data.strip_prefix(DELETE_PREFIX) {
+            return id.parse().ok().map(Self::Delete);
+        }
+        if let Some(page) = data.strip_prefix(PAGE_PREFIX) {
+            return page.parse().ok().map(Self::ListPage);
+        }
+        if let Some(user) = data.strip_prefix(NUKE_CONFIRM_PREFIX) {
+            return user.parse().ok().map(Self::NukeConfirm);
+        }
+        if let Some(user) = data.strip_prefix(NUKE_CANCEL_PREFIX) {
+            return user.parse().ok().map(Self::NukeCancel);
+        }
+        let forwarded: [ForwardedPrefix; 8] = [
+            (EDIT_PREFIX, Self::Edit),
+            (SETTINGS_PREFIX, Self::Settings),
+            (DUPLICATE_PREFIX, Self::Duplicate),
+            (SUGGEST_PREFIX, Self::Suggest),
+            (RESTORE_PREFIX, Self::Restore),
+            (ONBOARDING_PREFIX, Self::Onboarding),
+            (ASSIGN_PREFIX, Self::Assign),
+            (VOICE_DELETE_PREFIX, Self::VoiceDelete),
+        ];
+        for (prefix, action) in forwarded {
+            if let Some(rest) = data.strip_prefix(prefix) {
+                return Some(action(rest.to_string()));
+            }
+        }
+        // List messages sent before toggles were prefixed carry the bare id.
+        // Accepted for one more release.
+        data.parse().ok().map(Self::Toggle)
+    }
+}
+
+impl fmt::Display for CallbackAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Toggle(id) => write!(f, "{TOGGLE_PREFIX}{id}"),
+            Self::Delete(id) => write!(f, "{DELETE_PREFIX}{id}"),
+            Self::DeleteDone => f.write_str(DELETE_DONE),
+            Self::DeletePage(page) => write!(f, "{DELETE_PAGE_PREFIX}{page}"),
+            Self::ListPage(page) => write!(f, "{PAGE_PREFIX}{page}"),
+            Self::NukeConfirm(user) => write!(f, "{NUKE_CONFIRM_PREFIX}{user}"),
+            Self::NukeCancel(user) => write!(f, "{NUKE_CANCEL_PREFIX}{user}"),
+            Self::Edit(rest) => write!(f, "{EDIT_PREFIX}{rest}"),
+            Self::Settings(rest) => write!(f, "{SETTINGS_PREFIX}{rest}"),
+            Self::Duplicate(rest) => write!(f, "{DUPLICATE_PREFIX}{rest}"),
+            Self::Suggest(rest) => write!(f, "{SUGGEST_PREFIX}{rest}"),
+            Self::Restore(rest) => write!(f, "{RESTORE_PREFIX}{rest}"),
+            Self::Onboarding(rest) => write!(f, "{ONBOARDING_PREFIX}{rest}"),
+            Self::Assign(rest) => write!(f, "{ASSIGN_PREFIX}{rest}"),
+            Self::VoiceDelete(rest) => write!(f, "{VOICE_DELETE_PREFIX}{rest}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_action_round_trips() {
+        let actions = [
+            CallbackAction::Toggle(42),
+            CallbackAction::Toggle(-3),
+            CallbackAction::Delete(7),
+            CallbackAction::DeleteDone,
+            CallbackAction::DeletePage(2),
+            CallbackAction::ListPage(0),
+            CallbackAction::NukeConfirm(7),
+            CallbackAction::NukeCancel(7),
+            CallbackAction::Edit("cancel".into()),
+            CallbackAction::Settings("style=minimal".into()),
+            CallbackAction::Duplicate("add_5".into()),
+            CallbackAction::Suggest("9".into()),
+            CallbackAction::Restore("11".into()),
+            CallbackAction::Onboarding("sample".into()),
+            CallbackAction::Assign("done".into()),
+            CallbackAction::VoiceDelete("confirm".into()),
+        ];
+        for action in actions {
+            let data = action.to_string();
+            assert_eq!(CallbackAction::parse(&data), Some(action), "{data}");
+        }
+        assert_eq!(CallbackAction::Toggle(42).to_string(), "toggle_42");
+    }
+
+    #[test]
+    fn legacy_bare_ids_still_toggle() {
+        assert_eq!(
+            CallbackAction::parse("42"),
+            Some(CallbackAction::Toggle(42))
+        );
+    }
+
+    #[test]
+    fn malformed_payloads_are_rejected() {
+        for data in [
+            "",
+            "toggle_",
+            "toggle_milk",
+            "delete_",
+            "delete_x",
+            "delete_page_-1",
+            "page_next",
+            "nuke_confirm_",
+            "nuke_cancel_-1",
+            "unknown_1",
+            "4.2",
+        ] {
+            assert_eq!(CallbackAction::parse(data), None, "{data:?}");
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use crate::config::DeleteSessionTtl;
+use crate::db::delete_session::DeleteSession;
 use crate::db::Database;
 use anyhow::Result;
 use std::collections::HashSet;
@@ -8,38 +10,98 @@ use teloxide::{
         User, UserId,
     },
 };
+use tracing::Instrument;
 
 use crate::db::Item;
+use crate::list_style::ListStyle;
 use crate::messages::{
-    delete_dm_text, delete_user_selecting_text, DEFAULT_CHAT_NAME, DELETE_DM_FAILED,
-    DELETE_DONE_LABEL, DELETE_SELECT_PROMPT, NO_ACTIVE_LIST_TO_EDIT,
+    delete_dm_text, delete_in_chat_text, delete_session_expired_text, delete_user_removed_text,
+    delete_user_selecting_text, item_deleted_text, item_selected_text, item_toggled_text,
+    items_deleted_text, DEFAULT_CHAT_NAME, DELETE_DONE_LABEL, DELETE_PANEL_NOT_YOURS,
+    DELETE_PANEL_STALE, DELETE_SELECT_PROMPT, DELETE_SESSION_EXPIRED, ITEM_GONE,
+    NO_ACTIVE_LIST_TO_EDIT,
 };
 
+use super::assign::handle_assign_callback;
+use super::callbacks::CallbackAction;
+use super::duplicates::handle_duplicate_callback;
+use super::edit::handle_edit_callback;
 use super::keyboard::build_item_buttons;
 use super::list_service::ListService;
-use crate::utils::{try_delete_message, try_edit_message};
+use super::message_tracker::MessageTracker;
+use super::nuke::{handle_nuke_callback, NukeAnswer};
+use super::onboarding::handle_onboarding_callback;
+use super::pagination::{handle_page_callback, page_buttons, page_range};
+use super::permissions::delete_user_message;
+use super::settings::handle_settings_callback;
+use super::suggestions::handle_suggest_callback;
+use super::trash::handle_restore_callback;
+use super::voice_confirm::handle_voice_delete_callback;
+use crate::notify::{ListEvent, ListEventKind};
+use crate::state::SharedState;
+use crate::utils::{topic_of, try_delete_message, try_edit_message, EditOutcome, InTopic};
 
+/// Seconds an untouched delete panel stays open before it is cleaned up,
+/// unless `DELETE_SESSION_TTL` says otherwise.
+pub const DELETE_SESSION_TTL_SECS: u64 = 600;
+
+/// What a press on a delete panel found in the database.
+enum SessionState {
+    Active(DeleteSession),
+    /// No session, or the press came from a panel that was replaced.
+    Stale,
+    /// The session outlived its TTL and has just been cleaned up.
+    Expired,
+    /// An in-chat panel pressed by someone other than its owner.
+    Foreign,
+}
+
+impl SessionState {
+    /// The session, or the toast explaining why the press was refused.
+    fn into_active(self) -> Result<DeleteSession, &'static str> {
+        match self {
+            SessionState::Active(session) => Ok(session),
+            SessionState::Stale => Err(DELETE_PANEL_STALE),
+            SessionState::Expired => Err(DELETE_SESSION_EXPIRED),
+            SessionState::Foreign => Err(DELETE_PANEL_NOT_YOURS),
+        }
+    }
+}
+
+/// Whether a session opened at `created_at` is older than `ttl` at `now`.
+fn session_expired(session: &DeleteSession, now: i64, ttl: DeleteSessionTtl) -> bool {
+    now - session.created_at > i64::try_from(ttl.0).unwrap_or(i64::MAX)
+}
+
+/// The delete prompt with one selection button per item on `page`, page
+/// buttons when the list spans several pages, and the done button last.
 pub fn format_delete_list(
     items: &[Item],
     selected: &HashSet<i64>,
+    style: &ListStyle,
+    page: usize,
 ) -> (String, InlineKeyboardMarkup) {
     let text = DELETE_SELECT_PROMPT.to_string();
+    let (range, page, pages) = page_range(items.len(), page);
 
     let mut keyboard_buttons = build_item_buttons(
-        items,
+        &items[range],
         |item| {
             if selected.contains(&item.id) {
-                format!("❌ {}", item.text)
+                format!("{} {}", style.selected, item.label())
             } else {
-                format!("⬜ {}", item.text)
+                format!("{} {}", style.unchecked, item.label())
             }
         },
-        |item| format!("delete_{}", item.id),
+        |item| CallbackAction::Delete(item.id).to_string(),
     );
 
+    if pages > 1 {
+        keyboard_buttons.push(page_buttons(page, pages, CallbackAction::DeletePage));
+    }
     keyboard_buttons.push(vec![InlineKeyboardButton::callback(
         DELETE_DONE_LABEL,
-        "delete_done",
+        CallbackAction::DeleteDone.to_string(),
     )]);
 
     (text, InlineKeyboardMarkup::new(keyboard_buttons))
@@ -51,13 +113,109 @@ async fn cleanup_previous_session(bot: &Bot, db: &Database, user_id: UserId) ->
         if let Some((chat_id, msg_id)) = prev.notice {
             try_delete_message(bot, chat_id, msg_id).await;
         }
-        if let Some(dm) = prev.dm_message_id {
-            try_delete_message(bot, ChatId(user_id.0 as i64), dm).await;
+        if let Some(panel) = prev.dm_message_id {
+            try_delete_message(bot, prev.panel_chat(user_id.0 as i64), panel).await;
         }
     }
     Ok(())
 }
 
+async fn finish_delete_notice(
+    bot: &Bot,
+    chat_id: ChatId,
+    message_id: MessageId,
+    text: String,
+    delete_after_timeout: u64,
+) {
+    tracing::debug!(
+        chat_id = chat_id.0,
+        message_id = message_id.0,
+        "Finishing delete notice"
+    );
+    let markup = InlineKeyboardMarkup::new(Vec::<Vec<InlineKeyboardButton>>::new());
+    if let EditOutcome::MessageMissing =
+        try_edit_message(bot, chat_id, message_id, text, markup).await
+    {
+        return;
+    }
+    drop(crate::delete_after(
+        bot.clone(),
+        chat_id,
+        message_id,
+        delete_after_timeout,
+    ));
+}
+
+async fn expire_delete_session(
+    bot: &Bot,
+    db: &Database,
+    user_id: i64,
+    dm_message_id: MessageId,
+    user_name: &str,
+    delete_after_timeout: u64,
+) -> Result<bool> {
+    let Some(session) = db.get_delete_session(user_id).await? else {
+        return Ok(false);
+    };
+    if session.dm_message_id != Some(dm_message_id) {
+        tracing::trace!(user_id, "Delete session already replaced; not expiring");
+        return Ok(false);
+    }
+
+    tracing::debug!(
+        user_id,
+        chat_id = session.chat_id.0,
+        "Expiring delete session"
+    );
+    try_delete_message(bot, session.panel_chat(user_id), dm_message_id).await;
+    if let Some((chat_id, notice_id)) = session.notice {
+        finish_delete_notice(
+            bot,
+            chat_id,
+            notice_id,
+            delete_session_expired_text(user_name),
+            delete_after_timeout,
+        )
+        .await;
+    }
+    db.clear_delete_session(user_id).await?;
+    Ok(true)
+}
+
+fn schedule_session_expiry(
+    bot: Bot,
+    db: Database,
+    user_id: i64,
+    dm_message_id: MessageId,
+    user_name: String,
+    delete_after_timeout: u64,
+    ttl: DeleteSessionTtl,
+) -> tokio::task::JoinHandle<()> {
+    tracing::debug!(
+        user_id,
+        ttl_secs = ttl.0,
+        "Scheduling delete session expiry"
+    );
+    tokio::spawn(
+        async move {
+            tokio::time::sleep(std::time::Duration::from_secs(ttl.0)).await;
+            if let Err(err) = expire_delete_session(
+                &bot,
+                &db,
+                user_id,
+                dm_message_id,
+                &user_name,
+                delete_after_timeout,
+            )
+            .await
+            {
+                tracing::warn!(error = %err, user_id, "Failed to expire delete session");
+            }
+        }
+        .in_current_span(),
+    )
+}
+
 async fn start_delete_session(
     bot: &Bot,
     msg: &Message,
@@ -65,6 +223,7 @@ async fn start_delete_session(
     db: &Database,
     items: &[Item],
     delete_after_timeout: u64,
+    ttl: DeleteSessionTtl,
 ) -> Result<()> {
     tracing::debug!(
         chat_id = msg.chat.id.0,
@@ -75,7 +234,8 @@ async fn start_delete_session(
     db.init_delete_session(user.id.0 as i64, msg.chat.id)
         .await?;
 
-    let (base_text, keyboard) = format_delete_list(items, &HashSet::new());
+    let style = db.get_settings(msg.chat.id).await?.list_style;
+    let (base_text, keyboard) = format_delete_list(items, &HashSet::new(), &style, 0);
     let chat_name = msg
         .chat
         .title()
@@ -85,7 +245,7 @@ async fn start_delete_session(
 
     match bot
         .send_message(UserId(user.id.0), dm_text)
-        .reply_markup(keyboard)
+        .reply_markup(keyboard.clone())
         .await
     {
         Ok(dm_msg) => {
@@ -94,19 +254,42 @@ async fn start_delete_session(
             if !msg.chat.is_private() {
                 let info = bot
                     .send_message(msg.chat.id, delete_user_selecting_text(&user.first_name))
+                    .in_topic(topic_of(msg))
                     .await?;
                 db.set_delete_notice(user.id.0 as i64, msg.chat.id, info.id)
                     .await?;
             }
+            drop(schedule_session_expiry(
+                bot.clone(),
+                db.clone(),
+                user.id.0 as i64,
+                dm_msg.id,
+                user.first_name.clone(),
+                delete_after_timeout,
+                ttl,
+            ));
         }
         Err(err) => {
-            tracing::warn!("failed to send DM: {}", err);
-            let warn = bot.send_message(msg.chat.id, DELETE_DM_FAILED).await?;
-            drop(crate::delete_after(
+            // Users who never started the bot privately get the panel in the
+            // chat instead; only they may press it.
+            tracing::warn!(error = %err, "Failed to send delete DM; posting the panel in the chat");
+            let panel = bot
+                .send_message(
+                    msg.chat.id,
+                    delete_in_chat_text(&user.first_name, &base_text),
+                )
+                .reply_markup(keyboard)
+                .in_topic(topic_of(msg))
+                .await?;
+            db.set_delete_dm_message(user.id.0 as i64, panel.id).await?;
+            drop(schedule_session_expiry(
                 bot.clone(),
-                warn.chat.id,
-                warn.id,
+                db.clone(),
+                user.id.0 as i64,
+                panel.id,
+                user.first_name.clone(),
                 delete_after_timeout,
+                ttl,
             ));
         }
     }
@@ -117,26 +300,66 @@ async fn start_delete_session(
 async fn process_done_callback(
     bot: &Bot,
     msg: &MaybeInaccessibleMessage,
-    user_id: i64,
+    user: &User,
     db: &Database,
-) -> Result<()> {
-    if let Some(session) = load_valid_session(db, user_id, msg).await? {
-        if !session.selected.is_empty() {
-            let ids: Vec<i64> = session.selected.iter().copied().collect();
-            db.delete_items_count(session.chat_id, &ids).await?;
-        }
-        if let Some(main_list_id) = db.get_last_list_message_id(session.chat_id).await? {
-            ListService::new(db)
-                .update_message(bot, session.chat_id, MessageId(main_list_id))
-                .await?;
-        }
-        if let Some((chat_id, notice_id)) = session.notice {
-            try_delete_message(bot, chat_id, notice_id).await;
+    shared: &SharedState,
+    delete_after_timeout: u64,
+    ttl: DeleteSessionTtl,
+) -> Result<Option<String>> {
+    let user_id = user.id.0 as i64;
+    let session = match load_valid_session(bot, db, user_id, msg, ttl).await? {
+        SessionState::Active(session) => session,
+        SessionState::Stale => {
+            try_delete_message(bot, msg.chat().id, msg.id()).await;
+            return Ok(Some(DELETE_PANEL_STALE.to_string()));
         }
-        db.clear_delete_session(user_id).await?;
+        state => return Ok(state.into_active().err().map(str::to_string)),
+    };
+    let mut removed = 0;
+    let mut answer = None;
+    if !session.selected.is_empty() {
+        let labels: Vec<String> = db
+            .list_items(session.chat_id)
+            .await?
+            .iter()
+            .filter(|item| session.selected.contains(&item.id))
+            .map(Item::label)
+            .collect();
+        let ids: Vec<i64> = session.selected.iter().copied().collect();
+        removed = db.delete_items_count(session.chat_id, &ids).await?;
+        answer = match labels.as_slice() {
+            _ if removed == 0 => None,
+            [label] if removed == 1 => Some(item_deleted_text(label)),
+            _ => Some(items_deleted_text(removed)),
+        };
+    }
+    if removed > 0 {
+        shared.events.list_changed(
+            db,
+            ListEvent::batch(session.chat_id, ListEventKind::Delete, removed),
+        );
+    }
+    if let Some(main_list_id) = MessageTracker::new(db).current(session.chat_id).await? {
+        ListService::new(db, shared)
+            .update_message(bot, session.chat_id, main_list_id)
+            .await?;
+    }
+    if let Some((chat_id, notice_id)) = session.notice {
+        finish_delete_notice(
+            bot,
+            chat_id,
+            notice_id,
+            delete_user_removed_text(&user.first_name, removed),
+            delete_after_timeout,
+        )
+        .await;
+    }
+    db.clear_delete_session(user_id).await?;
+    if removed > 0 {
+        refresh_delete_panels(bot, db, session.chat_id).await?;
     }
     try_delete_message(bot, msg.chat().id, msg.id()).await;
-    Ok(())
+    Ok(answer)
 }
 
 async fn toggle_selection(
@@ -145,34 +368,146 @@ async fn toggle_selection(
     user_id: i64,
     id: i64,
     db: &Database,
+    ttl: DeleteSessionTtl,
+) -> Result<Option<String>> {
+    let mut session = match load_valid_session(bot, db, user_id, msg, ttl)
+        .await?
+        .into_active()
+    {
+        Ok(session) => session,
+        Err(refusal) => return Ok(Some(refusal.to_string())),
+    };
+    let Some(item) = db.get_item(session.chat_id, id).await? else {
+        render_delete_session(bot, msg.chat().id, msg.id(), db, user_id, &session).await?;
+        return Ok(Some(ITEM_GONE.to_string()));
+    };
+    let selected = session.selected.insert(id);
+    if !selected {
+        session.selected.remove(&id);
+    }
+    db.update_delete_selection(user_id, &session.selected)
+        .await?;
+    render_delete_session(bot, msg.chat().id, msg.id(), db, user_id, &session).await?;
+    Ok(Some(item_selected_text(&item.label(), selected)))
+}
+
+/// Move the delete keyboard to `page`, keeping the selection.
+async fn change_delete_page(
+    bot: &Bot,
+    msg: &MaybeInaccessibleMessage,
+    user_id: i64,
+    page: usize,
+    db: &Database,
+    ttl: DeleteSessionTtl,
+) -> Result<Option<String>> {
+    let mut session = match load_valid_session(bot, db, user_id, msg, ttl)
+        .await?
+        .into_active()
+    {
+        Ok(session) => session,
+        Err(refusal) => return Ok(Some(refusal.to_string())),
+    };
+    tracing::debug!(user_id, page, "Changing delete page");
+    db.set_delete_page(user_id, page).await?;
+    session.page = page;
+    render_delete_session(bot, msg.chat().id, msg.id(), db, user_id, &session).await?;
+    Ok(None)
+}
+
+async fn render_delete_session(
+    bot: &Bot,
+    panel_chat: ChatId,
+    panel_id: MessageId,
+    db: &Database,
+    user_id: i64,
+    session: &DeleteSession,
 ) -> Result<()> {
-    if let Some(mut session) = load_valid_session(db, user_id, msg).await? {
-        if session.selected.contains(&id) {
-            session.selected.remove(&id);
-        } else {
-            session.selected.insert(id);
+    let items = db.list_items(session.chat_id).await?;
+    let style = db.get_settings(session.chat_id).await?.list_style;
+    let (text, keyboard) = format_delete_list(&items, &session.selected, &style, session.page);
+    if let EditOutcome::MessageMissing =
+        try_edit_message(bot, panel_chat, panel_id, text, keyboard).await
+    {
+        tracing::debug!(user_id, "Delete panel is gone; ending the session");
+        db.clear_delete_session(user_id).await?;
+    }
+    Ok(())
+}
+
+/// Re-render every open delete panel of `chat_id` after items were removed
+/// elsewhere, dropping ids that are gone from each stored selection.
+pub async fn refresh_delete_panels(bot: &Bot, db: &Database, chat_id: ChatId) -> Result<()> {
+    let sessions = db.sessions_for_chat(chat_id).await?;
+    if sessions.is_empty() {
+        return Ok(());
+    }
+    let live: HashSet<i64> = db
+        .list_items(chat_id)
+        .await?
+        .iter()
+        .map(|item| item.id)
+        .collect();
+    for (user_id, mut session) in sessions {
+        let Some(panel_id) = session.dm_message_id else {
+            continue;
+        };
+        let before = session.selected.len();
+        session.selected.retain(|id| live.contains(id));
+        if session.selected.len() != before {
+            db.update_delete_selection(user_id, &session.selected)
+                .await?;
         }
-        db.update_delete_selection(user_id, &session.selected)
-            .await?;
-        let items = db.list_items(session.chat_id).await?;
-        let (text, keyboard) = format_delete_list(&items, &session.selected);
-        try_edit_message(bot, msg.chat().id, msg.id(), text, keyboard).await;
+        tracing::debug!(
+            user_id,
+            chat_id = chat_id.0,
+            dropped = before - session.selected.len(),
+            "Refreshing delete panel"
+        );
+        let panel_chat = session.panel_chat(user_id);
+        render_delete_session(bot, panel_chat, panel_id, db, user_id, &session).await?;
     }
     Ok(())
 }
 
+/// Load the session `msg` belongs to, refusing presses on panels posted in a
+/// chat for someone else. A session older than `ttl` is torn
+/// down on the spot: its panel and notice are deleted and the row cleared, so
+/// an old selection can never remove items added since.
 async fn load_valid_session(
+    bot: &Bot,
     db: &Database,
     user_id: i64,
     msg: &MaybeInaccessibleMessage,
-) -> Result<Option<crate::db::delete_session::DeleteSession>> {
-    let Some(session) = db.get_delete_session(user_id).await? else {
-        return Ok(None);
+    ttl: DeleteSessionTtl,
+) -> Result<SessionState> {
+    let own = db.get_delete_session(user_id).await?;
+    let Some(session) = own.filter(|session| session.dm_message_id == Some(msg.id())) else {
+        if let Some(owner) = db.delete_panel_owner(msg.chat().id, msg.id()).await? {
+            tracing::debug!(
+                user_id,
+                owner,
+                "Refusing press on another user's delete panel"
+            );
+            return Ok(SessionState::Foreign);
+        }
+        return Ok(SessionState::Stale);
     };
-    if session.dm_message_id.map(|m| m.0) != Some(msg.id().0) {
-        return Ok(None);
+    if !session_expired(&session, chrono::Utc::now().timestamp(), ttl) {
+        return Ok(SessionState::Active(session));
     }
-    Ok(Some(session))
+
+    tracing::debug!(
+        user_id,
+        chat_id = session.chat_id.0,
+        created_at = session.created_at,
+        "Rejecting expired delete session"
+    );
+    try_delete_message(bot, msg.chat().id, msg.id()).await;
+    if let Some((chat_id, notice_id)) = session.notice {
+        try_delete_message(bot, chat_id, notice_id).await;
+    }
+    db.clear_delete_session(user_id).await?;
+    Ok(SessionState::Expired)
 }
 
 pub async fn enter_delete_mode(
@@ -180,17 +515,23 @@ pub async fn enter_delete_mode(
     msg: Message,
     db: &Database,
     delete_after_timeout: u64,
+    ttl: DeleteSessionTtl,
 ) -> Result<()> {
     tracing::debug!(
         chat_id = msg.chat.id.0,
         user_id = msg.from.as_ref().map(|u| u.id.0),
         "Entering delete mode",
     );
-    try_delete_message(&bot, msg.chat.id, msg.id).await;
+    delete_user_message(&bot, db, msg.chat.id, msg.id).await?;
 
-    if db.get_last_list_message_id(msg.chat.id).await?.is_none() {
+    if MessageTracker::new(db)
+        .current(msg.chat.id)
+        .await?
+        .is_none()
+    {
         let sent_msg = bot
             .send_message(msg.chat.id, NO_ACTIVE_LIST_TO_EDIT)
+            .in_topic(topic_of(&msg))
             .await?;
         drop(crate::delete_after(
             bot.clone(),
@@ -213,42 +554,187 @@ pub async fn enter_delete_mode(
         return Ok(());
     }
 
-    start_delete_session(&bot, &msg, user, db, &items, delete_after_timeout).await
+    start_delete_session(&bot, &msg, user, db, &items, delete_after_timeout, ttl).await
 }
 
-pub async fn callback_handler(bot: Bot, q: CallbackQuery, db: Database) -> Result<()> {
-    if let (Some(data), Some(msg)) = (q.data, q.message) {
-        if let Some(id_str) = data.strip_prefix("delete_") {
-            let user_id = q.from.id.0 as i64;
+/// Check or uncheck an item of the list message. The item is loaded first so
+/// the toast can name it.
+async fn toggle_item(
+    bot: &Bot,
+    msg: &MaybeInaccessibleMessage,
+    db: &Database,
+    shared: &SharedState,
+    id: i64,
+) -> Result<Option<String>> {
+    let chat_id = msg.chat().id;
+    let answer = match db.get_item(chat_id, id).await? {
+        Some(item) if db.toggle_item_count(chat_id, id).await? > 0 => {
+            shared
+                .events
+                .list_changed(db, ListEvent::item(chat_id, ListEventKind::Toggle, id));
+            item_toggled_text(&item.label(), !item.done)
+        }
+        _ => ITEM_GONE.to_string(),
+    };
+    ListService::new(db, shared)
+        .update_message(bot, chat_id, msg.id())
+        .await?;
+    Ok(Some(answer))
+}
 
-            if id_str == "done" {
-                process_done_callback(&bot, &msg, user_id, &db).await?;
-            } else if let Ok(id) = id_str.parse::<i64>() {
-                toggle_selection(&bot, &msg, user_id, id, &db).await?;
-            }
-        } else if let Ok(id) = data.parse::<i64>() {
-            db.toggle_item_count(msg.chat().id, id).await?;
-            ListService::new(&db)
-                .update_message(&bot, msg.chat().id, msg.id())
-                .await?;
+/// Handle one button press and return the toast to show for it, if any.
+async fn dispatch_callback(
+    bot: &Bot,
+    q: &CallbackQuery,
+    db: &Database,
+    shared: &SharedState,
+    delete_after_timeout: u64,
+    ttl: DeleteSessionTtl,
+) -> Result<Option<String>> {
+    let (Some(data), Some(msg)) = (q.data.as_deref(), q.message.as_ref()) else {
+        return Ok(None);
+    };
+    let from = &q.from;
+    let user_id = from.id.0 as i64;
+    let Some(action) = CallbackAction::parse(data) else {
+        tracing::debug!(data, "Ignoring unknown callback data");
+        return Ok(None);
+    };
+    let answer = match action {
+        CallbackAction::Toggle(id) => toggle_item(bot, msg, db, shared, id).await?,
+        CallbackAction::Delete(id) => toggle_selection(bot, msg, user_id, id, db, ttl).await?,
+        CallbackAction::DeleteDone => {
+            process_done_callback(bot, msg, from, db, shared, delete_after_timeout, ttl).await?
+        }
+        CallbackAction::DeletePage(page) => {
+            change_delete_page(bot, msg, user_id, page, db, ttl).await?
+        }
+        CallbackAction::ListPage(page) => {
+            handle_page_callback(bot, msg, db, shared, page).await?;
+            None
+        }
+        CallbackAction::NukeConfirm(owner) => {
+            let answer = NukeAnswer {
+                owner,
+                confirmed: true,
+            };
+            handle_nuke_callback(bot, msg, from, db, shared, answer, delete_after_timeout).await?
         }
+        CallbackAction::NukeCancel(owner) => {
+            let answer = NukeAnswer {
+                owner,
+                confirmed: false,
+            };
+            handle_nuke_callback(bot, msg, from, db, shared, answer, delete_after_timeout).await?
+        }
+        CallbackAction::Edit(rest) => {
+            handle_edit_callback(bot, msg, from, db, &rest).await?;
+            None
+        }
+        CallbackAction::Settings(rest) => {
+            handle_settings_callback(bot, msg, db, shared, &rest).await?;
+            None
+        }
+        CallbackAction::Duplicate(rest) => {
+            handle_duplicate_callback(bot, msg.chat().id, db, shared, &rest).await?;
+            None
+        }
+        CallbackAction::Suggest(rest) => {
+            handle_suggest_callback(bot, msg, from, db, shared, &rest).await?;
+            None
+        }
+        CallbackAction::Restore(rest) => {
+            handle_restore_callback(bot, msg, db, shared, &rest).await?;
+            None
+        }
+        CallbackAction::Onboarding(action) => {
+            handle_onboarding_callback(bot, msg.chat().id, from, db, shared, &action).await?;
+            None
+        }
+        CallbackAction::Assign(rest) => {
+            handle_assign_callback(bot, msg, from, db, shared, &rest).await?
+        }
+        CallbackAction::VoiceDelete(rest) => {
+            handle_voice_delete_callback(bot, msg, from, db, shared, &rest).await?
+        }
+    };
+    Ok(answer)
+}
+
+/// Telegram shows at most this many characters in a callback toast.
+const TOAST_MAX_CHARS: usize = 200;
+
+fn toast_text(text: &str) -> String {
+    if text.chars().count() <= TOAST_MAX_CHARS {
+        return text.to_string();
     }
+    let mut truncated: String = text.chars().take(TOAST_MAX_CHARS - 1).collect();
+    truncated.push('…');
+    truncated
+}
 
-    bot.answer_callback_query(q.id).await?;
-    Ok(())
+pub async fn callback_handler(
+    bot: Bot,
+    q: CallbackQuery,
+    db: Database,
+    shared: SharedState,
+    delete_after_timeout: u64,
+    delete_session_ttl: DeleteSessionTtl,
+) -> Result<()> {
+    let result = dispatch_callback(
+        &bot,
+        &q,
+        &db,
+        &shared,
+        delete_after_timeout,
+        delete_session_ttl,
+    )
+    .await;
+
+    // Answered exactly once, errors included, so the button stops spinning.
+    let mut answer = bot.answer_callback_query(q.id);
+    if let Ok(Some(text)) = &result {
+        tracing::trace!(text = %text, "Answering callback with a toast");
+        answer = answer.text(toast_text(text)).show_alert(false);
+    }
+    answer.await?;
+    result.map(drop)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::tests::util::init_test_db;
+    use crate::tests::util::{init_test_db, test_bot, SENT_MESSAGE};
     use reqwest::Client;
     use teloxide::types::{ChatId, MaybeInaccessibleMessage, MessageId, UserId};
     use wiremock::{
-        matchers::{method, path, path_regex},
+        matchers::{body_partial_json, body_string_contains, method, path, path_regex},
         Mock, MockServer, ResponseTemplate,
     };
 
+    async fn mock_notice_server(expected_text: &str) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/EditMessageText"))
+            .and(body_string_contains(expected_text))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(r#"{"ok":true,"result":true}"#, "application/json"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path_regex(r"^/botTEST/[Dd]eleteMessage$"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(r#"{"ok":true,"result":true}"#, "application/json"),
+            )
+            .mount(&server)
+            .await;
+        server
+    }
+
     #[tokio::test]
     async fn cleanup_previous_session_deletes_messages() {
         let server = MockServer::start().await;
@@ -308,7 +794,9 @@ mod tests {
         let msg_json = r#"{"message_id":5,"date":0,"chat":{"id":1,"type":"private"}}"#;
         let msg: MaybeInaccessibleMessage = serde_json::from_str(msg_json).unwrap();
 
-        toggle_selection(&bot, &msg, 1, item_id, &db).await.unwrap();
+        toggle_selection(&bot, &msg, 1, item_id, &db, DeleteSessionTtl::default())
+            .await
+            .unwrap();
         let session = db.get_delete_session(1).await.unwrap().unwrap();
         assert!(session.selected.contains(&item_id));
         server.verify().await;
@@ -328,7 +816,731 @@ mod tests {
         let msg_json = r#"{"message_id":6,"date":0,"chat":{"id":1,"type":"private"}}"#;
         let msg: MaybeInaccessibleMessage = serde_json::from_str(msg_json).unwrap();
 
-        let session = load_valid_session(&db, user.0 as i64, &msg).await.unwrap();
-        assert!(session.is_none());
+        let bot = Bot::new("test");
+        let state = load_valid_session(&bot, &db, user.0 as i64, &msg, DeleteSessionTtl::default())
+            .await
+            .unwrap();
+        assert!(matches!(state, SessionState::Stale));
+        assert!(db
+            .get_delete_session(user.0 as i64)
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn done_callback_updates_notice_with_removed_count() {
+        let server = mock_notice_server("Anna removed 1 item.").await;
+        let bot = test_bot(&server);
+        let db = init_test_db().await;
+        let chat = ChatId(-100);
+        db.add_item_count(chat, "Milk").await.unwrap();
+        let item_id = db.list_items(chat).await.unwrap()[0].id;
+
+        db.init_delete_session(1, chat).await.unwrap();
+        db.set_delete_dm_message(1, MessageId(5)).await.unwrap();
+        db.set_delete_notice(1, chat, MessageId(9)).await.unwrap();
+        db.update_delete_selection(1, &HashSet::from([item_id]))
+            .await
+            .unwrap();
+
+        let user: User =
+            serde_json::from_str(r#"{"id":1,"is_bot":false,"first_name":"Anna"}"#).unwrap();
+        let msg_json = r#"{"message_id":5,"date":0,"chat":{"id":1,"type":"private"}}"#;
+        let msg: MaybeInaccessibleMessage = serde_json::from_str(msg_json).unwrap();
+
+        process_done_callback(
+            &bot,
+            &msg,
+            &user,
+            &db,
+            &SharedState::new().unwrap(),
+            60,
+            DeleteSessionTtl::default(),
+        )
+        .await
+        .unwrap();
+
+        assert!(db.list_items(chat).await.unwrap().is_empty());
+        assert!(db.get_delete_session(1).await.unwrap().is_none());
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn done_callback_skips_items_removed_meanwhile() {
+        let server = mock_notice_server("Anna removed 1 item.").await;
+        let bot = test_bot(&server);
+        let db = init_test_db().await;
+        let chat = ChatId(-100);
+        db.add_items_count(chat, &["Milk".into(), "Eggs".into(), "Bread".into()])
+            .await
+            .unwrap();
+        let ids: Vec<i64> = db
+            .list_items(chat)
+            .await
+            .unwrap()
+            .iter()
+            .map(|item| item.id)
+            .collect();
+
+        db.init_delete_session(1, chat).await.unwrap();
+        db.set_delete_dm_message(1, MessageId(5)).await.unwrap();
+        db.set_delete_notice(1, chat, MessageId(9)).await.unwrap();
+        db.update_delete_selection(1, &HashSet::from([ids[0], ids[1]]))
+            .await
+            .unwrap();
+        // Someone else removes a selected item before Done is pressed.
+        db.delete_item_count(chat, ids[1]).await.unwrap();
+
+        let user: User =
+            serde_json::from_str(r#"{"id":1,"is_bot":false,"first_name":"Anna"}"#).unwrap();
+        let msg_json = r#"{"message_id":5,"date":0,"chat":{"id":1,"type":"private"}}"#;
+        let msg: MaybeInaccessibleMessage = serde_json::from_str(msg_json).unwrap();
+
+        process_done_callback(
+            &bot,
+            &msg,
+            &user,
+            &db,
+            &SharedState::new().unwrap(),
+            60,
+            DeleteSessionTtl::default(),
+        )
+        .await
+        .unwrap();
+
+        let texts: Vec<String> = db
+            .list_items(chat)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|item| item.text)
+            .collect();
+        assert_eq!(texts, vec!["Bread"]);
+        assert!(db.get_delete_session(1).await.unwrap().is_none());
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn expire_delete_session_updates_notice() {
+        let server = mock_notice_server("Anna's delete session expired.").await;
+        let bot = test_bot(&server);
+        let db = init_test_db().await;
+        let chat = ChatId(-100);
+
+        db.init_delete_session(1, chat).await.unwrap();
+        db.set_delete_dm_message(1, MessageId(5)).await.unwrap();
+        db.set_delete_notice(1, chat, MessageId(9)).await.unwrap();
+
+        let expired = expire_delete_session(&bot, &db, 1, MessageId(5), "Anna", 60)
+            .await
+            .unwrap();
+
+        assert!(expired);
+        assert!(db.get_delete_session(1).await.unwrap().is_none());
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn expire_delete_session_ignores_replaced_session() {
+        let db = init_test_db().await;
+        let bot = Bot::new("test");
+        db.init_delete_session(1, ChatId(1)).await.unwrap();
+        db.set_delete_dm_message(1, MessageId(6)).await.unwrap();
+
+        let expired = expire_delete_session(&bot, &db, 1, MessageId(5), "Anna", 60)
+            .await
+            .unwrap();
+
+        assert!(!expired);
+        assert!(db.get_delete_session(1).await.unwrap().is_some());
+    }
+
+    #[test]
+    fn sessions_expire_once_older_than_the_ttl() {
+        let session = DeleteSession {
+            chat_id: ChatId(1),
+            selected: HashSet::new(),
+            notice: None,
+            dm_message_id: Some(MessageId(5)),
+            page: 0,
+            created_at: 1_000,
+        };
+        let ttl = DeleteSessionTtl(600);
+        assert!(!session_expired(&session, 1_000, ttl));
+        assert!(!session_expired(&session, 1_600, ttl));
+        assert!(session_expired(&session, 1_601, ttl));
+    }
+
+    #[tokio::test]
+    async fn expired_session_is_torn_down_on_press() {
+        let server = toast_server(DELETE_SESSION_EXPIRED).await;
+        let db = init_test_db().await;
+        let chat = ChatId(-100);
+        db.add_item_count(chat, "Milk").await.unwrap();
+        let id = db.list_items(chat).await.unwrap()[0].id;
+        db.init_delete_session(1, chat).await.unwrap();
+        db.set_delete_dm_message(1, MessageId(5)).await.unwrap();
+        db.set_delete_notice(1, chat, MessageId(9)).await.unwrap();
+        db.update_delete_selection(1, &HashSet::from([id]))
+            .await
+            .unwrap();
+        sqlx::query("UPDATE delete_session SET created_at = created_at - 601")
+            .execute(db.pool())
+            .await
+            .unwrap();
+
+        callback_handler(
+            test_bot(&server),
+            callback_query("delete_done"),
+            db.clone(),
+            SharedState::new().unwrap(),
+            60,
+            DeleteSessionTtl(600),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(db.list_items(chat).await.unwrap().len(), 1);
+        assert!(db.get_delete_session(1).await.unwrap().is_none());
+        let deleted: Vec<serde_json::Value> = server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .filter(|request| {
+                request
+                    .url
+                    .path()
+                    .eq_ignore_ascii_case("/botTEST/deleteMessage")
+            })
+            .map(|request| serde_json::from_slice(&request.body).unwrap())
+            .collect();
+        assert_eq!(deleted.len(), 2);
+        assert_eq!(deleted[1]["message_id"], 9);
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn fresh_session_is_not_expired() {
+        let server = toast_server("Milk selected for deletion").await;
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_item_count(chat, "Milk").await.unwrap();
+        let id = db.list_items(chat).await.unwrap()[0].id;
+        db.init_delete_session(1, chat).await.unwrap();
+        db.set_delete_dm_message(1, MessageId(5)).await.unwrap();
+
+        callback_handler(
+            test_bot(&server),
+            callback_query(&format!("delete_{id}")),
+            db.clone(),
+            SharedState::new().unwrap(),
+            60,
+            DeleteSessionTtl(600),
+        )
+        .await
+        .unwrap();
+
+        let session = db.get_delete_session(1).await.unwrap().unwrap();
+        assert!(session.selected.contains(&id));
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn failed_dm_posts_the_panel_in_the_chat() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SendMessage"))
+            .and(body_partial_json(serde_json::json!({"chat_id": 1})))
+            .respond_with(ResponseTemplate::new(403).set_body_raw(
+                r#"{"ok":false,"error_code":403,"description":"Forbidden: bot can't initiate conversation with a user"}"#,
+                "application/json",
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SendMessage"))
+            .and(body_partial_json(serde_json::json!({"chat_id": -100})))
+            .and(body_string_contains("Only Anna can use it"))
+            .and(body_string_contains("delete_done"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"ok":true,"result":{"message_id":12,"date":0,"chat":{"id":-100,"type":"group","title":"Family"}}}"#,
+                "application/json",
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let db = init_test_db().await;
+        let chat = ChatId(-100);
+        db.add_item_count(chat, "Milk").await.unwrap();
+        let items = db.list_items(chat).await.unwrap();
+        let msg: Message = serde_json::from_value(serde_json::json!({
+            "message_id": 1,
+            "date": 0,
+            "chat": {"id": -100, "type": "group", "title": "Family"},
+            "from": {"id": 1, "is_bot": false, "first_name": "Anna"},
+            "text": "/delete",
+        }))
+        .unwrap();
+        let user = msg.from.clone().unwrap();
+
+        start_delete_session(
+            &test_bot(&server),
+            &msg,
+            &user,
+            &db,
+            &items,
+            60,
+            DeleteSessionTtl::default(),
+        )
+        .await
+        .unwrap();
+
+        let session = db.get_delete_session(1).await.unwrap().unwrap();
+        assert_eq!(session.dm_message_id, Some(MessageId(12)));
+        assert_eq!(session.panel_chat(1), chat);
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn only_the_owner_can_press_an_in_chat_panel() {
+        let server = toast_server(DELETE_PANEL_NOT_YOURS).await;
+        let db = init_test_db().await;
+        let chat = ChatId(-100);
+        db.add_item_count(chat, "Milk").await.unwrap();
+        let id = db.list_items(chat).await.unwrap()[0].id;
+        db.init_delete_session(1, chat).await.unwrap();
+        db.set_delete_dm_message(1, MessageId(12)).await.unwrap();
+        let press: CallbackQuery = serde_json::from_value(serde_json::json!({
+            "id": "q1",
+            "from": {"id": 2, "is_bot": false, "first_name": "Ben"},
+            "chat_instance": "c",
+            "data": CallbackAction::Delete(id).to_string(),
+            "message": {
+                "message_id": 12,
+                "date": 0,
+                "chat": {"id": -100, "type": "group", "title": "Family"},
+            },
+        }))
+        .unwrap();
+
+        callback_handler(
+            test_bot(&server),
+            press,
+            db.clone(),
+            SharedState::new().unwrap(),
+            60,
+            DeleteSessionTtl::default(),
+        )
+        .await
+        .unwrap();
+
+        let session = db.get_delete_session(1).await.unwrap().unwrap();
+        assert!(session.selected.is_empty());
+        assert_eq!(server.received_requests().await.unwrap().len(), 1);
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn done_refreshes_other_panels_of_the_chat() {
+        let server = toast_server("Milk deleted").await;
+        let db = init_test_db().await;
+        let chat = ChatId(-100);
+        db.add_items_count(chat, &["Milk".into(), "Eggs".into()])
+            .await
+            .unwrap();
+        let ids: Vec<i64> = db
+            .list_items(chat)
+            .await
+            .unwrap()
+            .iter()
+            .map(|item| item.id)
+            .collect();
+        for (user, panel) in [(1, 5), (2, 6)] {
+            db.init_delete_session(user, chat).await.unwrap();
+            db.set_delete_dm_message(user, MessageId(panel))
+                .await
+                .unwrap();
+            db.set_delete_notice(user, chat, MessageId(panel + 10))
+                .await
+                .unwrap();
+        }
+        db.update_delete_selection(1, &HashSet::from([ids[0]]))
+            .await
+            .unwrap();
+        db.update_delete_selection(2, &HashSet::from([ids[0], ids[1]]))
+            .await
+            .unwrap();
+
+        callback_handler(
+            test_bot(&server),
+            callback_query("delete_done"),
+            db.clone(),
+            SharedState::new().unwrap(),
+            60,
+            DeleteSessionTtl::default(),
+        )
+        .await
+        .unwrap();
+
+        let other = db.get_delete_session(2).await.unwrap().unwrap();
+        assert_eq!(other.selected, HashSet::from([ids[1]]));
+        let refreshed: Vec<serde_json::Value> = server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .filter(|request| request.url.path() == "/botTEST/EditMessageText")
+            .map(|request| serde_json::from_slice(&request.body).unwrap())
+            .filter(|body: &serde_json::Value| body["chat_id"] == 2)
+            .collect();
+        assert_eq!(refreshed.len(), 1);
+        assert_eq!(refreshed[0]["message_id"], 6);
+        let panel = refreshed[0].to_string();
+        assert!(!panel.contains("Milk"));
+        assert!(panel.contains("Eggs"));
+        server.verify().await;
+    }
+
+    async fn paging_server(expected_label: &str, edits: u64) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/EditMessageText"))
+            .and(body_string_contains(expected_label))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(r#"{"ok":true,"result":true}"#, "application/json"),
+            )
+            .expect(edits)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/AnswerCallbackQuery"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(r#"{"ok":true,"result":true}"#, "application/json"),
+            )
+            .mount(&server)
+            .await;
+        server
+    }
+
+    fn callback_query(data: &str) -> CallbackQuery {
+        serde_json::from_value(serde_json::json!({
+            "id": "q1",
+            "from": {"id": 1, "is_bot": false, "first_name": "Anna"},
+            "chat_instance": "c",
+            "data": data,
+            "message": {"message_id": 5, "date": 0, "chat": {"id": 1, "type": "private"}},
+        }))
+        .unwrap()
+    }
+
+    async fn numbered_items(db: &Database, chat: ChatId, count: usize) -> Vec<i64> {
+        let texts: Vec<String> = (1..=count).map(|n| format!("Item {n}")).collect();
+        db.add_items_count(chat, &texts).await.unwrap();
+        db.list_items(chat)
+            .await
+            .unwrap()
+            .iter()
+            .map(|item| item.id)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn toggling_an_item_keeps_the_list_page() {
+        let server = paging_server("2/3", 1).await;
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        let ids = numbered_items(&db, chat, 101).await;
+        db.set_list_page(chat, 1).await.unwrap();
+
+        callback_handler(
+            test_bot(&server),
+            callback_query(&CallbackAction::Toggle(ids[60]).to_string()),
+            db.clone(),
+            SharedState::new().unwrap(),
+            60,
+            DeleteSessionTtl::default(),
+        )
+        .await
+        .unwrap();
+
+        assert!(db.list_items(chat).await.unwrap()[60].done);
+        assert_eq!(db.list_page(chat).await.unwrap(), 1);
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn delete_keyboard_pages_keep_selection() {
+        let server = paging_server("3/3", 2).await;
+        let bot = test_bot(&server);
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        let ids = numbered_items(&db, chat, 101).await;
+        db.init_delete_session(1, chat).await.unwrap();
+        db.set_delete_dm_message(1, MessageId(5)).await.unwrap();
+
+        callback_handler(
+            bot.clone(),
+            callback_query("delete_page_2"),
+            db.clone(),
+            SharedState::new().unwrap(),
+            60,
+            DeleteSessionTtl::default(),
+        )
+        .await
+        .unwrap();
+        callback_handler(
+            bot,
+            callback_query(&format!("delete_{}", ids[100])),
+            db.clone(),
+            SharedState::new().unwrap(),
+            60,
+            DeleteSessionTtl::default(),
+        )
+        .await
+        .unwrap();
+
+        let session = db.get_delete_session(1).await.unwrap().unwrap();
+        assert_eq!(session.page, 2);
+        assert!(session.selected.contains(&ids[100]));
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn concurrent_toggles_leave_the_newest_list_on_screen() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SendMessage"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(SENT_MESSAGE, "application/json")
+                    .set_delay(std::time::Duration::from_millis(5)),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(r#"{"ok":true,"result":true}"#, "application/json")
+                    .set_delay(std::time::Duration::from_millis(5)),
+            )
+            .mount(&server)
+            .await;
+        let bot = test_bot(&server);
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        let ids = numbered_items(&db, chat, 10).await;
+        let shared = SharedState::new().unwrap();
+
+        let taps: Vec<_> = ids
+            .iter()
+            .chain(&ids[..5])
+            .map(|&id| {
+                tokio::spawn(callback_handler(
+                    bot.clone(),
+                    callback_query(&CallbackAction::Toggle(id).to_string()),
+                    db.clone(),
+                    shared.clone(),
+                    60,
+                    DeleteSessionTtl::default(),
+                ))
+            })
+            .collect();
+        for tap in taps {
+            tap.await.unwrap().unwrap();
+        }
+
+        let edits: Vec<serde_json::Value> = server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .filter(|request| request.url.path() == "/botTEST/EditMessageText")
+            .map(|request| serde_json::from_slice(&request.body).unwrap())
+            .collect();
+        assert_eq!(edits.len(), 15);
+        let items = db.list_items(chat).await.unwrap();
+        assert_eq!(items.iter().filter(|item| item.done).count(), 5);
+        let settings = db.get_settings(chat).await.unwrap();
+        let expected = crate::handlers::list::format_list_page(&items, &settings, 0).text;
+        assert_eq!(edits.last().unwrap()["text"], expected);
+
+        // `/done` deletes the list and sends it again; a toggle landing in
+        // between would edit a list that is already gone.
+        db.update_last_list_message_id(chat, 5).await.unwrap();
+        let archive = tokio::spawn({
+            let (bot, db, shared) = (bot.clone(), db.clone(), shared.clone());
+            async move {
+                ListService::new(&db, &shared)
+                    .archive_checked(bot, chat)
+                    .await
+            }
+        });
+        let mut taps = Vec::new();
+        for &id in &ids[..5] {
+            tokio::time::sleep(std::time::Duration::from_millis(4)).await;
+            taps.push(tokio::spawn(callback_handler(
+                bot.clone(),
+                callback_query(&CallbackAction::Toggle(id).to_string()),
+                db.clone(),
+                shared.clone(),
+                60,
+                DeleteSessionTtl::default(),
+            )));
+        }
+        archive.await.unwrap().unwrap();
+        for tap in taps {
+            tap.await.unwrap().unwrap();
+        }
+
+        let requests = server.received_requests().await.unwrap();
+        let deleted = requests
+            .iter()
+            .position(|request| request.url.path() == "/botTEST/DeleteMessage")
+            .unwrap();
+        let resent = requests
+            .iter()
+            .position(|request| {
+                request.url.path() == "/botTEST/SendMessage"
+                    && String::from_utf8_lossy(&request.body).contains("reply_markup")
+            })
+            .unwrap();
+        assert!(deleted < resent);
+        assert!(requests[deleted..resent]
+            .iter()
+            .all(|request| request.url.path() != "/botTEST/EditMessageText"));
+    }
+
+    /// Answers the callback query with a body containing `toast` exactly
+    /// once; every other Bot API call succeeds.
+    async fn toast_server(toast: &str) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/AnswerCallbackQuery"))
+            .and(body_string_contains(toast))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(r#"{"ok":true,"result":true}"#, "application/json"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(r#"{"ok":true,"result":true}"#, "application/json"),
+            )
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[tokio::test]
+    async fn toggle_answers_with_the_item_name() {
+        let server = toast_server("✅ Milk checked").await;
+        let db = init_test_db().await;
+        db.add_item_count(ChatId(1), "Milk").await.unwrap();
+        let id = db.list_items(ChatId(1)).await.unwrap()[0].id;
+
+        let data = CallbackAction::Toggle(id).to_string();
+        callback_handler(
+            test_bot(&server),
+            callback_query(&data),
+            db,
+            SharedState::new().unwrap(),
+            60,
+            DeleteSessionTtl::default(),
+        )
+        .await
+        .unwrap();
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn missing_item_and_stale_panel_are_reported() {
+        let db = init_test_db().await;
+        for (data, toast) in [
+            (CallbackAction::Toggle(999).to_string(), ITEM_GONE),
+            (CallbackAction::Delete(999).to_string(), DELETE_PANEL_STALE),
+        ] {
+            let server = toast_server(toast).await;
+            callback_handler(
+                test_bot(&server),
+                callback_query(&data),
+                db.clone(),
+                SharedState::new().unwrap(),
+                60,
+                DeleteSessionTtl::default(),
+            )
+            .await
+            .unwrap();
+            server.verify().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn delete_done_answers_with_the_removed_item() {
+        let server = toast_server("🗑 Milk deleted").await;
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_item_count(chat, "Milk").await.unwrap();
+        let id = db.list_items(chat).await.unwrap()[0].id;
+        db.init_delete_session(1, chat).await.unwrap();
+        db.set_delete_dm_message(1, MessageId(5)).await.unwrap();
+        db.update_delete_selection(1, &HashSet::from([id]))
+            .await
+            .unwrap();
+
+        callback_handler(
+            test_bot(&server),
+            callback_query("delete_done"),
+            db.clone(),
+            SharedState::new().unwrap(),
+            60,
+            DeleteSessionTtl::default(),
+        )
+        .await
+        .unwrap();
+        assert!(db.list_items(chat).await.unwrap().is_empty());
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn failed_callbacks_are_still_answered() {
+        let server = toast_server("callback_query_id").await;
+        let db = init_test_db().await;
+        db.pool().close().await;
+
+        let result = callback_handler(
+            test_bot(&server),
+            callback_query(&CallbackAction::Toggle(1).to_string()),
+            db,
+            SharedState::new().unwrap(),
+            60,
+            DeleteSessionTtl::default(),
+        )
+        .await;
+        assert!(result.is_err());
+        let answers = server
+            .received_requests()
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|request| request.url.path() == "/botTEST/AnswerCallbackQuery")
+            .count();
+        assert_eq!(answers, 1);
+        server.verify().await;
+    }
+
+    #[test]
+    fn long_toasts_are_truncated() {
+        let toast = toast_text(&"x".repeat(300));
+        assert_eq!(toast.chars().count(), TOAST_MAX_CHARS);
+        assert!(toast.ends_with('…'));
+        assert_eq!(toast_text("✅ Milk checked"), "✅ Milk checked");
     }
 }
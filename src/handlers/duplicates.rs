@@ -0,0 +1,519 @@
+//! Ask before re-adding an item that is already on the list but checked off,
+//! and skip AI-parsed items that are on it unchecked.
+
+use anyhow::Result;
+use std::collections::HashSet;
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup},
+};
+use tracing::Instrument;
+
+use super::list_service::ListService;
+use crate::db::{Database, DuplicateAction, Item};
+use crate::messages::{duplicate_checked_text, DUPLICATE_ADD_BUTTON, DUPLICATE_UNCHECK_BUTTON};
+use crate::notify::{ListEvent, ListEventKind};
+use crate::state::SharedState;
+use crate::text_utils::normalize_for_match;
+use crate::utils::try_delete_message;
+
+pub const DUPLICATE_PREFIX: &str = "dup_";
+/// Seconds before an unanswered prompt falls back to the chat's default.
+pub const DUPLICATE_PROMPT_TIMEOUT_SECS: u64 = 60;
+
+fn action_label(action: DuplicateAction) -> &'static str {
+    match action {
+        DuplicateAction::Add => DUPLICATE_ADD_BUTTON,
+        DuplicateAction::Uncheck => DUPLICATE_UNCHECK_BUTTON,
+    }
+}
+
+fn duplicate_keyboard(item_id: i64) -> InlineKeyboardMarkup {
+    let buttons = DuplicateAction::ALL.into_iter().map(|action| {
+        InlineKeyboardButton::callback(
+            action_label(action),
+            format!("{DUPLICATE_PREFIX}{}_{item_id}", action.name()),
+        )
+    });
+    InlineKeyboardMarkup::new([buttons.collect::<Vec<_>>()])
+}
+
+fn parse_callback(data: &str) -> Option<(DuplicateAction, i64)> {
+    let (action, id) = data.split_once('_')?;
+    Some((DuplicateAction::parse(action)?, id.parse().ok()?))
+}
+
+/// Split new entries into ones to insert and checked-off items they repeat.
+/// An entry only counts as a repeat when no unchecked copy is on the list.
+pub fn split_checked_duplicates(existing: &[Item], items: Vec<String>) -> (Vec<String>, Vec<Item>) {
+    let unchecked: HashSet<String> = existing
+        .iter()
+        .filter(|item| !item.done)
+        .map(|item| normalize_for_match(&item.text))
+        .collect();
+    let mut fresh = Vec::new();
+    let mut duplicates: Vec<Item> = Vec::new();
+    for text in items {
+        let needle = normalize_for_match(&text);
+        let checked = existing
+            .iter()
+            .find(|item| item.done && normalize_for_match(&item.text) == needle);
+        match checked {
+            Some(item) if !unchecked.contains(&needle) => {
+                if !duplicates.iter().any(|dup| dup.id == item.id) {
+                    duplicates.push(item.clone());
+                }
+            }
+            _ => fresh.push(text),
+        }
+    }
+    (fresh, duplicates)
+}
+
+/// Drop entries that repeat an unchecked item on the list or an earlier
+/// entry of the batch. Checked-off repeats are kept for
+/// [`split_checked_duplicates`] to ask about. Returns the kept entries and
+/// how many were skipped.
+pub fn skip_listed_duplicates<T>(
+    existing: &[Item],
+    items: Vec<T>,
+    text: impl Fn(&T) -> &str,
+) -> (Vec<T>, usize) {
+    let mut seen: HashSet<String> = existing
+        .iter()
+        .filter(|item| !item.done)
+        .map(|item| normalize_for_match(&item.text))
+        .collect();
+    let total = items.len();
+    let fresh: Vec<T> = items
+        .into_iter()
+        .filter(|entry| seen.insert(normalize_for_match(text(entry))))
+        .collect();
+    let skipped = total - fresh.len();
+    (fresh, skipped)
+}
+
+/// Skip AI-parsed entries that are already on the list, unless the chat
+/// turned this off with `/settings dedupe off`.
+pub async fn skip_listed_items<T>(
+    db: &Database,
+    chat_id: ChatId,
+    items: Vec<T>,
+    text: impl Fn(&T) -> &str,
+) -> Result<Vec<T>> {
+    if items.is_empty() || !db.get_settings(chat_id).await?.skip_listed_items {
+        return Ok(items);
+    }
+    let existing = db.list_items(chat_id).await?;
+    let (fresh, skipped) = skip_listed_duplicates(&existing, items, text);
+    if skipped > 0 {
+        tracing::info!(chat_id = chat_id.0, skipped, "Skipped {skipped} duplicates");
+    }
+    Ok(fresh)
+}
+
+/// Ask the chat about each checked-off duplicate and apply the chat's
+/// default when nobody answers within `timeout_secs`.
+pub async fn prompt_checked_duplicates(
+    bot: &Bot,
+    chat_id: ChatId,
+    db: &Database,
+    shared: &SharedState,
+    duplicates: &[Item],
+    timeout_secs: u64,
+) -> Result<()> {
+    for item in duplicates {
+        let created_at = chrono::Utc::now().timestamp();
+        if !db
+            .create_duplicate_prompt(chat_id, item.id, created_at)
+            .await?
+        {
+            tracing::debug!(
+                chat_id = chat_id.0,
+                item_id = item.id,
+                "Duplicate prompt already open"
+            );
+            continue;
+        }
+        let prompt = bot
+            .send_message(chat_id, duplicate_checked_text(&item.text))
+            .reply_markup(duplicate_keyboard(item.id))
+            .await?;
+        db.set_duplicate_prompt_message(item.id, prompt.id).await?;
+        schedule_default(
+            bot.clone(),
+            db.clone(),
+            shared.clone(),
+            chat_id,
+            item.id,
+            timeout_secs,
+        );
+    }
+    Ok(())
+}
+
+fn schedule_default(
+    bot: Bot,
+    db: Database,
+    shared: SharedState,
+    chat_id: ChatId,
+    item_id: i64,
+    secs: u64,
+) {
+    tracing::debug!(
+        chat_id = chat_id.0,
+        item_id,
+        delay_secs = secs,
+        "Scheduling duplicate prompt default"
+    );
+    tokio::spawn(
+        async move {
+            tokio::time::sleep(std::time::Duration::from_secs(secs)).await;
+            let result = async {
+                let action = db.get_settings(chat_id).await?.duplicate_default;
+                resolve_duplicate(&bot, &db, &shared, chat_id, item_id, action).await
+            }
+            .await;
+            if let Err(err) = result {
+                tracing::warn!(error = %err, chat_id = chat_id.0, item_id, "Failed to apply duplicate default");
+            }
+        }
+        .in_current_span(),
+    );
+}
+
+/// Apply `action` to a pending prompt. Returns `false` when the prompt was
+/// already answered or timed out.
+pub async fn resolve_duplicate(
+    bot: &Bot,
+    db: &Database,
+    shared: &SharedState,
+    chat_id: ChatId,
+    item_id: i64,
+    action: DuplicateAction,
+) -> Result<bool> {
+    let Some(prompt) = db.take_duplicate_prompt(chat_id, item_id).await? else {
+        tracing::debug!(
+            chat_id = chat_id.0,
+            item_id,
+            "Duplicate prompt already resolved"
+        );
+        return Ok(false);
+    };
+    if let Some(message_id) = prompt.message_id {
+        try_delete_message(bot, chat_id, message_id).await;
+    }
+
+    let items = db.list_items(chat_id).await?;
+    let Some(item) = items.iter().find(|item| item.id == item_id) else {
+        tracing::debug!(
+            chat_id = chat_id.0,
+            item_id,
+            "Duplicate item no longer listed"
+        );
+        return Ok(true);
+    };
+    let event = match action {
+        DuplicateAction::Add => {
+            db.add_item_count(chat_id, &item.text).await?;
+            ListEvent::batch(chat_id, ListEventKind::Add, 1)
+        }
+        DuplicateAction::Uncheck => {
+            db.uncheck_item_count(chat_id, item_id).await?;
+            ListEvent::item(chat_id, ListEventKind::Toggle, item_id)
+        }
+    };
+    shared.events.list_changed(db, event);
+    tracing::info!(
+        chat_id = chat_id.0,
+        item_id,
+        action = action.name(),
+        "Resolved duplicate item"
+    );
+    ListService::new(db, shared)
+        .send_list(bot.clone(), chat_id)
+        .await?;
+    Ok(true)
+}
+
+/// Handle a `dup_<action>_<id>` button press.
+pub async fn handle_duplicate_callback(
+    bot: &Bot,
+    chat_id: ChatId,
+    db: &Database,
+    shared: &SharedState,
+    data: &str,
+) -> Result<()> {
+    let Some((action, item_id)) = parse_callback(data) else {
+        tracing::warn!(data, "Unknown duplicate callback");
+        return Ok(());
+    };
+    resolve_duplicate(bot, db, shared, chat_id, item_id, action).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::chat_settings::{DUPLICATE_DEFAULT, SKIP_LISTED_ITEMS};
+    use crate::tests::util::{init_test_db, test_bot, SENT_MESSAGE};
+    use proptest::prelude::*;
+    use wiremock::{
+        matchers::{body_string_contains, method, path, path_regex},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn item(id: i64, text: &str, done: bool) -> Item {
+        Item {
+            id,
+            text: text.to_string(),
+            done,
+            quantity: 1,
+            added_by_user_id: None,
+            added_by_name: None,
+            category: None,
+            created_at: 0,
+            assigned_to_user_id: None,
+            assigned_to_name: None,
+            price_cents: None,
+        }
+    }
+
+    async fn bot_server() -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SendMessage"))
+            .and(body_string_contains("already checked off"))
+            .and(body_string_contains("dup_uncheck_1"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(SENT_MESSAGE, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SendMessage"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(SENT_MESSAGE, "application/json"))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path_regex(r"^/botTEST/[Dd]eleteMessage$"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(r#"{"ok":true,"result":true}"#, "application/json"),
+            )
+            .mount(&server)
+            .await;
+        server
+    }
+
+    async fn checked_milk(db: &Database) -> Vec<Item> {
+        let chat = ChatId(1);
+        db.add_items_count(chat, &["Milk".to_string()])
+            .await
+            .unwrap();
+        db.toggle_item_count(chat, 1).await.unwrap();
+        db.list_items(chat).await.unwrap()
+    }
+
+    /// The chat's items with creation times cleared so they compare equal
+    /// to [`item`].
+    async fn stored_items(db: &Database) -> Vec<Item> {
+        let mut items = db.list_items(ChatId(1)).await.unwrap();
+        for item in &mut items {
+            item.created_at = 0;
+        }
+        items
+    }
+
+    #[test]
+    fn split_prompts_only_for_checked_items() {
+        let existing = vec![
+            item(1, "Milk", true),
+            item(2, "Bread", false),
+            item(3, "Eggs", true),
+            item(4, "eggs", false),
+        ];
+        let items = ["milk", "MILK", "Bread", "Eggs", "Jam"]
+            .map(String::from)
+            .to_vec();
+        let (fresh, duplicates) = split_checked_duplicates(&existing, items);
+        assert_eq!(fresh, vec!["Bread", "Eggs", "Jam"]);
+        assert_eq!(duplicates, vec![item(1, "Milk", true)]);
+    }
+
+    #[test]
+    fn listed_and_repeated_entries_are_skipped() {
+        let existing = vec![item(1, "Milk", false), item(2, "Eggs", true)];
+        let items = ["milk", "Eggs", "Bread", "bread ", "2 Jam", "jam"]
+            .map(String::from)
+            .to_vec();
+        let (fresh, skipped) = skip_listed_duplicates(&existing, items, String::as_str);
+        assert_eq!(fresh, vec!["Eggs", "Bread", "2 Jam"]);
+        assert_eq!(skipped, 3);
+    }
+
+    #[tokio::test]
+    async fn listed_items_are_kept_when_disabled() {
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_items_count(chat, &["Milk".to_string()])
+            .await
+            .unwrap();
+        let items = vec!["milk".to_string(), "Tea".to_string()];
+
+        let kept = skip_listed_items(&db, chat, items.clone(), String::as_str)
+            .await
+            .unwrap();
+        assert_eq!(kept, vec!["Tea"]);
+
+        db.set_setting(chat, SKIP_LISTED_ITEMS, "false")
+            .await
+            .unwrap();
+        let kept = skip_listed_items(&db, chat, items.clone(), String::as_str)
+            .await
+            .unwrap();
+        assert_eq!(kept, items);
+    }
+
+    proptest! {
+        #[test]
+        fn split_keeps_every_entry_without_checked_items(
+            items in proptest::collection::vec("[a-zA-Z ]{1,12}", 0..8)
+        ) {
+            let existing: Vec<Item> = items
+                .iter()
+                .enumerate()
+                .map(|(i, text)| item(i as i64, text, false))
+                .collect();
+            let (fresh, duplicates) = split_checked_duplicates(&existing, items.clone());
+            prop_assert_eq!(fresh, items);
+            prop_assert!(duplicates.is_empty());
+        }
+    }
+
+    #[test]
+    fn callback_data_round_trips() {
+        let keyboard = duplicate_keyboard(42);
+        let data: Vec<_> = keyboard.inline_keyboard[0]
+            .iter()
+            .map(|button| match &button.kind {
+                teloxide::types::InlineKeyboardButtonKind::CallbackData(data) => data.clone(),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(data, vec!["dup_add_42", "dup_uncheck_42"]);
+        assert_eq!(
+            parse_callback("uncheck_42"),
+            Some((DuplicateAction::Uncheck, 42))
+        );
+        assert_eq!(parse_callback("later_42"), None);
+    }
+
+    #[tokio::test]
+    async fn add_button_inserts_copy() {
+        let server = bot_server().await;
+        let bot = test_bot(&server);
+        let db = init_test_db().await;
+        let items = checked_milk(&db).await;
+
+        prompt_checked_duplicates(
+            &bot,
+            ChatId(1),
+            &db,
+            &SharedState::new().unwrap(),
+            &items,
+            3600,
+        )
+        .await
+        .unwrap();
+        handle_duplicate_callback(&bot, ChatId(1), &db, &SharedState::new().unwrap(), "add_1")
+            .await
+            .unwrap();
+        // A second press finds nothing to resolve.
+        handle_duplicate_callback(
+            &bot,
+            ChatId(1),
+            &db,
+            &SharedState::new().unwrap(),
+            "uncheck_1",
+        )
+        .await
+        .unwrap();
+
+        let items = stored_items(&db).await;
+        assert_eq!(items, vec![item(1, "Milk", true), item(2, "Milk", false)]);
+    }
+
+    #[tokio::test]
+    async fn uncheck_button_reuses_item() {
+        let server = bot_server().await;
+        let bot = test_bot(&server);
+        let db = init_test_db().await;
+        let items = checked_milk(&db).await;
+
+        prompt_checked_duplicates(
+            &bot,
+            ChatId(1),
+            &db,
+            &SharedState::new().unwrap(),
+            &items,
+            3600,
+        )
+        .await
+        .unwrap();
+        handle_duplicate_callback(
+            &bot,
+            ChatId(1),
+            &db,
+            &SharedState::new().unwrap(),
+            "uncheck_1",
+        )
+        .await
+        .unwrap();
+
+        let items = stored_items(&db).await;
+        assert_eq!(items, vec![item(1, "Milk", false)]);
+    }
+
+    #[tokio::test]
+    async fn timeout_applies_chat_default() {
+        let server = bot_server().await;
+        let bot = test_bot(&server);
+        let db = init_test_db().await;
+        let items = checked_milk(&db).await;
+
+        prompt_checked_duplicates(
+            &bot,
+            ChatId(1),
+            &db,
+            &SharedState::new().unwrap(),
+            &items,
+            0,
+        )
+        .await
+        .unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        let items = stored_items(&db).await;
+        assert_eq!(items, vec![item(1, "Milk", false)]);
+
+        db.set_setting(ChatId(1), DUPLICATE_DEFAULT, "add")
+            .await
+            .unwrap();
+        db.toggle_item_count(ChatId(1), 1).await.unwrap();
+        let server = bot_server().await;
+        let bot = test_bot(&server);
+        let items = db.list_items(ChatId(1)).await.unwrap();
+        prompt_checked_duplicates(
+            &bot,
+            ChatId(1),
+            &db,
+            &SharedState::new().unwrap(),
+            &items,
+            0,
+        )
+        .await
+        .unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        let items = stored_items(&db).await;
+        assert_eq!(items, vec![item(1, "Milk", true), item(2, "Milk", false)]);
+    }
+}
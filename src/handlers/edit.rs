@@ -0,0 +1,389 @@
+use anyhow::Result;
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup, MaybeInaccessibleMessage, User, UserId},
+};
+
+use crate::db::{Database, EditSession, Item};
+use crate::list_style::ListStyle;
+use crate::messages::{
+    edit_dm_text, edit_send_new_text, item_renamed_text, DEFAULT_CHAT_NAME, EDIT_CANCELLED,
+    EDIT_CANCEL_LABEL, EDIT_DM_FAILED, EDIT_ITEM_GONE, EDIT_SELECT_PROMPT, NO_ACTIVE_LIST_TO_EDIT,
+};
+use crate::notify::{ListEvent, ListEventKind};
+use crate::state::SharedState;
+use crate::text_utils::parse_item_line;
+use crate::utils::{try_delete_message, try_edit_message};
+
+use super::list_service::ListService;
+use super::message_tracker::MessageTracker;
+use super::permissions::delete_user_message;
+
+pub const EDIT_PREFIX: &str = "edit_";
+const EDIT_CANCEL: &str = "cancel";
+
+/// Seconds after which an unfinished edit stops capturing the user's messages.
+pub const EDIT_SESSION_TTL_SECS: i64 = 600;
+
+fn cancel_row() -> Vec<InlineKeyboardButton> {
+    vec![InlineKeyboardButton::callback(
+        EDIT_CANCEL_LABEL,
+        format!("{EDIT_PREFIX}{EDIT_CANCEL}"),
+    )]
+}
+
+pub fn format_edit_list(items: &[Item], style: &ListStyle) -> (String, InlineKeyboardMarkup) {
+    let mut keyboard: Vec<Vec<InlineKeyboardButton>> = items
+        .iter()
+        .map(|item| {
+            let mark = if item.done {
+                &style.checked
+            } else {
+                &style.unchecked
+            };
+            vec![InlineKeyboardButton::callback(
+                format!("{mark} {}", item.label()),
+                format!("{EDIT_PREFIX}{}", item.id),
+            )]
+        })
+        .collect();
+    keyboard.push(cancel_row());
+    (
+        EDIT_SELECT_PROMPT.to_string(),
+        InlineKeyboardMarkup::new(keyboard),
+    )
+}
+
+fn is_expired(session: &EditSession, now: i64) -> bool {
+    now - session.started_at > EDIT_SESSION_TTL_SECS
+}
+
+pub async fn enter_edit_mode(
+    bot: Bot,
+    msg: Message,
+    db: &Database,
+    delete_after_timeout: u64,
+) -> Result<()> {
+    tracing::debug!(
+        chat_id = msg.chat.id.0,
+        user_id = msg.from.as_ref().map(|u| u.id.0),
+        "Entering edit mode",
+    );
+    delete_user_message(&bot, db, msg.chat.id, msg.id).await?;
+
+    let items = db.list_items(msg.chat.id).await?;
+    if items.is_empty()
+        || MessageTracker::new(db)
+            .current(msg.chat.id)
+            .await?
+            .is_none()
+    {
+        let sent = bot
+            .send_message(msg.chat.id, NO_ACTIVE_LIST_TO_EDIT)
+            .await?;
+        drop(crate::delete_after(
+            bot.clone(),
+            sent.chat.id,
+            sent.id,
+            delete_after_timeout,
+        ));
+        return Ok(());
+    }
+
+    let Some(user) = msg.from.as_ref() else {
+        return Ok(());
+    };
+    let user_id = user.id.0 as i64;
+
+    if let Some(dm) = db
+        .get_edit_session(user_id)
+        .await?
+        .and_then(|previous| previous.dm_message_id)
+    {
+        try_delete_message(&bot, ChatId(user_id), dm).await;
+    }
+    db.init_edit_session(user_id, msg.chat.id, chrono::Utc::now().timestamp())
+        .await?;
+
+    let style = db.get_settings(msg.chat.id).await?.list_style;
+    let (prompt, keyboard) = format_edit_list(&items, &style);
+    let chat_name = msg
+        .chat
+        .title()
+        .map(ToString::to_string)
+        .unwrap_or_else(|| DEFAULT_CHAT_NAME.to_string());
+
+    match bot
+        .send_message(UserId(user.id.0), edit_dm_text(&chat_name, &prompt))
+        .reply_markup(keyboard)
+        .await
+    {
+        Ok(dm) => db.set_edit_dm_message(user_id, dm.id).await?,
+        Err(err) => {
+            tracing::warn!(error = %err, user_id, "Failed to send edit DM");
+            db.clear_edit_session(user_id).await?;
+            let warn = bot.send_message(msg.chat.id, EDIT_DM_FAILED).await?;
+            drop(crate::delete_after(
+                bot.clone(),
+                warn.chat.id,
+                warn.id,
+                delete_after_timeout,
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Handle a tap on the edit keyboard: either pick the item to rename or
+/// cancel the edit.
+pub async fn handle_edit_callback(
+    bot: &Bot,
+    msg: &MaybeInaccessibleMessage,
+    user: &User,
+    db: &Database,
+    data: &str,
+) -> Result<()> {
+    let user_id = user.id.0 as i64;
+    let Some(session) = db.get_edit_session(user_id).await? else {
+        return Ok(());
+    };
+    if session.dm_message_id != Some(msg.id()) {
+        tracing::trace!(user_id, "Ignoring tap on a stale edit keyboard");
+        return Ok(());
+    }
+
+    let no_buttons = InlineKeyboardMarkup::new(Vec::<Vec<InlineKeyboardButton>>::new());
+    if data == EDIT_CANCEL {
+        db.clear_edit_session(user_id).await?;
+        try_edit_message(bot, msg.chat().id, msg.id(), EDIT_CANCELLED, no_buttons).await;
+        return Ok(());
+    }
+    let Ok(item_id) = data.parse::<i64>() else {
+        return Ok(());
+    };
+
+    let items = db.list_items(session.chat_id).await?;
+    let Some(item) = items.iter().find(|item| item.id == item_id) else {
+        db.clear_edit_session(user_id).await?;
+        try_edit_message(bot, msg.chat().id, msg.id(), EDIT_ITEM_GONE, no_buttons).await;
+        return Ok(());
+    };
+    db.select_edit_item(user_id, item_id).await?;
+    try_edit_message(
+        bot,
+        msg.chat().id,
+        msg.id(),
+        edit_send_new_text(&item.text),
+        InlineKeyboardMarkup::new(vec![cancel_row()]),
+    )
+    .await;
+    Ok(())
+}
+
+/// Use a private message as the new text of the item the sender picked in
+/// `/edit`. Returns `false` when the message is not part of an edit, so it
+/// can be handled as usual.
+pub async fn apply_pending_edit(
+    bot: &Bot,
+    msg: &Message,
+    db: &Database,
+    shared: &SharedState,
+) -> Result<bool> {
+    if !msg.chat.is_private() {
+        return Ok(false);
+    }
+    let (Some(user), Some(text)) = (msg.from.as_ref(), msg.text()) else {
+        return Ok(false);
+    };
+    let user_id = user.id.0 as i64;
+    let Some(session) = db.get_edit_session(user_id).await? else {
+        return Ok(false);
+    };
+    let Some(item_id) = session.item_id else {
+        return Ok(false);
+    };
+    if is_expired(&session, chrono::Utc::now().timestamp()) {
+        tracing::debug!(user_id, "Edit session expired");
+        db.clear_edit_session(user_id).await?;
+        return Ok(false);
+    }
+    let Some(new_text) = text.lines().find_map(parse_item_line) else {
+        return Ok(false);
+    };
+
+    let renamed = db.rename_item(session.chat_id, item_id, &new_text).await?;
+    db.clear_edit_session(user_id).await?;
+    if let Some(dm) = session.dm_message_id {
+        try_delete_message(bot, msg.chat.id, dm).await;
+    }
+    if renamed == 0 {
+        bot.send_message(msg.chat.id, EDIT_ITEM_GONE).await?;
+        return Ok(true);
+    }
+    shared.events.list_changed(
+        db,
+        ListEvent::item(session.chat_id, ListEventKind::Update, item_id),
+    );
+
+    tracing::debug!(
+        chat_id = session.chat_id.0,
+        item_id,
+        "Renamed item via edit session"
+    );
+    bot.send_message(msg.chat.id, item_renamed_text(&new_text))
+        .await?;
+    if let Some(list_id) = MessageTracker::new(db).current(session.chat_id).await? {
+        ListService::new(db, shared)
+            .update_message(bot, session.chat_id, list_id)
+            .await?;
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::util::{init_test_db, test_bot};
+    use teloxide::types::MessageId;
+    use wiremock::{
+        matchers::{body_string_contains, method, path, path_regex},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    const OK_TRUE: &str = r#"{"ok":true,"result":true}"#;
+
+    fn dm(text: &str) -> Message {
+        serde_json::from_str(&format!(
+            r#"{{"message_id":20,"date":0,"chat":{{"id":1,"type":"private"}},"from":{{"id":1,"is_bot":false,"first_name":"Anna"}},"text":"{text}"}}"#
+        ))
+        .unwrap()
+    }
+
+    async fn renamed_server() -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/EditMessageText"))
+            .and(body_string_contains("Milk"))
+            .and(body_string_contains(r#""message_id":9"#))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(OK_TRUE, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path_regex(r"^/botTEST/[Dd]eleteMessage$"))
+            .and(body_string_contains(r#""message_id":5"#))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(OK_TRUE, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SendMessage"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"ok":true,"result":{"message_id":21,"date":0,"chat":{"id":1,"type":"private"},"text":"ok"}}"#,
+                "application/json",
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[tokio::test]
+    async fn next_private_message_renames_picked_item() {
+        let server = renamed_server().await;
+        let bot = test_bot(&server);
+        let db = init_test_db().await;
+        let chat = ChatId(-100);
+        db.add_items_count(chat, &["Mlik".into(), "Eggs".into()])
+            .await
+            .unwrap();
+        let item_id = db.list_items(chat).await.unwrap()[0].id;
+        db.update_last_list_message_id(chat, 9).await.unwrap();
+        db.init_edit_session(1, chat, chrono::Utc::now().timestamp())
+            .await
+            .unwrap();
+        db.set_edit_dm_message(1, MessageId(5)).await.unwrap();
+        db.select_edit_item(1, item_id).await.unwrap();
+
+        assert!(
+            apply_pending_edit(&bot, &dm("Milk"), &db, &SharedState::new().unwrap())
+                .await
+                .unwrap()
+        );
+
+        let texts: Vec<_> = db
+            .list_items(chat)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|item| item.text)
+            .collect();
+        assert_eq!(texts, vec!["Milk", "Eggs"]);
+        assert!(db.get_edit_session(1).await.unwrap().is_none());
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn message_without_picked_item_is_not_an_edit() {
+        let db = init_test_db().await;
+        let bot = Bot::new("test");
+        db.init_edit_session(1, ChatId(-100), chrono::Utc::now().timestamp())
+            .await
+            .unwrap();
+        assert!(
+            !apply_pending_edit(&bot, &dm("Milk"), &db, &SharedState::new().unwrap())
+                .await
+                .unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn expired_session_is_dropped() {
+        let db = init_test_db().await;
+        let bot = Bot::new("test");
+        let started = chrono::Utc::now().timestamp() - EDIT_SESSION_TTL_SECS - 1;
+        db.init_edit_session(1, ChatId(-100), started)
+            .await
+            .unwrap();
+        db.select_edit_item(1, 1).await.unwrap();
+
+        assert!(
+            !apply_pending_edit(&bot, &dm("Milk"), &db, &SharedState::new().unwrap())
+                .await
+                .unwrap()
+        );
+        assert!(db.get_edit_session(1).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn tapping_an_item_asks_for_new_text() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/EditMessageText"))
+            .and(body_string_contains("Mlik"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(OK_TRUE, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let bot = test_bot(&server);
+        let db = init_test_db().await;
+        let chat = ChatId(-100);
+        db.add_item_count(chat, "Mlik").await.unwrap();
+        let item_id = db.list_items(chat).await.unwrap()[0].id;
+        db.init_edit_session(1, chat, 0).await.unwrap();
+        db.set_edit_dm_message(1, MessageId(5)).await.unwrap();
+
+        let user: User =
+            serde_json::from_str(r#"{"id":1,"is_bot":false,"first_name":"Anna"}"#).unwrap();
+        let msg: MaybeInaccessibleMessage =
+            serde_json::from_str(r#"{"message_id":5,"date":0,"chat":{"id":1,"type":"private"}}"#)
+                .unwrap();
+        handle_edit_callback(&bot, &msg, &user, &db, &item_id.to_string())
+            .await
+            .unwrap();
+
+        let session = db.get_edit_session(1).await.unwrap().unwrap();
+        assert_eq!(session.item_id, Some(item_id));
+        server.verify().await;
+    }
+}
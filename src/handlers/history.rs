@@ -0,0 +1,99 @@
+use anyhow::Result;
+use teloxide::prelude::*;
+
+use super::list::format_archived_list;
+use crate::db::{ArchiveRecord, Database};
+use crate::list_style::ListStyle;
+use crate::messages::{archive_entry_header, HISTORY_EMPTY, HISTORY_HEADER};
+
+/// Number of archives shown by `/history`.
+pub const HISTORY_LIMIT: i64 = 5;
+
+fn format_archived_at(timestamp: i64) -> String {
+    chrono::DateTime::<chrono::Utc>::from_timestamp(timestamp, 0)
+        .map(|dt| dt.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_else(|| "unknown date".to_string())
+}
+
+pub fn format_history(archives: &[ArchiveRecord], style: &ListStyle) -> String {
+    if archives.is_empty() {
+        return HISTORY_EMPTY.to_string();
+    }
+    let entries: Vec<String> = archives
+        .iter()
+        .map(|archive| {
+            let items = format_archived_list(&archive.items, style);
+            let header = archive_entry_header(
+                &format_archived_at(archive.archived_at),
+                archive.items.len(),
+            );
+            format!("{header}\n{items}")
+        })
+        .collect();
+    format!("{HISTORY_HEADER}\n\n{}", entries.join("\n"))
+}
+
+pub async fn show_history(bot: Bot, msg: Message, db: Database) -> Result<()> {
+    let chat_id = msg.chat.id;
+    let archives = db.recent_archives(chat_id, HISTORY_LIMIT).await?;
+    tracing::debug!(
+        chat_id = chat_id.0,
+        count = archives.len(),
+        "Showing history"
+    );
+    let style = db.get_settings(chat_id).await?.list_style;
+    bot.send_message(chat_id, format_history(&archives, &style))
+        .parse_mode(teloxide::types::ParseMode::Html)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Item;
+
+    fn item(text: &str, done: bool) -> Item {
+        Item {
+            id: 1,
+            text: text.to_string(),
+            done,
+            quantity: 1,
+            added_by_user_id: None,
+            added_by_name: None,
+            category: None,
+            created_at: 0,
+            assigned_to_user_id: None,
+            assigned_to_name: None,
+            price_cents: None,
+        }
+    }
+
+    #[test]
+    fn history_lists_archives_with_dates() {
+        let archives = vec![
+            ArchiveRecord {
+                id: 2,
+                archived_at: 1_704_067_200,
+                items: vec![item("Eggs", true), item("Milk", false)],
+            },
+            ArchiveRecord {
+                id: 1,
+                archived_at: 0,
+                items: vec![item("Bread", true)],
+            },
+        ];
+        let text = format_history(&archives, &ListStyle::default());
+        assert_eq!(
+            text,
+            "Recent archives:\n\n\
+             📦 2024-01-01 00:00 UTC · 2 items\n☑️ Eggs\n⬜ Milk\n\n\
+             📦 1970-01-01 00:00 UTC · 1 item\n✅ Bread\n"
+        );
+    }
+
+    #[test]
+    fn empty_history_says_so() {
+        assert_eq!(format_history(&[], &ListStyle::default()), HISTORY_EMPTY);
+    }
+}
@@ -1,5 +1,8 @@
 use teloxide::types::InlineKeyboardButton;
 
+/// Telegram rejects inline keyboards with more buttons than this.
+pub const MAX_KEYBOARD_BUTTONS: usize = 100;
+
 pub fn build_item_buttons<T, F, G>(
     items: &[T],
     label: F,
@@ -1,65 +1,316 @@
-use crate::db::{Database, Item};
-use crate::text_utils::capitalize_first;
+use crate::ai::common::PricedItem;
+use crate::db::{ChatSettings, Database, Item, ItemAuthor};
+use crate::list_style::ListStyle;
+use crate::messages::{
+    list_progress_text, list_total_text, CHECKED_ITEMS_SEPARATOR, UNCATEGORIZED_HEADER,
+};
+use crate::notify::{ListEvent, ListEventKind};
+use crate::render;
+use crate::state::SharedState;
+use crate::text_utils::{capitalize_first, normalize_for_match};
 use anyhow::Result;
+use std::collections::HashMap;
 use teloxide::{
     prelude::*,
-    types::{ChatId, InlineKeyboardMarkup},
+    types::{ChatId, InlineKeyboardButton, InlineKeyboardMarkup, MessageId},
+};
+
+use super::callbacks::CallbackAction;
+use super::duplicates::{
+    prompt_checked_duplicates, split_checked_duplicates, DUPLICATE_PROMPT_TIMEOUT_SECS,
 };
+use super::keyboard::{build_item_buttons, MAX_KEYBOARD_BUTTONS};
+use super::pagination::page_buttons;
+use super::refresh::send_list_or_defer;
 
-use super::keyboard::build_item_buttons;
-use super::list_service::ListService;
+/// Telegram rejects message text longer than this many UTF-16 code units.
+pub const MESSAGE_TEXT_LIMIT: usize = 4096;
+/// Most item buttons on one page of the list or delete keyboard, leaving
+/// room for the page and done rows under Telegram's
+/// [`MAX_KEYBOARD_BUTTONS`].
+pub const LIST_PAGE_ITEMS: usize = 50;
+// A page row holds at most three buttons and the delete keyboard adds a
+// done button.
+const _: () = assert!(LIST_PAGE_ITEMS + 4 <= MAX_KEYBOARD_BUTTONS);
+
+/// Length as Telegram counts it. HTML tags are counted too, which only
+/// makes pages a little shorter than they could be.
+fn text_len(text: &str) -> usize {
+    text.encode_utf16().count()
+}
 
 struct ListFormatter;
 
+/// What [`ListFormatter::format_list`] adds on top of the plain item lines.
+#[derive(Default)]
+struct ListOptions {
+    show_authors: bool,
+    checked_last: bool,
+    /// Start with a "3 of 7 done" line.
+    progress: bool,
+    /// Split the list into pages that each fit one Telegram message.
+    paged: bool,
+}
+
+/// Text and item buttons of one page, before navigation is added.
+struct RenderedPage {
+    text: String,
+    buttons: Vec<Vec<InlineKeyboardButton>>,
+}
+
+/// One page of a list message, as sent to Telegram.
+pub struct ListPage {
+    pub text: String,
+    pub keyboard: InlineKeyboardMarkup,
+    /// Zero-based, clamped to the pages that exist.
+    pub page: usize,
+    pub pages: usize,
+}
+
 impl ListFormatter {
-    fn format_list(items: &[Item]) -> (String, InlineKeyboardMarkup) {
-        let mut text = String::new();
+    /// Render `page` of the list, clamped to the pages that exist, with a row
+    /// of page buttons under the items when there is more than one page.
+    fn format_list(
+        items: &[Item],
+        style: &ListStyle,
+        options: ListOptions,
+        page: usize,
+    ) -> ListPage {
+        let mut rendered = Self::format_pages(items, style, options);
+        let pages = rendered.len();
+        let page = page.min(pages - 1);
+        let RenderedPage { text, mut buttons } = rendered.swap_remove(page);
+        if pages > 1 {
+            buttons.push(page_buttons(page, pages, CallbackAction::ListPage));
+        }
+        ListPage {
+            text,
+            keyboard: InlineKeyboardMarkup::new(buttons),
+            page,
+            pages,
+        }
+    }
+
+    /// Render the list, starting a new page whenever the next item would
+    /// push the text past [`MESSAGE_TEXT_LIMIT`] or the keyboard past
+    /// [`LIST_PAGE_ITEMS`]. Every page repeats the progress line and the
+    /// header of the category it starts in. Always returns at least one page.
+    fn format_pages(items: &[Item], style: &ListStyle, options: ListOptions) -> Vec<RenderedPage> {
+        let ListOptions {
+            show_authors,
+            checked_last,
+            progress,
+            paged,
+        } = options;
+        let mut heading = String::new();
+        if progress && !items.is_empty() {
+            let done = items.iter().filter(|item| item.done).count();
+            heading.push_str(&list_progress_text(done, items.len()));
+            heading.push('\n');
+        }
         let all_done = items.iter().all(|i| i.done);
+        let checked_last = checked_last && !all_done;
+        let grouped = items.iter().any(|item| item.category.is_some());
+        let items = Self::ordered(items, checked_last, grouped);
 
-        for item in items {
-            let (_mark, label) = Self::format_item_entry(item, all_done);
-            text.push_str(&label);
-            text.push('\n');
+        let label = |item: &Item| Self::format_item_entry(item, all_done, style, show_authors).1;
+        let entry = |item: &Item, previous| {
+            let mut entry = Self::item_prefix(item, previous, checked_last, grouped);
+            entry.push_str(&render::escape(&label(item)));
+            entry.push('\n');
+            entry
+        };
+
+        let mut ranges = Vec::new();
+        let mut text = heading.clone();
+        let mut start = 0;
+        let mut previous: Option<(bool, Option<&str>)> = None;
+        for (index, item) in items.iter().enumerate() {
+            let mut line = entry(item, previous);
+            let full = index - start >= LIST_PAGE_ITEMS
+                || text_len(&text) + text_len(&line) > MESSAGE_TEXT_LIMIT;
+            if paged && index > start && full {
+                ranges.push((std::mem::replace(&mut text, heading.clone()), start..index));
+                start = index;
+                line = entry(item, None);
+            }
+            previous = Some((checked_last && item.done, item.category.as_deref()));
+            text.push_str(&line);
         }
+        ranges.push((text, start..items.len()));
 
         if all_done && !items.is_empty() {
             tracing::debug!("List fully checked out");
         }
 
-        let keyboard_buttons = build_item_buttons(
-            items,
-            |item| Self::format_item_entry(item, all_done).1,
-            |item| item.id.to_string(),
-        );
+        ranges
+            .into_iter()
+            .map(|(text, range)| RenderedPage {
+                text,
+                buttons: build_item_buttons(&items[range], label, |item| {
+                    CallbackAction::Toggle(item.id).to_string()
+                }),
+            })
+            .collect()
+    }
+
+    /// Order items for display so keyboard rows line up with the text:
+    /// unchecked before checked when `checked_last` is set, then by category
+    /// name with uncategorized items last when `grouped`, otherwise keeping
+    /// the list order. With neither set the given order is kept.
+    fn ordered(items: &[Item], checked_last: bool, grouped: bool) -> Vec<Item> {
+        let mut ordered = items.to_vec();
+        if !checked_last && !grouped {
+            return ordered;
+        }
+        ordered.sort_by(|a, b| {
+            let key = |item: &Item| {
+                let category = item.category.as_deref().filter(|_| grouped);
+                (
+                    checked_last && item.done,
+                    grouped && category.is_none(),
+                    category.map(str::to_owned),
+                )
+            };
+            key(a).cmp(&key(b))
+        });
+        ordered
+    }
+
+    /// The separator and category header that go above `item` when it
+    /// follows an item in `previous` (its section and category).
+    fn item_prefix(
+        item: &Item,
+        previous: Option<(bool, Option<&str>)>,
+        checked_last: bool,
+        grouped: bool,
+    ) -> String {
+        let mut entry = String::new();
+        let section = checked_last && item.done;
+        let category = item.category.as_deref();
+        if section && previous.is_some_and(|(done, _)| !done) {
+            entry.push_str(CHECKED_ITEMS_SEPARATOR);
+            entry.push('\n');
+        }
+        if grouped && previous != Some((section, category)) {
+            entry.push_str(&Self::category_header(category));
+            entry.push('\n');
+        }
+        entry
+    }
 
-        (text, InlineKeyboardMarkup::new(keyboard_buttons))
+    fn category_header(category: Option<&str>) -> String {
+        let name = category.map_or_else(|| UNCATEGORIZED_HEADER.to_string(), capitalize_first);
+        render::bold(&name)
     }
 
     fn format_plain_list(items: &[Item]) -> String {
+        let grouped = items.iter().any(|item| item.category.is_some());
         let mut text = String::new();
-        for item in items {
-            text.push_str(&format!("• {}\n", item.text));
+        for item in &Self::ordered(items, false, grouped) {
+            match item.price_cents {
+                Some(cents) => {
+                    text.push_str(&format!("• {} — {}\n", item.label(), format_price(cents)))
+                }
+                None => text.push_str(&format!("• {}\n", item.label())),
+            }
+        }
+        if let Some(total) = total_price_cents(items) {
+            text.push_str(&list_total_text(&format_price(total)));
+            text.push('\n');
         }
         text
     }
 
-    fn format_item_entry(item: &Item, all_done: bool) -> (&'static str, String) {
+    fn format_item_entry<'a>(
+        item: &Item,
+        all_done: bool,
+        style: &'a ListStyle,
+        show_authors: bool,
+    ) -> (&'a str, String) {
         let mark = if all_done {
-            "✅"
+            style.all_done.as_str()
         } else if item.done {
-            "☑️"
+            style.checked.as_str()
         } else {
-            "⬜"
+            style.unchecked.as_str()
+        };
+        let mut label = match item.added_by_name.as_deref() {
+            Some(name) if show_authors => format!("{mark} {} ({name})", item.label()),
+            _ => format!("{mark} {}", item.label()),
         };
-        let label = format!("{mark} {}", item.text);
+        if let Some(assignee) = item.assigned_to_name.as_deref() {
+            label.push_str(&format!(" → {assignee}"));
+        }
         (mark, label)
     }
 }
 
-pub fn format_list(items: &[Item]) -> (String, InlineKeyboardMarkup) {
-    ListFormatter::format_list(items)
+/// Render the first page of the list as HTML text plus its toggle keyboard,
+/// headed by a "3 of 7 done" progress line. Once any item has a category,
+/// items are grouped under bold category headers with uncategorized ones
+/// last.
+pub fn format_list(items: &[Item], style: &ListStyle) -> (String, InlineKeyboardMarkup) {
+    let options = ListOptions {
+        progress: true,
+        paged: true,
+        ..Default::default()
+    };
+    let page = ListFormatter::format_list(items, style, options, 0);
+    (page.text, page.keyboard)
+}
+
+/// [`format_list`] with checked items moved below a separator line.
+pub fn format_list_checked_last(
+    items: &[Item],
+    style: &ListStyle,
+) -> (String, InlineKeyboardMarkup) {
+    let options = ListOptions {
+        checked_last: true,
+        progress: true,
+        paged: true,
+        ..Default::default()
+    };
+    let page = ListFormatter::format_list(items, style, options, 0);
+    (page.text, page.keyboard)
+}
+
+/// One page of the list honouring the chat's settings: who added what and
+/// whether checked items sink to the bottom. Lists too long for one message
+/// get a row of page buttons under the items; `page` past the end shows the
+/// last page.
+pub fn format_list_page(items: &[Item], settings: &ChatSettings, page: usize) -> ListPage {
+    let options = ListOptions {
+        show_authors: settings.show_authors,
+        checked_last: settings.checked_last,
+        progress: true,
+        paged: true,
+    };
+    ListFormatter::format_list(items, &settings.list_style, options, page)
+}
+
+/// Text of an archived list: the item lines of [`format_list`] for the whole
+/// list, without the progress line or keyboard.
+pub fn format_archived_list(items: &[Item], style: &ListStyle) -> String {
+    ListFormatter::format_list(items, style, ListOptions::default(), 0).text
+}
+
+/// A price in cents as a decimal amount, e.g. `3.49`.
+pub fn format_price(cents: i64) -> String {
+    format!("{}.{:02}", cents / 100, cents % 100)
 }
 
+/// Sum of the prices on the list, or `None` when no item has one.
+pub fn total_price_cents(items: &[Item]) -> Option<i64> {
+    items
+        .iter()
+        .filter_map(|item| item.price_cents)
+        .reduce(|total, cents| total + cents)
+}
+
+/// Plain-text bullet list for `/share`, with items of the same category kept
+/// together but no headers, so the text can be pasted back as a list. Items
+/// read from a receipt show their price, followed by a total line.
 pub fn format_plain_list(items: &[Item]) -> String {
     ListFormatter::format_plain_list(items)
 }
@@ -74,67 +325,255 @@ where
         .collect()
 }
 
+/// Give uncategorized unchecked items the category suggested for their text.
+/// Items the user already filed somewhere keep their category.
+async fn apply_categories(
+    db: &Database,
+    chat_id: ChatId,
+    categories: &[(String, String)],
+) -> Result<()> {
+    if categories.is_empty() {
+        return Ok(());
+    }
+    let by_text: HashMap<String, &str> = categories
+        .iter()
+        .map(|(text, category)| (normalize_for_match(text), category.as_str()))
+        .collect();
+    let mut categorized = 0;
+    for item in db.list_items(chat_id).await? {
+        if item.done || item.category.is_some() {
+            continue;
+        }
+        if let Some(category) = by_text.get(&normalize_for_match(&item.text)) {
+            db.set_item_category(chat_id, item.id, Some(category))
+                .await?;
+            categorized += 1;
+        }
+    }
+    tracing::debug!(chat_id = chat_id.0, categorized, "Applied item categories");
+    Ok(())
+}
+
+/// Give unchecked items the price read for their text, replacing an older
+/// price so the latest receipt wins.
+async fn apply_prices(db: &Database, chat_id: ChatId, prices: &[(String, i64)]) -> Result<()> {
+    if prices.is_empty() {
+        return Ok(());
+    }
+    let by_text: HashMap<String, i64> = prices
+        .iter()
+        .map(|(text, cents)| (normalize_for_match(text), *cents))
+        .collect();
+    let mut priced = 0;
+    for item in db.list_items(chat_id).await? {
+        if item.done {
+            continue;
+        }
+        if let Some(&cents) = by_text.get(&normalize_for_match(&item.text)) {
+            db.set_item_price(chat_id, item.id, Some(cents)).await?;
+            priced += 1;
+        }
+    }
+    tracing::debug!(chat_id = chat_id.0, priced, "Applied item prices");
+    Ok(())
+}
+
+/// How [`insert_items_inner`] files and logs a batch of new items.
+#[derive(Default)]
+struct InsertOptions<'a> {
+    context: Option<&'a str>,
+    author: Option<&'a ItemAuthor>,
+    categories: &'a [(String, String)],
+    prices: &'a [(String, i64)],
+    /// Text message the items came from, so later edits can update them.
+    source: Option<MessageId>,
+}
+
+/// Add `items` and refresh the list.
 async fn insert_items_inner(
     bot: Bot,
     chat_id: ChatId,
     db: &Database,
+    shared: &SharedState,
     items: Vec<String>,
-    context: Option<&str>,
+    options: InsertOptions<'_>,
 ) -> Result<usize> {
-    let added = db.add_items_count(chat_id, &items).await? as usize;
+    let _task = shared.tasks.track();
+    let (added, duplicates) = {
+        // Released before the list is sent, which takes the lock itself.
+        let _lock = shared.chat_locks.lock(chat_id).await;
+        let existing = db.list_items(chat_id).await?;
+        let (items, duplicates) = split_checked_duplicates(&existing, items);
+        let added = match options.source {
+            Some(message_id) => {
+                db.add_message_items(chat_id, message_id, &items, options.author)
+                    .await?
+            }
+            None => db.add_items_by(chat_id, &items, options.author).await?,
+        } as usize;
+        (added, duplicates)
+    };
 
     if added > 0 {
         tracing::debug!(chat_id = chat_id.0, added, "Inserted items");
-        ListService::new(db).send_list(bot, chat_id).await?;
-        if let Some(context) = context {
+        shared.metrics.record_items_added("telegram", added as u64);
+        shared.events.list_changed(
+            db,
+            ListEvent::batch(chat_id, ListEventKind::Add, added as u64),
+        );
+        apply_categories(db, chat_id, options.categories).await?;
+        apply_prices(db, chat_id, options.prices).await?;
+        send_list_or_defer(&bot, db, shared, chat_id).await?;
+        if let Some(context) = options.context {
             tracing::info!(chat_id = chat_id.0, added, context, "Added items");
         }
     } else {
         tracing::debug!(chat_id = chat_id.0, "No items inserted");
     }
+    prompt_checked_duplicates(
+        &bot,
+        chat_id,
+        db,
+        shared,
+        &duplicates,
+        DUPLICATE_PROMPT_TIMEOUT_SECS,
+    )
+    .await?;
     Ok(added)
 }
 
-pub async fn insert_items<I>(bot: Bot, chat_id: ChatId, db: &Database, items: I) -> Result<usize>
+pub async fn insert_items<I>(
+    bot: Bot,
+    chat_id: ChatId,
+    db: &Database,
+    shared: &SharedState,
+    items: I,
+) -> Result<usize>
 where
     I: IntoIterator<Item = String>,
 {
     let items: Vec<String> = items.into_iter().collect();
-    insert_items_inner(bot, chat_id, db, items, None).await
+    insert_items_inner(bot, chat_id, db, shared, items, InsertOptions::default()).await
 }
 
 pub async fn insert_items_with_log<I>(
     bot: Bot,
     chat_id: ChatId,
     db: &Database,
+    shared: &SharedState,
     items: I,
     context: &str,
+    author: Option<&ItemAuthor>,
 ) -> Result<usize>
 where
     I: IntoIterator<Item = String>,
 {
     let items: Vec<String> = items.into_iter().collect();
-    insert_items_inner(bot, chat_id, db, items, Some(context)).await
+    let options = InsertOptions {
+        context: Some(context),
+        author,
+        ..InsertOptions::default()
+    };
+    insert_items_inner(bot, chat_id, db, shared, items, options).await
+}
+
+/// [`insert_items_with_log`] for the lines of text message `msg`,
+/// remembering which items the message added.
+pub async fn insert_message_items(
+    bot: Bot,
+    db: &Database,
+    shared: &SharedState,
+    msg: &Message,
+    items: Vec<String>,
+    context: &str,
+) -> Result<usize> {
+    let author = msg.from.as_ref().map(ItemAuthor::from);
+    let options = InsertOptions {
+        context: Some(context),
+        author: author.as_ref(),
+        source: Some(msg.id),
+        ..InsertOptions::default()
+    };
+    insert_items_inner(bot, msg.chat.id, db, shared, items, options).await
 }
 
 pub async fn insert_capitalized_items_with_log<I>(
     bot: Bot,
     chat_id: ChatId,
     db: &Database,
+    shared: &SharedState,
     items: I,
     context: &str,
+    author: Option<&ItemAuthor>,
 ) -> Result<usize>
 where
     I: IntoIterator<Item = String>,
 {
     let items = capitalize_items(items);
-    insert_items_inner(bot, chat_id, db, items, Some(context)).await
+    let options = InsertOptions {
+        context: Some(context),
+        author,
+        ..InsertOptions::default()
+    };
+    insert_items_inner(bot, chat_id, db, shared, items, options).await
+}
+
+/// [`insert_capitalized_items_with_log`] for items from `msg` that came with
+/// suggested categories, given as `(text, category)` pairs.
+pub async fn insert_categorized_items_with_log(
+    bot: Bot,
+    db: &Database,
+    shared: &SharedState,
+    msg: &Message,
+    items: Vec<String>,
+    categories: &[(String, String)],
+    context: &str,
+) -> Result<usize> {
+    let items = capitalize_items(items);
+    let author = msg.from.as_ref().map(ItemAuthor::from);
+    let options = InsertOptions {
+        context: Some(context),
+        author: author.as_ref(),
+        categories,
+        ..InsertOptions::default()
+    };
+    insert_items_inner(bot, msg.chat.id, db, shared, items, options).await
+}
+
+/// [`insert_capitalized_items_with_log`] for items read from a receipt.
+/// Items without a price are added all the same.
+pub async fn insert_priced_items_with_log(
+    bot: Bot,
+    chat_id: ChatId,
+    db: &Database,
+    shared: &SharedState,
+    items: Vec<PricedItem>,
+    context: &str,
+    author: Option<&ItemAuthor>,
+) -> Result<usize> {
+    let prices: Vec<(String, i64)> = items
+        .iter()
+        .filter_map(|item| Some((capitalize_first(&item.text), item.price_cents?)))
+        .collect();
+    let items = capitalize_items(items.into_iter().map(|item| item.text));
+    let options = InsertOptions {
+        context: Some(context),
+        author,
+        prices: &prices,
+        ..InsertOptions::default()
+    };
+    insert_items_inner(bot, chat_id, db, shared, items, options).await
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{capitalize_items, ListFormatter};
-    use crate::db::Item;
+    use super::{
+        capitalize_items, format_list, format_list_page, format_plain_list, format_price,
+        total_price_cents, ListFormatter, LIST_PAGE_ITEMS, MESSAGE_TEXT_LIMIT,
+    };
+    use crate::db::{ChatSettings, Item};
+    use crate::handlers::callbacks::PAGE_PREFIX;
+    use crate::list_style::ListStyle;
 
     #[test]
     fn capitalize_items_preserves_sequences() {
@@ -149,8 +588,17 @@ mod tests {
             id: 1,
             text: "Milk".to_string(),
             done: false,
+            quantity: 1,
+            added_by_user_id: None,
+            added_by_name: None,
+            category: None,
+            created_at: 0,
+            assigned_to_user_id: None,
+            assigned_to_name: None,
+            price_cents: None,
         };
-        let (mark, label) = ListFormatter::format_item_entry(&item, false);
+        let style = ListStyle::default();
+        let (mark, label) = ListFormatter::format_item_entry(&item, false, &style, false);
         assert_eq!(mark, "⬜");
         assert_eq!(label, "⬜ Milk");
     }
@@ -161,8 +609,17 @@ mod tests {
             id: 2,
             text: "Eggs".to_string(),
             done: true,
+            quantity: 1,
+            added_by_user_id: None,
+            added_by_name: None,
+            category: None,
+            created_at: 0,
+            assigned_to_user_id: None,
+            assigned_to_name: None,
+            price_cents: None,
         };
-        let (mark, label) = ListFormatter::format_item_entry(&item, false);
+        let style = ListStyle::default();
+        let (mark, label) = ListFormatter::format_item_entry(&item, false, &style, false);
         assert_eq!(mark, "☑️");
         assert_eq!(label, "☑️ Eggs");
     }
@@ -173,9 +630,277 @@ mod tests {
             id: 3,
             text: "Bread".to_string(),
             done: true,
+            quantity: 1,
+            added_by_user_id: None,
+            added_by_name: None,
+            category: None,
+            created_at: 0,
+            assigned_to_user_id: None,
+            assigned_to_name: None,
+            price_cents: None,
         };
-        let (mark, label) = ListFormatter::format_item_entry(&item, true);
+        let style = ListStyle::default();
+        let (mark, label) = ListFormatter::format_item_entry(&item, true, &style, false);
         assert_eq!(mark, "✅");
         assert_eq!(label, "✅ Bread");
     }
+
+    fn priced_item(id: i64, text: &str, price_cents: Option<i64>) -> Item {
+        Item {
+            id,
+            text: text.to_string(),
+            done: false,
+            quantity: 1,
+            added_by_user_id: None,
+            added_by_name: None,
+            category: None,
+            created_at: 0,
+            assigned_to_user_id: None,
+            assigned_to_name: None,
+            price_cents,
+        }
+    }
+
+    #[test]
+    fn prices_add_up_to_a_total() {
+        let items = [
+            priced_item(1, "Milk", Some(349)),
+            priced_item(2, "Bread", None),
+            priced_item(3, "Cheese", Some(1251)),
+            priced_item(4, "Gum", Some(5)),
+        ];
+        assert_eq!(total_price_cents(&items), Some(1605));
+        assert_eq!(total_price_cents(&items[1..2]), None);
+        assert_eq!(format_price(1605), "16.05");
+        assert_eq!(format_price(5), "0.05");
+        assert_eq!(
+            format_plain_list(&items),
+            "• Milk — 3.49\n• Bread\n• Cheese — 12.51\n• Gum — 0.05\nTotal: 16.05\n"
+        );
+    }
+
+    #[test]
+    fn plain_list_without_prices_has_no_total() {
+        let items = [priced_item(1, "Milk", None)];
+        assert_eq!(format_plain_list(&items), "• Milk\n");
+    }
+
+    #[test]
+    fn checked_items_sink_when_enabled() {
+        let items: Vec<Item> = [
+            ("Milk", true),
+            ("Eggs", false),
+            ("Bread", true),
+            ("Tea", false),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(id, (text, done))| Item {
+            id: id as i64,
+            text: text.to_string(),
+            done,
+            quantity: 1,
+            added_by_user_id: None,
+            added_by_name: None,
+            category: None,
+            created_at: 0,
+            assigned_to_user_id: None,
+            assigned_to_name: None,
+            price_cents: None,
+        })
+        .collect();
+        let mut settings = ChatSettings::default();
+        let text = format_list_page(&items, &settings, 0).text;
+        assert_eq!(text, "2 of 4 done\n☑️ Milk\n⬜ Eggs\n☑️ Bread\n⬜ Tea\n");
+
+        settings.checked_last = true;
+        let page = format_list_page(&items, &settings, 0);
+        let (text, keyboard) = (page.text, page.keyboard);
+        assert_eq!(
+            text,
+            "2 of 4 done\n⬜ Eggs\n⬜ Tea\n── Checked ──\n☑️ Milk\n☑️ Bread\n"
+        );
+        let labels: Vec<_> = keyboard
+            .inline_keyboard
+            .iter()
+            .map(|row| row[0].text.as_str())
+            .collect();
+        assert_eq!(labels, vec!["⬜ Eggs", "⬜ Tea", "☑️ Milk", "☑️ Bread"]);
+    }
+
+    #[test]
+    fn format_item_entry_names_author_when_enabled() {
+        let mut item = Item {
+            id: 4,
+            text: "Milk".to_string(),
+            done: false,
+            quantity: 2,
+            added_by_user_id: Some(7),
+            added_by_name: Some("Anna".to_string()),
+            category: None,
+            created_at: 0,
+            assigned_to_user_id: None,
+            assigned_to_name: None,
+            price_cents: None,
+        };
+        let style = ListStyle::default();
+        let (_, label) = ListFormatter::format_item_entry(&item, false, &style, true);
+        assert_eq!(label, "⬜ 2× Milk (Anna)");
+        let (_, label) = ListFormatter::format_item_entry(&item, false, &style, false);
+        assert_eq!(label, "⬜ 2× Milk");
+
+        // Rows added before authors were tracked render unchanged.
+        item.added_by_name = None;
+        let (_, label) = ListFormatter::format_item_entry(&item, false, &style, true);
+        assert_eq!(label, "⬜ 2× Milk");
+    }
+
+    #[test]
+    fn format_list_names_the_assignee() {
+        let item = Item {
+            id: 5,
+            text: "Milk".to_string(),
+            done: false,
+            quantity: 1,
+            added_by_user_id: Some(7),
+            added_by_name: Some("Anna".to_string()),
+            category: None,
+            created_at: 0,
+            assigned_to_user_id: Some(8),
+            assigned_to_name: Some("Bo".to_string()),
+            price_cents: None,
+        };
+        let (text, keyboard) = format_list(std::slice::from_ref(&item), &ListStyle::default());
+        assert_eq!(text, "0 of 1 done\n⬜ Milk → Bo\n");
+        assert_eq!(keyboard.inline_keyboard[0][0].text, "⬜ Milk → Bo");
+
+        let style = ListStyle::default();
+        let (_, label) = ListFormatter::format_item_entry(&item, false, &style, true);
+        assert_eq!(label, "⬜ Milk (Anna) → Bo");
+    }
+
+    #[test]
+    fn categories_group_within_checked_sections() {
+        let items: Vec<Item> = [
+            ("Milk", true, Some("dairy")),
+            ("Apples", false, None),
+            ("Cheese", false, Some("dairy")),
+            ("Pears", false, Some("produce")),
+            ("Salt", true, None),
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(id, (text, done, category))| Item {
+            id: id as i64,
+            text: text.to_string(),
+            done,
+            quantity: 1,
+            added_by_user_id: None,
+            added_by_name: None,
+            category: category.map(str::to_string),
+            created_at: 0,
+            assigned_to_user_id: None,
+            assigned_to_name: None,
+            price_cents: None,
+        })
+        .collect();
+        let settings = ChatSettings {
+            checked_last: true,
+            ..Default::default()
+        };
+        let page = format_list_page(&items, &settings, 0);
+        let (text, keyboard) = (page.text, page.keyboard);
+        assert_eq!(
+            text,
+            "2 of 5 done\n<b>Dairy</b>\n⬜ Cheese\n<b>Produce</b>\n⬜ Pears\n<b>Other</b>\n⬜ Apples\n\
+             ── Checked ──\n<b>Dairy</b>\n☑️ Milk\n<b>Other</b>\n☑️ Salt\n"
+        );
+        let ids: Vec<_> = keyboard
+            .inline_keyboard
+            .iter()
+            .map(|row| match &row[0].kind {
+                teloxide::types::InlineKeyboardButtonKind::CallbackData(data) => data.clone(),
+                _ => panic!("expected callback data"),
+            })
+            .collect();
+        assert_eq!(
+            ids,
+            vec!["toggle_2", "toggle_3", "toggle_1", "toggle_0", "toggle_4"]
+        );
+    }
+
+    fn numbered_items(count: usize, text: impl Fn(usize) -> String) -> Vec<Item> {
+        (0..count)
+            .map(|id| Item {
+                id: id as i64,
+                text: text(id),
+                done: id % 3 == 0,
+                quantity: 1,
+                added_by_user_id: None,
+                added_by_name: None,
+                category: None,
+                created_at: 0,
+                assigned_to_user_id: None,
+                assigned_to_name: None,
+                price_cents: None,
+            })
+            .collect()
+    }
+
+    fn callback_data(button: &teloxide::types::InlineKeyboardButton) -> &str {
+        match &button.kind {
+            teloxide::types::InlineKeyboardButtonKind::CallbackData(data) => data,
+            _ => panic!("expected callback data"),
+        }
+    }
+
+    #[test]
+    fn long_lists_are_split_into_pages() {
+        let items = numbered_items(150, |id| format!("Item {id}"));
+        let settings = ChatSettings::default();
+        let first = format_list_page(&items, &settings, 0);
+        assert_eq!(first.pages, 3);
+        assert!(first.text.starts_with("50 of 150 done\n"));
+
+        let mut ids = Vec::new();
+        for page in 0..first.pages {
+            let page = format_list_page(&items, &settings, page);
+            assert!(page.text.starts_with("50 of 150 done\n"));
+            let (nav, rows) = page.keyboard.inline_keyboard.split_last().unwrap();
+            assert!(rows.len() <= LIST_PAGE_ITEMS);
+            assert!(nav
+                .iter()
+                .all(|b| callback_data(b).starts_with(PAGE_PREFIX)));
+            assert_eq!(
+                nav[usize::from(page.page > 0)].text,
+                format!("{}/3", page.page + 1)
+            );
+            ids.extend(rows.iter().map(|row| callback_data(&row[0]).to_string()));
+        }
+        let expected: Vec<String> = (0..150).map(|id| format!("toggle_{id}")).collect();
+        assert_eq!(ids, expected);
+
+        let clamped = format_list_page(&items, &settings, 9);
+        assert_eq!(clamped.page, 2);
+        assert!(clamped.text.contains("Item 149"));
+    }
+
+    #[test]
+    fn pages_stay_within_the_message_limit() {
+        let items = numbered_items(150, |id| format!("{id} {}", "long item name ".repeat(8)));
+        let settings = ChatSettings::default();
+        let first = format_list_page(&items, &settings, 0);
+        assert!(first.pages > 3, "text, not button count, splits this list");
+        let mut total = 0;
+        for page in 0..first.pages {
+            let page = format_list_page(&items, &settings, page);
+            assert!(page.text.encode_utf16().count() <= MESSAGE_TEXT_LIMIT);
+            total += page.keyboard.inline_keyboard.len() - 1;
+        }
+        assert_eq!(total, 150);
+
+        let short = format_list_page(&items[..3], &settings, 0);
+        assert_eq!(short.pages, 1);
+        assert_eq!(short.keyboard.inline_keyboard.len(), 3);
+    }
 }
@@ -1,19 +1,35 @@
 use anyhow::Result;
 use teloxide::{
     prelude::*,
-    types::{ChatId, InlineKeyboardButton, InlineKeyboardMarkup, Message, MessageId},
+    types::{
+        ChatId, InlineKeyboardButton, InlineKeyboardMarkup, InputFile, Message, MessageId,
+        ParseMode, ThreadId,
+    },
 };
 
-use super::list::{format_list, format_plain_list};
+use super::list::{format_archived_list, format_list_page, format_plain_list};
+use super::message_tracker::MessageTracker;
+use super::nuke::nuke_keyboard;
+use super::pagination::split_message;
+use super::permissions::delete_user_message;
+use super::refresh::{defer_on_send_failure, send_notice_or_defer};
+use super::suggestions::{suggestion_keyboard, SUGGESTION_LIMIT};
+use crate::db::chat_state::PendingRefresh;
 use crate::db::{Database, Item};
+use crate::export::ExportFormat;
 use crate::messages::{
-    ARCHIVED_LIST_HEADER, CHECKED_ITEMS_ARCHIVED, LIST_ARCHIVED, LIST_EMPTY, LIST_EMPTY_ADD_ITEM,
-    LIST_NOW_EMPTY, LIST_NUKED, NO_ACTIVE_LIST_TO_ARCHIVE, NO_CHECKED_ITEMS_TO_ARCHIVE,
+    undo_done_text, ARCHIVED_LIST_HEADER, CHECKED_ITEMS_ARCHIVED, CHECKED_ITEMS_AUTO_ARCHIVED,
+    EXPORT_USAGE, LIST_ARCHIVED, LIST_AUTO_ARCHIVED, LIST_EMPTY, LIST_EMPTY_ADD_ITEM,
+    LIST_NOW_EMPTY, LIST_NUKED, NOTHING_TO_UNDO, NO_ACTIVE_LIST_TO_ARCHIVE,
+    NO_CHECKED_ITEMS_TO_ARCHIVE, NUKE_CONFIRM_PROMPT,
 };
-use crate::utils::{try_delete_message, try_edit_message};
+use crate::notify::{ListEvent, ListEventKind};
+use crate::state::SharedState;
+use crate::utils::{is_forum, topic_of, InTopic};
 
 pub struct ListService<'a> {
     db: &'a Database,
+    shared: &'a SharedState,
 }
 
 enum ListRenderTarget {
@@ -22,14 +38,41 @@ enum ListRenderTarget {
 }
 
 impl<'a> ListService<'a> {
-    pub fn new(db: &'a Database) -> Self {
-        Self { db }
+    pub fn new(db: &'a Database, shared: &'a SharedState) -> Self {
+        Self { db, shared }
     }
 
-    pub async fn send_list(&self, bot: Bot, chat_id: ChatId) -> Result<()> {
-        if let Some(msg_id) = self.db.get_last_list_message_id(chat_id).await? {
-            try_delete_message(&bot, chat_id, MessageId(msg_id)).await;
+    fn tracker(&self) -> MessageTracker<'a> {
+        MessageTracker::new(self.db)
+    }
+
+    /// The forum topic the chat's list lives in.
+    async fn topic(&self, chat_id: ChatId) -> Result<Option<ThreadId>> {
+        self.db.chat_thread(chat_id).await
+    }
+
+    /// Move the list of a forum chat to the topic `msg` was posted in. A
+    /// chat keeps one list, so it follows whichever topic used it last.
+    pub async fn follow_topic(&self, msg: &Message) -> Result<()> {
+        if !is_forum(&msg.chat) {
+            return Ok(());
+        }
+        let chat_id = msg.chat.id;
+        let thread_id = topic_of(msg);
+        if self.topic(chat_id).await? == thread_id {
+            return Ok(());
         }
+        tracing::debug!(
+            chat_id = chat_id.0,
+            thread_id = ?thread_id.map(|ThreadId(id)| id.0),
+            "List follows topic"
+        );
+        self.db.set_chat_thread(chat_id, thread_id).await
+    }
+
+    pub async fn send_list(&self, bot: Bot, chat_id: ChatId) -> Result<()> {
+        let _lock = self.shared.chat_locks.lock(chat_id).await;
+        self.tracker().delete(&bot, chat_id).await?;
 
         self.render_list(
             &bot,
@@ -45,16 +88,49 @@ impl<'a> ListService<'a> {
             return Ok(());
         };
         let text = format_plain_list(&items);
-        bot.send_message(chat_id, text).await?;
+        bot.send_message(chat_id, text)
+            .in_topic(self.topic(chat_id).await?)
+            .await?;
+        Ok(())
+    }
+
+    /// Send the list as a file in the format named by `args`, defaulting to
+    /// plain text.
+    pub async fn export(&self, bot: Bot, chat_id: ChatId, args: &str) -> Result<()> {
+        let Some(format) = ExportFormat::parse(args) else {
+            tracing::debug!(chat_id = chat_id.0, args, "Unknown export format");
+            bot.send_message(chat_id, EXPORT_USAGE)
+                .in_topic(self.topic(chat_id).await?)
+                .await?;
+            return Ok(());
+        };
+        let Some(items) = self.load_items_or_notify(&bot, chat_id, LIST_EMPTY).await? else {
+            return Ok(());
+        };
+        let file_name = format!(
+            "list-{}.{}",
+            chrono::Utc::now().format("%Y-%m-%d"),
+            format.extension()
+        );
+        tracing::debug!(chat_id = chat_id.0, count = items.len(), %file_name, "Exporting list");
+        let file = InputFile::memory(format.render(&items).into_bytes()).file_name(file_name);
+        let request = bot.send_document(chat_id, file);
+        match self.topic(chat_id).await? {
+            Some(thread_id) => request.message_thread_id(thread_id).await?,
+            None => request.await?,
+        };
         Ok(())
     }
 
+    /// Re-render the list message in place. Renders of one chat run one at a
+    /// time, so the last edit always shows the newest state.
     pub async fn update_message(
         &self,
         bot: &Bot,
         chat_id: ChatId,
         message_id: MessageId,
     ) -> Result<()> {
+        let _lock = self.shared.chat_locks.lock(chat_id).await;
         self.render_list(
             bot,
             chat_id,
@@ -64,77 +140,185 @@ impl<'a> ListService<'a> {
         .await
     }
 
+    /// Switch the list message to `page` and remember it for later edits.
+    pub async fn show_page(
+        &self,
+        bot: &Bot,
+        chat_id: ChatId,
+        message_id: MessageId,
+        page: usize,
+    ) -> Result<()> {
+        tracing::debug!(chat_id = chat_id.0, page, "Showing list page");
+        self.db.set_list_page(chat_id, page).await?;
+        self.update_message(bot, chat_id, message_id).await
+    }
+
     pub async fn archive(&self, bot: Bot, chat_id: ChatId) -> Result<()> {
-        let Some((last_message_id, items)) = self
+        if self
             .load_active_items(&bot, chat_id, NO_ACTIVE_LIST_TO_ARCHIVE)
             .await?
-        else {
+            .is_none()
+        {
             return Ok(());
-        };
-
-        let (final_text, _) = format_list(&items);
-        let archived_text = format!("{ARCHIVED_LIST_HEADER}\n{}", final_text);
-
-        try_delete_message(&bot, chat_id, last_message_id).await;
-        bot.send_message(chat_id, archived_text).await?;
-
-        self.db.delete_all_items_count(chat_id).await?;
-        self.db.clear_last_list_message_id(chat_id).await?;
-
-        bot.send_message(chat_id, LIST_ARCHIVED).await?;
-        Ok(())
+        }
+        let _lock = self.shared.chat_locks.lock(chat_id).await;
+        self.archive_all(&bot, chat_id, LIST_ARCHIVED).await
     }
 
     pub async fn archive_checked(&self, bot: Bot, chat_id: ChatId) -> Result<()> {
-        let Some((last_message_id, items)) = self
+        let Some(items) = self
             .load_active_items(&bot, chat_id, NO_ACTIVE_LIST_TO_ARCHIVE)
             .await?
         else {
             return Ok(());
         };
 
-        let (done, remaining): (Vec<_>, Vec<_>) = items.into_iter().partition(|i| i.done);
-
-        if done.is_empty() {
+        if !items.iter().any(|item| item.done) {
             bot.send_message(chat_id, NO_CHECKED_ITEMS_TO_ARCHIVE)
+                .in_topic(self.topic(chat_id).await?)
                 .await?;
             return Ok(());
         }
 
-        if remaining.is_empty() {
-            self.archive(bot, chat_id).await?;
+        self.archive_done(&bot, chat_id, false).await
+    }
+
+    /// Archive checked items on behalf of the auto-archive task. Unlike
+    /// `/done` it stays quiet when there is nothing to archive, and the
+    /// notice says the archive was automatic.
+    pub async fn auto_archive_checked(&self, bot: &Bot, chat_id: ChatId) -> Result<()> {
+        let items = self.db.list_items(chat_id).await?;
+        let done = items.iter().filter(|item| item.done).count();
+        if done == 0 {
+            tracing::debug!(chat_id = chat_id.0, "No checked items to auto-archive");
             return Ok(());
         }
+        tracing::info!(chat_id = chat_id.0, done, "Auto-archiving checked items");
+        self.archive_done(bot, chat_id, true).await
+    }
+
+    /// Archive every active item. The caller holds the chat's lock, so no
+    /// render can edit the list between the tracker delete and the resend.
+    async fn archive_all(&self, bot: &Bot, chat_id: ChatId, notice: &str) -> Result<()> {
+        let items = self
+            .db
+            .archive_items(chat_id, chrono::Utc::now().timestamp())
+            .await?;
+        self.shared.events.list_changed(
+            self.db,
+            ListEvent::batch(chat_id, ListEventKind::Archive, items.len() as u64),
+        );
+        let style = self.db.get_settings(chat_id).await?.list_style;
+        let final_text = format_archived_list(&items, &style);
+        let archived_text = format!("{ARCHIVED_LIST_HEADER}\n{}", final_text);
+
+        self.tracker().delete(bot, chat_id).await?;
+        self.send_archived_text(bot, chat_id, &archived_text)
+            .await?;
+
+        send_notice_or_defer(bot, self.db, self.shared, chat_id, notice).await?;
+        Ok(())
+    }
 
+    /// Archive the checked items and send the list again with what is left.
+    /// The list is read under the chat's lock, so the resend cannot show an
+    /// older state than a concurrent render.
+    async fn archive_done(&self, bot: &Bot, chat_id: ChatId, automatic: bool) -> Result<()> {
+        let _lock = self.shared.chat_locks.lock(chat_id).await;
+        let items = self.db.list_items(chat_id).await?;
+        if items.is_empty() {
+            tracing::debug!(chat_id = chat_id.0, "List was archived meanwhile");
+            return Ok(());
+        }
+        if items.iter().all(|item| item.done) {
+            let notice = if automatic {
+                LIST_AUTO_ARCHIVED
+            } else {
+                LIST_ARCHIVED
+            };
+            return self.archive_all(bot, chat_id, notice).await;
+        }
+
+        let done = self
+            .db
+            .archive_checked_items(chat_id, chrono::Utc::now().timestamp())
+            .await?;
+        let remaining = self.db.list_items(chat_id).await?;
         tracing::debug!(
             chat_id = chat_id.0,
             done = done.len(),
             remaining = remaining.len(),
             "Archiving checked items"
         );
-
-        let (archived_text, _) = format_list(&done);
+        self.shared.events.list_changed(
+            self.db,
+            ListEvent::batch(chat_id, ListEventKind::ArchiveDone, done.len() as u64),
+        );
+        let style = self.db.get_settings(chat_id).await?.list_style;
+        let archived_text = format_archived_list(&done, &style);
         let archived_text = format!("{ARCHIVED_LIST_HEADER}\n{}", archived_text);
-        try_delete_message(&bot, chat_id, last_message_id).await;
-        bot.send_message(chat_id, archived_text).await?;
-
-        let ids: Vec<i64> = done.iter().map(|i| i.id).collect();
-        self.db.delete_items_count(chat_id, &ids).await?;
+        self.tracker().delete(bot, chat_id).await?;
+        self.send_archived_text(bot, chat_id, &archived_text)
+            .await?;
 
-        bot.send_message(chat_id, CHECKED_ITEMS_ARCHIVED).await?;
+        let notice = if automatic {
+            CHECKED_ITEMS_AUTO_ARCHIVED
+        } else {
+            CHECKED_ITEMS_ARCHIVED
+        };
+        send_notice_or_defer(bot, self.db, self.shared, chat_id, notice).await?;
 
-        self.send_list_message(&bot, chat_id, &remaining).await?;
+        let sent = self.send_list_message(bot, chat_id, &remaining).await;
+        let pending = PendingRefresh {
+            list: true,
+            notice: None,
+        };
+        defer_on_send_failure(bot, self.db, self.shared, chat_id, sent, pending).await?;
         Ok(())
     }
 
+    /// Ask the sender of `/nuke` to confirm; the prompt disappears after
+    /// `delete_after_timeout` seconds if nobody answers.
     pub async fn nuke(&self, bot: Bot, msg: Message, delete_after_timeout: u64) -> Result<()> {
-        try_delete_message(&bot, msg.chat.id, msg.id).await;
-        if let Some(list_message_id) = self.db.get_last_list_message_id(msg.chat.id).await? {
-            try_delete_message(&bot, msg.chat.id, MessageId(list_message_id)).await;
+        delete_user_message(&bot, self.db, msg.chat.id, msg.id).await?;
+        let Some(user) = msg.from.as_ref() else {
+            tracing::debug!(chat_id = msg.chat.id.0, "Ignoring /nuke without a sender");
+            return Ok(());
+        };
+        tracing::debug!(
+            chat_id = msg.chat.id.0,
+            user_id = user.id.0,
+            "Asking to confirm nuke"
+        );
+        let prompt = bot
+            .send_message(msg.chat.id, NUKE_CONFIRM_PROMPT)
+            .in_topic(topic_of(&msg))
+            .reply_markup(nuke_keyboard(user.id))
+            .await?;
+        drop(crate::delete_after(
+            bot,
+            prompt.chat.id,
+            prompt.id,
+            delete_after_timeout,
+        ));
+        Ok(())
+    }
+
+    /// Delete every item of the chat once `/nuke` is confirmed.
+    pub async fn wipe(&self, bot: &Bot, chat_id: ChatId, delete_after_timeout: u64) -> Result<()> {
+        let _lock = self.shared.chat_locks.lock(chat_id).await;
+        self.tracker().delete(bot, chat_id).await?;
+        let removed = self.db.delete_all_items_count(chat_id).await?;
+        if removed > 0 {
+            self.shared.events.list_changed(
+                self.db,
+                ListEvent::batch(chat_id, ListEventKind::Nuke, removed),
+            );
         }
-        self.db.delete_all_items_count(msg.chat.id).await?;
-        self.db.clear_last_list_message_id(msg.chat.id).await?;
-        let confirmation = bot.send_message(msg.chat.id, LIST_NUKED).await?;
+        let confirmation = bot
+            .send_message(chat_id, LIST_NUKED)
+            .in_topic(self.topic(chat_id).await?)
+            .await?;
         drop(crate::delete_after(
             bot.clone(),
             confirmation.chat.id,
@@ -144,28 +328,40 @@ impl<'a> ListService<'a> {
         Ok(())
     }
 
+    /// Reverse the chat's last nuke, archive, or deletion and show the
+    /// restored list.
+    pub async fn undo(&self, bot: Bot, chat_id: ChatId) -> Result<()> {
+        let topic = self.topic(chat_id).await?;
+        let Some((action, items)) = self.db.undo_last(chat_id).await? else {
+            bot.send_message(chat_id, NOTHING_TO_UNDO)
+                .in_topic(topic)
+                .await?;
+            return Ok(());
+        };
+        self.shared.events.list_changed(
+            self.db,
+            ListEvent::batch(chat_id, ListEventKind::Restore, items.len() as u64),
+        );
+        bot.send_message(chat_id, undo_done_text(action, items.len()))
+            .in_topic(topic)
+            .await?;
+        self.send_list(bot, chat_id).await
+    }
+
     async fn load_active_items(
         &self,
         bot: &Bot,
         chat_id: ChatId,
         empty_message: &str,
-    ) -> Result<Option<(MessageId, Vec<Item>)>> {
-        let last_message_id = match self.db.get_last_list_message_id(chat_id).await? {
-            Some(id) => MessageId(id),
-            None => {
-                bot.send_message(chat_id, empty_message).await?;
-                return Ok(None);
-            }
-        };
-
-        let Some(items) = self
-            .load_items_or_notify(bot, chat_id, empty_message)
-            .await?
-        else {
+    ) -> Result<Option<Vec<Item>>> {
+        if self.tracker().current(chat_id).await?.is_none() {
+            bot.send_message(chat_id, empty_message)
+                .in_topic(self.topic(chat_id).await?)
+                .await?;
             return Ok(None);
-        };
+        }
 
-        Ok(Some((last_message_id, items)))
+        self.load_items_or_notify(bot, chat_id, empty_message).await
     }
 
     async fn render_list(
@@ -176,13 +372,45 @@ impl<'a> ListService<'a> {
         empty_message: &str,
     ) -> Result<()> {
         let items = self.db.list_items(chat_id).await?;
-        if items.is_empty() {
-            self.render_empty(bot, chat_id, target, empty_message)
-                .await?;
+        if !self
+            .render_once(bot, chat_id, target, &items, empty_message)
+            .await?
+        {
             return Ok(());
         }
+        // The tracked message was deleted by hand or is too old to edit:
+        // replace it so the chat keeps a live list.
+        tracing::info!(
+            chat_id = chat_id.0,
+            "List message can no longer be edited, sending a new one"
+        );
+        self.tracker().delete(bot, chat_id).await?;
+        self.render_once(
+            bot,
+            chat_id,
+            ListRenderTarget::SendNew,
+            &items,
+            empty_message,
+        )
+        .await?;
+        Ok(())
+    }
 
-        self.render_items(bot, chat_id, target, &items).await
+    /// Render `items` once. Returns `true` when an edit found the tracked
+    /// message lost.
+    async fn render_once(
+        &self,
+        bot: &Bot,
+        chat_id: ChatId,
+        target: ListRenderTarget,
+        items: &[Item],
+        empty_message: &str,
+    ) -> Result<bool> {
+        if items.is_empty() {
+            self.render_empty(bot, chat_id, target, empty_message).await
+        } else {
+            self.render_items(bot, chat_id, target, items).await
+        }
     }
 
     async fn render_empty(
@@ -191,20 +419,27 @@ impl<'a> ListService<'a> {
         chat_id: ChatId,
         target: ListRenderTarget,
         empty_message: &str,
-    ) -> Result<()> {
+    ) -> Result<bool> {
         match target {
             ListRenderTarget::SendNew => {
-                let sent = bot.send_message(chat_id, empty_message).await?;
-                self.db
-                    .update_last_list_message_id(chat_id, sent.id)
-                    .await?;
+                let top = self.db.top_items(chat_id, SUGGESTION_LIMIT).await?;
+                let request = bot
+                    .send_message(chat_id, empty_message)
+                    .in_topic(self.topic(chat_id).await?);
+                let sent = match suggestion_keyboard(&top) {
+                    Some(keyboard) => request.reply_markup(keyboard).await?,
+                    None => request.await?,
+                };
+                self.tracker().track(chat_id, sent.id).await?;
+                Ok(false)
             }
             ListRenderTarget::Edit(message_id) => {
                 let markup = InlineKeyboardMarkup::new(Vec::<Vec<InlineKeyboardButton>>::new());
-                try_edit_message(bot, chat_id, message_id, empty_message, markup).await;
+                self.tracker()
+                    .edit(bot, chat_id, message_id, empty_message, markup)
+                    .await
             }
         }
-        Ok(())
     }
 
     async fn render_items(
@@ -213,26 +448,57 @@ impl<'a> ListService<'a> {
         chat_id: ChatId,
         target: ListRenderTarget,
         items: &[Item],
-    ) -> Result<()> {
+    ) -> Result<bool> {
         match target {
-            ListRenderTarget::SendNew => self.send_list_message(bot, chat_id, items).await,
+            ListRenderTarget::SendNew => {
+                self.send_list_message(bot, chat_id, items).await?;
+                Ok(false)
+            }
             ListRenderTarget::Edit(message_id) => {
-                let (text, keyboard) = format_list(items);
-                try_edit_message(bot, chat_id, message_id, text, keyboard).await;
-                Ok(())
+                let settings = self.db.get_settings(chat_id).await?;
+                let requested = self.db.list_page(chat_id).await?;
+                let page = format_list_page(items, &settings, requested);
+                if page.page != requested {
+                    self.db.set_list_page(chat_id, page.page).await?;
+                }
+                self.tracker()
+                    .edit(bot, chat_id, message_id, page.text, page.keyboard)
+                    .await
             }
         }
     }
 
+    /// Send the list as a new message, starting on its first page.
     async fn send_list_message(&self, bot: &Bot, chat_id: ChatId, items: &[Item]) -> Result<()> {
-        let (text, keyboard) = format_list(items);
+        let settings = self.db.get_settings(chat_id).await?;
+        let page = format_list_page(items, &settings, 0);
+        if page.pages > 1 {
+            tracing::debug!(
+                chat_id = chat_id.0,
+                pages = page.pages,
+                "List split into pages"
+            );
+        }
+        self.db.set_list_page(chat_id, 0).await?;
         let sent = bot
-            .send_message(chat_id, text)
-            .reply_markup(keyboard)
-            .await?;
-        self.db
-            .update_last_list_message_id(chat_id, sent.id)
+            .send_message(chat_id, page.text)
+            .in_topic(self.topic(chat_id).await?)
+            .parse_mode(ParseMode::Html)
+            .reply_markup(page.keyboard)
             .await?;
+        self.tracker().track(chat_id, sent.id).await
+    }
+
+    /// Archived lists carry no keyboard, so a long one is simply sent as
+    /// several messages.
+    async fn send_archived_text(&self, bot: &Bot, chat_id: ChatId, text: &str) -> Result<()> {
+        let topic = self.topic(chat_id).await?;
+        for chunk in split_message(text) {
+            bot.send_message(chat_id, chunk)
+                .in_topic(topic)
+                .parse_mode(ParseMode::Html)
+                .await?;
+        }
         Ok(())
     }
 
@@ -244,9 +510,220 @@ impl<'a> ListService<'a> {
     ) -> Result<Option<Vec<Item>>> {
         let items = self.db.list_items(chat_id).await?;
         if items.is_empty() {
-            bot.send_message(chat_id, empty_message).await?;
+            bot.send_message(chat_id, empty_message)
+                .in_topic(self.topic(chat_id).await?)
+                .await?;
             return Ok(None);
         }
         Ok(Some(items))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::util::{init_test_db, test_bot, SENT_MESSAGE};
+    use wiremock::{
+        matchers::{body_string_contains, method, path, path_regex},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    const EDIT_NOT_FOUND: &str =
+        r#"{"ok":false,"error_code":400,"description":"Bad Request: message to edit not found"}"#;
+    const CANT_EDIT: &str =
+        r#"{"ok":false,"error_code":400,"description":"Bad Request: message can't be edited"}"#;
+
+    /// Edit error -> whether the old message is deleted before resending.
+    #[tokio::test]
+    async fn lost_list_message_is_sent_again() {
+        for (body, deletes) in [(EDIT_NOT_FOUND, 0), (CANT_EDIT, 1)] {
+            let server = MockServer::start().await;
+            Mock::given(method("POST"))
+                .and(path("/botTEST/EditMessageText"))
+                .respond_with(ResponseTemplate::new(400).set_body_raw(body, "application/json"))
+                .expect(1)
+                .mount(&server)
+                .await;
+            Mock::given(method("POST"))
+                .and(path_regex(r"^/botTEST/[Dd]eleteMessage$"))
+                .respond_with(
+                    ResponseTemplate::new(200)
+                        .set_body_raw(r#"{"ok":true,"result":true}"#, "application/json"),
+                )
+                .expect(deletes)
+                .mount(&server)
+                .await;
+            Mock::given(method("POST"))
+                .and(path("/botTEST/SendMessage"))
+                .respond_with(
+                    ResponseTemplate::new(200).set_body_raw(SENT_MESSAGE, "application/json"),
+                )
+                .expect(1)
+                .mount(&server)
+                .await;
+
+            let db = init_test_db().await;
+            let chat = ChatId(1);
+            db.add_item_count(chat, "Milk").await.unwrap();
+            db.update_last_list_message_id(chat, 5).await.unwrap();
+
+            ListService::new(&db, &SharedState::new().unwrap())
+                .update_message(&test_bot(&server), chat, MessageId(5))
+                .await
+                .unwrap();
+            server.verify().await;
+            assert_eq!(
+                db.get_last_list_message_id(chat).await.unwrap(),
+                Some(7),
+                "{body}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn lost_untracked_message_is_not_replaced() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/EditMessageText"))
+            .respond_with(
+                ResponseTemplate::new(400).set_body_raw(EDIT_NOT_FOUND, "application/json"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_item_count(chat, "Milk").await.unwrap();
+        db.update_last_list_message_id(chat, 6).await.unwrap();
+
+        ListService::new(&db, &SharedState::new().unwrap())
+            .update_message(&test_bot(&server), chat, MessageId(5))
+            .await
+            .unwrap();
+        server.verify().await;
+        assert_eq!(db.get_last_list_message_id(chat).await.unwrap(), Some(6));
+    }
+
+    fn forum_message(thread_id: Option<i32>) -> Message {
+        let mut msg = serde_json::json!({
+            "message_id": 30,
+            "date": 0,
+            "chat": {"id": -100, "type": "supergroup", "title": "Home", "is_forum": true},
+            "text": "/list",
+        });
+        if let Some(thread_id) = thread_id {
+            msg["message_thread_id"] = thread_id.into();
+            msg["is_topic_message"] = true.into();
+        }
+        serde_json::from_value(msg).unwrap()
+    }
+
+    /// Two topics of one forum share the chat's single list; it moves to
+    /// whichever topic asked for it last.
+    #[tokio::test]
+    async fn list_follows_the_topic_it_was_used_in() {
+        let server = MockServer::start().await;
+        for thread_id in [10, 20] {
+            Mock::given(method("POST"))
+                .and(path("/botTEST/SendMessage"))
+                .and(body_string_contains(format!(
+                    r#""message_thread_id":{thread_id}"#
+                )))
+                .and(body_string_contains("Milk"))
+                .respond_with(
+                    ResponseTemplate::new(200).set_body_raw(SENT_MESSAGE, "application/json"),
+                )
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+        Mock::given(method("POST"))
+            .and(path_regex(r"^/botTEST/[Dd]eleteMessage$"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(r#"{"ok":true,"result":true}"#, "application/json"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let db = init_test_db().await;
+        let chat = ChatId(-100);
+        db.add_item_count(chat, "Milk").await.unwrap();
+        let shared = SharedState::new().unwrap();
+        let service = ListService::new(&db, &shared);
+        let bot = test_bot(&server);
+
+        for thread_id in [10, 20] {
+            service
+                .follow_topic(&forum_message(Some(thread_id)))
+                .await
+                .unwrap();
+            service.send_list(bot.clone(), chat).await.unwrap();
+        }
+        server.verify().await;
+        assert_eq!(
+            db.chat_thread(chat).await.unwrap(),
+            Some(ThreadId(MessageId(20)))
+        );
+
+        service.follow_topic(&forum_message(None)).await.unwrap();
+        assert_eq!(db.chat_thread(chat).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn export_sends_the_list_as_a_document() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SendDocument"))
+            .and(body_string_contains(".csv"))
+            .and(body_string_contains("id,text,done,quantity"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(SENT_MESSAGE, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SendMessage"))
+            .and(body_string_contains("Usage: /export"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(SENT_MESSAGE, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_item_count(chat, "Milk").await.unwrap();
+        let shared = SharedState::new().unwrap();
+        let service = ListService::new(&db, &shared);
+        let bot = test_bot(&server);
+        service.export(bot.clone(), chat, "csv").await.unwrap();
+        service.export(bot, chat, "pdf").await.unwrap();
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn export_of_an_empty_list_sends_no_file() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SendMessage"))
+            .and(body_string_contains("Your list is empty!"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(SENT_MESSAGE, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SendDocument"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(SENT_MESSAGE, "application/json"))
+            .expect(0)
+            .mount(&server)
+            .await;
+
+        let db = init_test_db().await;
+        ListService::new(&db, &SharedState::new().unwrap())
+            .export(test_bot(&server), ChatId(1), "")
+            .await
+            .unwrap();
+        server.verify().await;
+    }
+}
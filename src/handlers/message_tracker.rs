@@ -0,0 +1,277 @@
+//! Keeps `chat_state.last_list_message_id` in step with what Telegram
+//! actually shows: the id is only forgotten once the message is known to be
+//! gone.
+
+use anyhow::Result;
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardMarkup, MessageId, ParseMode},
+    ApiError, RequestError,
+};
+
+use crate::db::Database;
+use crate::utils::EditOutcome;
+
+/// Whether Telegram reports that the message no longer exists.
+fn is_message_gone(err: &RequestError) -> bool {
+    matches!(
+        err,
+        RequestError::Api(
+            ApiError::MessageToDeleteNotFound
+                | ApiError::MessageToEditNotFound
+                | ApiError::MessageIdInvalid
+        )
+    )
+}
+
+pub struct MessageTracker<'a> {
+    db: &'a Database,
+}
+
+impl<'a> MessageTracker<'a> {
+    pub fn new(db: &'a Database) -> Self {
+        Self { db }
+    }
+
+    /// The tracked list message. An id too large for teloxide's
+    /// [`MessageId`] cannot be edited or deleted, so it counts as untracked.
+    pub async fn current(&self, chat_id: ChatId) -> Result<Option<MessageId>> {
+        let Some(stored) = self.db.get_last_list_message_id(chat_id).await? else {
+            return Ok(None);
+        };
+        match i32::try_from(stored) {
+            Ok(id) => Ok(Some(MessageId(id))),
+            Err(_) => {
+                tracing::warn!(
+                    chat_id = chat_id.0,
+                    message_id = stored,
+                    "Tracked list message id does not fit a MessageId"
+                );
+                Ok(None)
+            }
+        }
+    }
+
+    /// Remember a newly sent list message.
+    pub async fn track(&self, chat_id: ChatId, message_id: MessageId) -> Result<()> {
+        self.db
+            .update_last_list_message_id(chat_id, message_id.0.into())
+            .await
+    }
+
+    /// Delete the tracked list message. Returns `true` when no tracked
+    /// message remains; a message that could not be deleted stays tracked so
+    /// the next call tries again.
+    pub async fn delete(&self, bot: &Bot, chat_id: ChatId) -> Result<bool> {
+        let Some(message_id) = self.current(chat_id).await? else {
+            return Ok(true);
+        };
+        match bot.delete_message(chat_id, message_id).await {
+            Ok(_) => {}
+            Err(err) if is_message_gone(&err) => {
+                tracing::debug!(
+                    chat_id = chat_id.0,
+                    message_id = message_id.0,
+                    "Tracked list message already gone"
+                );
+            }
+            Err(err) => {
+                tracing::warn!(
+                    error = %err,
+                    chat_id = chat_id.0,
+                    message_id = message_id.0,
+                    "Failed to delete list message, keeping it tracked",
+                );
+                return Ok(false);
+            }
+        }
+        self.db
+            .clear_last_list_message_id_if(chat_id, message_id.0.into())
+            .await?;
+        Ok(true)
+    }
+
+    /// Edit a list message in place; the text is sent as HTML. Returns `true`
+    /// when the message is the tracked one and Telegram says it is gone or can
+    /// no longer be edited, so the caller should send a fresh list. A gone
+    /// message also stops being tracked.
+    pub async fn edit(
+        &self,
+        bot: &Bot,
+        chat_id: ChatId,
+        message_id: MessageId,
+        text: impl Into<String>,
+        markup: InlineKeyboardMarkup,
+    ) -> Result<bool> {
+        let Err(err) = bot
+            .edit_message_text(chat_id, message_id, text)
+            .parse_mode(ParseMode::Html)
+            .reply_markup(markup)
+            .await
+        else {
+            return Ok(false);
+        };
+        let gone = is_message_gone(&err);
+        match EditOutcome::from_error(err) {
+            EditOutcome::MessageMissing => {}
+            EditOutcome::Other(err) => {
+                tracing::warn!(
+                    error = %err,
+                    chat_id = chat_id.0,
+                    message_id = message_id.0,
+                    "Failed to edit message",
+                );
+                return Ok(false);
+            }
+            EditOutcome::Edited | EditOutcome::NotModified => return Ok(false),
+        }
+        tracing::debug!(
+            chat_id = chat_id.0,
+            message_id = message_id.0,
+            gone,
+            "List message can no longer be edited"
+        );
+        let tracked = self.current(chat_id).await? == Some(message_id);
+        if gone {
+            self.db
+                .clear_last_list_message_id_if(chat_id, message_id.0.into())
+                .await?;
+        }
+        Ok(tracked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::util::{init_test_db, test_bot};
+    use wiremock::{
+        matchers::{method, path, path_regex},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    const OK_TRUE: &str = r#"{"ok":true,"result":true}"#;
+    const NOT_FOUND: &str =
+        r#"{"ok":false,"error_code":400,"description":"Bad Request: message to delete not found"}"#;
+    const CANT_DELETE: &str =
+        r#"{"ok":false,"error_code":400,"description":"Bad Request: message can't be deleted"}"#;
+    const THROTTLED: &str = r#"{"ok":false,"error_code":429,"description":"Too Many Requests: retry after 1","parameters":{"retry_after":1}}"#;
+    const EDIT_NOT_FOUND: &str =
+        r#"{"ok":false,"error_code":400,"description":"Bad Request: message to edit not found"}"#;
+
+    async fn server_with(endpoint: &str, status: u16, body: &str) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path_regex(endpoint))
+            .respond_with(ResponseTemplate::new(status).set_body_raw(body, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        server
+    }
+
+    /// Delete outcome -> whether the stored id should be cleared.
+    #[tokio::test]
+    async fn delete_clears_id_only_when_message_is_gone() {
+        let cases = [
+            (200, OK_TRUE, true),
+            (400, NOT_FOUND, true),
+            (400, CANT_DELETE, false),
+            (429, THROTTLED, false),
+        ];
+        for (status, body, cleared) in cases {
+            let server = server_with(r"^/botTEST/[Dd]eleteMessage$", status, body).await;
+            let db = init_test_db().await;
+            let tracker = MessageTracker::new(&db);
+            tracker.track(ChatId(1), MessageId(5)).await.unwrap();
+
+            let gone = tracker.delete(&test_bot(&server), ChatId(1)).await.unwrap();
+            assert_eq!(gone, cleared, "{body}");
+            let expected = if cleared { None } else { Some(MessageId(5)) };
+            assert_eq!(
+                tracker.current(ChatId(1)).await.unwrap(),
+                expected,
+                "{body}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn id_beyond_message_id_range_is_untracked() {
+        let db = init_test_db().await;
+        db.update_last_list_message_id(ChatId(1), i64::from(i32::MAX) + 1)
+            .await
+            .unwrap();
+        let tracker = MessageTracker::new(&db);
+        assert_eq!(tracker.current(ChatId(1)).await.unwrap(), None);
+
+        tracker.track(ChatId(1), MessageId(i32::MAX)).await.unwrap();
+        assert_eq!(
+            tracker.current(ChatId(1)).await.unwrap(),
+            Some(MessageId(i32::MAX))
+        );
+    }
+
+    #[tokio::test]
+    async fn delete_without_tracked_message_is_noop() {
+        let server = MockServer::start().await;
+        let db = init_test_db().await;
+        assert!(MessageTracker::new(&db)
+            .delete(&test_bot(&server), ChatId(1))
+            .await
+            .unwrap());
+        assert!(server.received_requests().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn edit_of_missing_message_forgets_it() {
+        let server = server_with("^/botTEST/EditMessageText$", 400, EDIT_NOT_FOUND).await;
+        let db = init_test_db().await;
+        let tracker = MessageTracker::new(&db);
+        tracker.track(ChatId(1), MessageId(5)).await.unwrap();
+
+        let lost = tracker
+            .edit(
+                &test_bot(&server),
+                ChatId(1),
+                MessageId(5),
+                "List",
+                InlineKeyboardMarkup::default(),
+            )
+            .await
+            .unwrap();
+        assert!(lost);
+        assert_eq!(tracker.current(ChatId(1)).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn stale_clear_keeps_newer_message() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/EditMessageText"))
+            .respond_with(
+                ResponseTemplate::new(400).set_body_raw(EDIT_NOT_FOUND, "application/json"),
+            )
+            .mount(&server)
+            .await;
+        let db = init_test_db().await;
+        let tracker = MessageTracker::new(&db);
+        tracker.track(ChatId(1), MessageId(6)).await.unwrap();
+
+        let lost = tracker
+            .edit(
+                &test_bot(&server),
+                ChatId(1),
+                MessageId(5),
+                "List",
+                InlineKeyboardMarkup::default(),
+            )
+            .await
+            .unwrap();
+        assert!(!lost);
+        assert_eq!(
+            tracker.current(ChatId(1)).await.unwrap(),
+            Some(MessageId(6))
+        );
+    }
+}
@@ -0,0 +1,175 @@
+//! `/nuke` asks before wiping the list, and only the user who sent it can
+//! answer the prompt.
+
+use anyhow::Result;
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup, MaybeInaccessibleMessage, User},
+};
+
+use super::callbacks::CallbackAction;
+use super::list_service::ListService;
+use crate::db::Database;
+use crate::messages::{NUKE_CANCELLED, NUKE_CANCEL_LABEL, NUKE_CONFIRM_LABEL, NUKE_NOT_YOURS};
+use crate::state::SharedState;
+use crate::utils::try_delete_message;
+
+/// Confirm and cancel buttons bound to the user who sent `/nuke`.
+pub fn nuke_keyboard(owner: UserId) -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback(
+            NUKE_CONFIRM_LABEL,
+            CallbackAction::NukeConfirm(owner.0).to_string(),
+        ),
+        InlineKeyboardButton::callback(
+            NUKE_CANCEL_LABEL,
+            CallbackAction::NukeCancel(owner.0).to_string(),
+        ),
+    ]])
+}
+
+/// A tap on one of the [`nuke_keyboard`] buttons.
+#[derive(Clone, Copy, Debug)]
+pub struct NukeAnswer {
+    /// User who sent `/nuke`.
+    pub owner: u64,
+    pub confirmed: bool,
+}
+
+/// Answer the prompt. Taps by anyone but the owner change nothing; the
+/// owner's answer removes the prompt and, if confirmed, wipes the list.
+pub async fn handle_nuke_callback(
+    bot: &Bot,
+    msg: &MaybeInaccessibleMessage,
+    from: &User,
+    db: &Database,
+    shared: &SharedState,
+    answer: NukeAnswer,
+    delete_after_timeout: u64,
+) -> Result<Option<String>> {
+    let NukeAnswer { owner, confirmed } = answer;
+    let chat_id = msg.chat().id;
+    if from.id.0 != owner {
+        tracing::debug!(
+            chat_id = chat_id.0,
+            user_id = from.id.0,
+            owner,
+            "Ignoring nuke answer from another user"
+        );
+        return Ok(Some(NUKE_NOT_YOURS.to_string()));
+    }
+    tracing::debug!(chat_id = chat_id.0, confirmed, "Nuke prompt answered");
+    try_delete_message(bot, chat_id, msg.id()).await;
+    if !confirmed {
+        return Ok(Some(NUKE_CANCELLED.to_string()));
+    }
+    ListService::new(db, shared)
+        .wipe(bot, chat_id, delete_after_timeout)
+        .await?;
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::util::{init_test_db, test_bot, SENT_MESSAGE};
+    use wiremock::{
+        matchers::{method, path, path_regex},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    /// Counts prompt deletions and nuke confirmations.
+    async fn server(deletes: u64, sends: u64) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path_regex(r"^/botTEST/[Dd]eleteMessage$"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(r#"{"ok":true,"result":true}"#, "application/json"),
+            )
+            .expect(deletes)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SendMessage"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(SENT_MESSAGE, "application/json"))
+            .expect(sends)
+            .mount(&server)
+            .await;
+        server
+    }
+
+    fn prompt() -> MaybeInaccessibleMessage {
+        serde_json::from_str(
+            r#"{"message_id":9,"date":0,"chat":{"id":1,"type":"group","title":"g"}}"#,
+        )
+        .unwrap()
+    }
+
+    fn user(id: u64) -> User {
+        serde_json::from_value(serde_json::json!({"id": id, "is_bot": false, "first_name": "Anna"}))
+            .unwrap()
+    }
+
+    async fn answer(
+        server: &MockServer,
+        db: &Database,
+        by: u64,
+        confirmed: bool,
+    ) -> Option<String> {
+        let answer = NukeAnswer {
+            owner: 7,
+            confirmed,
+        };
+        handle_nuke_callback(
+            &test_bot(server),
+            &prompt(),
+            &user(by),
+            db,
+            &SharedState::new().unwrap(),
+            answer,
+            5,
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn owner_confirming_wipes_the_list() {
+        let server = server(1, 1).await;
+        let db = init_test_db().await;
+        db.add_item_count(ChatId(1), "Milk").await.unwrap();
+
+        assert_eq!(answer(&server, &db, 7, true).await, None);
+        assert!(db.list_items(ChatId(1)).await.unwrap().is_empty());
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn owner_cancelling_keeps_the_list() {
+        let server = server(1, 0).await;
+        let db = init_test_db().await;
+        db.add_item_count(ChatId(1), "Milk").await.unwrap();
+
+        assert_eq!(
+            answer(&server, &db, 7, false).await.as_deref(),
+            Some(NUKE_CANCELLED)
+        );
+        assert_eq!(db.list_items(ChatId(1)).await.unwrap().len(), 1);
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn other_users_cannot_confirm() {
+        let server = server(0, 0).await;
+        let db = init_test_db().await;
+        db.add_item_count(ChatId(1), "Milk").await.unwrap();
+
+        assert_eq!(
+            answer(&server, &db, 8, true).await.as_deref(),
+            Some(NUKE_NOT_YOURS)
+        );
+        assert_eq!(db.list_items(ChatId(1)).await.unwrap().len(), 1);
+        server.verify().await;
+    }
+}
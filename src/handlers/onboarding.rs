@@ -0,0 +1,258 @@
+use anyhow::Result;
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup, User},
+};
+
+use super::list::insert_items;
+use super::pairing::{pair_chat, PAIRING_PREFIX};
+use super::text::help;
+use super::tokens::{send_new_token, TokenRequest};
+use crate::db::chat_settings::ONBOARDED;
+use crate::db::Database;
+use crate::messages::{
+    ONBOARDING_SAMPLE_BUTTON, ONBOARDING_SAMPLE_HINT, ONBOARDING_TEXT, ONBOARDING_TOKEN_BUTTON,
+    ONBOARDING_VOICE_BUTTON, ONBOARDING_VOICE_INFO,
+};
+use crate::state::SharedState;
+
+pub const ONBOARDING_PREFIX: &str = "onboard_";
+const SAMPLE_ITEMS: [&str; 3] = ["Milk", "Bread", "Apples"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OnboardingAction {
+    SampleItems,
+    VoiceInfo,
+    ApiToken,
+}
+
+impl OnboardingAction {
+    const ALL: [Self; 3] = [Self::SampleItems, Self::VoiceInfo, Self::ApiToken];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::SampleItems => "sample",
+            Self::VoiceInfo => "voice",
+            Self::ApiToken => "token",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::SampleItems => ONBOARDING_SAMPLE_BUTTON,
+            Self::VoiceInfo => ONBOARDING_VOICE_BUTTON,
+            Self::ApiToken => ONBOARDING_TOKEN_BUTTON,
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+fn onboarding_keyboard() -> InlineKeyboardMarkup {
+    let rows = OnboardingAction::ALL.into_iter().map(|action| {
+        vec![InlineKeyboardButton::callback(
+            action.label(),
+            format!("{ONBOARDING_PREFIX}{}", action.name()),
+        )]
+    });
+    InlineKeyboardMarkup::new(rows)
+}
+
+/// Handle `/start`: a `pair_<code>` payload finishes pairing a device,
+/// private chats without any stored settings get the onboarding tour once,
+/// everyone else the regular help text.
+pub async fn start(bot: Bot, msg: Message, db: Database, payload: String) -> Result<()> {
+    if let Some(code) = payload.trim().strip_prefix(PAIRING_PREFIX) {
+        return pair_chat(bot, msg, db, code).await;
+    }
+    let chat_id = msg.chat.id;
+    if !msg.chat.is_private() || db.has_settings(chat_id).await? {
+        return help(bot, msg).await;
+    }
+
+    tracing::debug!(chat_id = chat_id.0, "Sending onboarding");
+    bot.send_message(chat_id, ONBOARDING_TEXT)
+        .reply_markup(onboarding_keyboard())
+        .await?;
+    db.set_setting(chat_id, ONBOARDED, "true").await?;
+    Ok(())
+}
+
+/// Run the demo flow behind an onboarding button.
+pub async fn handle_onboarding_callback(
+    bot: &Bot,
+    chat_id: ChatId,
+    from: &User,
+    db: &Database,
+    shared: &SharedState,
+    action: &str,
+) -> Result<()> {
+    let Some(action) = OnboardingAction::parse(action) else {
+        tracing::debug!(chat_id = chat_id.0, action, "Unknown onboarding action");
+        return Ok(());
+    };
+    tracing::debug!(chat_id = chat_id.0, ?action, "Onboarding action");
+
+    match action {
+        OnboardingAction::SampleItems => {
+            let items = SAMPLE_ITEMS.map(str::to_string);
+            insert_items(bot.clone(), chat_id, db, shared, items).await?;
+            bot.send_message(chat_id, ONBOARDING_SAMPLE_HINT).await?;
+        }
+        OnboardingAction::VoiceInfo => {
+            bot.send_message(chat_id, ONBOARDING_VOICE_INFO).await?;
+        }
+        OnboardingAction::ApiToken => {
+            send_new_token(bot, chat_id, db, Some(from), TokenRequest::default()).await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::TOKEN_ISSUED;
+    use crate::tests::util::{init_test_db, test_bot, SENT_MESSAGE};
+    use teloxide::types::InlineKeyboardButtonKind;
+    use wiremock::{
+        matchers::{body_string_contains, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn start_message(chat_type: &str, chat_id: i64) -> Message {
+        let chat = match chat_type {
+            "private" => format!(r#"{{"id":{chat_id},"type":"private"}}"#),
+            _ => format!(r#"{{"id":{chat_id},"type":"group","title":"Family"}}"#),
+        };
+        serde_json::from_str(&format!(
+            r#"{{"message_id":1,"date":0,"chat":{chat},"text":"/start"}}"#
+        ))
+        .unwrap()
+    }
+
+    fn test_user() -> User {
+        serde_json::from_str(r#"{"id":5,"is_bot":false,"first_name":"Ann"}"#).unwrap()
+    }
+
+    async fn expect_message(server: &MockServer, text: &str, times: u64) {
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SendMessage"))
+            .and(body_string_contains(text))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(SENT_MESSAGE, "application/json"))
+            .expect(times)
+            .mount(server)
+            .await;
+    }
+
+    async fn accept_messages(server: &MockServer) {
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SendMessage"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(SENT_MESSAGE, "application/json"))
+            .mount(server)
+            .await;
+    }
+
+    #[test]
+    fn keyboard_routes_every_action() {
+        let keyboard = onboarding_keyboard();
+        assert_eq!(keyboard.inline_keyboard.len(), OnboardingAction::ALL.len());
+        for (row, action) in keyboard.inline_keyboard.iter().zip(OnboardingAction::ALL) {
+            let InlineKeyboardButtonKind::CallbackData(data) = &row[0].kind else {
+                panic!("expected callback data");
+            };
+            let name = data.strip_prefix(ONBOARDING_PREFIX).unwrap();
+            assert_eq!(OnboardingAction::parse(name), Some(action));
+        }
+    }
+
+    #[tokio::test]
+    async fn onboarding_only_sent_on_first_start() {
+        let server = MockServer::start().await;
+        expect_message(&server, "onboard_sample", 1).await;
+        expect_message(&server, "Commands:", 1).await;
+        let db = init_test_db().await;
+        let bot = test_bot(&server);
+
+        start(
+            bot.clone(),
+            start_message("private", 1),
+            db.clone(),
+            String::new(),
+        )
+        .await
+        .unwrap();
+        assert!(db.has_settings(ChatId(1)).await.unwrap());
+        start(bot, start_message("private", 1), db, String::new())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn group_start_shows_help() {
+        let server = MockServer::start().await;
+        expect_message(&server, "onboard_sample", 0).await;
+        expect_message(&server, "Commands:", 1).await;
+        let db = init_test_db().await;
+
+        start(
+            test_bot(&server),
+            start_message("group", -10),
+            db.clone(),
+            String::new(),
+        )
+        .await
+        .unwrap();
+        assert!(!db.has_settings(ChatId(-10)).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn sample_button_inserts_demo_items() {
+        let server = MockServer::start().await;
+        expect_message(&server, "Tap an item", 1).await;
+        accept_messages(&server).await;
+        let db = init_test_db().await;
+
+        handle_onboarding_callback(
+            &test_bot(&server),
+            ChatId(1),
+            &test_user(),
+            &db,
+            &SharedState::new().unwrap(),
+            "sample",
+        )
+        .await
+        .unwrap();
+        let texts: Vec<String> = db
+            .list_items(ChatId(1))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|item| item.text)
+            .collect();
+        assert_eq!(texts, SAMPLE_ITEMS);
+    }
+
+    #[tokio::test]
+    async fn token_button_issues_token() {
+        let server = MockServer::start().await;
+        expect_message(&server, TOKEN_ISSUED, 1).await;
+        let db = init_test_db().await;
+
+        handle_onboarding_callback(
+            &test_bot(&server),
+            ChatId(1),
+            &test_user(),
+            &db,
+            &SharedState::new().unwrap(),
+            "token",
+        )
+        .await
+        .unwrap();
+        let tokens = db.list_tokens(ChatId(1)).await.unwrap();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].issuer_user_id, Some(5));
+    }
+}
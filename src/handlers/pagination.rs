@@ -0,0 +1,135 @@
+//! Lists too long for one Telegram message are shown a page at a time, with
+//! buttons to move between pages. The page shown is remembered per chat so
+//! edits after toggles and API changes stay on it.
+
+use anyhow::Result;
+use std::ops::Range;
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardButton, MaybeInaccessibleMessage},
+};
+
+use super::callbacks::CallbackAction;
+use super::list::{LIST_PAGE_ITEMS, MESSAGE_TEXT_LIMIT};
+use super::list_service::ListService;
+use crate::db::Database;
+use crate::messages::{list_page_label, LIST_PAGE_NEXT, LIST_PAGE_PREV};
+use crate::state::SharedState;
+
+/// "◀ Prev", "2/4" and "Next ▶" for zero-based `page` of `pages`, each
+/// sending the `action` for its target page. The arrows are left out on the
+/// first and last page, and the label re-renders the current page.
+pub fn page_buttons(
+    page: usize,
+    pages: usize,
+    action: fn(usize) -> CallbackAction,
+) -> Vec<InlineKeyboardButton> {
+    let button = |label: String, target: usize| {
+        InlineKeyboardButton::callback(label, action(target).to_string())
+    };
+    let mut row = Vec::new();
+    if page > 0 {
+        row.push(button(LIST_PAGE_PREV.to_string(), page - 1));
+    }
+    row.push(button(list_page_label(page + 1, pages), page));
+    if page + 1 < pages {
+        row.push(button(LIST_PAGE_NEXT.to_string(), page + 1));
+    }
+    row
+}
+
+/// The items shown on `page` when `len` items are split into pages of
+/// [`LIST_PAGE_ITEMS`], with the page clamped to the last one and the page
+/// count. An empty list still has one page.
+pub fn page_range(len: usize, page: usize) -> (Range<usize>, usize, usize) {
+    let pages = len.div_ceil(LIST_PAGE_ITEMS).max(1);
+    let page = page.min(pages - 1);
+    let start = page * LIST_PAGE_ITEMS;
+    (start..len.min(start + LIST_PAGE_ITEMS), page, pages)
+}
+
+/// Split `text` at line breaks into chunks that each fit one message. A
+/// single line longer than the limit is kept whole.
+pub fn split_message(text: &str) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut chunk = String::new();
+    for line in text.split_inclusive('\n') {
+        let len = chunk.encode_utf16().count() + line.encode_utf16().count();
+        if !chunk.is_empty() && len > MESSAGE_TEXT_LIMIT {
+            chunks.push(std::mem::take(&mut chunk));
+        }
+        chunk.push_str(line);
+    }
+    if !chunk.is_empty() || chunks.is_empty() {
+        chunks.push(chunk);
+    }
+    chunks
+}
+
+/// Show the page named by a list page button.
+pub async fn handle_page_callback(
+    bot: &Bot,
+    msg: &MaybeInaccessibleMessage,
+    db: &Database,
+    shared: &SharedState,
+    page: usize,
+) -> Result<()> {
+    ListService::new(db, shared)
+        .show_page(bot, msg.chat().id, msg.id(), page)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use teloxide::types::InlineKeyboardButtonKind;
+
+    fn callbacks(row: &[InlineKeyboardButton]) -> Vec<&str> {
+        row.iter()
+            .map(|button| match &button.kind {
+                InlineKeyboardButtonKind::CallbackData(data) => data.as_str(),
+                _ => panic!("expected callback data"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn page_buttons_hide_arrows_at_the_ends() {
+        assert_eq!(
+            callbacks(&page_buttons(0, 3, CallbackAction::ListPage)),
+            ["page_0", "page_1"]
+        );
+        assert_eq!(
+            callbacks(&page_buttons(1, 3, CallbackAction::ListPage)),
+            ["page_0", "page_1", "page_2"]
+        );
+        let last = page_buttons(2, 3, CallbackAction::DeletePage);
+        assert_eq!(callbacks(&last), ["delete_page_1", "delete_page_2"]);
+        assert_eq!(last[1].text, "3/3");
+    }
+
+    #[test]
+    fn page_range_clamps_to_the_last_page() {
+        assert_eq!(page_range(0, 0), (0..0, 0, 1));
+        assert_eq!(page_range(LIST_PAGE_ITEMS, 1), (0..LIST_PAGE_ITEMS, 0, 1));
+        let len = LIST_PAGE_ITEMS * 2 + 1;
+        assert_eq!(
+            page_range(len, 1),
+            (LIST_PAGE_ITEMS..LIST_PAGE_ITEMS * 2, 1, 3)
+        );
+        assert_eq!(page_range(len, 7), (LIST_PAGE_ITEMS * 2..len, 2, 3));
+    }
+
+    #[test]
+    fn split_message_breaks_at_lines() {
+        let line = format!("{}\n", "x".repeat(99));
+        let text = line.repeat(100);
+        let chunks = split_message(&text);
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks
+            .iter()
+            .all(|chunk| chunk.len() <= MESSAGE_TEXT_LIMIT && chunk.ends_with('\n')));
+        assert_eq!(chunks.concat(), text);
+        assert_eq!(split_message("short"), ["short"]);
+    }
+}
@@ -0,0 +1,91 @@
+//! `/start pair_<code>` finishes pairing a device that asked the API for a
+//! code: the code is bound to the chat that opened the link, and the device
+//! then exchanges it for a token of that chat.
+
+use anyhow::Result;
+use teloxide::prelude::*;
+
+use super::tokens::issuer_display_name;
+use crate::db::Database;
+use crate::messages::{PAIRING_DONE, PAIRING_EXPIRED};
+
+/// Marks a `/start` payload as a pairing code.
+pub const PAIRING_PREFIX: &str = "pair_";
+
+/// Bind `code` to the chat of `msg` and tell the sender how it went.
+pub async fn pair_chat(bot: Bot, msg: Message, db: Database, code: &str) -> Result<()> {
+    let chat_id = msg.chat.id;
+    let issuer = msg.from.as_ref();
+    let bound = db
+        .bind_pairing(
+            code,
+            chat_id,
+            issuer.map(|user| user.id.0 as i64),
+            issuer.map(issuer_display_name).as_deref(),
+            chrono::Utc::now().timestamp(),
+        )
+        .await?;
+    tracing::debug!(chat_id = chat_id.0, bound, "Pairing link opened");
+    let reply = if bound { PAIRING_DONE } else { PAIRING_EXPIRED };
+    bot.send_message(chat_id, reply).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::PairingClaim;
+    use crate::tests::util::{init_test_db, test_bot, SENT_MESSAGE};
+    use wiremock::{
+        matchers::{body_string_contains, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn start_message(code: &str) -> Message {
+        serde_json::from_value(serde_json::json!({
+            "message_id": 1,
+            "date": 0,
+            "chat": {"id": 1, "type": "private"},
+            "from": {"id": 5, "is_bot": false, "first_name": "Ann"},
+            "text": format!("/start {PAIRING_PREFIX}{code}"),
+        }))
+        .unwrap()
+    }
+
+    async fn expect_reply(server: &MockServer, text: &str) {
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SendMessage"))
+            .and(body_string_contains(text))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(SENT_MESSAGE, "application/json"))
+            .expect(1)
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn opening_the_link_binds_the_chat_once() {
+        let server = MockServer::start().await;
+        expect_reply(&server, "Device connected").await;
+        expect_reply(&server, "expired or was already used").await;
+        let db = init_test_db().await;
+        let now = chrono::Utc::now().timestamp();
+        db.create_pairing("abc", now, now + 300).await.unwrap();
+        let bot = test_bot(&server);
+
+        pair_chat(bot.clone(), start_message("abc"), db.clone(), "abc")
+            .await
+            .unwrap();
+        pair_chat(bot, start_message("abc"), db.clone(), "abc")
+            .await
+            .unwrap();
+        assert_eq!(
+            db.claim_pairing("abc", now).await.unwrap(),
+            PairingClaim::Claimed {
+                chat_id: ChatId(1),
+                issuer_user_id: Some(5),
+                issuer_name: Some("Ann".to_string()),
+            }
+        );
+        server.verify().await;
+    }
+}
@@ -1,5 +1,11 @@
+use crate::ai::config::AiConfig;
+use crate::ai::gpt::classify_list_message;
 use crate::ai::stt::parse_items;
 
+/// In strict mode, a single line of more words than this without a comma
+/// reads as a sentence rather than items.
+pub const STRICT_MAX_WORDS: usize = 6;
+
 pub fn parse_items_with_fallback(
     text: &str,
     gpt_result: anyhow::Result<Vec<String>>,
@@ -21,9 +27,54 @@ pub fn parse_items_with_fallback(
     }
 }
 
+/// The cheap half of strict mode: questions and long single lines without
+/// commas are conversation.
+pub fn looks_like_chatter(text: &str) -> bool {
+    let text = text.trim();
+    if text.ends_with('?') {
+        return true;
+    }
+    !text.contains([',', '\n']) && text.split_whitespace().count() > STRICT_MAX_WORDS
+}
+
+/// Whether a message in a strict-mode chat should be read as items. Text
+/// that passes the heuristic is checked with the model when GPT parsing is
+/// enabled; if that call fails the message is kept.
+pub async fn is_list_message(text: &str, ai_config: Option<&AiConfig>) -> bool {
+    if looks_like_chatter(text) {
+        tracing::debug!("Message looks like chatter");
+        return false;
+    }
+    let Some(config) = ai_config.filter(|config| config.enable_gpt_parse) else {
+        tracing::debug!("GPT parsing disabled; keeping the message on the heuristic alone");
+        return true;
+    };
+    match classify_list_message(
+        &config.api_key,
+        &config.gpt_model,
+        text,
+        config.openai_chat_url.as_deref(),
+        &config.openai,
+    )
+    .await
+    {
+        Ok(is_list) => is_list,
+        Err(err) => {
+            tracing::warn!(error = %err, "Message classification failed; keeping it");
+            true
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::parse_items_with_fallback;
+    use super::*;
+    use crate::ai::common::OpenAiClient;
+    use crate::ai::config::DEFAULT_VOICE_DELETE_CONFIRM_THRESHOLD;
+    use wiremock::{
+        matchers::{body_string_contains, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
 
     #[test]
     fn parse_items_with_fallback_uses_gpt_success() {
@@ -39,4 +90,98 @@ mod tests {
             vec!["a".to_string(), "b".to_string(), "c".to_string()]
         );
     }
+
+    #[test]
+    fn questions_and_long_sentences_look_like_chatter() {
+        assert!(looks_like_chatter("can you get milk?"));
+        assert!(looks_like_chatter(
+            "I think we should go to the other shop tomorrow"
+        ));
+        assert!(!looks_like_chatter("milk"));
+        assert!(!looks_like_chatter(
+            "2 large cartons of milk, eggs, bread and some butter"
+        ));
+        assert!(!looks_like_chatter(
+            "milk\neggs\nbread\nbutter\ncheese\napples\npears"
+        ));
+    }
+
+    fn classifier_config(server: &MockServer) -> AiConfig {
+        AiConfig {
+            api_key: "k".into(),
+            stt_model: "whisper-1".into(),
+            gpt_model: "gpt-4.1".into(),
+            vision_model: "gpt-4o".into(),
+            openai_chat_url: Some(format!("{}/v1/chat/completions", server.uri())),
+            openai_stt_url: None,
+            enable_stt: true,
+            enable_vision: true,
+            enable_gpt_parse: true,
+            enable_voice_commands: true,
+            voice_delete_confirm_threshold: DEFAULT_VOICE_DELETE_CONFIRM_THRESHOLD,
+            stt_language: None,
+            openai: OpenAiClient::default(),
+            prompts: Default::default(),
+            vision_max_edge: None,
+        }
+    }
+
+    async fn mount_verdict(server: &MockServer, text: &str, is_list: bool) {
+        let content = serde_json::json!({ "is_list": is_list }).to_string();
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .and(body_string_contains(text))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "choices": [{ "message": { "content": content } }]
+            })))
+            .expect(1)
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn model_decides_what_passes_the_heuristic() {
+        let server = MockServer::start().await;
+        mount_verdict(&server, "ok thanks", false).await;
+        mount_verdict(&server, "oat milk", true).await;
+        let config = classifier_config(&server);
+
+        assert!(!is_list_message("ok thanks", Some(&config)).await);
+        assert!(is_list_message("oat milk", Some(&config)).await);
+        assert!(!is_list_message("who is going shopping?", Some(&config)).await);
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn failed_classification_keeps_the_message() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(ResponseTemplate::new(400))
+            .mount(&server)
+            .await;
+        let config = classifier_config(&server);
+
+        assert!(is_list_message("ok thanks", Some(&config)).await);
+        assert!(is_list_message("ok thanks", None).await);
+    }
+
+    #[tokio::test]
+    async fn disabled_gpt_parsing_skips_the_model() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+        let config = AiConfig {
+            enable_gpt_parse: false,
+            ..classifier_config(&server)
+        };
+
+        assert!(is_list_message("ok thanks", Some(&config)).await);
+        assert!(!is_list_message("who is going shopping?", Some(&config)).await);
+        server.verify().await;
+    }
 }
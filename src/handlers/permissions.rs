@@ -0,0 +1,315 @@
+//! Cached view of what the bot may do in each chat, so features that need
+//! admin rights can skip cleanly instead of failing on every attempt.
+
+use anyhow::Result;
+use teloxide::types::{ChatMemberKind, ChatMemberUpdated, MessageId};
+use teloxide::{prelude::*, ApiError, RequestError};
+
+use crate::db::{BotCapabilities, Database};
+use crate::messages::DELETE_RIGHTS_NEEDED;
+
+pub fn capabilities_from_member(kind: &ChatMemberKind) -> BotCapabilities {
+    match kind {
+        ChatMemberKind::Owner(_) => BotCapabilities {
+            can_delete: true,
+            can_pin: true,
+        },
+        ChatMemberKind::Administrator(admin) => BotCapabilities {
+            can_delete: admin.can_delete_messages,
+            can_pin: admin.can_pin_messages,
+        },
+        ChatMemberKind::Restricted(restricted) => BotCapabilities {
+            can_delete: false,
+            can_pin: restricted.can_pin_messages,
+        },
+        ChatMemberKind::Member | ChatMemberKind::Left | ChatMemberKind::Banned(_) => {
+            BotCapabilities {
+                can_delete: false,
+                can_pin: false,
+            }
+        }
+    }
+}
+
+/// Refresh the cache whenever Telegram reports a change to the bot's own
+/// membership.
+pub async fn handle_my_chat_member(update: ChatMemberUpdated, db: Database) -> Result<()> {
+    // Private chats have no admin rights; the bot can always delete there.
+    if update.chat.is_private() {
+        return Ok(());
+    }
+    let capabilities = capabilities_from_member(&update.new_chat_member.kind);
+    tracing::debug!(
+        chat_id = update.chat.id.0,
+        can_delete = capabilities.can_delete,
+        can_pin = capabilities.can_pin,
+        "Bot membership changed"
+    );
+    db.set_bot_capabilities(update.chat.id, capabilities, update.date.timestamp())
+        .await
+}
+
+fn is_missing_delete_right(err: &RequestError) -> bool {
+    matches!(err, RequestError::Api(ApiError::MessageCantBeDeleted))
+}
+
+async fn notify_missing_delete_right(bot: &Bot, db: &Database, chat_id: ChatId) -> Result<()> {
+    if db.claim_delete_notice(chat_id).await? {
+        tracing::info!(
+            chat_id = chat_id.0,
+            "Telling chat the bot needs delete rights"
+        );
+        bot.send_message(chat_id, DELETE_RIGHTS_NEEDED).await?;
+    }
+    Ok(())
+}
+
+/// Delete a message sent by a user. Skips the request when the bot is known
+/// to lack delete rights, and records the missing right when Telegram
+/// refuses. The chat is told once either way. Returns whether the message
+/// was deleted.
+pub async fn delete_user_message(
+    bot: &Bot,
+    db: &Database,
+    chat_id: ChatId,
+    message_id: MessageId,
+) -> Result<bool> {
+    let mut capabilities = db.bot_capabilities(chat_id).await?;
+    if !capabilities.can_delete {
+        tracing::debug!(
+            chat_id = chat_id.0,
+            message_id = message_id.0,
+            "Skipping delete without rights"
+        );
+        notify_missing_delete_right(bot, db, chat_id).await?;
+        return Ok(false);
+    }
+
+    match bot.delete_message(chat_id, message_id).await {
+        Ok(_) => {
+            tracing::debug!(
+                chat_id = chat_id.0,
+                message_id = message_id.0,
+                "Deleted user message"
+            );
+            Ok(true)
+        }
+        Err(err) if is_missing_delete_right(&err) && !chat_id.is_user() => {
+            tracing::warn!(
+                error = %err,
+                chat_id = chat_id.0,
+                message_id = message_id.0,
+                "Bot lacks delete rights"
+            );
+            capabilities.can_delete = false;
+            db.set_bot_capabilities(chat_id, capabilities, chrono::Utc::now().timestamp())
+                .await?;
+            notify_missing_delete_right(bot, db, chat_id).await?;
+            Ok(false)
+        }
+        Err(err) => {
+            tracing::warn!(
+                error = %err,
+                chat_id = chat_id.0,
+                message_id = message_id.0,
+                "Failed to delete message",
+            );
+            Ok(false)
+        }
+    }
+}
+
+/// Whether the sender of `msg` may run admin-only commands. Private chats are
+/// unrestricted; in groups only the owner and administrators qualify,
+/// including anonymous admins posting as the group itself.
+pub async fn sender_is_admin(bot: &Bot, msg: &Message) -> Result<bool> {
+    if !msg.chat.is_group() && !msg.chat.is_supergroup() {
+        return Ok(true);
+    }
+    if msg
+        .sender_chat
+        .as_ref()
+        .is_some_and(|chat| chat.id == msg.chat.id)
+    {
+        return Ok(true);
+    }
+    let Some(user) = msg.from.as_ref() else {
+        return Ok(false);
+    };
+    let member = bot.get_chat_member(msg.chat.id, user.id).await?;
+    let admin = matches!(
+        member.kind,
+        ChatMemberKind::Owner(_) | ChatMemberKind::Administrator(_)
+    );
+    tracing::debug!(
+        chat_id = msg.chat.id.0,
+        user_id = user.id.0,
+        admin,
+        "Checked sender's admin status"
+    );
+    Ok(admin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::util::{init_test_db, test_bot, SENT_MESSAGE};
+    use wiremock::{
+        matchers::{body_string_contains, method, path, path_regex},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    const CANT_DELETE: &str =
+        r#"{"ok":false,"error_code":400,"description":"Bad Request: message can't be deleted"}"#;
+
+    async fn mount_delete(server: &MockServer, status: u16, body: &str, times: u64) {
+        Mock::given(method("POST"))
+            .and(path_regex(r"^/botTEST/[Dd]eleteMessage$"))
+            .respond_with(ResponseTemplate::new(status).set_body_raw(body, "application/json"))
+            .expect(times)
+            .mount(server)
+            .await;
+    }
+
+    async fn expect_notice(server: &MockServer, times: u64) {
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SendMessage"))
+            .and(body_string_contains("Delete messages"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(SENT_MESSAGE, "application/json"))
+            .expect(times)
+            .mount(server)
+            .await;
+    }
+
+    fn membership_update(status_json: &str) -> ChatMemberUpdated {
+        serde_json::from_str(&format!(
+            r#"{{"chat":{{"id":-5,"type":"group","title":"Family"}},"from":{{"id":1,"is_bot":false,"first_name":"Ann"}},"date":100,"old_chat_member":{{"user":{{"id":99,"is_bot":true,"first_name":"Bot"}},"status":"member"}},"new_chat_member":{{"user":{{"id":99,"is_bot":true,"first_name":"Bot"}},{status_json}}}}}"#
+        ))
+        .unwrap()
+    }
+
+    const ADMIN_NO_DELETE: &str = r#""status":"administrator","can_be_edited":false,"is_anonymous":false,"can_manage_chat":true,"can_delete_messages":false,"can_manage_video_chats":false,"can_restrict_members":false,"can_promote_members":false,"can_change_info":false,"can_invite_users":true,"can_pin_messages":true,"can_post_stories":false,"can_edit_stories":false,"can_delete_stories":false"#;
+
+    #[tokio::test]
+    async fn permission_error_flips_cache_and_notifies_once() {
+        let server = MockServer::start().await;
+        mount_delete(&server, 400, CANT_DELETE, 1).await;
+        expect_notice(&server, 1).await;
+        let db = init_test_db().await;
+        let bot = test_bot(&server);
+        let chat = ChatId(-5);
+
+        assert!(!delete_user_message(&bot, &db, chat, MessageId(42))
+            .await
+            .unwrap());
+        assert!(!db.bot_capabilities(chat).await.unwrap().can_delete);
+
+        // Later attempts skip the request and stay quiet.
+        assert!(!delete_user_message(&bot, &db, chat, MessageId(43))
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn missing_message_keeps_rights() {
+        let server = MockServer::start().await;
+        mount_delete(
+            &server,
+            400,
+            r#"{"ok":false,"error_code":400,"description":"Bad Request: message to delete not found"}"#,
+            1,
+        )
+        .await;
+        expect_notice(&server, 0).await;
+        let db = init_test_db().await;
+
+        delete_user_message(&test_bot(&server), &db, ChatId(-5), MessageId(42))
+            .await
+            .unwrap();
+        assert!(db.bot_capabilities(ChatId(-5)).await.unwrap().can_delete);
+    }
+
+    #[tokio::test]
+    async fn membership_updates_refresh_cache() {
+        let server = MockServer::start().await;
+        mount_delete(&server, 200, r#"{"ok":true,"result":true}"#, 1).await;
+        expect_notice(&server, 1).await;
+        let db = init_test_db().await;
+        let bot = test_bot(&server);
+        let chat = ChatId(-5);
+
+        handle_my_chat_member(membership_update(ADMIN_NO_DELETE), db.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            db.bot_capabilities(chat).await.unwrap(),
+            BotCapabilities {
+                can_delete: false,
+                can_pin: true
+            }
+        );
+        assert!(!delete_user_message(&bot, &db, chat, MessageId(42))
+            .await
+            .unwrap());
+
+        handle_my_chat_member(
+            membership_update(r#""status":"creator","is_anonymous":false"#),
+            db.clone(),
+        )
+        .await
+        .unwrap();
+        assert!(delete_user_message(&bot, &db, chat, MessageId(42))
+            .await
+            .unwrap());
+    }
+
+    fn command_from(chat_json: &str) -> Message {
+        serde_json::from_str(&format!(
+            r#"{{"message_id":1,"date":0,"chat":{chat_json},"from":{{"id":7,"is_bot":false,"first_name":"Ann"}},"text":"/nuke"}}"#
+        ))
+        .unwrap()
+    }
+
+    /// GetChatMember status -> whether the sender counts as an admin.
+    #[tokio::test]
+    async fn only_group_admins_pass() {
+        let user = r#""user":{"id":7,"is_bot":false,"first_name":"Ann"}"#;
+        let cases = [
+            (format!(r#"{{{user},"status":"member"}}"#), false),
+            (format!("{{{user},{ADMIN_NO_DELETE}}}"), true),
+            (
+                format!(r#"{{{user},"status":"creator","is_anonymous":false}}"#),
+                true,
+            ),
+        ];
+        for (member, admin) in cases {
+            let server = MockServer::start().await;
+            Mock::given(method("POST"))
+                .and(path("/botTEST/GetChatMember"))
+                .and(body_string_contains(r#""user_id":7"#))
+                .respond_with(ResponseTemplate::new(200).set_body_raw(
+                    format!(r#"{{"ok":true,"result":{member}}}"#),
+                    "application/json",
+                ))
+                .expect(1)
+                .mount(&server)
+                .await;
+
+            let msg = command_from(r#"{"id":-5,"type":"group","title":"Family"}"#);
+            assert_eq!(
+                sender_is_admin(&test_bot(&server), &msg).await.unwrap(),
+                admin,
+                "{member}"
+            );
+            server.verify().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn private_chats_skip_the_admin_check() {
+        let server = MockServer::start().await;
+        let msg = command_from(r#"{"id":7,"type":"private"}"#);
+        assert!(sender_is_admin(&test_bot(&server), &msg).await.unwrap());
+        assert!(server.received_requests().await.unwrap().is_empty());
+    }
+}
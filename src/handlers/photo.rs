@@ -1,55 +1,319 @@
-use crate::db::Database;
+use crate::db::{Database, ItemAuthor};
+use crate::state::SharedState;
 use crate::utils::download_telegram_file;
 use anyhow::Result;
+use std::time::Duration;
 use teloxide::prelude::*;
+use tokio::task::JoinHandle;
 
-use super::list::insert_capitalized_items_with_log;
+use super::duplicates::skip_listed_items;
+use super::list::{insert_capitalized_items_with_log, insert_priced_items_with_log};
+use super::list_service::ListService;
 use crate::ai::config::AiConfig;
-use crate::ai::vision::parse_photo_items;
+use crate::ai::prompts::PromptKind;
+use crate::ai::stt::parse_items;
+use crate::ai::vision::{parse_album_items, parse_photo_items, parse_receipt_items, VisionModel};
 
+/// How long the photos of an album are collected after the first arrives.
+/// Telegram delivers album members as separate messages in quick succession.
+const ALBUM_WINDOW: Duration = Duration::from_millis(1500);
+
+/// The file id of the largest size of the photo in `msg`.
+fn largest_photo(msg: &Message) -> Option<&str> {
+    msg.photo()?
+        .iter()
+        .max_by_key(|p| p.file.size)
+        .map(|p| p.file.id.as_str())
+}
+
+/// Add the photo's items. Without vision only the caption is read; with it
+/// the caption goes along with the photo and is read on its own only when
+/// the vision request fails.
 pub async fn add_items_from_photo(
     bot: Bot,
     msg: Message,
     db: Database,
+    shared: SharedState,
     ai_config: Option<AiConfig>,
 ) -> Result<()> {
-    let Some(config) = ai_config else {
-        return Ok(());
+    let Some(config) = ai_config.filter(|config| config.enable_vision) else {
+        tracing::debug!(
+            chat_id = msg.chat.id.0,
+            "Vision unavailable; reading the caption only"
+        );
+        let caption = msg.caption();
+        return add_caption_items(bot, &msg, &db, &shared, caption, "from photo caption").await;
     };
 
-    let photo_sizes = match msg.photo() {
-        Some(p) => p,
-        None => return Ok(()),
-    };
-    let Some(file_id) = photo_sizes
-        .iter()
-        .max_by_key(|p| p.file.size)
-        .map(|p| &p.file.id)
-    else {
+    if let Some(group) = msg.media_group_id().map(str::to_string) {
+        let _flush = buffer_album_photo(bot, msg, db, shared, config, group, ALBUM_WINDOW).await?;
+        return Ok(());
+    }
+
+    let Some(file_id) = largest_photo(&msg) else {
         tracing::debug!("photo had no usable sizes");
         return Ok(());
     };
 
     let bytes = download_telegram_file(&bot, file_id).await?;
+    let settings = db.get_settings(msg.chat.id).await?;
+    let caption = msg.caption();
+    if settings.receipt_mode {
+        let photos = std::slice::from_ref(&bytes);
+        return add_items_from_receipt(bot, &msg, &db, &shared, &config, photos, caption).await;
+    }
+    let prompts = config.prompts.overridden_by(&settings.prompts);
 
     tracing::debug!(model = %config.vision_model, "parsing photo with OpenAI vision");
     let items = match parse_photo_items(
-        &config.api_key,
-        &config.vision_model,
+        &VisionModel::from(&config),
+        prompts.get(PromptKind::Photo),
         &bytes,
-        config.openai_chat_url.as_deref(),
+        None,
+        caption,
     )
     .await
     {
         Ok(list) => list,
         Err(err) => {
             tracing::warn!("photo parsing failed: {}", err);
-            Vec::new()
+            return add_caption_items(bot, &msg, &db, &shared, caption, "from photo caption").await;
         }
     };
 
-    let _added =
-        insert_capitalized_items_with_log(bot, msg.chat.id, &db, items, "from photo").await?;
+    insert_photo_items(bot, &msg, &db, &shared, items, "from photo").await
+}
+
+/// Hold an album photo in the database until its media group is complete.
+/// The first member spawns a task that parses the whole album after `window`;
+/// later members only join it and get no handle.
+async fn buffer_album_photo(
+    bot: Bot,
+    msg: Message,
+    db: Database,
+    shared: SharedState,
+    config: AiConfig,
+    group: String,
+    window: Duration,
+) -> Result<Option<JoinHandle<()>>> {
+    let chat_id = msg.chat.id;
+    if !db.add_album_photo(chat_id, &group, &msg).await? {
+        tracing::debug!(chat_id = chat_id.0, %group, "Buffered album photo");
+        return Ok(None);
+    }
+    tracing::debug!(chat_id = chat_id.0, %group, "Collecting album photos");
+    Ok(Some(schedule_album_flush(
+        bot, db, shared, config, chat_id, group, window,
+    )))
+}
+
+/// Parse the photos buffered for album `group` once `window` has passed.
+fn schedule_album_flush(
+    bot: Bot,
+    db: Database,
+    shared: SharedState,
+    config: AiConfig,
+    chat_id: ChatId,
+    group: String,
+    window: Duration,
+) -> JoinHandle<()> {
+    let tasks = shared.tasks.clone();
+    tasks.spawn(async move {
+        tokio::time::sleep(window).await;
+        let flushed = match db.take_album_photos(chat_id, &group).await {
+            Ok(messages) => add_items_from_album(bot, messages, db, shared, config).await,
+            Err(err) => Err(err),
+        };
+        if let Err(err) = flushed {
+            tracing::warn!(chat_id = chat_id.0, %group, error = %err, "Album parsing failed");
+        }
+    })
+}
+
+/// Parse the albums whose photos were still being collected when the bot
+/// last stopped. Without vision only their captions are read.
+pub async fn resume_pending_albums(
+    bot: &Bot,
+    db: &Database,
+    shared: &SharedState,
+    ai_config: Option<&AiConfig>,
+) -> Result<()> {
+    let config = ai_config.filter(|config| config.enable_vision);
+    for (chat_id, group) in db.pending_albums().await? {
+        tracing::debug!(chat_id = chat_id.0, %group, "Resuming pending album");
+        if let Some(config) = config {
+            let _flush = schedule_album_flush(
+                bot.clone(),
+                db.clone(),
+                shared.clone(),
+                config.clone(),
+                chat_id,
+                group,
+                Duration::ZERO,
+            );
+            continue;
+        }
+        let messages = db.take_album_photos(chat_id, &group).await?;
+        let Some(first) = messages.first() else {
+            continue;
+        };
+        let caption = album_caption(&messages);
+        add_caption_items(
+            bot.clone(),
+            first,
+            db,
+            shared,
+            caption.as_deref(),
+            "from album caption",
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// The caption of an album. Telegram puts it on one of its photos.
+fn album_caption(messages: &[Message]) -> Option<String> {
+    let captions: Vec<&str> = messages.iter().filter_map(Message::caption).collect();
+    (!captions.is_empty()).then(|| captions.join("\n"))
+}
+
+/// Parse the photos of one album with a single vision request and add the
+/// items in one batch.
+async fn add_items_from_album(
+    bot: Bot,
+    messages: Vec<Message>,
+    db: Database,
+    shared: SharedState,
+    config: AiConfig,
+) -> Result<()> {
+    let Some(first) = messages.first() else {
+        return Ok(());
+    };
+    let mut photos = Vec::with_capacity(messages.len());
+    for file_id in messages.iter().filter_map(largest_photo) {
+        match download_telegram_file(&bot, file_id).await {
+            Ok(bytes) => photos.push(bytes),
+            Err(err) => {
+                tracing::warn!(error = %err, "Skipping album photo that failed to download")
+            }
+        }
+    }
+    if photos.is_empty() {
+        tracing::debug!(chat_id = first.chat.id.0, "Album had no usable photos");
+        return Ok(());
+    }
+
+    let caption = album_caption(&messages);
+    let caption = caption.as_deref();
+
+    let settings = db.get_settings(first.chat.id).await?;
+    if settings.receipt_mode {
+        return add_items_from_receipt(bot, first, &db, &shared, &config, &photos, caption).await;
+    }
+    let prompts = config.prompts.overridden_by(&settings.prompts);
+    tracing::debug!(
+        model = %config.vision_model,
+        photos = photos.len(),
+        "parsing album with OpenAI vision"
+    );
+    let items = match parse_album_items(
+        &VisionModel::from(&config),
+        prompts.get(PromptKind::Photo),
+        &photos,
+        caption,
+    )
+    .await
+    {
+        Ok(list) => list,
+        Err(err) => {
+            tracing::warn!("album parsing failed: {}", err);
+            return add_caption_items(bot, first, &db, &shared, caption, "from album caption")
+                .await;
+        }
+    };
+
+    insert_photo_items(bot, first, &db, &shared, items, "from album").await
+}
+
+/// Add the products on a receipt together with their prices.
+async fn add_items_from_receipt(
+    bot: Bot,
+    msg: &Message,
+    db: &Database,
+    shared: &SharedState,
+    config: &AiConfig,
+    photos: &[Vec<u8>],
+    caption: Option<&str>,
+) -> Result<()> {
+    tracing::debug!(model = %config.vision_model, "parsing receipt with OpenAI vision");
+    let items = match parse_receipt_items(&VisionModel::from(config), photos, caption).await {
+        Ok(list) => list,
+        Err(err) => {
+            tracing::warn!("receipt parsing failed: {}", err);
+            return add_caption_items(bot, msg, db, shared, caption, "from receipt caption").await;
+        }
+    };
+    let items = skip_listed_items(db, msg.chat.id, items, |item| item.text.as_str()).await?;
+
+    if !items.is_empty() {
+        ListService::new(db, shared).follow_topic(msg).await?;
+    }
+    let author = msg.from.as_ref().map(ItemAuthor::from);
+    let _added = insert_priced_items_with_log(
+        bot,
+        msg.chat.id,
+        db,
+        shared,
+        items,
+        "from receipt",
+        author.as_ref(),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Add the items named in `caption`, split and cleaned like a transcription.
+/// The fallback when the photo itself cannot be read.
+async fn add_caption_items(
+    bot: Bot,
+    msg: &Message,
+    db: &Database,
+    shared: &SharedState,
+    caption: Option<&str>,
+    context: &str,
+) -> Result<()> {
+    let items = caption.map(parse_items).unwrap_or_default();
+    if items.is_empty() {
+        tracing::debug!(chat_id = msg.chat.id.0, "No items in photo caption");
+        return Ok(());
+    }
+    insert_photo_items(bot, msg, db, shared, items, context).await
+}
+
+async fn insert_photo_items(
+    bot: Bot,
+    msg: &Message,
+    db: &Database,
+    shared: &SharedState,
+    items: Vec<String>,
+    context: &str,
+) -> Result<()> {
+    let items = skip_listed_items(db, msg.chat.id, items, String::as_str).await?;
+    if !items.is_empty() {
+        ListService::new(db, shared).follow_topic(msg).await?;
+    }
+    let author = msg.from.as_ref().map(ItemAuthor::from);
+    let _added = insert_capitalized_items_with_log(
+        bot,
+        msg.chat.id,
+        db,
+        shared,
+        items,
+        context,
+        author.as_ref(),
+    )
+    .await?;
 
     Ok(())
 }
@@ -57,7 +321,255 @@ pub async fn add_items_from_photo(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::tests::util::init_test_db;
+    use crate::ai::common::OpenAiClient;
+    use crate::ai::config::DEFAULT_VOICE_DELETE_CONFIRM_THRESHOLD;
+    use crate::db::chat_settings::RECEIPT_MODE;
+    use crate::tests::util::{init_test_db, SENT_MESSAGE};
+    use reqwest::Client;
+    use wiremock::{
+        matchers::{method, path, path_regex},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    /// Serves `downloads` photo downloads and one vision answer of `content`.
+    async fn photo_server(downloads: u64, content: &str) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/GetFile"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"ok":true,"result":{"file_id":"f","file_unique_id":"u","file_path":"photo.jpg"}}"#,
+                "application/json",
+            ))
+            .expect(downloads)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/file/botTEST/photo.jpg"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("img", "image/jpeg"))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "choices": [{ "message": { "content": content } }]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path_regex(r"^/botTEST/[Ss]endMessage$"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(SENT_MESSAGE, "application/json"))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path_regex(r"^/botTEST/[Dd]eleteMessage$"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(r#"{"ok":true,"result":true}"#, "application/json"),
+            )
+            .mount(&server)
+            .await;
+        server
+    }
+
+    fn photo_json(message_id: i32, file_id: &str) -> serde_json::Value {
+        serde_json::json!({
+            "message_id": message_id,
+            "date": 0,
+            "chat": {"id": 1, "type": "private"},
+            "from": {"id": 5, "is_bot": false, "first_name": "Anna"},
+            "photo": [{"file_id": file_id, "file_unique_id": file_id, "width": 1, "height": 1, "file_size": 3}],
+        })
+    }
+
+    fn photo_message(message_id: i32, file_id: &str) -> Message {
+        serde_json::from_value(photo_json(message_id, file_id)).unwrap()
+    }
+
+    fn album_message(message_id: i32, file_id: &str) -> Message {
+        let mut json = photo_json(message_id, file_id);
+        json["media_group_id"] = "album-1".into();
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn photo_setup(server: &MockServer) -> (Bot, AiConfig) {
+        let client = Client::builder().no_proxy().build().unwrap();
+        let bot = Bot::with_client("TEST", client)
+            .set_api_url(reqwest::Url::parse(&server.uri()).unwrap());
+        let config = AiConfig {
+            api_key: "k".into(),
+            stt_model: "m".into(),
+            gpt_model: "g".into(),
+            vision_model: "v".into(),
+            openai_chat_url: Some(format!("{}/v1/chat/completions", server.uri())),
+            openai_stt_url: None,
+            enable_stt: true,
+            enable_vision: true,
+            enable_gpt_parse: true,
+            enable_voice_commands: true,
+            voice_delete_confirm_threshold: DEFAULT_VOICE_DELETE_CONFIRM_THRESHOLD,
+            stt_language: None,
+            openai: OpenAiClient::default(),
+            prompts: Default::default(),
+            vision_max_edge: None,
+        };
+        (bot, config)
+    }
+
+    #[tokio::test]
+    async fn album_photos_are_parsed_in_one_batch() {
+        let server = photo_server(2, r#"{"items":["milk","eggs"]}"#).await;
+        let (bot, config) = photo_setup(&server);
+        let db = init_test_db().await;
+        let window = Duration::from_millis(50);
+
+        let flush = buffer_album_photo(
+            bot.clone(),
+            album_message(1, "a"),
+            db.clone(),
+            SharedState::new().unwrap(),
+            config.clone(),
+            "album-1".into(),
+            window,
+        )
+        .await
+        .unwrap();
+        let joined = buffer_album_photo(
+            bot,
+            album_message(2, "b"),
+            db.clone(),
+            SharedState::new().unwrap(),
+            config,
+            "album-1".into(),
+            window,
+        )
+        .await
+        .unwrap();
+        assert!(joined.is_none());
+        flush.unwrap().await.unwrap();
+
+        let items = db.list_items(ChatId(1)).await.unwrap();
+        let texts: Vec<_> = items.iter().map(|item| item.text.as_str()).collect();
+        assert_eq!(texts, ["Milk", "Eggs"]);
+        assert!(db.pending_albums().await.unwrap().is_empty());
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn albums_pending_at_restart_are_parsed() {
+        let server = photo_server(2, r#"{"items":["milk","eggs"]}"#).await;
+        let (bot, config) = photo_setup(&server);
+        let db = init_test_db().await;
+        for (id, file_id) in [(1, "a"), (2, "b")] {
+            db.add_album_photo(ChatId(1), "album-1", &album_message(id, file_id))
+                .await
+                .unwrap();
+        }
+
+        let shared = SharedState::new().unwrap();
+        resume_pending_albums(&bot, &db, &shared, Some(&config))
+            .await
+            .unwrap();
+        shared.tasks.wait(Duration::from_secs(5)).await;
+
+        let items = db.list_items(ChatId(1)).await.unwrap();
+        let texts: Vec<_> = items.iter().map(|item| item.text.as_str()).collect();
+        assert_eq!(texts, ["Milk", "Eggs"]);
+        assert!(db.pending_albums().await.unwrap().is_empty());
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn receipts_keep_prices_where_readable() {
+        let server = photo_server(
+            1,
+            r#"{"items":[{"text":"milk","price":3.49},{"text":"bread","price":null},"eggs"]}"#,
+        )
+        .await;
+        let (bot, config) = photo_setup(&server);
+        let db = init_test_db().await;
+        db.set_setting(ChatId(1), RECEIPT_MODE, "true")
+            .await
+            .unwrap();
+
+        add_items_from_photo(
+            bot,
+            photo_message(1, "r"),
+            db.clone(),
+            SharedState::new().unwrap(),
+            Some(config),
+        )
+        .await
+        .unwrap();
+
+        let items = db.list_items(ChatId(1)).await.unwrap();
+        let prices: Vec<_> = items
+            .iter()
+            .map(|item| (item.text.as_str(), item.price_cents))
+            .collect();
+        assert_eq!(
+            prices,
+            [("Milk", Some(349)), ("Bread", None), ("Eggs", None)]
+        );
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn photo_items_already_listed_are_skipped() {
+        let server = photo_server(1, r#"{"items":["milk","eggs","Eggs"]}"#).await;
+        let (bot, config) = photo_setup(&server);
+        let db = init_test_db().await;
+        db.add_items_count(ChatId(1), &["Milk".to_string()])
+            .await
+            .unwrap();
+
+        add_items_from_photo(
+            bot,
+            photo_message(1, "p"),
+            db.clone(),
+            SharedState::new().unwrap(),
+            Some(config),
+        )
+        .await
+        .unwrap();
+
+        let items = db.list_items(ChatId(1)).await.unwrap();
+        let counts: Vec<_> = items
+            .iter()
+            .map(|item| (item.text.as_str(), item.quantity))
+            .collect();
+        assert_eq!(counts, [("Milk", 1), ("Eggs", 1)]);
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn captions_are_read_without_vision() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path_regex(r"^/botTEST/[Ss]endMessage$"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(SENT_MESSAGE, "application/json"))
+            .mount(&server)
+            .await;
+        let (bot, _) = photo_setup(&server);
+        let db = init_test_db().await;
+        let mut json = photo_json(1, "c");
+        json["caption"] = "milk\n✅ 2 lemons".into();
+        let msg: Message = serde_json::from_value(json).unwrap();
+
+        add_items_from_photo(bot, msg, db.clone(), SharedState::new().unwrap(), None)
+            .await
+            .unwrap();
+
+        let items = db.list_items(ChatId(1)).await.unwrap();
+        let labels: Vec<_> = items.iter().map(|item| item.label()).collect();
+        assert_eq!(labels, ["Milk", "2× lemons"]);
+        assert!(server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .all(|req| req.url.path() != "/botTEST/GetFile"));
+    }
 
     #[tokio::test]
     async fn photo_with_no_sizes_returns_ok() {
@@ -72,9 +584,49 @@ mod tests {
             vision_model: "v".into(),
             openai_chat_url: None,
             openai_stt_url: None,
+            enable_stt: true,
+            enable_vision: true,
+            enable_gpt_parse: true,
+            enable_voice_commands: true,
+            voice_delete_confirm_threshold: DEFAULT_VOICE_DELETE_CONFIRM_THRESHOLD,
+            stt_language: None,
+            openai: OpenAiClient::default(),
+            prompts: Default::default(),
+            vision_max_edge: None,
+        });
+
+        let res = add_items_from_photo(bot, msg, db, SharedState::new().unwrap(), ai_config).await;
+        assert!(res.is_ok());
+    }
+
+    #[tokio::test]
+    async fn photo_ignored_when_vision_disabled() {
+        let db = init_test_db().await;
+        let bot = Bot::new("test");
+        let json = r#"{"message_id":1,"date":0,"chat":{"id":1,"type":"private"},"photo":[{"file_id":"f","file_unique_id":"u","width":1,"height":1,"file_size":1}]}"#;
+        let msg: Message = serde_json::from_str(json).unwrap();
+        let ai_config = Some(AiConfig {
+            api_key: "k".into(),
+            stt_model: "m".into(),
+            gpt_model: "g".into(),
+            vision_model: "v".into(),
+            openai_chat_url: None,
+            openai_stt_url: None,
+            enable_stt: true,
+            enable_vision: false,
+            enable_gpt_parse: true,
+            enable_voice_commands: true,
+            voice_delete_confirm_threshold: DEFAULT_VOICE_DELETE_CONFIRM_THRESHOLD,
+            stt_language: None,
+            openai: OpenAiClient::default(),
+            prompts: Default::default(),
+            vision_max_edge: None,
         });
 
-        let res = add_items_from_photo(bot, msg, db, ai_config).await;
+        let res =
+            add_items_from_photo(bot, msg, db.clone(), SharedState::new().unwrap(), ai_config)
+                .await;
         assert!(res.is_ok());
+        assert!(db.list_items(ChatId(1)).await.unwrap().is_empty());
     }
 }
@@ -0,0 +1,206 @@
+//! `/prompt` shows the AI prompts a chat uses and lets admins replace them
+//! for the chat. See [`Prompts`] for how overrides are resolved.
+
+use anyhow::Result;
+use teloxide::prelude::*;
+
+use super::permissions::sender_is_admin;
+use crate::ai::config::AiConfig;
+use crate::ai::prompts::{PromptKind, Prompts};
+use crate::db::Database;
+use crate::messages::{
+    prompt_overview_text, prompt_text, ADMIN_ONLY_COMMAND, PROMPT_NOT_OVERRIDDEN, PROMPT_RESET,
+    PROMPT_SAVED, PROMPT_USAGE,
+};
+use crate::utils::{topic_of, InTopic};
+
+/// A parsed `/prompt` invocation.
+#[derive(Debug, PartialEq)]
+enum PromptCommand<'a> {
+    Overview,
+    Show(PromptKind),
+    Set(PromptKind, &'a str),
+    Reset(PromptKind),
+}
+
+fn parse_prompt_command(args: &str) -> Option<PromptCommand<'_>> {
+    let args = args.trim();
+    if args.is_empty() {
+        return Some(PromptCommand::Overview);
+    }
+    let (name, rest) = args
+        .split_once(char::is_whitespace)
+        .map_or((args, ""), |(name, rest)| (name, rest.trim()));
+    let kind = PromptKind::parse(name)?;
+    Some(match rest {
+        "" => PromptCommand::Show(kind),
+        "reset" => PromptCommand::Reset(kind),
+        prompt => PromptCommand::Set(kind, prompt),
+    })
+}
+
+/// Where the prompt in effect for `kind` comes from.
+fn prompt_source(configured: &Prompts, chat: &Prompts, kind: PromptKind) -> &'static str {
+    if chat.override_of(kind).is_some() {
+        "set for this chat"
+    } else if configured.override_of(kind).is_some() {
+        "set by the bot's configuration"
+    } else {
+        "built in"
+    }
+}
+
+/// Handle `/prompt`, `/prompt <name>`, `/prompt <name> <prompt>` and
+/// `/prompt <name> reset`. Changing prompts is reserved for admins in groups.
+pub async fn handle_prompt(
+    bot: Bot,
+    msg: Message,
+    db: Database,
+    ai_config: Option<AiConfig>,
+    args: String,
+) -> Result<()> {
+    let chat_id = msg.chat.id;
+    let topic = topic_of(&msg);
+    let Some(command) = parse_prompt_command(&args) else {
+        bot.send_message(chat_id, PROMPT_USAGE)
+            .in_topic(topic)
+            .await?;
+        return Ok(());
+    };
+    tracing::debug!(chat_id = chat_id.0, ?command, "Prompt command");
+
+    let changes = matches!(command, PromptCommand::Set(..) | PromptCommand::Reset(_));
+    if changes && !sender_is_admin(&bot, &msg).await? {
+        tracing::info!(chat_id = chat_id.0, "Refusing prompt change from non-admin");
+        bot.send_message(chat_id, ADMIN_ONLY_COMMAND)
+            .in_topic(topic)
+            .await?;
+        return Ok(());
+    }
+
+    let configured = ai_config.map(|config| config.prompts).unwrap_or_default();
+    let chat = db.get_settings(chat_id).await?.prompts;
+    let reply = match command {
+        PromptCommand::Overview => {
+            let lines: Vec<String> = PromptKind::ALL
+                .into_iter()
+                .map(|kind| {
+                    format!(
+                        "• {}: {}",
+                        kind.name(),
+                        prompt_source(&configured, &chat, kind)
+                    )
+                })
+                .collect();
+            prompt_overview_text(&lines)
+        }
+        PromptCommand::Show(kind) => prompt_text(
+            kind.name(),
+            prompt_source(&configured, &chat, kind),
+            configured.overridden_by(&chat).get(kind),
+        ),
+        PromptCommand::Set(kind, prompt) => {
+            db.set_setting(chat_id, kind.setting_key(), prompt).await?;
+            PROMPT_SAVED.to_string()
+        }
+        PromptCommand::Reset(kind) => {
+            if db.clear_setting(chat_id, kind.setting_key()).await? {
+                PROMPT_RESET.to_string()
+            } else {
+                PROMPT_NOT_OVERRIDDEN.to_string()
+            }
+        }
+    };
+    bot.send_message(chat_id, reply).in_topic(topic).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::util::{init_test_db, SENT_MESSAGE};
+    use reqwest::Client;
+    use teloxide::types::ChatId;
+    use wiremock::{
+        matchers::{body_string_contains, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[test]
+    fn parses_subcommands() {
+        assert_eq!(parse_prompt_command(" "), Some(PromptCommand::Overview));
+        assert_eq!(
+            parse_prompt_command("photo"),
+            Some(PromptCommand::Show(PromptKind::Photo))
+        );
+        assert_eq!(
+            parse_prompt_command("voice reset"),
+            Some(PromptCommand::Reset(PromptKind::VoiceCommand))
+        );
+        assert_eq!(
+            parse_prompt_command("text Split on commas.\nKeep brands."),
+            Some(PromptCommand::Set(
+                PromptKind::Text,
+                "Split on commas.\nKeep brands."
+            ))
+        );
+        assert_eq!(parse_prompt_command("list something"), None);
+    }
+
+    #[test]
+    fn sources_follow_the_override_chain() {
+        let mut configured = Prompts::default();
+        let mut chat = Prompts::default();
+        let kind = PromptKind::Photo;
+        assert_eq!(prompt_source(&configured, &chat, kind), "built in");
+        configured.set(kind, "env");
+        assert_eq!(
+            prompt_source(&configured, &chat, kind),
+            "set by the bot's configuration"
+        );
+        chat.set(kind, "chat");
+        assert_eq!(prompt_source(&configured, &chat, kind), "set for this chat");
+    }
+
+    fn prompt_message(text: &str) -> Message {
+        serde_json::from_value(serde_json::json!({
+            "message_id": 1,
+            "date": 0,
+            "chat": {"id": 1, "type": "private"},
+            "from": {"id": 5, "is_bot": false, "first_name": "Ann"},
+            "text": text,
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn set_show_and_reset_a_chat_prompt() {
+        let server = MockServer::start().await;
+        for reply in [PROMPT_SAVED, "Read prices too", PROMPT_RESET] {
+            Mock::given(method("POST"))
+                .and(path("/botTEST/SendMessage"))
+                .and(body_string_contains(reply))
+                .respond_with(
+                    ResponseTemplate::new(200).set_body_raw(SENT_MESSAGE, "application/json"),
+                )
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+        let client = Client::builder().no_proxy().build().unwrap();
+        let bot = Bot::with_client("TEST", client)
+            .set_api_url(reqwest::Url::parse(&server.uri()).unwrap());
+        let db = init_test_db().await;
+
+        for args in ["photo Read prices too", "photo", "photo reset"] {
+            let msg = prompt_message(&format!("/prompt {args}"));
+            handle_prompt(bot.clone(), msg, db.clone(), None, args.to_string())
+                .await
+                .unwrap();
+        }
+
+        let prompts = db.get_settings(ChatId(1)).await.unwrap().prompts;
+        assert_eq!(prompts, Prompts::default());
+        server.verify().await;
+    }
+}
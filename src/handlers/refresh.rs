@@ -0,0 +1,317 @@
+//! Deferred delivery of list refreshes and confirmations. Once the database
+//! has changed, a Telegram outage should delay the visible update rather
+//! than fail the handler.
+
+use anyhow::Result;
+use std::time::Duration;
+use teloxide::{prelude::*, RequestError};
+use tracing::Instrument;
+
+use super::list_service::ListService;
+use crate::db::chat_state::PendingRefresh;
+use crate::db::Database;
+use crate::state::SharedState;
+use crate::utils::InTopic;
+
+/// How often and how patiently a deferred refresh is retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubled after every failure.
+    pub initial_delay: Duration,
+}
+
+pub const REFRESH_RETRY: RetryPolicy = RetryPolicy {
+    max_attempts: 5,
+    initial_delay: Duration::from_secs(1),
+};
+
+impl RetryPolicy {
+    fn delay(&self, attempt: u32) -> Duration {
+        self.initial_delay.saturating_mul(1 << attempt.min(16))
+    }
+}
+
+fn is_messaging_failure(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<RequestError>().is_some()
+}
+
+/// Pass `result` through unless it failed on a Telegram request; such
+/// failures are recorded as `pending` and retried in the background.
+pub async fn defer_on_send_failure(
+    bot: &Bot,
+    db: &Database,
+    shared: &SharedState,
+    chat_id: ChatId,
+    result: Result<()>,
+    pending: PendingRefresh,
+) -> Result<()> {
+    match result {
+        Ok(()) => Ok(()),
+        Err(err) if is_messaging_failure(&err) => {
+            tracing::warn!(error = %err, chat_id = chat_id.0, "Deferring Telegram update");
+            db.mark_refresh_pending(chat_id, &pending).await?;
+            schedule_refresh_retry(
+                bot.clone(),
+                db.clone(),
+                shared.clone(),
+                chat_id,
+                REFRESH_RETRY,
+            );
+            Ok(())
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Send a new list message, deferring it when Telegram is unavailable.
+pub async fn send_list_or_defer(
+    bot: &Bot,
+    db: &Database,
+    shared: &SharedState,
+    chat_id: ChatId,
+) -> Result<()> {
+    let result = ListService::new(db, shared)
+        .send_list(bot.clone(), chat_id)
+        .await;
+    let pending = PendingRefresh {
+        list: true,
+        notice: None,
+    };
+    defer_on_send_failure(bot, db, shared, chat_id, result, pending).await
+}
+
+/// Send a confirmation, deferring it when Telegram is unavailable.
+pub async fn send_notice_or_defer(
+    bot: &Bot,
+    db: &Database,
+    shared: &SharedState,
+    chat_id: ChatId,
+    text: &str,
+) -> Result<()> {
+    let result = match db.chat_thread(chat_id).await {
+        Ok(topic) => bot
+            .send_message(chat_id, text)
+            .in_topic(topic)
+            .await
+            .map(drop)
+            .map_err(Into::into),
+        Err(err) => Err(err),
+    };
+    let pending = PendingRefresh {
+        list: false,
+        notice: Some(text.to_string()),
+    };
+    defer_on_send_failure(bot, db, shared, chat_id, result, pending).await
+}
+
+/// Deliver what is pending for the chat. Anything that fails again is put
+/// back so the next attempt picks it up.
+async fn flush_pending(
+    bot: &Bot,
+    db: &Database,
+    shared: &SharedState,
+    chat_id: ChatId,
+) -> Result<()> {
+    let mut pending = db.take_pending_refresh(chat_id).await?;
+    if pending.is_empty() {
+        return Ok(());
+    }
+    tracing::debug!(chat_id = chat_id.0, ?pending, "Retrying deferred update");
+    if let Some(text) = &pending.notice {
+        let topic = db.chat_thread(chat_id).await?;
+        if let Err(err) = bot
+            .send_message(chat_id, text.as_str())
+            .in_topic(topic)
+            .await
+        {
+            db.mark_refresh_pending(chat_id, &pending).await?;
+            return Err(err.into());
+        }
+        pending.notice = None;
+    }
+    if pending.list {
+        if let Err(err) = ListService::new(db, shared)
+            .send_list(bot.clone(), chat_id)
+            .await
+        {
+            db.mark_refresh_pending(chat_id, &pending).await?;
+            return Err(err);
+        }
+    }
+    tracing::info!(chat_id = chat_id.0, "Delivered deferred update");
+    Ok(())
+}
+
+pub fn schedule_refresh_retry(
+    bot: Bot,
+    db: Database,
+    shared: SharedState,
+    chat_id: ChatId,
+    policy: RetryPolicy,
+) {
+    tracing::debug!(chat_id = chat_id.0, ?policy, "Scheduling refresh retry");
+    tokio::spawn(
+        async move {
+            for attempt in 0..policy.max_attempts {
+                tokio::time::sleep(policy.delay(attempt)).await;
+                match flush_pending(&bot, &db, &shared, chat_id).await {
+                    Ok(()) => return,
+                    Err(err) => tracing::warn!(
+                        error = %err,
+                        chat_id = chat_id.0,
+                        attempt = attempt + 1,
+                        "Deferred update failed"
+                    ),
+                }
+            }
+            tracing::error!(
+                chat_id = chat_id.0,
+                attempts = policy.max_attempts,
+                "Giving up on deferred update until restart"
+            );
+        }
+        .in_current_span(),
+    );
+}
+
+/// Restart retries for updates left pending by a previous run.
+pub async fn resume_pending_refreshes(
+    bot: &Bot,
+    db: &Database,
+    shared: &SharedState,
+) -> Result<()> {
+    let chats = db.pending_refresh_chats().await?;
+    if !chats.is_empty() {
+        tracing::info!(count = chats.len(), "Resuming deferred updates");
+    }
+    for chat_id in chats {
+        schedule_refresh_retry(
+            bot.clone(),
+            db.clone(),
+            shared.clone(),
+            chat_id,
+            REFRESH_RETRY,
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::list::insert_items;
+    use crate::tests::util::{init_test_db, test_bot, SENT_MESSAGE};
+    use wiremock::{
+        matchers::{body_string_contains, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    const UNAVAILABLE: &str = r#"{"ok":false,"error_code":502,"description":"Bad Gateway"}"#;
+
+    async fn flaky_send(server: &MockServer, text: &str, failures: u64) {
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SendMessage"))
+            .and(body_string_contains(text))
+            .respond_with(ResponseTemplate::new(429).set_body_raw(UNAVAILABLE, "application/json"))
+            .up_to_n_times(failures)
+            .expect(failures)
+            .mount(server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SendMessage"))
+            .and(body_string_contains(text))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(SENT_MESSAGE, "application/json"))
+            .expect(1)
+            .mount(server)
+            .await;
+    }
+
+    async fn wait_for_sends(server: &MockServer, count: usize) {
+        for _ in 0..50 {
+            let sends = server
+                .received_requests()
+                .await
+                .unwrap()
+                .iter()
+                .filter(|request| request.url.path() == "/botTEST/SendMessage")
+                .count();
+            if sends >= count {
+                // Let the retry task finish its database writes.
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("deferred update never landed");
+    }
+
+    #[test]
+    fn delay_doubles() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            initial_delay: Duration::from_millis(10),
+        };
+        assert_eq!(policy.delay(0), Duration::from_millis(10));
+        assert_eq!(policy.delay(2), Duration::from_millis(40));
+    }
+
+    #[tokio::test]
+    async fn failed_list_refresh_is_retried() {
+        let server = MockServer::start().await;
+        flaky_send(&server, "Milk", 1).await;
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+
+        let added = insert_items(
+            test_bot(&server),
+            chat,
+            &db,
+            &SharedState::new().unwrap(),
+            ["Milk".to_string()],
+        )
+        .await
+        .unwrap();
+        assert_eq!(added, 1);
+        assert_eq!(db.pending_refresh_chats().await.unwrap(), vec![chat]);
+
+        wait_for_sends(&server, 2).await;
+        assert!(db.pending_refresh_chats().await.unwrap().is_empty());
+        assert_eq!(db.get_last_list_message_id(chat).await.unwrap(), Some(7));
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn failed_notice_is_retried() {
+        let server = MockServer::start().await;
+        flaky_send(&server, "Archived", 1).await;
+        let db = init_test_db().await;
+        let bot = test_bot(&server);
+        let chat = ChatId(1);
+
+        send_notice_or_defer(&bot, &db, &SharedState::new().unwrap(), chat, "Archived")
+            .await
+            .unwrap();
+        assert_eq!(db.pending_refresh_chats().await.unwrap(), vec![chat]);
+        wait_for_sends(&server, 2).await;
+        assert!(db.pending_refresh_chats().await.unwrap().is_empty());
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn database_errors_still_propagate() {
+        let server = MockServer::start().await;
+        let db = init_test_db().await;
+        let result = defer_on_send_failure(
+            &test_bot(&server),
+            &db,
+            &SharedState::new().unwrap(),
+            ChatId(1),
+            Err(anyhow::anyhow!("disk full")),
+            PendingRefresh::default(),
+        )
+        .await;
+        assert!(result.is_err());
+        assert!(db.pending_refresh_chats().await.unwrap().is_empty());
+    }
+}
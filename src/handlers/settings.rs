@@ -0,0 +1,497 @@
+mod panel;
+
+pub use panel::{handle_settings_callback, open_settings_panel, SETTINGS_PREFIX};
+
+use anyhow::Result;
+use teloxide::prelude::*;
+
+use super::list_service::ListService;
+use super::message_tracker::MessageTracker;
+use crate::db::chat_settings::{
+    AUTO_ARCHIVE_AFTER_HOURS, DUPLICATE_DEFAULT, LIST_STYLE, RECEIPT_MODE, SKIP_LISTED_ITEMS,
+    STRICT_MODE, TIDY_USER_MESSAGES,
+};
+use crate::db::{Database, DuplicateAction};
+use crate::list_style::{ListStyle, ListStylePreset};
+use crate::messages::{
+    auto_archive_enabled_text, duplicates_default_text, AUTO_ARCHIVE_DISABLED, AUTO_ARCHIVE_USAGE,
+    DEDUPE_DISABLED, DEDUPE_ENABLED, DEDUPE_USAGE, DUPLICATES_USAGE, LIST_STYLE_INVALID,
+    LIST_STYLE_PREVIEW_HEADER, LIST_STYLE_UPDATED, RECEIPT_DISABLED, RECEIPT_ENABLED,
+    RECEIPT_USAGE, SETTINGS_USAGE, STRICT_DISABLED, STRICT_ENABLED, STRICT_USAGE, TIDY_DISABLED,
+    TIDY_ENABLED, TIDY_USAGE,
+};
+use crate::state::SharedState;
+
+fn style_preview_line(name: &str, style: &ListStyle) -> String {
+    format!(
+        "{name}: {} Milk  {} Bread  {} All done",
+        style.unchecked, style.checked, style.all_done
+    )
+}
+
+fn style_preview_text() -> String {
+    let lines: Vec<String> = ListStylePreset::ALL
+        .into_iter()
+        .map(|preset| style_preview_line(preset.name(), &preset.style()))
+        .collect();
+    format!("{LIST_STYLE_PREVIEW_HEADER}\n{}", lines.join("\n"))
+}
+
+/// Resolve `/settings style ...` arguments into the value to store.
+fn parse_style_args(args: &[&str]) -> Option<(String, ListStyle)> {
+    match args {
+        [name] => {
+            let preset = ListStylePreset::parse(name)?;
+            Some((preset.name().to_string(), preset.style()))
+        }
+        ["custom", markers @ ..] => {
+            let style = ListStyle::custom(&markers.join(" "))?;
+            Some((style.custom_setting_value(), style))
+        }
+        _ => None,
+    }
+}
+
+async fn handle_style(
+    bot: Bot,
+    msg: Message,
+    db: Database,
+    shared: SharedState,
+    args: &[&str],
+) -> Result<()> {
+    if args.is_empty() {
+        bot.send_message(msg.chat.id, style_preview_text()).await?;
+        return Ok(());
+    }
+
+    let Some((value, style)) = parse_style_args(args) else {
+        tracing::debug!(chat_id = msg.chat.id.0, ?args, "Invalid list style");
+        let text = format!("{LIST_STYLE_INVALID}\n\n{}", style_preview_text());
+        bot.send_message(msg.chat.id, text).await?;
+        return Ok(());
+    };
+
+    db.set_setting(msg.chat.id, LIST_STYLE, &value).await?;
+    tracing::info!(chat_id = msg.chat.id.0, style = %value, "List style updated");
+    let text = format!(
+        "{LIST_STYLE_UPDATED}\n{}",
+        style_preview_line(args[0], &style)
+    );
+    bot.send_message(msg.chat.id, text).await?;
+
+    refresh_list(&bot, &db, &shared, msg.chat.id).await
+}
+
+/// Redraw the tracked list message, e.g. after its markers changed.
+async fn refresh_list(
+    bot: &Bot,
+    db: &Database,
+    shared: &SharedState,
+    chat_id: ChatId,
+) -> Result<()> {
+    if let Some(list_message_id) = MessageTracker::new(db).current(chat_id).await? {
+        ListService::new(db, shared)
+            .update_message(bot, chat_id, list_message_id)
+            .await?;
+    }
+    Ok(())
+}
+
+async fn handle_tidy(bot: Bot, msg: Message, db: Database, args: &[&str]) -> Result<()> {
+    let enabled = match args {
+        [] => db.get_settings(msg.chat.id).await?.tidy_user_messages,
+        ["on"] => true,
+        ["off"] => false,
+        _ => {
+            bot.send_message(msg.chat.id, TIDY_USAGE).await?;
+            return Ok(());
+        }
+    };
+    if !args.is_empty() {
+        db.set_setting(msg.chat.id, TIDY_USER_MESSAGES, &enabled.to_string())
+            .await?;
+        tracing::info!(chat_id = msg.chat.id.0, enabled, "Tidy mode updated");
+    }
+    let text = if enabled { TIDY_ENABLED } else { TIDY_DISABLED };
+    bot.send_message(msg.chat.id, text).await?;
+    Ok(())
+}
+
+async fn handle_dedupe(bot: Bot, msg: Message, db: Database, args: &[&str]) -> Result<()> {
+    let enabled = match args {
+        [] => db.get_settings(msg.chat.id).await?.skip_listed_items,
+        ["on"] => true,
+        ["off"] => false,
+        _ => {
+            bot.send_message(msg.chat.id, DEDUPE_USAGE).await?;
+            return Ok(());
+        }
+    };
+    if !args.is_empty() {
+        db.set_setting(msg.chat.id, SKIP_LISTED_ITEMS, &enabled.to_string())
+            .await?;
+        tracing::info!(
+            chat_id = msg.chat.id.0,
+            enabled,
+            "Duplicate skipping updated"
+        );
+    }
+    let text = if enabled {
+        DEDUPE_ENABLED
+    } else {
+        DEDUPE_DISABLED
+    };
+    bot.send_message(msg.chat.id, text).await?;
+    Ok(())
+}
+
+async fn handle_strict(bot: Bot, msg: Message, db: Database, args: &[&str]) -> Result<()> {
+    let enabled = match args {
+        [] => db.get_settings(msg.chat.id).await?.strict_mode,
+        ["on"] => true,
+        ["off"] => false,
+        _ => {
+            bot.send_message(msg.chat.id, STRICT_USAGE).await?;
+            return Ok(());
+        }
+    };
+    if !args.is_empty() {
+        db.set_setting(msg.chat.id, STRICT_MODE, &enabled.to_string())
+            .await?;
+        tracing::info!(chat_id = msg.chat.id.0, enabled, "Strict mode updated");
+    }
+    let text = if enabled {
+        STRICT_ENABLED
+    } else {
+        STRICT_DISABLED
+    };
+    bot.send_message(msg.chat.id, text).await?;
+    Ok(())
+}
+
+/// `/receipt [on|off]` shows or switches whether photos are read as receipts.
+pub async fn handle_receipt(bot: Bot, msg: Message, db: Database, args: String) -> Result<()> {
+    let parts: Vec<&str> = args.split_whitespace().collect();
+    let enabled = match parts[..] {
+        [] => db.get_settings(msg.chat.id).await?.receipt_mode,
+        ["on"] => true,
+        ["off"] => false,
+        _ => {
+            bot.send_message(msg.chat.id, RECEIPT_USAGE).await?;
+            return Ok(());
+        }
+    };
+    if !parts.is_empty() {
+        db.set_setting(msg.chat.id, RECEIPT_MODE, &enabled.to_string())
+            .await?;
+        tracing::info!(chat_id = msg.chat.id.0, enabled, "Receipt mode updated");
+    }
+    let text = if enabled {
+        RECEIPT_ENABLED
+    } else {
+        RECEIPT_DISABLED
+    };
+    bot.send_message(msg.chat.id, text).await?;
+    Ok(())
+}
+
+async fn handle_duplicates(bot: Bot, msg: Message, db: Database, args: &[&str]) -> Result<()> {
+    let action = match args {
+        [] => Some(db.get_settings(msg.chat.id).await?.duplicate_default),
+        [name] => DuplicateAction::parse(name),
+        _ => None,
+    };
+    let Some(action) = action else {
+        bot.send_message(msg.chat.id, DUPLICATES_USAGE).await?;
+        return Ok(());
+    };
+    if !args.is_empty() {
+        db.set_setting(msg.chat.id, DUPLICATE_DEFAULT, action.name())
+            .await?;
+        tracing::info!(
+            chat_id = msg.chat.id.0,
+            action = action.name(),
+            "Duplicate default updated"
+        );
+    }
+    bot.send_message(msg.chat.id, duplicates_default_text(action.name()))
+        .await?;
+    Ok(())
+}
+
+/// `/settings autoarchive [hours|off]`; `0` turns it off like `off`.
+async fn handle_auto_archive(bot: Bot, msg: Message, db: Database, args: &[&str]) -> Result<()> {
+    let hours = match args {
+        [] => Some(db.get_settings(msg.chat.id).await?.auto_archive_after_hours),
+        ["off"] => Some(None),
+        [hours] => hours
+            .parse::<u32>()
+            .ok()
+            .map(|hours| (hours > 0).then_some(hours)),
+        _ => None,
+    };
+    let Some(hours) = hours else {
+        bot.send_message(msg.chat.id, AUTO_ARCHIVE_USAGE).await?;
+        return Ok(());
+    };
+    if !args.is_empty() {
+        db.set_setting(
+            msg.chat.id,
+            AUTO_ARCHIVE_AFTER_HOURS,
+            &hours.unwrap_or(0).to_string(),
+        )
+        .await?;
+        tracing::info!(chat_id = msg.chat.id.0, ?hours, "Auto-archive updated");
+    }
+    let text = match hours {
+        Some(hours) => auto_archive_enabled_text(hours),
+        None => AUTO_ARCHIVE_DISABLED.to_string(),
+    };
+    bot.send_message(msg.chat.id, text).await?;
+    Ok(())
+}
+
+pub async fn handle_settings(
+    bot: Bot,
+    msg: Message,
+    db: Database,
+    shared: SharedState,
+    args: String,
+) -> Result<()> {
+    let parts: Vec<&str> = args.split_whitespace().collect();
+    tracing::debug!(chat_id = msg.chat.id.0, ?parts, "Handling settings command");
+    match parts.split_first() {
+        Some((&"style", rest)) => handle_style(bot, msg, db, shared, rest).await,
+        Some((&"tidy", rest)) => handle_tidy(bot, msg, db, rest).await,
+        Some((&"strict", rest)) => handle_strict(bot, msg, db, rest).await,
+        Some((&"dedupe", rest)) => handle_dedupe(bot, msg, db, rest).await,
+        Some((&"duplicates", rest)) => handle_duplicates(bot, msg, db, rest).await,
+        Some((&"autoarchive", rest)) => handle_auto_archive(bot, msg, db, rest).await,
+        None => open_settings_panel(&bot, msg.chat.id, &db).await,
+        _ => {
+            bot.send_message(msg.chat.id, SETTINGS_USAGE).await?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::util::init_test_db;
+    use reqwest::Client;
+    use teloxide::types::ChatId;
+    use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn style_preview_lists_every_preset() {
+        let preview = style_preview_text();
+        for preset in ListStylePreset::ALL {
+            let style = preset.style();
+            assert!(preview.contains(&style_preview_line(preset.name(), &style)));
+        }
+    }
+
+    #[test]
+    fn parse_style_args_accepts_presets_and_custom() {
+        let (value, style) = parse_style_args(&["Minimal"]).unwrap();
+        assert_eq!(value, "minimal");
+        assert_eq!(style, ListStylePreset::Minimal.style());
+
+        let (value, style) = parse_style_args(&["custom", "○", "●", "★"]).unwrap();
+        assert_eq!(value, "custom:○ ● ★");
+        assert_eq!(style.unchecked, "○");
+
+        assert!(parse_style_args(&["custom", "○"]).is_none());
+        assert!(parse_style_args(&["neon"]).is_none());
+    }
+
+    #[tokio::test]
+    async fn settings_style_persists_choice() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"ok":true,"result":{"message_id":1,"date":0,"chat":{"id":1,"type":"private"}}}"#,
+                "application/json",
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = Client::builder().no_proxy().build().unwrap();
+        let bot = Bot::with_client("TEST", client)
+            .set_api_url(reqwest::Url::parse(&server.uri()).unwrap());
+        let db = init_test_db().await;
+        let msg: Message = serde_json::from_str(
+            r#"{"message_id":1,"date":0,"chat":{"id":1,"type":"private"},"text":"/settings style colorful"}"#,
+        )
+        .unwrap();
+
+        handle_settings(
+            bot,
+            msg,
+            db.clone(),
+            SharedState::new().unwrap(),
+            "style colorful".to_string(),
+        )
+        .await
+        .unwrap();
+
+        let settings = db.get_settings(ChatId(1)).await.unwrap();
+        assert_eq!(settings.list_style, ListStylePreset::Colorful.style());
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn settings_tidy_toggles_flag() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"ok":true,"result":{"message_id":1,"date":0,"chat":{"id":1,"type":"private"}}}"#,
+                "application/json",
+            ))
+            .expect(3)
+            .mount(&server)
+            .await;
+        let client = Client::builder().no_proxy().build().unwrap();
+        let bot = Bot::with_client("TEST", client)
+            .set_api_url(reqwest::Url::parse(&server.uri()).unwrap());
+        let db = init_test_db().await;
+        let msg: Message = serde_json::from_str(
+            r#"{"message_id":1,"date":0,"chat":{"id":1,"type":"private"},"text":"/settings tidy"}"#,
+        )
+        .unwrap();
+
+        handle_settings(
+            bot.clone(),
+            msg.clone(),
+            db.clone(),
+            SharedState::new().unwrap(),
+            "tidy on".into(),
+        )
+        .await
+        .unwrap();
+        assert!(db.get_settings(ChatId(1)).await.unwrap().tidy_user_messages);
+        handle_settings(
+            bot.clone(),
+            msg.clone(),
+            db.clone(),
+            SharedState::new().unwrap(),
+            "tidy maybe".into(),
+        )
+        .await
+        .unwrap();
+        assert!(db.get_settings(ChatId(1)).await.unwrap().tidy_user_messages);
+        handle_settings(
+            bot,
+            msg,
+            db.clone(),
+            SharedState::new().unwrap(),
+            "tidy off".into(),
+        )
+        .await
+        .unwrap();
+        assert!(!db.get_settings(ChatId(1)).await.unwrap().tidy_user_messages);
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn settings_duplicates_sets_default() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"ok":true,"result":{"message_id":1,"date":0,"chat":{"id":1,"type":"private"}}}"#,
+                "application/json",
+            ))
+            .expect(2)
+            .mount(&server)
+            .await;
+        let client = Client::builder().no_proxy().build().unwrap();
+        let bot = Bot::with_client("TEST", client)
+            .set_api_url(reqwest::Url::parse(&server.uri()).unwrap());
+        let db = init_test_db().await;
+        let msg: Message = serde_json::from_str(
+            r#"{"message_id":1,"date":0,"chat":{"id":1,"type":"private"},"text":"/settings duplicates"}"#,
+        )
+        .unwrap();
+
+        handle_settings(
+            bot.clone(),
+            msg.clone(),
+            db.clone(),
+            SharedState::new().unwrap(),
+            "duplicates add".into(),
+        )
+        .await
+        .unwrap();
+        let settings = db.get_settings(ChatId(1)).await.unwrap();
+        assert_eq!(settings.duplicate_default, DuplicateAction::Add);
+        handle_settings(
+            bot,
+            msg,
+            db.clone(),
+            SharedState::new().unwrap(),
+            "duplicates later".into(),
+        )
+        .await
+        .unwrap();
+        let settings = db.get_settings(ChatId(1)).await.unwrap();
+        assert_eq!(settings.duplicate_default, DuplicateAction::Add);
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn settings_autoarchive_sets_hours() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"ok":true,"result":{"message_id":1,"date":0,"chat":{"id":1,"type":"private"}}}"#,
+                "application/json",
+            ))
+            .expect(3)
+            .mount(&server)
+            .await;
+        let client = Client::builder().no_proxy().build().unwrap();
+        let bot = Bot::with_client("TEST", client)
+            .set_api_url(reqwest::Url::parse(&server.uri()).unwrap());
+        let db = init_test_db().await;
+        let msg: Message = serde_json::from_str(
+            r#"{"message_id":1,"date":0,"chat":{"id":1,"type":"private"},"text":"/settings autoarchive"}"#,
+        )
+        .unwrap();
+
+        handle_settings(
+            bot.clone(),
+            msg.clone(),
+            db.clone(),
+            SharedState::new().unwrap(),
+            "autoarchive 24".into(),
+        )
+        .await
+        .unwrap();
+        let settings = db.get_settings(ChatId(1)).await.unwrap();
+        assert_eq!(settings.auto_archive_after_hours, Some(24));
+        handle_settings(
+            bot.clone(),
+            msg.clone(),
+            db.clone(),
+            SharedState::new().unwrap(),
+            "autoarchive soon".into(),
+        )
+        .await
+        .unwrap();
+        let settings = db.get_settings(ChatId(1)).await.unwrap();
+        assert_eq!(settings.auto_archive_after_hours, Some(24));
+        handle_settings(
+            bot,
+            msg,
+            db.clone(),
+            SharedState::new().unwrap(),
+            "autoarchive off".into(),
+        )
+        .await
+        .unwrap();
+        let settings = db.get_settings(ChatId(1)).await.unwrap();
+        assert_eq!(settings.auto_archive_after_hours, None);
+        server.verify().await;
+    }
+}
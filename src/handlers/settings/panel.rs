@@ -0,0 +1,426 @@
+//! Inline `/settings` panel. Every row comes from [`SETTINGS`], so a new
+//! per-chat flag only needs a registry entry.
+
+use anyhow::Result;
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup, MaybeInaccessibleMessage, MessageId},
+};
+use tracing::Instrument;
+
+use super::refresh_list;
+use crate::db::chat_settings::{
+    CHECKED_LAST, DUPLICATE_DEFAULT, LIST_STYLE, SHOW_AUTHORS, TIDY_USER_MESSAGES, VOICE_FEEDBACK,
+};
+use crate::db::{ChatSettings, Database};
+use crate::list_style::ListStylePreset;
+use crate::messages::{settings_choice_text, SETTINGS_BACK_BUTTON, SETTINGS_PANEL_TEXT};
+use crate::state::SharedState;
+use crate::utils::{try_delete_message, try_edit_message};
+
+pub const SETTINGS_PREFIX: &str = "set_";
+const BACK: &str = "back";
+/// Seconds of inactivity before the panel removes itself.
+pub const SETTINGS_PANEL_TTL_SECS: u64 = 300;
+
+/// How a setting is changed from the panel.
+enum SettingKind {
+    /// Flipped between `true` and `false` in place.
+    Toggle,
+    /// Picked from a fixed list of stored values in a sub-menu.
+    Choice(&'static [&'static str]),
+}
+
+struct SettingEntry {
+    key: &'static str,
+    label: &'static str,
+    kind: SettingKind,
+    /// Current value in the form stored in `chat_settings`.
+    current: fn(&ChatSettings) -> String,
+}
+
+const STYLE_CHOICES: &[&str] = &["classic", "minimal", "colorful"];
+const DUPLICATE_CHOICES: &[&str] = &["uncheck", "add"];
+
+const SETTINGS: &[SettingEntry] = &[
+    SettingEntry {
+        key: VOICE_FEEDBACK,
+        label: "Voice feedback",
+        kind: SettingKind::Toggle,
+        current: |settings| settings.voice_feedback.to_string(),
+    },
+    SettingEntry {
+        key: TIDY_USER_MESSAGES,
+        label: "Tidy messages",
+        kind: SettingKind::Toggle,
+        current: |settings| settings.tidy_user_messages.to_string(),
+    },
+    SettingEntry {
+        key: LIST_STYLE,
+        label: "List style",
+        kind: SettingKind::Choice(STYLE_CHOICES),
+        current: |settings| {
+            ListStylePreset::ALL
+                .into_iter()
+                .find(|preset| preset.style() == settings.list_style)
+                .map_or("custom", ListStylePreset::name)
+                .to_string()
+        },
+    },
+    SettingEntry {
+        key: DUPLICATE_DEFAULT,
+        label: "Unanswered duplicates",
+        kind: SettingKind::Choice(DUPLICATE_CHOICES),
+        current: |settings| settings.duplicate_default.name().to_string(),
+    },
+    SettingEntry {
+        key: SHOW_AUTHORS,
+        label: "Show who added items",
+        kind: SettingKind::Toggle,
+        current: |settings| settings.show_authors.to_string(),
+    },
+    SettingEntry {
+        key: CHECKED_LAST,
+        label: "Checked items last",
+        kind: SettingKind::Toggle,
+        current: |settings| settings.checked_last.to_string(),
+    },
+];
+
+fn find_entry(key: &str) -> Option<&'static SettingEntry> {
+    SETTINGS.iter().find(|entry| entry.key == key)
+}
+
+fn button(label: String, data: String) -> Vec<InlineKeyboardButton> {
+    vec![InlineKeyboardButton::callback(label, data)]
+}
+
+fn panel_keyboard(settings: &ChatSettings) -> InlineKeyboardMarkup {
+    let rows = SETTINGS.iter().map(|entry| {
+        let value = (entry.current)(settings);
+        let label = match entry.kind {
+            SettingKind::Toggle => {
+                let mark = if value == "true" { "✅" } else { "❌" };
+                format!("{}: {mark}", entry.label)
+            }
+            SettingKind::Choice(_) => format!("{}: {value} ›", entry.label),
+        };
+        button(label, format!("{SETTINGS_PREFIX}{}", entry.key))
+    });
+    InlineKeyboardMarkup::new(rows)
+}
+
+fn choice_keyboard(entry: &SettingEntry, choices: &[&str], current: &str) -> InlineKeyboardMarkup {
+    let mut rows: Vec<_> = choices
+        .iter()
+        .map(|choice| {
+            let label = if *choice == current {
+                format!("• {choice}")
+            } else {
+                choice.to_string()
+            };
+            button(label, format!("{SETTINGS_PREFIX}{}={choice}", entry.key))
+        })
+        .collect();
+    rows.push(button(
+        SETTINGS_BACK_BUTTON.to_string(),
+        format!("{SETTINGS_PREFIX}{BACK}"),
+    ));
+    InlineKeyboardMarkup::new(rows)
+}
+
+/// Send a fresh panel, replacing any earlier one in the chat.
+pub async fn open_settings_panel(bot: &Bot, chat_id: ChatId, db: &Database) -> Result<()> {
+    let settings = db.get_settings(chat_id).await?;
+    let panel = bot
+        .send_message(chat_id, SETTINGS_PANEL_TEXT)
+        .reply_markup(panel_keyboard(&settings))
+        .await?;
+    let now = chrono::Utc::now().timestamp();
+    if let Some(previous) = db.open_settings_panel(chat_id, panel.id, now).await? {
+        try_delete_message(bot, chat_id, previous).await;
+    }
+    schedule_panel_expiry(
+        bot.clone(),
+        db.clone(),
+        chat_id,
+        panel.id,
+        SETTINGS_PANEL_TTL_SECS,
+    );
+    Ok(())
+}
+
+fn schedule_panel_expiry(bot: Bot, db: Database, chat_id: ChatId, message_id: MessageId, ttl: u64) {
+    tracing::debug!(
+        chat_id = chat_id.0,
+        message_id = message_id.0,
+        ttl_secs = ttl,
+        "Scheduling settings panel expiry"
+    );
+    tokio::spawn(
+        async move {
+            tokio::time::sleep(std::time::Duration::from_secs(ttl)).await;
+            let idle_since = chrono::Utc::now().timestamp() - ttl as i64;
+            match db.expire_settings_panel(chat_id, message_id, idle_since).await {
+                Ok(true) => try_delete_message(&bot, chat_id, message_id).await,
+                Ok(false) => {}
+                Err(err) => {
+                    tracing::warn!(error = %err, chat_id = chat_id.0, "Failed to expire settings panel")
+                }
+            }
+        }
+        .in_current_span(),
+    );
+}
+
+/// Apply a `set_...` button press and redraw the panel in place.
+pub async fn handle_settings_callback(
+    bot: &Bot,
+    msg: &MaybeInaccessibleMessage,
+    db: &Database,
+    shared: &SharedState,
+    data: &str,
+) -> Result<()> {
+    let chat_id = msg.chat().id;
+    let now = chrono::Utc::now().timestamp();
+    if !db.touch_settings_panel(chat_id, msg.id(), now).await? {
+        tracing::debug!(
+            chat_id = chat_id.0,
+            message_id = msg.id().0,
+            "Ignoring closed settings panel"
+        );
+        return Ok(());
+    }
+    schedule_panel_expiry(
+        bot.clone(),
+        db.clone(),
+        chat_id,
+        msg.id(),
+        SETTINGS_PANEL_TTL_SECS,
+    );
+
+    let (key, value) = match data.split_once('=') {
+        Some((key, value)) => (key, Some(value)),
+        None => (data, None),
+    };
+    let settings = db.get_settings(chat_id).await?;
+    if key == BACK {
+        try_edit_message(
+            bot,
+            chat_id,
+            msg.id(),
+            SETTINGS_PANEL_TEXT,
+            panel_keyboard(&settings),
+        )
+        .await;
+        return Ok(());
+    }
+    let Some(entry) = find_entry(key) else {
+        tracing::warn!(data, "Unknown settings callback");
+        return Ok(());
+    };
+
+    let new_value = match (&entry.kind, value) {
+        (SettingKind::Toggle, None) => Some(((entry.current)(&settings) != "true").to_string()),
+        (SettingKind::Choice(choices), None) => {
+            let current = (entry.current)(&settings);
+            let keyboard = choice_keyboard(entry, choices, &current);
+            try_edit_message(
+                bot,
+                chat_id,
+                msg.id(),
+                settings_choice_text(entry.label),
+                keyboard,
+            )
+            .await;
+            None
+        }
+        (SettingKind::Choice(choices), Some(value)) if choices.contains(&value) => {
+            Some(value.to_string())
+        }
+        _ => {
+            tracing::warn!(data, "Invalid settings value");
+            None
+        }
+    };
+    let Some(new_value) = new_value else {
+        return Ok(());
+    };
+
+    db.set_setting(chat_id, entry.key, &new_value).await?;
+    tracing::info!(chat_id = chat_id.0, key = entry.key, value = %new_value, "Setting changed from panel");
+    let settings = db.get_settings(chat_id).await?;
+    try_edit_message(
+        bot,
+        chat_id,
+        msg.id(),
+        SETTINGS_PANEL_TEXT,
+        panel_keyboard(&settings),
+    )
+    .await;
+    if entry.key == LIST_STYLE {
+        refresh_list(bot, db, shared, chat_id).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DeleteSessionTtl;
+    use crate::db::DuplicateAction;
+    use crate::handlers::callback_handler;
+    use crate::tests::util::{init_test_db, test_bot};
+    use teloxide::types::InlineKeyboardButtonKind;
+    use wiremock::{
+        matchers::{method, path, path_regex},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    const PANEL_MESSAGE: &str =
+        r#"{"ok":true,"result":{"message_id":7,"date":0,"chat":{"id":1,"type":"private"}}}"#;
+    const OK_TRUE: &str = r#"{"ok":true,"result":true}"#;
+
+    async fn panel_server() -> MockServer {
+        let server = MockServer::start().await;
+        for endpoint in ["/botTEST/SendMessage", "/botTEST/EditMessageText"] {
+            Mock::given(method("POST"))
+                .and(path(endpoint))
+                .respond_with(
+                    ResponseTemplate::new(200).set_body_raw(PANEL_MESSAGE, "application/json"),
+                )
+                .mount(&server)
+                .await;
+        }
+        Mock::given(method("POST"))
+            .and(path_regex(
+                r"^/botTEST/([Dd]eleteMessage|AnswerCallbackQuery)$",
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(OK_TRUE, "application/json"))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    fn press(data: &str) -> CallbackQuery {
+        serde_json::from_str(&format!(
+            r#"{{"id":"q","from":{{"id":1,"is_bot":false,"first_name":"Ann"}},"chat_instance":"c","data":"{data}","message":{{"message_id":7,"date":0,"chat":{{"id":1,"type":"private"}},"text":"Settings"}}}}"#
+        ))
+        .unwrap()
+    }
+
+    fn callback_data(keyboard: &InlineKeyboardMarkup) -> Vec<String> {
+        keyboard
+            .inline_keyboard
+            .iter()
+            .flatten()
+            .filter_map(|button| match &button.kind {
+                InlineKeyboardButtonKind::CallbackData(data) => Some(data.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn registry_choices_match_setting_values() {
+        let presets: Vec<_> = ListStylePreset::ALL.map(ListStylePreset::name).to_vec();
+        assert_eq!(STYLE_CHOICES, presets.as_slice());
+        for choice in DUPLICATE_CHOICES {
+            assert!(DuplicateAction::parse(choice).is_some());
+        }
+        assert_eq!(DUPLICATE_CHOICES.len(), DuplicateAction::ALL.len());
+    }
+
+    #[test]
+    fn callback_data_fits_telegram_limit() {
+        let keyboard = panel_keyboard(&ChatSettings::default());
+        let mut data = callback_data(&keyboard);
+        for entry in SETTINGS {
+            if let SettingKind::Choice(choices) = entry.kind {
+                data.extend(callback_data(&choice_keyboard(entry, choices, "")));
+            }
+        }
+        assert!(data.iter().all(|data| data.len() <= 64));
+        assert!(data.contains(&"set_tidy_user_messages".to_string()));
+        assert!(data.contains(&"set_list_style=minimal".to_string()));
+    }
+
+    #[tokio::test]
+    async fn toggles_and_choices_persist() {
+        let server = panel_server().await;
+        let bot = test_bot(&server);
+        let db = init_test_db().await;
+        open_settings_panel(&bot, ChatId(1), &db).await.unwrap();
+
+        for data in [
+            "set_tidy_user_messages",
+            "set_voice_feedback",
+            "set_duplicate_default",
+            "set_duplicate_default=add",
+            "set_list_style=colorful",
+            "set_list_style=fancy",
+        ] {
+            callback_handler(
+                bot.clone(),
+                press(data),
+                db.clone(),
+                SharedState::new().unwrap(),
+                0,
+                DeleteSessionTtl::default(),
+            )
+            .await
+            .unwrap();
+        }
+
+        let settings = db.get_settings(ChatId(1)).await.unwrap();
+        assert!(settings.tidy_user_messages);
+        assert!(!settings.voice_feedback);
+        assert_eq!(settings.duplicate_default, DuplicateAction::Add);
+        assert_eq!(settings.list_style, ListStylePreset::Colorful.style());
+
+        callback_handler(
+            bot,
+            press("set_tidy_user_messages"),
+            db.clone(),
+            SharedState::new().unwrap(),
+            0,
+            DeleteSessionTtl::default(),
+        )
+        .await
+        .unwrap();
+        assert!(!db.get_settings(ChatId(1)).await.unwrap().tidy_user_messages);
+    }
+
+    #[tokio::test]
+    async fn idle_panel_is_deleted_and_ignored() {
+        let server = panel_server().await;
+        let bot = test_bot(&server);
+        let db = init_test_db().await;
+        db.open_settings_panel(ChatId(1), MessageId(7), 0)
+            .await
+            .unwrap();
+
+        schedule_panel_expiry(bot.clone(), db.clone(), ChatId(1), MessageId(7), 0);
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        let deletes = server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .filter(|request| request.url.path().ends_with("eleteMessage"))
+            .count();
+        assert_eq!(deletes, 1);
+
+        callback_handler(
+            bot,
+            press("set_tidy_user_messages"),
+            db.clone(),
+            SharedState::new().unwrap(),
+            0,
+            DeleteSessionTtl::default(),
+        )
+        .await
+        .unwrap();
+        assert!(!db.get_settings(ChatId(1)).await.unwrap().tidy_user_messages);
+    }
+}
@@ -0,0 +1,90 @@
+use anyhow::Result;
+use teloxide::prelude::*;
+
+use super::tokens::generate_token;
+use crate::config::PublicUrl;
+use crate::db::Database;
+use crate::messages::{share_link_text, SHARE_LINK_NONE, SHARE_LINK_REVOKED, SHARE_LINK_USAGE};
+
+/// A parsed `/sharelink` invocation.
+#[derive(Debug, PartialEq)]
+enum ShareLinkCommand {
+    Show,
+    Revoke,
+}
+
+fn parse_share_link_command(text: &str) -> Option<ShareLinkCommand> {
+    match text.trim() {
+        "" => Some(ShareLinkCommand::Show),
+        "revoke" => Some(ShareLinkCommand::Revoke),
+        _ => None,
+    }
+}
+
+/// Handle `/sharelink`, which reuses the chat's live link or creates one, and
+/// `/sharelink revoke`.
+pub async fn handle_share_link(
+    bot: Bot,
+    msg: Message,
+    db: Database,
+    public_url: PublicUrl,
+    args: String,
+) -> Result<()> {
+    let chat_id = msg.chat.id;
+    let Some(command) = parse_share_link_command(&args) else {
+        bot.send_message(chat_id, SHARE_LINK_USAGE).await?;
+        return Ok(());
+    };
+    tracing::debug!(chat_id = chat_id.0, ?command, "Share link command");
+
+    match command {
+        ShareLinkCommand::Show => {
+            let slug = match db.active_share_link(chat_id).await? {
+                Some(slug) => slug,
+                None => {
+                    let slug = generate_token();
+                    db.create_share_link(chat_id, &slug, chrono::Utc::now().timestamp())
+                        .await?;
+                    slug
+                }
+            };
+            let url = public_url.link(&format!("/share/{slug}"));
+            bot.send_message(chat_id, share_link_text(&url, public_url.is_configured()))
+                .await?;
+        }
+        ShareLinkCommand::Revoke => {
+            let revoked = db
+                .revoke_share_links(chat_id, chrono::Utc::now().timestamp())
+                .await?;
+            let response = if revoked > 0 {
+                SHARE_LINK_REVOKED
+            } else {
+                SHARE_LINK_NONE
+            };
+            bot.send_message(chat_id, response).await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_subcommands() {
+        assert_eq!(parse_share_link_command(""), Some(ShareLinkCommand::Show));
+        assert_eq!(
+            parse_share_link_command(" revoke "),
+            Some(ShareLinkCommand::Revoke)
+        );
+        assert_eq!(parse_share_link_command("delete"), None);
+    }
+
+    #[test]
+    fn links_use_the_public_url_when_set() {
+        let url = PublicUrl::new(Some("https://list.example/".into()));
+        assert_eq!(url.link("/share/abc"), "https://list.example/share/abc");
+        assert_eq!(PublicUrl::default().link("/share/abc"), "/share/abc");
+    }
+}
@@ -0,0 +1,169 @@
+use anyhow::Result;
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup, MaybeInaccessibleMessage, User},
+};
+
+use super::list::insert_items_with_log;
+use crate::db::{Database, ItemAuthor, TopItem};
+use crate::state::SharedState;
+
+/// Callback prefix for the quick-add buttons shown under an empty list.
+pub const SUGGEST_PREFIX: &str = "suggest_";
+/// Number of frequently added items offered as quick-add buttons.
+pub const SUGGESTION_LIMIT: i64 = 5;
+
+/// One row of quick-add buttons, or `None` when there is nothing to suggest.
+pub fn suggestion_keyboard(items: &[TopItem]) -> Option<InlineKeyboardMarkup> {
+    if items.is_empty() {
+        return None;
+    }
+    let row: Vec<InlineKeyboardButton> = items
+        .iter()
+        .map(|item| {
+            InlineKeyboardButton::callback(
+                format!("➕ {}", item.text),
+                format!("{SUGGEST_PREFIX}{}", item.id),
+            )
+        })
+        .collect();
+    Some(InlineKeyboardMarkup::new(vec![row]))
+}
+
+/// Add the suggested item behind a `suggest_<id>` button and show the list.
+pub async fn handle_suggest_callback(
+    bot: &Bot,
+    msg: &MaybeInaccessibleMessage,
+    user: &User,
+    db: &Database,
+    shared: &SharedState,
+    data: &str,
+) -> Result<()> {
+    let Ok(id) = data.parse::<i64>() else {
+        tracing::debug!(data, "Ignoring malformed suggestion callback");
+        return Ok(());
+    };
+    let chat_id = msg.chat().id;
+    let Some(text) = db.history_text(chat_id, id).await? else {
+        tracing::debug!(chat_id = chat_id.0, id, "Suggestion no longer known");
+        return Ok(());
+    };
+    tracing::debug!(chat_id = chat_id.0, text = %text, "Adding suggested item");
+    let author = ItemAuthor::from(user);
+    insert_items_with_log(
+        bot.clone(),
+        chat_id,
+        db,
+        shared,
+        [text],
+        "via suggestion",
+        Some(&author),
+    )
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::util::{init_test_db, test_bot, SENT_MESSAGE};
+    use teloxide::types::InlineKeyboardButtonKind;
+    use wiremock::{
+        matchers::{body_string_contains, method, path, path_regex},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[test]
+    fn suggestion_buttons_share_one_row() {
+        assert!(suggestion_keyboard(&[]).is_none());
+        let items = [
+            TopItem {
+                id: 3,
+                text: "Milk".into(),
+                count: 4,
+            },
+            TopItem {
+                id: 8,
+                text: "Bread".into(),
+                count: 2,
+            },
+        ];
+        let keyboard = suggestion_keyboard(&items).unwrap();
+        assert_eq!(keyboard.inline_keyboard.len(), 1);
+        let button = &keyboard.inline_keyboard[0][1];
+        assert_eq!(button.text, "➕ Bread");
+        match &button.kind {
+            InlineKeyboardButtonKind::CallbackData(data) => assert_eq!(data, "suggest_8"),
+            _ => panic!("expected callback data"),
+        }
+    }
+
+    #[tokio::test]
+    async fn tapping_a_suggestion_adds_it() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SendMessage"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(SENT_MESSAGE, "application/json"))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path_regex(r"^/botTEST/[Dd]eleteMessage$"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(r#"{"ok":true,"result":true}"#, "application/json"),
+            )
+            .mount(&server)
+            .await;
+
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_item_count(chat, "Milk").await.unwrap();
+        db.archive_items(chat, 100).await.unwrap();
+        let id = db.top_items(chat, SUGGESTION_LIMIT).await.unwrap()[0].id;
+
+        let msg: MaybeInaccessibleMessage =
+            serde_json::from_str(r#"{"message_id":9,"date":0,"chat":{"id":1,"type":"private"}}"#)
+                .unwrap();
+        let user: User =
+            serde_json::from_str(r#"{"id":5,"is_bot":false,"first_name":"Anna"}"#).unwrap();
+        handle_suggest_callback(
+            &test_bot(&server),
+            &msg,
+            &user,
+            &db,
+            &SharedState::new().unwrap(),
+            &id.to_string(),
+        )
+        .await
+        .unwrap();
+
+        let items = db.list_items(chat).await.unwrap();
+        assert_eq!(items[0].text, "Milk");
+        assert_eq!(items[0].added_by_name.as_deref(), Some("Anna"));
+        assert_eq!(db.top_items(chat, 1).await.unwrap()[0].count, 2);
+    }
+
+    #[tokio::test]
+    async fn empty_list_offers_frequent_items() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SendMessage"))
+            .and(body_string_contains("suggest_"))
+            .and(body_string_contains("Bread"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(SENT_MESSAGE, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_item_count(chat, "Bread").await.unwrap();
+        db.archive_items(chat, 100).await.unwrap();
+
+        crate::handlers::ListService::new(&db, &SharedState::new().unwrap())
+            .send_list(test_bot(&server), chat)
+            .await
+            .unwrap();
+        server.verify().await;
+    }
+}
@@ -1,40 +1,169 @@
 use crate::db::Database;
 use anyhow::Result;
-use teloxide::prelude::*;
+use std::collections::HashSet;
+use teloxide::{prelude::*, types::MessageId};
+use tracing::Instrument;
 
 use crate::ai::config::AiConfig;
-use crate::ai::gpt::parse_items_gpt;
-use crate::messages::{GPT_PARSING_DISABLED, HELP_TEXT};
-use crate::text_utils::parse_item_line;
+use crate::ai::gpt::parse_categorized_items_gpt;
+use crate::ai::prompts::PromptKind;
+use crate::messages::GPT_PARSING_DISABLED;
+use crate::notify::{ListEvent, ListEventKind};
+use crate::render;
+use crate::state::SharedState;
+use crate::text_utils::{normalize_for_match, parse_item_line};
+use crate::utils::{topic_of, InTopic};
 
-use super::list::{insert_capitalized_items_with_log, insert_items_with_log};
-use super::parse::parse_items_with_fallback;
+use super::delete::refresh_delete_panels;
+use super::duplicates::skip_listed_items;
+use super::edit::apply_pending_edit;
+use super::list::{insert_categorized_items_with_log, insert_message_items};
+use super::list_service::ListService;
+use super::message_tracker::MessageTracker;
+use super::parse::{is_list_message, parse_items_with_fallback};
+use super::permissions::delete_user_message;
 
 pub async fn help(bot: Bot, msg: Message) -> Result<()> {
-    bot.send_message(msg.chat.id, HELP_TEXT)
+    bot.send_message(msg.chat.id, render::help())
+        .in_topic(topic_of(&msg))
         .parse_mode(teloxide::types::ParseMode::Html)
         .await?;
     Ok(())
 }
 
-pub async fn add_items_from_text(bot: Bot, msg: Message, db: Database) -> Result<()> {
+pub async fn add_items_from_text(
+    bot: Bot,
+    msg: Message,
+    db: Database,
+    shared: SharedState,
+    ai_config: Option<AiConfig>,
+    delete_after_timeout: u64,
+) -> Result<()> {
     let Some(text) = msg.text() else {
         return Ok(());
     };
+    if apply_pending_edit(&bot, &msg, &db, &shared).await? {
+        return Ok(());
+    }
+    let settings = db.get_settings(msg.chat.id).await?;
+    if settings.strict_mode && !is_list_message(text, ai_config.as_ref()).await {
+        tracing::debug!(
+            chat_id = msg.chat.id.0,
+            message_id = msg.id.0,
+            "Strict mode: ignoring conversational message"
+        );
+        return Ok(());
+    }
     let items: Vec<String> = text.lines().filter_map(parse_item_line).collect();
+    if !items.is_empty() {
+        ListService::new(&db, &shared).follow_topic(&msg).await?;
+    }
+
+    let added =
+        insert_message_items(bot.clone(), &db, &shared, &msg, items, "via text message").await?;
+    if added > 0 && settings.tidy_user_messages {
+        schedule_tidy(bot, db, msg.chat.id, msg.id, delete_after_timeout);
+    }
+    Ok(())
+}
+
+/// Bring the list in line with an edited text message: new lines are added
+/// and unchecked items the message created but no longer lists are removed.
+/// Edits of messages that added nothing to the current list are ignored.
+pub async fn update_items_from_edit(
+    bot: Bot,
+    msg: Message,
+    db: Database,
+    shared: SharedState,
+) -> Result<()> {
+    let Some(text) = msg.text() else {
+        return Ok(());
+    };
+    let chat_id = msg.chat.id;
+    let Some(recorded) = db.message_items(chat_id, msg.id).await? else {
+        tracing::debug!(
+            chat_id = chat_id.0,
+            message_id = msg.id.0,
+            "Ignoring edit of a message without items on the current list"
+        );
+        return Ok(());
+    };
+    let lines: Vec<String> = text.lines().filter_map(parse_item_line).collect();
+    let wanted: HashSet<String> = lines.iter().map(|line| normalize_for_match(line)).collect();
+    let known: HashSet<String> = recorded
+        .iter()
+        .map(|(item, _)| normalize_for_match(&item.text))
+        .collect();
+    let stale: Vec<i64> = recorded
+        .iter()
+        .filter(|(item, created)| {
+            *created && !item.done && !wanted.contains(&normalize_for_match(&item.text))
+        })
+        .map(|(item, _)| item.id)
+        .collect();
+    let fresh: Vec<String> = lines
+        .into_iter()
+        .filter(|line| !known.contains(&normalize_for_match(line)))
+        .collect();
+    tracing::debug!(
+        chat_id = chat_id.0,
+        message_id = msg.id.0,
+        added = fresh.len(),
+        removed = stale.len(),
+        "Applying message edit"
+    );
 
-    let _added = insert_items_with_log(bot, msg.chat.id, &db, items, "via text message").await?;
+    let removed = db.delete_items_count(chat_id, &stale).await?;
+    if removed > 0 {
+        shared.events.list_changed(
+            &db,
+            ListEvent::batch(chat_id, ListEventKind::Delete, removed),
+        );
+        refresh_delete_panels(&bot, &db, chat_id).await?;
+    }
+    let added =
+        insert_message_items(bot.clone(), &db, &shared, &msg, fresh, "via edited message").await?;
+    if removed > 0 && added == 0 {
+        if let Some(list_id) = MessageTracker::new(&db).current(chat_id).await? {
+            ListService::new(&db, &shared)
+                .update_message(&bot, chat_id, list_id)
+                .await?;
+        }
+    }
     Ok(())
 }
 
+/// Delete the user's message after `secs` so the sender still sees it land.
+fn schedule_tidy(bot: Bot, db: Database, chat_id: ChatId, message_id: MessageId, secs: u64) {
+    tracing::debug!(
+        chat_id = chat_id.0,
+        message_id = message_id.0,
+        delay_secs = secs,
+        "Scheduling user message cleanup"
+    );
+    tokio::spawn(
+        async move {
+            tokio::time::sleep(std::time::Duration::from_secs(secs)).await;
+            if let Err(err) = delete_user_message(&bot, &db, chat_id, message_id).await {
+                tracing::warn!(error = %err, chat_id = chat_id.0, "Failed to tidy user message");
+            }
+        }
+        .in_current_span(),
+    );
+}
+
 pub async fn add_items_from_parsed_text(
     bot: Bot,
     msg: Message,
     db: Database,
+    shared: SharedState,
     ai_config: Option<AiConfig>,
 ) -> Result<()> {
-    let Some(config) = ai_config else {
-        bot.send_message(msg.chat.id, GPT_PARSING_DISABLED).await?;
+    let Some(config) = ai_config.filter(|config| config.enable_gpt_parse) else {
+        tracing::debug!(chat_id = msg.chat.id.0, "GPT parsing disabled");
+        bot.send_message(msg.chat.id, GPT_PARSING_DISABLED)
+            .in_topic(topic_of(&msg))
+            .await?;
         return Ok(());
     };
 
@@ -42,20 +171,304 @@ pub async fn add_items_from_parsed_text(
         return Ok(());
     };
 
+    let settings = db.get_settings(msg.chat.id).await?;
+    let prompts = config.prompts.overridden_by(&settings.prompts);
+    let parsed = parse_categorized_items_gpt(
+        &config.api_key,
+        &config.gpt_model,
+        prompts.get(PromptKind::Text),
+        text,
+        config.openai_chat_url.as_deref(),
+        &config.openai,
+    )
+    .await;
+    let categories: Vec<(String, String)> = parsed
+        .iter()
+        .flatten()
+        .filter_map(|item| Some((item.text.clone(), item.category.clone()?)))
+        .collect();
     let items = parse_items_with_fallback(
         text,
-        parse_items_gpt(
-            &config.api_key,
-            &config.gpt_model,
-            text,
-            config.openai_chat_url.as_deref(),
-        )
-        .await,
+        parsed.map(|items| items.into_iter().map(|item| item.text).collect()),
         "gpt_parse",
     );
+    let items = skip_listed_items(&db, msg.chat.id, items, String::as_str).await?;
+    tracing::debug!(
+        chat_id = msg.chat.id.0,
+        categorized = categories.len(),
+        "Parsed categories via GPT"
+    );
 
-    let _added =
-        insert_capitalized_items_with_log(bot, msg.chat.id, &db, items, "via /parse").await?;
+    let _added = insert_categorized_items_with_log(
+        bot,
+        &db,
+        &shared,
+        &msg,
+        items,
+        &categories,
+        "via /parse",
+    )
+    .await?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::chat_settings::{STRICT_MODE, TIDY_USER_MESSAGES};
+    use crate::db::BotCapabilities;
+    use crate::messages::DELETE_RIGHTS_NEEDED;
+    use crate::tests::util::{init_test_db, test_bot, SENT_MESSAGE};
+    use wiremock::{
+        matchers::{body_string_contains, method, path, path_regex},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn group_message(text: &str) -> Message {
+        serde_json::from_str(&format!(
+            r#"{{"message_id":42,"date":0,"chat":{{"id":-5,"type":"group","title":"Family"}},"text":"{text}"}}"#
+        ))
+        .unwrap()
+    }
+
+    async fn list_server(delete_status: u16, delete_calls: u64) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path_regex(r"^/botTEST/[Dd]eleteMessage$"))
+            .and(body_string_contains(r#""message_id":42"#))
+            .respond_with(ResponseTemplate::new(delete_status).set_body_raw(
+                if delete_status == 200 {
+                    r#"{"ok":true,"result":true}"#
+                } else {
+                    r#"{"ok":false,"error_code":400,"description":"Bad Request: message can't be deleted"}"#
+                },
+                "application/json",
+            ))
+            .expect(delete_calls)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path_regex(r"^/botTEST/[Dd]eleteMessage$"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(r#"{"ok":true,"result":true}"#, "application/json"),
+            )
+            .mount(&server)
+            .await;
+        server
+    }
+
+    async fn expect_message(server: &MockServer, text: &str, times: u64) {
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SendMessage"))
+            .and(body_string_contains(text))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(SENT_MESSAGE, "application/json"))
+            .expect(times)
+            .mount(server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SendMessage"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(SENT_MESSAGE, "application/json"))
+            .mount(server)
+            .await;
+    }
+
+    async fn settle() {
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    }
+
+    #[tokio::test]
+    async fn user_message_kept_when_tidy_off() {
+        let server = list_server(200, 0).await;
+        expect_message(&server, "Milk", 1).await;
+        let db = init_test_db().await;
+
+        add_items_from_text(
+            test_bot(&server),
+            group_message("Milk"),
+            db,
+            SharedState::new().unwrap(),
+            None,
+            0,
+        )
+        .await
+        .unwrap();
+        settle().await;
+    }
+
+    #[tokio::test]
+    async fn user_message_deleted_when_tidy_on() {
+        let server = list_server(200, 1).await;
+        expect_message(&server, "Milk", 1).await;
+        let db = init_test_db().await;
+        db.set_setting(ChatId(-5), TIDY_USER_MESSAGES, "true")
+            .await
+            .unwrap();
+
+        add_items_from_text(
+            test_bot(&server),
+            group_message("Milk"),
+            db,
+            SharedState::new().unwrap(),
+            None,
+            0,
+        )
+        .await
+        .unwrap();
+        settle().await;
+    }
+
+    #[tokio::test]
+    async fn tidy_skipped_without_delete_rights() {
+        let server = list_server(200, 0).await;
+        expect_message(&server, DELETE_RIGHTS_NEEDED, 1).await;
+        let db = init_test_db().await;
+        db.set_setting(ChatId(-5), TIDY_USER_MESSAGES, "true")
+            .await
+            .unwrap();
+        let no_delete = BotCapabilities {
+            can_delete: false,
+            can_pin: false,
+        };
+        db.set_bot_capabilities(ChatId(-5), no_delete, 0)
+            .await
+            .unwrap();
+
+        add_items_from_text(
+            test_bot(&server),
+            group_message("Milk"),
+            db.clone(),
+            SharedState::new().unwrap(),
+            None,
+            0,
+        )
+        .await
+        .unwrap();
+        settle().await;
+        assert!(
+            db.get_settings(ChatId(-5))
+                .await
+                .unwrap()
+                .tidy_user_messages
+        );
+    }
+
+    async fn texts(db: &Database) -> Vec<String> {
+        db.list_items(ChatId(-5))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|item| item.text)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn edited_message_updates_its_items() {
+        let server = list_server(200, 0).await;
+        expect_message(&server, "Butter", 1).await;
+        let db = init_test_db().await;
+        let bot = test_bot(&server);
+        add_items_from_text(
+            bot.clone(),
+            group_message("Milk\\nEggs"),
+            db.clone(),
+            SharedState::new().unwrap(),
+            None,
+            0,
+        )
+        .await
+        .unwrap();
+
+        update_items_from_edit(
+            bot.clone(),
+            group_message("Milk\\nButter"),
+            db.clone(),
+            SharedState::new().unwrap(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(texts(&db).await, ["Milk", "Butter"]);
+
+        let milk = db.list_items(ChatId(-5)).await.unwrap()[0].id;
+        db.toggle_item_count(ChatId(-5), milk).await.unwrap();
+        update_items_from_edit(
+            bot,
+            group_message("Butter"),
+            db.clone(),
+            SharedState::new().unwrap(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(texts(&db).await, ["Milk", "Butter"]);
+        settle().await;
+    }
+
+    #[tokio::test]
+    async fn edits_from_before_the_last_archive_are_ignored() {
+        let server = list_server(200, 0).await;
+        expect_message(&server, "Tea", 0).await;
+        let db = init_test_db().await;
+        let bot = test_bot(&server);
+        add_items_from_text(
+            bot.clone(),
+            group_message("Milk"),
+            db.clone(),
+            SharedState::new().unwrap(),
+            None,
+            0,
+        )
+        .await
+        .unwrap();
+        db.archive_items(ChatId(-5), 1).await.unwrap();
+
+        update_items_from_edit(
+            bot,
+            group_message("Milk\\nTea"),
+            db.clone(),
+            SharedState::new().unwrap(),
+        )
+        .await
+        .unwrap();
+        assert!(texts(&db).await.is_empty());
+        settle().await;
+    }
+
+    #[tokio::test]
+    async fn strict_mode_skips_chatter() {
+        let server = list_server(200, 0).await;
+        expect_message(&server, "Milk", 1).await;
+        let db = init_test_db().await;
+        db.set_setting(ChatId(-5), STRICT_MODE, "true")
+            .await
+            .unwrap();
+        let bot = test_bot(&server);
+
+        for text in ["anyone going to the shop after work today", "need milk?"] {
+            add_items_from_text(
+                bot.clone(),
+                group_message(text),
+                db.clone(),
+                SharedState::new().unwrap(),
+                None,
+                0,
+            )
+            .await
+            .unwrap();
+        }
+        assert!(texts(&db).await.is_empty());
+
+        add_items_from_text(
+            bot,
+            group_message("Milk"),
+            db.clone(),
+            SharedState::new().unwrap(),
+            None,
+            0,
+        )
+        .await
+        .unwrap();
+        assert_eq!(texts(&db).await, ["Milk"]);
+        settle().await;
+    }
+}
@@ -5,18 +5,21 @@ use rand::rngs::OsRng;
 use rand::TryRngCore;
 use teloxide::prelude::*;
 use teloxide::types::{ParseMode, User};
-use teloxide::utils::html::escape;
 
-use crate::db::{Database, TokenRecord};
+use super::permissions::delete_user_message;
+use crate::db::tokens::token_preview;
+use crate::db::{Database, NewToken, TokenRecord, TokenScope};
 use crate::messages::{
-    TOKENS_EMPTY, TOKEN_ISSUED, TOKEN_NOT_FOUND, TOKEN_REVOKED, TOKEN_REVOKE_USAGE,
+    TOKENS_EMPTY, TOKEN_AMBIGUOUS, TOKEN_DM_FAILED, TOKEN_ISSUED, TOKEN_NOT_FOUND, TOKEN_REVOKED,
+    TOKEN_REVOKE_USAGE,
 };
+use crate::render::escape;
 
 fn now_timestamp() -> i64 {
     chrono::Utc::now().timestamp()
 }
 
-fn generate_token() -> String {
+pub(crate) fn generate_token() -> String {
     let mut bytes = [0u8; 32];
     OsRng
         .try_fill_bytes(&mut bytes)
@@ -24,10 +27,6 @@ fn generate_token() -> String {
     URL_SAFE_NO_PAD.encode(bytes)
 }
 
-fn token_preview(token: &str) -> String {
-    token.chars().take(6).collect()
-}
-
 fn format_timestamp(timestamp: Option<i64>, fallback: &str) -> String {
     timestamp
         .and_then(|value| chrono::DateTime::<chrono::Utc>::from_timestamp(value, 0))
@@ -35,12 +34,16 @@ fn format_timestamp(timestamp: Option<i64>, fallback: &str) -> String {
         .unwrap_or_else(|| fallback.to_string())
 }
 
-fn format_token_list(tokens: &[TokenRecord]) -> String {
+fn format_token_list(tokens: &[TokenRecord], now: i64) -> String {
     let mut lines = Vec::new();
     for token in tokens {
         let issued = format_timestamp(Some(token.issued_at), "unknown");
         let last_used = format_timestamp(token.last_used_at, "never");
         let revoked = format_timestamp(token.revoked_at, "not revoked");
+        let mut expires = format_timestamp(token.expires_at, "never");
+        if token.is_expired(now) {
+            expires.push_str(" (expired)");
+        }
         let name = token
             .name
             .as_deref()
@@ -55,23 +58,101 @@ fn format_token_list(tokens: &[TokenRecord]) -> String {
             (None, None) => String::new(),
         };
         lines.push(format!(
-            "<code>{}</code>\n{name}{issuer}issued: {issued}\nlast used: {last_used}\nrevoked: {revoked}",
-            token.token,
+            "<code>{}…</code>\nid: {}\n{name}{issuer}issued: {issued}\nlast used: {last_used}\nrevoked: {revoked}\nexpires: {expires}\nscope: {}",
+            escape(&token.token_preview),
+            token.id,
+            token.scope().name(),
         ));
     }
     format!("<b>Tokens</b>\n\n{}", lines.join("\n\n"))
 }
 
-fn parse_token_name(text: &str) -> Option<String> {
-    let trimmed = text.trim();
-    if trimmed.is_empty() {
-        None
+/// Which of a chat's tokens a `/revoke_token` argument names.
+#[derive(Debug, PartialEq, Eq)]
+enum TokenMatch {
+    One(i64),
+    Ambiguous,
+    None,
+}
+
+/// Find the active token named by `arg`: its id from `/tokens`, or its
+/// preview when no other active token shares it.
+fn match_token(tokens: &[TokenRecord], arg: &str) -> TokenMatch {
+    let active = || tokens.iter().filter(|token| token.revoked_at.is_none());
+    if let Ok(id) = arg.parse::<i64>() {
+        if active().any(|token| token.id == id) {
+            return TokenMatch::One(id);
+        }
+    }
+    let preview = arg.trim_end_matches('…');
+    let mut matches = active().filter(|token| token.token_preview == preview);
+    match (matches.next(), matches.next()) {
+        (Some(token), None) => TokenMatch::One(token.id),
+        (Some(_), Some(_)) => TokenMatch::Ambiguous,
+        (None, _) => TokenMatch::None,
+    }
+}
+
+/// Parse a lifetime such as `30m`, `12h`, `7d` or `2w` into seconds.
+fn parse_token_lifetime(text: &str) -> Option<i64> {
+    let unit = text.chars().last()?;
+    let multiplier = match unit.to_ascii_lowercase() {
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        'w' => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+    let amount: i64 = text[..text.len() - unit.len_utf8()].parse().ok()?;
+    if amount <= 0 {
+        return None;
+    }
+    amount.checked_mul(multiplier)
+}
+
+/// Flag that issues a token which can only read the list.
+const READ_ONLY_FLAG: &str = "--readonly";
+/// Flag that issues a token which can also manage tokens over the API.
+const ADMIN_FLAG: &str = "--admin";
+
+/// Options given to `/create_token`.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct TokenRequest {
+    pub name: Option<String>,
+    /// Lifetime in seconds; the token never expires when unset.
+    pub lifetime: Option<i64>,
+    pub scope: TokenScope,
+}
+
+/// Parse `/create_token` arguments: an optional name, an optional lifetime as
+/// the last word and a `--readonly` or `--admin` flag anywhere
+/// (`/create_token dashboard 7d --readonly`). `--readonly` wins if both are
+/// given.
+fn parse_token_request(text: &str) -> TokenRequest {
+    let mut words: Vec<&str> = text.split_whitespace().collect();
+    let read_only = words.contains(&READ_ONLY_FLAG);
+    let admin = words.contains(&ADMIN_FLAG);
+    words.retain(|word| *word != READ_ONLY_FLAG && *word != ADMIN_FLAG);
+    let scope = if read_only {
+        TokenScope::Read
+    } else if admin {
+        TokenScope::Admin
     } else {
-        Some(trimmed.to_string())
+        TokenScope::Write
+    };
+    let lifetime = words.last().and_then(|word| parse_token_lifetime(word));
+    if lifetime.is_some() {
+        words.pop();
+    }
+    let name = (!words.is_empty()).then(|| words.join(" "));
+    TokenRequest {
+        name,
+        lifetime,
+        scope,
     }
 }
 
-fn issuer_display_name(user: &User) -> String {
+pub(crate) fn issuer_display_name(user: &User) -> String {
     if let Some(username) = user.username.as_deref() {
         format!("@{username}")
     } else if let Some(last_name) = user.last_name.as_deref() {
@@ -87,56 +168,148 @@ pub async fn issue_token(
     db: Database,
     requested_name: String,
 ) -> Result<()> {
+    hide_group_command(&bot, &db, &msg).await?;
+    let request = parse_token_request(&requested_name);
+    send_new_token(&bot, msg.chat.id, &db, msg.from.as_ref(), request).await
+}
+
+/// Delete a command sent in a group, since it may carry a token or ask for
+/// a secret.
+pub(crate) async fn hide_group_command(bot: &Bot, db: &Database, msg: &Message) -> Result<()> {
+    if !msg.chat.is_private() {
+        delete_user_message(bot, db, msg.chat.id, msg.id).await?;
+    }
+    Ok(())
+}
+
+/// Send an HTML reply carrying a secret of `chat_id`. Private chats get it
+/// directly; for a group it goes to `user`'s private chat so the group never
+/// sees it. Returns whether the reply was delivered.
+pub(crate) async fn send_private_reply(
+    bot: &Bot,
+    chat_id: ChatId,
+    user: Option<&User>,
+    text: String,
+) -> Result<bool> {
+    if chat_id.is_user() {
+        bot.send_message(chat_id, text)
+            .parse_mode(ParseMode::Html)
+            .await?;
+        return Ok(true);
+    }
+    let sent = match user {
+        Some(user) => bot
+            .send_message(user.id, text)
+            .parse_mode(ParseMode::Html)
+            .await
+            .map_err(|err| {
+                tracing::warn!(
+                    error = %err,
+                    chat_id = chat_id.0,
+                    user_id = user.id.0,
+                    "Failed to send reply privately"
+                );
+            })
+            .is_ok(),
+        None => false,
+    };
+    if sent {
+        tracing::debug!(chat_id = chat_id.0, "Sent reply privately");
+    }
+    Ok(sent)
+}
+
+/// Send a reply about the tokens of `chat_id` with [`send_private_reply`];
+/// a group only gets a warning if the private message fails.
+async fn send_token_reply(
+    bot: &Bot,
+    chat_id: ChatId,
+    user: Option<&User>,
+    text: String,
+) -> Result<()> {
+    if !send_private_reply(bot, chat_id, user, text).await? {
+        bot.send_message(chat_id, TOKEN_DM_FAILED).await?;
+    }
+    Ok(())
+}
+
+/// Create a token for `chat_id` and send it to the chat, or to `issuer`
+/// privately when the chat is a group.
+pub(crate) async fn send_new_token(
+    bot: &Bot,
+    chat_id: ChatId,
+    db: &Database,
+    issuer: Option<&User>,
+    request: TokenRequest,
+) -> Result<()> {
+    let TokenRequest {
+        name,
+        lifetime,
+        scope,
+    } = request;
     let token = generate_token();
     let issued_at = now_timestamp();
+    let expires_at = lifetime.map(|seconds| issued_at.saturating_add(seconds));
     let preview = token_preview(&token);
-    let name = parse_token_name(&requested_name);
-    let issuer_user_id = msg.from.as_ref().map(|user| user.id.0 as i64);
-    let issuer_name = msg.from.as_ref().map(issuer_display_name);
+    let issuer_user_id = issuer.map(|user| user.id.0 as i64);
+    let issuer_name = issuer.map(issuer_display_name);
     tracing::debug!(
-        chat_id = msg.chat.id.0,
+        chat_id = chat_id.0,
         token_preview = %preview,
         name = name.as_deref(),
         issuer_user_id,
         issuer_name = issuer_name.as_deref(),
+        expires_at,
+        scope = scope.name(),
         "Issuing token"
     );
     db.create_token(
-        msg.chat.id,
+        chat_id,
         &token,
-        name.as_deref(),
-        issuer_user_id,
-        issuer_name.as_deref(),
-        issued_at,
+        &NewToken {
+            name: name.as_deref(),
+            issuer_user_id,
+            issuer_name: issuer_name.as_deref(),
+            issued_at,
+            expires_at,
+            scope,
+        },
     )
     .await?;
 
-    let response = format!("{TOKEN_ISSUED}\n<code>{token}</code>");
-    bot.send_message(msg.chat.id, response)
-        .parse_mode(ParseMode::Html)
-        .await?;
-    Ok(())
+    let mut response = format!("{TOKEN_ISSUED}\n<code>{token}</code>");
+    if expires_at.is_some() {
+        let expires = format_timestamp(expires_at, "never");
+        response.push_str(&format!("\nexpires: {expires}"));
+    }
+    if scope != TokenScope::Write {
+        response.push_str(&format!("\nscope: {}", scope.name()));
+    }
+    send_token_reply(bot, chat_id, issuer, response).await
 }
 
 pub async fn list_tokens(bot: Bot, msg: Message, db: Database) -> Result<()> {
+    hide_group_command(&bot, &db, &msg).await?;
     let tokens = db.list_tokens(msg.chat.id).await?;
-    if tokens.is_empty() {
-        bot.send_message(msg.chat.id, TOKENS_EMPTY).await?;
-        return Ok(());
-    }
-
-    let response = format_token_list(&tokens);
-    bot.send_message(msg.chat.id, response)
-        .parse_mode(ParseMode::Html)
-        .await?;
-    Ok(())
+    let response = if tokens.is_empty() {
+        escape(TOKENS_EMPTY)
+    } else {
+        format_token_list(&tokens, now_timestamp())
+    };
+    send_token_reply(&bot, msg.chat.id, msg.from.as_ref(), response).await
 }
 
 pub async fn revoke_token(bot: Bot, msg: Message, db: Database, token: String) -> Result<()> {
+    hide_group_command(&bot, &db, &msg).await?;
     let token = token.trim();
     if token.is_empty() {
-        bot.send_message(msg.chat.id, TOKEN_REVOKE_USAGE).await?;
-        return Ok(());
+        return send_token_reply(
+            &bot,
+            msg.chat.id,
+            msg.from.as_ref(),
+            escape(TOKEN_REVOKE_USAGE),
+        )
+        .await;
     }
 
     let revoked_at = now_timestamp();
@@ -146,12 +319,308 @@ pub async fn revoke_token(bot: Bot, msg: Message, db: Database, token: String) -
         token_preview = %preview,
         "Revoking token"
     );
-    let revoked = db.revoke_token(msg.chat.id, token, revoked_at).await?;
+    let mut revoked = db.revoke_token(msg.chat.id, token, revoked_at).await?;
+    if !revoked {
+        let tokens = db.list_tokens(msg.chat.id).await?;
+        match match_token(&tokens, token) {
+            TokenMatch::One(id) => {
+                tracing::debug!(
+                    chat_id = msg.chat.id.0,
+                    token_id = id,
+                    "Matched token by id or preview"
+                );
+                revoked = db.revoke_token_by_id(msg.chat.id, id, revoked_at).await?;
+            }
+            TokenMatch::Ambiguous => {
+                tracing::debug!(chat_id = msg.chat.id.0, "Token preview is ambiguous");
+                let text = escape(TOKEN_AMBIGUOUS);
+                return send_token_reply(&bot, msg.chat.id, msg.from.as_ref(), text).await;
+            }
+            TokenMatch::None => {}
+        }
+    }
     let response = if revoked {
         TOKEN_REVOKED
     } else {
         TOKEN_NOT_FOUND
     };
-    bot.send_message(msg.chat.id, response).await?;
-    Ok(())
+    send_token_reply(&bot, msg.chat.id, msg.from.as_ref(), escape(response)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::util::{init_test_db, test_bot};
+    use wiremock::{
+        matchers::{body_string_contains, method, path, path_regex},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    fn record(expires_at: Option<i64>) -> TokenRecord {
+        TokenRecord {
+            id: 1,
+            chat_id: 1,
+            token_hash: "hash".into(),
+            token_preview: "abcdef".into(),
+            name: None,
+            issuer_user_id: None,
+            issuer_name: None,
+            issued_at: 0,
+            last_used_at: None,
+            revoked_at: None,
+            expires_at,
+            scope: "write".into(),
+        }
+    }
+
+    fn request(name: Option<&str>, lifetime: Option<i64>, scope: TokenScope) -> TokenRequest {
+        TokenRequest {
+            name: name.map(str::to_string),
+            lifetime,
+            scope,
+        }
+    }
+
+    #[test]
+    fn parses_name_and_lifetime() {
+        let write = TokenScope::Write;
+        assert_eq!(parse_token_request(""), request(None, None, write));
+        assert_eq!(
+            parse_token_request("guest"),
+            request(Some("guest"), None, write)
+        );
+        assert_eq!(
+            parse_token_request("weekend guest 7d"),
+            request(Some("weekend guest"), Some(7 * 24 * 60 * 60), write)
+        );
+        assert_eq!(
+            parse_token_request("12h"),
+            request(None, Some(12 * 60 * 60), write)
+        );
+        assert_eq!(
+            parse_token_request("guest 0d"),
+            request(Some("guest 0d"), None, write)
+        );
+        assert_eq!(
+            parse_token_request("room 101"),
+            request(Some("room 101"), None, write)
+        );
+    }
+
+    #[test]
+    fn parses_read_only_flag() {
+        let read = TokenScope::Read;
+        assert_eq!(
+            parse_token_request("dashboard --readonly"),
+            request(Some("dashboard"), None, read)
+        );
+        assert_eq!(
+            parse_token_request("--readonly kitchen screen 2w"),
+            request(Some("kitchen screen"), Some(14 * 24 * 60 * 60), read)
+        );
+        assert_eq!(parse_token_request("--readonly"), request(None, None, read));
+    }
+
+    #[test]
+    fn parses_admin_flag() {
+        assert_eq!(
+            parse_token_request("provisioning --admin 1d"),
+            request(Some("provisioning"), Some(24 * 60 * 60), TokenScope::Admin)
+        );
+        assert_eq!(
+            parse_token_request("--admin --readonly"),
+            request(None, None, TokenScope::Read)
+        );
+    }
+
+    #[test]
+    fn token_list_marks_expired_tokens() {
+        let listed = format_token_list(&[record(Some(100))], 100);
+        assert!(listed.contains("<code>abcdef…</code>\nid: 1\n"));
+        assert!(listed.contains("expires: 1970-01-01T00:01:40+00:00 (expired)"));
+
+        let listed = format_token_list(&[record(Some(100)), record(None)], 99);
+        assert!(!listed.contains("(expired)"));
+        assert!(listed.contains("expires: never"));
+        assert!(listed.contains("scope: write"));
+    }
+
+    #[test]
+    fn tokens_match_by_id_or_unique_preview() {
+        let mut other = record(None);
+        other.id = 2;
+        other.token_preview = "ghijkl".into();
+        let mut revoked = record(None);
+        revoked.id = 3;
+        revoked.token_preview = "ghijkl".into();
+        revoked.revoked_at = Some(5);
+        let tokens = [record(None), other, revoked];
+
+        assert_eq!(match_token(&tokens, "2"), TokenMatch::One(2));
+        assert_eq!(match_token(&tokens, "3"), TokenMatch::None);
+        assert_eq!(match_token(&tokens, "abcdef"), TokenMatch::One(1));
+        assert_eq!(match_token(&tokens, "ghijkl…"), TokenMatch::One(2));
+        assert_eq!(match_token(&tokens, "zzzzzz"), TokenMatch::None);
+
+        let mut twin = record(None);
+        twin.id = 4;
+        assert_eq!(
+            match_token(&[record(None), twin], "abcdef"),
+            TokenMatch::Ambiguous
+        );
+    }
+
+    fn group_command(text: &str) -> Message {
+        serde_json::from_value(serde_json::json!({
+            "message_id": 3,
+            "date": 0,
+            "chat": {"id": -5, "type": "group", "title": "Family"},
+            "from": {"id": 7, "is_bot": false, "first_name": "Ann"},
+            "text": text,
+        }))
+        .unwrap()
+    }
+
+    /// Chat id and text of every message sent.
+    async fn sent_messages(server: &MockServer) -> Vec<(i64, String)> {
+        server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .filter(|request| request.url.path() == "/botTEST/SendMessage")
+            .map(|request| {
+                let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+                (
+                    body["chat_id"].as_i64().unwrap(),
+                    body["text"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect()
+    }
+
+    /// Expects the command to be deleted and answers messages to user 7
+    /// with `dm_status` and `dm_body`.
+    async fn group_server(dm_status: u16, dm_body: &str) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path_regex(r"^/botTEST/[Dd]eleteMessage$"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(r#"{"ok":true,"result":true}"#, "application/json"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SendMessage"))
+            .and(body_string_contains(r#""chat_id":7"#))
+            .respond_with(
+                ResponseTemplate::new(dm_status).set_body_raw(dm_body, "application/json"),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SendMessage"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"ok":true,"result":{"message_id":9,"date":0,"chat":{"id":-5,"type":"group","title":"Family"}}}"#,
+                "application/json",
+            ))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[tokio::test]
+    async fn group_tokens_are_sent_privately() {
+        let server = group_server(
+            200,
+            r#"{"ok":true,"result":{"message_id":4,"date":0,"chat":{"id":7,"type":"private"}}}"#,
+        )
+        .await;
+        let db = init_test_db().await;
+
+        issue_token(
+            test_bot(&server),
+            group_command("/create_token dashboard"),
+            db.clone(),
+            "dashboard".into(),
+        )
+        .await
+        .unwrap();
+
+        let sent = sent_messages(&server).await;
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].0, 7);
+        assert!(sent[0].1.contains(TOKEN_ISSUED));
+        assert_eq!(db.list_tokens(ChatId(-5)).await.unwrap().len(), 1);
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn group_gets_a_warning_when_the_dm_fails() {
+        let server = group_server(
+            403,
+            r#"{"ok":false,"error_code":403,"description":"Forbidden: bot can't initiate conversation with a user"}"#,
+        )
+        .await;
+        let db = init_test_db().await;
+
+        issue_token(
+            test_bot(&server),
+            group_command("/create_token"),
+            db.clone(),
+            String::new(),
+        )
+        .await
+        .unwrap();
+
+        let sent = sent_messages(&server).await;
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[1], (-5, TOKEN_DM_FAILED.to_string()));
+        assert!(sent
+            .iter()
+            .filter(|(chat, _)| *chat == -5)
+            .all(|(_, text)| !text.contains(TOKEN_ISSUED)));
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn tokens_are_revoked_by_id() {
+        let server = group_server(
+            200,
+            r#"{"ok":true,"result":{"message_id":4,"date":0,"chat":{"id":7,"type":"private"}}}"#,
+        )
+        .await;
+        let db = init_test_db().await;
+        db.create_token(
+            ChatId(-5),
+            "lost-token",
+            &NewToken {
+                issued_at: 1,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+        let id = db.list_tokens(ChatId(-5)).await.unwrap()[0].id;
+
+        revoke_token(
+            test_bot(&server),
+            group_command(&format!("/revoke_token {id}")),
+            db.clone(),
+            id.to_string(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            sent_messages(&server).await,
+            [(7, TOKEN_REVOKED.to_string())]
+        );
+        assert!(db.list_tokens(ChatId(-5)).await.unwrap()[0]
+            .revoked_at
+            .is_some());
+        server.verify().await;
+    }
 }
@@ -0,0 +1,192 @@
+use anyhow::Result;
+use std::time::Duration;
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup, MaybeInaccessibleMessage},
+};
+use tracing::Instrument;
+
+use super::keyboard::build_item_buttons;
+use super::refresh::send_list_or_defer;
+use crate::db::{Database, Item};
+use crate::messages::{TRASH_EMPTY, TRASH_HEADER};
+use crate::notify::{ListEvent, ListEventKind};
+use crate::state::SharedState;
+use crate::utils::try_edit_message;
+
+/// Callback prefix for the restore buttons shown by `/trash`.
+pub const RESTORE_PREFIX: &str = "restore_";
+/// Number of trashed items offered by `/trash`.
+pub const TRASH_LIMIT: i64 = 10;
+/// Trashed items older than this are removed for good.
+pub const TRASH_RETENTION_DAYS: i64 = 30;
+const TRASH_PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+pub fn format_trash(items: &[Item]) -> (String, InlineKeyboardMarkup) {
+    if items.is_empty() {
+        let no_buttons = InlineKeyboardMarkup::new(Vec::<Vec<InlineKeyboardButton>>::new());
+        return (TRASH_EMPTY.to_string(), no_buttons);
+    }
+    let buttons = build_item_buttons(
+        items,
+        |item| format!("♻️ {}", item.label()),
+        |item| format!("{RESTORE_PREFIX}{}", item.id),
+    );
+    (TRASH_HEADER.to_string(), InlineKeyboardMarkup::new(buttons))
+}
+
+pub async fn show_trash(bot: Bot, msg: Message, db: Database) -> Result<()> {
+    let chat_id = msg.chat.id;
+    let items = db.list_deleted_items(chat_id, TRASH_LIMIT).await?;
+    tracing::debug!(chat_id = chat_id.0, count = items.len(), "Showing trash");
+    if items.is_empty() {
+        bot.send_message(chat_id, TRASH_EMPTY).await?;
+        return Ok(());
+    }
+    let (text, keyboard) = format_trash(&items);
+    bot.send_message(chat_id, text)
+        .reply_markup(keyboard)
+        .await?;
+    Ok(())
+}
+
+/// Restore the item named by a `restore_<id>` button, then refresh both the
+/// trash message and the list.
+pub async fn handle_restore_callback(
+    bot: &Bot,
+    msg: &MaybeInaccessibleMessage,
+    db: &Database,
+    shared: &SharedState,
+    data: &str,
+) -> Result<()> {
+    let Ok(id) = data.parse::<i64>() else {
+        tracing::debug!(data, "Ignoring malformed restore callback");
+        return Ok(());
+    };
+    let chat_id = msg.chat().id;
+    let restored = db.restore_item(chat_id, id).await?;
+    tracing::debug!(
+        chat_id = chat_id.0,
+        item_id = id,
+        restored,
+        "Restore pressed"
+    );
+
+    let items = db.list_deleted_items(chat_id, TRASH_LIMIT).await?;
+    let (text, keyboard) = format_trash(&items);
+    try_edit_message(bot, chat_id, msg.id(), text, keyboard).await;
+
+    if restored > 0 {
+        shared
+            .events
+            .list_changed(db, ListEvent::item(chat_id, ListEventKind::Restore, id));
+        send_list_or_defer(bot, db, shared, chat_id).await?;
+    }
+    Ok(())
+}
+
+/// Cutoff before which trashed items are purged, given the current time.
+pub fn purge_cutoff(now: i64) -> i64 {
+    now - TRASH_RETENTION_DAYS * 24 * 60 * 60
+}
+
+/// Periodically remove items that have sat in the trash for longer than
+/// [`TRASH_RETENTION_DAYS`].
+pub fn spawn_trash_purge(db: Database) {
+    tokio::spawn(
+        async move {
+            let mut interval = tokio::time::interval(TRASH_PURGE_INTERVAL);
+            loop {
+                interval.tick().await;
+                let cutoff = purge_cutoff(chrono::Utc::now().timestamp());
+                if let Err(err) = db.purge_deleted(cutoff).await {
+                    tracing::warn!(error = %err, "Failed to purge trashed items");
+                }
+            }
+        }
+        .in_current_span(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::util::{init_test_db, test_bot, SENT_MESSAGE};
+    use teloxide::types::{ChatId, InlineKeyboardButtonKind};
+    use wiremock::{
+        matchers::{body_string_contains, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[test]
+    fn trash_buttons_carry_restore_ids() {
+        let item = Item {
+            id: 4,
+            text: "Milk".to_string(),
+            done: false,
+            quantity: 2,
+            added_by_user_id: None,
+            added_by_name: None,
+            category: None,
+            created_at: 0,
+            assigned_to_user_id: None,
+            assigned_to_name: None,
+            price_cents: None,
+        };
+        let (text, keyboard) = format_trash(&[item]);
+        assert_eq!(text, TRASH_HEADER);
+        let button = &keyboard.inline_keyboard[0][0];
+        assert_eq!(button.text, "♻️ 2× Milk");
+        match &button.kind {
+            InlineKeyboardButtonKind::CallbackData(data) => assert_eq!(data, "restore_4"),
+            _ => panic!("expected callback data"),
+        }
+        assert_eq!(format_trash(&[]).0, TRASH_EMPTY);
+    }
+
+    #[test]
+    fn purge_cutoff_keeps_retention_window() {
+        assert_eq!(purge_cutoff(3_000_000), 3_000_000 - 30 * 86_400);
+    }
+
+    #[tokio::test]
+    async fn restore_button_puts_item_back_and_empties_trash() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/EditMessageText"))
+            .and(body_string_contains(TRASH_EMPTY))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(SENT_MESSAGE, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SendMessage"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(SENT_MESSAGE, "application/json"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_item_count(chat, "Milk").await.unwrap();
+        let id = db.list_items(chat).await.unwrap()[0].id;
+        db.delete_item_count(chat, id).await.unwrap();
+
+        let msg: MaybeInaccessibleMessage =
+            serde_json::from_str(r#"{"message_id":9,"date":0,"chat":{"id":1,"type":"private"}}"#)
+                .unwrap();
+        handle_restore_callback(
+            &test_bot(&server),
+            &msg,
+            &db,
+            &SharedState::new().unwrap(),
+            &id.to_string(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(db.list_items(chat).await.unwrap()[0].text, "Milk");
+        assert!(db.list_deleted_items(chat, 10).await.unwrap().is_empty());
+        server.verify().await;
+    }
+}
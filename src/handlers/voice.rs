@@ -1,127 +1,496 @@
 use crate::db::Database;
-use crate::utils::download_telegram_file;
+use crate::state::SharedState;
+use crate::utils::{download_telegram_file, topic_of, InTopic};
 use anyhow::Result;
-use teloxide::prelude::*;
+use teloxide::{prelude::*, types::ThreadId};
 
 use crate::ai::config::AiConfig;
-use crate::ai::gpt::{interpret_voice_command, VoiceCommand};
-use crate::ai::stt::{transcribe_audio, DEFAULT_PROMPT};
-use crate::messages::VOICE_REMOVED_PREFIX;
-use crate::text_utils::normalize_for_match;
+use crate::ai::gpt::{interpret_voice_command, VoiceCommand, VoiceCommandRequest};
+use crate::ai::prompts::PromptKind;
+use crate::ai::stt::{parse_items, transcribe_audio, AudioFile};
+use crate::messages::{
+    recording_too_large_text, voice_heard_added_text, voice_heard_checked_text,
+    voice_heard_removed_text, voice_nothing_found_text, voice_removed_text,
+};
+use crate::notify::{ListEvent, ListEventKind};
+use crate::text_utils::{capitalize_first, normalize_for_match, truncate_graphemes};
 
-use crate::db::Item;
+use crate::db::{Item, ItemAuthor};
 
-pub async fn delete_matching_items(
+/// Bots can only download files up to this size from Telegram.
+pub const MAX_RECORDING_BYTES: u32 = 20 * 1024 * 1024;
+
+/// Something to listen to: a voice message, an audio file or a round video
+/// note.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Recording {
+    file_id: String,
+    size: u32,
+    file_name: String,
+    mime_type: Option<String>,
+}
+
+impl Recording {
+    fn of(msg: &Message) -> Option<Self> {
+        if let Some(voice) = msg.voice() {
+            return Some(Self {
+                file_id: voice.file.id.clone(),
+                size: voice.file.size,
+                file_name: "voice.ogg".to_string(),
+                mime_type: voice.mime_type.as_ref().map(ToString::to_string),
+            });
+        }
+        if let Some(audio) = msg.audio() {
+            let mime_type = audio.mime_type.as_ref().map(ToString::to_string);
+            let file_name = audio
+                .file_name
+                .clone()
+                .unwrap_or_else(|| format!("audio.{}", audio_extension(mime_type.as_deref())));
+            return Some(Self {
+                file_id: audio.file.id.clone(),
+                size: audio.file.size,
+                file_name,
+                mime_type,
+            });
+        }
+        msg.video_note().map(|note| Self {
+            file_id: note.file.id.clone(),
+            size: note.file.size,
+            file_name: "video_note.mp4".to_string(),
+            mime_type: Some("video/mp4".to_string()),
+        })
+    }
+}
+
+/// Guess the extension of an unnamed audio file; the transcription API
+/// goes by the file name.
+fn audio_extension(mime_type: Option<&str>) -> &'static str {
+    match mime_type {
+        Some("audio/ogg") => "ogg",
+        Some("audio/mp4" | "audio/m4a" | "audio/x-m4a") => "m4a",
+        Some("audio/wav" | "audio/x-wav") => "wav",
+        Some("audio/webm") => "webm",
+        Some("audio/flac") => "flac",
+        _ => "mp3",
+    }
+}
+
+/// Whether `msg` carries a recording [`add_items_from_voice`] can listen to.
+pub fn has_recording(msg: &Message) -> bool {
+    Recording::of(msg).is_some()
+}
+
+/// Move the items of `current` that `items` names out of it, one per mention.
+/// Also returns the requests that match nothing on the list; repeated
+/// mentions of an item that was found are not among them.
+fn take_matching_items(current: &mut Vec<Item>, items: &[String]) -> (Vec<Item>, Vec<String>) {
+    let mut found: Vec<Item> = Vec::new();
+    let mut not_found: Vec<String> = Vec::new();
+    for item in items {
+        let needle = normalize_for_match(item);
+        if let Some(pos) = current
+            .iter()
+            .position(|i| normalize_for_match(&i.text) == needle)
+        {
+            found.push(current.remove(pos));
+        } else if !found.iter().any(|i| normalize_for_match(&i.text) == needle)
+            && !not_found
+                .iter()
+                .any(|missing| normalize_for_match(missing) == needle)
+        {
+            not_found.push(item.clone());
+        }
+    }
+    (found, not_found)
+}
+
+/// Delete `found` from the list and return the texts of the removed items.
+pub(super) async fn remove_items(
+    db: &Database,
+    shared: &SharedState,
+    chat_id: ChatId,
+    found: Vec<Item>,
+) -> Result<Vec<String>> {
+    let ids: Vec<i64> = found.iter().map(|item| item.id).collect();
+    let removed = db.delete_items_count(chat_id, &ids).await?;
+    if removed > 0 {
+        shared.events.list_changed(
+            db,
+            ListEvent::batch(chat_id, ListEventKind::Delete, removed),
+        );
+    }
+    Ok(found.into_iter().map(|item| item.text).collect())
+}
+
+/// Tell the chat which items a voice request removed, and which it could not
+/// find, then send the updated list.
+pub(super) async fn announce_voice_removal(
+    bot: &Bot,
+    db: &Database,
+    shared: &SharedState,
+    chat_id: ChatId,
+    topic: Option<ThreadId>,
+    deleted: &[String],
+    not_found: &[String],
+) -> Result<()> {
+    tracing::info!(
+        "Deleted {} item(s) via voice for chat {}",
+        deleted.len(),
+        chat_id
+    );
+    bot.send_message(chat_id, voice_removed_text(deleted, not_found))
+        .in_topic(topic)
+        .await?;
+    ListService::new(db, shared)
+        .send_list(bot.clone(), chat_id)
+        .await?;
+    refresh_delete_panels(bot, db, chat_id).await
+}
+
+/// Check off the unchecked items of `current` that `items` names; items that
+/// are already checked are left alone. Returns the texts that were checked.
+pub async fn check_matching_items(
     db: &Database,
+    shared: &SharedState,
     chat_id: ChatId,
-    current: &mut Vec<Item>,
+    current: &[Item],
     items: &[String],
 ) -> Result<Vec<String>> {
-    let mut deleted = Vec::new();
-    let mut ids = Vec::new();
+    let mut checked = Vec::new();
+    let mut seen = Vec::new();
     for item in items {
         let needle = normalize_for_match(item);
-        if let Some(pos) = current
+        let Some(found) = current
             .iter()
-            .position(|i| normalize_for_match(&i.text) == needle)
-        {
-            let found = current.remove(pos);
-            ids.push(found.id);
-            deleted.push(found.text);
+            .find(|i| !i.done && !seen.contains(&i.id) && normalize_for_match(&i.text) == needle)
+        else {
+            continue;
+        };
+        seen.push(found.id);
+        if db.toggle_item_count(chat_id, found.id).await? > 0 {
+            shared.events.list_changed(
+                db,
+                ListEvent::item(chat_id, ListEventKind::Toggle, found.id),
+            );
+            checked.push(found.text.clone());
         }
     }
-    db.delete_items_count(chat_id, &ids).await?;
-    Ok(deleted)
+    tracing::debug!(
+        chat_id = chat_id.0,
+        checked = checked.len(),
+        "Checked items via voice"
+    );
+    Ok(checked)
 }
 
-use super::list::insert_capitalized_items_with_log;
+use super::delete::refresh_delete_panels;
+use super::duplicates::skip_listed_items;
+use super::list::insert_items_with_log;
 use super::list_service::ListService;
+use super::message_tracker::MessageTracker;
 use super::parse::parse_items_with_fallback;
+use super::voice_confirm::{ask_voice_deletion, needs_confirmation};
+
+/// Longest transcription excerpt quoted back in voice confirmations.
+const TRANSCRIPT_PREVIEW_GRAPHEMES: usize = 80;
+
+struct VoiceFeedback<'a> {
+    enabled: bool,
+    transcript: &'a str,
+    delete_after_timeout: u64,
+    topic: Option<ThreadId>,
+}
+
+impl VoiceFeedback<'_> {
+    async fn send(&self, bot: &Bot, chat_id: ChatId, text: impl FnOnce(&str) -> String) {
+        if !self.enabled {
+            return;
+        }
+        let preview = truncate_graphemes(self.transcript.trim(), TRANSCRIPT_PREVIEW_GRAPHEMES);
+        tracing::debug!(chat_id = chat_id.0, "Sending voice feedback");
+        match bot
+            .send_message(chat_id, text(&preview))
+            .in_topic(self.topic)
+            .await
+        {
+            Ok(sent) => drop(crate::delete_after(
+                bot.clone(),
+                sent.chat.id,
+                sent.id,
+                self.delete_after_timeout,
+            )),
+            Err(err) => tracing::warn!(error = %err, "Failed to send voice feedback"),
+        }
+    }
+}
+
+async fn add_voice_items(
+    bot: &Bot,
+    chat_id: ChatId,
+    db: &Database,
+    shared: &SharedState,
+    items: Vec<String>,
+    author: Option<&ItemAuthor>,
+    feedback: &VoiceFeedback<'_>,
+) -> Result<()> {
+    let items: Vec<String> = items.iter().map(|item| capitalize_first(item)).collect();
+    let items = skip_listed_items(db, chat_id, items, String::as_str).await?;
+    let added = insert_items_with_log(
+        bot.clone(),
+        chat_id,
+        db,
+        shared,
+        items.clone(),
+        "from voice",
+        author,
+    )
+    .await?;
+    if added > 0 {
+        feedback
+            .send(bot, chat_id, |heard| voice_heard_added_text(heard, &items))
+            .await;
+    }
+    Ok(())
+}
 
 pub async fn add_items_from_voice(
     bot: Bot,
     msg: Message,
     db: Database,
+    shared: SharedState,
     ai_config: Option<AiConfig>,
+    delete_after_timeout: u64,
 ) -> Result<()> {
     let Some(config) = ai_config else {
         return Ok(());
     };
+    if !config.enable_stt {
+        tracing::debug!(
+            chat_id = msg.chat.id.0,
+            "Speech-to-text disabled; ignoring voice"
+        );
+        return Ok(());
+    }
 
-    let voice = match msg.voice() {
-        Some(v) => v,
-        None => return Ok(()),
+    let Some(recording) = Recording::of(&msg) else {
+        return Ok(());
     };
+    if recording.size > MAX_RECORDING_BYTES {
+        tracing::debug!(
+            chat_id = msg.chat.id.0,
+            size = recording.size,
+            "Recording too large; skipping"
+        );
+        let sent = bot
+            .send_message(
+                msg.chat.id,
+                recording_too_large_text(MAX_RECORDING_BYTES / (1024 * 1024)),
+            )
+            .in_topic(topic_of(&msg))
+            .await?;
+        drop(crate::delete_after(
+            bot.clone(),
+            sent.chat.id,
+            sent.id,
+            delete_after_timeout,
+        ));
+        return Ok(());
+    }
 
-    let audio = download_telegram_file(&bot, &voice.file.id).await?;
+    let settings = db.get_settings(msg.chat.id).await?;
+    let prompts = config.prompts.overridden_by(&settings.prompts);
+    let bytes = download_telegram_file(&bot, &recording.file_id).await?;
+    let audio = AudioFile {
+        bytes: &bytes,
+        file_name: &recording.file_name,
+        mime_type: recording.mime_type.as_deref(),
+    };
+    tracing::debug!(
+        chat_id = msg.chat.id.0,
+        file_name = %recording.file_name,
+        "Transcribing recording"
+    );
 
-    match transcribe_audio(
+    let _task = shared.tasks.track();
+    let transcription = match transcribe_audio(
         &config.stt_model,
         &config.api_key,
-        Some(DEFAULT_PROMPT),
-        &audio,
+        Some(prompts.get(PromptKind::Transcription)),
+        config.stt_language.as_deref(),
+        audio,
         config.openai_stt_url.as_deref(),
+        &config.openai,
     )
     .await
     {
-        Ok(text) => {
-            if text.trim().is_empty() {
-                tracing::debug!("voice transcription empty; ignoring");
-                return Ok(());
-            }
-            let mut current = db.list_items(msg.chat.id).await?;
-            let list_texts: Vec<String> = current.iter().map(|i| i.text.clone()).collect();
-            match interpret_voice_command(
-                &config.api_key,
-                &config.gpt_model,
-                &text,
-                &list_texts,
-                config.openai_chat_url.as_deref(),
+        Ok(transcription) => transcription,
+        Err(err) => {
+            tracing::warn!("transcription failed: {}", err);
+            return Ok(());
+        }
+    };
+    let text = transcription.text;
+    // Whisper names the language it heard; otherwise fall back to the one
+    // that was asked for.
+    let language = transcription
+        .language
+        .or_else(|| config.stt_language.clone());
+    tracing::debug!(
+        chat_id = msg.chat.id.0,
+        language = ?language,
+        "Transcribed recording"
+    );
+
+    if text.trim().is_empty() {
+        tracing::debug!("voice transcription empty; ignoring");
+        return Ok(());
+    }
+
+    ListService::new(&db, &shared).follow_topic(&msg).await?;
+    let author = msg.from.as_ref().map(ItemAuthor::from);
+    let feedback = VoiceFeedback {
+        enabled: settings.voice_feedback,
+        transcript: &text,
+        delete_after_timeout,
+        topic: topic_of(&msg),
+    };
+
+    if !config.enable_voice_commands {
+        tracing::debug!(
+            chat_id = msg.chat.id.0,
+            "Voice commands disabled; parsing transcription locally"
+        );
+        return add_voice_items(
+            &bot,
+            msg.chat.id,
+            &db,
+            &shared,
+            parse_items(&text),
+            author.as_ref(),
+            &feedback,
+        )
+        .await;
+    }
+
+    let mut current = db.list_items(msg.chat.id).await?;
+    let list_texts: Vec<String> = current.iter().map(|i| i.text.clone()).collect();
+    let request = VoiceCommandRequest {
+        instructions: prompts.get(PromptKind::VoiceCommand),
+        text: &text,
+        language: language.as_deref(),
+        list: &list_texts,
+    };
+    match interpret_voice_command(
+        &config.api_key,
+        &config.gpt_model,
+        request,
+        config.openai_chat_url.as_deref(),
+        &config.openai,
+    )
+    .await
+    {
+        Ok(VoiceCommand::Add(items)) => {
+            add_voice_items(
+                &bot,
+                msg.chat.id,
+                &db,
+                &shared,
+                items,
+                author.as_ref(),
+                &feedback,
             )
-            .await
-            {
-                Ok(VoiceCommand::Add(items)) => {
-                    let _added = insert_capitalized_items_with_log(
+            .await?;
+        }
+        Ok(VoiceCommand::Delete(items)) => {
+            let list_len = current.len();
+            let (found, not_found) = take_matching_items(&mut current, &items);
+            if found.is_empty() {
+                tracing::debug!(
+                    chat_id = msg.chat.id.0,
+                    requested = items.len(),
+                    "No voice deletion matched the list"
+                );
+                if !not_found.is_empty() {
+                    let sent = bot
+                        .send_message(msg.chat.id, voice_nothing_found_text(&not_found))
+                        .in_topic(topic_of(&msg))
+                        .await?;
+                    drop(crate::delete_after(
                         bot.clone(),
-                        msg.chat.id,
-                        &db,
-                        items,
-                        "from voice",
-                    )
-                    .await?;
+                        sent.chat.id,
+                        sent.id,
+                        delete_after_timeout,
+                    ));
                 }
-                Ok(VoiceCommand::Delete(items)) => {
-                    let deleted =
-                        delete_matching_items(&db, msg.chat.id, &mut current, &items).await?;
-                    if !deleted.is_empty() {
-                        tracing::info!(
-                            "Deleted {} item(s) via voice for chat {}",
-                            deleted.len(),
-                            msg.chat.id
-                        );
-                        let lines: Vec<String> = deleted.iter().map(|t| format!("• {t}")).collect();
-                        let msg_text = format!("{VOICE_REMOVED_PREFIX}{}", lines.join("\n"));
-                        bot.send_message(msg.chat.id, msg_text).await?;
-                        ListService::new(&db)
+                return Ok(());
+            }
+            if let (true, Some(sender)) = (
+                needs_confirmation(found.len(), list_len, config.voice_delete_confirm_threshold),
+                msg.from.as_ref(),
+            ) {
+                return ask_voice_deletion(
+                    &bot,
+                    msg.chat.id,
+                    topic_of(&msg),
+                    &db,
+                    sender.id,
+                    &found,
+                )
+                .await;
+            }
+            let deleted = remove_items(&db, &shared, msg.chat.id, found).await?;
+            if !deleted.is_empty() {
+                announce_voice_removal(
+                    &bot,
+                    &db,
+                    &shared,
+                    msg.chat.id,
+                    topic_of(&msg),
+                    &deleted,
+                    &not_found,
+                )
+                .await?;
+                feedback
+                    .send(&bot, msg.chat.id, |heard| {
+                        voice_heard_removed_text(heard, &deleted)
+                    })
+                    .await;
+            }
+        }
+        Ok(VoiceCommand::Check(items)) => {
+            let checked = check_matching_items(&db, &shared, msg.chat.id, &current, &items).await?;
+            if !checked.is_empty() {
+                match MessageTracker::new(&db).current(msg.chat.id).await? {
+                    Some(list_id) => {
+                        ListService::new(&db, &shared)
+                            .update_message(&bot, msg.chat.id, list_id)
+                            .await?
+                    }
+                    None => {
+                        ListService::new(&db, &shared)
                             .send_list(bot.clone(), msg.chat.id)
-                            .await?;
+                            .await?
                     }
                 }
-                Err(err) => {
-                    let items = parse_items_with_fallback(&text, Err(err), "voice_command");
-                    let _added = insert_capitalized_items_with_log(
-                        bot.clone(),
-                        msg.chat.id,
-                        &db,
-                        items,
-                        "from voice",
-                    )
-                    .await?;
-                }
+                feedback
+                    .send(&bot, msg.chat.id, |heard| {
+                        voice_heard_checked_text(heard, &checked)
+                    })
+                    .await;
             }
         }
         Err(err) => {
-            tracing::warn!("transcription failed: {}", err);
+            let items = parse_items_with_fallback(&text, Err(err), "voice_command");
+            add_voice_items(
+                &bot,
+                msg.chat.id,
+                &db,
+                &shared,
+                items,
+                author.as_ref(),
+                &feedback,
+            )
+            .await?;
         }
     }
 
@@ -131,8 +500,585 @@ pub async fn add_items_from_voice(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::tests::util::init_test_db;
-    use teloxide::types::ChatId;
+    use crate::ai::common::{OpenAiClient, RequestPolicy};
+    use crate::ai::config::DEFAULT_VOICE_DELETE_CONFIRM_THRESHOLD;
+    use crate::db::chat_settings::VOICE_FEEDBACK;
+    use crate::metrics::Metrics;
+    use crate::tests::util::{init_test_db, SENT_MESSAGE};
+    use reqwest::Client;
+    use std::time::Duration;
+    use teloxide::types::{ChatId, MessageId};
+    use wiremock::{
+        matchers::{body_string_contains, method, path, path_regex},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    async fn voice_server(
+        transcript: &str,
+        chat_status: u16,
+        command: serde_json::Value,
+    ) -> MockServer {
+        let content = command.to_string();
+        let chat = ResponseTemplate::new(chat_status).set_body_json(serde_json::json!({
+            "choices": [{ "message": { "content": content } }]
+        }));
+        voice_server_answering(transcript, chat).await
+    }
+
+    async fn voice_server_answering(transcript: &str, chat: ResponseTemplate) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/GetFile"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"ok":true,"result":{"file_id":"f","file_unique_id":"u","file_path":"voice.ogg"}}"#,
+                "application/json",
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/file/botTEST/voice.ogg"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw("ogg", "audio/ogg"))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/audio/transcriptions"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(serde_json::json!({ "text": transcript })),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(chat)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path_regex(r"^/botTEST/[Dd]eleteMessage$"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(r#"{"ok":true,"result":true}"#, "application/json"),
+            )
+            .mount(&server)
+            .await;
+        server
+    }
+
+    async fn expect_feedback(server: &MockServer, text: &str, times: u64) {
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SendMessage"))
+            .and(body_string_contains(text))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(SENT_MESSAGE, "application/json"))
+            .expect(times)
+            .mount(server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SendMessage"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(SENT_MESSAGE, "application/json"))
+            .mount(server)
+            .await;
+    }
+
+    fn voice_setup(server: &MockServer) -> (Bot, Message, Option<AiConfig>) {
+        let client = Client::builder().no_proxy().build().unwrap();
+        let bot = Bot::with_client("TEST", client)
+            .set_api_url(reqwest::Url::parse(&server.uri()).unwrap());
+        let msg: Message = serde_json::from_str(
+            r#"{"message_id":1,"date":0,"chat":{"id":1,"type":"private"},"from":{"id":5,"is_bot":false,"first_name":"Anna"},"voice":{"file_id":"f","file_unique_id":"u","duration":1,"mime_type":"audio/ogg","file_size":3}}"#,
+        )
+        .unwrap();
+        let config = AiConfig {
+            api_key: "k".into(),
+            stt_model: "whisper-1".into(),
+            gpt_model: "gpt-4.1".into(),
+            vision_model: "gpt-4o".into(),
+            openai_chat_url: Some(format!("{}/v1/chat/completions", server.uri())),
+            openai_stt_url: Some(format!("{}/v1/audio/transcriptions", server.uri())),
+            enable_stt: true,
+            enable_vision: true,
+            enable_gpt_parse: true,
+            enable_voice_commands: true,
+            voice_delete_confirm_threshold: DEFAULT_VOICE_DELETE_CONFIRM_THRESHOLD,
+            stt_language: None,
+            // Retry the scripted server errors without waiting.
+            openai: OpenAiClient::new(
+                RequestPolicy {
+                    base_delay: Duration::ZERO,
+                    ..RequestPolicy::default()
+                },
+                Metrics::default(),
+            ),
+            prompts: Default::default(),
+            vision_max_edge: None,
+        };
+        (bot, msg, Some(config))
+    }
+
+    /// Delete the items of `current` that `items` names. Returns the texts of
+    /// the removed items and the requests that matched nothing.
+    async fn delete_matching_items(
+        db: &Database,
+        chat_id: ChatId,
+        current: &mut Vec<Item>,
+        items: &[String],
+    ) -> Result<(Vec<String>, Vec<String>)> {
+        let (found, not_found) = take_matching_items(current, items);
+        let deleted = remove_items(db, &SharedState::new().unwrap(), chat_id, found).await?;
+        Ok((deleted, not_found))
+    }
+
+    #[tokio::test]
+    async fn voice_add_sends_transcription_feedback() {
+        let server = voice_server(
+            "add milk and two lemons",
+            200,
+            serde_json::json!({ "add": ["milk", "2 lemons"] }),
+        )
+        .await;
+        expect_feedback(
+            &server,
+            "Heard: 'add milk and two lemons' → added Milk, 2 lemons",
+            1,
+        )
+        .await;
+        let (bot, msg, config) = voice_setup(&server);
+        let db = init_test_db().await;
+
+        add_items_from_voice(
+            bot,
+            msg,
+            db.clone(),
+            SharedState::new().unwrap(),
+            config,
+            60,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(db.list_items(ChatId(1)).await.unwrap().len(), 2);
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn voice_delete_sends_transcription_feedback() {
+        let server = voice_server(
+            "delete milk",
+            200,
+            serde_json::json!({ "delete": ["Milk"] }),
+        )
+        .await;
+        expect_feedback(&server, "Heard: 'delete milk' → removed Milk", 1).await;
+        let (bot, msg, config) = voice_setup(&server);
+        let db = init_test_db().await;
+        db.add_item_count(ChatId(1), "Milk").await.unwrap();
+
+        add_items_from_voice(
+            bot,
+            msg,
+            db.clone(),
+            SharedState::new().unwrap(),
+            config,
+            60,
+        )
+        .await
+        .unwrap();
+
+        assert!(db.list_items(ChatId(1)).await.unwrap().is_empty());
+        server.verify().await;
+    }
+
+    /// A message from the voice setup's sender carrying `media` instead.
+    fn recording_message(kind: &str, media: serde_json::Value) -> Message {
+        let mut msg = serde_json::json!({
+            "message_id": 1,
+            "date": 0,
+            "chat": {"id": 1, "type": "private"},
+            "from": {"id": 5, "is_bot": false, "first_name": "Anna"},
+        });
+        msg[kind] = media;
+        serde_json::from_value(msg).unwrap()
+    }
+
+    async fn transcribed_file_names(server: &MockServer) -> Vec<String> {
+        server
+            .received_requests()
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|req| req.url.path() == "/v1/audio/transcriptions")
+            .map(|req| {
+                let body = String::from_utf8_lossy(&req.body).into_owned();
+                let start = body.find("filename=\"").unwrap() + "filename=\"".len();
+                body[start..].split('"').next().unwrap().to_string()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn audio_files_and_video_notes_are_transcribed() {
+        let server = voice_server("milk", 200, serde_json::json!({ "add": ["milk"] })).await;
+        // The second recording names Milk again, which is skipped as listed.
+        expect_feedback(&server, "Heard: 'milk' → added Milk", 1).await;
+        let (bot, _, config) = voice_setup(&server);
+        let db = init_test_db().await;
+        let audio = recording_message(
+            "audio",
+            serde_json::json!({
+                "file_id": "f", "file_unique_id": "u", "duration": 1,
+                "file_name": "list.m4a", "mime_type": "audio/mp4", "file_size": 3,
+            }),
+        );
+        let note = recording_message(
+            "video_note",
+            serde_json::json!({
+                "file_id": "f", "file_unique_id": "u", "length": 240,
+                "duration": 1, "file_size": 3,
+            }),
+        );
+
+        add_items_from_voice(
+            bot.clone(),
+            audio,
+            db.clone(),
+            SharedState::new().unwrap(),
+            config.clone(),
+            60,
+        )
+        .await
+        .unwrap();
+        add_items_from_voice(
+            bot,
+            note,
+            db.clone(),
+            SharedState::new().unwrap(),
+            config,
+            60,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            transcribed_file_names(&server).await,
+            ["list.m4a", "video_note.mp4"]
+        );
+        assert_eq!(db.list_items(ChatId(1)).await.unwrap().len(), 1);
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn oversized_recordings_are_skipped() {
+        let server = voice_server("milk", 200, serde_json::json!({ "add": ["milk"] })).await;
+        expect_feedback(&server, "too long for me to listen to", 1).await;
+        let (bot, _, config) = voice_setup(&server);
+        let db = init_test_db().await;
+        let audio = recording_message(
+            "audio",
+            serde_json::json!({
+                "file_id": "f", "file_unique_id": "u", "duration": 3600,
+                "mime_type": "audio/mpeg", "file_size": MAX_RECORDING_BYTES + 1,
+            }),
+        );
+
+        add_items_from_voice(
+            bot,
+            audio,
+            db.clone(),
+            SharedState::new().unwrap(),
+            config,
+            60,
+        )
+        .await
+        .unwrap();
+
+        let requests = server.received_requests().await.unwrap();
+        assert!(requests
+            .iter()
+            .all(|req| req.url.path() != "/botTEST/GetFile"));
+        assert!(db.list_items(ChatId(1)).await.unwrap().is_empty());
+        server.verify().await;
+    }
+
+    #[test]
+    fn unnamed_audio_gets_an_extension_from_its_type() {
+        assert_eq!(audio_extension(Some("audio/x-m4a")), "m4a");
+        assert_eq!(audio_extension(Some("audio/ogg")), "ogg");
+        assert_eq!(audio_extension(None), "mp3");
+    }
+
+    #[tokio::test]
+    async fn voice_check_marks_unchecked_items_done() {
+        let server = voice_server(
+            "milk and bread are done",
+            200,
+            serde_json::json!({ "check": ["Milk", "Bread"] }),
+        )
+        .await;
+        expect_feedback(
+            &server,
+            "Heard: 'milk and bread are done' → checked Milk",
+            1,
+        )
+        .await;
+        let (bot, msg, config) = voice_setup(&server);
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_items_count(chat, &["Milk".into(), "Bread".into()])
+            .await
+            .unwrap();
+        let bread = db.list_items(chat).await.unwrap()[1].id;
+        db.toggle_item_count(chat, bread).await.unwrap();
+
+        add_items_from_voice(
+            bot,
+            msg,
+            db.clone(),
+            SharedState::new().unwrap(),
+            config,
+            60,
+        )
+        .await
+        .unwrap();
+
+        let items = db.list_items(chat).await.unwrap();
+        assert_eq!(items.len(), 2);
+        assert!(items.iter().all(|item| item.done));
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn voice_delete_reports_items_not_found() {
+        let server = voice_server(
+            "delete milk and durian",
+            200,
+            serde_json::json!({ "delete": ["Milk", "Durian"] }),
+        )
+        .await;
+        expect_feedback(&server, "Not found: Durian", 1).await;
+        let (bot, msg, config) = voice_setup(&server);
+        let db = init_test_db().await;
+        db.add_items_count(ChatId(1), &["Milk".into(), "Eggs".into(), "Tea".into()])
+            .await
+            .unwrap();
+
+        add_items_from_voice(
+            bot,
+            msg,
+            db.clone(),
+            SharedState::new().unwrap(),
+            config,
+            60,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(db.list_items(ChatId(1)).await.unwrap().len(), 2);
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn voice_delete_of_unknown_items_says_so() {
+        let server = voice_server(
+            "delete durian",
+            200,
+            serde_json::json!({ "delete": ["Durian"] }),
+        )
+        .await;
+        expect_feedback(&server, "None of these are on the list: Durian", 1).await;
+        let (bot, msg, config) = voice_setup(&server);
+        let db = init_test_db().await;
+        db.add_item_count(ChatId(1), "Milk").await.unwrap();
+
+        add_items_from_voice(
+            bot,
+            msg,
+            db.clone(),
+            SharedState::new().unwrap(),
+            config,
+            60,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(db.list_items(ChatId(1)).await.unwrap().len(), 1);
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn voice_bulk_delete_asks_for_confirmation() {
+        let server = voice_server(
+            "delete milk eggs and bread",
+            200,
+            serde_json::json!({ "delete": ["Milk", "Eggs", "Bread"] }),
+        )
+        .await;
+        expect_feedback(&server, "Remove these items?", 1).await;
+        let (bot, msg, config) = voice_setup(&server);
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_items_count(
+            chat,
+            &["Milk".into(), "Eggs".into(), "Bread".into(), "Tea".into()],
+        )
+        .await
+        .unwrap();
+
+        add_items_from_voice(
+            bot,
+            msg,
+            db.clone(),
+            SharedState::new().unwrap(),
+            config,
+            60,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(db.list_items(chat).await.unwrap().len(), 4);
+        let prompt = db
+            .get_voice_deletion_prompt(chat, MessageId(7))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(prompt.item_ids.len(), 3);
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn voice_fallback_sends_transcription_feedback() {
+        let server = voice_server("bread, eggs", 500, serde_json::json!({})).await;
+        expect_feedback(&server, "Heard: 'bread, eggs' → added Bread, Eggs", 1).await;
+        let (bot, msg, config) = voice_setup(&server);
+        let db = init_test_db().await;
+
+        add_items_from_voice(
+            bot,
+            msg,
+            db.clone(),
+            SharedState::new().unwrap(),
+            config,
+            60,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(db.list_items(ChatId(1)).await.unwrap().len(), 2);
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn voice_falls_back_when_interpretation_times_out() {
+        let chat = ResponseTemplate::new(200)
+            .set_body_json(serde_json::json!({
+                "choices": [{ "message": { "content": r#"{"delete":["Bread"]}"# } }]
+            }))
+            .set_delay(Duration::from_secs(5));
+        let server = voice_server_answering("bread, eggs", chat).await;
+        expect_feedback(&server, "Heard: 'bread, eggs' → added Bread, Eggs", 1).await;
+        let (bot, msg, mut config) = voice_setup(&server);
+        if let Some(config) = config.as_mut() {
+            config.openai = OpenAiClient::new(
+                RequestPolicy {
+                    timeout: Duration::from_millis(200),
+                    ..*config.openai.policy()
+                },
+                Metrics::default(),
+            );
+        }
+        let db = init_test_db().await;
+
+        add_items_from_voice(
+            bot,
+            msg,
+            db.clone(),
+            SharedState::new().unwrap(),
+            config,
+            60,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(db.list_items(ChatId(1)).await.unwrap().len(), 2);
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn chat_and_configured_prompts_reach_the_models() {
+        let server = voice_server("milk", 200, serde_json::json!({ "add": ["milk"] })).await;
+        expect_feedback(&server, "Heard: 'milk' → added Milk", 1).await;
+        let (bot, msg, mut config) = voice_setup(&server);
+        if let Some(config) = config.as_mut() {
+            config
+                .prompts
+                .set(PromptKind::VoiceCommand, "Configured voice prompt");
+            config
+                .prompts
+                .set(PromptKind::Transcription, "Configured stt prompt");
+        }
+        let db = init_test_db().await;
+        db.set_setting(
+            ChatId(1),
+            PromptKind::Transcription.setting_key(),
+            "Chat stt prompt",
+        )
+        .await
+        .unwrap();
+
+        add_items_from_voice(
+            bot,
+            msg,
+            db.clone(),
+            SharedState::new().unwrap(),
+            config,
+            60,
+        )
+        .await
+        .unwrap();
+
+        let bodies: Vec<(String, String)> = server
+            .received_requests()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|req| {
+                let body = String::from_utf8_lossy(&req.body).into_owned();
+                (req.url.path().to_string(), body)
+            })
+            .collect();
+        let body_of = |endpoint: &str| {
+            bodies
+                .iter()
+                .find(|(path, _)| path == endpoint)
+                .map(|(_, body)| body.clone())
+                .unwrap()
+        };
+        assert!(body_of("/v1/audio/transcriptions").contains("Chat stt prompt"));
+        assert!(body_of("/v1/chat/completions").contains("Configured voice prompt"));
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn voice_feedback_respects_chat_setting() {
+        let server = voice_server("milk", 200, serde_json::json!({ "add": ["milk"] })).await;
+        expect_feedback(&server, "Heard:", 0).await;
+        let (bot, msg, config) = voice_setup(&server);
+        let db = init_test_db().await;
+        db.set_setting(ChatId(1), VOICE_FEEDBACK, "false")
+            .await
+            .unwrap();
+
+        add_items_from_voice(
+            bot,
+            msg,
+            db.clone(),
+            SharedState::new().unwrap(),
+            config,
+            60,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(db.list_items(ChatId(1)).await.unwrap().len(), 1);
+        server.verify().await;
+    }
 
     #[tokio::test]
     async fn delete_matching_multiple() {
@@ -142,8 +1088,10 @@ mod tests {
             db.add_item_count(chat, "Item").await.unwrap();
         }
 
+        // Repeated adds merge into one row, which a single mention removes.
         let mut current = db.list_items(chat).await.unwrap();
-        let deleted = delete_matching_items(
+        assert_eq!(current.len(), 1);
+        let (deleted, not_found) = delete_matching_items(
             &db,
             chat,
             &mut current,
@@ -151,7 +1099,8 @@ mod tests {
         )
         .await
         .unwrap();
-        assert_eq!(deleted.len(), 3);
+        assert_eq!(deleted, vec!["Item".to_string()]);
+        assert!(not_found.is_empty());
         assert!(current.is_empty());
         let remaining = db.list_items(chat).await.unwrap();
         assert!(remaining.is_empty());
@@ -166,16 +1115,21 @@ mod tests {
         db.add_item_count(chat, "Carrot").await.unwrap();
 
         let mut current = db.list_items(chat).await.unwrap();
-        let deleted = delete_matching_items(
+        let (deleted, not_found) = delete_matching_items(
             &db,
             chat,
             &mut current,
-            &["Banana".to_string(), "Carrot".to_string()],
+            &[
+                "Banana".to_string(),
+                "Durian".to_string(),
+                "Carrot".to_string(),
+            ],
         )
         .await
         .unwrap();
 
         assert_eq!(deleted, vec!["Banana".to_string(), "Carrot".to_string()]);
+        assert_eq!(not_found, vec!["Durian".to_string()]);
         assert_eq!(current.len(), 1);
         assert_eq!(current[0].text, "Apple");
 
@@ -0,0 +1,267 @@
+//! Voice commands that would remove several items, or most of the list, ask
+//! their sender to confirm first: a misheard note should not wipe the list.
+
+use anyhow::Result;
+use std::collections::HashSet;
+use teloxide::{
+    prelude::*,
+    types::{InlineKeyboardButton, InlineKeyboardMarkup, MaybeInaccessibleMessage, ThreadId, User},
+};
+
+use super::callbacks::CallbackAction;
+use super::voice::{announce_voice_removal, remove_items};
+use crate::db::{Database, Item};
+use crate::messages::{
+    items_deleted_text, voice_delete_prompt_text, VOICE_DELETE_CANCELLED,
+    VOICE_DELETE_CANCEL_LABEL, VOICE_DELETE_CONFIRM_LABEL, VOICE_DELETE_NOT_YOURS,
+    VOICE_DELETE_STALE,
+};
+use crate::state::SharedState;
+use crate::utils::{topic_of, try_delete_message, InTopic};
+
+/// Buttons of a voice deletion prompt; the prompt's message identifies it.
+pub const VOICE_DELETE_PREFIX: &str = "vdel_";
+const CONFIRM: &str = "confirm";
+const CANCEL: &str = "cancel";
+
+/// Whether removing `matched` of `list_len` items needs confirming: more
+/// than `threshold` items, or more than half the list. Single items never do.
+pub fn needs_confirmation(matched: usize, list_len: usize, threshold: usize) -> bool {
+    matched > 1 && (matched > threshold || matched * 2 > list_len)
+}
+
+fn voice_delete_keyboard() -> InlineKeyboardMarkup {
+    InlineKeyboardMarkup::new(vec![vec![
+        InlineKeyboardButton::callback(
+            VOICE_DELETE_CONFIRM_LABEL,
+            CallbackAction::VoiceDelete(CONFIRM.to_string()).to_string(),
+        ),
+        InlineKeyboardButton::callback(
+            VOICE_DELETE_CANCEL_LABEL,
+            CallbackAction::VoiceDelete(CANCEL.to_string()).to_string(),
+        ),
+    ]])
+}
+
+/// Post the items a voice command would remove and wait for `owner` to
+/// confirm.
+pub async fn ask_voice_deletion(
+    bot: &Bot,
+    chat_id: ChatId,
+    topic: Option<ThreadId>,
+    db: &Database,
+    owner: UserId,
+    items: &[Item],
+) -> Result<()> {
+    tracing::debug!(
+        chat_id = chat_id.0,
+        count = items.len(),
+        "Asking to confirm voice deletion"
+    );
+    let labels: Vec<String> = items.iter().map(Item::label).collect();
+    let prompt = bot
+        .send_message(chat_id, voice_delete_prompt_text(&labels))
+        .reply_markup(voice_delete_keyboard())
+        .in_topic(topic)
+        .await?;
+    let ids: Vec<i64> = items.iter().map(|item| item.id).collect();
+    db.create_voice_deletion_prompt(
+        chat_id,
+        prompt.id,
+        owner.0 as i64,
+        &ids,
+        chrono::Utc::now().timestamp(),
+    )
+    .await
+}
+
+/// Answer a voice deletion prompt. Only the sender of the voice message may;
+/// their answer removes the prompt and, if confirmed, the items still on the
+/// list.
+pub async fn handle_voice_delete_callback(
+    bot: &Bot,
+    msg: &MaybeInaccessibleMessage,
+    from: &User,
+    db: &Database,
+    shared: &SharedState,
+    data: &str,
+) -> Result<Option<String>> {
+    let chat_id = msg.chat().id;
+    let Some(prompt) = db.get_voice_deletion_prompt(chat_id, msg.id()).await? else {
+        try_delete_message(bot, chat_id, msg.id()).await;
+        return Ok(Some(VOICE_DELETE_STALE.to_string()));
+    };
+    if prompt.user_id != from.id.0 as i64 {
+        tracing::debug!(
+            chat_id = chat_id.0,
+            user_id = from.id.0,
+            owner = prompt.user_id,
+            "Ignoring voice deletion answer from another user"
+        );
+        return Ok(Some(VOICE_DELETE_NOT_YOURS.to_string()));
+    }
+    if !db.take_voice_deletion_prompt(chat_id, msg.id()).await? {
+        return Ok(Some(VOICE_DELETE_STALE.to_string()));
+    }
+    let confirmed = data == CONFIRM;
+    tracing::debug!(chat_id = chat_id.0, confirmed, "Voice deletion answered");
+    try_delete_message(bot, chat_id, msg.id()).await;
+    if !confirmed {
+        return Ok(Some(VOICE_DELETE_CANCELLED.to_string()));
+    }
+
+    let ids: HashSet<i64> = prompt.item_ids.into_iter().collect();
+    let found: Vec<Item> = db
+        .list_items(chat_id)
+        .await?
+        .into_iter()
+        .filter(|item| ids.contains(&item.id))
+        .collect();
+    let deleted = remove_items(db, shared, chat_id, found).await?;
+    if deleted.is_empty() {
+        return Ok(None);
+    }
+    let topic = msg.regular_message().and_then(topic_of);
+    announce_voice_removal(bot, db, shared, chat_id, topic, &deleted, &[]).await?;
+    Ok(Some(items_deleted_text(deleted.len() as u64)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::util::{init_test_db, test_bot, SENT_MESSAGE};
+    use teloxide::types::MessageId;
+    use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+    async fn server() -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(SENT_MESSAGE, "application/json"))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    fn prompt() -> MaybeInaccessibleMessage {
+        serde_json::from_str(
+            r#"{"message_id":9,"date":0,"chat":{"id":1,"type":"group","title":"g"}}"#,
+        )
+        .unwrap()
+    }
+
+    fn user(id: u64) -> User {
+        serde_json::from_value(serde_json::json!({"id": id, "is_bot": false, "first_name": "Anna"}))
+            .unwrap()
+    }
+
+    #[test]
+    fn bulk_or_majority_deletions_need_confirming() {
+        assert!(!needs_confirmation(1, 1, 3));
+        assert!(!needs_confirmation(2, 10, 3));
+        assert!(!needs_confirmation(3, 10, 3));
+        assert!(needs_confirmation(4, 10, 3));
+        assert!(!needs_confirmation(2, 4, 3));
+        assert!(needs_confirmation(3, 4, 3));
+    }
+
+    #[tokio::test]
+    async fn only_the_sender_can_confirm() {
+        let server = server().await;
+        let bot = test_bot(&server);
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_items_count(chat, &["Milk".into(), "Eggs".into(), "Tea".into()])
+            .await
+            .unwrap();
+        let ids: Vec<i64> = db.list_items(chat).await.unwrap()[..2]
+            .iter()
+            .map(|item| item.id)
+            .collect();
+        db.create_voice_deletion_prompt(chat, MessageId(9), 7, &ids, 0)
+            .await
+            .unwrap();
+
+        let answer = handle_voice_delete_callback(
+            &bot,
+            &prompt(),
+            &user(8),
+            &db,
+            &SharedState::new().unwrap(),
+            CONFIRM,
+        )
+        .await
+        .unwrap();
+        assert_eq!(answer.as_deref(), Some(VOICE_DELETE_NOT_YOURS));
+        assert_eq!(db.list_items(chat).await.unwrap().len(), 3);
+
+        let answer = handle_voice_delete_callback(
+            &bot,
+            &prompt(),
+            &user(7),
+            &db,
+            &SharedState::new().unwrap(),
+            CONFIRM,
+        )
+        .await
+        .unwrap();
+        assert_eq!(answer, Some(items_deleted_text(2)));
+        let left: Vec<String> = db
+            .list_items(chat)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|item| item.text)
+            .collect();
+        assert_eq!(left, ["Tea"]);
+
+        let answer = handle_voice_delete_callback(
+            &bot,
+            &prompt(),
+            &user(7),
+            &db,
+            &SharedState::new().unwrap(),
+            CONFIRM,
+        )
+        .await
+        .unwrap();
+        assert_eq!(answer.as_deref(), Some(VOICE_DELETE_STALE));
+    }
+
+    #[tokio::test]
+    async fn cancelling_keeps_the_items() {
+        let server = server().await;
+        let db = init_test_db().await;
+        let chat = ChatId(1);
+        db.add_items_count(chat, &["Milk".into(), "Eggs".into()])
+            .await
+            .unwrap();
+        let ids: Vec<i64> = db
+            .list_items(chat)
+            .await
+            .unwrap()
+            .iter()
+            .map(|item| item.id)
+            .collect();
+        db.create_voice_deletion_prompt(chat, MessageId(9), 7, &ids, 0)
+            .await
+            .unwrap();
+
+        let answer = handle_voice_delete_callback(
+            &test_bot(&server),
+            &prompt(),
+            &user(7),
+            &db,
+            &SharedState::new().unwrap(),
+            CANCEL,
+        )
+        .await
+        .unwrap();
+        assert_eq!(answer.as_deref(), Some(VOICE_DELETE_CANCELLED));
+        assert_eq!(db.list_items(chat).await.unwrap().len(), 2);
+        assert!(db
+            .get_voice_deletion_prompt(chat, MessageId(9))
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,316 @@
+use anyhow::Result;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use teloxide::prelude::*;
+use teloxide::types::ParseMode;
+
+use super::tokens::{generate_token, hide_group_command, send_private_reply};
+use crate::db::{Database, Webhook};
+use crate::messages::{
+    webhook_added_text, WEBHOOKS_EMPTY, WEBHOOK_DM_FAILED, WEBHOOK_INVALID_URL, WEBHOOK_NOT_FOUND,
+    WEBHOOK_REMOVED, WEBHOOK_USAGE,
+};
+use crate::render::escape;
+
+/// A parsed `/webhook` invocation.
+#[derive(Debug, PartialEq)]
+enum WebhookCommand {
+    Add(String),
+    List,
+    Remove(i64),
+}
+
+fn parse_webhook_command(text: &str) -> Option<WebhookCommand> {
+    let mut words = text.split_whitespace();
+    let command = match (words.next()?, words.next()) {
+        ("add", Some(url)) => WebhookCommand::Add(url.to_string()),
+        ("list", None) => WebhookCommand::List,
+        ("remove", Some(id)) => WebhookCommand::Remove(id.parse().ok()?),
+        _ => return None,
+    };
+    words.next().is_none().then_some(command)
+}
+
+/// Only absolute http(s) URLs on public hosts can receive deliveries, so
+/// chat members cannot make the bot call services on its own network. Names
+/// are checked as written; they are not resolved.
+fn valid_webhook_url(url: &str) -> bool {
+    let Ok(url) = reqwest::Url::parse(url) else {
+        return false;
+    };
+    if !matches!(url.scheme(), "http" | "https") {
+        return false;
+    }
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => is_public_ipv4(ip),
+        Ok(IpAddr::V6(ip)) => is_public_ipv6(ip),
+        Err(_) => is_public_domain(host),
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [first, second, ..] = ip.octets();
+    // 0.0.0.0/8 and the 100.64.0.0/10 carrier-grade NAT range.
+    let reserved = first == 0 || (first == 100 && second & 0xc0 == 64);
+    !(ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || reserved)
+}
+
+fn is_public_ipv6(ip: Ipv6Addr) -> bool {
+    if let Some(mapped) = ip.to_ipv4_mapped() {
+        return is_public_ipv4(mapped);
+    }
+    let first = ip.segments()[0];
+    // fc00::/7 unique local and fe80::/10 link-local addresses.
+    let local = first & 0xfe00 == 0xfc00 || first & 0xffc0 == 0xfe80;
+    !(ip.is_loopback() || ip.is_unspecified() || local)
+}
+
+/// Single-label names and local-only suffixes only resolve inside a network.
+fn is_public_domain(host: &str) -> bool {
+    let host = host.trim_end_matches('.');
+    host.contains('.')
+        && ![".localhost", ".local", ".internal", ".lan", ".home.arpa"]
+            .iter()
+            .any(|suffix| host.ends_with(suffix))
+}
+
+fn format_webhook_list(hooks: &[Webhook]) -> String {
+    let lines: Vec<String> = hooks
+        .iter()
+        .map(|hook| format!("{}. <code>{}</code>", hook.id, escape(&hook.url)))
+        .collect();
+    format!("<b>Webhooks</b>\n\n{}", lines.join("\n"))
+}
+
+/// Handle `/webhook add <url>`, `/webhook list` and `/webhook remove <id>`.
+pub async fn handle_webhook(bot: Bot, msg: Message, db: Database, args: String) -> Result<()> {
+    let chat_id = msg.chat.id;
+    let Some(command) = parse_webhook_command(&args) else {
+        bot.send_message(chat_id, WEBHOOK_USAGE).await?;
+        return Ok(());
+    };
+    tracing::debug!(chat_id = chat_id.0, ?command, "Webhook command");
+
+    match command {
+        WebhookCommand::Add(url) => {
+            hide_group_command(&bot, &db, &msg).await?;
+            if !valid_webhook_url(&url) {
+                bot.send_message(chat_id, WEBHOOK_INVALID_URL).await?;
+                return Ok(());
+            }
+            let secret = generate_token();
+            let id = db
+                .add_webhook(chat_id, &url, &secret, chrono::Utc::now().timestamp())
+                .await?;
+            let text = webhook_added_text(id, &escape(&url), &secret);
+            if !send_private_reply(&bot, chat_id, msg.from.as_ref(), text).await? {
+                // Nobody could ever verify deliveries signed with a secret
+                // that was never shown.
+                db.remove_webhook(chat_id, id).await?;
+                tracing::debug!(
+                    chat_id = chat_id.0,
+                    id,
+                    "Dropped webhook with undelivered secret"
+                );
+                bot.send_message(chat_id, WEBHOOK_DM_FAILED).await?;
+            }
+        }
+        WebhookCommand::List => {
+            let hooks = db.list_webhooks(chat_id).await?;
+            if hooks.is_empty() {
+                bot.send_message(chat_id, WEBHOOKS_EMPTY).await?;
+            } else {
+                bot.send_message(chat_id, format_webhook_list(&hooks))
+                    .parse_mode(ParseMode::Html)
+                    .await?;
+            }
+        }
+        WebhookCommand::Remove(id) => {
+            let response = if db.remove_webhook(chat_id, id).await? {
+                WEBHOOK_REMOVED
+            } else {
+                WEBHOOK_NOT_FOUND
+            };
+            bot.send_message(chat_id, response).await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::util::{init_test_db, test_bot};
+    use wiremock::{
+        matchers::{body_string_contains, method, path, path_regex},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[test]
+    fn parses_subcommands() {
+        assert_eq!(
+            parse_webhook_command("add https://hub.local/hook"),
+            Some(WebhookCommand::Add("https://hub.local/hook".into()))
+        );
+        assert_eq!(parse_webhook_command(" list "), Some(WebhookCommand::List));
+        assert_eq!(
+            parse_webhook_command("remove 3"),
+            Some(WebhookCommand::Remove(3))
+        );
+        assert_eq!(parse_webhook_command(""), None);
+        assert_eq!(parse_webhook_command("add"), None);
+        assert_eq!(parse_webhook_command("remove three"), None);
+        assert_eq!(parse_webhook_command("list extra"), None);
+    }
+
+    #[test]
+    fn accepts_only_http_urls() {
+        assert!(valid_webhook_url("https://hooks.example.com/hook"));
+        assert!(valid_webhook_url("http://203.0.113.5:8123/api/webhook/x"));
+        assert!(!valid_webhook_url("ftp://hooks.example.com/hook"));
+        assert!(!valid_webhook_url("hooks.example.com/hook"));
+    }
+
+    #[test]
+    fn rejects_internal_hosts() {
+        for url in [
+            "http://localhost:8080/hook",
+            "http://api.localhost/hook",
+            "https://hub.local/hook",
+            "http://router/hook",
+            "http://127.0.0.1/hook",
+            "http://0x7f.1/hook",
+            "http://10.0.0.8/hook",
+            "http://192.168.1.5:8123/api/webhook/x",
+            "http://169.254.169.254/latest/meta-data",
+            "http://100.64.0.1/hook",
+            "http://0.0.0.0/hook",
+            "http://[::1]/hook",
+            "http://[fd00::1]/hook",
+            "http://[fe80::1]/hook",
+            "http://[::ffff:127.0.0.1]/hook",
+        ] {
+            assert!(!valid_webhook_url(url), "{url} should be rejected");
+        }
+    }
+
+    fn group_command(text: &str) -> Message {
+        serde_json::from_value(serde_json::json!({
+            "message_id": 3,
+            "date": 0,
+            "chat": {"id": -5, "type": "group", "title": "Family"},
+            "from": {"id": 7, "is_bot": false, "first_name": "Ann"},
+            "text": text,
+        }))
+        .unwrap()
+    }
+
+    /// Expects the command to be deleted and answers messages to user 7
+    /// with `dm_status` and `dm_body`.
+    async fn group_server(dm_status: u16, dm_body: &str) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path_regex(r"^/botTEST/[Dd]eleteMessage$"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(r#"{"ok":true,"result":true}"#, "application/json"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SendMessage"))
+            .and(body_string_contains(r#""chat_id":7"#))
+            .respond_with(
+                ResponseTemplate::new(dm_status).set_body_raw(dm_body, "application/json"),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/SendMessage"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(
+                r#"{"ok":true,"result":{"message_id":9,"date":0,"chat":{"id":-5,"type":"group","title":"Family"}}}"#,
+                "application/json",
+            ))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    /// Chat id and text of every message sent.
+    async fn sent_messages(server: &MockServer) -> Vec<(i64, String)> {
+        server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .filter(|request| request.url.path() == "/botTEST/SendMessage")
+            .map(|request| {
+                let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+                (
+                    body["chat_id"].as_i64().unwrap(),
+                    body["text"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn group_secrets_are_sent_privately() {
+        let server = group_server(
+            200,
+            r#"{"ok":true,"result":{"message_id":4,"date":0,"chat":{"id":7,"type":"private"}}}"#,
+        )
+        .await;
+        let db = init_test_db().await;
+
+        handle_webhook(
+            test_bot(&server),
+            group_command("/webhook add https://hooks.example.com/hook"),
+            db.clone(),
+            "add https://hooks.example.com/hook".into(),
+        )
+        .await
+        .unwrap();
+
+        let hooks = db.list_webhooks(ChatId(-5)).await.unwrap();
+        assert_eq!(hooks.len(), 1);
+        let sent = sent_messages(&server).await;
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].0, 7);
+        assert!(sent[0].1.contains(&hooks[0].secret));
+        server.verify().await;
+    }
+
+    #[tokio::test]
+    async fn webhook_is_dropped_when_the_dm_fails() {
+        let server = group_server(
+            403,
+            r#"{"ok":false,"error_code":403,"description":"Forbidden: bot can't initiate conversation with a user"}"#,
+        )
+        .await;
+        let db = init_test_db().await;
+
+        handle_webhook(
+            test_bot(&server),
+            group_command("/webhook add https://hooks.example.com/hook"),
+            db.clone(),
+            "add https://hooks.example.com/hook".into(),
+        )
+        .await
+        .unwrap();
+
+        assert!(db.list_webhooks(ChatId(-5)).await.unwrap().is_empty());
+        let sent = sent_messages(&server).await;
+        assert_eq!(sent.last(), Some(&(-5, WEBHOOK_DM_FAILED.to_string())));
+        server.verify().await;
+    }
+}
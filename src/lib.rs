@@ -3,41 +3,78 @@ use teloxide::prelude::*;
 
 pub mod ai;
 mod api;
+mod chat_locks;
 mod command_handler;
 mod commands;
 mod config;
 pub mod db;
+mod export;
 mod handlers;
+mod list_style;
 mod messages;
+mod metrics;
+mod notify;
+mod render;
+mod shutdown;
+mod startup_checks;
+mod state;
 mod system_info;
+mod telemetry;
 mod text_utils;
 mod utils;
 
 pub mod tests;
 
+use ai::common::OpenAiClient;
 pub use ai::gpt::parse_items_gpt;
 pub use ai::stt::parse_items;
-pub use api::{router as api_router, ApiConfig};
-use command_handler::handle_command;
+pub use api::{
+    router as api_router, serve as serve_api, ApiConfig, CorsOrigins, DEFAULT_MAX_ITEM_LENGTH,
+};
+use command_handler::{handle_command, CommandContext};
 pub use commands::Command;
-pub use config::Config;
+pub use config::{AdminCommands, Config, DeleteSessionTtl, PublicUrl};
 pub use db::Item;
 pub use handlers::{
     add_items_from_parsed_text, add_items_from_photo, add_items_from_text, add_items_from_voice,
-    callback_handler, enter_delete_mode, format_delete_list, format_list, format_plain_list, help,
-    insert_items, issue_token, list_tokens, revoke_token, show_system_info, ListService,
+    callback_handler, enter_delete_mode, format_delete_list, format_list, format_list_checked_last,
+    format_plain_list, handle_my_chat_member, help, insert_items, issue_token, list_tokens,
+    revoke_token, show_system_info, update_items_from_edit, ListService,
 };
+pub use list_style::{ListStyle, ListStylePreset};
 pub use messages::*;
+pub use metrics::Metrics;
+pub use state::SharedState;
 pub use system_info::get_system_info;
 pub use text_utils::{capitalize_first, normalize_for_match, parse_item_line};
 pub use utils::delete_after;
 
-pub async fn run() -> Result<()> {
-    let config = Config::from_env();
-
+fn init_tracing() {
     tracing_subscriber::fmt()
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
         .init();
+}
+
+/// Mark partially applied migrations as resolved after checking their schema
+/// objects exist, then exit without starting the bot.
+pub async fn repair_migrations() -> Result<()> {
+    let config = Config::from_env();
+    init_tracing();
+
+    let db_url = db::prepare_sqlite_url(&config.db_url);
+    let pool = db::connect_db(&db_url, config.db_pool_size, config.db_busy_timeout).await?;
+    let repaired = db::migrations::repair_migrations(&pool, &db::migrations::MIGRATOR).await?;
+    if repaired.is_empty() {
+        tracing::info!("No dirty migrations found");
+    } else {
+        tracing::info!(?repaired, "Repaired migrations");
+    }
+    Ok(())
+}
+
+pub async fn run() -> Result<()> {
+    let config = Config::from_env();
+    init_tracing();
 
     tracing::info!("Starting list bot...");
 
@@ -51,48 +88,114 @@ pub async fn run() -> Result<()> {
             "OpenAI configuration loaded"
         );
     }
-    let ai_config = config.ai.clone();
+    let shared = SharedState::new()?;
+    // OpenAI calls count into the registry `GET /metrics` renders.
+    let ai_config = config.ai.clone().map(|mut cfg| {
+        cfg.openai = OpenAiClient::new(*cfg.openai.policy(), shared.metrics.clone());
+        cfg
+    });
     let delete_after_timeout = config.delete_after_timeout;
+    let delete_session_ttl = config.delete_session_ttl;
+    let command_context = CommandContext {
+        ai_config: ai_config.clone(),
+        delete_after_timeout,
+        delete_session_ttl,
+        public_url: config.public_url.clone(),
+        admin_commands: config.admin_commands.clone(),
+    };
 
     // --- SQLite Pool ---
     let db_url = db::prepare_sqlite_url(&config.db_url);
 
     tracing::info!("Connecting to database at: {}", &db_url);
 
-    let pool = db::connect_db(&db_url, config.db_pool_size).await?;
+    let pool = db::connect_db(&db_url, config.db_pool_size, config.db_busy_timeout).await?;
     let db = db::Database::new(pool);
 
     tracing::info!("Database connection successful.");
 
-    sqlx::migrate!("./migrations").run(&*db).await?;
+    db::migrations::run_migrations(&db).await?;
+
+    if config.startup_checks {
+        let report = startup_checks::run_startup_checks(
+            &bot,
+            &db,
+            ai_config.as_ref(),
+            startup_checks::STARTUP_CHECK_TIMEOUT,
+        )
+        .await;
+        if !report.ok() {
+            anyhow::bail!("Startup checks failed: {}", report.summary());
+        }
+    }
+
+    commands::register_commands(&bot).await;
+    handlers::refresh::resume_pending_refreshes(&bot, &db, &shared).await?;
+    handlers::photo::resume_pending_albums(&bot, &db, &shared, ai_config.as_ref()).await?;
+    handlers::trash::spawn_trash_purge(db.clone());
+    handlers::auto_archive::spawn_auto_archive(bot.clone(), db.clone(), shared.clone());
 
     let api_addr = config.api_bind_addr.clone();
     let api_listener = tokio::net::TcpListener::bind(&api_addr).await?;
     let api_config = api::ApiConfig {
         rate_limit_per_second: config.api_rate_limit_per_second,
+        integration_secret: config.integration_secret.clone(),
+        mutation_rate_per_second: config.api_mutation_rate_per_second,
+        max_item_length: config.api_max_item_length,
+        cors_origins: config.api_cors_origins.clone(),
+        log_request_bodies: config.api_log_request_bodies,
     };
     if let Some(limit) = api_config.rate_limit_per_second {
         tracing::info!(limit, "API rate limit configured");
     }
-    let api_router = api::router(db.clone(), api_config);
-    tracing::info!(api_addr = %api_addr, "API server listening");
-    tokio::spawn(async move {
-        if let Err(err) = axum::serve(api_listener, api_router).await {
-            tracing::error!(error = %err, "API server stopped");
+    if let Some(rate) = api_config.mutation_rate_per_second {
+        tracing::info!(rate, "API mutation scheduling configured");
+    }
+    if let Some(origins) = &api_config.cors_origins {
+        tracing::info!(?origins, "API CORS configured");
+    }
+    let api_router = api::router(db.clone(), shared.clone(), api_config);
+    // Every `ctrl_c` listener is woken, so this stops alongside the dispatcher.
+    let shutdown = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            tracing::error!(error = %err, "Failed to listen for Ctrl-C");
+            std::future::pending::<()>().await;
         }
-    });
+    };
+    // Requests in flight at Ctrl-C get the grace period to finish.
+    let api_grace = async {
+        if tokio::signal::ctrl_c().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+        tokio::time::sleep(shutdown::SHUTDOWN_GRACE_PERIOD).await;
+    };
+    let api_server = async move {
+        tokio::select! {
+            result = api::serve(api_listener, api_router, shutdown) => {
+                if let Err(err) = result {
+                    tracing::error!(error = %err, "API server stopped");
+                }
+            }
+            () = api_grace => {
+                tracing::warn!("API requests still running after the grace period");
+            }
+        }
+    };
+    let shutdown_db = db.clone();
 
     // --- Command Enum ---
     // defined in the commands module
 
     // --- Handler Setup ---
-    let handler = dptree::entry()
+    let handler = telemetry::instrument_updates()
+        .branch(Update::filter_my_chat_member().endpoint(handle_my_chat_member))
         .branch(Update::filter_callback_query().endpoint(callback_handler))
+        .branch(Update::filter_edited_message().endpoint(update_items_from_edit))
         .branch(
             Update::filter_message()
                 .branch(
                     dptree::entry()
-                        .filter(|msg: Message| msg.voice().is_some())
+                        .filter(|msg: Message| handlers::voice::has_recording(&msg))
                         .endpoint(add_items_from_voice),
                 )
                 .branch(
@@ -100,26 +203,39 @@ pub async fn run() -> Result<()> {
                         .filter(|msg: Message| msg.photo().is_some())
                         .endpoint(add_items_from_photo),
                 )
-                .branch(dptree::entry().filter_command::<Command>().endpoint(
-                    |bot: Bot,
-                     msg: Message,
-                     cmd: Command,
-                     db: db::Database,
-                     ai_config: Option<crate::ai::config::AiConfig>,
-                     delete_after_timeout: u64| async move {
-                        handle_command(bot, msg, cmd, db, ai_config, delete_after_timeout).await
-                    },
-                ))
+                .branch(
+                    dptree::entry()
+                        .filter_command::<Command>()
+                        .endpoint(handle_command),
+                )
                 .branch(dptree::endpoint(add_items_from_text)),
         );
 
     // --- Dispatcher ---
-    Dispatcher::builder(bot, handler)
-        .dependencies(dptree::deps![db, ai_config, delete_after_timeout])
+    let tasks = shared.tasks.clone();
+    let mut dispatcher = Dispatcher::builder(bot, handler)
+        .dependencies(dptree::deps![
+            db,
+            shared,
+            ai_config,
+            delete_after_timeout,
+            delete_session_ttl,
+            command_context
+        ])
         .enable_ctrlc_handler()
-        .build()
-        .dispatch()
-        .await;
+        .build();
+
+    tokio::join!(dispatcher.dispatch(), api_server);
+
+    tracing::info!(active = tasks.active(), "Waiting for in-flight work");
+    if !tasks.wait(shutdown::SHUTDOWN_GRACE_PERIOD).await {
+        tracing::warn!(
+            active = tasks.active(),
+            "Shutting down with work still in flight"
+        );
+    }
+    shutdown_db.close().await;
+    tracing::info!("Shutdown complete");
 
     Ok(())
 }
@@ -0,0 +1,128 @@
+//! Marker sets used when rendering list entries.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListStylePreset {
+    Classic,
+    Minimal,
+    Colorful,
+}
+
+impl ListStylePreset {
+    pub const ALL: [Self; 3] = [Self::Classic, Self::Minimal, Self::Colorful];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Classic => "classic",
+            Self::Minimal => "minimal",
+            Self::Colorful => "colorful",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|preset| preset.name().eq_ignore_ascii_case(value.trim()))
+    }
+
+    pub fn style(self) -> ListStyle {
+        let (unchecked, checked, all_done, selected) = match self {
+            Self::Classic => ("⬜", "☑️", "✅", "❌"),
+            Self::Minimal => ("▫️", "✓", "✓", "✗"),
+            Self::Colorful => ("🛒", "✔️", "🎉", "❌"),
+        };
+        ListStyle {
+            unchecked: unchecked.to_string(),
+            checked: checked.to_string(),
+            all_done: all_done.to_string(),
+            selected: selected.to_string(),
+        }
+    }
+}
+
+/// Prefix of the stored setting value for user-defined markers.
+const CUSTOM_PREFIX: &str = "custom:";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListStyle {
+    pub unchecked: String,
+    pub checked: String,
+    pub all_done: String,
+    pub selected: String,
+}
+
+impl Default for ListStyle {
+    fn default() -> Self {
+        ListStylePreset::Classic.style()
+    }
+}
+
+impl ListStyle {
+    /// Build a style from three whitespace separated markers
+    /// (unchecked, checked, all done).
+    pub fn custom(markers: &str) -> Option<Self> {
+        let parts: Vec<&str> = markers.split_whitespace().collect();
+        let [unchecked, checked, all_done] = parts.as_slice() else {
+            return None;
+        };
+        Some(Self {
+            unchecked: unchecked.to_string(),
+            checked: checked.to_string(),
+            all_done: all_done.to_string(),
+            selected: ListStylePreset::Classic.style().selected,
+        })
+    }
+
+    /// Parse the value stored in the `list_style` chat setting.
+    pub fn from_setting(value: &str) -> Option<Self> {
+        match value.strip_prefix(CUSTOM_PREFIX) {
+            Some(markers) => Self::custom(markers),
+            None => ListStylePreset::parse(value).map(ListStylePreset::style),
+        }
+    }
+
+    /// Value to persist for user-defined markers.
+    pub fn custom_setting_value(&self) -> String {
+        format!(
+            "{CUSTOM_PREFIX}{} {} {}",
+            self.unchecked, self.checked, self.all_done
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn presets_parse_by_name() {
+        for preset in ListStylePreset::ALL {
+            assert_eq!(ListStylePreset::parse(preset.name()), Some(preset));
+            assert_eq!(ListStyle::from_setting(preset.name()), Some(preset.style()));
+        }
+        assert_eq!(
+            ListStylePreset::parse(" Minimal "),
+            Some(ListStylePreset::Minimal)
+        );
+        assert!(ListStylePreset::parse("fancy").is_none());
+    }
+
+    #[test]
+    fn custom_requires_three_markers() {
+        assert!(ListStyle::custom("a b").is_none());
+        assert!(ListStyle::custom("a b c d").is_none());
+        assert!(ListStyle::custom("[ ] [x] [*]").is_none());
+        let style = ListStyle::custom("○ ● ★").unwrap();
+        assert_eq!(style.all_done, "★");
+        assert_eq!(style.selected, "❌");
+    }
+
+    proptest! {
+        #[test]
+        fn custom_setting_roundtrip(markers in proptest::collection::vec("[^\\s]{1,4}", 3)) {
+            let style = ListStyle::custom(&markers.join(" ")).unwrap();
+            let stored = style.custom_setting_value();
+            prop_assert_eq!(ListStyle::from_setting(&stored), Some(style));
+        }
+    }
+}
@@ -2,5 +2,8 @@ use anyhow::Result;
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    if std::env::args().any(|arg| arg == "--repair-migrations") {
+        return shopbot::repair_migrations().await;
+    }
     shopbot::run().await
 }
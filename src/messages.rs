@@ -3,21 +3,107 @@
 //! Keep all user-facing strings in this module so they stay in one place and are
 //! easy to update or translate.
 
-pub const HELP_TEXT: &str =
+use crate::db::UndoAction;
+
+pub const HELP_INTRO: &str =
     "Send me any text to add it to your list. Each line will be a new item.\n\
-             You can tap the checkbox button next to an item to mark it as bought.\n\n\
-             <b>Commands:</b>\n\
-             /list - Show the current list.\n\
-             /archive - Finalize and archive the current list, starting a new one.\n\
-             /done - Archive only checked items, keeping the rest.\n\
-             /delete - Show a temporary panel to delete items from the list.\n\
-             /share - Send the list as plain text for copying.\n\
-             /nuke - Completely delete the current list.\n\
-             /parse - Parse this message into items via GPT.\n\
-             /info - Show system information.\n\
-             /create_token [name] - Issue a new token for this list.\n\
-             /tokens - List issued tokens for this list.\n\
-             /revoke_token <token> - Revoke a token.";
+     You can tap the checkbox button next to an item to mark it as bought.";
+pub const HELP_COMMANDS_HEADER: &str = "Commands:";
+/// One plain-text line per command; they are escaped when the help is rendered.
+pub const HELP_COMMANDS: &[&str] = &[
+    "/list - Show the current list.",
+    "/archive - Finalize and archive the current list, starting a new one.",
+    "/done - Archive only checked items, keeping the rest.",
+    "/history - Show recently archived lists.",
+    "/delete - Show a temporary panel to delete items from the list.",
+    "/edit - Pick an item in a private chat and send its new text.",
+    "/assign - Claim items in a private chat so others know who buys what.",
+    "/mine - Show the items assigned to you.",
+    "/share - Send the list as plain text for copying.",
+    "/export [text|markdown|csv] - Send the list as a file.",
+    "/nuke - Completely delete the current list, after you confirm.",
+    "/undo - Reverse the last nuke, archive, or deletion.",
+    "/trash - Restore recently deleted items.",
+    "/parse - Parse this message into items via GPT.",
+    "/info - Show system information.",
+    "/create_token [name] [lifetime] [--readonly|--admin] - Issue a new token for this list, e.g. /create_token guest 7d.",
+    "/tokens - List issued tokens for this list.",
+    "/revoke_token <token> - Revoke a token.",
+    "/webhook add <url> | list | remove <id> - Notify a URL whenever the list changes.",
+    "/sharelink [revoke] - Get a read-only web link to the list, or revoke it.",
+    "/settings - Open the settings panel.",
+    "/settings style [name] - Preview or choose the list markers.",
+    "/settings tidy [on|off] - Remove messages that add items once they are listed.",
+    "/settings duplicates [uncheck|add] - What to do when nobody answers a checked-off duplicate prompt.",
+    "/settings autoarchive [hours|off] - Archive checked items once nothing was checked off for that long.",
+];
+
+pub const ONBOARDING_TEXT: &str =
+    "Welcome! Send me any text and each line becomes an item on your list.\n\
+     Try one of these to see how it works:";
+pub const ONBOARDING_SAMPLE_BUTTON: &str = "🛒 Add sample items";
+pub const ONBOARDING_VOICE_BUTTON: &str = "🎙 Voice parsing info";
+pub const ONBOARDING_TOKEN_BUTTON: &str = "🔑 Create an API token";
+pub const ONBOARDING_SAMPLE_HINT: &str =
+    "Tap an item in the list above to check it off, tap it again to undo. Send /help for all commands.";
+pub const ONBOARDING_VOICE_INFO: &str =
+    "Send a voice message such as \"milk, eggs and bread\" and I'll transcribe it into items. \
+     You can also say \"remove the eggs\", or send a photo of a handwritten list. \
+     Voice and photo parsing are available when the bot owner has configured an OpenAI key.";
+
+pub const SETTINGS_USAGE: &str = "Usage: /settings\n\
+     /settings style [classic|minimal|colorful|custom <unchecked> <checked> <all done>]\n\
+     /settings tidy [on|off]\n\
+     /settings strict [on|off]\n\
+     /settings dedupe [on|off]\n\
+     /settings duplicates [uncheck|add]\n\
+     /settings autoarchive [hours|off]";
+pub const TIDY_USAGE: &str = "Usage: /settings tidy [on|off]";
+pub const TIDY_ENABLED: &str = "I'll remove messages that add items once they're on the list.";
+pub const TIDY_DISABLED: &str = "I'll keep messages that add items.";
+pub const STRICT_USAGE: &str = "Usage: /settings strict [on|off]";
+pub const STRICT_ENABLED: &str =
+    "Strict mode is on: I'll skip questions and chatter instead of adding them as items.";
+pub const STRICT_DISABLED: &str = "Strict mode is off: every text message adds items.";
+pub const DEDUPE_USAGE: &str = "Usage: /settings dedupe [on|off]";
+pub const DEDUPE_ENABLED: &str =
+    "Items read from /parse, photos and voice that are already on the list will be skipped.";
+pub const DEDUPE_DISABLED: &str =
+    "Items read from /parse, photos and voice are added even when already on the list.";
+pub const RECEIPT_USAGE: &str = "Usage: /receipt [on|off]";
+pub const RECEIPT_ENABLED: &str =
+    "Receipt mode is on: photos are read as receipts, and items keep the price paid.";
+pub const RECEIPT_DISABLED: &str = "Receipt mode is off: photos only add item names.";
+pub const DELETE_RIGHTS_NEEDED: &str = "I need the 'Delete messages' right for this.";
+pub const SETTINGS_PANEL_TEXT: &str = "Settings for this chat. Tap a row to change it:";
+pub const SETTINGS_BACK_BUTTON: &str = "⬅️ Back";
+
+pub fn settings_choice_text(label: &str) -> String {
+    format!("{label}:")
+}
+
+pub const DUPLICATES_USAGE: &str = "Usage: /settings duplicates [uncheck|add]";
+
+pub fn duplicates_default_text(action: &str) -> String {
+    format!("Unanswered duplicate prompts will {action} the item.")
+}
+
+pub const AUTO_ARCHIVE_USAGE: &str = "Usage: /settings autoarchive [hours|off]";
+pub const AUTO_ARCHIVE_DISABLED: &str = "Checked items are only archived with /done.";
+
+pub fn auto_archive_enabled_text(hours: u32) -> String {
+    format!("Checked items will be archived once nothing has been checked off for {hours} h.")
+}
+
+pub fn duplicate_checked_text(item: &str) -> String {
+    format!("{item} is already checked off — add again or uncheck it?")
+}
+pub const DUPLICATE_ADD_BUTTON: &str = "➕ Add again";
+pub const DUPLICATE_UNCHECK_BUTTON: &str = "↩️ Uncheck";
+pub const LIST_STYLE_PREVIEW_HEADER: &str = "Available list styles:";
+pub const LIST_STYLE_UPDATED: &str = "List style updated:";
+pub const LIST_STYLE_INVALID: &str =
+    "Unknown list style. Use a preset name or \"custom\" followed by three markers.";
 
 pub const GPT_PARSING_DISABLED: &str = "GPT parsing is disabled.";
 
@@ -28,15 +114,86 @@ pub const LIST_EMPTY_ADD_ITEM: &str = "Your list is empty! Send any message to a
 pub const LIST_EMPTY: &str = "Your list is empty!";
 pub const LIST_NOW_EMPTY: &str = "List is now empty!";
 pub const LIST_ARCHIVED: &str = "List archived! Send a message to start a new one.";
+pub const LIST_AUTO_ARCHIVED: &str =
+    "Every item was checked off a while ago, so I archived the list automatically. \
+     Send a message to start a new one.";
 pub const LIST_NUKED: &str = "The active list has been nuked.";
+pub const NUKE_CONFIRM_PROMPT: &str = "💣 Delete every item on the list?";
+pub const NUKE_CONFIRM_LABEL: &str = "💣 Yes, nuke it";
+pub const NUKE_CANCEL_LABEL: &str = "Cancel";
+pub const NUKE_CANCELLED: &str = "Nuke cancelled";
+pub const NUKE_NOT_YOURS: &str = "Only whoever sent /nuke can answer this";
+pub const ADMIN_ONLY_COMMAND: &str = "Only group admins can use this command here.";
 pub const CHECKED_ITEMS_ARCHIVED: &str = "Checked items archived!";
+pub const CHECKED_ITEMS_AUTO_ARCHIVED: &str = "Checked items archived automatically!";
 pub const NO_CHECKED_ITEMS_TO_ARCHIVE: &str = "There are no checked items to archive.";
+pub const NOTHING_TO_UNDO: &str = "There is nothing left to undo.";
+
+pub fn undo_done_text(action: UndoAction, count: usize) -> String {
+    let what = match action {
+        UndoAction::Nuke => "the nuke",
+        UndoAction::Archive => "the archive",
+        UndoAction::Delete => "the deletion",
+    };
+    let noun = if count == 1 { "item" } else { "items" };
+    format!("↩️ Undid {what}: {count} {noun} back on the list.")
+}
 
 pub const TOKEN_ISSUED: &str = "Here is your new token:";
 pub const TOKENS_EMPTY: &str = "No tokens have been issued for this list yet.";
-pub const TOKEN_REVOKE_USAGE: &str = "Usage: /revoke_token <token>";
+pub const TOKEN_REVOKE_USAGE: &str = "Usage: /revoke_token <id, preview or token>";
 pub const TOKEN_REVOKED: &str = "Token revoked.";
 pub const TOKEN_NOT_FOUND: &str = "Token not found or already revoked.";
+pub const TOKEN_AMBIGUOUS: &str =
+    "Several tokens share that preview; revoke by the id shown in /tokens.";
+pub const TOKEN_DM_FAILED: &str =
+    "Tokens are only sent privately, and I couldn't message you. Have you started me in private?";
+pub const PAIRING_DONE: &str = "🔗 Device connected. It can now read and change this list.";
+pub const PAIRING_EXPIRED: &str =
+    "This connect link has expired or was already used. Start connecting again from your app.";
+
+pub const WEBHOOK_USAGE: &str = "Usage: /webhook add <url>, /webhook list, or /webhook remove <id>";
+pub const WEBHOOK_INVALID_URL: &str =
+    "Webhook URLs must start with http:// or https:// and point to a public host.";
+pub const WEBHOOK_DM_FAILED: &str =
+    "Webhook secrets are only sent privately, and I couldn't message you. \
+     Start me in private and add the webhook again.";
+pub const WEBHOOKS_EMPTY: &str = "No webhooks are registered for this list.";
+pub const WEBHOOK_REMOVED: &str = "Webhook removed.";
+pub const WEBHOOK_NOT_FOUND: &str = "Webhook not found.";
+pub fn webhook_added_text(id: i64, url: &str, secret: &str) -> String {
+    format!(
+        "Webhook {id} added for <code>{url}</code>.\n\
+         Deliveries are signed with this secret (shown only once):\n<code>{secret}</code>"
+    )
+}
+
+pub const PROMPT_USAGE: &str = "Usage: /prompt <text|photo|transcription|voice> to show a prompt, \
+     add new instructions to replace it for this chat, or add reset to go back to the default.";
+pub const PROMPT_SAVED: &str = "Prompt saved for this chat.";
+pub const PROMPT_RESET: &str = "Prompt reset. This chat uses the default again.";
+pub const PROMPT_NOT_OVERRIDDEN: &str = "This chat has no prompt of its own to reset.";
+pub fn prompt_overview_text(lines: &[String]) -> String {
+    format!("Prompts in use:\n{}\n\n{PROMPT_USAGE}", lines.join("\n"))
+}
+pub fn prompt_text(name: &str, source: &str, prompt: &str) -> String {
+    format!("The {name} prompt ({source}):\n\n{prompt}")
+}
+
+pub const EXPORT_USAGE: &str = "Usage: /export [text|markdown|csv]";
+pub const SHARE_LINK_USAGE: &str = "Usage: /sharelink or /sharelink revoke";
+pub const SHARE_LINK_REVOKED: &str = "Share link revoked. The old link no longer works.";
+pub const SHARE_LINK_NONE: &str = "This list has no share link to revoke.";
+pub fn share_link_text(url: &str, absolute: bool) -> String {
+    let mut text = format!(
+        "Anyone with this link can view the list:\n{url}\n\
+         Send /sharelink revoke to disable it."
+    );
+    if !absolute {
+        text.push_str("\nSet PUBLIC_URL to send the full address of the API here.");
+    }
+    text
+}
 
 pub const DELETE_SELECT_PROMPT: &str = "Select items to delete, then tap 'Done Deleting'.";
 pub const DELETE_DONE_LABEL: &str = "🗑️ Done Deleting";
@@ -47,9 +204,161 @@ pub fn delete_dm_text(chat_name: &str, list_text: &str) -> String {
 pub fn delete_user_selecting_text(user_name: &str) -> String {
     format!("{user_name} is selecting items to delete...")
 }
-pub const DELETE_DM_FAILED: &str =
-    "Unable to send you a private delete panel. Have you started me in private?";
+pub const ITEM_GONE: &str = "Item no longer exists";
+pub const DELETE_PANEL_STALE: &str = "This delete panel is no longer active";
+pub const DELETE_SESSION_EXPIRED: &str = "Session expired, run /delete again";
+
+pub fn item_toggled_text(item: &str, done: bool) -> String {
+    if done {
+        format!("✅ {item} checked")
+    } else {
+        format!("⬜ {item} unchecked")
+    }
+}
+
+pub fn item_selected_text(item: &str, selected: bool) -> String {
+    if selected {
+        format!("❌ {item} selected for deletion")
+    } else {
+        format!("{item} no longer selected")
+    }
+}
+
+pub fn item_deleted_text(item: &str) -> String {
+    format!("🗑 {item} deleted")
+}
+
+pub fn items_deleted_text(count: u64) -> String {
+    format!("🗑 {count} items deleted")
+}
+
+pub fn delete_user_removed_text(user_name: &str, count: u64) -> String {
+    let noun = if count == 1 { "item" } else { "items" };
+    format!("{user_name} removed {count} {noun}.")
+}
+
+pub fn delete_session_expired_text(user_name: &str) -> String {
+    format!("{user_name}'s delete session expired.")
+}
+pub fn delete_in_chat_text(user_name: &str, list_text: &str) -> String {
+    format!("I couldn't message {user_name} privately, so the delete panel is here. Only {user_name} can use it.\n\n{list_text}")
+}
+pub const DELETE_PANEL_NOT_YOURS: &str = "This panel isn't yours";
+pub const ASSIGN_SELECT_PROMPT: &str =
+    "Tap the items you will take care of; tap one of yours again to give it up.";
+pub const ASSIGN_DONE_LABEL: &str = "✅ Done";
+pub const ASSIGN_FINISHED: &str = "Assignments saved.";
+pub const ASSIGN_PANEL_STALE: &str = "This assign panel is no longer active";
+pub const ASSIGN_DM_FAILED: &str =
+    "Unable to send you a private assign panel. Have you started me in private?";
+pub fn assign_dm_text(chat_name: &str, prompt: &str) -> String {
+    format!("Assigning items in {chat_name}.\n\n{prompt}")
+}
+pub fn item_assigned_text(item: &str, mine: bool) -> String {
+    if mine {
+        format!("✋ {item} is yours")
+    } else {
+        format!("{item} is no longer yours")
+    }
+}
+pub const MINE_EMPTY: &str = "Nothing on the list is assigned to you. Use /assign to claim items.";
+pub fn mine_text(list: &str) -> String {
+    format!("Your items:\n{list}")
+}
+
+pub const EDIT_SELECT_PROMPT: &str = "Tap the item you want to rename.";
+pub const EDIT_CANCEL_LABEL: &str = "✖️ Cancel";
+pub const EDIT_CANCELLED: &str = "Edit cancelled.";
+pub const EDIT_ITEM_GONE: &str = "That item is no longer on the list.";
+pub const EDIT_DM_FAILED: &str =
+    "Unable to send you a private edit panel. Have you started me in private?";
+pub fn edit_dm_text(chat_name: &str, prompt: &str) -> String {
+    format!("Editing items in {chat_name}.\n\n{prompt}")
+}
+
+pub fn edit_send_new_text(item: &str) -> String {
+    format!("Send me the new text for \"{item}\".")
+}
+
+pub fn item_renamed_text(item: &str) -> String {
+    format!("Renamed to \"{item}\".")
+}
 pub const DEFAULT_CHAT_NAME: &str = "your list";
 
 pub const ARCHIVED_LIST_HEADER: &str = "--- Archived List ---";
+pub fn list_progress_text(done: usize, total: usize) -> String {
+    format!("{done} of {total} done")
+}
+
+pub fn list_total_text(total: &str) -> String {
+    format!("Total: {total}")
+}
+
+pub const CHECKED_ITEMS_SEPARATOR: &str = "── Checked ──";
+pub const LIST_PAGE_PREV: &str = "◀ Prev";
+pub const LIST_PAGE_NEXT: &str = "Next ▶";
+
+pub fn list_page_label(page: usize, pages: usize) -> String {
+    format!("{page}/{pages}")
+}
+
+pub const UNCATEGORIZED_HEADER: &str = "Other";
+pub const HISTORY_HEADER: &str = "Recent archives:";
+pub const HISTORY_EMPTY: &str = "No archived lists yet. Use /archive to save one.";
+pub const TRASH_HEADER: &str = "Recently deleted items. Tap one to put it back on the list:";
+pub const TRASH_EMPTY: &str = "The trash is empty.";
+
+pub fn archive_entry_header(date: &str, count: usize) -> String {
+    let noun = if count == 1 { "item" } else { "items" };
+    format!("📦 {date} · {count} {noun}")
+}
 pub const VOICE_REMOVED_PREFIX: &str = "🗑 Removed via voice request:\n";
+/// Starts the line of a voice removal naming requests not on the list.
+pub const VOICE_NOT_FOUND_LABEL: &str = "Not found:";
+
+pub fn voice_removed_text(deleted: &[String], not_found: &[String]) -> String {
+    let lines: Vec<String> = deleted.iter().map(|t| format!("• {t}")).collect();
+    let mut text = format!("{VOICE_REMOVED_PREFIX}{}", lines.join("\n"));
+    if !not_found.is_empty() {
+        text.push_str(&format!(
+            "\n\n{VOICE_NOT_FOUND_LABEL} {}",
+            not_found.join(", ")
+        ));
+    }
+    text
+}
+
+pub fn voice_nothing_found_text(requested: &[String]) -> String {
+    format!("🤷 None of these are on the list: {}", requested.join(", "))
+}
+pub fn recording_too_large_text(max_mb: u32) -> String {
+    format!("🎙 That recording is too long for me to listen to (the limit is {max_mb} MB)")
+}
+pub const VOICE_DELETE_CONFIRM_LABEL: &str = "Confirm ✅";
+pub const VOICE_DELETE_CANCEL_LABEL: &str = "Cancel ❌";
+pub const VOICE_DELETE_CANCELLED: &str = "Nothing was removed";
+pub const VOICE_DELETE_NOT_YOURS: &str = "Only whoever sent the voice message can answer this";
+pub const VOICE_DELETE_STALE: &str = "This request is no longer active";
+
+pub fn voice_delete_prompt_text(items: &[String]) -> String {
+    let lines: Vec<String> = items.iter().map(|item| format!("• {item}")).collect();
+    format!("🎙 Remove these items?\n{}", lines.join("\n"))
+}
+
+pub fn voice_heard_added_text(transcript: &str, items: &[String]) -> String {
+    format!("🎙 Heard: '{transcript}' → added {}", items.join(", "))
+}
+
+pub fn voice_heard_removed_text(transcript: &str, items: &[String]) -> String {
+    format!("🎙 Heard: '{transcript}' → removed {}", items.join(", "))
+}
+
+pub fn voice_heard_checked_text(transcript: &str, items: &[String]) -> String {
+    format!("🎙 Heard: '{transcript}' → checked {}", items.join(", "))
+}
+
+pub const INTEGRATION_NO_ITEMS: &str = "I didn't catch any items to add.";
+
+pub fn integration_added_text(items: &[String]) -> String {
+    format!("Added {} to your list.", items.join(", "))
+}
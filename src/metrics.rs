@@ -0,0 +1,227 @@
+//! Counters, gauges and histograms rendered in the Prometheus text exposition
+//! format by `GET /metrics`. One registry lives in [`crate::state::SharedState`]
+//! and clones share it. Values reset on restart, as Prometheus expects from
+//! counters.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Upper bounds in seconds shared by every latency histogram.
+const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    /// Observations per bucket, not cumulative; the last slot is `+Inf`.
+    buckets: [u64; LATENCY_BUCKETS.len() + 1],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        let slot = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| seconds <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.buckets[slot] += 1;
+        self.sum += seconds;
+        self.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+        for (bound, observed) in LATENCY_BUCKETS.iter().zip(self.buckets) {
+            cumulative += observed;
+            let _ = writeln!(out, "{name}_bucket{{{labels},le=\"{bound}\"}} {cumulative}");
+        }
+        let _ = writeln!(out, "{name}_bucket{{{labels},le=\"+Inf\"}} {}", self.count);
+        let _ = writeln!(out, "{name}_sum{{{labels}}} {}", self.sum);
+        let _ = writeln!(out, "{name}_count{{{labels}}} {}", self.count);
+    }
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    /// Keyed by method, matched route and status code.
+    api_requests: BTreeMap<(String, String, u16), u64>,
+    api_latency: BTreeMap<String, Histogram>,
+    /// Keyed by where the items came from: telegram, api or integration.
+    items_added: BTreeMap<&'static str, u64>,
+    /// Keyed by OpenAI endpoint path and outcome.
+    openai_requests: BTreeMap<(String, &'static str), u64>,
+    openai_latency: BTreeMap<String, Histogram>,
+    /// API mutations waiting for the write scheduler.
+    write_queue_depth: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    registry: Arc<Mutex<Registry>>,
+}
+
+impl Metrics {
+    fn registry(&self) -> std::sync::MutexGuard<'_, Registry> {
+        self.registry
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Count a finished API request; `route` is the matched route pattern so
+    /// item ids do not create a series each.
+    pub fn record_api_request(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        let mut registry = self.registry();
+        *registry
+            .api_requests
+            .entry((method.to_string(), route.to_string(), status))
+            .or_default() += 1;
+        registry
+            .api_latency
+            .entry(route.to_string())
+            .or_default()
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn record_items_added(&self, source: &'static str, count: u64) {
+        *self.registry().items_added.entry(source).or_default() += count;
+    }
+
+    pub fn record_openai_request(&self, endpoint: &str, success: bool, elapsed: Duration) {
+        let outcome = if success { "ok" } else { "error" };
+        let mut registry = self.registry();
+        *registry
+            .openai_requests
+            .entry((endpoint.to_string(), outcome))
+            .or_default() += 1;
+        registry
+            .openai_latency
+            .entry(endpoint.to_string())
+            .or_default()
+            .observe(elapsed.as_secs_f64());
+    }
+
+    pub fn set_write_queue_depth(&self, depth: usize) {
+        self.registry().write_queue_depth = depth;
+    }
+
+    /// Render every series in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let registry = self.registry();
+        let mut out = String::new();
+
+        out.push_str("# HELP shopbot_api_requests_total API requests by route and status.\n");
+        out.push_str("# TYPE shopbot_api_requests_total counter\n");
+        for ((method, route, status), count) in &registry.api_requests {
+            let _ = writeln!(
+                out,
+                "shopbot_api_requests_total{{method=\"{}\",route=\"{}\",status=\"{status}\"}} {count}",
+                escape_label(method),
+                escape_label(route),
+            );
+        }
+
+        out.push_str("# HELP shopbot_api_request_duration_seconds API request latency.\n");
+        out.push_str("# TYPE shopbot_api_request_duration_seconds histogram\n");
+        for (route, histogram) in &registry.api_latency {
+            histogram.render(
+                &mut out,
+                "shopbot_api_request_duration_seconds",
+                &format!("route=\"{}\"", escape_label(route)),
+            );
+        }
+
+        out.push_str("# HELP shopbot_items_added_total Items added by source.\n");
+        out.push_str("# TYPE shopbot_items_added_total counter\n");
+        for (source, count) in &registry.items_added {
+            let _ = writeln!(
+                out,
+                "shopbot_items_added_total{{source=\"{source}\"}} {count}"
+            );
+        }
+
+        out.push_str("# HELP shopbot_openai_requests_total OpenAI requests by outcome.\n");
+        out.push_str("# TYPE shopbot_openai_requests_total counter\n");
+        for ((endpoint, outcome), count) in &registry.openai_requests {
+            let _ = writeln!(
+                out,
+                "shopbot_openai_requests_total{{endpoint=\"{}\",outcome=\"{outcome}\"}} {count}",
+                escape_label(endpoint),
+            );
+        }
+
+        out.push_str("# HELP shopbot_openai_request_duration_seconds OpenAI request latency.\n");
+        out.push_str("# TYPE shopbot_openai_request_duration_seconds histogram\n");
+        for (endpoint, histogram) in &registry.openai_latency {
+            histogram.render(
+                &mut out,
+                "shopbot_openai_request_duration_seconds",
+                &format!("endpoint=\"{}\"", escape_label(endpoint)),
+            );
+        }
+
+        out.push_str("# HELP shopbot_write_queue_depth API mutations waiting for admission.\n");
+        out.push_str("# TYPE shopbot_write_queue_depth gauge\n");
+        let _ = writeln!(
+            out,
+            "shopbot_write_queue_depth {}",
+            registry.write_queue_depth
+        );
+        out
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn histogram_buckets_are_cumulative() {
+        let metrics = Metrics::default();
+        for millis in [3, 40, 40, 20_000] {
+            metrics.record_api_request("GET", "/api/list", 200, Duration::from_millis(millis));
+        }
+        let text = metrics.render();
+        assert!(text.contains(
+            "shopbot_api_requests_total{method=\"GET\",route=\"/api/list\",status=\"200\"} 4"
+        ));
+        for line in [
+            "shopbot_api_request_duration_seconds_bucket{route=\"/api/list\",le=\"0.005\"} 1",
+            "shopbot_api_request_duration_seconds_bucket{route=\"/api/list\",le=\"0.05\"} 3",
+            "shopbot_api_request_duration_seconds_bucket{route=\"/api/list\",le=\"10\"} 3",
+            "shopbot_api_request_duration_seconds_bucket{route=\"/api/list\",le=\"+Inf\"} 4",
+            "shopbot_api_request_duration_seconds_count{route=\"/api/list\"} 4",
+        ] {
+            assert!(text.contains(line), "missing {line} in\n{text}");
+        }
+    }
+
+    #[test]
+    fn write_queue_depth_is_a_gauge() {
+        let metrics = Metrics::default();
+        assert!(metrics.render().contains("shopbot_write_queue_depth 0\n"));
+        metrics.set_write_queue_depth(3);
+        metrics.set_write_queue_depth(2);
+        let text = metrics.render();
+        assert!(text.contains("# TYPE shopbot_write_queue_depth gauge\n"));
+        assert!(text.contains("shopbot_write_queue_depth 2\n"));
+    }
+
+    #[test]
+    fn labels_are_escaped() {
+        let metrics = Metrics::default();
+        metrics.record_openai_request("/v1/\"odd\"", false, Duration::from_millis(1));
+        assert!(metrics.render().contains(
+            "shopbot_openai_requests_total{endpoint=\"/v1/\\\"odd\\\"\",outcome=\"error\"} 1"
+        ));
+    }
+}
@@ -0,0 +1,350 @@
+//! Webhook notifications sent after a chat's list changes.
+//!
+//! Every change also wakes API clients long-polling for the chat's list
+//! revision, which the write bumped in its own transaction.
+//!
+//! Deliveries run in the background so neither Telegram replies nor API
+//! responses wait for a slow receiver. Each request carries an
+//! `x-shopbot-signature: sha256=<hex>` HMAC of the body using the webhook's
+//! secret, the same scheme the assistant integrations accept.
+
+use anyhow::Result;
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use teloxide::types::ChatId;
+use tokio::sync::Notify;
+use tracing::Instrument;
+
+use crate::api::SIGNATURE_HEADER;
+use crate::db::{Database, Item, Webhook};
+use crate::shutdown::TaskTracker;
+
+/// Attempts per delivery before it is dropped.
+pub const DELIVERY_ATTEMPTS: u32 = 3;
+/// Wait before the first retry; doubled for every further attempt.
+const RETRY_BACKOFF: Duration = Duration::from_millis(500);
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ListEventKind {
+    Add,
+    Toggle,
+    Update,
+    Delete,
+    Archive,
+    ArchiveDone,
+    Nuke,
+    Reorder,
+    /// Items brought back by `/undo` or from the trash.
+    Restore,
+}
+
+/// What changed. `item_id` names the affected item for single-item events;
+/// batch events only carry the number of affected items.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListEvent {
+    pub chat_id: ChatId,
+    pub kind: ListEventKind,
+    pub item_id: Option<i64>,
+    pub affected: u64,
+}
+
+impl ListEvent {
+    pub fn item(chat_id: ChatId, kind: ListEventKind, item_id: i64) -> Self {
+        Self {
+            chat_id,
+            kind,
+            item_id: Some(item_id),
+            affected: 1,
+        }
+    }
+
+    pub fn batch(chat_id: ChatId, kind: ListEventKind, affected: u64) -> Self {
+        Self {
+            chat_id,
+            kind,
+            item_id: None,
+            affected,
+        }
+    }
+}
+
+/// JSON body of a delivery. `item` is the item's current state, or `null`
+/// when it no longer is on the list (e.g. after a delete).
+#[derive(Debug, Serialize)]
+struct EventPayload<'a> {
+    chat_id: i64,
+    event: ListEventKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    item_id: Option<i64>,
+    item: Option<&'a Item>,
+    affected: u64,
+}
+
+/// Sends events to every webhook registered for the event's chat.
+#[derive(Debug, Clone)]
+pub struct Notifier {
+    client: reqwest::Client,
+    backoff: Duration,
+}
+
+impl Notifier {
+    pub fn new(client: reqwest::Client, backoff: Duration) -> Self {
+        Self { client, backoff }
+    }
+
+    /// Deliver `event` to all of the chat's webhooks. Failures are logged,
+    /// never returned.
+    pub async fn dispatch(&self, db: &Database, event: &ListEvent) {
+        let hooks = match db.list_webhooks(event.chat_id).await {
+            Ok(hooks) => hooks,
+            Err(err) => {
+                tracing::warn!(chat_id = event.chat_id.0, error = %err, "Failed to load webhooks");
+                return;
+            }
+        };
+        if hooks.is_empty() {
+            return;
+        }
+
+        let item = match event.item_id {
+            Some(id) => db.get_item(event.chat_id, id).await.unwrap_or_else(|err| {
+                tracing::warn!(chat_id = event.chat_id.0, item_id = id, error = %err, "Failed to load event item");
+                None
+            }),
+            None => None,
+        };
+        let payload = EventPayload {
+            chat_id: event.chat_id.0,
+            event: event.kind,
+            item_id: event.item_id,
+            item: item.as_ref(),
+            affected: event.affected,
+        };
+        let body = match serde_json::to_vec(&payload) {
+            Ok(body) => body,
+            Err(err) => {
+                tracing::error!(error = %err, "Failed to serialize webhook payload");
+                return;
+            }
+        };
+        for hook in &hooks {
+            self.deliver(hook, &body).await;
+        }
+    }
+
+    /// POST `body` to one webhook, retrying failed attempts with exponential
+    /// backoff. Returns whether the receiver accepted it.
+    pub async fn deliver(&self, hook: &Webhook, body: &[u8]) -> bool {
+        let signature = sign(&hook.secret, body);
+        for attempt in 1..=DELIVERY_ATTEMPTS {
+            let result = self
+                .client
+                .post(&hook.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(SIGNATURE_HEADER, &signature)
+                .body(body.to_vec())
+                .send()
+                .await
+                .and_then(reqwest::Response::error_for_status);
+            match result {
+                Ok(_) => {
+                    tracing::debug!(webhook_id = hook.id, attempt, "Delivered webhook");
+                    return true;
+                }
+                Err(err) => {
+                    tracing::warn!(webhook_id = hook.id, attempt, error = %err, "Webhook delivery failed");
+                }
+            }
+            if attempt < DELIVERY_ATTEMPTS {
+                tokio::time::sleep(self.backoff * 2u32.pow(attempt - 1)).await;
+            }
+        }
+        false
+    }
+}
+
+/// `sha256=<hex>` HMAC of `body` keyed with `secret`.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Wake-ups for requests waiting on a chat's next revision.
+#[derive(Debug, Default)]
+pub struct ChangeWaiters {
+    by_chat: Mutex<HashMap<ChatId, Arc<Notify>>>,
+}
+
+impl ChangeWaiters {
+    fn by_chat(&self) -> std::sync::MutexGuard<'_, HashMap<ChatId, Arc<Notify>>> {
+        self.by_chat
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Handle notified every time the chat's revision is bumped. Enable its
+    /// `notified()` future before reading the revision so no bump is missed.
+    pub fn subscribe(&self, chat_id: ChatId) -> Arc<Notify> {
+        self.by_chat().entry(chat_id).or_default().clone()
+    }
+
+    /// Wake every request waiting on the chat and forget chats nobody waits on.
+    pub fn wake(&self, chat_id: ChatId) {
+        let mut by_chat = self.by_chat();
+        if let Some(notify) = by_chat.get(&chat_id) {
+            notify.notify_waiters();
+        }
+        by_chat.retain(|_, notify| Arc::strong_count(notify) > 1);
+    }
+}
+
+/// Fans list changes out to long-polling clients and webhooks. Built once in
+/// `run()`; clones share the waiters.
+#[derive(Debug, Clone)]
+pub struct ListEvents {
+    waiters: Arc<ChangeWaiters>,
+    notifier: Notifier,
+    /// Deliveries are tracked so shutdown waits for them.
+    tasks: TaskTracker,
+}
+
+impl ListEvents {
+    /// Fails when the webhook client cannot be built; there is no fallback,
+    /// since a default client would follow redirects.
+    pub fn new(tasks: TaskTracker) -> Result<Self> {
+        // Redirects could lead deliveries to hosts `/webhook add` refuses.
+        let client = reqwest::Client::builder()
+            .timeout(DELIVERY_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::none())
+            .build()?;
+        Ok(Self {
+            waiters: Arc::default(),
+            notifier: Notifier::new(client, RETRY_BACKOFF),
+            tasks,
+        })
+    }
+
+    pub fn waiters(&self) -> &ChangeWaiters {
+        &self.waiters
+    }
+
+    /// Wake long-polling clients and notify the chat's webhooks about
+    /// `event` in the background. The write itself already bumped the
+    /// chat's revision.
+    pub fn list_changed(&self, db: &Database, event: ListEvent) {
+        tracing::trace!(chat_id = event.chat_id.0, kind = ?event.kind, "Queueing list event");
+        self.waiters.wake(event.chat_id);
+        let db = db.clone();
+        let notifier = self.notifier.clone();
+        self.tasks.spawn(
+            async move { notifier.dispatch(&db, &event).await }
+                .instrument(tracing::Span::current()),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::util::init_test_db;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn test_notifier() -> Notifier {
+        let client = reqwest::Client::builder().no_proxy().build().unwrap();
+        Notifier::new(client, Duration::from_millis(1))
+    }
+
+    #[tokio::test]
+    async fn toggle_event_is_signed_and_carries_the_item() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/hook"))
+            .and(header("content-type", "application/json"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let db = init_test_db().await;
+        let chat = ChatId(5);
+        let item = db.add_item_returning(chat, "Milk", None).await.unwrap();
+        db.toggle_item_count(chat, item.id).await.unwrap();
+        db.add_webhook(chat, &format!("{}/hook", server.uri()), "hub-secret", 1)
+            .await
+            .unwrap();
+
+        test_notifier()
+            .dispatch(&db, &ListEvent::item(chat, ListEventKind::Toggle, item.id))
+            .await;
+
+        let requests = server.received_requests().await.unwrap();
+        let request = &requests[0];
+        let signature = request.headers.get(SIGNATURE_HEADER).unwrap();
+        assert_eq!(
+            signature.to_str().unwrap(),
+            sign("hub-secret", &request.body)
+        );
+
+        let payload: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(payload["chat_id"], 5);
+        assert_eq!(payload["event"], "toggle");
+        assert_eq!(payload["item"]["id"], item.id);
+        assert_eq!(payload["item"]["text"], "Milk");
+        assert_eq!(payload["item"]["done"], true);
+    }
+
+    #[tokio::test]
+    async fn delivery_retries_until_accepted() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(204))
+            .mount(&server)
+            .await;
+
+        let hook = Webhook {
+            id: 1,
+            chat_id: 1,
+            url: server.uri(),
+            secret: "s".into(),
+            created_at: 0,
+        };
+        assert!(test_notifier().deliver(&hook, b"{}").await);
+        assert_eq!(server.received_requests().await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn delivery_gives_up_after_bounded_attempts() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&server)
+            .await;
+
+        let hook = Webhook {
+            id: 1,
+            chat_id: 1,
+            url: server.uri(),
+            secret: "s".into(),
+            created_at: 0,
+        };
+        assert!(!test_notifier().deliver(&hook, b"{}").await);
+        assert_eq!(
+            server.received_requests().await.unwrap().len(),
+            DELIVERY_ATTEMPTS as usize
+        );
+    }
+}
@@ -0,0 +1,144 @@
+//! HTML for messages sent with `ParseMode::Html`. Anything not produced by
+//! this module is treated as plain text and escaped, so item text such as
+//! `<b>milk` or `Salt & pepper` shows up as typed instead of breaking the
+//! message.
+
+use crate::messages::{HELP_COMMANDS, HELP_COMMANDS_HEADER, HELP_INTRO};
+
+/// Escape `<`, `>` and `&` in plain text.
+pub fn escape(text: &str) -> String {
+    teloxide::utils::html::escape(text)
+}
+
+/// Plain text in bold.
+pub fn bold(text: &str) -> String {
+    format!("<b>{}</b>", escape(text))
+}
+
+/// The `/help` message.
+pub fn help() -> String {
+    let mut text = format!("{}\n\n{}\n", escape(HELP_INTRO), bold(HELP_COMMANDS_HEADER));
+    let commands: Vec<String> = HELP_COMMANDS.iter().map(|line| escape(line)).collect();
+    text.push_str(&commands.join("\n"));
+    text
+}
+
+/// Check `text` the way Telegram parses HTML: only the tags this module
+/// emits, properly nested, and only the entities [`escape`] produces.
+#[cfg(test)]
+pub(crate) fn check_html(text: &str) -> Result<(), String> {
+    const TAGS: [&str; 4] = ["b", "i", "u", "code"];
+    let mut open: Vec<&str> = Vec::new();
+    let mut rest = text;
+    while let Some(index) = rest.find(['<', '&']) {
+        let tail = &rest[index..];
+        if tail.starts_with('&') {
+            let entity = ["&lt;", "&gt;", "&amp;", "&quot;"]
+                .into_iter()
+                .find(|entity| tail.starts_with(entity))
+                .ok_or_else(|| format!("bare '&' in {text:?}"))?;
+            rest = &tail[entity.len()..];
+            continue;
+        }
+        let end = tail
+            .find('>')
+            .ok_or_else(|| format!("unclosed tag in {text:?}"))?;
+        let tag = &tail[1..end];
+        if let Some(name) = tag.strip_prefix('/') {
+            if open.pop() != Some(name) {
+                return Err(format!("unexpected </{name}> in {text:?}"));
+            }
+        } else if TAGS.contains(&tag) {
+            open.push(tag);
+        } else {
+            return Err(format!("unsupported tag <{tag}> in {text:?}"));
+        }
+        rest = &tail[end + 1..];
+    }
+    match open.last() {
+        Some(tag) => Err(format!("<{tag}> never closed in {text:?}")),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{ChatSettings, Item};
+    use crate::handlers::history::format_history;
+    use crate::handlers::list::{format_archived_list, format_list_page};
+    use crate::list_style::ListStyle;
+
+    fn item(id: i64, text: &str, category: Option<&str>) -> Item {
+        Item {
+            id,
+            text: text.to_string(),
+            done: id % 2 == 0,
+            quantity: 1,
+            added_by_user_id: Some(7),
+            added_by_name: Some("<Ann & Bo>".to_string()),
+            category: category.map(str::to_string),
+            created_at: 0,
+            assigned_to_user_id: None,
+            assigned_to_name: None,
+            price_cents: None,
+        }
+    }
+
+    fn tricky_items() -> Vec<Item> {
+        vec![
+            item(1, "<b>milk", Some("<dairy>")),
+            item(2, "Salt & pepper", None),
+            item(3, "🥑 Avocado > 2", Some("produce & fruit")),
+            item(4, "a</b>", Some("<dairy>")),
+        ]
+    }
+
+    #[test]
+    fn check_html_rejects_what_telegram_rejects() {
+        assert!(check_html("<b>Dairy</b>\n&lt;b&gt;milk &amp; 🥛").is_ok());
+        assert!(check_html("<b>milk").is_err());
+        assert!(check_html("Salt & pepper").is_err());
+        assert!(check_html("/revoke_token <token>").is_err());
+        assert!(check_html("a</b>").is_err());
+    }
+
+    #[test]
+    fn help_parses_as_html() {
+        let help = help();
+        check_html(&help).unwrap();
+        assert!(help.contains("<b>Commands:</b>"));
+        assert!(help.contains("/revoke_token &lt;token&gt; - Revoke a token."));
+    }
+
+    #[test]
+    fn grouped_list_escapes_items_and_headers() {
+        let settings = ChatSettings {
+            show_authors: true,
+            checked_last: true,
+            ..Default::default()
+        };
+        let text = format_list_page(&tricky_items(), &settings, 0).text;
+        check_html(&text).unwrap();
+        assert!(text.contains("<b>&lt;dairy&gt;</b>"));
+        assert!(text.contains("&lt;b&gt;milk (&lt;Ann &amp; Bo&gt;)"));
+        assert!(text.contains("Salt &amp; pepper"));
+        assert!(text.contains("🥑 Avocado &gt; 2"));
+    }
+
+    #[test]
+    fn archived_output_escapes_items() {
+        let style = ListStyle::default();
+        let items = tricky_items();
+        let archived = format_archived_list(&items, &style);
+        check_html(&archived).unwrap();
+        assert!(archived.contains("a&lt;/b&gt;"));
+
+        let archive = crate::db::ArchiveRecord {
+            id: 1,
+            archived_at: 0,
+            items,
+        };
+        check_html(&format_history(&[archive], &style)).unwrap();
+    }
+}
@@ -0,0 +1,114 @@
+//! Tracks in-flight work so shutdown can let it finish, for at most a grace
+//! period, before the database pool is closed.
+
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// How long shutdown waits for in-flight requests and tracked tasks.
+pub const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Default)]
+struct Inner {
+    active: AtomicUsize,
+    idle: Notify,
+}
+
+/// Counts running pieces of work; clones share the count.
+#[derive(Clone, Debug, Default)]
+pub struct TaskTracker {
+    inner: Arc<Inner>,
+}
+
+/// Marks one piece of work as running until dropped.
+#[derive(Debug)]
+pub struct TaskGuard {
+    inner: Arc<Inner>,
+}
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        if self.inner.active.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.inner.idle.notify_waiters();
+        }
+    }
+}
+
+impl TaskTracker {
+    /// Count the caller as running until the guard is dropped.
+    pub fn track(&self) -> TaskGuard {
+        self.inner.active.fetch_add(1, Ordering::AcqRel);
+        TaskGuard {
+            inner: self.inner.clone(),
+        }
+    }
+
+    /// `tokio::spawn` a task that is tracked until it completes.
+    pub fn spawn<F>(&self, task: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let guard = self.track();
+        tokio::spawn(async move {
+            let _guard = guard;
+            task.await
+        })
+    }
+
+    pub fn active(&self) -> usize {
+        self.inner.active.load(Ordering::Acquire)
+    }
+
+    /// Wait until nothing tracked is running or `grace` has passed. Returns
+    /// whether everything finished.
+    pub async fn wait(&self, grace: Duration) -> bool {
+        let deadline = Instant::now() + grace;
+        loop {
+            let idle = self.inner.idle.notified();
+            tokio::pin!(idle);
+            idle.as_mut().enable();
+            if self.active() == 0 {
+                return true;
+            }
+            if tokio::time::timeout_at(deadline, idle).await.is_err() {
+                return self.active() == 0;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn wait_returns_once_tracked_work_finishes() {
+        let tracker = TaskTracker::default();
+        let guard = tracker.track();
+        let handle = tracker.spawn(async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            7
+        });
+        assert_eq!(tracker.active(), 2);
+        drop(guard);
+
+        assert!(tracker.wait(Duration::from_secs(5)).await);
+        assert_eq!(handle.await.unwrap(), 7);
+        assert_eq!(tracker.active(), 0);
+    }
+
+    #[tokio::test]
+    async fn wait_gives_up_after_the_grace_period() {
+        let tracker = TaskTracker::default();
+        let _guard = tracker.track();
+        let started = std::time::Instant::now();
+        assert!(!tracker.wait(Duration::from_millis(50)).await);
+        assert!(started.elapsed() >= Duration::from_millis(50));
+        assert_eq!(tracker.active(), 1);
+    }
+}
@@ -0,0 +1,272 @@
+//! Optional connectivity checks run before the dispatcher starts.
+
+use anyhow::{anyhow, Result};
+use std::future::Future;
+use std::time::Duration;
+use teloxide::prelude::*;
+
+use crate::ai::common::{send_openai_request, OPENAI_CHAT_URL};
+use crate::ai::config::AiConfig;
+use crate::db::Database;
+
+/// Upper bound for a single check so a hung endpoint cannot stall boot.
+pub const STARTUP_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckResult {
+    pub name: &'static str,
+    /// Hard checks abort startup when they fail; soft ones only warn.
+    pub hard: bool,
+    pub error: Option<String>,
+}
+
+impl CheckResult {
+    pub fn passed(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckReport {
+    pub results: Vec<CheckResult>,
+}
+
+impl CheckReport {
+    /// Whether every hard check passed.
+    pub fn ok(&self) -> bool {
+        self.results
+            .iter()
+            .all(|result| result.passed() || !result.hard)
+    }
+
+    pub fn summary(&self) -> String {
+        self.results
+            .iter()
+            .map(|result| match &result.error {
+                None => format!("{}: PASS", result.name),
+                Some(err) => format!("{}: FAIL ({err})", result.name),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+async fn run_check<F>(name: &'static str, hard: bool, timeout: Duration, check: F) -> CheckResult
+where
+    F: Future<Output = Result<()>>,
+{
+    tracing::debug!(check = name, "Running startup check");
+    let error = match tokio::time::timeout(timeout, check).await {
+        Ok(Ok(())) => None,
+        Ok(Err(err)) => Some(err.to_string()),
+        Err(_) => Some(format!("timed out after {}s", timeout.as_secs_f32())),
+    };
+    match &error {
+        None => tracing::info!(check = name, "Startup check PASS"),
+        Some(err) if hard => tracing::error!(check = name, error = %err, "Startup check FAIL"),
+        Some(err) => tracing::warn!(check = name, error = %err, "Startup check FAIL"),
+    }
+    CheckResult { name, hard, error }
+}
+
+async fn check_telegram(bot: &Bot) -> Result<()> {
+    let me = bot.get_me().await?;
+    tracing::debug!(username = me.username(), "Telegram reachable");
+    Ok(())
+}
+
+async fn check_database(db: &Database) -> Result<()> {
+    let mut tx = db.pool().begin().await?;
+    sqlx::query("CREATE TEMP TABLE startup_check (value INTEGER NOT NULL)")
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("INSERT INTO startup_check (value) VALUES (1)")
+        .execute(&mut *tx)
+        .await?;
+    let value: i64 = sqlx::query_scalar("SELECT value FROM startup_check")
+        .fetch_one(&mut *tx)
+        .await?;
+    tx.rollback().await?;
+    if value != 1 {
+        return Err(anyhow!("read back {value} instead of 1"));
+    }
+    Ok(())
+}
+
+async fn check_openai(config: &AiConfig) -> Result<()> {
+    let url = config.openai_chat_url.as_deref().unwrap_or(OPENAI_CHAT_URL);
+    let body = serde_json::json!({
+        "model": config.gpt_model,
+        "max_tokens": 1,
+        "messages": [{ "role": "user", "content": "ping" }],
+    });
+    let client = config.openai.http_client(url)?;
+    send_openai_request(&config.api_key, &config.openai, || {
+        Ok(client.post(url).json(&body))
+    })
+    .await?;
+    Ok(())
+}
+
+/// Check Telegram, the database and, when configured, the chat completion API.
+///
+/// Telegram and the database are hard requirements; an unreachable OpenAI
+/// endpoint only degrades AI features, so it is reported as a soft failure.
+pub async fn run_startup_checks(
+    bot: &Bot,
+    db: &Database,
+    ai_config: Option<&AiConfig>,
+    timeout: Duration,
+) -> CheckReport {
+    let mut results = vec![
+        run_check("telegram", true, timeout, check_telegram(bot)).await,
+        run_check("database", true, timeout, check_database(db)).await,
+    ];
+    if let Some(config) = ai_config {
+        results.push(run_check("openai", false, timeout, check_openai(config)).await);
+    }
+    let report = CheckReport { results };
+    tracing::info!(summary = %report.summary(), ok = report.ok(), "Startup checks finished");
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::common::OpenAiClient;
+    use crate::ai::config::DEFAULT_VOICE_DELETE_CONFIRM_THRESHOLD;
+    use crate::tests::util::{init_test_db, test_bot};
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    const GET_ME: &str = r#"{"ok":true,"result":{"id":1,"is_bot":true,"first_name":"Test","username":"testbot","can_join_groups":true,"can_read_all_group_messages":false,"supports_inline_queries":false}}"#;
+
+    fn ai_config(server: &MockServer) -> AiConfig {
+        AiConfig {
+            api_key: "k".into(),
+            stt_model: "whisper-1".into(),
+            gpt_model: "gpt-4.1".into(),
+            vision_model: "gpt-4o".into(),
+            openai_chat_url: Some(format!("{}/v1/chat/completions", server.uri())),
+            openai_stt_url: None,
+            enable_stt: true,
+            enable_vision: true,
+            enable_gpt_parse: true,
+            enable_voice_commands: true,
+            voice_delete_confirm_threshold: DEFAULT_VOICE_DELETE_CONFIRM_THRESHOLD,
+            stt_language: None,
+            openai: OpenAiClient::default(),
+            prompts: Default::default(),
+            vision_max_edge: None,
+        }
+    }
+
+    async fn mount_get_me(server: &MockServer, status: u16, body: &str) {
+        Mock::given(method("POST"))
+            .and(path("/botTEST/GetMe"))
+            .respond_with(ResponseTemplate::new(status).set_body_raw(body, "application/json"))
+            .mount(server)
+            .await;
+    }
+
+    async fn mount_chat(server: &MockServer, status: u16) {
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(
+                ResponseTemplate::new(status).set_body_json(serde_json::json!({
+                    "choices": [{ "message": { "content": "pong" } }]
+                })),
+            )
+            .expect(1)
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn all_checks_pass() {
+        let server = MockServer::start().await;
+        mount_get_me(&server, 200, GET_ME).await;
+        mount_chat(&server, 200).await;
+        let db = init_test_db().await;
+        let config = ai_config(&server);
+
+        let report = run_startup_checks(
+            &test_bot(&server),
+            &db,
+            Some(&config),
+            STARTUP_CHECK_TIMEOUT,
+        )
+        .await;
+        assert!(report.ok(), "{}", report.summary());
+        assert_eq!(report.results.len(), 3);
+        assert_eq!(
+            report.summary(),
+            "telegram: PASS, database: PASS, openai: PASS"
+        );
+    }
+
+    #[tokio::test]
+    async fn telegram_failure_is_hard() {
+        let server = MockServer::start().await;
+        mount_get_me(
+            &server,
+            401,
+            r#"{"ok":false,"error_code":401,"description":"Unauthorized"}"#,
+        )
+        .await;
+        let db = init_test_db().await;
+
+        let report = run_startup_checks(&test_bot(&server), &db, None, STARTUP_CHECK_TIMEOUT).await;
+        assert!(!report.ok());
+        assert!(!report.results[0].passed());
+        assert!(report.results[1].passed());
+    }
+
+    #[tokio::test]
+    async fn openai_failure_is_reported_but_soft() {
+        let server = MockServer::start().await;
+        mount_get_me(&server, 200, GET_ME).await;
+        mount_chat(&server, 401).await;
+        let db = init_test_db().await;
+        let config = ai_config(&server);
+
+        let report = run_startup_checks(
+            &test_bot(&server),
+            &db,
+            Some(&config),
+            STARTUP_CHECK_TIMEOUT,
+        )
+        .await;
+        assert!(report.ok());
+        let openai = &report.results[2];
+        assert_eq!(openai.name, "openai");
+        assert!(!openai.passed());
+        assert!(report.summary().contains("openai: FAIL"));
+    }
+
+    #[tokio::test]
+    async fn hung_endpoint_times_out() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/botTEST/GetMe"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(GET_ME, "application/json")
+                    .set_delay(Duration::from_secs(5)),
+            )
+            .mount(&server)
+            .await;
+        let db = init_test_db().await;
+
+        let report =
+            run_startup_checks(&test_bot(&server), &db, None, Duration::from_millis(200)).await;
+        assert!(!report.ok());
+        assert!(report.results[0]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("timed out"));
+    }
+}
@@ -0,0 +1,34 @@
+//! State the bot handlers and the API share for the lifetime of the process.
+//! Built once in `run()` and handed to the dispatcher as a dependency and to
+//! the API as router state; tests build their own.
+
+use anyhow::Result;
+
+use crate::chat_locks::ChatLocks;
+use crate::metrics::Metrics;
+use crate::notify::ListEvents;
+use crate::shutdown::TaskTracker;
+
+#[derive(Clone, Debug)]
+pub struct SharedState {
+    /// Wakes long-polling clients and notifies webhooks after list changes.
+    pub events: ListEvents,
+    /// Handler work and background tasks that shutdown waits for.
+    pub tasks: TaskTracker,
+    /// Serializes list renders per chat.
+    pub chat_locks: ChatLocks,
+    /// Series rendered by `GET /metrics`.
+    pub metrics: Metrics,
+}
+
+impl SharedState {
+    pub fn new() -> Result<Self> {
+        let tasks = TaskTracker::default();
+        Ok(Self {
+            events: ListEvents::new(tasks.clone())?,
+            tasks,
+            chat_locks: ChatLocks::default(),
+            metrics: Metrics::default(),
+        })
+    }
+}
@@ -0,0 +1,155 @@
+//! Tracing spans that correlate a Telegram update or API request with all
+//! downstream handler, database, and OpenAI events.
+
+use std::sync::Arc;
+use teloxide::dispatching::DpHandlerDescription;
+use teloxide::prelude::*;
+use teloxide::types::Update;
+use tracing::Instrument;
+
+/// Span carrying the identifiers of a single Telegram update.
+pub fn update_span(update: &Update) -> tracing::Span {
+    let chat_id = update.chat().map(|chat| chat.id.0);
+    let user_id = update.from().map(|user| user.id.0);
+    tracing::info_span!("update", update_id = update.id.0, chat_id, user_id)
+}
+
+/// Entry handler that runs the rest of the chain inside [`update_span`].
+pub fn instrument_updates<Output>() -> Handler<'static, DependencyMap, Output, DpHandlerDescription>
+where
+    Output: Send + Sync + 'static,
+{
+    dptree::from_fn_with_description(
+        <DpHandlerDescription as dptree::HandlerDescription>::entry(),
+        |mut deps: DependencyMap, cont| async move {
+            let Some(update) = deps.remove::<Update>() else {
+                return cont(deps).await;
+            };
+            let span = update_span(&update);
+            deps.insert(Arc::unwrap_or_clone(update));
+            cont(deps).instrument(span).await
+        },
+    )
+}
+
+/// Span for one API request, keyed by the generated request id and the
+/// caller-supplied correlation id.
+pub fn api_request_span(request_id: &str, correlation_id: &str) -> tracing::Span {
+    tracing::info_span!("api_request", request_id, correlation_id)
+}
+
+#[cfg(test)]
+pub(crate) mod capture {
+    use std::collections::HashMap;
+    use std::fmt::Debug;
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id};
+    use tracing::{Event, Subscriber};
+    use tracing_subscriber::layer::{Context, Layer};
+    use tracing_subscriber::registry::LookupSpan;
+
+    #[derive(Default)]
+    struct Fields(HashMap<String, String>);
+
+    impl Visit for Fields {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name().to_string(), value.to_string());
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.0
+                .insert(field.name().to_string(), format!("{value:?}"));
+        }
+    }
+
+    /// Event message and fields together with every field of its enclosing
+    /// spans.
+    #[derive(Debug, Clone)]
+    pub struct CapturedEvent {
+        pub message: String,
+        pub fields: HashMap<String, String>,
+        pub span_fields: HashMap<String, String>,
+    }
+
+    /// Layer recording events and the fields of the spans around them.
+    #[derive(Clone, Default)]
+    pub struct CaptureLayer {
+        pub events: Arc<Mutex<Vec<CapturedEvent>>>,
+    }
+
+    impl<S> Layer<S> for CaptureLayer
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+            let mut fields = Fields::default();
+            attrs.record(&mut fields);
+            if let Some(span) = ctx.span(id) {
+                span.extensions_mut().insert(fields);
+            }
+        }
+
+        fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+            let mut fields = Fields::default();
+            event.record(&mut fields);
+            let mut span_fields = HashMap::new();
+            if let Some(scope) = ctx.event_scope(event) {
+                for span in scope {
+                    if let Some(recorded) = span.extensions().get::<Fields>() {
+                        for (key, value) in &recorded.0 {
+                            span_fields
+                                .entry(key.clone())
+                                .or_insert_with(|| value.clone());
+                        }
+                    }
+                }
+            }
+            self.events.lock().unwrap().push(CapturedEvent {
+                message: fields.0.remove("message").unwrap_or_default(),
+                fields: fields.0,
+                span_fields,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::capture::CaptureLayer;
+    use super::*;
+    use crate::db::Database;
+    use crate::tests::util::init_test_db;
+    use std::ops::ControlFlow;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn nested_events_carry_update_id() {
+        let layer = CaptureLayer::default();
+        let subscriber = tracing_subscriber::registry().with(layer.clone());
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let db = init_test_db().await;
+        let update: Update = serde_json::from_str(
+            r#"{"update_id":77,"message":{"message_id":1,"date":0,"chat":{"id":5,"type":"private"},"from":{"id":9,"is_bot":false,"first_name":"Ann"},"text":"Milk"}}"#,
+        )
+        .unwrap();
+        let handler = instrument_updates::<()>().chain(dptree::endpoint(
+            |db: Database, update: Update| async move {
+                db.list_items(update.chat().unwrap().id).await.unwrap();
+            },
+        ));
+
+        let result = handler.dispatch(dptree::deps![db, update]).await;
+        assert!(matches!(result, ControlFlow::Break(())));
+
+        let events = layer.events.lock().unwrap();
+        let listing = events
+            .iter()
+            .find(|event| event.message == "Listing items")
+            .expect("database event captured");
+        assert_eq!(listing.span_fields["update_id"], "77");
+        assert_eq!(listing.span_fields["chat_id"], "5");
+        assert_eq!(listing.span_fields["user_id"], "9");
+    }
+}
@@ -1,33 +1,175 @@
-use crate::db::{connect_db, Database};
+use crate::db::{connect_db, Database, DEFAULT_BUSY_TIMEOUT};
+#[cfg(test)]
+use teloxide::Bot;
+#[cfg(test)]
+use wiremock::MockServer;
+
+/// Bot API reply to a `sendMessage` call in chat 1.
+#[cfg(test)]
+pub const SENT_MESSAGE: &str =
+    r#"{"ok":true,"result":{"message_id":7,"date":0,"chat":{"id":1,"type":"private"}}}"#;
+
+/// A bot that talks to `server` instead of Telegram.
+#[cfg(test)]
+pub fn test_bot(server: &MockServer) -> Bot {
+    let client = reqwest::Client::builder().no_proxy().build().unwrap();
+    Bot::with_client("TEST", client).set_api_url(reqwest::Url::parse(&server.uri()).unwrap())
+}
 
 pub async fn init_test_db() -> Database {
-    let pool = connect_db("sqlite::memory:", 1)
+    let pool = connect_db("sqlite::memory:", 1, DEFAULT_BUSY_TIMEOUT)
         .await
         .expect("failed to create in-memory database");
 
     sqlx::query(
-        "CREATE TABLE items(\n    id INTEGER PRIMARY KEY AUTOINCREMENT,\n    chat_id INTEGER NOT NULL,\n    text TEXT NOT NULL,\n    done BOOLEAN NOT NULL DEFAULT 0\n)"
+        "CREATE TABLE items(\n    id INTEGER PRIMARY KEY AUTOINCREMENT,\n    chat_id INTEGER NOT NULL,\n    text TEXT NOT NULL,\n    done BOOLEAN NOT NULL DEFAULT 0,\n    quantity INTEGER NOT NULL DEFAULT 1,\n    added_by_user_id INTEGER,\n    added_by_name TEXT,\n    category TEXT,\n    created_at INTEGER NOT NULL DEFAULT 0,\n    deleted_at INTEGER,\n    position INTEGER,\n    done_at INTEGER,\n    assigned_to_user_id INTEGER,\n    assigned_to_name TEXT,\n    price_cents INTEGER\n)"
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    sqlx::query(
+        "CREATE TABLE chat_state(\n    chat_id INTEGER PRIMARY KEY,\n    last_list_message_id INTEGER,\n    refresh_pending BOOLEAN NOT NULL DEFAULT 0,\n    pending_notice TEXT,\n    revision INTEGER NOT NULL DEFAULT 0,\n    list_page INTEGER NOT NULL DEFAULT 0,\n    thread_id INTEGER\n)"
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    sqlx::query(
+        "CREATE TABLE delete_session(\n    user_id INTEGER PRIMARY KEY,\n    chat_id INTEGER NOT NULL,\n    selected TEXT NOT NULL DEFAULT '',\n    notice_chat_id INTEGER,\n    notice_message_id INTEGER,\n    dm_message_id INTEGER,\n    page INTEGER NOT NULL DEFAULT 0,\n    created_at INTEGER NOT NULL DEFAULT 0\n)"
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    sqlx::query(
+        "CREATE TABLE tokens(\n    id INTEGER PRIMARY KEY AUTOINCREMENT,\n    chat_id INTEGER NOT NULL,\n    token_hash TEXT NOT NULL,\n    name TEXT,\n    issuer_user_id INTEGER,\n    issuer_name TEXT,\n    issued_at INTEGER NOT NULL,\n    last_used_at INTEGER,\n    revoked_at INTEGER,\n    token_preview TEXT NOT NULL DEFAULT '',\n    expires_at INTEGER,\n    scope TEXT NOT NULL DEFAULT 'write'\n)"
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    sqlx::query(
+        "CREATE TABLE chat_settings(\n    chat_id INTEGER NOT NULL,\n    key TEXT NOT NULL,\n    value TEXT NOT NULL,\n    PRIMARY KEY (chat_id, key)\n)"
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    sqlx::query(
+        "CREATE TABLE bot_permissions(\n    chat_id INTEGER PRIMARY KEY,\n    can_delete BOOLEAN NOT NULL,\n    can_pin BOOLEAN NOT NULL,\n    delete_notice_sent BOOLEAN NOT NULL DEFAULT 0,\n    updated_at INTEGER NOT NULL\n)"
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    sqlx::query(
+        "CREATE TABLE duplicate_prompt(\n    item_id INTEGER PRIMARY KEY,\n    chat_id INTEGER NOT NULL,\n    message_id INTEGER,\n    created_at INTEGER NOT NULL\n)"
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    sqlx::query(
+        "CREATE TABLE settings_panel(\n    chat_id INTEGER PRIMARY KEY,\n    message_id INTEGER NOT NULL,\n    last_used_at INTEGER NOT NULL\n)"
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    sqlx::query(
+        "CREATE TABLE webhooks(\n    id INTEGER PRIMARY KEY AUTOINCREMENT,\n    chat_id INTEGER NOT NULL,\n    url TEXT NOT NULL,\n    secret TEXT NOT NULL,\n    created_at INTEGER NOT NULL\n)"
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    sqlx::query(
+        "CREATE TABLE share_links(\n    slug TEXT PRIMARY KEY,\n    chat_id INTEGER NOT NULL,\n    created_at INTEGER NOT NULL,\n    revoked_at INTEGER\n)"
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    sqlx::query(
+        "CREATE TABLE message_items(\n    chat_id INTEGER NOT NULL,\n    message_id INTEGER NOT NULL,\n    item_id INTEGER NOT NULL,\n    created BOOLEAN NOT NULL,\n    PRIMARY KEY (chat_id, message_id, item_id)\n)"
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    sqlx::query(
+        "CREATE TABLE pairings(\n    code TEXT PRIMARY KEY,\n    created_at INTEGER NOT NULL,\n    expires_at INTEGER NOT NULL,\n    chat_id INTEGER,\n    issuer_user_id INTEGER,\n    issuer_name TEXT,\n    claimed_at INTEGER\n)"
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    sqlx::query(
+        "CREATE TABLE archives(\n    id INTEGER PRIMARY KEY AUTOINCREMENT,\n    chat_id INTEGER NOT NULL,\n    archived_at INTEGER NOT NULL,\n    items TEXT NOT NULL\n)"
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    sqlx::query(
+        "CREATE TABLE undo_log(\n    chat_id INTEGER PRIMARY KEY,\n    action TEXT NOT NULL,\n    items TEXT NOT NULL,\n    archive_id INTEGER\n)"
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    sqlx::query(
+        "CREATE TABLE edit_session(\n    user_id INTEGER PRIMARY KEY,\n    chat_id INTEGER NOT NULL,\n    item_id INTEGER,\n    dm_message_id INTEGER,\n    started_at INTEGER NOT NULL\n)"
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    sqlx::query(
+        "CREATE TABLE assign_session(\n    user_id INTEGER PRIMARY KEY,\n    chat_id INTEGER NOT NULL,\n    dm_message_id INTEGER\n)"
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    sqlx::query(
+        "CREATE TABLE voice_deletion_prompt(\n    chat_id INTEGER NOT NULL,\n    message_id INTEGER NOT NULL,\n    user_id INTEGER NOT NULL,\n    item_ids TEXT NOT NULL,\n    created_at INTEGER NOT NULL,\n    PRIMARY KEY (chat_id, message_id)\n)"
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    sqlx::query(
+        "CREATE TABLE item_history(\n    id INTEGER PRIMARY KEY AUTOINCREMENT,\n    chat_id INTEGER NOT NULL,\n    key TEXT NOT NULL,\n    text TEXT NOT NULL,\n    added_at INTEGER NOT NULL\n)",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    sqlx::query(
+        "CREATE TABLE pending_album_photos(\n    chat_id INTEGER NOT NULL,\n    media_group_id TEXT NOT NULL,\n    message_id INTEGER NOT NULL,\n    message TEXT NOT NULL,\n    PRIMARY KEY (chat_id, media_group_id, message_id)\n)"
     )
     .execute(&pool)
     .await
     .unwrap();
 
     sqlx::query(
-        "CREATE TABLE chat_state(\n    chat_id INTEGER PRIMARY KEY,\n    last_list_message_id INTEGER\n)"
+        "CREATE TRIGGER items_insert_revision\nAFTER INSERT ON items\nWHEN NEW.deleted_at IS NULL\nBEGIN\n    INSERT INTO chat_state (chat_id, revision) VALUES (NEW.chat_id, 1)\n    ON CONFLICT(chat_id) DO UPDATE SET revision = revision + 1;\nEND",
     )
     .execute(&pool)
     .await
     .unwrap();
 
     sqlx::query(
-        "CREATE TABLE delete_session(\n    user_id INTEGER PRIMARY KEY,\n    chat_id INTEGER NOT NULL,\n    selected TEXT NOT NULL DEFAULT '',\n    notice_chat_id INTEGER,\n    notice_message_id INTEGER,\n    dm_message_id INTEGER\n)"
+        "CREATE TRIGGER items_update_revision\nAFTER UPDATE ON items\nWHEN NEW.deleted_at IS NULL OR OLD.deleted_at IS NULL\nBEGIN\n    INSERT INTO chat_state (chat_id, revision) VALUES (NEW.chat_id, 1)\n    ON CONFLICT(chat_id) DO UPDATE SET revision = revision + 1;\nEND",
     )
     .execute(&pool)
     .await
     .unwrap();
 
     sqlx::query(
-        "CREATE TABLE tokens(\n    id INTEGER PRIMARY KEY AUTOINCREMENT,\n    chat_id INTEGER NOT NULL,\n    token TEXT NOT NULL,\n    name TEXT,\n    issuer_user_id INTEGER,\n    issuer_name TEXT,\n    issued_at INTEGER NOT NULL,\n    last_used_at INTEGER,\n    revoked_at INTEGER\n)"
+        "CREATE TRIGGER items_delete_revision\nAFTER DELETE ON items\nWHEN OLD.deleted_at IS NULL\nBEGIN\n    INSERT INTO chat_state (chat_id, revision) VALUES (OLD.chat_id, 1)\n    ON CONFLICT(chat_id) DO UPDATE SET revision = revision + 1;\nEND",
     )
     .execute(&pool)
     .await
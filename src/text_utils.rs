@@ -1,4 +1,4 @@
-use crate::messages::ARCHIVED_LIST_HEADER;
+use crate::messages::{ARCHIVED_LIST_HEADER, CHECKED_ITEMS_SEPARATOR, VOICE_NOT_FOUND_LABEL};
 use tracing::trace;
 
 /// Clean a single text line from a user message.
@@ -12,15 +12,29 @@ pub fn parse_item_line(line: &str) -> Option<String> {
         trace!("Ignoring archived list separator");
         return None;
     }
+    if line.trim() == CHECKED_ITEMS_SEPARATOR {
+        trace!("Ignoring checked items separator");
+        return None;
+    }
+    if is_progress_line(line) {
+        trace!("Ignoring list progress line");
+        return None;
+    }
 
     let cleaned = line
-        .trim_start_matches(['☑', '✅', '⬜', '🛒', '•', '🗑', '\u{fe0f}', '-', '*'])
+        .trim_start_matches([
+            '☑', '✅', '⬜', '🛒', '•', '🗑', '▫', '✓', '✔', '🎉', '\u{fe0f}', '-', '*',
+        ])
         .trim();
 
     if cleaned.starts_with("Removed via voice request") {
         trace!("Ignoring removal header");
         return None;
     }
+    if cleaned.starts_with(VOICE_NOT_FOUND_LABEL) {
+        trace!("Ignoring voice removal misses");
+        return None;
+    }
 
     if cleaned.is_empty() {
         trace!("Line empty after cleaning");
@@ -32,6 +46,16 @@ pub fn parse_item_line(line: &str) -> Option<String> {
     }
 }
 
+/// Whether the line is the "3 of 7 done" header of a list message.
+fn is_progress_line(line: &str) -> bool {
+    let Some(counts) = line.trim().strip_suffix(" done") else {
+        return false;
+    };
+    counts.split_once(" of ").is_some_and(|(done, total)| {
+        done.parse::<usize>().is_ok() && total.parse::<usize>().is_ok()
+    })
+}
+
 use unicode_segmentation::UnicodeSegmentation;
 
 pub fn capitalize_first(text: &str) -> String {
@@ -48,6 +72,46 @@ pub fn capitalize_first(text: &str) -> String {
     }
 }
 
+/// Shorten text to at most `max` graphemes, appending an ellipsis when cut.
+///
+/// Cutting on grapheme boundaries keeps emoji and combining characters intact.
+pub fn truncate_graphemes(text: &str, max: usize) -> String {
+    let mut graphemes = text.graphemes(true);
+    let head: String = graphemes.by_ref().take(max).collect();
+    if graphemes.next().is_some() {
+        format!("{}…", head.trim_end())
+    } else {
+        head
+    }
+}
+
+/// Largest leading count read as a quantity; bigger numbers such as a year
+/// stay part of the item text.
+const MAX_LEADING_QUANTITY: i64 = 999;
+
+/// Split a leading count such as "2 lemons" or "3× eggs" off an item.
+/// Returns the count and the text after it, or 1 and the unchanged text
+/// when the item does not start with a count.
+pub fn split_quantity(text: &str) -> (i64, &str) {
+    let trimmed = text.trim_start();
+    let digits = trimmed
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(trimmed.len());
+    let rest = &trimmed[digits..];
+    let rest = rest.strip_prefix(['x', '×']).unwrap_or(rest);
+    let name = rest.trim();
+    if digits == 0 || !rest.starts_with(char::is_whitespace) || name.is_empty() {
+        return (1, text);
+    }
+    match trimmed[..digits].parse::<i64>() {
+        Ok(count) if (1..=MAX_LEADING_QUANTITY).contains(&count) => {
+            trace!(original = %text, count, name, "split quantity");
+            (count, name)
+        }
+        _ => (1, text),
+    }
+}
+
 /// Normalize an item string for matching operations.
 ///
 /// This removes any leading quantity digits and whitespace and
@@ -76,11 +140,42 @@ mod tests {
         assert_eq!(capitalize_first("🍎 apple"), "🍎 apple");
     }
 
+    #[test]
+    fn truncate_graphemes_keeps_short_text() {
+        assert_eq!(truncate_graphemes("milk", 10), "milk");
+    }
+
+    #[test]
+    fn truncate_graphemes_cuts_on_boundary() {
+        assert_eq!(truncate_graphemes("👨‍👩‍👧 family pack", 3), "👨‍👩‍👧 f…");
+        assert_eq!(truncate_graphemes("éclair", 2), "éc…");
+    }
+
+    #[test]
+    fn split_quantity_reads_leading_counts() {
+        assert_eq!(split_quantity("2 lemons"), (2, "lemons"));
+        assert_eq!(split_quantity("3× eggs "), (3, "eggs"));
+        assert_eq!(split_quantity("4x milk"), (4, "milk"));
+        assert_eq!(split_quantity("Milk"), (1, "Milk"));
+        assert_eq!(split_quantity("7up"), (1, "7up"));
+        assert_eq!(split_quantity("2xl shirt"), (1, "2xl shirt"));
+        assert_eq!(split_quantity("0 apples"), (1, "0 apples"));
+        assert_eq!(split_quantity("1984 novel"), (1, "1984 novel"));
+        assert_eq!(split_quantity("12"), (1, "12"));
+    }
+
     #[test]
     fn parse_item_line_ignores_archived_header() {
         assert!(parse_item_line(ARCHIVED_LIST_HEADER).is_none());
     }
 
+    #[test]
+    fn parse_item_line_ignores_voice_removal_misses() {
+        let text = crate::messages::voice_removed_text(&["Milk".into()], &["Durian".into()]);
+        let items: Vec<String> = text.lines().filter_map(parse_item_line).collect();
+        assert_eq!(items, ["Milk"]);
+    }
+
     #[test]
     fn parse_item_line_trims_and_returns_text() {
         assert_eq!(parse_item_line("✅ Milk  "), Some("Milk".to_string()));
@@ -91,4 +186,13 @@ mod tests {
         assert_eq!(parse_item_line("- Milk"), Some("Milk".to_string()));
         assert_eq!(parse_item_line("* Milk"), Some("Milk".to_string()));
     }
+
+    #[test]
+    fn parse_item_line_ignores_progress_header() {
+        assert!(parse_item_line(&crate::messages::list_progress_text(3, 7)).is_none());
+        assert_eq!(
+            parse_item_line("2 of them done"),
+            Some("2 of them done".to_string())
+        );
+    }
 }
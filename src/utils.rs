@@ -2,10 +2,15 @@ use anyhow::Result;
 use futures_util::StreamExt;
 use teloxide::{
     net::Download,
+    payloads::SendMessageSetters,
     prelude::*,
-    types::{ChatId, InlineKeyboardMarkup, MessageId},
-    RequestError,
+    types::{
+        Chat, ChatId, ChatKind, ChatPublic, InlineKeyboardMarkup, MessageId, PublicChatKind,
+        ThreadId,
+    },
+    ApiError, RequestError,
 };
+use tracing::Instrument;
 
 /// Default timeout in seconds for temporary messages.
 pub const DEFAULT_DELETE_AFTER_TIMEOUT: u64 = 5;
@@ -23,25 +28,62 @@ pub fn delete_after(
         delay_secs = secs,
         "Scheduling message deletion"
     );
-    tokio::spawn(async move {
-        tokio::time::sleep(std::time::Duration::from_secs(secs)).await;
-        let res = bot.delete_message(chat_id, message_id).await;
-        if let Err(ref err) = res {
-            tracing::warn!(
-                error = %err,
+    tokio::spawn(
+        async move {
+            tokio::time::sleep(std::time::Duration::from_secs(secs)).await;
+            let res = bot.delete_message(chat_id, message_id).await;
+            if let Err(ref err) = res {
+                tracing::warn!(
+                    error = %err,
+                    chat_id = chat_id.0,
+                    message_id = message_id.0,
+                    "Failed to delete message",
+                );
+            }
+            tracing::debug!(
                 chat_id = chat_id.0,
                 message_id = message_id.0,
-                "Failed to delete message",
+                "Finished delete_after task"
             );
         }
-        tracing::debug!(
-            chat_id = chat_id.0,
-            message_id = message_id.0,
-            "Finished delete_after task"
-        );
-    })
+        .in_current_span(),
+    )
+}
+
+/// Whether the chat is a supergroup with forum topics turned on.
+pub fn is_forum(chat: &Chat) -> bool {
+    matches!(
+        &chat.kind,
+        ChatKind::Public(ChatPublic {
+            kind: PublicChatKind::Supergroup(group),
+            ..
+        }) if group.is_forum
+    )
+}
+
+/// The forum topic `msg` was posted in; `None` outside topics, including the
+/// General topic.
+pub fn topic_of(msg: &Message) -> Option<ThreadId> {
+    if msg.is_topic_message {
+        msg.thread_id
+    } else {
+        None
+    }
+}
+
+/// Send into a forum topic when there is one, so replies do not land in
+/// General.
+pub trait InTopic: SendMessageSetters {
+    fn in_topic(self, thread_id: Option<ThreadId>) -> Self {
+        match thread_id {
+            Some(thread_id) => self.message_thread_id(thread_id),
+            None => self,
+        }
+    }
 }
 
+impl<R: SendMessageSetters> InTopic for R {}
+
 /// Attempt to delete a message and log a warning on failure.
 pub async fn try_delete_message(bot: &Bot, chat_id: ChatId, message_id: MessageId) {
     if let Err(err) = bot.delete_message(chat_id, message_id).await {
@@ -54,26 +96,77 @@ pub async fn try_delete_message(bot: &Bot, chat_id: ChatId, message_id: MessageI
     }
 }
 
-/// Attempt to edit a message and log a warning on failure.
+/// What became of an edit made with [`try_edit_message`].
+#[derive(Debug)]
+pub enum EditOutcome {
+    Edited,
+    /// Telegram rejected the edit because nothing would change.
+    NotModified,
+    /// The message was deleted or can no longer be edited.
+    MessageMissing,
+    Other(RequestError),
+}
+
+impl EditOutcome {
+    /// Classify an edit error. Descriptions teloxide does not know yet are
+    /// matched on their text.
+    pub(crate) fn from_error(err: RequestError) -> Self {
+        match &err {
+            RequestError::Api(ApiError::MessageNotModified) => Self::NotModified,
+            RequestError::Api(
+                ApiError::MessageToEditNotFound
+                | ApiError::MessageIdInvalid
+                | ApiError::MessageCantBeEdited,
+            ) => Self::MessageMissing,
+            RequestError::Api(ApiError::Unknown(description)) => {
+                let description = description.to_ascii_lowercase();
+                if description.contains("message is not modified") {
+                    Self::NotModified
+                } else if description.contains("message to edit not found")
+                    || description.contains("message can't be edited")
+                {
+                    Self::MessageMissing
+                } else {
+                    Self::Other(err)
+                }
+            }
+            _ => Self::Other(err),
+        }
+    }
+}
+
+/// Attempt to edit a message. Only unexpected failures are logged as
+/// warnings; callers decide what to do about a missing message.
 pub async fn try_edit_message(
     bot: &Bot,
     chat_id: ChatId,
     message_id: MessageId,
     text: impl Into<String>,
     markup: InlineKeyboardMarkup,
-) {
-    if let Err(err) = bot
+) -> EditOutcome {
+    let Err(err) = bot
         .edit_message_text(chat_id, message_id, text)
         .reply_markup(markup)
         .await
-    {
-        tracing::warn!(
+    else {
+        return EditOutcome::Edited;
+    };
+    let outcome = EditOutcome::from_error(err);
+    match &outcome {
+        EditOutcome::Other(err) => tracing::warn!(
             error = %err,
             chat_id = chat_id.0,
             message_id = message_id.0,
             "Failed to edit message",
-        );
+        ),
+        _ => tracing::debug!(
+            chat_id = chat_id.0,
+            message_id = message_id.0,
+            outcome = ?outcome,
+            "Message edit skipped",
+        ),
     }
+    outcome
 }
 
 /// Download a file from Telegram and return the raw bytes.
@@ -128,26 +221,53 @@ mod tests {
         Ok(())
     }
 
+    /// Edit response -> outcome.
     #[tokio::test]
-    async fn try_edit_message_sends_request() -> Result<(), RequestError> {
-        let server = MockServer::start().await;
-        Mock::given(method("POST"))
-            .and(path("/botTEST/EditMessageText"))
-            .respond_with(
-                ResponseTemplate::new(200)
-                    .set_body_raw(r#"{"ok":true,"result":true}"#, "application/json"),
-            )
-            .expect(1)
-            .mount(&server)
-            .await;
+    async fn try_edit_message_classifies_errors() {
+        let cases = [
+            (
+                200,
+                r#"{"ok":true,"result":{"message_id":2,"date":0,"chat":{"id":1,"type":"private"}}}"#,
+                "Edited",
+            ),
+            (
+                400,
+                r#"{"ok":false,"error_code":400,"description":"Bad Request: message is not modified: specified new message content and reply markup are exactly the same as a current content and reply markup of the message"}"#,
+                "NotModified",
+            ),
+            (
+                400,
+                r#"{"ok":false,"error_code":400,"description":"Bad Request: message to edit not found"}"#,
+                "MessageMissing",
+            ),
+            (
+                400,
+                r#"{"ok":false,"error_code":400,"description":"Bad Request: message can't be edited"}"#,
+                "MessageMissing",
+            ),
+            (
+                400,
+                r#"{"ok":false,"error_code":400,"description":"Bad Request: can't parse entities: Unsupported start tag \"token\""}"#,
+                "Other",
+            ),
+        ];
+        for (status, body, expected) in cases {
+            let server = MockServer::start().await;
+            Mock::given(method("POST"))
+                .and(path("/botTEST/EditMessageText"))
+                .respond_with(ResponseTemplate::new(status).set_body_raw(body, "application/json"))
+                .expect(1)
+                .mount(&server)
+                .await;
 
-        let client = Client::builder().no_proxy().build().unwrap();
-        let bot = Bot::with_client("TEST", client)
-            .set_api_url(reqwest::Url::parse(&server.uri()).unwrap());
-        let markup = InlineKeyboardMarkup::new(Vec::<Vec<InlineKeyboardButton>>::new());
-        try_edit_message(&bot, ChatId(1), MessageId(2), "hi", markup).await;
-        server.verify().await;
-        Ok(())
+            let client = Client::builder().no_proxy().build().unwrap();
+            let bot = Bot::with_client("TEST", client)
+                .set_api_url(reqwest::Url::parse(&server.uri()).unwrap());
+            let markup = InlineKeyboardMarkup::new(Vec::<Vec<InlineKeyboardButton>>::new());
+            let outcome = try_edit_message(&bot, ChatId(1), MessageId(2), "hi", markup).await;
+            assert!(format!("{outcome:?}").starts_with(expected), "{body}");
+            server.verify().await;
+        }
     }
 
     #[tokio::test]
@@ -1,8 +1,9 @@
-use axum::body::Body;
+use axum::body::{to_bytes, Body};
 use axum::http::{header::AUTHORIZATION, Request, StatusCode};
 use serde_json::json;
+use shopbot::db::{NewToken, TokenScope};
 use shopbot::tests::util::init_test_db;
-use shopbot::{api_router, ApiConfig};
+use shopbot::{api_router, serve_api, ApiConfig, SharedState, DEFAULT_MAX_ITEM_LENGTH};
 use teloxide::types::ChatId;
 use tower::ServiceExt;
 
@@ -10,14 +11,27 @@ use tower::ServiceExt;
 async fn api_add_toggle_delete_flow() {
     let db = init_test_db().await;
     let chat_id = ChatId(70);
-    db.create_token(chat_id, "token-flow", None, None, None, 1)
-        .await
-        .unwrap();
+    db.create_token(
+        chat_id,
+        "token-flow",
+        &NewToken {
+            issued_at: 1,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
 
     let app = api_router(
         db.clone(),
+        SharedState::new().unwrap(),
         ApiConfig {
             rate_limit_per_second: None,
+            integration_secret: None,
+            mutation_rate_per_second: None,
+            max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+            cors_origins: None,
+            log_request_bodies: false,
         },
     );
 
@@ -85,14 +99,27 @@ async fn api_add_toggle_delete_flow() {
 async fn api_rate_limit_rejects_second_request() {
     let db = init_test_db().await;
     let chat_id = ChatId(71);
-    db.create_token(chat_id, "token-rate", None, None, None, 1)
-        .await
-        .unwrap();
+    db.create_token(
+        chat_id,
+        "token-rate",
+        &NewToken {
+            issued_at: 1,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
 
     let app = api_router(
         db,
+        SharedState::new().unwrap(),
         ApiConfig {
             rate_limit_per_second: Some(1),
+            integration_secret: None,
+            mutation_rate_per_second: None,
+            max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+            cors_origins: None,
+            log_request_bodies: false,
         },
     );
 
@@ -121,3 +148,403 @@ async fn api_rate_limit_rejects_second_request() {
         .unwrap();
     assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
 }
+
+#[tokio::test]
+async fn api_rate_limit_is_per_chat() {
+    let db = init_test_db().await;
+    for (chat_id, token) in [(ChatId(72), "token-noisy"), (ChatId(73), "token-quiet")] {
+        db.create_token(
+            chat_id,
+            token,
+            &NewToken {
+                issued_at: 1,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    }
+
+    let app = api_router(
+        db,
+        SharedState::new().unwrap(),
+        ApiConfig {
+            rate_limit_per_second: Some(1),
+            integration_secret: None,
+            mutation_rate_per_second: None,
+            max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+            cors_origins: None,
+            log_request_bodies: false,
+        },
+    );
+    let list = |token: &str| {
+        Request::builder()
+            .uri("/api/list")
+            .header(AUTHORIZATION, format!("Bearer {token}"))
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let response = app.clone().oneshot(list("token-noisy")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = app.clone().oneshot(list("token-noisy")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    let response = app.clone().oneshot(list("token-quiet")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app.oneshot(list("wrong-token")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn api_concurrent_requests_same_token_succeed() {
+    let db = init_test_db().await;
+    let chat_id = ChatId(72);
+    db.create_token(
+        chat_id,
+        "token-burst",
+        &NewToken {
+            issued_at: 1,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    db.add_item_count(chat_id, "Rice").await.unwrap();
+
+    let app = api_router(
+        db.clone(),
+        SharedState::new().unwrap(),
+        ApiConfig {
+            rate_limit_per_second: None,
+            integration_secret: None,
+            mutation_rate_per_second: None,
+            max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+            cors_origins: None,
+            log_request_bodies: false,
+        },
+    );
+
+    let requests = (0..50).map(|_| {
+        let app = app.clone();
+        tokio::spawn(async move {
+            app.oneshot(
+                Request::builder()
+                    .uri("/api/list")
+                    .header(AUTHORIZATION, "Bearer token-burst")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap()
+            .status()
+        })
+    });
+    let statuses = futures_util::future::join_all(requests).await;
+
+    for status in statuses {
+        assert_eq!(status.unwrap(), StatusCode::OK);
+    }
+    let tokens = db.list_tokens(chat_id).await.unwrap();
+    assert!(tokens[0].last_used_at.is_some());
+}
+
+#[tokio::test]
+async fn api_mutation_flood_does_not_block_bot_writes() {
+    let db = init_test_db().await;
+    let chat_id = ChatId(73);
+    db.create_token(
+        chat_id,
+        "token-flood",
+        &NewToken {
+            issued_at: 1,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    db.add_item_count(chat_id, "Bread").await.unwrap();
+    let bread = db.list_items(chat_id).await.unwrap()[0].id;
+
+    let app = api_router(
+        db.clone(),
+        SharedState::new().unwrap(),
+        ApiConfig {
+            rate_limit_per_second: None,
+            integration_secret: None,
+            mutation_rate_per_second: Some(10),
+            max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+            cors_origins: None,
+            log_request_bodies: false,
+        },
+    );
+
+    let started = std::time::Instant::now();
+    let requests: Vec<_> = (0..25)
+        .map(|i| {
+            let app = app.clone();
+            tokio::spawn(async move {
+                app.oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri("/api/add")
+                        .header(AUTHORIZATION, "Bearer token-flood")
+                        .header("content-type", "application/json")
+                        .body(Body::from(
+                            serde_json::to_vec(&json!({ "text": format!("Item {i}") })).unwrap(),
+                        ))
+                        .unwrap(),
+                )
+                .await
+                .unwrap()
+                .status()
+            })
+        })
+        .collect();
+
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    let toggle_started = std::time::Instant::now();
+    db.toggle_item_count(chat_id, bread).await.unwrap();
+    assert!(toggle_started.elapsed() < std::time::Duration::from_millis(500));
+
+    let statuses = futures_util::future::join_all(requests).await;
+    for status in statuses {
+        assert_eq!(status.unwrap(), StatusCode::CREATED);
+    }
+    assert!(started.elapsed() >= std::time::Duration::from_millis(1900));
+    assert_eq!(db.list_items(chat_id).await.unwrap().len(), 26);
+}
+
+#[tokio::test]
+async fn openapi_document_lists_routes() {
+    let db = init_test_db().await;
+    let app = api_router(
+        db,
+        SharedState::new().unwrap(),
+        ApiConfig {
+            rate_limit_per_second: None,
+            integration_secret: None,
+            mutation_rate_per_second: None,
+            max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+            cors_origins: None,
+            log_request_bodies: false,
+        },
+    );
+
+    let response = app
+        .oneshot(
+            Request::builder()
+                .uri("/api/openapi.json")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let spec: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+    assert!(spec["openapi"].as_str().unwrap().starts_with("3."));
+    for (route, method) in [
+        ("/list", "get"),
+        ("/add", "post"),
+        ("/toggle", "post"),
+        ("/delete", "post"),
+        ("/archive", "post"),
+        ("/nuke", "post"),
+        ("/done", "post"),
+        ("/items/{id}", "get"),
+        ("/items/{id}", "put"),
+        ("/items/{id}", "delete"),
+    ] {
+        let current = &spec["paths"][format!("/api/v1{route}")][method];
+        assert!(current.is_object(), "{method} /api/v1{route} missing");
+        assert!(current["deprecated"].is_null());
+        let alias = &spec["paths"][format!("/api{route}")][method];
+        assert_eq!(alias["deprecated"], true, "{method} /api{route}");
+    }
+    assert!(spec["paths"]["/api/v1/export"]["get"].is_object());
+    assert!(spec["paths"]["/api/export"].is_null());
+    assert!(spec["paths"]["/integrations/alexa"].is_null());
+    let codes = spec["components"]["schemas"]["Error"]["properties"]["error"]["enum"]
+        .as_array()
+        .unwrap();
+    assert!(codes.contains(&json!("not_found")));
+}
+
+#[tokio::test]
+async fn unversioned_routes_are_deprecated_aliases_of_v1() {
+    let db = init_test_db().await;
+    db.create_token(
+        ChatId(76),
+        "token-v1",
+        &NewToken {
+            issued_at: 1,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    db.add_item_count(ChatId(76), "Tea").await.unwrap();
+    let app = api_router(
+        db,
+        SharedState::new().unwrap(),
+        ApiConfig {
+            rate_limit_per_second: None,
+            integration_secret: None,
+            mutation_rate_per_second: None,
+            max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+            cors_origins: None,
+            log_request_bodies: false,
+        },
+    );
+    let get = |uri: &str| {
+        app.clone().oneshot(
+            Request::builder()
+                .uri(uri)
+                .header(AUTHORIZATION, "Bearer token-v1")
+                .body(Body::empty())
+                .unwrap(),
+        )
+    };
+
+    let current = get("/api/v1/list").await.unwrap();
+    assert_eq!(current.status(), StatusCode::OK);
+    assert_eq!(current.headers()["x-api-version"], "1");
+    assert!(current.headers().get("deprecation").is_none());
+    let current = to_bytes(current.into_body(), usize::MAX).await.unwrap();
+
+    let alias = get("/api/list").await.unwrap();
+    assert_eq!(alias.status(), StatusCode::OK);
+    assert_eq!(alias.headers()["x-api-version"], "1");
+    assert_eq!(alias.headers()["deprecation"], "true");
+    assert_eq!(
+        alias.headers()["link"],
+        "</api/v1/list>; rel=\"successor-version\""
+    );
+    let alias = to_bytes(alias.into_body(), usize::MAX).await.unwrap();
+    assert_eq!(current, alias);
+
+    assert_eq!(
+        get("/api/v1/export").await.unwrap().status(),
+        StatusCode::OK
+    );
+    assert_eq!(
+        get("/api/export").await.unwrap().status(),
+        StatusCode::NOT_FOUND
+    );
+
+    let unauthenticated = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/healthz")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(unauthenticated.headers()["x-api-version"], "1");
+}
+
+#[tokio::test]
+async fn api_server_serves_on_a_socket_until_shutdown() {
+    let db = init_test_db().await;
+    let chat_id = ChatId(75);
+    db.create_token(
+        chat_id,
+        "token-socket",
+        &NewToken {
+            issued_at: 1,
+            scope: TokenScope::Read,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    db.add_item_count(chat_id, "Rice").await.unwrap();
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let app = api_router(
+        db,
+        SharedState::new().unwrap(),
+        ApiConfig {
+            rate_limit_per_second: None,
+            integration_secret: None,
+            mutation_rate_per_second: None,
+            max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+            cors_origins: None,
+            log_request_bodies: false,
+        },
+    );
+    let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+    let server = tokio::spawn(serve_api(listener, app, async {
+        let _ = stopped.await;
+    }));
+
+    let response = reqwest::Client::new()
+        .get(format!("http://{addr}/api/list"))
+        .bearer_auth("token-socket")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let payload: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(payload["items"][0]["text"], "Rice");
+
+    stop.send(()).unwrap();
+    server.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn shutdown_lets_in_flight_requests_finish() {
+    let db = init_test_db().await;
+    db.create_token(
+        ChatId(76),
+        "token-drain",
+        &NewToken {
+            issued_at: 1,
+            scope: TokenScope::Read,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let app = api_router(
+        db,
+        SharedState::new().unwrap(),
+        ApiConfig {
+            rate_limit_per_second: None,
+            integration_secret: None,
+            mutation_rate_per_second: None,
+            max_item_length: DEFAULT_MAX_ITEM_LENGTH,
+            cors_origins: None,
+            log_request_bodies: false,
+        },
+    );
+    let (stop, stopped) = tokio::sync::oneshot::channel::<()>();
+    let server = tokio::spawn(serve_api(listener, app, async {
+        let _ = stopped.await;
+    }));
+
+    // A long poll that only answers after its one-second timeout.
+    let slow = tokio::spawn(
+        reqwest::Client::new()
+            .get(format!("http://{addr}/api/v1/changes?timeout=1"))
+            .bearer_auth("token-drain")
+            .send(),
+    );
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    stop.send(()).unwrap();
+
+    let response = slow.await.unwrap().unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let payload: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(payload["changed"], false);
+    server.await.unwrap().unwrap();
+}
@@ -1,7 +1,7 @@
 use reqwest::Client;
 use shopbot::tests::util::init_test_db;
-use shopbot::{ListService, NO_CHECKED_ITEMS_TO_ARCHIVE};
-use teloxide::{prelude::*, types::MessageId};
+use shopbot::{ListService, SharedState, NO_CHECKED_ITEMS_TO_ARCHIVE};
+use teloxide::prelude::*;
 use wiremock::matchers::{method, path};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
@@ -31,16 +31,15 @@ async fn archive_checked_archives_only_done() {
     let bot =
         Bot::with_client("TEST", client).set_api_url(reqwest::Url::parse(&server.uri()).unwrap());
     let db = init_test_db().await;
+    let shared = SharedState::new().unwrap();
     let chat = ChatId(1);
     db.add_item_count(chat, "Milk").await.unwrap();
     db.add_item_count(chat, "Eggs").await.unwrap();
     let items = db.list_items(chat).await.unwrap();
     db.toggle_item_count(chat, items[0].id).await.unwrap();
-    db.update_last_list_message_id(chat, MessageId(5))
-        .await
-        .unwrap();
+    db.update_last_list_message_id(chat, 5).await.unwrap();
 
-    ListService::new(&db)
+    ListService::new(&db, &shared)
         .archive_checked(bot, chat)
         .await
         .unwrap();
@@ -68,13 +67,12 @@ async fn archive_checked_none_done() {
     let bot =
         Bot::with_client("TEST", client).set_api_url(reqwest::Url::parse(&server.uri()).unwrap());
     let db = init_test_db().await;
+    let shared = SharedState::new().unwrap();
     let chat = ChatId(1);
     db.add_item_count(chat, "Milk").await.unwrap();
-    db.update_last_list_message_id(chat, MessageId(3))
-        .await
-        .unwrap();
+    db.update_last_list_message_id(chat, 3).await.unwrap();
 
-    ListService::new(&db)
+    ListService::new(&db, &shared)
         .archive_checked(bot, chat)
         .await
         .unwrap();
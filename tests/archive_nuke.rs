@@ -1,11 +1,10 @@
 use reqwest::Client;
 use shopbot::tests::util::init_test_db;
-use shopbot::{ListService, LIST_NUKED};
-use teloxide::{
-    prelude::*,
-    types::{Message, MessageId},
+use shopbot::{
+    callback_handler, DeleteSessionTtl, ListService, SharedState, LIST_NUKED, NOTHING_TO_UNDO,
 };
-use wiremock::matchers::{method, path};
+use teloxide::{prelude::*, types::Message};
+use wiremock::matchers::{body_string_contains, method, path};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
 #[tokio::test]
@@ -17,7 +16,7 @@ async fn archive_clears_data_and_sends_confirmation() {
             ResponseTemplate::new(200)
                 .set_body_raw(r#"{"ok":true,"result":true}"#, "application/json"),
         )
-        .expect(1)
+        .expect(2)
         .mount(&server)
         .await;
     Mock::given(method("POST"))
@@ -26,7 +25,7 @@ async fn archive_clears_data_and_sends_confirmation() {
             r#"{"ok":true,"result":{"message_id":2,"date":0,"chat":{"id":1,"type":"private"},"text":"archived"}}"#,
             "application/json",
         ))
-        .expect(2)
+        .expect(4)
         .mount(&server)
         .await;
 
@@ -34,14 +33,16 @@ async fn archive_clears_data_and_sends_confirmation() {
     let bot =
         Bot::with_client("TEST", client).set_api_url(reqwest::Url::parse(&server.uri()).unwrap());
     let db = init_test_db().await;
+    let shared = SharedState::new().unwrap();
     let chat = ChatId(1);
     db.add_item_count(chat, "Milk").await.unwrap();
-    db.update_last_list_message_id(chat, MessageId(10))
+    db.update_last_list_message_id(chat, 10).await.unwrap();
+
+    ListService::new(&db, &shared)
+        .archive(bot.clone(), chat)
         .await
         .unwrap();
 
-    ListService::new(&db).archive(bot, chat).await.unwrap();
-
     let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM items")
         .fetch_one(&*db)
         .await
@@ -49,11 +50,25 @@ async fn archive_clears_data_and_sends_confirmation() {
     assert_eq!(count.0, 0);
     assert!(db.get_last_list_message_id(chat).await.unwrap().is_none());
 
+    db.add_item_count(chat, "Eggs").await.unwrap();
+    db.update_last_list_message_id(chat, 11).await.unwrap();
+    ListService::new(&db, &shared)
+        .archive(bot, chat)
+        .await
+        .unwrap();
+
+    let archives = db.recent_archives(chat, 5).await.unwrap();
+    let texts: Vec<_> = archives
+        .iter()
+        .map(|archive| archive.items[0].text.as_str())
+        .collect();
+    assert_eq!(texts, vec!["Eggs", "Milk"]);
+
     server.verify().await;
 }
 
 #[tokio::test]
-async fn nuke_clears_data_and_sends_confirmation() {
+async fn nuke_asks_first_and_clears_data_once_confirmed() {
     let server = MockServer::start().await;
     Mock::given(method("POST"))
         .and(path("/botTEST/DeleteMessage"))
@@ -61,35 +76,77 @@ async fn nuke_clears_data_and_sends_confirmation() {
             ResponseTemplate::new(200)
                 .set_body_raw(r#"{"ok":true,"result":true}"#, "application/json"),
         )
-        .expect(2)
+        .expect(3)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/botTEST/SendMessage"))
+        .and(body_string_contains("nuke_confirm_7"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            r#"{"ok":true,"result":{"message_id":2,"date":0,"chat":{"id":1,"type":"private"},"text":"prompt"}}"#,
+            "application/json",
+        ))
+        .expect(1)
         .mount(&server)
         .await;
     Mock::given(method("POST"))
         .and(path("/botTEST/SendMessage"))
+        .and(body_string_contains(LIST_NUKED))
         .respond_with(ResponseTemplate::new(200).set_body_raw(
-            format!(r#"{{"ok":true,"result":{{"message_id":2,"date":0,"chat":{{"id":1,"type":"private"}},"text":"{}"}}}}"#, LIST_NUKED),
+            format!(r#"{{"ok":true,"result":{{"message_id":3,"date":0,"chat":{{"id":1,"type":"private"}},"text":"{}"}}}}"#, LIST_NUKED),
             "application/json",
         ))
         .expect(1)
         .mount(&server)
         .await;
+    Mock::given(method("POST"))
+        .and(path("/botTEST/AnswerCallbackQuery"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_raw(r#"{"ok":true,"result":true}"#, "application/json"),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
 
     let client = Client::builder().no_proxy().build().unwrap();
     let bot =
         Bot::with_client("TEST", client).set_api_url(reqwest::Url::parse(&server.uri()).unwrap());
     let db = init_test_db().await;
+    let shared = SharedState::new().unwrap();
     let chat = ChatId(1);
     db.add_item_count(chat, "Milk").await.unwrap();
-    db.update_last_list_message_id(chat, MessageId(5))
-        .await
-        .unwrap();
+    db.update_last_list_message_id(chat, 5).await.unwrap();
 
     let msg: Message = serde_json::from_str(
-        r#"{"message_id":1,"date":0,"chat":{"id":1,"type":"private"},"text":"/nuke"}"#,
+        r#"{"message_id":1,"date":0,"chat":{"id":1,"type":"private"},"from":{"id":7,"is_bot":false,"first_name":"Anna"},"text":"/nuke"}"#,
     )
     .unwrap();
 
-    ListService::new(&db).nuke(bot, msg, 5).await.unwrap();
+    ListService::new(&db, &shared)
+        .nuke(bot.clone(), msg, 5)
+        .await
+        .unwrap();
+    assert_eq!(db.list_items(chat).await.unwrap().len(), 1);
+
+    let confirm: CallbackQuery = serde_json::from_value(serde_json::json!({
+        "id": "q1",
+        "from": {"id": 7, "is_bot": false, "first_name": "Anna"},
+        "chat_instance": "c",
+        "data": "nuke_confirm_7",
+        "message": {"message_id": 2, "date": 0, "chat": {"id": 1, "type": "private"}},
+    }))
+    .unwrap();
+    callback_handler(
+        bot,
+        confirm,
+        db.clone(),
+        shared.clone(),
+        5,
+        DeleteSessionTtl::default(),
+    )
+    .await
+    .unwrap();
 
     let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM items")
         .fetch_one(&*db)
@@ -97,6 +154,64 @@ async fn nuke_clears_data_and_sends_confirmation() {
         .unwrap();
     assert_eq!(count.0, 0);
     assert!(db.get_last_list_message_id(chat).await.unwrap().is_none());
+    assert!(db.recent_archives(chat, 5).await.unwrap().is_empty());
+
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn undo_restores_nuked_list_once() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/botTEST/DeleteMessage"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_raw(r#"{"ok":true,"result":true}"#, "application/json"),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/botTEST/SendMessage"))
+        .and(body_string_contains(NOTHING_TO_UNDO))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            r#"{"ok":true,"result":{"message_id":4,"date":0,"chat":{"id":1,"type":"private"},"text":"none"}}"#,
+            "application/json",
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/botTEST/SendMessage"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            r#"{"ok":true,"result":{"message_id":3,"date":0,"chat":{"id":1,"type":"private"},"text":"ok"}}"#,
+            "application/json",
+        ))
+        .mount(&server)
+        .await;
+
+    let client = Client::builder().no_proxy().build().unwrap();
+    let bot =
+        Bot::with_client("TEST", client).set_api_url(reqwest::Url::parse(&server.uri()).unwrap());
+    let db = init_test_db().await;
+    let shared = SharedState::new().unwrap();
+    let chat = ChatId(1);
+    db.add_item_count(chat, "Milk").await.unwrap();
+    db.add_item_count(chat, "Eggs").await.unwrap();
+    let service = ListService::new(&db, &shared);
+    service.wipe(&bot, chat, 5).await.unwrap();
+    service.undo(bot.clone(), chat).await.unwrap();
+
+    let texts: Vec<_> = db
+        .list_items(chat)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|item| item.text)
+        .collect();
+    assert_eq!(texts, vec!["Milk", "Eggs"]);
+    assert_eq!(db.get_last_list_message_id(chat).await.unwrap(), Some(3));
 
+    service.undo(bot, chat).await.unwrap();
+    assert_eq!(db.list_items(chat).await.unwrap().len(), 2);
     server.verify().await;
 }
@@ -1,5 +1,8 @@
+use shopbot::ai::common::DEFAULT_OPENAI_RETRY_DEADLINE;
 use shopbot::ai::config::AiConfig;
+use shopbot::ai::prompts::PromptKind;
 use shopbot::Config;
+use std::time::Duration;
 
 use serial_test::serial;
 
@@ -88,6 +91,21 @@ fn config_from_env_custom_pool_size() {
     assert!(cfg.ai.is_none());
 }
 
+#[test]
+#[serial]
+fn config_from_env_busy_timeout() {
+    std::env::set_var("DB_URL", "db");
+    std::env::remove_var("DB_BUSY_TIMEOUT_MS");
+    std::env::remove_var("OPENAI_API_KEY");
+    let cfg = Config::from_env();
+    assert_eq!(cfg.db_busy_timeout, std::time::Duration::from_secs(5));
+
+    std::env::set_var("DB_BUSY_TIMEOUT_MS", "250");
+    let cfg = Config::from_env();
+    assert_eq!(cfg.db_busy_timeout, std::time::Duration::from_millis(250));
+    std::env::remove_var("DB_BUSY_TIMEOUT_MS");
+}
+
 #[test]
 #[serial]
 fn config_from_env_default_delete_after_timeout() {
@@ -109,3 +127,113 @@ fn config_from_env_custom_delete_after_timeout() {
     assert_eq!(cfg.delete_after_timeout, 10);
     assert!(cfg.ai.is_none());
 }
+
+fn clear_ai_flags() {
+    std::env::remove_var("AI_ENABLE_STT");
+    std::env::remove_var("AI_ENABLE_VISION");
+    std::env::remove_var("AI_ENABLE_GPT_PARSE");
+    std::env::remove_var("AI_ENABLE_VOICE_COMMANDS");
+}
+
+#[test]
+#[serial]
+fn ai_config_capabilities_default_enabled() {
+    std::env::set_var("OPENAI_API_KEY", "k");
+    clear_ai_flags();
+    let cfg = AiConfig::from_env().unwrap();
+    assert!(cfg.enable_stt);
+    assert!(cfg.enable_vision);
+    assert!(cfg.enable_gpt_parse);
+    assert!(cfg.enable_voice_commands);
+}
+
+#[test]
+#[serial]
+fn ai_config_capabilities_each_combination() {
+    std::env::set_var("OPENAI_API_KEY", "k");
+    let flags = [
+        "AI_ENABLE_STT",
+        "AI_ENABLE_VISION",
+        "AI_ENABLE_GPT_PARSE",
+        "AI_ENABLE_VOICE_COMMANDS",
+    ];
+    for mask in 0u8..16 {
+        for (bit, flag) in flags.iter().enumerate() {
+            let value = if mask & (1 << bit) != 0 {
+                "true"
+            } else {
+                "false"
+            };
+            std::env::set_var(flag, value);
+        }
+        let cfg = AiConfig::from_env().unwrap();
+        assert_eq!(cfg.enable_stt, mask & 1 != 0);
+        assert_eq!(cfg.enable_vision, mask & 2 != 0);
+        assert_eq!(cfg.enable_gpt_parse, mask & 4 != 0);
+        assert_eq!(cfg.enable_voice_commands, mask & 8 != 0);
+    }
+    clear_ai_flags();
+}
+
+#[test]
+#[serial]
+fn ai_config_capability_flag_spellings() {
+    std::env::set_var("OPENAI_API_KEY", "k");
+    clear_ai_flags();
+    std::env::set_var("AI_ENABLE_STT", "0");
+    std::env::set_var("AI_ENABLE_VISION", "OFF");
+    std::env::set_var("AI_ENABLE_GPT_PARSE", "no");
+    std::env::set_var("AI_ENABLE_VOICE_COMMANDS", "1");
+    let cfg = AiConfig::from_env().unwrap();
+    assert!(!cfg.enable_stt);
+    assert!(!cfg.enable_vision);
+    assert!(!cfg.enable_gpt_parse);
+    assert!(cfg.enable_voice_commands);
+    clear_ai_flags();
+}
+
+#[test]
+#[serial]
+fn ai_config_from_env_retry_policy() {
+    std::env::set_var("OPENAI_API_KEY", "k");
+    std::env::set_var("OPENAI_MAX_ATTEMPTS", "5");
+    std::env::set_var("OPENAI_RETRY_BASE_DELAY_MS", "250");
+    std::env::set_var("OPENAI_RETRY_DEADLINE_SECS", "bogus");
+    let config = AiConfig::from_env().unwrap();
+    let policy = config.openai.policy();
+    std::env::remove_var("OPENAI_MAX_ATTEMPTS");
+    std::env::remove_var("OPENAI_RETRY_BASE_DELAY_MS");
+    std::env::remove_var("OPENAI_RETRY_DEADLINE_SECS");
+    assert_eq!(policy.max_attempts, 5);
+    assert_eq!(policy.base_delay, Duration::from_millis(250));
+    assert_eq!(policy.deadline, DEFAULT_OPENAI_RETRY_DEADLINE);
+}
+
+#[test]
+#[serial]
+fn ai_config_from_env_prompts() {
+    std::env::set_var("OPENAI_API_KEY", "k");
+    std::env::set_var("OPENAI_PHOTO_PROMPT", "Read the receipt");
+    std::env::set_var("OPENAI_STT_PROMPT", " ");
+    let prompts = AiConfig::from_env().unwrap().prompts;
+    std::env::remove_var("OPENAI_PHOTO_PROMPT");
+    std::env::remove_var("OPENAI_STT_PROMPT");
+    assert_eq!(prompts.get(PromptKind::Photo), "Read the receipt");
+    assert_eq!(
+        prompts.get(PromptKind::Transcription),
+        PromptKind::Transcription.built_in()
+    );
+}
+
+#[test]
+#[serial]
+fn ai_config_from_env_vision_max_edge() {
+    std::env::set_var("OPENAI_API_KEY", "k");
+    std::env::remove_var("OPENAI_VISION_MAX_EDGE");
+    assert_eq!(AiConfig::from_env().unwrap().vision_max_edge, Some(1024));
+    std::env::set_var("OPENAI_VISION_MAX_EDGE", "2048");
+    assert_eq!(AiConfig::from_env().unwrap().vision_max_edge, Some(2048));
+    std::env::set_var("OPENAI_VISION_MAX_EDGE", "0");
+    assert_eq!(AiConfig::from_env().unwrap().vision_max_edge, None);
+    std::env::remove_var("OPENAI_VISION_MAX_EDGE");
+}
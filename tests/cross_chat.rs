@@ -33,8 +33,10 @@ async fn delete_multiple_different_chat_has_no_effect() {
     let db = init_test_db().await;
     let chat1 = ChatId(1);
     let chat2 = ChatId(2);
-    for _ in 0..3 {
-        db.add_item_count(chat1, "Item").await.unwrap();
+    for i in 0..3 {
+        db.add_item_count(chat1, &format!("Item {i}"))
+            .await
+            .unwrap();
     }
     let items = db.list_items(chat1).await.unwrap();
     let ids: Vec<i64> = items.iter().map(|i| i.id).collect();
@@ -43,3 +45,23 @@ async fn delete_multiple_different_chat_has_no_effect() {
     let remaining = db.list_items(chat1).await.unwrap();
     assert_eq!(remaining.len(), 3);
 }
+
+#[tokio::test]
+async fn get_and_update_different_chat_find_nothing() {
+    let db = init_test_db().await;
+    let chat1 = ChatId(1);
+    let chat2 = ChatId(2);
+    db.add_item_count(chat1, "Milk").await.unwrap();
+    let item = db.list_items(chat1).await.unwrap()[0].clone();
+
+    assert!(db.get_item(chat2, item.id).await.unwrap().is_none());
+    let updated = db
+        .update_item(chat2, item.id, Some("Oat milk"), Some(true))
+        .await
+        .unwrap();
+    assert!(updated.is_none());
+
+    let after = db.get_item(chat1, item.id).await.unwrap().unwrap();
+    assert_eq!(after.text, "Milk");
+    assert!(!after.done);
+}
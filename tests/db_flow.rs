@@ -1,6 +1,11 @@
 use anyhow::Result;
+use shopbot::db::migrations::run_migrations;
+use shopbot::db::{connect_db, prepare_sqlite_url, Database, NewToken, DEFAULT_BUSY_TIMEOUT};
 use shopbot::tests::util::init_test_db;
-use teloxide::types::{ChatId, MessageId};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use teloxide::types::ChatId;
 
 #[tokio::test]
 async fn basic_item_flow() -> Result<()> {
@@ -37,7 +42,7 @@ async fn last_message_id_roundtrip() -> Result<()> {
 
     assert!(db.get_last_list_message_id(chat).await?.is_none());
 
-    db.update_last_list_message_id(chat, MessageId(99)).await?;
+    db.update_last_list_message_id(chat, 99).await?;
     assert_eq!(db.get_last_list_message_id(chat).await?, Some(99));
 
     db.clear_last_list_message_id(chat).await?;
@@ -64,3 +69,101 @@ async fn delete_multiple_items() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn concurrent_writers_on_file_db() -> Result<()> {
+    let (db, path) = file_db("wal").await?;
+
+    let (mode,): (String,) = sqlx::query_as("PRAGMA journal_mode")
+        .fetch_one(db.pool())
+        .await?;
+    assert_eq!(mode, "wal");
+
+    let mut tasks = Vec::new();
+    for worker in 0..8 {
+        let db = db.clone();
+        tasks.push(tokio::spawn(async move {
+            for n in 0..20 {
+                db.add_item_count(ChatId(worker), &format!("item {n}"))
+                    .await?;
+            }
+            anyhow::Ok(())
+        }));
+    }
+    for task in tasks {
+        task.await??;
+    }
+
+    for worker in 0..8 {
+        assert_eq!(db.list_items(ChatId(worker)).await?.len(), 20);
+    }
+
+    remove_file_db(db, &path).await;
+    Ok(())
+}
+
+/// Open a migrated, file-backed database so several pooled connections can
+/// run at once, unlike the single-connection in-memory test database.
+async fn file_db(name: &str) -> Result<(Database, PathBuf)> {
+    let path = std::env::temp_dir().join(format!("shopbot-{name}-{}.db", std::process::id()));
+    let url = prepare_sqlite_url(&format!("sqlite:{}", path.display()));
+    let pool = connect_db(&url, 4, DEFAULT_BUSY_TIMEOUT).await?;
+    let db = Database::new(pool);
+    run_migrations(&db).await?;
+    Ok((db, path))
+}
+
+async fn remove_file_db(db: Database, path: &Path) {
+    db.pool().close().await;
+    for suffix in ["", "-wal", "-shm"] {
+        let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+    }
+}
+
+#[tokio::test]
+async fn no_token_use_succeeds_after_revocation() -> Result<()> {
+    let (db, path) = file_db("revoke").await?;
+    let chat = ChatId(3);
+    db.create_token(
+        chat,
+        "burst-token",
+        &NewToken {
+            issued_at: 1,
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    let revoked = Arc::new(AtomicBool::new(false));
+    let mut tasks = Vec::new();
+    for _ in 0..8 {
+        let db = db.clone();
+        let revoked = revoked.clone();
+        tasks.push(tokio::spawn(async move {
+            loop {
+                let revoked_before = revoked.load(Ordering::SeqCst);
+                let used = db.use_token("burst-token", 2).await?;
+                assert!(
+                    !(revoked_before && used.is_some()),
+                    "token was accepted after revocation completed"
+                );
+                if used.is_none() {
+                    return anyhow::Ok(());
+                }
+                tokio::task::yield_now().await;
+            }
+        }));
+    }
+
+    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    assert!(db.revoke_token(chat, "burst-token", 3).await?);
+    revoked.store(true, Ordering::SeqCst);
+
+    for task in tasks {
+        task.await??;
+    }
+    assert!(db.use_token("burst-token", 4).await?.is_none());
+
+    remove_file_db(db, &path).await;
+    Ok(())
+}
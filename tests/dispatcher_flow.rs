@@ -1,6 +1,6 @@
 use reqwest::Client;
 use shopbot::tests::util::init_test_db;
-use shopbot::ListService;
+use shopbot::{ListService, SharedState};
 use teloxide::{prelude::*, utils::command::BotCommands};
 use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
 
@@ -36,6 +36,7 @@ async fn dispatcher_add_then_list() {
     let bot =
         Bot::with_client("TEST", client).set_api_url(reqwest::Url::parse(&server.uri()).unwrap());
     let db = init_test_db().await;
+    let shared = SharedState::new().unwrap();
     let ai_config: Option<shopbot::ai::config::AiConfig> = None;
     let delete_after_timeout = 5u64;
 
@@ -58,9 +59,10 @@ async fn dispatcher_add_then_list() {
                      msg: Message,
                      cmd: Command,
                      db: shopbot::db::Database,
+                     shared: SharedState,
                      ai_config: Option<shopbot::ai::config::AiConfig>,
                      delete_after_timeout: u64| async move {
-                        let service = ListService::new(&db);
+                        let service = ListService::new(&db, &shared);
                         match cmd {
                             Command::Start | Command::Help => shopbot::help(bot, msg).await?,
                             Command::List => service.send_list(bot, msg.chat.id).await?,
@@ -69,13 +71,20 @@ async fn dispatcher_add_then_list() {
                                 service.archive_checked(bot, msg.chat.id).await?
                             }
                             Command::Delete => {
-                                shopbot::enter_delete_mode(bot, msg, &db, delete_after_timeout)
-                                    .await?
+                                shopbot::enter_delete_mode(
+                                    bot,
+                                    msg,
+                                    &db,
+                                    delete_after_timeout,
+                                    shopbot::DeleteSessionTtl::default(),
+                                )
+                                .await?
                             }
                             Command::Share => service.share_list(bot, msg.chat.id).await?,
                             Command::Nuke => service.nuke(bot, msg, delete_after_timeout).await?,
                             Command::Parse => {
-                                shopbot::add_items_from_parsed_text(bot, msg, db, ai_config).await?
+                                shopbot::add_items_from_parsed_text(bot, msg, db, shared, ai_config)
+                                    .await?
                             }
                             Command::Info => shopbot::show_system_info(bot, msg).await?,
                         }
@@ -116,6 +125,7 @@ async fn dispatcher_add_then_list() {
             bot.clone(),
             me.clone(),
             db.clone(),
+            shared.clone(),
             ai_config.clone(),
             delete_after_timeout
         ])
@@ -126,6 +136,7 @@ async fn dispatcher_add_then_list() {
             bot,
             me,
             db,
+            shared,
             ai_config,
             delete_after_timeout
         ])
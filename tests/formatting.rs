@@ -1,4 +1,9 @@
-use shopbot::{format_delete_list, format_list, format_plain_list, Item};
+use shopbot::{
+    format_delete_list, format_list, format_list_checked_last, format_plain_list, parse_item_line,
+    Item, ListStyle, ListStylePreset,
+};
+use std::collections::HashSet;
+use teloxide::types::{InlineKeyboardButton, InlineKeyboardButtonKind, InlineKeyboardMarkup};
 
 fn sample_items() -> Vec<Item> {
     vec![
@@ -6,11 +11,27 @@ fn sample_items() -> Vec<Item> {
             id: 1,
             text: "Apples".to_string(),
             done: false,
+            quantity: 1,
+            added_by_user_id: None,
+            added_by_name: None,
+            category: None,
+            created_at: 0,
+            assigned_to_user_id: None,
+            assigned_to_name: None,
+            price_cents: None,
         },
         Item {
             id: 2,
             text: "Milk".to_string(),
             done: true,
+            quantity: 1,
+            added_by_user_id: None,
+            added_by_name: None,
+            category: None,
+            created_at: 0,
+            assigned_to_user_id: None,
+            assigned_to_name: None,
+            price_cents: None,
         },
     ]
 }
@@ -21,11 +42,27 @@ fn all_done_items() -> Vec<Item> {
             id: 1,
             text: "Apples".to_string(),
             done: true,
+            quantity: 1,
+            added_by_user_id: None,
+            added_by_name: None,
+            category: None,
+            created_at: 0,
+            assigned_to_user_id: None,
+            assigned_to_name: None,
+            price_cents: None,
         },
         Item {
             id: 2,
             text: "Milk".to_string(),
             done: true,
+            quantity: 1,
+            added_by_user_id: None,
+            added_by_name: None,
+            category: None,
+            created_at: 0,
+            assigned_to_user_id: None,
+            assigned_to_name: None,
+            price_cents: None,
         },
     ]
 }
@@ -33,9 +70,9 @@ fn all_done_items() -> Vec<Item> {
 #[test]
 fn test_format_list() {
     let items = sample_items();
-    let (text, keyboard) = format_list(&items);
+    let (text, keyboard) = format_list(&items, &ListStyle::default());
 
-    assert_eq!(text, "⬜ Apples\n☑️ Milk\n");
+    assert_eq!(text, "1 of 2 done\n⬜ Apples\n☑️ Milk\n");
 
     let labels: Vec<&str> = keyboard
         .inline_keyboard
@@ -46,13 +83,58 @@ fn test_format_list() {
 }
 
 #[test]
-fn test_format_delete_list() {
-    use std::collections::HashSet;
+fn test_format_list_shows_quantity() {
+    let mut items = sample_items();
+    items[0].quantity = 2;
+    let (text, keyboard) = format_list(&items, &ListStyle::default());
+
+    assert_eq!(text, "1 of 2 done\n⬜ 2× Apples\n☑️ Milk\n");
+    assert_eq!(keyboard.inline_keyboard[0][0].text, "⬜ 2× Apples");
+}
+
+#[test]
+fn test_format_list_checked_last() {
+    let mut items = sample_items();
+    items.reverse();
+    items.push(Item {
+        id: 3,
+        text: "Bread".to_string(),
+        done: false,
+        quantity: 1,
+        added_by_user_id: None,
+        added_by_name: None,
+        category: None,
+        created_at: 0,
+        assigned_to_user_id: None,
+        assigned_to_name: None,
+        price_cents: None,
+    });
+    let (text, keyboard) = format_list_checked_last(&items, &ListStyle::default());
+
+    assert_eq!(
+        text,
+        "1 of 3 done\n⬜ Apples\n⬜ Bread\n── Checked ──\n☑️ Milk\n"
+    );
+    let callbacks: Vec<String> = keyboard
+        .inline_keyboard
+        .iter()
+        .map(|row| match &row[0].kind {
+            teloxide::types::InlineKeyboardButtonKind::CallbackData(data) => data.clone(),
+            _ => panic!("expected callback data"),
+        })
+        .collect();
+    assert_eq!(callbacks, vec!["toggle_1", "toggle_3", "toggle_2"]);
+
+    let (text, _) = format_list_checked_last(&all_done_items(), &ListStyle::default());
+    assert_eq!(text, "2 of 2 done\n✅ Apples\n✅ Milk\n");
+}
 
+#[test]
+fn test_format_delete_list() {
     let items = sample_items();
     let mut selected = HashSet::new();
     selected.insert(1);
-    let (text, keyboard) = format_delete_list(&items, &selected);
+    let (text, keyboard) = format_delete_list(&items, &selected, &ListStyle::default(), 0);
 
     assert_eq!(text, "Select items to delete, then tap 'Done Deleting'.");
 
@@ -74,8 +156,8 @@ fn test_format_plain_list() {
 #[test]
 fn test_format_list_all_done() {
     let items = all_done_items();
-    let (text, keyboard) = format_list(&items);
-    assert_eq!(text, "✅ Apples\n✅ Milk\n");
+    let (text, keyboard) = format_list(&items, &ListStyle::default());
+    assert_eq!(text, "2 of 2 done\n✅ Apples\n✅ Milk\n");
 
     let labels: Vec<&str> = keyboard
         .inline_keyboard
@@ -84,3 +166,164 @@ fn test_format_list_all_done() {
         .collect();
     assert_eq!(labels, vec!["✅ Apples", "✅ Milk"]);
 }
+
+#[test]
+fn test_format_list_every_preset() {
+    for preset in ListStylePreset::ALL {
+        let style = preset.style();
+        let (text, _) = format_list(&sample_items(), &style);
+        assert_eq!(
+            text,
+            format!(
+                "1 of 2 done\n{} Apples\n{} Milk\n",
+                style.unchecked, style.checked
+            )
+        );
+
+        let (text, _) = format_list(&all_done_items(), &style);
+        assert_eq!(
+            text,
+            format!("2 of 2 done\n{0} Apples\n{0} Milk\n", style.all_done),
+            "preset {}",
+            preset.name()
+        );
+    }
+}
+
+#[test]
+fn test_format_delete_list_every_preset() {
+    for preset in ListStylePreset::ALL {
+        let style = preset.style();
+        let selected = HashSet::from([2]);
+        let (_, keyboard) = format_delete_list(&sample_items(), &selected, &style, 0);
+        assert_eq!(
+            keyboard.inline_keyboard[0][0].text,
+            format!("{} Apples", style.unchecked)
+        );
+        assert_eq!(
+            keyboard.inline_keyboard[1][0].text,
+            format!("{} Milk", style.selected)
+        );
+    }
+}
+
+#[test]
+fn test_every_preset_round_trips_through_parse_item_line() {
+    for preset in ListStylePreset::ALL {
+        let style = preset.style();
+        for items in [sample_items(), all_done_items()] {
+            let (text, _) = format_list(&items, &style);
+            let parsed: Vec<String> = text.lines().filter_map(parse_item_line).collect();
+            assert_eq!(parsed, vec!["Apples", "Milk"], "preset {}", preset.name());
+
+            let (text, _) = format_list_checked_last(&items, &style);
+            let parsed: Vec<String> = text.lines().filter_map(parse_item_line).collect();
+            assert_eq!(parsed, vec!["Apples", "Milk"], "preset {}", preset.name());
+        }
+    }
+}
+
+#[test]
+fn test_format_list_groups_by_category() {
+    let mut items = sample_items();
+    items[1].category = Some("dairy".to_string());
+    items.push(Item {
+        id: 3,
+        text: "Salt & pepper".to_string(),
+        done: false,
+        quantity: 1,
+        added_by_user_id: None,
+        added_by_name: None,
+        category: None,
+        created_at: 0,
+        assigned_to_user_id: None,
+        assigned_to_name: None,
+        price_cents: None,
+    });
+    items.push(Item {
+        id: 4,
+        text: "Kefir".to_string(),
+        done: false,
+        quantity: 1,
+        added_by_user_id: None,
+        added_by_name: None,
+        category: Some("dairy".to_string()),
+        created_at: 0,
+        assigned_to_user_id: None,
+        assigned_to_name: None,
+        price_cents: None,
+    });
+    let (text, keyboard) = format_list(&items, &ListStyle::default());
+
+    assert_eq!(
+        text,
+        "1 of 4 done\n<b>Dairy</b>\n☑️ Milk\n⬜ Kefir\n<b>Other</b>\n⬜ Apples\n⬜ Salt &amp; pepper\n"
+    );
+    assert_eq!(keyboard.inline_keyboard[3][0].text, "⬜ Salt & pepper");
+    assert_eq!(
+        format_plain_list(&items),
+        "• Milk\n• Kefir\n• Apples\n• Salt & pepper\n"
+    );
+}
+
+fn many_items(count: usize) -> Vec<Item> {
+    (1..=count as i64)
+        .map(|id| Item {
+            id,
+            text: format!("Item {id}"),
+            done: false,
+            quantity: 1,
+            added_by_user_id: None,
+            added_by_name: None,
+            category: None,
+            created_at: 0,
+            assigned_to_user_id: None,
+            assigned_to_name: None,
+            price_cents: None,
+        })
+        .collect()
+}
+
+fn button_count(keyboard: &InlineKeyboardMarkup) -> usize {
+    keyboard.inline_keyboard.iter().map(Vec::len).sum()
+}
+
+fn callback(button: &InlineKeyboardButton) -> &str {
+    match &button.kind {
+        InlineKeyboardButtonKind::CallbackData(data) => data,
+        _ => panic!("expected callback data"),
+    }
+}
+
+#[test]
+fn test_keyboards_stay_under_button_limit() {
+    for count in [99, 100, 101] {
+        let items = many_items(count);
+        let (_, keyboard) = format_list(&items, &ListStyle::default());
+        assert!(button_count(&keyboard) <= 100, "{count} items");
+        let nav = keyboard.inline_keyboard.last().unwrap();
+        assert!(callback(&nav[0]).starts_with("page_"), "{count} items");
+
+        let mut seen = Vec::new();
+        let mut page = 0;
+        loop {
+            let (_, keyboard) =
+                format_delete_list(&items, &HashSet::new(), &ListStyle::default(), page);
+            assert!(button_count(&keyboard) <= 100, "{count} items");
+            let rows = &keyboard.inline_keyboard;
+            assert_eq!(callback(&rows[rows.len() - 1][0]), "delete_done");
+            let nav = &rows[rows.len() - 2];
+            seen.extend(
+                rows[..rows.len() - 2]
+                    .iter()
+                    .map(|row| callback(&row[0]).to_string()),
+            );
+            match nav.last().map(callback) {
+                Some(next) if next == format!("delete_page_{}", page + 1) => page += 1,
+                _ => break,
+            }
+        }
+        let expected: Vec<String> = (1..=count).map(|id| format!("delete_{id}")).collect();
+        assert_eq!(seen, expected);
+    }
+}
@@ -1,4 +1,6 @@
-use shopbot::ai::gpt::parse_items_gpt;
+use shopbot::ai::common::OpenAiClient;
+use shopbot::ai::gpt::{parse_categorized_items_gpt, parse_items_gpt};
+use shopbot::ai::prompts::CATEGORIZED_PARSING_PROMPT;
 use wiremock::matchers::{method, path};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
@@ -15,8 +17,51 @@ async fn test_parse_items_gpt_numbers() {
         .await;
 
     let url = format!("{}/v1/chat/completions", server.uri());
-    let items = parse_items_gpt("k", "gpt-4.1", "one milk and 2 eggs", Some(&url))
-        .await
-        .unwrap();
+    let items = parse_items_gpt(
+        "k",
+        "gpt-4.1",
+        "one milk and 2 eggs",
+        Some(&url),
+        &OpenAiClient::default(),
+    )
+    .await
+    .unwrap();
     assert_eq!(items, vec!["one milk", "2 eggs"]);
 }
+
+#[tokio::test]
+async fn test_parse_categorized_items_gpt() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            r#"{"choices":[{"message":{"content":"{\"items\":[{\"text\":\"milk\",\"category\":\" Dairy \"},{\"text\":\"2 eggs\",\"category\":\"\"},\"bread\"]}"}}]}"#,
+            "application/json",
+        ))
+        .mount(&server)
+        .await;
+
+    let url = format!("{}/v1/chat/completions", server.uri());
+    let items = parse_categorized_items_gpt(
+        "k",
+        "gpt-4.1",
+        CATEGORIZED_PARSING_PROMPT,
+        "milk, 2 eggs, bread",
+        Some(&url),
+        &OpenAiClient::default(),
+    )
+    .await
+    .unwrap();
+    let items: Vec<_> = items
+        .into_iter()
+        .map(|item| (item.text, item.category))
+        .collect();
+    assert_eq!(
+        items,
+        vec![
+            ("milk".to_string(), Some("dairy".to_string())),
+            ("2 eggs".to_string(), None),
+            ("bread".to_string(), None),
+        ]
+    );
+}
@@ -1,6 +1,6 @@
 use reqwest::Client;
-use shopbot::insert_items;
 use shopbot::tests::util::init_test_db;
+use shopbot::{insert_items, SharedState};
 use teloxide::prelude::*;
 use wiremock::matchers::method;
 use wiremock::{Mock, MockServer, ResponseTemplate};
@@ -22,9 +22,15 @@ async fn insert_items_adds_and_sends() {
         Bot::with_client("TEST", client).set_api_url(reqwest::Url::parse(&server.uri()).unwrap());
     let db = init_test_db().await;
 
-    let added = insert_items(bot, ChatId(1), &db, vec!["Milk".to_string()])
-        .await
-        .unwrap();
+    let added = insert_items(
+        bot,
+        ChatId(1),
+        &db,
+        &SharedState::new().unwrap(),
+        vec!["Milk".to_string()],
+    )
+    .await
+    .unwrap();
     assert_eq!(added, 1);
 
     let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM items")
@@ -49,9 +55,15 @@ async fn insert_items_empty_sends_nothing() {
         Bot::with_client("TEST", client).set_api_url(reqwest::Url::parse(&server.uri()).unwrap());
     let db = init_test_db().await;
 
-    let added = insert_items(bot, ChatId(1), &db, Vec::<String>::new())
-        .await
-        .unwrap();
+    let added = insert_items(
+        bot,
+        ChatId(1),
+        &db,
+        &SharedState::new().unwrap(),
+        Vec::<String>::new(),
+    )
+    .await
+    .unwrap();
     assert_eq!(added, 0);
 
     let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM items")
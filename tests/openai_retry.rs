@@ -0,0 +1,96 @@
+use shopbot::ai::common::{OpenAiClient, RequestPolicy};
+use shopbot::ai::gpt::parse_items_gpt;
+use shopbot::Metrics;
+use std::time::Duration;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const ITEMS: &str = r#"{"choices":[{"message":{"content":"{\"items\":[\"Milk\"]}"}}]}"#;
+
+fn fast_client() -> OpenAiClient {
+    OpenAiClient::new(
+        RequestPolicy {
+            base_delay: Duration::from_millis(1),
+            ..RequestPolicy::default()
+        },
+        Metrics::default(),
+    )
+}
+
+async fn parse(server: &MockServer, openai: &OpenAiClient) -> anyhow::Result<Vec<String>> {
+    let url = format!("{}/v1/chat/completions", server.uri());
+    parse_items_gpt("k", "gpt-4.1", "milk", Some(&url), openai).await
+}
+
+#[tokio::test]
+async fn rate_limited_request_is_retried() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "0"))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(ITEMS, "application/json"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let openai = fast_client();
+    let items = parse(&server, &openai).await.unwrap();
+    assert_eq!(items, vec!["Milk"]);
+    server.verify().await;
+
+    let text = openai.metrics().render();
+    for outcome in ["ok", "error"] {
+        let series = format!(
+            "shopbot_openai_requests_total{{endpoint=\"/v1/chat/completions\",outcome=\"{outcome}\"}} 1"
+        );
+        assert!(text.contains(&series), "missing {series} in\n{text}");
+    }
+}
+
+#[tokio::test]
+async fn server_errors_give_up_after_max_attempts() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(503))
+        .expect(3)
+        .mount(&server)
+        .await;
+
+    assert!(parse(&server, &fast_client()).await.is_err());
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn client_errors_are_not_retried() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(400))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    assert!(parse(&server, &fast_client()).await.is_err());
+    server.verify().await;
+}
+
+#[tokio::test]
+async fn retries_stop_at_the_deadline() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "60"))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    assert!(parse(&server, &OpenAiClient::default()).await.is_err());
+    server.verify().await;
+}
@@ -1,5 +1,6 @@
-use shopbot::ai::stt::transcribe_audio;
-use wiremock::matchers::{method, path};
+use shopbot::ai::common::OpenAiClient;
+use shopbot::ai::stt::{transcribe_audio, AudioFile};
+use wiremock::matchers::{body_string_contains, method, path};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
 #[tokio::test]
@@ -7,6 +8,8 @@ async fn test_transcribe_audio_parsing() {
     let server = MockServer::start().await;
     Mock::given(method("POST"))
         .and(path("/v1/audio/transcriptions"))
+        .and(body_string_contains(r#"filename="note.m4a""#))
+        .and(body_string_contains("audio/mp4"))
         .respond_with(
             ResponseTemplate::new(200).set_body_raw(r#"{"text":"milk"}"#, "application/json"),
         )
@@ -14,8 +17,60 @@ async fn test_transcribe_audio_parsing() {
         .await;
 
     let url = format!("{}/v1/audio/transcriptions", server.uri());
-    let res = transcribe_audio("whisper-1", "k", None, b"123", Some(&url))
-        .await
-        .unwrap();
-    assert_eq!(res, "milk");
+    let audio = AudioFile {
+        bytes: b"123",
+        file_name: "note.m4a",
+        mime_type: Some("audio/mp4"),
+    };
+    let res = transcribe_audio(
+        "whisper-1",
+        "k",
+        None,
+        None,
+        audio,
+        Some(&url),
+        &OpenAiClient::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.text, "milk");
+    assert_eq!(res.language, None);
+}
+
+#[tokio::test]
+async fn test_transcribe_audio_language() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/audio/transcriptions"))
+        .and(body_string_contains("name=\"language\"\r\n\r\nru\r\n"))
+        .and(body_string_contains(
+            "name=\"response_format\"\r\n\r\nverbose_json\r\n",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            r#"{"task":"transcribe","language":"russian","duration":1.5,"text":"молоко","segments":[]}"#,
+            "application/json",
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let url = format!("{}/v1/audio/transcriptions", server.uri());
+    let audio = AudioFile {
+        bytes: b"123",
+        file_name: "voice.ogg",
+        mime_type: Some("audio/ogg"),
+    };
+    let res = transcribe_audio(
+        "whisper-1",
+        "k",
+        None,
+        Some("ru"),
+        audio,
+        Some(&url),
+        &OpenAiClient::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(res.text, "молоко");
+    assert_eq!(res.language.as_deref(), Some("russian"));
 }
@@ -1,7 +1,19 @@
-use shopbot::ai::vision::parse_photo_items;
-use wiremock::matchers::{method, path};
+use shopbot::ai::common::OpenAiClient;
+use shopbot::ai::prompts::PHOTO_PARSING_PROMPT;
+use shopbot::ai::vision::{parse_album_items, parse_photo_items, VisionModel};
+use wiremock::matchers::{body_string_contains, method, path};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
+fn vision_model<'a>(url: &'a str, openai: &'a OpenAiClient) -> VisionModel<'a> {
+    VisionModel {
+        api_key: "k",
+        model: "gpt-4o",
+        max_edge: None,
+        url: Some(url),
+        openai,
+    }
+}
+
 #[tokio::test]
 async fn test_parse_photo_items() {
     let server = MockServer::start().await;
@@ -15,8 +27,80 @@ async fn test_parse_photo_items() {
         .await;
 
     let url = format!("{}/v1/chat/completions", server.uri());
-    let items = parse_photo_items("k", "gpt-4o", b"img", Some(&url))
-        .await
-        .unwrap();
+    let openai = OpenAiClient::default();
+    let items = parse_photo_items(
+        &vision_model(&url, &openai),
+        PHOTO_PARSING_PROMPT,
+        b"img",
+        None,
+        None,
+    )
+    .await
+    .unwrap();
     assert_eq!(items, vec!["apples"]);
 }
+
+#[tokio::test]
+async fn album_photos_share_one_request() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            r#"{"choices":[{"message":{"content":"{\"items\":[\"apples\",\"bread\"]}"}}]}"#,
+            "application/json",
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let url = format!("{}/v1/chat/completions", server.uri());
+    let openai = OpenAiClient::default();
+    let items = parse_album_items(
+        &vision_model(&url, &openai),
+        PHOTO_PARSING_PROMPT,
+        &[b"page one".to_vec(), b"page two".to_vec()],
+        None,
+    )
+    .await
+    .unwrap();
+    assert_eq!(items, vec!["apples", "bread"]);
+
+    let requests = server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    let images = body["messages"][1]["content"].as_array().unwrap();
+    assert_eq!(images.len(), 2);
+    assert!(images.iter().all(|image| image["type"] == "image_url"));
+}
+
+#[tokio::test]
+async fn captions_are_sent_after_the_photo() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_string_contains("add these plus 2 lemons"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            r#"{"choices":[{"message":{"content":"{\"items\":[\"apples\",\"2 lemons\"]}"}}]}"#,
+            "application/json",
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let url = format!("{}/v1/chat/completions", server.uri());
+    let openai = OpenAiClient::default();
+    let items = parse_photo_items(
+        &vision_model(&url, &openai),
+        PHOTO_PARSING_PROMPT,
+        b"img",
+        None,
+        Some("add these plus 2 lemons"),
+    )
+    .await
+    .unwrap();
+    assert_eq!(items, vec!["apples", "2 lemons"]);
+
+    let requests = server.received_requests().await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+    let caption = body["messages"][2]["content"].as_str().unwrap();
+    assert!(caption.ends_with("add these plus 2 lemons"));
+}
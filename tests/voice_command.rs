@@ -1,5 +1,7 @@
-use shopbot::ai::gpt::{interpret_voice_command_test, VoiceCommand};
-use wiremock::matchers::{method, path};
+use shopbot::ai::common::OpenAiClient;
+use shopbot::ai::gpt::{interpret_voice_command_test, VoiceCommand, VoiceCommandRequest};
+use shopbot::ai::prompts::VOICE_COMMAND_PROMPT;
+use wiremock::matchers::{body_string_contains, method, path};
 use wiremock::{Mock, MockServer, ResponseTemplate};
 
 #[tokio::test]
@@ -18,9 +20,14 @@ async fn test_voice_command_delete() {
     let res = interpret_voice_command_test(
         "k",
         "gpt-4.1",
-        "delete milk",
-        &["Milk".to_string(), "Bread".to_string()],
+        VoiceCommandRequest {
+            instructions: VOICE_COMMAND_PROMPT,
+            text: "delete milk",
+            language: None,
+            list: &["Milk".to_string(), "Bread".to_string()],
+        },
         &url,
+        &OpenAiClient::default(),
     )
     .await
     .unwrap();
@@ -43,11 +50,48 @@ async fn test_voice_command_add() {
     let res = interpret_voice_command_test(
         "k",
         "gpt-4.1",
-        "apples",
-        &["Milk".to_string(), "Bread".to_string()],
+        VoiceCommandRequest {
+            instructions: VOICE_COMMAND_PROMPT,
+            text: "apples",
+            language: None,
+            list: &["Milk".to_string(), "Bread".to_string()],
+        },
         &url,
+        &OpenAiClient::default(),
     )
     .await
     .unwrap();
     assert_eq!(res, VoiceCommand::Add(vec!["Apples".to_string()]));
 }
+
+#[tokio::test]
+async fn test_voice_command_check() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(body_string_contains("check"))
+        .and(body_string_contains("transcribed from english speech"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            r#"{"choices":[{"message":{"content":"{\"check\":[\"Milk\"]}"}}]}"#,
+            "application/json",
+        ))
+        .mount(&server)
+        .await;
+
+    let url = format!("{}/v1/chat/completions", server.uri());
+    let res = interpret_voice_command_test(
+        "k",
+        "gpt-4.1",
+        VoiceCommandRequest {
+            instructions: VOICE_COMMAND_PROMPT,
+            text: "milk is done",
+            language: Some("english"),
+            list: &["Milk".to_string(), "Bread".to_string()],
+        },
+        &url,
+        &OpenAiClient::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(res, VoiceCommand::Check(vec!["Milk".to_string()]));
+}